@@ -7,14 +7,117 @@
 #include <Python.h>
 #include <string.h>
 #include <stdlib.h>
+#include <stdio.h>
+#include <fcntl.h>
+#include <unistd.h>
 
 // Python模块路径
 #define PYTHON_MODULE "src.amdb.database"
 #define PYTHON_CLASS "Database"
+#define PYTHON_ERRORS_MODULE "src.amdb.errors"
+#define PYTHON_FATAL_ERROR "FatalError"
+#define PYTHON_BUSY_ERROR "BusyError"
+#define PYTHON_INVALID_OPTION_ERROR "InvalidOptionError"
+#define PYTHON_READ_ONLY_ERROR "ReadOnlyError"
+#define PYTHON_NOT_FOUND_ERROR "DatabaseNotFoundError"
+#define PYTHON_FAULTS_MODULE "src.amdb.faults"
 
 // 全局Python模块
 static PyObject* g_amdb_module = NULL;
 static PyObject* g_database_class = NULL;
+// 引擎的致命错误类型，转换为 AMDB_FATAL
+static PyObject* g_fatal_error_class = NULL;
+// 引擎的暂时性错误类型，转换为 AMDB_BUSY
+static PyObject* g_busy_error_class = NULL;
+// 打开选项不合法或与记录不一致，转换为 AMDB_INVALID_ARG
+static PyObject* g_invalid_option_error_class = NULL;
+// 只读打开的数据库拒绝写入，转换为 AMDB_READ_ONLY
+static PyObject* g_read_only_error_class = NULL;
+// 数据目录不存在且不允许新建，转换为 AMDB_NOT_FOUND
+static PyObject* g_not_found_error_class = NULL;
+
+// I/O统计：调用线程在导出函数内的I/O计为前台，进程内其余I/O计为后台。
+// 计数器只在持有GIL时修改
+static amdb_io_counters_t g_foreground_io;
+// 读取 /proc 计数器本身产生的I/O，从统计中扣除
+static amdb_io_counters_t g_probe_io;
+// 导出函数的嵌套深度（例如 amdb_delete 调用 amdb_put），只统计最外层
+static __thread int g_call_depth = 0;
+// 由 amdb_set_background_thread 标记的线程，其调用不计为前台
+static __thread bool g_background_thread = false;
+
+// 读取 /proc 下的I/O计数器，返回读取的字节数，失败时返回-1。
+// 只用一次 read 调用，本身的开销恰为返回的字节数和1次读操作
+static long read_proc_io(const char* path, amdb_io_counters_t* out) {
+#ifdef __linux__
+    int fd = open(path, O_RDONLY);
+    if (fd < 0) {
+        return -1;
+    }
+    char buf[512];
+    ssize_t len = read(fd, buf, sizeof(buf) - 1);
+    close(fd);
+    if (len < 0) {
+        return -1;
+    }
+    buf[len] = '\0';
+
+    memset(out, 0, sizeof(*out));
+    for (char* line = strtok(buf, "\n"); line; line = strtok(NULL, "\n")) {
+        unsigned long long value;
+        if (sscanf(line, "rchar: %llu", &value) == 1) {
+            out->bytes_read = value;
+        } else if (sscanf(line, "wchar: %llu", &value) == 1) {
+            out->bytes_written = value;
+        } else if (sscanf(line, "syscr: %llu", &value) == 1) {
+            out->read_ops = value;
+        } else if (sscanf(line, "syscw: %llu", &value) == 1) {
+            out->write_ops = value;
+        }
+    }
+    return (long)len;
+#else
+    (void)path;
+    (void)out;
+    return -1;
+#endif
+}
+
+static void add_probe(long len) {
+    if (len >= 0) {
+        g_probe_io.bytes_read += (uint64_t)len;
+        g_probe_io.read_ops += 1;
+    }
+}
+
+// 把本线程在 [before, after] 间的I/O计入前台；after 包含读取 before 本身产生的I/O
+static void add_foreground(const amdb_io_counters_t* before, long before_len,
+                           const amdb_io_counters_t* after) {
+    g_foreground_io.bytes_read += after->bytes_read - before->bytes_read - (uint64_t)before_len;
+    g_foreground_io.bytes_written += after->bytes_written - before->bytes_written;
+    g_foreground_io.read_ops += after->read_ops - before->read_ops - 1;
+    g_foreground_io.write_ops += after->write_ops - before->write_ops;
+}
+
+// 持有GIL执行调用并返回其状态码；导出函数都经由它进入Python，因此可从任意线程调用
+#define WITH_GIL(call) do { \
+    PyGILState_STATE gil_state_ = PyGILState_Ensure(); \
+    bool measure_ = g_call_depth++ == 0 && !g_background_thread; \
+    amdb_io_counters_t io_before_, io_after_; \
+    long before_len_ = measure_ ? read_proc_io("/proc/thread-self/io", &io_before_) : -1; \
+    amdb_status_t status_ = (call); \
+    if (before_len_ >= 0) { \
+        long after_len_ = read_proc_io("/proc/thread-self/io", &io_after_); \
+        if (after_len_ >= 0) { \
+            add_foreground(&io_before_, before_len_, &io_after_); \
+        } \
+        add_probe(before_len_); \
+        add_probe(after_len_); \
+    } \
+    g_call_depth--; \
+    PyGILState_Release(gil_state_); \
+    return status_; \
+} while (0)
 
 // 初始化Python环境
 static int init_python() {
@@ -40,12 +143,51 @@ static int init_python() {
         PyErr_Print();
         return -1;
     }
+
+    PyObject* errors_module = PyImport_ImportModule(PYTHON_ERRORS_MODULE);
+    if (!errors_module) {
+        PyErr_Print();
+        return -1;
+    }
+    g_fatal_error_class = PyObject_GetAttrString(errors_module, PYTHON_FATAL_ERROR);
+    g_busy_error_class = PyObject_GetAttrString(errors_module, PYTHON_BUSY_ERROR);
+    g_invalid_option_error_class = PyObject_GetAttrString(errors_module, PYTHON_INVALID_OPTION_ERROR);
+    g_read_only_error_class = PyObject_GetAttrString(errors_module, PYTHON_READ_ONLY_ERROR);
+    g_not_found_error_class = PyObject_GetAttrString(errors_module, PYTHON_NOT_FOUND_ERROR);
+    Py_DECREF(errors_module);
+    if (!g_fatal_error_class || !g_busy_error_class || !g_invalid_option_error_class
+        || !g_read_only_error_class || !g_not_found_error_class) {
+        PyErr_Print();
+        return -1;
+    }
     
+    // 释放初始化线程持有的GIL，之后各调用按需获取
+    PyEval_SaveThread();
     return 0;
 }
 
 // 清理Python环境
 static void cleanup_python() {
+    if (g_not_found_error_class) {
+        Py_DECREF(g_not_found_error_class);
+        g_not_found_error_class = NULL;
+    }
+    if (g_read_only_error_class) {
+        Py_DECREF(g_read_only_error_class);
+        g_read_only_error_class = NULL;
+    }
+    if (g_invalid_option_error_class) {
+        Py_DECREF(g_invalid_option_error_class);
+        g_invalid_option_error_class = NULL;
+    }
+    if (g_busy_error_class) {
+        Py_DECREF(g_busy_error_class);
+        g_busy_error_class = NULL;
+    }
+    if (g_fatal_error_class) {
+        Py_DECREF(g_fatal_error_class);
+        g_fatal_error_class = NULL;
+    }
     if (g_database_class) {
         Py_DECREF(g_database_class);
         g_database_class = NULL;
@@ -74,9 +216,33 @@ static PyObject* create_database_instance(const char* data_dir) {
 // 转换Python异常到状态码
 static amdb_status_t handle_python_error() {
     if (PyErr_Occurred()) {
+        amdb_status_t status = AMDB_ERROR;
+        if (g_fatal_error_class && PyErr_ExceptionMatches(g_fatal_error_class)) {
+            status = AMDB_FATAL;
+        } else if (g_busy_error_class && PyErr_ExceptionMatches(g_busy_error_class)) {
+            status = AMDB_BUSY;
+        } else if (g_invalid_option_error_class && PyErr_ExceptionMatches(g_invalid_option_error_class)) {
+            status = AMDB_INVALID_ARG;
+        } else if (g_read_only_error_class && PyErr_ExceptionMatches(g_read_only_error_class)) {
+            status = AMDB_READ_ONLY;
+        } else if (g_not_found_error_class && PyErr_ExceptionMatches(g_not_found_error_class)) {
+            status = AMDB_NOT_FOUND;
+        } else if (PyErr_ExceptionMatches(PyExc_TimeoutError)) {
+            status = AMDB_TIMED_OUT;
+        }
         PyErr_Print();
-        return AMDB_ERROR;
+        return status;
+    }
+    return AMDB_OK;
+}
+
+static amdb_status_t init_locked(const char* data_dir, amdb_handle_t* handle) {
+    PyObject* db = create_database_instance(data_dir);
+    if (!db) {
+        return handle_python_error();
     }
+    
+    *handle = (amdb_handle_t)db;
     return AMDB_OK;
 }
 
@@ -84,37 +250,151 @@ amdb_status_t amdb_init(const char* data_dir, amdb_handle_t* handle) {
     if (init_python() != 0) {
         return AMDB_ERROR;
     }
-    
-    PyObject* db = create_database_instance(data_dir);
+    WITH_GIL(init_locked(data_dir, handle));
+}
+
+static amdb_status_t init_with_options_locked(const char* data_dir,
+                                              const char* const* names, const char* const* values,
+                                              size_t count, amdb_handle_t* handle) {
+    PyObject* options = PyDict_New();
+    if (!options) {
+        return handle_python_error();
+    }
+    for (size_t i = 0; i < count; i++) {
+        PyObject* value = PyUnicode_FromString(values[i]);
+        if (!value || PyDict_SetItemString(options, names[i], value) != 0) {
+            Py_XDECREF(value);
+            Py_DECREF(options);
+            return handle_python_error();
+        }
+        Py_DECREF(value);
+    }
+
+    PyObject* args = Py_BuildValue("(s)", data_dir);
+    PyObject* kwargs = Py_BuildValue("{s:O}", "options", options);
+    Py_DECREF(options);
+    PyObject* db = NULL;
+    if (args && kwargs) {
+        db = PyObject_Call(g_database_class, args, kwargs);
+    }
+    Py_XDECREF(args);
+    Py_XDECREF(kwargs);
     if (!db) {
         return handle_python_error();
     }
-    
+
     *handle = (amdb_handle_t)db;
     return AMDB_OK;
 }
 
-amdb_status_t amdb_close(amdb_handle_t handle) {
+amdb_status_t amdb_init_with_options(const char* data_dir,
+                                     const char* const* names, const char* const* values,
+                                     size_t count, amdb_handle_t* handle) {
+    if (!data_dir || !handle || (count > 0 && (!names || !values))) {
+        return AMDB_INVALID_ARG;
+    }
+    if (init_python() != 0) {
+        return AMDB_ERROR;
+    }
+    WITH_GIL(init_with_options_locked(data_dir, names, values, count, handle));
+}
+
+static amdb_status_t close_locked(amdb_handle_t handle, amdb_close_mode_t mode) {
     if (!handle) {
         return AMDB_INVALID_ARG;
     }
     
     PyObject* db = (PyObject*)handle;
+    amdb_status_t status = AMDB_OK;
     
-    // 调用flush方法
-    PyObject* result = PyObject_CallMethod(db, "flush", NULL);
-    if (result) {
-        Py_DECREF(result);
+    if (mode != AMDB_CLOSE_DETACH) {
+        // 调用flush或sync方法；失败时仍释放句柄，并返回错误
+        PyObject* result = mode == AMDB_CLOSE_SYNC
+            ? PyObject_CallMethod(db, "sync", NULL)
+            : PyObject_CallMethod(db, "flush", NULL);
+        if (result) {
+            Py_DECREF(result);
+        } else {
+            status = handle_python_error();
+        }
+    }
+    
+    // 句柄释放后对象可能因循环引用延迟回收，先显式释放数据目录的锁
+    PyObject* released = PyObject_CallMethod(db, "release_lock", NULL);
+    if (released) {
+        Py_DECREF(released);
+    } else {
+        PyErr_Clear();
     }
     
     Py_DECREF(db);
+    return status;
+}
+
+amdb_status_t amdb_close(amdb_handle_t handle) {
+    WITH_GIL(close_locked(handle, AMDB_CLOSE_FLUSH));
+}
+
+amdb_status_t amdb_close_with(amdb_handle_t handle, amdb_close_mode_t mode) {
+    WITH_GIL(close_locked(handle, mode));
+}
+
+static amdb_status_t sync_locked(amdb_handle_t handle) {
+    if (!handle) {
+        return AMDB_INVALID_ARG;
+    }
+    PyObject* result = PyObject_CallMethod((PyObject*)handle, "sync", NULL);
+    if (!result) {
+        return handle_python_error();
+    }
+    Py_DECREF(result);
     return AMDB_OK;
 }
 
-amdb_status_t amdb_put(amdb_handle_t handle,
-                       const uint8_t* key, size_t key_len,
-                       const uint8_t* value, size_t value_len,
-                       uint8_t* root_hash) {
+amdb_status_t amdb_sync(amdb_handle_t handle) {
+    WITH_GIL(sync_locked(handle));
+}
+
+static amdb_status_t refresh_locked(amdb_handle_t handle, uint8_t* changed) {
+    if (!handle || !changed) {
+        return AMDB_INVALID_ARG;
+    }
+    PyObject* result = PyObject_CallMethod((PyObject*)handle, "refresh", NULL);
+    if (!result) {
+        return handle_python_error();
+    }
+    *changed = PyObject_IsTrue(result) == 1 ? 1 : 0;
+    Py_DECREF(result);
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_refresh(amdb_handle_t handle, uint8_t* changed) {
+    WITH_GIL(refresh_locked(handle, changed));
+}
+
+static amdb_status_t namespace_open_locked(amdb_handle_t handle, const char* name,
+                                           amdb_handle_t* ns_handle) {
+    if (!handle || !name || !ns_handle) {
+        return AMDB_INVALID_ARG;
+    }
+    PyObject* ns = PyObject_CallMethod((PyObject*)handle, "namespace", "s", name);
+    if (!ns) {
+        return handle_python_error();
+    }
+    // 新引用归调用方所有，由 amdb_close 释放
+    *ns_handle = (amdb_handle_t)ns;
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_namespace_open(amdb_handle_t handle, const char* name,
+                                  amdb_handle_t* ns_handle) {
+    WITH_GIL(namespace_open_locked(handle, name, ns_handle));
+}
+
+static amdb_status_t put_locked(amdb_handle_t handle,
+                                const uint8_t* key, size_t key_len,
+                                const uint8_t* value, size_t value_len,
+                                uint8_t* root_hash) {
     if (!handle || !key || !value) {
         return AMDB_INVALID_ARG;
     }
@@ -155,10 +435,105 @@ amdb_status_t amdb_put(amdb_handle_t handle,
     return AMDB_OK;
 }
 
-amdb_status_t amdb_get(amdb_handle_t handle,
+amdb_status_t amdb_put(amdb_handle_t handle,
                        const uint8_t* key, size_t key_len,
-                       uint32_t version,
-                       amdb_result_t* result) {
+                       const uint8_t* value, size_t value_len,
+                       uint8_t* root_hash) {
+    WITH_GIL(put_locked(handle, key, key_len, value, value_len, root_hash));
+}
+
+// 流式写入按len_hint预分配的上限，超出部分按需增长
+#define PUT_STREAM_MAX_PREALLOC (64 * 1024 * 1024)
+
+// 流式写入状态
+typedef struct {
+    amdb_handle_t handle;
+    uint8_t* key;
+    size_t key_len;
+    uint8_t* data;
+    size_t len;
+    size_t cap;
+} put_stream_t;
+
+amdb_status_t amdb_put_stream_begin(amdb_handle_t handle,
+                                    const uint8_t* key, size_t key_len,
+                                    uint64_t len_hint,
+                                    amdb_put_stream_t* stream) {
+    if (!handle || !key || !stream) {
+        return AMDB_INVALID_ARG;
+    }
+    
+    put_stream_t* s = calloc(1, sizeof(put_stream_t));
+    if (!s) {
+        return AMDB_MEMORY_ERROR;
+    }
+    s->handle = handle;
+    s->key = malloc(key_len > 0 ? key_len : 1);
+    s->cap = 4096;
+    if (len_hint > 0) {
+        s->cap = len_hint < PUT_STREAM_MAX_PREALLOC ? (size_t)len_hint : PUT_STREAM_MAX_PREALLOC;
+    }
+    s->data = malloc(s->cap);
+    if (!s->key || !s->data) {
+        amdb_put_stream_abort(s);
+        return AMDB_MEMORY_ERROR;
+    }
+    memcpy(s->key, key, key_len);
+    s->key_len = key_len;
+    
+    *stream = (amdb_put_stream_t)s;
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_put_stream_write(amdb_put_stream_t stream,
+                                    const uint8_t* data, size_t data_len) {
+    put_stream_t* s = (put_stream_t*)stream;
+    if (!s || (!data && data_len > 0)) {
+        return AMDB_INVALID_ARG;
+    }
+    
+    if (s->len + data_len > s->cap) {
+        size_t new_cap = s->cap * 2;
+        while (new_cap < s->len + data_len) {
+            new_cap *= 2;
+        }
+        uint8_t* grown = realloc(s->data, new_cap);
+        if (!grown) {
+            return AMDB_MEMORY_ERROR;
+        }
+        s->data = grown;
+        s->cap = new_cap;
+    }
+    
+    memcpy(s->data + s->len, data, data_len);
+    s->len += data_len;
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_put_stream_finish(amdb_put_stream_t stream, uint8_t* root_hash) {
+    put_stream_t* s = (put_stream_t*)stream;
+    if (!s) {
+        return AMDB_INVALID_ARG;
+    }
+    
+    amdb_status_t status = amdb_put(s->handle, s->key, s->key_len, s->data, s->len, root_hash);
+    amdb_put_stream_abort(s);
+    return status;
+}
+
+void amdb_put_stream_abort(amdb_put_stream_t stream) {
+    put_stream_t* s = (put_stream_t*)stream;
+    if (s) {
+        free(s->key);
+        free(s->data);
+        free(s);
+    }
+}
+
+static amdb_status_t get_locked(amdb_handle_t handle,
+                                const uint8_t* key, size_t key_len,
+                                uint64_t version,
+                                amdb_result_t* result) {
     if (!handle || !key || !result) {
         return AMDB_INVALID_ARG;
     }
@@ -170,7 +545,7 @@ amdb_status_t amdb_get(amdb_handle_t handle,
     if (version == 0) {
         value_obj = PyObject_CallMethod(db, "get", "O", key_obj);
     } else {
-        PyObject* version_obj = PyLong_FromUnsignedLong(version);
+        PyObject* version_obj = PyLong_FromUnsignedLongLong(version);
         value_obj = PyObject_CallMethod(db, "get", "OO", key_obj, version_obj);
         Py_DECREF(version_obj);
     }
@@ -196,7 +571,7 @@ amdb_status_t amdb_get(amdb_handle_t handle,
         const char* data = PyBytes_AsString(value_obj);
         size_t data_len = PyBytes_Size(value_obj);
         
-        result->data = malloc(data_len);
+        result->data = malloc(data_len > 0 ? data_len : 1);
         if (!result->data) {
             Py_DECREF(value_obj);
             return AMDB_MEMORY_ERROR;
@@ -215,127 +590,2628 @@ amdb_status_t amdb_get(amdb_handle_t handle,
     return result->status;
 }
 
-amdb_status_t amdb_delete(amdb_handle_t handle,
-                          const uint8_t* key, size_t key_len) {
-    // 简化实现：通过put空值实现删除
-    uint8_t empty_value = 0;
-    uint8_t root_hash[32];
-    return amdb_put(handle, key, key_len, &empty_value, 0, root_hash);
+amdb_status_t amdb_get(amdb_handle_t handle,
+                       const uint8_t* key, size_t key_len,
+                       uint32_t version,
+                       amdb_result_t* result) {
+    WITH_GIL(get_locked(handle, key, key_len, version, result));
 }
 
-amdb_status_t amdb_batch_put(amdb_handle_t handle,
-                             const uint8_t** keys, const size_t* key_lens,
-                             const uint8_t** values, const size_t* value_lens,
-                             size_t count,
-                             uint8_t* root_hash) {
-    if (!handle || !keys || !values || count == 0) {
+amdb_status_t amdb_get64(amdb_handle_t handle,
+                         const uint8_t* key, size_t key_len,
+                         uint64_t version,
+                         amdb_result_t* result) {
+    WITH_GIL(get_locked(handle, key, key_len, version, result));
+}
+
+static amdb_status_t get_chunk_locked(amdb_handle_t handle,
+                                      const uint8_t* key, size_t key_len,
+                                      uint64_t version,
+                                      uint64_t offset,
+                                      uint8_t* buf, size_t buf_len,
+                                      size_t* read_len, uint64_t* total_len) {
+    if (!handle || !key || !buf || !read_len || !total_len) {
         return AMDB_INVALID_ARG;
     }
     
     PyObject* db = (PyObject*)handle;
+    PyObject* key_obj = PyBytes_FromStringAndSize((const char*)key, key_len);
     
-    // 创建Python列表
-    PyObject* items = PyList_New(count);
-    for (size_t i = 0; i < count; i++) {
-        PyObject* key_obj = PyBytes_FromStringAndSize((const char*)keys[i], key_lens[i]);
-        PyObject* value_obj = PyBytes_FromStringAndSize((const char*)values[i], value_lens[i]);
-        PyObject* item = PyTuple_New(2);
-        PyTuple_SetItem(item, 0, key_obj);
-        PyTuple_SetItem(item, 1, value_obj);
-        PyList_SetItem(items, i, item);
+    PyObject* value_obj = NULL;
+    if (version == 0) {
+        value_obj = PyObject_CallMethod(db, "get", "O", key_obj);
+    } else {
+        PyObject* version_obj = PyLong_FromUnsignedLongLong(version);
+        value_obj = PyObject_CallMethod(db, "get", "OO", key_obj, version_obj);
+        Py_DECREF(version_obj);
     }
+    Py_DECREF(key_obj);
     
-    PyObject* result = PyObject_CallMethod(db, "batch_put", "O", items);
-    Py_DECREF(items);
-    
-    if (!result) {
+    if (!value_obj) {
         return handle_python_error();
     }
+    if (value_obj == Py_None || !PyBytes_Check(value_obj)) {
+        Py_DECREF(value_obj);
+        return AMDB_NOT_FOUND;
+    }
     
-    if (PyTuple_Check(result) && PyTuple_Size(result) == 2) {
-        PyObject* root_hash_obj = PyTuple_GetItem(result, 1);
-        if (PyBytes_Check(root_hash_obj)) {
-            const char* hash_data = PyBytes_AsString(root_hash_obj);
-            memcpy(root_hash, hash_data, 32);
-        }
+    const char* data = PyBytes_AsString(value_obj);
+    uint64_t data_len = (uint64_t)PyBytes_Size(value_obj);
+    
+    *total_len = data_len;
+    *read_len = 0;
+    if (offset < data_len) {
+        uint64_t remaining = data_len - offset;
+        size_t n = remaining < buf_len ? (size_t)remaining : buf_len;
+        memcpy(buf, data + offset, n);
+        *read_len = n;
     }
     
-    Py_DECREF(result);
+    Py_DECREF(value_obj);
     return AMDB_OK;
 }
 
-amdb_status_t amdb_get_root_hash(amdb_handle_t handle, uint8_t* root_hash) {
-    if (!handle || !root_hash) {
+amdb_status_t amdb_get_chunk(amdb_handle_t handle,
+                             const uint8_t* key, size_t key_len,
+                             uint32_t version,
+                             uint64_t offset,
+                             uint8_t* buf, size_t buf_len,
+                             size_t* read_len, uint64_t* total_len) {
+    WITH_GIL(get_chunk_locked(handle, key, key_len, version, offset,
+                              buf, buf_len, read_len, total_len));
+}
+
+amdb_status_t amdb_get_chunk64(amdb_handle_t handle,
+                               const uint8_t* key, size_t key_len,
+                               uint64_t version,
+                               uint64_t offset,
+                               uint8_t* buf, size_t buf_len,
+                               size_t* read_len, uint64_t* total_len) {
+    WITH_GIL(get_chunk_locked(handle, key, key_len, version, offset,
+                              buf, buf_len, read_len, total_len));
+}
+
+static amdb_status_t delete_locked(amdb_handle_t handle,
+                                   const uint8_t* key, size_t key_len) {
+    if (!handle || !key) {
         return AMDB_INVALID_ARG;
     }
-    
-    PyObject* db = (PyObject*)handle;
-    PyObject* result = PyObject_CallMethod(db, "get_root_hash", NULL);
-    
-    if (!result) {
+    PyObject* key_obj = PyBytes_FromStringAndSize((const char*)key, key_len);
+    if (!key_obj) {
         return handle_python_error();
     }
-    
-    if (PyBytes_Check(result)) {
-        const char* hash_data = PyBytes_AsString(result);
-        memcpy(root_hash, hash_data, 32);
-        Py_DECREF(result);
-        return AMDB_OK;
+    // 引擎写入删除标记；空值是普通的值
+    PyObject* result = PyObject_CallMethod((PyObject*)handle, "delete", "O", key_obj);
+    Py_DECREF(key_obj);
+    if (!result) {
+        return handle_python_error();
     }
-    
     Py_DECREF(result);
-    return AMDB_ERROR;
+    return AMDB_OK;
 }
 
-void amdb_free_result(amdb_result_t* result) {
-    if (result && result->data) {
-        free(result->data);
-        result->data = NULL;
-        result->data_len = 0;
-    }
-    if (result && result->error_msg) {
-        free(result->error_msg);
-        result->error_msg = NULL;
-    }
+amdb_status_t amdb_delete(amdb_handle_t handle,
+                          const uint8_t* key, size_t key_len) {
+    WITH_GIL(delete_locked(handle, key, key_len));
 }
 
-const char* amdb_error_string(amdb_status_t status) {
-    switch (status) {
-        case AMDB_OK: return "Success";
-        case AMDB_ERROR: return "General error";
-        case AMDB_NOT_FOUND: return "Not found";
-        case AMDB_INVALID_ARG: return "Invalid argument";
-        case AMDB_IO_ERROR: return "I/O error";
-        case AMDB_MEMORY_ERROR: return "Memory error";
-        default: return "Unknown error";
-    }
+static uint64_t dict_u64(PyObject* dict, const char* field) {
+    PyObject* value = PyDict_GetItemString(dict, field);
+    return value ? PyLong_AsUnsignedLongLong(value) : 0;
 }
 
-// 其他函数的简化实现
-amdb_status_t amdb_range_query(amdb_handle_t handle,
-                               const uint8_t* start_key, size_t start_key_len,
-                               const uint8_t* end_key, size_t end_key_len,
-                               amdb_result_t** results, size_t* result_count) {
-    // TODO: 完整实现
-    *results = NULL;
-    *result_count = 0;
-    return AMDB_OK;
+static double dict_double(PyObject* dict, const char* field) {
+    PyObject* value = PyDict_GetItemString(dict, field);
+    return value ? PyFloat_AsDouble(value) : 0.0;
 }
 
-amdb_status_t amdb_get_history(amdb_handle_t handle,
-                              const uint8_t* key, size_t key_len,
-                              uint32_t start_version, uint32_t end_version,
-                              uint32_t** versions, size_t* version_count) {
-    // TODO: 完整实现
-    *versions = NULL;
-    *version_count = 0;
-    return AMDB_OK;
+// [(key, value), ...] 列表，值为NULL的项为 (key, None) 即删除；出错时返回NULL
+static PyObject* batch_items(const uint8_t** keys, const size_t* key_lens,
+                             const uint8_t** values, const size_t* value_lens,
+                             size_t count) {
+    PyObject* items = PyList_New((Py_ssize_t)count);
+    if (!items) {
+        return NULL;
+    }
+    for (size_t i = 0; i < count; i++) {
+        PyObject* key_obj = PyBytes_FromStringAndSize((const char*)keys[i], key_lens[i]);
+        PyObject* value_obj = Py_None;
+        if (values[i]) {
+            value_obj = PyBytes_FromStringAndSize((const char*)values[i], value_lens[i]);
+        } else {
+            Py_INCREF(Py_None);
+        }
+        PyObject* item = key_obj && value_obj ? PyTuple_Pack(2, key_obj, value_obj) : NULL;
+        Py_XDECREF(key_obj);
+        Py_XDECREF(value_obj);
+        if (!item) {
+            Py_DECREF(items);
+            return NULL;
+        }
+        PyList_SET_ITEM(items, (Py_ssize_t)i, item);
+    }
+    return items;
 }
 
-amdb_status_t amdb_verify(amdb_handle_t handle,
-                         const uint8_t* key, size_t key_len,
-                         const uint8_t* value, size_t value_len,
-                         const uint8_t** proof, size_t proof_count,
+static amdb_status_t batch_put_locked(amdb_handle_t handle,
+                                      const uint8_t** keys, const size_t* key_lens,
+                                      const uint8_t** values, const size_t* value_lens,
+                                      size_t count,
+                                      uint8_t* root_hash,
+                                      amdb_commit_stats_t* stats,
+                                      uint64_t* version) {
+    if (!handle || !keys || !values || count == 0) {
+        return AMDB_INVALID_ARG;
+    }
+    
+    PyObject* db = (PyObject*)handle;
+
+    // 一次提交写入全部键值对，引擎只计算一次根哈希，不产生中间根
+    PyObject* items = batch_items(keys, key_lens, values, value_lens, count);
+    if (!items) {
+        return handle_python_error();
+    }
+
+    // 需要统计时传入字典，由引擎填写
+    PyObject* stats_dict = NULL;
+    if (stats) {
+        stats_dict = PyDict_New();
+        if (!stats_dict) {
+            Py_DECREF(items);
+            return handle_python_error();
+        }
+    }
+    // 需要版本时由引擎在提交的同一把锁内读取，返回 (version, merkle_root_hash)
+    PyObject* result = stats_dict
+        ? PyObject_CallMethod(db, "commit_batch", "OO", items, stats_dict)
+        : PyObject_CallMethod(db, version ? "commit_batch_versioned" : "commit_batch", "O", items);
+    Py_DECREF(items);
+    if (!result) {
+        Py_XDECREF(stats_dict);
+        return handle_python_error();
+    }
+    if (stats_dict) {
+        stats->inserted = dict_u64(stats_dict, "inserted");
+        stats->updated = dict_u64(stats_dict, "updated");
+        stats->deleted = dict_u64(stats_dict, "deleted");
+        stats->bytes_written = dict_u64(stats_dict, "bytes_written");
+        stats->nodes_touched = dict_u64(stats_dict, "nodes_touched");
+        stats->hash_secs = dict_double(stats_dict, "hash_time");
+        stats->io_secs = dict_double(stats_dict, "io_time");
+        Py_DECREF(stats_dict);
+    }
+
+    // 返回 (success, merkle_root_hash)
+    PyObject* hash_obj = PyTuple_Check(result) && PyTuple_Size(result) >= 2
+        ? PyTuple_GetItem(result, 1) : NULL;
+    if (root_hash && hash_obj && PyBytes_Check(hash_obj) && PyBytes_Size(hash_obj) >= 32) {
+        memcpy(root_hash, PyBytes_AsString(hash_obj), 32);
+    }
+    if (version) {
+        *version = hash_obj ? (uint64_t)PyLong_AsUnsignedLongLong(PyTuple_GetItem(result, 0)) : 0;
+    }
+    Py_DECREF(result);
+    if (version && PyErr_Occurred()) {
+        return handle_python_error();
+    }
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_batch_put(amdb_handle_t handle,
+                             const uint8_t** keys, const size_t* key_lens,
+                             const uint8_t** values, const size_t* value_lens,
+                             size_t count,
+                             uint8_t* root_hash) {
+    WITH_GIL(batch_put_locked(handle, keys, key_lens, values, value_lens, count, root_hash,
+                              NULL, NULL));
+}
+
+amdb_status_t amdb_batch_put_stats(amdb_handle_t handle,
+                                   const uint8_t** keys, const size_t* key_lens,
+                                   const uint8_t** values, const size_t* value_lens,
+                                   size_t count,
+                                   uint8_t* root_hash,
+                                   amdb_commit_stats_t* stats) {
+    if (!stats) {
+        return AMDB_INVALID_ARG;
+    }
+    WITH_GIL(batch_put_locked(handle, keys, key_lens, values, value_lens, count, root_hash,
+                              stats, NULL));
+}
+
+amdb_status_t amdb_batch_put_versioned(amdb_handle_t handle,
+                                       const uint8_t** keys, const size_t* key_lens,
+                                       const uint8_t** values, const size_t* value_lens,
+                                       size_t count,
+                                       uint8_t* root_hash,
+                                       uint64_t* version) {
+    if (!version) {
+        return AMDB_INVALID_ARG;
+    }
+    WITH_GIL(batch_put_locked(handle, keys, key_lens, values, value_lens, count, root_hash,
+                              NULL, version));
+}
+
+static amdb_status_t batch_root_hash_locked(amdb_handle_t handle,
+                                            const uint8_t** keys, const size_t* key_lens,
+                                            const uint8_t** values, const size_t* value_lens,
+                                            size_t count,
+                                            uint8_t* root_hash) {
+    if (!handle || !root_hash || (count > 0 && (!keys || !values))) {
+        return AMDB_INVALID_ARG;
+    }
+    PyObject* items = batch_items(keys, key_lens, values, value_lens, count);
+    if (!items) {
+        return handle_python_error();
+    }
+    PyObject* hash_obj = PyObject_CallMethod((PyObject*)handle, "batch_root_hash", "O", items);
+    Py_DECREF(items);
+    if (!hash_obj) {
+        return handle_python_error();
+    }
+    if (!PyBytes_Check(hash_obj)) {
+        Py_DECREF(hash_obj);
+        return AMDB_ERROR;
+    }
+    // 与 amdb_get_root_hash 相同，不足32字节的根哈希补零
+    Py_ssize_t hash_len = PyBytes_Size(hash_obj);
+    memset(root_hash, 0, 32);
+    memcpy(root_hash, PyBytes_AsString(hash_obj), hash_len < 32 ? (size_t)hash_len : 32);
+    Py_DECREF(hash_obj);
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_batch_root_hash(amdb_handle_t handle,
+                                   const uint8_t** keys, const size_t* key_lens,
+                                   const uint8_t** values, const size_t* value_lens,
+                                   size_t count,
+                                   uint8_t* root_hash) {
+    WITH_GIL(batch_root_hash_locked(handle, keys, key_lens, values, value_lens, count, root_hash));
+}
+
+static amdb_status_t batch_root_hash_at_locked(amdb_handle_t handle, uint64_t version,
+                                               const uint8_t** keys, const size_t* key_lens,
+                                               const uint8_t** values, const size_t* value_lens,
+                                               size_t count,
+                                               uint8_t* root_hash) {
+    if (!handle || !root_hash || (count > 0 && (!keys || !values))) {
+        return AMDB_INVALID_ARG;
+    }
+    PyObject* items = batch_items(keys, key_lens, values, value_lens, count);
+    if (!items) {
+        return handle_python_error();
+    }
+    PyObject* hash_obj = PyObject_CallMethod((PyObject*)handle, "batch_root_hash_at", "KO",
+                                             (unsigned long long)version, items);
+    Py_DECREF(items);
+    if (!hash_obj) {
+        return handle_python_error();
+    }
+    if (hash_obj == Py_None) {
+        Py_DECREF(hash_obj);
+        return AMDB_NOT_FOUND;
+    }
+    if (!PyBytes_Check(hash_obj)) {
+        Py_DECREF(hash_obj);
+        return AMDB_ERROR;
+    }
+    Py_ssize_t hash_len = PyBytes_Size(hash_obj);
+    memset(root_hash, 0, 32);
+    memcpy(root_hash, PyBytes_AsString(hash_obj), hash_len < 32 ? (size_t)hash_len : 32);
+    Py_DECREF(hash_obj);
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_batch_root_hash_at(amdb_handle_t handle, uint64_t version,
+                                      const uint8_t** keys, const size_t* key_lens,
+                                      const uint8_t** values, const size_t* value_lens,
+                                      size_t count,
+                                      uint8_t* root_hash) {
+    WITH_GIL(batch_root_hash_at_locked(handle, version, keys, key_lens, values, value_lens,
+                                       count, root_hash));
+}
+
+static amdb_status_t get_root_hash_locked(amdb_handle_t handle, uint8_t* root_hash) {
+    if (!handle || !root_hash) {
+        return AMDB_INVALID_ARG;
+    }
+    
+    PyObject* db = (PyObject*)handle;
+    PyObject* result = PyObject_CallMethod(db, "get_root_hash", NULL);
+    
+    if (!result) {
+        return handle_python_error();
+    }
+    
+    if (PyBytes_Check(result)) {
+        // 空树的根为空子树占位哈希，默认是空字节串，不足32字节的部分补零
+        Py_ssize_t hash_len = PyBytes_Size(result);
+        size_t copy_len = hash_len < 32 ? (size_t)hash_len : 32;
+        memset(root_hash, 0, 32);
+        memcpy(root_hash, PyBytes_AsString(result), copy_len);
+        Py_DECREF(result);
+        return AMDB_OK;
+    }
+    
+    Py_DECREF(result);
+    return AMDB_ERROR;
+}
+
+amdb_status_t amdb_get_root_hash(amdb_handle_t handle, uint8_t* root_hash) {
+    WITH_GIL(get_root_hash_locked(handle, root_hash));
+}
+
+void amdb_free_result(amdb_result_t* result) {
+    if (result && result->data) {
+        free(result->data);
+        result->data = NULL;
+        result->data_len = 0;
+    }
+    if (result && result->error_msg) {
+        free(result->error_msg);
+        result->error_msg = NULL;
+    }
+}
+
+const char* amdb_error_string(amdb_status_t status) {
+    switch (status) {
+        case AMDB_OK: return "Success";
+        case AMDB_ERROR: return "General error";
+        case AMDB_NOT_FOUND: return "Not found";
+        case AMDB_INVALID_ARG: return "Invalid argument";
+        case AMDB_IO_ERROR: return "I/O error";
+        case AMDB_MEMORY_ERROR: return "Memory error";
+        case AMDB_FATAL: return "Fatal engine error";
+        case AMDB_BUSY: return "Engine busy";
+        case AMDB_TIMED_OUT: return "Operation timed out";
+        case AMDB_READ_ONLY: return "Database is read-only";
+        default: return "Unknown error";
+    }
+}
+
+// 比较Python字节对象与给定边界（字节序）
+static int compare_key(PyObject* key_obj, const uint8_t* bound, size_t bound_len) {
+    const char* data = PyBytes_AsString(key_obj);
+    size_t len = PyBytes_Size(key_obj);
+    size_t n = len < bound_len ? len : bound_len;
+    int cmp = memcmp(data, bound, n);
+    if (cmp != 0) {
+        return cmp;
+    }
+    return len < bound_len ? -1 : (len > bound_len ? 1 : 0);
+}
+
+// 复制Python字节对象到结果结构
+static amdb_status_t copy_bytes_to_result(PyObject* bytes_obj, amdb_result_t* result) {
+    const char* data = PyBytes_AsString(bytes_obj);
+    size_t data_len = PyBytes_Size(bytes_obj);
+
+    result->status = AMDB_OK;
+    result->error_msg = NULL;
+    result->data = malloc(data_len > 0 ? data_len : 1);
+    if (!result->data) {
+        result->data_len = 0;
+        return AMDB_MEMORY_ERROR;
+    }
+    memcpy(result->data, data, data_len);
+    result->data_len = data_len;
+    return AMDB_OK;
+}
+
+// 值是否表示已删除：None或删除标记
+static bool is_deleted_value(PyObject* value_obj) {
+    if (!PyBytes_Check(value_obj)) {
+        return true;
+    }
+    return PyBytes_Size(value_obj) == 11 &&
+           memcmp(PyBytes_AsString(value_obj), "__DELETED__", 11) == 0;
+}
+
+// 复制可能不存在的值：不存在或已删除时结果为 AMDB_NOT_FOUND，没有数据
+static amdb_status_t copy_value_to_result(PyObject* value_obj, amdb_result_t* result) {
+    if (value_obj == Py_None || is_deleted_value(value_obj)) {
+        result->status = AMDB_NOT_FOUND;
+        result->error_msg = NULL;
+        result->data = NULL;
+        result->data_len = 0;
+        return AMDB_OK;
+    }
+    return copy_bytes_to_result(value_obj, result);
+}
+
+// at_time < 0 表示读取最新值，否则读取该时间点的值
+// [start_key, end_key) 内的全部键（含已删除的键），按字节序排列；长度为0的边界表示不限制。
+// 存储引擎的range_query依赖B+树同步状态，这里由版本管理器在有序键集合上二分查找，
+// 只返回范围内的键。出错时返回NULL
+static PyObject* range_keys(PyObject* db,
+                            const uint8_t* start_key, size_t start_key_len,
+                            const uint8_t* end_key, size_t end_key_len) {
+    PyObject* version_manager = PyObject_GetAttrString(db, "version_manager");
+    if (!version_manager) {
+        return NULL;
+    }
+    PyObject* start_obj = PyBytes_FromStringAndSize((const char*)start_key, start_key_len);
+    PyObject* end_obj = PyBytes_FromStringAndSize((const char*)end_key, end_key_len);
+    PyObject* keys = start_obj && end_obj
+        ? PyObject_CallMethod(version_manager, "range_keys", "OO", start_obj, end_obj)
+        : NULL;
+    Py_XDECREF(start_obj);
+    Py_XDECREF(end_obj);
+    Py_DECREF(version_manager);
+    if (keys && !PyList_Check(keys)) {
+        Py_DECREF(keys);
+        PyErr_SetString(PyExc_TypeError, "range_keys must return a list");
+        return NULL;
+    }
+    return keys;
+}
+
+// 值是否满足全部过滤条件
+static bool value_matches(PyObject* value_obj,
+                          const amdb_value_filter_t* filters, size_t filter_count) {
+    const uint8_t* data = (const uint8_t*)PyBytes_AsString(value_obj);
+    uint64_t len = (uint64_t)PyBytes_Size(value_obj);
+    for (size_t i = 0; i < filter_count; i++) {
+        const amdb_value_filter_t* filter = &filters[i];
+        uint64_t offset = filter->kind == AMDB_FILTER_BYTES_AT ? filter->offset : 0;
+        switch (filter->kind) {
+        case AMDB_FILTER_LENGTH:
+            if (len < filter->min_len || len > filter->max_len) {
+                return false;
+            }
+            break;
+        case AMDB_FILTER_PREFIX:
+        case AMDB_FILTER_BYTES_AT:
+            if (offset > len || filter->bytes_len > len - offset ||
+                (filter->bytes_len > 0 &&
+                 memcmp(data + offset, filter->bytes, filter->bytes_len) != 0)) {
+                return false;
+            }
+            break;
+        default:
+            return false;
+        }
+    }
+    return true;
+}
+
+static bool valid_filters(const amdb_value_filter_t* filters, size_t filter_count) {
+    if (filter_count > 0 && !filters) {
+        return false;
+    }
+    for (size_t i = 0; i < filter_count; i++) {
+        if (filters[i].kind > AMDB_FILTER_BYTES_AT ||
+            (filters[i].bytes_len > 0 && !filters[i].bytes)) {
+            return false;
+        }
+    }
+    return true;
+}
+
+static amdb_status_t range_query_locked(amdb_handle_t handle,
+                                        const uint8_t* start_key, size_t start_key_len,
+                                        const uint8_t* end_key, size_t end_key_len,
+                                        bool include_deleted, double at_time,
+                                        size_t max_entries, size_t max_bytes,
+                                        amdb_result_t* next_key,
+                                        const amdb_value_filter_t* filters, size_t filter_count,
+                                        amdb_result_t** results, size_t* result_count) {
+    if (!handle || !results || !result_count ||
+        ((max_entries > 0 || max_bytes > 0) && !next_key) ||
+        !valid_filters(filters, filter_count)) {
+        return AMDB_INVALID_ARG;
+    }
+
+    *results = NULL;
+    *result_count = 0;
+    if (next_key) {
+        next_key->data = NULL;
+        next_key->data_len = 0;
+    }
+
+    PyObject* db = (PyObject*)handle;
+    PyObject* keys = range_keys(db, start_key, start_key_len, end_key, end_key_len);
+    if (!keys) {
+        return handle_python_error();
+    }
+
+    Py_ssize_t count = PyList_Size(keys);
+    if (count == 0) {
+        Py_DECREF(keys);
+        return AMDB_OK;
+    }
+
+    amdb_result_t* out = calloc((size_t)count * 2, sizeof(amdb_result_t));
+    if (!out) {
+        Py_DECREF(keys);
+        return AMDB_MEMORY_ERROR;
+    }
+
+    size_t n = 0;
+    size_t bytes = 0;
+    amdb_status_t status = AMDB_OK;
+    for (Py_ssize_t i = 0; i < count && status == AMDB_OK; i++) {
+        PyObject* key_obj = PyList_GetItem(keys, i);
+        // 已返回 max_entries 项或键值字节数达到 max_bytes 时停止，从当前键继续
+        if ((max_entries > 0 && n / 2 >= max_entries) || (max_bytes > 0 && bytes >= max_bytes)) {
+            status = copy_bytes_to_result(key_obj, next_key);
+            break;
+        }
+        PyObject* value_obj = at_time < 0
+            ? PyObject_CallMethod(db, "get", "O", key_obj)
+            : PyObject_CallMethod(db, "get_at_time", "Od", key_obj, at_time);
+        if (!value_obj) {
+            status = handle_python_error();
+            break;
+        }
+        // 已删除的键只在 include_deleted 时返回，其值的状态为 AMDB_NOT_FOUND；
+        // 按时间点读取时None表示该键在当时还不存在，始终跳过
+        bool absent = at_time >= 0 && value_obj == Py_None;
+        bool deleted = is_deleted_value(value_obj);
+        if (!absent &&
+            (deleted ? include_deleted : value_matches(value_obj, filters, filter_count))) {
+            status = copy_bytes_to_result(key_obj, &out[n]);
+            if (status == AMDB_OK) {
+                status = copy_value_to_result(value_obj, &out[n + 1]);
+            }
+            n += 2;
+            bytes += (size_t)PyBytes_Size(key_obj) + (size_t)out[n - 1].data_len;
+        }
+        Py_DECREF(value_obj);
+    }
+    Py_DECREF(keys);
+
+    if (status != AMDB_OK) {
+        amdb_free_results(out, n);
+        if (next_key) {
+            amdb_free_result(next_key);
+        }
+        return status;
+    }
+
+    *results = out;
+    *result_count = n;
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_range_query(amdb_handle_t handle,
+                               const uint8_t* start_key, size_t start_key_len,
+                               const uint8_t* end_key, size_t end_key_len,
+                               amdb_result_t** results, size_t* result_count) {
+    WITH_GIL(range_query_locked(handle, start_key, start_key_len, end_key, end_key_len,
+                                false, -1.0, 0, 0, NULL, NULL, 0, results, result_count));
+}
+
+amdb_status_t amdb_range_query_all(amdb_handle_t handle,
+                                   const uint8_t* start_key, size_t start_key_len,
+                                   const uint8_t* end_key, size_t end_key_len,
+                                   amdb_result_t** results, size_t* result_count) {
+    WITH_GIL(range_query_locked(handle, start_key, start_key_len, end_key, end_key_len,
+                                true, -1.0, 0, 0, NULL, NULL, 0, results, result_count));
+}
+
+amdb_status_t amdb_range_query_at(amdb_handle_t handle,
+                                  const uint8_t* start_key, size_t start_key_len,
+                                  const uint8_t* end_key, size_t end_key_len,
+                                  double timestamp,
+                                  amdb_result_t** results, size_t* result_count) {
+    if (timestamp < 0) {
+        return AMDB_INVALID_ARG;
+    }
+    WITH_GIL(range_query_locked(handle, start_key, start_key_len, end_key, end_key_len,
+                                true, timestamp, 0, 0, NULL, NULL, 0, results, result_count));
+}
+
+amdb_status_t amdb_range_query_page(amdb_handle_t handle,
+                                    const uint8_t* start_key, size_t start_key_len,
+                                    const uint8_t* end_key, size_t end_key_len,
+                                    size_t max_entries, size_t max_bytes,
+                                    amdb_result_t** results, size_t* result_count,
+                                    amdb_result_t* next_key) {
+    if (max_entries == 0 && max_bytes == 0) {
+        return AMDB_INVALID_ARG;
+    }
+    WITH_GIL(range_query_locked(handle, start_key, start_key_len, end_key, end_key_len,
+                                false, -1.0, max_entries, max_bytes, next_key, NULL, 0,
+                                results, result_count));
+}
+
+amdb_status_t amdb_range_query_filtered(amdb_handle_t handle,
+                                        const uint8_t* start_key, size_t start_key_len,
+                                        const uint8_t* end_key, size_t end_key_len,
+                                        const amdb_value_filter_t* filters, size_t filter_count,
+                                        amdb_result_t** results, size_t* result_count) {
+    WITH_GIL(range_query_locked(handle, start_key, start_key_len, end_key, end_key_len,
+                                false, -1.0, 0, 0, NULL, filters, filter_count,
+                                results, result_count));
+}
+
+// 范围游标状态：未读部分为 keys[front, back)。游标持有数据库对象的引用，
+// 数据库关闭后仍可安全释放
+typedef struct {
+    amdb_handle_t handle;
+    PyObject* keys;
+    Py_ssize_t front;
+    Py_ssize_t back;
+    double at_time;
+} cursor_t;
+
+static amdb_status_t cursor_open_locked(amdb_handle_t handle,
+                                        const uint8_t* start_key, size_t start_key_len,
+                                        const uint8_t* end_key, size_t end_key_len,
+                                        double timestamp, amdb_cursor_t* cursor) {
+    if (!handle || !cursor || (start_key_len > 0 && !start_key) || (end_key_len > 0 && !end_key)) {
+        return AMDB_INVALID_ARG;
+    }
+    *cursor = NULL;
+
+    cursor_t* c = calloc(1, sizeof(cursor_t));
+    if (!c) {
+        return AMDB_MEMORY_ERROR;
+    }
+    c->keys = range_keys((PyObject*)handle, start_key, start_key_len, end_key, end_key_len);
+    if (!c->keys) {
+        free(c);
+        return handle_python_error();
+    }
+    Py_INCREF((PyObject*)handle);
+    c->handle = handle;
+    c->front = 0;
+    c->back = PyList_Size(c->keys);
+    c->at_time = timestamp;
+
+    *cursor = (amdb_cursor_t)c;
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_cursor_open(amdb_handle_t handle,
+                               const uint8_t* start_key, size_t start_key_len,
+                               const uint8_t* end_key, size_t end_key_len,
+                               double timestamp,
+                               amdb_cursor_t* cursor) {
+    WITH_GIL(cursor_open_locked(handle, start_key, start_key_len, end_key, end_key_len,
+                                timestamp, cursor));
+}
+
+static amdb_status_t cursor_next_locked(cursor_t* c, bool from_back, size_t max_entries,
+                                        amdb_result_t** results, size_t* result_count) {
+    if (!c || max_entries == 0 || !results || !result_count) {
+        return AMDB_INVALID_ARG;
+    }
+    *results = NULL;
+    *result_count = 0;
+
+    Py_ssize_t left = c->back - c->front;
+    if (left <= 0) {
+        return AMDB_OK;
+    }
+    size_t cap = (size_t)left < max_entries ? (size_t)left : max_entries;
+    amdb_result_t* out = calloc(cap * 2, sizeof(amdb_result_t));
+    if (!out) {
+        return AMDB_MEMORY_ERROR;
+    }
+
+    PyObject* db = (PyObject*)c->handle;
+    size_t n = 0;
+    amdb_status_t status = AMDB_OK;
+    while (n / 2 < cap && c->front < c->back) {
+        PyObject* key_obj = PyList_GetItem(c->keys, from_back ? c->back - 1 : c->front);
+        PyObject* value_obj = c->at_time < 0
+            ? PyObject_CallMethod(db, "get", "O", key_obj)
+            : PyObject_CallMethod(db, "get_at_time", "Od", key_obj, c->at_time);
+        if (!value_obj) {
+            status = handle_python_error();
+            break;
+        }
+        // 出错时不移动位置，重试可从同一个键继续
+        if (from_back) {
+            c->back--;
+        } else {
+            c->front++;
+        }
+        if (value_obj != Py_None && !is_deleted_value(value_obj)) {
+            status = copy_bytes_to_result(key_obj, &out[n]);
+            if (status == AMDB_OK) {
+                status = copy_bytes_to_result(value_obj, &out[n + 1]);
+            }
+            n += 2;
+        }
+        Py_DECREF(value_obj);
+        if (status != AMDB_OK) {
+            break;
+        }
+    }
+
+    if (status != AMDB_OK) {
+        amdb_free_results(out, n);
+        return status;
+    }
+    if (n == 0) {
+        free(out);
+        return AMDB_OK;
+    }
+    *results = out;
+    *result_count = n;
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_cursor_next(amdb_cursor_t cursor, bool from_back, size_t max_entries,
+                               amdb_result_t** results, size_t* result_count) {
+    WITH_GIL(cursor_next_locked((cursor_t*)cursor, from_back, max_entries, results, result_count));
+}
+
+static amdb_status_t cursor_close_locked(cursor_t* c) {
+    if (!c) {
+        return AMDB_INVALID_ARG;
+    }
+    Py_DECREF(c->keys);
+    Py_DECREF((PyObject*)c->handle);
+    free(c);
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_cursor_close(amdb_cursor_t cursor) {
+    WITH_GIL(cursor_close_locked((cursor_t*)cursor));
+}
+
+static amdb_status_t multi_get_locked(amdb_handle_t handle,
+                                      const uint8_t** keys, const size_t* key_lens, size_t count,
+                                      amdb_result_t** results) {
+    if (!handle || !results || (count > 0 && (!keys || !key_lens))) {
+        return AMDB_INVALID_ARG;
+    }
+    *results = NULL;
+    if (count == 0) {
+        return AMDB_OK;
+    }
+
+    amdb_result_t* out = calloc(count, sizeof(amdb_result_t));
+    if (!out) {
+        return AMDB_MEMORY_ERROR;
+    }
+
+    PyObject* db = (PyObject*)handle;
+    amdb_status_t status = AMDB_OK;
+    for (size_t i = 0; i < count && status == AMDB_OK; i++) {
+        PyObject* key_obj = PyBytes_FromStringAndSize((const char*)keys[i], key_lens[i]);
+        if (!key_obj) {
+            status = handle_python_error();
+            break;
+        }
+        PyObject* value_obj = PyObject_CallMethod(db, "get", "O", key_obj);
+        Py_DECREF(key_obj);
+        if (!value_obj) {
+            status = handle_python_error();
+            break;
+        }
+        if (is_deleted_value(value_obj)) {
+            out[i].status = AMDB_NOT_FOUND;
+        } else {
+            status = copy_bytes_to_result(value_obj, &out[i]);
+        }
+        Py_DECREF(value_obj);
+    }
+
+    if (status != AMDB_OK) {
+        amdb_free_results(out, count);
+        return status;
+    }
+    *results = out;
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_multi_get(amdb_handle_t handle,
+                             const uint8_t** keys, const size_t* key_lens, size_t count,
+                             amdb_result_t** results) {
+    WITH_GIL(multi_get_locked(handle, keys, key_lens, count, results));
+}
+
+static amdb_status_t get_at_time_locked(amdb_handle_t handle,
+                                        const uint8_t* key, size_t key_len,
+                                        double timestamp,
+                                        amdb_result_t* result) {
+    if (!handle || !key || !result) {
+        return AMDB_INVALID_ARG;
+    }
+    result->data = NULL;
+    result->data_len = 0;
+
+    PyObject* db = (PyObject*)handle;
+    PyObject* key_obj = PyBytes_FromStringAndSize((const char*)key, key_len);
+    PyObject* value_obj = PyObject_CallMethod(db, "get_at_time", "Od", key_obj, timestamp);
+    Py_DECREF(key_obj);
+    if (!value_obj) {
+        result->status = handle_python_error();
+        return result->status;
+    }
+
+    if (value_obj == Py_None || is_deleted_value(value_obj)) {
+        result->status = AMDB_NOT_FOUND;
+    } else {
+        result->status = copy_bytes_to_result(value_obj, result);
+    }
+    Py_DECREF(value_obj);
+    return result->status;
+}
+
+amdb_status_t amdb_get_at_time(amdb_handle_t handle,
+                               const uint8_t* key, size_t key_len,
+                               double timestamp,
+                               amdb_result_t* result) {
+    WITH_GIL(get_at_time_locked(handle, key, key_len, timestamp, result));
+}
+
+static amdb_status_t commit_locked(amdb_handle_t handle, uint64_t* version, uint8_t* root_hash) {
+    if (!handle || !version || !root_hash) {
+        return AMDB_INVALID_ARG;
+    }
+    PyObject* result = PyObject_CallMethod((PyObject*)handle, "commit", NULL);
+    if (!result) {
+        return handle_python_error();
+    }
+    PyObject* number_obj = PyTuple_Check(result) && PyTuple_Size(result) == 2
+        ? PyTuple_GetItem(result, 0) : NULL;
+    PyObject* hash_obj = number_obj ? PyTuple_GetItem(result, 1) : NULL;
+    if (!number_obj || !PyLong_Check(number_obj) || !PyBytes_Check(hash_obj)) {
+        Py_DECREF(result);
+        return AMDB_ERROR;
+    }
+    *version = (uint64_t)PyLong_AsUnsignedLongLong(number_obj);
+    Py_ssize_t hash_len = PyBytes_Size(hash_obj);
+    memset(root_hash, 0, 32);
+    memcpy(root_hash, PyBytes_AsString(hash_obj), hash_len < 32 ? (size_t)hash_len : 32);
+    Py_DECREF(result);
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_commit(amdb_handle_t handle, uint64_t* version, uint8_t* root_hash) {
+    WITH_GIL(commit_locked(handle, version, root_hash));
+}
+
+static amdb_status_t get_state_version_locked(amdb_handle_t handle, uint64_t* version) {
+    if (!handle || !version) {
+        return AMDB_INVALID_ARG;
+    }
+    PyObject* result = PyObject_CallMethod((PyObject*)handle, "get_state_version", NULL);
+    if (!result) {
+        return handle_python_error();
+    }
+    *version = (uint64_t)PyLong_AsUnsignedLongLong(result);
+    Py_DECREF(result);
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_get_state_version(amdb_handle_t handle, uint64_t* version) {
+    WITH_GIL(get_state_version_locked(handle, version));
+}
+
+static amdb_status_t get_commit_root_locked(amdb_handle_t handle, uint64_t version,
+                                            uint8_t* root_hash) {
+    if (!handle || !root_hash) {
+        return AMDB_INVALID_ARG;
+    }
+    PyObject* commit = PyObject_CallMethod((PyObject*)handle, "get_commit", "K",
+                                           (unsigned long long)version);
+    if (!commit) {
+        return handle_python_error();
+    }
+    if (commit == Py_None) {
+        Py_DECREF(commit);
+        return AMDB_NOT_FOUND;
+    }
+    PyObject* hash_obj = PyTuple_Check(commit) && PyTuple_Size(commit) == 2
+        ? PyTuple_GetItem(commit, 1) : NULL;
+    if (!hash_obj || !PyBytes_Check(hash_obj)) {
+        Py_DECREF(commit);
+        return AMDB_ERROR;
+    }
+    // 与 amdb_get_root_hash 相同，不足32字节的根哈希补零
+    Py_ssize_t hash_len = PyBytes_Size(hash_obj);
+    memset(root_hash, 0, 32);
+    memcpy(root_hash, PyBytes_AsString(hash_obj), hash_len < 32 ? (size_t)hash_len : 32);
+    Py_DECREF(commit);
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_get_commit_root(amdb_handle_t handle, uint64_t version, uint8_t* root_hash) {
+    WITH_GIL(get_commit_root_locked(handle, version, root_hash));
+}
+
+static amdb_status_t merkle_frontier_locked(amdb_handle_t handle, uint64_t version,
+                                             amdb_result_t* last_key, uint8_t* leaf_hash,
+                                             amdb_result_t* path, uint8_t* root_hash) {
+    if (!handle || !last_key || !leaf_hash || !path || !root_hash) {
+        return AMDB_INVALID_ARG;
+    }
+    memset(last_key, 0, sizeof(*last_key));
+    memset(path, 0, sizeof(*path));
+    memset(leaf_hash, 0, 32);
+    memset(root_hash, 0, 32);
+
+    PyObject* frontier = PyObject_CallMethod((PyObject*)handle, "merkle_frontier", "K",
+                                             (unsigned long long)version);
+    if (!frontier) {
+        return handle_python_error();
+    }
+    if (frontier == Py_None) {
+        Py_DECREF(frontier);
+        return AMDB_NOT_FOUND;
+    }
+    PyObject* hash_obj = PyTuple_Check(frontier) && PyTuple_Size(frontier) == 2
+        ? PyTuple_GetItem(frontier, 0) : NULL;
+    if (!hash_obj || !PyBytes_Check(hash_obj)) {
+        Py_DECREF(frontier);
+        return AMDB_ERROR;
+    }
+    Py_ssize_t hash_len = PyBytes_Size(hash_obj);
+    memcpy(root_hash, PyBytes_AsString(hash_obj), hash_len < 32 ? (size_t)hash_len : 32);
+
+    PyObject* edge = PyTuple_GetItem(frontier, 1);
+    amdb_status_t status = AMDB_OK;
+    if (edge != Py_None) {
+        PyObject* key_obj = PyTuple_Check(edge) && PyTuple_Size(edge) == 3
+            ? PyTuple_GetItem(edge, 0) : NULL;
+        PyObject* leaf_obj = key_obj ? PyTuple_GetItem(edge, 1) : NULL;
+        PyObject* path_obj = key_obj ? PyTuple_GetItem(edge, 2) : NULL;
+        if (!key_obj || !PyBytes_Check(key_obj) || !PyBytes_Check(leaf_obj) ||
+            PyBytes_Size(leaf_obj) != 32 || !PyBytes_Check(path_obj)) {
+            Py_DECREF(frontier);
+            return AMDB_ERROR;
+        }
+        memcpy(leaf_hash, PyBytes_AsString(leaf_obj), 32);
+        status = copy_bytes_to_result(key_obj, last_key);
+        if (status == AMDB_OK) {
+            status = copy_bytes_to_result(path_obj, path);
+        }
+        if (status != AMDB_OK) {
+            amdb_free_result(last_key);
+        }
+    }
+    Py_DECREF(frontier);
+    return status;
+}
+
+amdb_status_t amdb_merkle_frontier(amdb_handle_t handle, uint64_t version,
+                                   amdb_result_t* last_key, uint8_t* leaf_hash,
+                                   amdb_result_t* path, uint8_t* root_hash) {
+    WITH_GIL(merkle_frontier_locked(handle, version, last_key, leaf_hash, path, root_hash));
+}
+
+static amdb_status_t diff_locked(amdb_handle_t handle, uint64_t from_version,
+                                 uint64_t to_version,
+                                 amdb_result_t** results, size_t* result_count) {
+    if (!handle || !results || !result_count) {
+        return AMDB_INVALID_ARG;
+    }
+    *results = NULL;
+    *result_count = 0;
+
+    PyObject* changes = PyObject_CallMethod((PyObject*)handle, "diff", "KK",
+                                            (unsigned long long)from_version,
+                                            (unsigned long long)to_version);
+    if (!changes) {
+        return handle_python_error();
+    }
+    if (changes == Py_None) {
+        Py_DECREF(changes);
+        return AMDB_NOT_FOUND;
+    }
+    if (!PyList_Check(changes)) {
+        Py_DECREF(changes);
+        return AMDB_ERROR;
+    }
+
+    Py_ssize_t count = PyList_Size(changes);
+    if (count == 0) {
+        Py_DECREF(changes);
+        return AMDB_OK;
+    }
+    amdb_result_t* out = calloc((size_t)count * 3, sizeof(amdb_result_t));
+    if (!out) {
+        Py_DECREF(changes);
+        return AMDB_MEMORY_ERROR;
+    }
+    size_t n = 0;
+    amdb_status_t status = AMDB_OK;
+    for (Py_ssize_t i = 0; i < count && status == AMDB_OK; i++) {
+        PyObject* change = PyList_GetItem(changes, i);
+        if (!PyTuple_Check(change) || PyTuple_Size(change) != 3) {
+            status = AMDB_ERROR;
+            break;
+        }
+        PyObject* key_obj = PyTuple_GetItem(change, 0);
+        status = PyBytes_Check(key_obj) ? copy_bytes_to_result(key_obj, &out[n]) : AMDB_ERROR;
+        for (Py_ssize_t j = 1; j < 3 && status == AMDB_OK; j++) {
+            status = copy_value_to_result(PyTuple_GetItem(change, j), &out[n + j]);
+        }
+        n += 3;
+    }
+    Py_DECREF(changes);
+
+    if (status != AMDB_OK) {
+        amdb_free_results(out, n);
+        return status;
+    }
+    *results = out;
+    *result_count = n;
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_diff(amdb_handle_t handle, uint64_t from_version, uint64_t to_version,
+                        amdb_result_t** results, size_t* result_count) {
+    WITH_GIL(diff_locked(handle, from_version, to_version, results, result_count));
+}
+
+// 把Python的键列表复制为结果数组；keys 为 None 时返回 AMDB_NOT_FOUND。释放 keys 的引用
+static amdb_status_t key_list_to_results(PyObject* keys,
+                                         amdb_result_t** results, size_t* result_count) {
+    if (keys == Py_None) {
+        Py_DECREF(keys);
+        return AMDB_NOT_FOUND;
+    }
+    if (!PyList_Check(keys)) {
+        Py_DECREF(keys);
+        return AMDB_ERROR;
+    }
+
+    Py_ssize_t count = PyList_Size(keys);
+    if (count == 0) {
+        Py_DECREF(keys);
+        return AMDB_OK;
+    }
+    amdb_result_t* out = calloc((size_t)count, sizeof(amdb_result_t));
+    if (!out) {
+        Py_DECREF(keys);
+        return AMDB_MEMORY_ERROR;
+    }
+    size_t n = 0;
+    amdb_status_t status = AMDB_OK;
+    for (Py_ssize_t i = 0; i < count && status == AMDB_OK; i++) {
+        PyObject* key = PyList_GetItem(keys, i);
+        status = PyBytes_Check(key) ? copy_bytes_to_result(key, &out[n]) : AMDB_ERROR;
+        n++;
+    }
+    Py_DECREF(keys);
+
+    if (status != AMDB_OK) {
+        amdb_free_results(out, n);
+        return status;
+    }
+    *results = out;
+    *result_count = n;
+    return AMDB_OK;
+}
+
+static amdb_status_t changed_keys_locked(amdb_handle_t handle, uint64_t from_version,
+                                         uint64_t to_version,
+                                         const uint8_t* prefix, size_t prefix_len,
+                                         amdb_result_t** results, size_t* result_count) {
+    if (!handle || (prefix_len > 0 && !prefix) || !results || !result_count) {
+        return AMDB_INVALID_ARG;
+    }
+    *results = NULL;
+    *result_count = 0;
+
+    PyObject* prefix_obj = PyBytes_FromStringAndSize((const char*)prefix, (Py_ssize_t)prefix_len);
+    if (!prefix_obj) {
+        return AMDB_MEMORY_ERROR;
+    }
+    PyObject* keys = PyObject_CallMethod((PyObject*)handle, "changed_keys", "KKO",
+                                         (unsigned long long)from_version,
+                                         (unsigned long long)to_version, prefix_obj);
+    Py_DECREF(prefix_obj);
+    if (!keys) {
+        return handle_python_error();
+    }
+    return key_list_to_results(keys, results, result_count);
+}
+
+amdb_status_t amdb_changed_keys(amdb_handle_t handle, uint64_t from_version, uint64_t to_version,
+                                const uint8_t* prefix, size_t prefix_len,
+                                amdb_result_t** results, size_t* result_count) {
+    WITH_GIL(changed_keys_locked(handle, from_version, to_version, prefix, prefix_len,
+                                 results, result_count));
+}
+
+static amdb_status_t partition_ranges_locked(amdb_handle_t handle, uint64_t version, size_t parts,
+                                             amdb_result_t** results, size_t* result_count) {
+    if (!handle || parts == 0 || !results || !result_count) {
+        return AMDB_INVALID_ARG;
+    }
+    *results = NULL;
+    *result_count = 0;
+
+    PyObject* keys = PyObject_CallMethod((PyObject*)handle, "partition_ranges", "nK",
+                                         (Py_ssize_t)parts, (unsigned long long)version);
+    if (!keys) {
+        return handle_python_error();
+    }
+    return key_list_to_results(keys, results, result_count);
+}
+
+amdb_status_t amdb_partition_ranges(amdb_handle_t handle, uint64_t version, size_t parts,
+                                    amdb_result_t** results, size_t* result_count) {
+    WITH_GIL(partition_ranges_locked(handle, version, parts, results, result_count));
+}
+
+static amdb_status_t key_history_locked(amdb_handle_t handle,
+                                        const uint8_t* key, size_t key_len,
+                                        uint64_t after_version, size_t max_entries,
+                                        bool include_values,
+                                        amdb_history_entry64_t** entries, size_t* entry_count) {
+    if (!handle || !key || max_entries == 0 || !entries || !entry_count) {
+        return AMDB_INVALID_ARG;
+    }
+    *entries = NULL;
+    *entry_count = 0;
+
+    PyObject* key_obj = PyBytes_FromStringAndSize((const char*)key, key_len);
+    if (!key_obj) {
+        return handle_python_error();
+    }
+    // 返回 [(键的版本号, 数据库版本, 根哈希, 是否为删除, 值), ...]
+    PyObject* list = PyObject_CallMethod((PyObject*)handle, "key_history", "OKnO", key_obj,
+                                         (unsigned long long)after_version, (Py_ssize_t)max_entries,
+                                         include_values ? Py_True : Py_False);
+    Py_DECREF(key_obj);
+    if (!list) {
+        return handle_python_error();
+    }
+    if (!PyList_Check(list)) {
+        Py_DECREF(list);
+        return AMDB_ERROR;
+    }
+    Py_ssize_t count = PyList_Size(list);
+    if (count == 0) {
+        Py_DECREF(list);
+        return AMDB_OK;
+    }
+    amdb_history_entry64_t* out = calloc((size_t)count, sizeof(amdb_history_entry64_t));
+    if (!out) {
+        Py_DECREF(list);
+        return AMDB_MEMORY_ERROR;
+    }
+
+    amdb_status_t status = AMDB_OK;
+    Py_ssize_t n = 0;
+    for (; n < count && status == AMDB_OK; n++) {
+        PyObject* item = PyList_GetItem(list, n);
+        if (!PyTuple_Check(item) || PyTuple_Size(item) != 5) {
+            status = AMDB_ERROR;
+            break;
+        }
+        PyObject* hash_obj = PyTuple_GetItem(item, 2);
+        PyObject* value_obj = PyTuple_GetItem(item, 4);
+        if (!PyBytes_Check(hash_obj)) {
+            status = AMDB_ERROR;
+            break;
+        }
+        out[n].key_version = (uint64_t)PyLong_AsUnsignedLongLong(PyTuple_GetItem(item, 0));
+        out[n].version = (uint64_t)PyLong_AsUnsignedLongLong(PyTuple_GetItem(item, 1));
+        Py_ssize_t hash_len = PyBytes_Size(hash_obj);
+        memcpy(out[n].root_hash, PyBytes_AsString(hash_obj), hash_len < 32 ? (size_t)hash_len : 32);
+        out[n].deleted = PyObject_IsTrue(PyTuple_GetItem(item, 3)) == 1;
+        if (value_obj != Py_None) {
+            status = copy_bytes_to_result(value_obj, &out[n].value);
+        }
+    }
+    Py_DECREF(list);
+
+    if (status != AMDB_OK) {
+        amdb_free_history64(out, (size_t)n);
+        return status;
+    }
+    *entries = out;
+    *entry_count = (size_t)count;
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_key_history64(amdb_handle_t handle,
+                                 const uint8_t* key, size_t key_len,
+                                 uint64_t after_version, size_t max_entries,
+                                 bool include_values,
+                                 amdb_history_entry64_t** entries, size_t* entry_count) {
+    WITH_GIL(key_history_locked(handle, key, key_len, after_version, max_entries,
+                                include_values, entries, entry_count));
+}
+
+amdb_status_t amdb_key_history(amdb_handle_t handle,
+                               const uint8_t* key, size_t key_len,
+                               uint32_t after_version, size_t max_entries,
+                               bool include_values,
+                               amdb_history_entry_t** entries, size_t* entry_count) {
+    if (!entries || !entry_count) {
+        return AMDB_INVALID_ARG;
+    }
+    *entries = NULL;
+    *entry_count = 0;
+    amdb_history_entry64_t* wide = NULL;
+    size_t count = 0;
+    amdb_status_t status = amdb_key_history64(handle, key, key_len, after_version, max_entries,
+                                              include_values, &wide, &count);
+    if (status != AMDB_OK || count == 0) {
+        return status;
+    }
+    // 版本号截断后会对应另一个版本，超出32位时报错而不是截断
+    for (size_t i = 0; i < count; i++) {
+        if (wide[i].key_version > UINT32_MAX) {
+            amdb_free_history64(wide, count);
+            return AMDB_INVALID_ARG;
+        }
+    }
+    amdb_history_entry_t* out = calloc(count, sizeof(amdb_history_entry_t));
+    if (!out) {
+        amdb_free_history64(wide, count);
+        return AMDB_MEMORY_ERROR;
+    }
+    for (size_t i = 0; i < count; i++) {
+        out[i].key_version = (uint32_t)wide[i].key_version;
+        out[i].version = wide[i].version;
+        memcpy(out[i].root_hash, wide[i].root_hash, 32);
+        out[i].deleted = wide[i].deleted;
+        out[i].value = wide[i].value;
+    }
+    // 值的所有权已转给 out
+    free(wide);
+    *entries = out;
+    *entry_count = count;
+    return AMDB_OK;
+}
+
+static amdb_status_t key_versions_locked(amdb_handle_t handle,
+                                         const uint8_t* key, size_t key_len,
+                                         uint64_t start_version, uint64_t end_version,
+                                         uint64_t** versions, size_t* version_count) {
+    if (!handle || !key || !versions || !version_count || end_version < start_version) {
+        return AMDB_INVALID_ARG;
+    }
+    *versions = NULL;
+    *version_count = 0;
+
+    PyObject* key_obj = PyBytes_FromStringAndSize((const char*)key, key_len);
+    if (!key_obj) {
+        return handle_python_error();
+    }
+    // 返回 [{'version': 键的版本号, ...}, ...]；结束版本号为 UINT64_MAX 时不设上界
+    PyObject* list = end_version == UINT64_MAX
+        ? PyObject_CallMethod((PyObject*)handle, "get_history", "OK", key_obj,
+                              (unsigned long long)start_version)
+        : PyObject_CallMethod((PyObject*)handle, "get_history", "OKK", key_obj,
+                              (unsigned long long)start_version,
+                              (unsigned long long)end_version);
+    Py_DECREF(key_obj);
+    if (!list) {
+        return handle_python_error();
+    }
+    if (!PyList_Check(list)) {
+        Py_DECREF(list);
+        return AMDB_ERROR;
+    }
+    Py_ssize_t count = PyList_Size(list);
+    if (count == 0) {
+        Py_DECREF(list);
+        return AMDB_OK;
+    }
+    uint64_t* out = malloc((size_t)count * sizeof(uint64_t));
+    if (!out) {
+        Py_DECREF(list);
+        return AMDB_MEMORY_ERROR;
+    }
+    for (Py_ssize_t i = 0; i < count; i++) {
+        PyObject* item = PyList_GetItem(list, i);
+        PyObject* version_obj = PyDict_Check(item) ? PyDict_GetItemString(item, "version") : NULL;
+        if (!version_obj) {
+            free(out);
+            Py_DECREF(list);
+            return AMDB_ERROR;
+        }
+        out[i] = (uint64_t)PyLong_AsUnsignedLongLong(version_obj);
+    }
+    Py_DECREF(list);
+    *versions = out;
+    *version_count = (size_t)count;
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_key_versions(amdb_handle_t handle,
+                                const uint8_t* key, size_t key_len,
+                                uint64_t start_version, uint64_t end_version,
+                                uint64_t** versions, size_t* version_count) {
+    WITH_GIL(key_versions_locked(handle, key, key_len, start_version, end_version,
+                                 versions, version_count));
+}
+
+// 快照只记录版本的提交时间，读取时按时间点读取；持有数据库对象的引用
+typedef struct {
+    amdb_handle_t handle;
+    uint64_t version;
+    double at_time;
+    uint8_t root_hash[32];
+} snapshot_t;
+
+static amdb_status_t snapshot_open_locked(amdb_handle_t handle, uint64_t version,
+                                          amdb_snapshot_t* snapshot) {
+    if (!handle || !snapshot) {
+        return AMDB_INVALID_ARG;
+    }
+    *snapshot = NULL;
+
+    PyObject* commit = PyObject_CallMethod((PyObject*)handle, "get_commit", "K",
+                                           (unsigned long long)version);
+    if (!commit) {
+        return handle_python_error();
+    }
+    if (commit == Py_None) {
+        Py_DECREF(commit);
+        return AMDB_NOT_FOUND;
+    }
+    PyObject* hash_obj = PyTuple_Check(commit) && PyTuple_Size(commit) == 2
+        ? PyTuple_GetItem(commit, 1) : NULL;
+    if (!hash_obj || !PyBytes_Check(hash_obj)) {
+        Py_DECREF(commit);
+        return AMDB_ERROR;
+    }
+
+    snapshot_t* snap = calloc(1, sizeof(snapshot_t));
+    if (!snap) {
+        Py_DECREF(commit);
+        return AMDB_MEMORY_ERROR;
+    }
+    snap->version = version;
+    snap->at_time = PyFloat_AsDouble(PyTuple_GetItem(commit, 0));
+    // 与 amdb_get_root_hash 相同，不足32字节的根哈希补零
+    Py_ssize_t hash_len = PyBytes_Size(hash_obj);
+    memcpy(snap->root_hash, PyBytes_AsString(hash_obj), hash_len < 32 ? (size_t)hash_len : 32);
+    Py_DECREF(commit);
+
+    Py_INCREF((PyObject*)handle);
+    snap->handle = handle;
+    *snapshot = (amdb_snapshot_t)snap;
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_snapshot_open(amdb_handle_t handle, uint64_t version,
+                                 amdb_snapshot_t* snapshot) {
+    WITH_GIL(snapshot_open_locked(handle, version, snapshot));
+}
+
+static amdb_status_t snapshot_open_at_root_locked(amdb_handle_t handle, const uint8_t* root_hash,
+                                                  amdb_snapshot_t* snapshot) {
+    if (!handle || !root_hash || !snapshot) {
+        return AMDB_INVALID_ARG;
+    }
+    *snapshot = NULL;
+
+    PyObject* hash_obj = PyBytes_FromStringAndSize((const char*)root_hash, 32);
+    PyObject* version_obj = PyObject_CallMethod((PyObject*)handle, "find_commit", "O", hash_obj);
+    Py_DECREF(hash_obj);
+    if (!version_obj) {
+        return handle_python_error();
+    }
+    if (version_obj == Py_None) {
+        Py_DECREF(version_obj);
+        return AMDB_NOT_FOUND;
+    }
+    uint64_t version = (uint64_t)PyLong_AsUnsignedLongLong(version_obj);
+    Py_DECREF(version_obj);
+    return snapshot_open_locked(handle, version, snapshot);
+}
+
+amdb_status_t amdb_snapshot_open_at_root(amdb_handle_t handle, const uint8_t* root_hash,
+                                         amdb_snapshot_t* snapshot) {
+    WITH_GIL(snapshot_open_at_root_locked(handle, root_hash, snapshot));
+}
+
+amdb_status_t amdb_snapshot_info(amdb_snapshot_t snapshot, uint64_t* version,
+                                 double* timestamp, uint8_t* root_hash) {
+    snapshot_t* snap = (snapshot_t*)snapshot;
+    if (!snap || !version || !timestamp || !root_hash) {
+        return AMDB_INVALID_ARG;
+    }
+    *version = snap->version;
+    *timestamp = snap->at_time;
+    memcpy(root_hash, snap->root_hash, 32);
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_snapshot_get(amdb_snapshot_t snapshot,
+                                const uint8_t* key, size_t key_len,
+                                amdb_result_t* result) {
+    snapshot_t* snap = (snapshot_t*)snapshot;
+    if (!snap) {
+        return AMDB_INVALID_ARG;
+    }
+    return amdb_get_at_time(snap->handle, key, key_len, snap->at_time, result);
+}
+
+amdb_status_t amdb_snapshot_cursor_open(amdb_snapshot_t snapshot,
+                                        const uint8_t* start_key, size_t start_key_len,
+                                        const uint8_t* end_key, size_t end_key_len,
+                                        amdb_cursor_t* cursor) {
+    snapshot_t* snap = (snapshot_t*)snapshot;
+    if (!snap) {
+        return AMDB_INVALID_ARG;
+    }
+    return amdb_cursor_open(snap->handle, start_key, start_key_len, end_key, end_key_len,
+                            snap->at_time, cursor);
+}
+
+static amdb_status_t snapshot_close_locked(snapshot_t* snap) {
+    if (!snap) {
+        return AMDB_INVALID_ARG;
+    }
+    Py_DECREF((PyObject*)snap->handle);
+    free(snap);
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_snapshot_close(amdb_snapshot_t snapshot) {
+    WITH_GIL(snapshot_close_locked((snapshot_t*)snapshot));
+}
+
+static amdb_status_t get_with_proof_locked(amdb_handle_t handle,
+                                           const uint8_t* key, size_t key_len,
+                                           amdb_result_t* value, uint64_t* version,
+                                           amdb_result_t* proof, uint8_t* root_hash) {
+    if (!handle || !key || !value || !version || !proof || !root_hash) {
+        return AMDB_INVALID_ARG;
+    }
+    value->data = NULL;
+    value->data_len = 0;
+    proof->data = NULL;
+    proof->data_len = 0;
+    *version = 0;
+
+    PyObject* db = (PyObject*)handle;
+    PyObject* key_obj = PyBytes_FromStringAndSize((const char*)key, key_len);
+    PyObject* result = PyObject_CallMethod(db, "get_with_path_proof", "O", key_obj);
+    Py_DECREF(key_obj);
+    if (!result) {
+        return handle_python_error();
+    }
+
+    if (!PyTuple_Check(result) || PyTuple_Size(result) != 4 ||
+        !PyBytes_Check(PyTuple_GetItem(result, 3))) {
+        Py_DECREF(result);
+        return AMDB_ERROR;
+    }
+    PyObject* value_obj = PyTuple_GetItem(result, 0);
+    PyObject* version_obj = PyTuple_GetItem(result, 1);
+    PyObject* proof_obj = PyTuple_GetItem(result, 2);
+    PyObject* root_obj = PyTuple_GetItem(result, 3);
+
+    // 与 amdb_get_root_hash 相同，不足32字节的根哈希补零
+    Py_ssize_t hash_len = PyBytes_Size(root_obj);
+    memset(root_hash, 0, 32);
+    memcpy(root_hash, PyBytes_AsString(root_obj), hash_len < 32 ? (size_t)hash_len : 32);
+    *version = (uint64_t)PyLong_AsUnsignedLongLong(version_obj);
+
+    amdb_status_t status = AMDB_OK;
+    if (proof_obj != Py_None) {
+        status = copy_bytes_to_result(proof_obj, proof);
+    }
+    if (status == AMDB_OK && value_obj != Py_None && !is_deleted_value(value_obj)) {
+        status = copy_bytes_to_result(value_obj, value);
+    }
+    Py_DECREF(result);
+    if (status != AMDB_OK) {
+        amdb_free_result(value);
+        amdb_free_result(proof);
+    }
+    value->status = status;
+    proof->status = status;
+    return status;
+}
+
+amdb_status_t amdb_get_with_proof64(amdb_handle_t handle,
+                                    const uint8_t* key, size_t key_len,
+                                    amdb_result_t* value, uint64_t* version,
+                                    amdb_result_t* proof, uint8_t* root_hash) {
+    WITH_GIL(get_with_proof_locked(handle, key, key_len, value, version, proof, root_hash));
+}
+
+amdb_status_t amdb_get_with_proof(amdb_handle_t handle,
+                                  const uint8_t* key, size_t key_len,
+                                  amdb_result_t* value, uint32_t* version,
+                                  amdb_result_t* proof, uint8_t* root_hash) {
+    if (!version) {
+        return AMDB_INVALID_ARG;
+    }
+    uint64_t wide = 0;
+    amdb_status_t status = amdb_get_with_proof64(handle, key, key_len, value, &wide,
+                                                 proof, root_hash);
+    *version = 0;
+    if (status != AMDB_OK) {
+        return status;
+    }
+    if (wide > UINT32_MAX) {
+        amdb_free_result(value);
+        amdb_free_result(proof);
+        return AMDB_INVALID_ARG;
+    }
+    *version = (uint32_t)wide;
+    return AMDB_OK;
+}
+
+static amdb_status_t get_tree_option_locked(amdb_handle_t handle, const char* name,
+                                            amdb_result_t* result) {
+    if (!handle || !name || !result) {
+        return AMDB_INVALID_ARG;
+    }
+    result->data = NULL;
+    result->data_len = 0;
+
+    PyObject* db = (PyObject*)handle;
+    PyObject* value_obj = PyObject_CallMethod(db, "get_tree_option", "s", name);
+    if (!value_obj) {
+        result->status = handle_python_error();
+        return result->status;
+    }
+
+    if (value_obj == Py_None) {
+        result->status = AMDB_NOT_FOUND;
+    } else {
+        PyObject* encoded = PyUnicode_AsUTF8String(value_obj);
+        result->status = encoded ? copy_bytes_to_result(encoded, result) : handle_python_error();
+        Py_XDECREF(encoded);
+    }
+    Py_DECREF(value_obj);
+    return result->status;
+}
+
+amdb_status_t amdb_get_tree_option(amdb_handle_t handle, const char* name,
+                                   amdb_result_t* result) {
+    WITH_GIL(get_tree_option_locked(handle, name, result));
+}
+
+static amdb_status_t background_error_locked(amdb_handle_t handle, amdb_result_t* result) {
+    if (!handle || !result) {
+        return AMDB_INVALID_ARG;
+    }
+    result->data = NULL;
+    result->data_len = 0;
+
+    PyObject* message = PyObject_CallMethod((PyObject*)handle, "background_error", NULL);
+    if (!message) {
+        result->status = handle_python_error();
+        return result->status;
+    }
+
+    if (message == Py_None) {
+        result->status = AMDB_NOT_FOUND;
+    } else {
+        PyObject* encoded = PyUnicode_AsUTF8String(message);
+        result->status = encoded ? copy_bytes_to_result(encoded, result) : handle_python_error();
+        Py_XDECREF(encoded);
+    }
+    Py_DECREF(message);
+    return result->status;
+}
+
+amdb_status_t amdb_background_error(amdb_handle_t handle, amdb_result_t* result) {
+    WITH_GIL(background_error_locked(handle, result));
+}
+
+static amdb_status_t multi_contains_locked(amdb_handle_t handle,
+                                           const uint8_t** keys, const size_t* key_lens,
+                                           size_t count, uint8_t* exists) {
+    if (!handle || (count > 0 && (!keys || !key_lens || !exists))) {
+        return AMDB_INVALID_ARG;
+    }
+
+    PyObject* db = (PyObject*)handle;
+    for (size_t i = 0; i < count; i++) {
+        PyObject* key_obj = PyBytes_FromStringAndSize((const char*)keys[i], key_lens[i]);
+        if (!key_obj) {
+            return handle_python_error();
+        }
+        PyObject* value_obj = PyObject_CallMethod(db, "get", "O", key_obj);
+        Py_DECREF(key_obj);
+        if (!value_obj) {
+            return handle_python_error();
+        }
+        exists[i] = is_deleted_value(value_obj) ? 0 : 1;
+        Py_DECREF(value_obj);
+    }
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_multi_contains(amdb_handle_t handle,
+                                  const uint8_t** keys, const size_t* key_lens, size_t count,
+                                  uint8_t* exists) {
+    WITH_GIL(multi_contains_locked(handle, keys, key_lens, count, exists));
+}
+
+static amdb_status_t multi_written_locked(amdb_handle_t handle,
+                                          const uint8_t** keys, const size_t* key_lens,
+                                          size_t count, uint8_t* written) {
+    if (!handle || (count > 0 && (!keys || !key_lens || !written))) {
+        return AMDB_INVALID_ARG;
+    }
+
+    PyObject* db = (PyObject*)handle;
+    for (size_t i = 0; i < count; i++) {
+        PyObject* key_obj = PyBytes_FromStringAndSize((const char*)keys[i], key_lens[i]);
+        if (!key_obj) {
+            return handle_python_error();
+        }
+        PyObject* result = PyObject_CallMethod(db, "has_history", "O", key_obj);
+        Py_DECREF(key_obj);
+        if (!result) {
+            return handle_python_error();
+        }
+        written[i] = PyObject_IsTrue(result) == 1 ? 1 : 0;
+        Py_DECREF(result);
+    }
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_multi_written(amdb_handle_t handle,
+                                 const uint8_t** keys, const size_t* key_lens, size_t count,
+                                 uint8_t* written) {
+    WITH_GIL(multi_written_locked(handle, keys, key_lens, count, written));
+}
+
+static amdb_status_t pin_locked(amdb_handle_t handle, double* timestamp, uint8_t* root_hash) {
+    if (!handle || !timestamp || !root_hash) {
+        return AMDB_INVALID_ARG;
+    }
+
+    PyObject* db = (PyObject*)handle;
+    PyObject* time_module = PyImport_ImportModule("time");
+    if (!time_module) {
+        return handle_python_error();
+    }
+    PyObject* lock = PyObject_GetAttrString(db, "lock");
+    if (!lock) {
+        Py_DECREF(time_module);
+        return handle_python_error();
+    }
+    PyObject* acquired = PyObject_CallMethod(lock, "acquire", NULL);
+    if (!acquired) {
+        Py_DECREF(lock);
+        Py_DECREF(time_module);
+        return handle_python_error();
+    }
+    Py_DECREF(acquired);
+
+    // 持锁读取根哈希和当前时间，并等到时钟越过该时间再释放锁，
+    // 保证之后的写入的版本时间戳都大于固定的时间点
+    amdb_status_t status = AMDB_OK;
+    PyObject* hash_obj = PyObject_CallMethod(db, "get_root_hash", NULL);
+    double pinned = -1.0;
+    if (!hash_obj) {
+        status = handle_python_error();
+    } else {
+        double now = -1.0;
+        do {
+            PyObject* now_obj = PyObject_CallMethod(time_module, "time", NULL);
+            if (!now_obj) {
+                status = handle_python_error();
+                break;
+            }
+            now = PyFloat_AsDouble(now_obj);
+            Py_DECREF(now_obj);
+            if (pinned < 0) {
+                pinned = now;
+            }
+        } while (now <= pinned);
+    }
+
+    PyObject* released = PyObject_CallMethod(lock, "release", NULL);
+    Py_XDECREF(released);
+    Py_DECREF(lock);
+    Py_DECREF(time_module);
+
+    if (status != AMDB_OK) {
+        Py_XDECREF(hash_obj);
+        return status;
+    }
+    if (!PyBytes_Check(hash_obj) || PyBytes_Size(hash_obj) < 32) {
+        Py_DECREF(hash_obj);
+        return AMDB_ERROR;
+    }
+    memcpy(root_hash, PyBytes_AsString(hash_obj), 32);
+    Py_DECREF(hash_obj);
+    *timestamp = pinned;
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_pin(amdb_handle_t handle, double* timestamp, uint8_t* root_hash) {
+    WITH_GIL(pin_locked(handle, timestamp, root_hash));
+}
+
+// 对 key_list 的前 n 个键执行 VersionManager.prune，累计删除的版本数
+static amdb_status_t prune_keys(PyObject* version_manager, PyObject* key_list, Py_ssize_t n,
+                                size_t keep_recent, uint64_t interval,
+                                const double* pinned, size_t pinned_count,
+                                uint64_t* removed) {
+    PyObject* pinned_tuple = PyTuple_New((Py_ssize_t)pinned_count);
+    for (size_t i = 0; pinned_tuple && i < pinned_count; i++) {
+        PyTuple_SET_ITEM(pinned_tuple, (Py_ssize_t)i, PyFloat_FromDouble(pinned[i]));
+    }
+    if (!pinned_tuple) {
+        return handle_python_error();
+    }
+
+    amdb_status_t status = AMDB_OK;
+    uint64_t total = 0;
+    for (Py_ssize_t i = 0; i < n; i++) {
+        PyObject* result = PyObject_CallMethod(version_manager, "prune", "OnKO",
+                                               PyList_GetItem(key_list, i),
+                                               (Py_ssize_t)keep_recent,
+                                               (unsigned long long)interval,
+                                               pinned_tuple);
+        if (!result) {
+            status = handle_python_error();
+            break;
+        }
+        total += PyLong_AsUnsignedLongLong(result);
+        Py_DECREF(result);
+    }
+    Py_DECREF(pinned_tuple);
+
+    if (removed) {
+        *removed = total;
+    }
+    return status;
+}
+
+static amdb_status_t prune_versions_locked(amdb_handle_t handle,
+                                           const uint8_t** keys, const size_t* key_lens, size_t count,
+                                           size_t keep_recent, uint64_t interval,
+                                           const double* pinned, size_t pinned_count,
+                                           uint64_t* removed) {
+    if (!handle || (count > 0 && (!keys || !key_lens)) || (pinned_count > 0 && !pinned)) {
+        return AMDB_INVALID_ARG;
+    }
+
+    PyObject* db = (PyObject*)handle;
+    PyObject* version_manager = PyObject_GetAttrString(db, "version_manager");
+    if (!version_manager) {
+        return handle_python_error();
+    }
+
+    // count 为0时处理全部键
+    PyObject* key_list;
+    if (count > 0) {
+        key_list = PyList_New((Py_ssize_t)count);
+        for (size_t i = 0; key_list && i < count; i++) {
+            PyList_SET_ITEM(key_list, (Py_ssize_t)i,
+                            PyBytes_FromStringAndSize((const char*)keys[i], key_lens[i]));
+        }
+    } else {
+        key_list = PyObject_CallMethod(version_manager, "get_all_keys", NULL);
+    }
+    if (!key_list) {
+        Py_DECREF(version_manager);
+        return handle_python_error();
+    }
+
+    amdb_status_t status = prune_keys(version_manager, key_list, PyList_Size(key_list),
+                                      keep_recent, interval, pinned, pinned_count, removed);
+    Py_DECREF(key_list);
+    Py_DECREF(version_manager);
+    return status;
+}
+
+amdb_status_t amdb_prune_versions(amdb_handle_t handle,
+                                  const uint8_t** keys, const size_t* key_lens, size_t count,
+                                  size_t keep_recent, uint64_t interval,
+                                  const double* pinned, size_t pinned_count,
+                                  uint64_t* removed) {
+    WITH_GIL(prune_versions_locked(handle, keys, key_lens, count, keep_recent, interval,
+                                   pinned, pinned_count, removed));
+}
+
+static amdb_status_t prune_versions_before_locked(amdb_handle_t handle, uint64_t version,
+                                                  const double* pinned, size_t pinned_count,
+                                                  amdb_prune_stats_t* stats) {
+    if (!handle || !stats || (pinned_count > 0 && !pinned)) {
+        return AMDB_INVALID_ARG;
+    }
+    memset(stats, 0, sizeof(*stats));
+
+    PyObject* pinned_tuple = PyTuple_New((Py_ssize_t)pinned_count);
+    for (size_t i = 0; pinned_tuple && i < pinned_count; i++) {
+        PyTuple_SET_ITEM(pinned_tuple, (Py_ssize_t)i, PyFloat_FromDouble(pinned[i]));
+    }
+    if (!pinned_tuple) {
+        return handle_python_error();
+    }
+    PyObject* dict = PyObject_CallMethod((PyObject*)handle, "prune_versions_before", "KO",
+                                         (unsigned long long)version, pinned_tuple);
+    Py_DECREF(pinned_tuple);
+    if (!dict) {
+        return handle_python_error();
+    }
+    if (dict == Py_None) {
+        Py_DECREF(dict);
+        return AMDB_NOT_FOUND;
+    }
+    if (!PyDict_Check(dict)) {
+        Py_DECREF(dict);
+        return AMDB_ERROR;
+    }
+    stats->versions_removed = dict_u64(dict, "versions_removed");
+    stats->keys_pruned = dict_u64(dict, "keys_pruned");
+    stats->bytes_reclaimed = dict_u64(dict, "bytes_reclaimed");
+    PyObject* pinned_obj = PyDict_GetItemString(dict, "pinned");
+    stats->pinned = pinned_obj && PyObject_IsTrue(pinned_obj) == 1;
+    stats->pinned_version = dict_u64(dict, "pinned_version");
+    Py_DECREF(dict);
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_prune_versions_before(amdb_handle_t handle, uint64_t version,
+                                         const double* pinned, size_t pinned_count,
+                                         amdb_prune_stats_t* stats) {
+    WITH_GIL(prune_versions_before_locked(handle, version, pinned, pinned_count, stats));
+}
+
+static amdb_status_t purge_key_history_locked(amdb_handle_t handle,
+                                              const uint8_t* key, size_t key_len,
+                                              amdb_purge_stats_t* stats) {
+    if (!handle || !key || !stats) {
+        return AMDB_INVALID_ARG;
+    }
+    memset(stats, 0, sizeof(*stats));
+
+    PyObject* key_obj = PyBytes_FromStringAndSize((const char*)key, key_len);
+    if (!key_obj) {
+        return handle_python_error();
+    }
+    PyObject* dict = PyObject_CallMethod((PyObject*)handle, "purge_key_history", "O", key_obj);
+    Py_DECREF(key_obj);
+    if (!dict) {
+        return handle_python_error();
+    }
+    if (!PyDict_Check(dict)) {
+        Py_DECREF(dict);
+        return AMDB_ERROR;
+    }
+    stats->versions_removed = dict_u64(dict, "versions_removed");
+    stats->bytes_removed = dict_u64(dict, "bytes_removed");
+    stats->first_version = dict_u64(dict, "first_version");
+    stats->last_version = dict_u64(dict, "last_version");
+    Py_DECREF(dict);
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_purge_key_history(amdb_handle_t handle, const uint8_t* key, size_t key_len,
+                                     amdb_purge_stats_t* stats) {
+    WITH_GIL(purge_key_history_locked(handle, key, key_len, stats));
+}
+
+static amdb_status_t history_values_locked(amdb_handle_t handle,
+                                           const uint8_t* prefix, size_t prefix_len,
+                                           amdb_result_t** results, size_t* result_count) {
+    if (!handle || (prefix_len > 0 && !prefix) || !results || !result_count) {
+        return AMDB_INVALID_ARG;
+    }
+    *results = NULL;
+    *result_count = 0;
+
+    PyObject* prefix_obj = PyBytes_FromStringAndSize((const char*)prefix, (Py_ssize_t)prefix_len);
+    if (!prefix_obj) {
+        return AMDB_MEMORY_ERROR;
+    }
+    PyObject* values = PyObject_CallMethod((PyObject*)handle, "history_values", "O", prefix_obj);
+    Py_DECREF(prefix_obj);
+    if (!values) {
+        return handle_python_error();
+    }
+    if (!PyList_Check(values)) {
+        Py_DECREF(values);
+        return AMDB_ERROR;
+    }
+
+    Py_ssize_t count = PyList_Size(values);
+    if (count == 0) {
+        Py_DECREF(values);
+        return AMDB_OK;
+    }
+    amdb_result_t* out = calloc((size_t)count, sizeof(amdb_result_t));
+    if (!out) {
+        Py_DECREF(values);
+        return AMDB_MEMORY_ERROR;
+    }
+    size_t n = 0;
+    amdb_status_t status = AMDB_OK;
+    for (Py_ssize_t i = 0; i < count && status == AMDB_OK; i++) {
+        PyObject* item = PyList_GetItem(values, i);
+        status = PyBytes_Check(item) ? copy_bytes_to_result(item, &out[n]) : AMDB_ERROR;
+        n++;
+    }
+    Py_DECREF(values);
+
+    if (status != AMDB_OK) {
+        amdb_free_results(out, n);
+        return status;
+    }
+    *results = out;
+    *result_count = n;
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_history_values(amdb_handle_t handle, const uint8_t* prefix, size_t prefix_len,
+                                  amdb_result_t** results, size_t* result_count) {
+    WITH_GIL(history_values_locked(handle, prefix, prefix_len, results, result_count));
+}
+
+static amdb_status_t compact_locked(amdb_handle_t handle, amdb_compact_result_t* result) {
+    if (!handle || !result) {
+        return AMDB_INVALID_ARG;
+    }
+    memset(result, 0, sizeof(*result));
+    PyObject* dict = PyObject_CallMethod((PyObject*)handle, "compact", NULL);
+    if (!dict) {
+        return handle_python_error();
+    }
+    if (!PyDict_Check(dict)) {
+        Py_DECREF(dict);
+        return AMDB_ERROR;
+    }
+    result->bytes_before = dict_u64(dict, "bytes_before");
+    result->bytes_after = dict_u64(dict, "bytes_after");
+    result->merges = dict_u64(dict, "merges");
+    Py_DECREF(dict);
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_compact(amdb_handle_t handle, amdb_compact_result_t* result) {
+    WITH_GIL(compact_locked(handle, result));
+}
+
+// 读取 (数据库版本, 根哈希) 形式的返回值并释放它
+static amdb_status_t copy_version_and_root(PyObject* result, uint64_t* version,
+                                           uint8_t* root_hash) {
+    PyObject* version_obj = PyTuple_Check(result) && PyTuple_Size(result) == 2
+        ? PyTuple_GetItem(result, 0) : NULL;
+    PyObject* hash_obj = version_obj ? PyTuple_GetItem(result, 1) : NULL;
+    if (!hash_obj || !PyBytes_Check(hash_obj)) {
+        Py_DECREF(result);
+        return AMDB_ERROR;
+    }
+    if (version) {
+        *version = PyLong_AsUnsignedLongLong(version_obj);
+    }
+    if (root_hash) {
+        Py_ssize_t hash_len = PyBytes_Size(hash_obj);
+        memset(root_hash, 0, 32);
+        memcpy(root_hash, PyBytes_AsString(hash_obj), hash_len < 32 ? (size_t)hash_len : 32);
+    }
+    Py_DECREF(result);
+    return AMDB_OK;
+}
+
+static amdb_status_t checkpoint_locked(amdb_handle_t handle, const char* path,
+                                       uint64_t* version, uint8_t* root_hash) {
+    if (!handle || !path) {
+        return AMDB_INVALID_ARG;
+    }
+    PyObject* result = PyObject_CallMethod((PyObject*)handle, "checkpoint", "s", path);
+    if (!result) {
+        return handle_python_error();
+    }
+    return copy_version_and_root(result, version, root_hash);
+}
+
+amdb_status_t amdb_checkpoint(amdb_handle_t handle, const char* path,
+                              uint64_t* version, uint8_t* root_hash) {
+    WITH_GIL(checkpoint_locked(handle, path, version, root_hash));
+}
+
+static amdb_status_t fork_locked(amdb_handle_t handle, const char* path, uint64_t version,
+                                 uint64_t* fork_version, uint8_t* root_hash) {
+    if (!handle || !path) {
+        return AMDB_INVALID_ARG;
+    }
+    PyObject* result = PyObject_CallMethod((PyObject*)handle, "fork", "sK", path,
+                                           (unsigned long long)version);
+    if (!result) {
+        return handle_python_error();
+    }
+    if (result == Py_None) {
+        Py_DECREF(result);
+        return AMDB_NOT_FOUND;
+    }
+    return copy_version_and_root(result, fork_version, root_hash);
+}
+
+amdb_status_t amdb_fork(amdb_handle_t handle, const char* path, uint64_t version,
+                        uint64_t* fork_version, uint8_t* root_hash) {
+    WITH_GIL(fork_locked(handle, path, version, fork_version, root_hash));
+}
+
+static amdb_status_t changes_since_locked(amdb_handle_t handle, uint64_t version, double timestamp,
+                                          uint8_t* base_root, uint64_t* version_at,
+                                          amdb_result_t** results, size_t* result_count) {
+    if (!handle || !base_root || !version_at || !results || !result_count) {
+        return AMDB_INVALID_ARG;
+    }
+    *results = NULL;
+    *result_count = 0;
+
+    PyObject* result = PyObject_CallMethod((PyObject*)handle, "changes_since", "Kd",
+                                           (unsigned long long)version, timestamp);
+    if (!result) {
+        return handle_python_error();
+    }
+    if (result == Py_None) {
+        Py_DECREF(result);
+        return AMDB_NOT_FOUND;
+    }
+    PyObject* hash_obj = PyTuple_Check(result) && PyTuple_Size(result) == 3
+        ? PyTuple_GetItem(result, 0) : NULL;
+    PyObject* changes = hash_obj ? PyTuple_GetItem(result, 2) : NULL;
+    if (!hash_obj || !PyBytes_Check(hash_obj) || !PyList_Check(changes)) {
+        Py_DECREF(result);
+        return AMDB_ERROR;
+    }
+    *version_at = PyLong_AsUnsignedLongLong(PyTuple_GetItem(result, 1));
+    Py_ssize_t hash_len = PyBytes_Size(hash_obj);
+    memset(base_root, 0, 32);
+    memcpy(base_root, PyBytes_AsString(hash_obj), hash_len < 32 ? (size_t)hash_len : 32);
+
+    Py_ssize_t count = PyList_Size(changes);
+    if (count == 0) {
+        Py_DECREF(result);
+        return AMDB_OK;
+    }
+    amdb_result_t* out = calloc((size_t)count * 2, sizeof(amdb_result_t));
+    if (!out) {
+        Py_DECREF(result);
+        return AMDB_MEMORY_ERROR;
+    }
+    size_t n = 0;
+    amdb_status_t status = AMDB_OK;
+    for (Py_ssize_t i = 0; i < count && status == AMDB_OK; i++) {
+        PyObject* pair = PyList_GetItem(changes, i);
+        if (!PyTuple_Check(pair) || PyTuple_Size(pair) != 2 ||
+            !PyBytes_Check(PyTuple_GetItem(pair, 0))) {
+            status = AMDB_ERROR;
+            break;
+        }
+        status = copy_bytes_to_result(PyTuple_GetItem(pair, 0), &out[n]);
+        if (status == AMDB_OK) {
+            status = copy_value_to_result(PyTuple_GetItem(pair, 1), &out[n + 1]);
+        }
+        n += 2;
+    }
+    Py_DECREF(result);
+
+    if (status != AMDB_OK) {
+        amdb_free_results(out, n);
+        return status;
+    }
+    *results = out;
+    *result_count = n;
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_changes_since(amdb_handle_t handle, uint64_t version, double timestamp,
+                                 uint8_t* base_root, uint64_t* version_at,
+                                 amdb_result_t** results, size_t* result_count) {
+    WITH_GIL(changes_since_locked(handle, version, timestamp, base_root, version_at,
+                                  results, result_count));
+}
+
+static amdb_status_t commit_changes_locked(amdb_handle_t handle, uint64_t version,
+                                           const uint8_t* prefix, size_t prefix_len,
+                                           uint8_t* root_hash,
+                                           amdb_result_t** results, size_t* result_count) {
+    if (!handle || (prefix_len > 0 && !prefix) || !root_hash || !results || !result_count) {
+        return AMDB_INVALID_ARG;
+    }
+    *results = NULL;
+    *result_count = 0;
+
+    PyObject* prefix_obj = PyBytes_FromStringAndSize((const char*)prefix, (Py_ssize_t)prefix_len);
+    if (!prefix_obj) {
+        return AMDB_MEMORY_ERROR;
+    }
+    PyObject* result = PyObject_CallMethod((PyObject*)handle, "commit_changes", "KO",
+                                           (unsigned long long)version, prefix_obj);
+    Py_DECREF(prefix_obj);
+    if (!result) {
+        return handle_python_error();
+    }
+    if (result == Py_None) {
+        Py_DECREF(result);
+        return AMDB_NOT_FOUND;
+    }
+    PyObject* hash_obj = PyTuple_Check(result) && PyTuple_Size(result) == 2
+        ? PyTuple_GetItem(result, 0) : NULL;
+    PyObject* changes = hash_obj ? PyTuple_GetItem(result, 1) : NULL;
+    if (!hash_obj || !PyBytes_Check(hash_obj) || !PyList_Check(changes)) {
+        Py_DECREF(result);
+        return AMDB_ERROR;
+    }
+    Py_ssize_t hash_len = PyBytes_Size(hash_obj);
+    memset(root_hash, 0, 32);
+    memcpy(root_hash, PyBytes_AsString(hash_obj), hash_len < 32 ? (size_t)hash_len : 32);
+
+    Py_ssize_t count = PyList_Size(changes);
+    if (count == 0) {
+        Py_DECREF(result);
+        return AMDB_OK;
+    }
+    amdb_result_t* out = calloc((size_t)count * 3, sizeof(amdb_result_t));
+    if (!out) {
+        Py_DECREF(result);
+        return AMDB_MEMORY_ERROR;
+    }
+    size_t n = 0;
+    amdb_status_t status = AMDB_OK;
+    for (Py_ssize_t i = 0; i < count && status == AMDB_OK; i++) {
+        PyObject* change = PyList_GetItem(changes, i);
+        if (!PyTuple_Check(change) || PyTuple_Size(change) != 3) {
+            status = AMDB_ERROR;
+            break;
+        }
+        PyObject* key_obj = PyTuple_GetItem(change, 0);
+        status = PyBytes_Check(key_obj) ? copy_bytes_to_result(key_obj, &out[n]) : AMDB_ERROR;
+        for (Py_ssize_t j = 1; j < 3 && status == AMDB_OK; j++) {
+            status = copy_value_to_result(PyTuple_GetItem(change, j), &out[n + j]);
+        }
+        n += 3;
+    }
+    Py_DECREF(result);
+
+    if (status != AMDB_OK) {
+        amdb_free_results(out, n);
+        return status;
+    }
+    *results = out;
+    *result_count = n;
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_commit_changes(amdb_handle_t handle, uint64_t version,
+                                  const uint8_t* prefix, size_t prefix_len,
+                                  uint8_t* root_hash,
+                                  amdb_result_t** results, size_t* result_count) {
+    WITH_GIL(commit_changes_locked(handle, version, prefix, prefix_len, root_hash,
+                                   results, result_count));
+}
+
+static amdb_status_t count_prefixes_locked(amdb_handle_t handle, size_t prefix_len,
+                                           const uint8_t* start_key, size_t start_key_len,
+                                           const uint8_t* end_key, size_t end_key_len,
+                                           uint64_t* count) {
+    if (!handle || (start_key_len > 0 && !start_key) || (end_key_len > 0 && !end_key) || !count) {
+        return AMDB_INVALID_ARG;
+    }
+    PyObject* start_obj = PyBytes_FromStringAndSize((const char*)start_key, (Py_ssize_t)start_key_len);
+    PyObject* end_obj = PyBytes_FromStringAndSize((const char*)end_key, (Py_ssize_t)end_key_len);
+    if (!start_obj || !end_obj) {
+        Py_XDECREF(start_obj);
+        Py_XDECREF(end_obj);
+        return AMDB_MEMORY_ERROR;
+    }
+    PyObject* result = PyObject_CallMethod((PyObject*)handle, "count_prefixes", "nOO",
+                                           (Py_ssize_t)prefix_len, start_obj, end_obj);
+    Py_DECREF(start_obj);
+    Py_DECREF(end_obj);
+    if (!result) {
+        return handle_python_error();
+    }
+    *count = PyLong_AsUnsignedLongLong(result);
+    Py_DECREF(result);
+    if (PyErr_Occurred()) {
+        return handle_python_error();
+    }
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_count_prefixes(amdb_handle_t handle, size_t prefix_len,
+                                  const uint8_t* start_key, size_t start_key_len,
+                                  const uint8_t* end_key, size_t end_key_len,
+                                  uint64_t* count) {
+    WITH_GIL(count_prefixes_locked(handle, prefix_len, start_key, start_key_len,
+                                   end_key, end_key_len, count));
+}
+
+static amdb_status_t prune_batch_locked(amdb_handle_t handle,
+                                        const uint8_t* start_key, size_t start_key_len,
+                                        size_t limit,
+                                        size_t keep_recent, uint64_t interval,
+                                        const double* pinned, size_t pinned_count,
+                                        amdb_result_t* next_key,
+                                        size_t* scanned, uint64_t* removed) {
+    if (!handle || limit == 0 || !next_key || (pinned_count > 0 && !pinned)) {
+        return AMDB_INVALID_ARG;
+    }
+    next_key->data = NULL;
+    next_key->data_len = 0;
+
+    PyObject* db = (PyObject*)handle;
+    PyObject* version_manager = PyObject_GetAttrString(db, "version_manager");
+    if (!version_manager) {
+        return handle_python_error();
+    }
+    PyObject* all_keys = PyObject_CallMethod(version_manager, "get_all_keys", NULL);
+    if (!all_keys) {
+        Py_DECREF(version_manager);
+        return handle_python_error();
+    }
+
+    // 取不小于 start_key 的键，按键序处理前 limit 个
+    PyObject* keys = PyList_New(0);
+    Py_ssize_t total = PyList_Size(all_keys);
+    for (Py_ssize_t i = 0; i < total; i++) {
+        PyObject* key_obj = PyList_GetItem(all_keys, i);
+        if (PyBytes_Check(key_obj) &&
+            (start_key_len == 0 || compare_key(key_obj, start_key, start_key_len) >= 0)) {
+            PyList_Append(keys, key_obj);
+        }
+    }
+    Py_DECREF(all_keys);
+    PyList_Sort(keys);
+
+    Py_ssize_t count = PyList_Size(keys);
+    Py_ssize_t n = count < (Py_ssize_t)limit ? count : (Py_ssize_t)limit;
+    amdb_status_t status = prune_keys(version_manager, keys, n, keep_recent, interval,
+                                      pinned, pinned_count, removed);
+    if (status == AMDB_OK && n < count) {
+        status = copy_bytes_to_result(PyList_GetItem(keys, n), next_key);
+    }
+    if (status == AMDB_OK && scanned) {
+        *scanned = (size_t)n;
+    }
+    Py_DECREF(keys);
+    Py_DECREF(version_manager);
+    return status;
+}
+
+amdb_status_t amdb_prune_batch(amdb_handle_t handle,
+                               const uint8_t* start_key, size_t start_key_len,
+                               size_t limit,
+                               size_t keep_recent, uint64_t interval,
+                               const double* pinned, size_t pinned_count,
+                               amdb_result_t* next_key,
+                               size_t* scanned, uint64_t* removed) {
+    WITH_GIL(prune_batch_locked(handle, start_key, start_key_len, limit, keep_recent, interval,
+                                pinned, pinned_count, next_key, scanned, removed));
+}
+
+static amdb_status_t get_pending_bytes_locked(amdb_handle_t handle, uint64_t* bytes) {
+    if (!handle || !bytes) {
+        return AMDB_INVALID_ARG;
+    }
+
+    PyObject* db = (PyObject*)handle;
+    PyObject* storage = PyObject_GetAttrString(db, "storage");
+    if (!storage) {
+        return handle_python_error();
+    }
+    PyObject* lsm_tree = PyObject_GetAttrString(storage, "lsm_tree");
+    Py_DECREF(storage);
+    if (!lsm_tree) {
+        return handle_python_error();
+    }
+    PyObject* pending = PyObject_CallMethod(lsm_tree, "pending_bytes", NULL);
+    Py_DECREF(lsm_tree);
+    if (!pending) {
+        return handle_python_error();
+    }
+    *bytes = PyLong_AsUnsignedLongLong(pending);
+    Py_DECREF(pending);
+    if (PyErr_Occurred()) {
+        return handle_python_error();
+    }
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_get_pending_bytes(amdb_handle_t handle, uint64_t* bytes) {
+    WITH_GIL(get_pending_bytes_locked(handle, bytes));
+}
+
+// 读取字典中的无符号整数字段，缺失时为0
+static amdb_status_t get_compaction_stats_locked(amdb_handle_t handle,
+                                                 amdb_compaction_stats_t* stats) {
+    if (!handle || !stats) {
+        return AMDB_INVALID_ARG;
+    }
+    memset(stats, 0, sizeof(*stats));
+
+    PyObject* db = (PyObject*)handle;
+    PyObject* storage = PyObject_GetAttrString(db, "storage");
+    if (!storage) {
+        return handle_python_error();
+    }
+    PyObject* lsm_tree = PyObject_GetAttrString(storage, "lsm_tree");
+    Py_DECREF(storage);
+    if (!lsm_tree) {
+        return handle_python_error();
+    }
+    PyObject* dict = PyObject_CallMethod(lsm_tree, "compaction_stats", NULL);
+    Py_DECREF(lsm_tree);
+    if (!dict) {
+        return handle_python_error();
+    }
+    if (!PyDict_Check(dict)) {
+        Py_DECREF(dict);
+        return AMDB_ERROR;
+    }
+
+    stats->pending_bytes = dict_u64(dict, "pending_bytes");
+    stats->bytes_ingested = dict_u64(dict, "bytes_ingested");
+    stats->bytes_flushed = dict_u64(dict, "bytes_flushed");
+    stats->bytes_compacted = dict_u64(dict, "bytes_compacted");
+    stats->compactions = dict_u64(dict, "compactions");
+
+    PyObject* durations = PyDict_GetItemString(dict, "recent_durations");
+    Py_ssize_t n = durations && PyList_Check(durations) ? PyList_Size(durations) : 0;
+    Py_ssize_t skip = n > AMDB_RECENT_COMPACTIONS ? n - AMDB_RECENT_COMPACTIONS : 0;
+    for (Py_ssize_t i = skip; i < n; i++) {
+        stats->recent_compaction_secs[stats->recent_count++] =
+            PyFloat_AsDouble(PyList_GetItem(durations, i));
+    }
+
+    // 文件列表：[(level, name, size), ...]
+    amdb_status_t status = AMDB_OK;
+    PyObject* files = PyDict_GetItemString(dict, "files");
+    Py_ssize_t count = files && PyList_Check(files) ? PyList_Size(files) : 0;
+    if (count > 0) {
+        stats->files = calloc((size_t)count, sizeof(amdb_file_stats_t));
+        if (!stats->files) {
+            status = AMDB_MEMORY_ERROR;
+        }
+    }
+    for (Py_ssize_t i = 0; status == AMDB_OK && i < count; i++) {
+        PyObject* file = PyList_GetItem(files, i);
+        const char* name = NULL;
+        if (PyTuple_Check(file) && PyTuple_Size(file) == 3) {
+            name = PyUnicode_AsUTF8(PyTuple_GetItem(file, 1));
+        }
+        if (!name) {
+            PyErr_Clear();
+            status = AMDB_ERROR;
+            break;
+        }
+        amdb_file_stats_t* out = &stats->files[stats->file_count];
+        out->level = (uint32_t)PyLong_AsUnsignedLong(PyTuple_GetItem(file, 0));
+        out->bytes = PyLong_AsUnsignedLongLong(PyTuple_GetItem(file, 2));
+        out->name = strdup(name);
+        if (!out->name) {
+            status = AMDB_MEMORY_ERROR;
+            break;
+        }
+        stats->file_count++;
+    }
+    Py_DECREF(dict);
+
+    if (status == AMDB_OK && PyErr_Occurred()) {
+        status = handle_python_error();
+    }
+    if (status != AMDB_OK) {
+        amdb_free_compaction_stats(stats);
+    }
+    return status;
+}
+
+amdb_status_t amdb_get_compaction_stats(amdb_handle_t handle, amdb_compaction_stats_t* stats) {
+    WITH_GIL(get_compaction_stats_locked(handle, stats));
+}
+
+static amdb_status_t get_io_stats_locked(amdb_handle_t handle, amdb_io_stats_t* stats) {
+    if (!handle || !stats) {
+        return AMDB_INVALID_ARG;
+    }
+    amdb_io_counters_t total;
+    long len = read_proc_io("/proc/self/io", &total);
+    if (len < 0) {
+        return AMDB_ERROR;
+    }
+    // 本次读取发生在API调用内，由外层计入前台
+    stats->foreground = g_foreground_io;
+    stats->background.bytes_read = total.bytes_read - g_foreground_io.bytes_read - g_probe_io.bytes_read;
+    stats->background.bytes_written = total.bytes_written - g_foreground_io.bytes_written - g_probe_io.bytes_written;
+    stats->background.read_ops = total.read_ops - g_foreground_io.read_ops - g_probe_io.read_ops;
+    stats->background.write_ops = total.write_ops - g_foreground_io.write_ops - g_probe_io.write_ops;
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_get_io_stats(amdb_handle_t handle, amdb_io_stats_t* stats) {
+    WITH_GIL(get_io_stats_locked(handle, stats));
+}
+
+static amdb_status_t get_stats_locked(amdb_handle_t handle, amdb_stats_t* stats) {
+    if (!handle || !stats) {
+        return AMDB_INVALID_ARG;
+    }
+    memset(stats, 0, sizeof(*stats));
+    PyObject* dict = PyObject_CallMethod((PyObject*)handle, "stats", NULL);
+    if (!dict) {
+        return handle_python_error();
+    }
+    if (!PyDict_Check(dict)) {
+        Py_DECREF(dict);
+        return AMDB_ERROR;
+    }
+    stats->key_count = dict_u64(dict, "key_count");
+    stats->state_version = dict_u64(dict, "state_version");
+    stats->merkle_nodes = dict_u64(dict, "merkle_nodes");
+    stats->disk_bytes = dict_u64(dict, "disk_bytes");
+    stats->sstable_count = dict_u64(dict, "sstable_count");
+    stats->pending_bytes = dict_u64(dict, "pending_bytes");
+    stats->bytes_ingested = dict_u64(dict, "bytes_ingested");
+    stats->bytes_flushed = dict_u64(dict, "bytes_flushed");
+    stats->bytes_compacted = dict_u64(dict, "bytes_compacted");
+    stats->compactions = dict_u64(dict, "compactions");
+    stats->cache_hits = dict_u64(dict, "cache_hits");
+    stats->cache_misses = dict_u64(dict, "cache_misses");
+    Py_DECREF(dict);
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_get_stats(amdb_handle_t handle, amdb_stats_t* stats) {
+    WITH_GIL(get_stats_locked(handle, stats));
+}
+
+// {'count', 'sum', 'max', 'buckets'} -> amdb_histogram_t
+static amdb_status_t histogram_from_dict(PyObject* dict, amdb_histogram_t* out) {
+    if (!dict || !PyDict_Check(dict)) {
+        return AMDB_ERROR;
+    }
+    out->count = dict_u64(dict, "count");
+    out->sum = dict_u64(dict, "sum");
+    out->max = dict_u64(dict, "max");
+    PyObject* buckets = PyDict_GetItemString(dict, "buckets");
+    Py_ssize_t n = buckets && PyList_Check(buckets) ? PyList_Size(buckets) : 0;
+    for (Py_ssize_t i = 0; i < n; i++) {
+        size_t bucket = i < AMDB_HISTOGRAM_BUCKETS ? (size_t)i : AMDB_HISTOGRAM_BUCKETS - 1;
+        out->buckets[bucket] += PyLong_AsUnsignedLongLong(PyList_GetItem(buckets, i));
+    }
+    return PyErr_Occurred() ? handle_python_error() : AMDB_OK;
+}
+
+static amdb_status_t get_read_stats_locked(amdb_handle_t handle, amdb_read_stats_t* stats) {
+    if (!handle || !stats) {
+        return AMDB_INVALID_ARG;
+    }
+    memset(stats, 0, sizeof(*stats));
+    PyObject* dict = PyObject_CallMethod((PyObject*)handle, "read_stats", NULL);
+    if (!dict) {
+        return handle_python_error();
+    }
+    amdb_status_t status = PyDict_Check(dict) ? AMDB_OK : AMDB_ERROR;
+    if (status == AMDB_OK) {
+        status = histogram_from_dict(PyDict_GetItemString(dict, "get"), &stats->get);
+    }
+    if (status == AMDB_OK) {
+        status = histogram_from_dict(PyDict_GetItemString(dict, "proof"), &stats->proof);
+    }
+    Py_DECREF(dict);
+    return status;
+}
+
+amdb_status_t amdb_get_read_stats(amdb_handle_t handle, amdb_read_stats_t* stats) {
+    WITH_GIL(get_read_stats_locked(handle, stats));
+}
+
+static amdb_status_t open_report_locked(amdb_handle_t handle, amdb_open_report_t* report,
+                                        amdb_result_t** warnings, size_t* warning_count) {
+    if (!handle || !report || !warnings || !warning_count) {
+        return AMDB_INVALID_ARG;
+    }
+    memset(report, 0, sizeof(*report));
+    *warnings = NULL;
+    *warning_count = 0;
+    PyObject* dict = PyObject_CallMethod((PyObject*)handle, "open_report", NULL);
+    if (!dict) {
+        return handle_python_error();
+    }
+    PyObject* list = PyDict_Check(dict) ? PyDict_GetItemString(dict, "warnings") : NULL;
+    if (!list || !PyList_Check(list)) {
+        Py_DECREF(dict);
+        return AMDB_ERROR;
+    }
+    report->wal_records = dict_u64(dict, "wal_records");
+    report->versions_recovered = dict_u64(dict, "versions_recovered");
+    report->key_versions = dict_u64(dict, "key_versions");
+    report->open_secs = dict_double(dict, "open_secs");
+
+    Py_ssize_t count = PyList_Size(list);
+    if (count == 0) {
+        Py_DECREF(dict);
+        return AMDB_OK;
+    }
+    amdb_result_t* out = calloc((size_t)count, sizeof(amdb_result_t));
+    if (!out) {
+        Py_DECREF(dict);
+        return AMDB_MEMORY_ERROR;
+    }
+    size_t n = 0;
+    amdb_status_t status = AMDB_OK;
+    for (Py_ssize_t i = 0; i < count && status == AMDB_OK; i++) {
+        PyObject* text = PyUnicode_AsUTF8String(PyList_GetItem(list, i));
+        if (!text) {
+            PyErr_Clear();
+            status = AMDB_ERROR;
+            break;
+        }
+        status = copy_bytes_to_result(text, &out[n]);
+        Py_DECREF(text);
+        n++;
+    }
+    Py_DECREF(dict);
+
+    if (status != AMDB_OK) {
+        amdb_free_results(out, n);
+        return status;
+    }
+    *warnings = out;
+    *warning_count = n;
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_open_report(amdb_handle_t handle, amdb_open_report_t* report,
+                               amdb_result_t** warnings, size_t* warning_count) {
+    WITH_GIL(open_report_locked(handle, report, warnings, warning_count));
+}
+
+void amdb_set_background_thread(bool background) {
+    g_background_thread = background;
+}
+
+static amdb_status_t fault_call_locked(const char* method, const char* point,
+                                       const char* action, uint32_t countdown) {
+    PyObject* faults = PyImport_ImportModule(PYTHON_FAULTS_MODULE);
+    if (!faults) {
+        return handle_python_error();
+    }
+    PyObject* result = point
+        ? PyObject_CallMethod(faults, method, "ssI", point, action, (unsigned int)countdown)
+        : PyObject_CallMethod(faults, method, NULL);
+    Py_DECREF(faults);
+    if (!result) {
+        // 未知的故障点或动作
+        if (PyErr_ExceptionMatches(PyExc_ValueError)) {
+            PyErr_Clear();
+            return AMDB_INVALID_ARG;
+        }
+        return handle_python_error();
+    }
+    Py_DECREF(result);
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_fault_inject(const char* point, const char* action, uint32_t countdown) {
+    if (!point || !action) {
+        return AMDB_INVALID_ARG;
+    }
+    if (init_python() != 0) {
+        return AMDB_ERROR;
+    }
+    WITH_GIL(fault_call_locked("inject", point, action, countdown));
+}
+
+amdb_status_t amdb_fault_clear(void) {
+    if (init_python() != 0) {
+        return AMDB_ERROR;
+    }
+    WITH_GIL(fault_call_locked("clear", NULL, NULL, 0));
+}
+
+// 其他函数的简化实现
+
+amdb_status_t amdb_get_history(amdb_handle_t handle,
+                              const uint8_t* key, size_t key_len,
+                              uint32_t start_version, uint32_t end_version,
+                              uint32_t** versions, size_t* version_count) {
+    // TODO: 完整实现
+    *versions = NULL;
+    *version_count = 0;
+    return AMDB_OK;
+}
+
+amdb_status_t amdb_verify(amdb_handle_t handle,
+                         const uint8_t* key, size_t key_len,
+                         const uint8_t* value, size_t value_len,
+                         const uint8_t** proof, size_t proof_count,
                          bool* valid) {
     // TODO: 完整实现
     *valid = false;
@@ -367,3 +3243,35 @@ void amdb_free_results(amdb_result_t* results, size_t count) {
     }
 }
 
+void amdb_free_history(amdb_history_entry_t* entries, size_t count) {
+    if (entries) {
+        for (size_t i = 0; i < count; i++) {
+            amdb_free_result(&entries[i].value);
+        }
+        free(entries);
+    }
+}
+
+void amdb_free_history64(amdb_history_entry64_t* entries, size_t count) {
+    if (entries) {
+        for (size_t i = 0; i < count; i++) {
+            amdb_free_result(&entries[i].value);
+        }
+        free(entries);
+    }
+}
+
+void amdb_free_versions(uint64_t* versions) {
+    free(versions);
+}
+
+void amdb_free_compaction_stats(amdb_compaction_stats_t* stats) {
+    if (stats && stats->files) {
+        for (size_t i = 0; i < stats->file_count; i++) {
+            free(stats->files[i].name);
+        }
+        free(stats->files);
+        stats->files = NULL;
+        stats->file_count = 0;
+    }
+}
@@ -1,6 +1,7 @@
 /**
  * AmDb C API
  * 提供C语言接口，作为其他语言绑定的基础
+ * 各函数在内部获取Python GIL，可从任意线程调用
  */
 
 #ifndef AMDB_H
@@ -21,15 +22,35 @@ typedef enum {
     AMDB_NOT_FOUND = -2,
     AMDB_INVALID_ARG = -3,
     AMDB_IO_ERROR = -4,
-    AMDB_MEMORY_ERROR = -5
+    AMDB_MEMORY_ERROR = -5,
+    AMDB_FATAL = -6,         // 数据损坏或后台任务失败，句柄不应再使用，需重新打开
+    AMDB_BUSY = -7,          // 暂时性错误：引擎暂时无法处理请求，稍后可重试
+    AMDB_TIMED_OUT = -8,     // 暂时性错误：等待引擎内部资源超时，稍后可重试
+    AMDB_READ_ONLY = -9      // 数据库以只读方式打开，拒绝写入
 } amdb_status_t;
 
+// 关闭方式
+typedef enum {
+    AMDB_CLOSE_FLUSH = 0,    // 刷新内存中的数据到磁盘
+    AMDB_CLOSE_SYNC = 1,     // 刷新并fsync数据目录下的文件
+    AMDB_CLOSE_DETACH = 2    // 不刷新，只释放句柄；未刷新的数据依赖WAL恢复
+} amdb_close_mode_t;
+
 // 数据库句柄
 typedef void* amdb_handle_t;
 
 // 事务句柄
 typedef void* amdb_tx_handle_t;
 
+// 流式写入句柄
+typedef void* amdb_put_stream_t;
+
+// 范围游标句柄
+typedef void* amdb_cursor_t;
+
+// 版本快照句柄
+typedef void* amdb_snapshot_t;
+
 // 结果结构
 typedef struct {
     amdb_status_t status;
@@ -38,14 +59,198 @@ typedef struct {
     size_t data_len;
 } amdb_result_t;
 
+// 最近几次压缩的耗时记录数
+#define AMDB_RECENT_COMPACTIONS 8
+
+// 单个数据文件的统计
+typedef struct {
+    uint32_t level;     // 0层为刷新产生的文件，1层为压缩合并产生的文件
+    char* name;         // 文件名（相对LSM目录）
+    uint64_t bytes;
+} amdb_file_stats_t;
+
+// 刷新与压缩统计，字节计数为进程内累计值，重启后清零
+typedef struct {
+    uint64_t pending_bytes;      // 尚未刷新到磁盘的MemTable字节数
+    uint64_t bytes_ingested;     // 写入的键值字节数
+    uint64_t bytes_flushed;      // 刷新写入磁盘的字节数
+    uint64_t bytes_compacted;    // 压缩写入磁盘的字节数
+    uint64_t compactions;
+    double recent_compaction_secs[AMDB_RECENT_COMPACTIONS];  // 由旧到新
+    size_t recent_count;
+    amdb_file_stats_t* files;
+    size_t file_count;
+} amdb_compaction_stats_t;
+
+// I/O计数，取自 /proc 中的 rchar/wchar/syscr/syscw，包含命中页缓存的读写
+typedef struct {
+    uint64_t bytes_read;
+    uint64_t bytes_written;
+    uint64_t read_ops;
+    uint64_t write_ops;
+} amdb_io_counters_t;
+
+// 进程内累计的I/O统计
+typedef struct {
+    amdb_io_counters_t foreground;  // 调用线程在API调用内产生的I/O
+    amdb_io_counters_t background;  // 进程内其余I/O：引擎后台线程、后台标记线程及API调用之外的I/O
+} amdb_io_stats_t;
+
+#define AMDB_HISTOGRAM_BUCKETS 64
+
+// 非负整数观测值的直方图：buckets[i] 为等于i的观测次数，最后一个桶计入不小于 AMDB_HISTOGRAM_BUCKETS-1 的观测值
+typedef struct {
+    uint64_t count;
+    uint64_t sum;
+    uint64_t max;
+    uint64_t buckets[AMDB_HISTOGRAM_BUCKETS];
+} amdb_histogram_t;
+
+// 读放大统计，进程内累计值，重新打开后清零
+typedef struct {
+    amdb_histogram_t get;    // 每次读取依次查找过的数据结构数：版本索引，未命中时再查B+树、MemTable和SSTable
+    amdb_histogram_t proof;  // 每个证明从根到叶子访问的Merkle节点数（含叶子）
+} amdb_read_stats_t;
+
+// 数据库健康统计，见 amdb_get_stats；计数类字段为进程内累计值，重新打开后清零
+typedef struct {
+    uint64_t key_count;        // 有效（未删除）的键数
+    uint64_t state_version;    // 数据库版本，见 amdb_get_state_version
+    uint64_t merkle_nodes;     // 内存中的Merkle节点数
+    uint64_t disk_bytes;       // 数据目录占用的字节数
+    uint64_t sstable_count;    // LSM树的数据文件数
+    uint64_t pending_bytes;    // 尚未刷新到磁盘的MemTable字节数
+    uint64_t bytes_ingested;   // 写入的键值字节数
+    uint64_t bytes_flushed;    // 刷新写入磁盘的字节数
+    uint64_t bytes_compacted;  // 压缩写入磁盘的字节数
+    uint64_t compactions;
+    uint64_t cache_hits;       // B+树节点缓存命中次数
+    uint64_t cache_misses;     // B+树节点缓存未命中（从磁盘加载）次数
+} amdb_stats_t;
+
+// 打开数据库时恢复过程的报告，见 amdb_open_report
+typedef struct {
+    uint64_t wal_records;         // 扫描到的完整WAL记录数；状态以版本记录为准，打开时不重放WAL
+    uint64_t versions_recovered;  // 从磁盘恢复的数据库版本数
+    uint64_t key_versions;        // 恢复的键版本数
+    double open_secs;             // 打开耗时
+} amdb_open_report_t;
+
+// 一次批量提交的统计，见 amdb_batch_put_stats
+typedef struct {
+    uint64_t inserted;       // 提交前不存在的键
+    uint64_t updated;        // 提交前已存在的键
+    uint64_t deleted;        // 删除的已存在的键，删除不存在的键不计入
+    uint64_t bytes_written;  // 写入的键值字节数
+    uint64_t nodes_touched;  // 新产生的Merkle节点数
+    double hash_secs;        // 计算根哈希的耗时
+    double io_secs;          // 写入存储、WAL及同步的耗时
+} amdb_commit_stats_t;
+
+// amdb_prune_versions_before 的结果
+typedef struct {
+    uint64_t versions_removed;  // 删除的版本数
+    uint64_t keys_pruned;       // 删除了版本的键数
+    uint64_t bytes_reclaimed;   // 删除的版本的值字节数，下次持久化时从磁盘释放
+    bool pinned;                // 为true时因固定的时间点而未删除任何版本
+    uint64_t pinned_version;    // pinned 为true时，最早的固定时间点可见的数据库版本
+} amdb_prune_stats_t;
+
+// amdb_purge_key_history 的结果
+typedef struct {
+    uint64_t versions_removed;  // 删除的版本数
+    uint64_t bytes_removed;     // 删除的版本的值字节数
+    uint64_t first_version;     // 状态中含有被删除的值的第一个数据库版本，未删除时为0
+    uint64_t last_version;      // 状态中含有被删除的值的最后一个数据库版本，未删除时为0
+} amdb_purge_stats_t;
+
+// amdb_compact 的结果
+typedef struct {
+    uint64_t bytes_before;  // 合并前数据目录的字节数（已持久化全部数据）
+    uint64_t bytes_after;   // 合并后数据目录的字节数
+    uint64_t merges;        // 合并的次数
+} amdb_compact_result_t;
+
+// 键的一个版本，见 amdb_key_history
+typedef struct {
+    uint32_t key_version;   // 键的版本号（amdb_get 的 version 参数）
+    uint64_t version;       // 写入该版本的数据库版本
+    uint8_t root_hash[32];  // 该数据库版本提交后的根哈希
+    bool deleted;           // 该版本是否为删除
+    amdb_result_t value;    // 值；删除或未请求值时 data 为NULL
+} amdb_history_entry_t;
+
+// 键的一个版本，见 amdb_key_history64；与 amdb_history_entry_t 相同，但键的版本号为64位
+typedef struct {
+    uint64_t key_version;   // 键的版本号（amdb_get64 的 version 参数）
+    uint64_t version;       // 写入该版本的数据库版本
+    uint8_t root_hash[32];  // 该数据库版本提交后的根哈希
+    bool deleted;           // 该版本是否为删除
+    amdb_result_t value;    // 值；删除或未请求值时 data 为NULL
+} amdb_history_entry64_t;
+
+// 值过滤条件的种类，见 amdb_value_filter_t
+typedef enum {
+    AMDB_FILTER_LENGTH = 0,    // 值长度在 [min_len, max_len] 内
+    AMDB_FILTER_PREFIX = 1,    // 值以 bytes 开头
+    AMDB_FILTER_BYTES_AT = 2   // 值从 offset 起的字节等于 bytes
+} amdb_filter_kind_t;
+
+// 一个值过滤条件，未用到的字段忽略
+typedef struct {
+    uint32_t kind;             // amdb_filter_kind_t
+    uint64_t min_len;
+    uint64_t max_len;
+    uint64_t offset;
+    const uint8_t* bytes;
+    size_t bytes_len;
+} amdb_value_filter_t;
+
 /**
  * 初始化数据库
+ * 读写打开时持有数据目录的独占锁（LOCK 文件），直到句柄关闭；已被另一个句柄或进程锁定时返回 AMDB_BUSY
  * @param data_dir 数据目录路径
  * @param handle 输出数据库句柄
  * @return 状态码
  */
 amdb_status_t amdb_init(const char* data_dir, amdb_handle_t* handle);
 
+/**
+ * 以选项初始化数据库；未知的选项名或不合法的值返回 AMDB_INVALID_ARG
+ * 打开选项每次打开可以不同：
+ *   read_only          true 时拒绝写入（返回 AMDB_READ_ONLY），关闭时不刷新；隐含不新建数据目录
+ *   create_if_missing  false 时数据目录不存在则返回 AMDB_NOT_FOUND，默认 true
+ *   cache_size         引擎缓存的字节数，默认取配置文件 [cache] size
+ *   node_cache_size    B+树节点缓存的节点数，默认 1000
+ *   sync               normal（按刷新策略持久化，默认）、commit（每次提交后刷新并fsync）、
+ *                      batched（提交后至多 sync_window_us 内fsync，窗口内的提交共用一次fsync）
+ *   sync_window_us     batched 的fsync窗口（微秒），默认 2000，只能与 sync=batched 一起给出
+ *   compression        true/false，默认取配置文件 [compression] enable
+ *   max_open_files     同时打开的SSTable文件数上限，达到上限时读写等待其他文件关闭；默认取配置文件
+ *                      [database] max_open_files；0（包括显式给出的 0）为不限制
+ *   target_file_size   SSTable的目标字节数：分片存储按该大小分割文件，合并不产生超过该大小的文件；
+ *                      默认取配置文件 [database] max_file_size（256MB）
+ *   lock_wait_ms       数据目录已被锁定时等待释放的毫秒数，超时返回 AMDB_TIMED_OUT；默认 0，立即返回 AMDB_BUSY
+ *   versioning         put（每次 amdb_put/amdb_delete 都是一次提交，默认）、
+ *                      commit（amdb_put/amdb_delete 只改变当前状态，由 amdb_commit 或 amdb_batch_put 提交）
+ * Merkle树创建选项只在新建数据目录时生效并记录下来；重新打开时给出的选项须与记录一致，否则返回 AMDB_INVALID_ARG。
+ * 目前支持的创建选项：
+ *   empty_hash   空子树（分支节点的空位和空树的根）的占位哈希，十六进制，默认为空
+ *   leaf_prefix  叶子节点哈希的前缀，十六进制，默认为 "leaf:"
+ *   node_prefix  扩展和分支节点哈希的前缀，十六进制，默认为空，表示分别使用 "ext:"、"branch:" 标签
+ *   key_framing  叶子节点哈希中键的编码：separator（键后接':'，默认）、none、u32be（4字节大端长度+键）
+ * 前缀最长255字节。
+ * @param data_dir 数据目录路径
+ * @param names 选项名数组
+ * @param values 选项值数组
+ * @param count 选项数量
+ * @param handle 输出数据库句柄
+ * @return 状态码
+ */
+amdb_status_t amdb_init_with_options(const char* data_dir,
+                                     const char* const* names, const char* const* values,
+                                     size_t count, amdb_handle_t* handle);
+
 /**
  * 关闭数据库
  * @param handle 数据库句柄
@@ -53,6 +258,45 @@ amdb_status_t amdb_init(const char* data_dir, amdb_handle_t* handle);
  */
 amdb_status_t amdb_close(amdb_handle_t handle);
 
+/**
+ * 按指定方式关闭数据库；刷新失败时仍释放句柄并返回错误
+ * @param handle 数据库句柄
+ * @param mode 关闭方式
+ * @return 状态码
+ */
+amdb_status_t amdb_close_with(amdb_handle_t handle, amdb_close_mode_t mode);
+
+/**
+ * 刷新全部数据并fsync数据目录下的文件，取消已安排的批量fsync；可与其他线程上的调用并发
+ * @param handle 数据库句柄
+ * @return 状态码
+ */
+amdb_status_t amdb_sync(amdb_handle_t handle);
+
+/**
+ * 只读句柄重新加载写入方（可以在另一进程中）已持久化的状态；读写句柄不做任何事，changed 为0。
+ * 读者看到的状态落后于写入方的提交，落后多少取决于写入方的 sync 选项：commit 为每次提交，
+ * batched 至多 sync_window_us，normal 为写入方的下一次刷新；amdb_get 在读取前自动检查，
+ * 其余读取（根哈希、证明、遍历等）使用最近一次重新加载的状态。写入方正在刷新时保持原来的状态
+ * @param handle 数据库句柄
+ * @param changed 输出是否转到了新的状态（1/0）
+ * @return 状态码
+ */
+amdb_status_t amdb_refresh(amdb_handle_t handle, uint8_t* changed);
+
+/**
+ * 打开（不存在时创建）命名空间：数据目录 namespaces/<name> 下与本库共享生命周期的独立数据库，
+ * 有自己的Merkle树和根哈希，可以用于所有以句柄为参数的函数。命名空间每次提交后，
+ * 其根哈希写入本库的键 "\0ns/" + name，本库的根哈希由此承诺各命名空间的根哈希
+ * 同一个 name 总是得到同一个数据库；返回的句柄用 amdb_close 释放，释放不影响本库
+ * @param handle 数据库句柄
+ * @param name 命名空间名，只能包含字母、数字、'_' 和 '-'（否则返回AMDB_INVALID_ARG）
+ * @param ns_handle 输出命名空间句柄
+ * @return 状态码
+ */
+amdb_status_t amdb_namespace_open(amdb_handle_t handle, const char* name,
+                                  amdb_handle_t* ns_handle);
+
 /**
  * 写入键值对
  * @param handle 数据库句柄
@@ -68,6 +312,46 @@ amdb_status_t amdb_put(amdb_handle_t handle,
                        const uint8_t* value, size_t value_len,
                        uint8_t* root_hash);
 
+/**
+ * 开始流式写入一个值
+ * 之后通过 amdb_put_stream_write 分块追加数据，amdb_put_stream_finish 提交；
+ * 未提交的流必须调用 amdb_put_stream_abort 释放
+ * @param handle 数据库句柄
+ * @param key 键
+ * @param key_len 键长度
+ * @param len_hint 预计的值长度（0表示未知），用于预分配
+ * @param stream 输出流式写入句柄
+ * @return 状态码
+ */
+amdb_status_t amdb_put_stream_begin(amdb_handle_t handle,
+                                    const uint8_t* key, size_t key_len,
+                                    uint64_t len_hint,
+                                    amdb_put_stream_t* stream);
+
+/**
+ * 向流式写入追加数据
+ * @param stream 流式写入句柄
+ * @param data 数据
+ * @param data_len 数据长度
+ * @return 状态码
+ */
+amdb_status_t amdb_put_stream_write(amdb_put_stream_t stream,
+                                    const uint8_t* data, size_t data_len);
+
+/**
+ * 提交流式写入并释放句柄（无论成功与否，句柄都不可再使用）
+ * @param stream 流式写入句柄
+ * @param root_hash 输出Merkle根哈希（32字节）
+ * @return 状态码
+ */
+amdb_status_t amdb_put_stream_finish(amdb_put_stream_t stream, uint8_t* root_hash);
+
+/**
+ * 放弃流式写入并释放句柄
+ * @param stream 流式写入句柄
+ */
+void amdb_put_stream_abort(amdb_put_stream_t stream);
+
 /**
  * 读取键值对
  * @param handle 数据库句柄
@@ -82,6 +366,105 @@ amdb_status_t amdb_get(amdb_handle_t handle,
                        uint32_t version,
                        amdb_result_t* result);
 
+/**
+ * 同 amdb_get，但版本号为64位；键的版本号可能超过 UINT32_MAX 时应使用本函数
+ * @param handle 数据库句柄
+ * @param key 键
+ * @param key_len 键长度
+ * @param version 版本号（0表示最新版本）
+ * @param result 输出结果
+ * @return 状态码
+ */
+amdb_status_t amdb_get64(amdb_handle_t handle,
+                         const uint8_t* key, size_t key_len,
+                         uint64_t version,
+                         amdb_result_t* result);
+
+/**
+ * 分块读取值
+ * 把值从 offset 开始的至多 buf_len 字节复制到调用方缓冲区，用于流式读取大值
+ * @param handle 数据库句柄
+ * @param key 键
+ * @param key_len 键长度
+ * @param version 版本号（0表示最新版本）
+ * @param offset 起始偏移
+ * @param buf 输出缓冲区
+ * @param buf_len 缓冲区长度
+ * @param read_len 输出实际复制的字节数（offset超出值长度时为0）
+ * @param total_len 输出值的总长度
+ * @return 状态码（键不存在时返回AMDB_NOT_FOUND）
+ */
+amdb_status_t amdb_get_chunk(amdb_handle_t handle,
+                             const uint8_t* key, size_t key_len,
+                             uint32_t version,
+                             uint64_t offset,
+                             uint8_t* buf, size_t buf_len,
+                             size_t* read_len, uint64_t* total_len);
+
+/**
+ * 同 amdb_get_chunk，但版本号为64位
+ */
+amdb_status_t amdb_get_chunk64(amdb_handle_t handle,
+                               const uint8_t* key, size_t key_len,
+                               uint64_t version,
+                               uint64_t offset,
+                               uint8_t* buf, size_t buf_len,
+                               size_t* read_len, uint64_t* total_len);
+
+/**
+ * 读取键在 timestamp 时刻的值，即提交时间不晚于 timestamp 的最后一个版本
+ * @param handle 数据库句柄
+ * @param key 键
+ * @param key_len 键长度
+ * @param timestamp Unix时间（秒，可带小数），与版本元数据中的提交时间比较
+ * @param result 输出结果
+ * @return 状态码（该时刻键不存在或已删除时返回AMDB_NOT_FOUND）
+ */
+amdb_status_t amdb_get_at_time(amdb_handle_t handle,
+                               const uint8_t* key, size_t key_len,
+                               double timestamp,
+                               amdb_result_t* result);
+
+/**
+ * 批量读取多个键的最新值
+ * @param handle 数据库句柄
+ * @param keys 键数组
+ * @param key_lens 键长度数组
+ * @param count 键数量
+ * @param results 输出与 keys 一一对应的结果数组（使用 amdb_free_results 释放）；
+ *                不存在或已删除的键 status 为 AMDB_NOT_FOUND、data 为NULL
+ * @return 状态码
+ */
+amdb_status_t amdb_multi_get(amdb_handle_t handle,
+                             const uint8_t** keys, const size_t* key_lens, size_t count,
+                             amdb_result_t** results);
+
+/**
+ * 批量检查多个键是否存在，不返回值
+ * @param handle 数据库句柄
+ * @param keys 键数组
+ * @param key_lens 键长度数组
+ * @param count 键数量
+ * @param exists 输出与 keys 一一对应的结果，存在为1，不存在或已删除为0
+ * @return 状态码
+ */
+amdb_status_t amdb_multi_contains(amdb_handle_t handle,
+                                  const uint8_t** keys, const size_t* key_lens, size_t count,
+                                  uint8_t* exists);
+
+/**
+ * 批量检查多个键是否写入过；与 amdb_multi_contains 不同，已删除的键同样为1
+ * @param handle 数据库句柄
+ * @param keys 键数组
+ * @param key_lens 键长度数组
+ * @param count 键数量
+ * @param written 输出与 keys 一一对应的结果，写入过为1，从未写入为0
+ * @return 状态码
+ */
+amdb_status_t amdb_multi_written(amdb_handle_t handle,
+                                 const uint8_t** keys, const size_t* key_lens, size_t count,
+                                 uint8_t* written);
+
 /**
  * 删除键值对
  * @param handle 数据库句柄
@@ -93,7 +476,8 @@ amdb_status_t amdb_delete(amdb_handle_t handle,
                           const uint8_t* key, size_t key_len);
 
 /**
- * 批量写入
+ * 批量写入：在一次提交中写入全部键值对，只计算一次根哈希（不产生中间根）；
+ * 同一个键出现多次时以最后一项为准；值为NULL表示删除，空值照常写入
  * @param handle 数据库句柄
  * @param keys 键数组
  * @param key_lens 键长度数组
@@ -109,12 +493,59 @@ amdb_status_t amdb_batch_put(amdb_handle_t handle,
                              size_t count,
                              uint8_t* root_hash);
 
+/**
+ * 同 amdb_batch_put，并在 stats 中返回本次提交的统计；统计按提交前的状态计算键是否存在，
+ * 因此比 amdb_batch_put 多读一次每个键
+ */
+amdb_status_t amdb_batch_put_stats(amdb_handle_t handle,
+                                   const uint8_t** keys, const size_t* key_lens,
+                                   const uint8_t** values, const size_t* value_lens,
+                                   size_t count,
+                                   uint8_t* root_hash,
+                                   amdb_commit_stats_t* stats);
+
+/**
+ * 同 amdb_batch_put，并在 version 中返回本次提交后的数据库版本；
+ * 提交与读取版本原子地进行，不会得到并发写入产生的版本
+ */
+amdb_status_t amdb_batch_put_versioned(amdb_handle_t handle,
+                                       const uint8_t** keys, const size_t* key_lens,
+                                       const uint8_t** values, const size_t* value_lens,
+                                       size_t count,
+                                       uint8_t* root_hash,
+                                       uint64_t* version);
+
+/**
+ * 计算以同样参数调用 amdb_batch_put 提交后的根哈希，不写入任何数据；count 为0时返回当前根哈希。
+ * 结果只在此后没有其他写入时与实际提交一致
+ */
+amdb_status_t amdb_batch_root_hash(amdb_handle_t handle,
+                                   const uint8_t** keys, const size_t* key_lens,
+                                   const uint8_t** values, const size_t* value_lens,
+                                   size_t count,
+                                   uint8_t* root_hash);
+
+/**
+ * 同 amdb_batch_root_hash，但在数据库版本 version 的状态之上计算（0表示新数据库的空状态），
+ * 由该版本的状态在内存中重建Merkle树，不写入任何数据
+ * @return 状态码（没有该版本或已被清理时返回AMDB_NOT_FOUND）
+ */
+amdb_status_t amdb_batch_root_hash_at(amdb_handle_t handle, uint64_t version,
+                                      const uint8_t** keys, const size_t* key_lens,
+                                      const uint8_t** values, const size_t* value_lens,
+                                      size_t count,
+                                      uint8_t* root_hash);
+
 /**
  * 范围查询
+ * 返回 [start_key, end_key) 内的最新键值对，按键的字节序升序排列；
+ * end_key_len 为0表示无上界。结果数组中键与值交替排列
+ * （results[2i] 为键，results[2i+1] 为值），result_count 为数组元素总数，
+ * 使用 amdb_free_results 释放。
  * @param handle 数据库句柄
- * @param start_key 起始键
+ * @param start_key 起始键（包含）
  * @param start_key_len 起始键长度
- * @param end_key 结束键
+ * @param end_key 结束键（不包含）
  * @param end_key_len 结束键长度
  * @param results 输出结果数组
  * @param result_count 输出结果数量
@@ -125,6 +556,589 @@ amdb_status_t amdb_range_query(amdb_handle_t handle,
                                const uint8_t* end_key, size_t end_key_len,
                                amdb_result_t** results, size_t* result_count);
 
+/**
+ * 范围查询（包括已删除的键）
+ * 与 amdb_range_query 相同，但同时返回已删除的键，其值的状态为 AMDB_NOT_FOUND、没有数据；
+ * 用于备份等需要完整复现Merkle状态的场景
+ * @param handle 数据库句柄
+ * @param start_key 起始键（包含）
+ * @param start_key_len 起始键长度
+ * @param end_key 结束键（不包含）
+ * @param end_key_len 结束键长度
+ * @param results 输出结果数组
+ * @param result_count 输出结果数量
+ * @return 状态码
+ */
+amdb_status_t amdb_range_query_all(amdb_handle_t handle,
+                                   const uint8_t* start_key, size_t start_key_len,
+                                   const uint8_t* end_key, size_t end_key_len,
+                                   amdb_result_t** results, size_t* result_count);
+
+/**
+ * 固定当前状态
+ * 原子地读取根哈希和一个时间点，之后的写入的版本时间戳都晚于该时间点；
+ * 配合 amdb_range_query_at 可在写入持续进行时读取固定时刻的一致状态
+ * @param handle 数据库句柄
+ * @param timestamp 输出固定的时间点（Unix秒）
+ * @param root_hash 输出该时刻的根哈希（32字节）
+ * @return 状态码
+ */
+amdb_status_t amdb_pin(amdb_handle_t handle, double* timestamp, uint8_t* root_hash);
+
+/**
+ * 按时间点范围查询
+ * 与 amdb_range_query_all 相同，但返回各键在 timestamp 时刻的值
+ * @param handle 数据库句柄
+ * @param start_key 起始键（包含）
+ * @param start_key_len 起始键长度
+ * @param end_key 结束键（不包含）
+ * @param end_key_len 结束键长度
+ * @param timestamp 时间点（Unix秒，通常由 amdb_pin 得到）
+ * @param results 输出结果数组
+ * @param result_count 输出结果数量
+ * @return 状态码
+ */
+amdb_status_t amdb_range_query_at(amdb_handle_t handle,
+                                  const uint8_t* start_key, size_t start_key_len,
+                                  const uint8_t* end_key, size_t end_key_len,
+                                  double timestamp,
+                                  amdb_result_t** results, size_t* result_count);
+
+/**
+ * 分页范围查询
+ * 与 amdb_range_query 相同，但返回 max_entries 项或键值字节数达到 max_bytes 后停止（至少返回一项），
+ * 以 next_key 起继续查询即可读取剩余部分；各页分别读取，不构成一致快照
+ * @param handle 数据库句柄
+ * @param start_key 起始键（包含）
+ * @param start_key_len 起始键长度
+ * @param end_key 结束键（不包含）
+ * @param end_key_len 结束键长度
+ * @param max_entries 每页的项数上限（0表示不限制）
+ * @param max_bytes 每页的键值字节数上限（0表示不限制；两者不能同时为0）
+ * @param results 输出结果数组
+ * @param result_count 输出结果数量
+ * @param next_key 输出下一页的起始键；已读完时 data 为NULL
+ * @return 状态码
+ */
+amdb_status_t amdb_range_query_page(amdb_handle_t handle,
+                                    const uint8_t* start_key, size_t start_key_len,
+                                    const uint8_t* end_key, size_t end_key_len,
+                                    size_t max_entries, size_t max_bytes,
+                                    amdb_result_t** results, size_t* result_count,
+                                    amdb_result_t* next_key);
+
+/**
+ * 带值过滤的范围查询
+ * 与 amdb_range_query 相同，但只返回值满足全部 filters 的键；过滤在引擎侧完成，
+ * 不满足条件的值不会复制给调用方
+ * @param handle 数据库句柄
+ * @param start_key 起始键（包含）
+ * @param start_key_len 起始键长度
+ * @param end_key 结束键（不包含）
+ * @param end_key_len 结束键长度
+ * @param filters 过滤条件数组（filter_count 为0时可为NULL）
+ * @param filter_count 过滤条件数量
+ * @param results 输出结果数组
+ * @param result_count 输出结果数量
+ * @return 状态码
+ */
+amdb_status_t amdb_range_query_filtered(amdb_handle_t handle,
+                                        const uint8_t* start_key, size_t start_key_len,
+                                        const uint8_t* end_key, size_t end_key_len,
+                                        const amdb_value_filter_t* filters, size_t filter_count,
+                                        amdb_result_t** results, size_t* result_count);
+
+/**
+ * 打开范围游标
+ * 打开时确定 [start_key, end_key) 内的键集合，之后写入的新键不会出现；值在读取时获取。
+ * timestamp 不小于0时读取该时刻可见的值（通常由 amdb_pin 得到），整个游标读到同一时刻的状态；
+ * 小于0时读取各键的最新值。游标必须调用 amdb_cursor_close 释放，且须在数据库关闭之前释放
+ * @param handle 数据库句柄
+ * @param start_key 起始键（包含）
+ * @param start_key_len 起始键长度（0表示无下界）
+ * @param end_key 结束键（不包含）
+ * @param end_key_len 结束键长度（0表示无上界）
+ * @param timestamp 读取的时间点，小于0表示最新
+ * @param cursor 输出游标句柄
+ * @return 状态码
+ */
+amdb_status_t amdb_cursor_open(amdb_handle_t handle,
+                               const uint8_t* start_key, size_t start_key_len,
+                               const uint8_t* end_key, size_t end_key_len,
+                               double timestamp,
+                               amdb_cursor_t* cursor);
+
+/**
+ * 从游标的一端读取最多 max_entries 个键值对，跳过已删除的键
+ * 结果数组中键和值交替排列（result_count 为键值对数的两倍）；两端读到同一位置后不再返回结果
+ * @param cursor 游标句柄
+ * @param from_back 为true时从末端按降序读取，否则从前端按升序读取
+ * @param max_entries 键值对数上限（须大于0）
+ * @param results 输出结果数组，用 amdb_free_results 释放
+ * @param result_count 输出结果数量，0表示该方向已读完
+ * @return 状态码
+ */
+amdb_status_t amdb_cursor_next(amdb_cursor_t cursor, bool from_back, size_t max_entries,
+                               amdb_result_t** results, size_t* result_count);
+
+/**
+ * 释放游标
+ * @param cursor 游标句柄
+ * @return 状态码
+ */
+amdb_status_t amdb_cursor_close(amdb_cursor_t cursor);
+
+/**
+ * 提交 versioning=commit 时上次提交之后的 amdb_put/amdb_delete，使数据库版本加1；
+ * 没有这样的写入时（包括 versioning=put 时）不产生新版本
+ * @param handle 数据库句柄
+ * @param version 输出提交后的数据库版本
+ * @param root_hash 输出提交后的根哈希（32字节）
+ * @return 状态码
+ */
+amdb_status_t amdb_commit(amdb_handle_t handle, uint64_t* version, uint8_t* root_hash);
+
+/**
+ * 读取当前的数据库版本
+ * 每次提交后加1（见 amdb_init_with_options 的 versioning），新数据库为0
+ * @param handle 数据库句柄
+ * @param version 输出数据库版本
+ * @return 状态码
+ */
+amdb_status_t amdb_get_state_version(amdb_handle_t handle, uint64_t* version);
+
+/**
+ * 读取某个数据库版本提交后的根哈希
+ * @param handle 数据库句柄
+ * @param version 数据库版本（从1开始）
+ * @param root_hash 输出根哈希（32字节）
+ * @return 状态码（没有该版本时返回AMDB_NOT_FOUND）
+ */
+amdb_status_t amdb_get_commit_root(amdb_handle_t handle, uint64_t version, uint8_t* root_hash);
+
+/**
+ * 读取某个数据库版本的Merkle树右边缘：从根沿最大的非空子节点到最大键所在叶子的路径
+ * 路径编码同 amdb_get_with_proof，但分支节点的每个子节点（包括路径上的）都写出哈希；
+ * 历史版本的节点不全时由该版本的状态重建树。空树时 last_key 与 path 的 data 为NULL，用 amdb_free_result 释放
+ * @param handle 数据库句柄
+ * @param version 数据库版本（0表示新数据库的空状态）
+ * @param last_key 输出最大键
+ * @param leaf_hash 输出最大键所在叶子的哈希（32字节）
+ * @param path 输出路径
+ * @param root_hash 输出该版本的根哈希（32字节，不足32字节时补零）
+ * @return 状态码（没有该版本或已被清理时返回AMDB_NOT_FOUND）
+ */
+amdb_status_t amdb_merkle_frontier(amdb_handle_t handle, uint64_t version,
+                                   amdb_result_t* last_key, uint8_t* leaf_hash,
+                                   amdb_result_t* path, uint8_t* root_hash);
+
+/**
+ * 比较两个数据库版本的Merkle树
+ * 同时遍历两棵树并跳过哈希相同的子树，得到两个版本间值不同的键，按键排序；
+ * 结果中键、from_version 的值、to_version 的值依次排列（result_count 为3的倍数），
+ * 不存在或已删除的值的状态为 AMDB_NOT_FOUND、没有数据，用 amdb_free_results 释放
+ * @param handle 数据库句柄
+ * @param from_version 起始数据库版本（0表示新数据库的空状态）
+ * @param to_version 目标数据库版本，可早于 from_version
+ * @param results 输出结果数组
+ * @param result_count 输出结果数量
+ * @return 状态码（任一版本不存在或已被清理时返回AMDB_NOT_FOUND）
+ */
+amdb_status_t amdb_diff(amdb_handle_t handle, uint64_t from_version, uint64_t to_version,
+                        amdb_result_t** results, size_t* result_count);
+
+/**
+ * 版本区间内变更过的键
+ * 数据库版本 from_version 之后到 to_version（含）之间写入或删除过的以 prefix 开头的键，按键排序；
+ * 写回原值的键同样在内，不读取值。结果只有键，用 amdb_free_results 释放
+ * @param handle 数据库句柄
+ * @param from_version 起始数据库版本（不含，0表示新数据库的空状态）
+ * @param to_version 结束数据库版本（包含），不早于 from_version
+ * @param prefix 键前缀（可为空）
+ * @param prefix_len 前缀长度
+ * @param results 输出键数组
+ * @param result_count 输出键数
+ * @return 状态码（to_version 早于 from_version，或任一版本不存在或已被清理时返回AMDB_NOT_FOUND）
+ */
+amdb_status_t amdb_changed_keys(amdb_handle_t handle, uint64_t from_version, uint64_t to_version,
+                                const uint8_t* prefix, size_t prefix_len,
+                                amdb_result_t** results, size_t* result_count);
+
+/**
+ * 把数据库版本的键空间分为至多 parts 个条目数大致相等的相邻区间，供并行处理分配工作
+ * 按该版本的Merkle树的结构定位分界点（子树的条目数按节点缓存），结果只由该版本的状态决定。
+ * 结果为第2个起各区间的起始键，升序，至多 parts-1 个；各区间依次为 [上一个起始键, 下一个起始键)，
+ * 第一个区间没有下界、最后一个没有上界。条目少于 parts 时区间相应减少。用 amdb_free_results 释放
+ * @param handle 数据库句柄
+ * @param version 数据库版本（0表示新数据库的空状态）
+ * @param parts 区间数，须大于0
+ * @param results 输出起始键数组
+ * @param result_count 输出起始键数
+ * @return 状态码（版本不存在或已被清理时返回AMDB_NOT_FOUND）
+ */
+amdb_status_t amdb_partition_ranges(amdb_handle_t handle, uint64_t version, size_t parts,
+                                    amdb_result_t** results, size_t* result_count);
+
+/**
+ * 读取键的版本号大于 after_version 的各个版本，按版本号升序；已被保留策略删除的版本不在其中
+ * @param handle 数据库句柄
+ * @param key 键
+ * @param key_len 键长度
+ * @param after_version 键的版本号下界（不包含），0表示从第一个版本开始
+ * @param max_entries 版本数上限（须大于0）
+ * @param include_values 为false时不复制值
+ * @param entries 输出版本数组，用 amdb_free_history 释放
+ * @param entry_count 输出版本数量，小于 max_entries 表示已读完
+ * @return 状态码（有版本号超过 UINT32_MAX 的版本时返回AMDB_INVALID_ARG，应改用 amdb_key_history64）
+ */
+amdb_status_t amdb_key_history(amdb_handle_t handle,
+                               const uint8_t* key, size_t key_len,
+                               uint32_t after_version, size_t max_entries,
+                               bool include_values,
+                               amdb_history_entry_t** entries, size_t* entry_count);
+
+/**
+ * 同 amdb_key_history，但版本号为64位
+ * @param entries 输出版本数组，用 amdb_free_history64 释放
+ */
+amdb_status_t amdb_key_history64(amdb_handle_t handle,
+                                 const uint8_t* key, size_t key_len,
+                                 uint64_t after_version, size_t max_entries,
+                                 bool include_values,
+                                 amdb_history_entry64_t** entries, size_t* entry_count);
+
+/**
+ * 读取键在版本号 [start_version, end_version] 内仍保留的版本号，按升序；已被保留策略删除的版本不在其中
+ * @param handle 数据库句柄
+ * @param key 键
+ * @param key_len 键长度
+ * @param start_version 起始版本号（包含）
+ * @param end_version 结束版本号（包含），UINT64_MAX表示到最新版本
+ * @param versions 输出版本号数组，用 amdb_free_versions 释放；没有版本时为NULL
+ * @param version_count 输出版本数量
+ * @return 状态码（end_version 小于 start_version 时返回AMDB_INVALID_ARG）
+ */
+amdb_status_t amdb_key_versions(amdb_handle_t handle,
+                                const uint8_t* key, size_t key_len,
+                                uint64_t start_version, uint64_t end_version,
+                                uint64_t** versions, size_t* version_count);
+
+/**
+ * 打开某个数据库版本的快照
+ * 快照上的读取都返回该版本提交后的状态，不受之后写入的影响；
+ * 已被保留策略删除的版本无法再读到。快照必须调用 amdb_snapshot_close 释放
+ * @param handle 数据库句柄
+ * @param version 数据库版本（从1开始，见 amdb_get_state_version）
+ * @param snapshot 输出快照句柄
+ * @return 状态码（没有该版本时返回AMDB_NOT_FOUND）
+ */
+amdb_status_t amdb_snapshot_open(amdb_handle_t handle, uint64_t version,
+                                 amdb_snapshot_t* snapshot);
+
+/**
+ * 打开根哈希为 root_hash 的最近一个数据库版本的快照，其余同 amdb_snapshot_open
+ * @param handle 数据库句柄
+ * @param root_hash 根哈希（32字节）
+ * @param snapshot 输出快照句柄
+ * @return 状态码（没有提交产生过该根哈希时返回AMDB_NOT_FOUND）
+ */
+amdb_status_t amdb_snapshot_open_at_root(amdb_handle_t handle, const uint8_t* root_hash,
+                                         amdb_snapshot_t* snapshot);
+
+/**
+ * 读取快照对应的版本
+ * @param snapshot 快照句柄
+ * @param version 输出数据库版本
+ * @param timestamp 输出该版本的提交时间（Unix秒），可传给 amdb_get_at_time 等按时间点读取的函数
+ * @param root_hash 输出该版本的根哈希（32字节）
+ * @return 状态码
+ */
+amdb_status_t amdb_snapshot_info(amdb_snapshot_t snapshot, uint64_t* version,
+                                 double* timestamp, uint8_t* root_hash);
+
+/**
+ * 从快照读取键的值
+ * @param snapshot 快照句柄
+ * @param key 键
+ * @param key_len 键长度
+ * @param result 输出结果
+ * @return 状态码（该版本中键不存在或已删除时返回AMDB_NOT_FOUND）
+ */
+amdb_status_t amdb_snapshot_get(amdb_snapshot_t snapshot,
+                                const uint8_t* key, size_t key_len,
+                                amdb_result_t* result);
+
+/**
+ * 在快照上打开范围游标，与以快照的提交时间调用 amdb_cursor_open 相同
+ * @param snapshot 快照句柄
+ * @param start_key 起始键（包含）
+ * @param start_key_len 起始键长度（0表示无下界）
+ * @param end_key 结束键（不包含）
+ * @param end_key_len 结束键长度（0表示无上界）
+ * @param cursor 输出游标句柄，用 amdb_cursor_close 释放，可在快照释放后继续使用
+ * @return 状态码
+ */
+amdb_status_t amdb_snapshot_cursor_open(amdb_snapshot_t snapshot,
+                                        const uint8_t* start_key, size_t start_key_len,
+                                        const uint8_t* end_key, size_t end_key_len,
+                                        amdb_cursor_t* cursor);
+
+/**
+ * 释放快照
+ * @param snapshot 快照句柄
+ * @return 状态码
+ */
+amdb_status_t amdb_snapshot_close(amdb_snapshot_t snapshot);
+
+/**
+ * 按保留策略删除旧版本
+ * 保留每个键最近 keep_recent 个版本（至少保留最新版本）、版本号为 interval 整数倍的版本
+ * （interval 为0时不保留），以及 pinned 中各时间点可见的版本；不改变当前状态和根哈希
+ * @param handle 数据库句柄
+ * @param keys 键数组（count 为0时处理全部键）
+ * @param key_lens 键长度数组
+ * @param count 键数量
+ * @param keep_recent 保留的最近版本数
+ * @param interval 额外保留的版本号间隔
+ * @param pinned 需保留可见版本的时间点数组（通常由 amdb_pin 得到）
+ * @param pinned_count 时间点数量
+ * @param removed 输出删除的版本数（可为NULL）
+ * @return 状态码
+ */
+amdb_status_t amdb_prune_versions(amdb_handle_t handle,
+                                  const uint8_t** keys, const size_t* key_lens, size_t count,
+                                  size_t keep_recent, uint64_t interval,
+                                  const double* pinned, size_t pinned_count,
+                                  uint64_t* removed);
+
+/**
+ * 删除早于数据库版本 version 的状态所需的旧版本：每个键保留 version 提交时可见的版本及之后的版本，
+ * 不改变当前状态和根哈希；之后早于 version 的数据库版本不能再打开快照或读取根哈希（返回AMDB_NOT_FOUND）
+ * pinned 中有早于 version 提交时间的时间点时不删除任何版本，stats->pinned 为true
+ * @param handle 数据库句柄
+ * @param version 数据库版本（从1开始）
+ * @param pinned 仍在使用的时间点数组（通常由 amdb_pin 或快照得到）
+ * @param pinned_count 时间点数量
+ * @param stats 输出结果
+ * @return 状态码（没有该版本时返回AMDB_NOT_FOUND）
+ */
+amdb_status_t amdb_prune_versions_before(amdb_handle_t handle, uint64_t version,
+                                         const double* pinned, size_t pinned_count,
+                                         amdb_prune_stats_t* stats);
+
+/**
+ * 删除键除最新版本外的全部历史版本及其WAL条目（合规删除），不改变当前状态和根哈希；
+ * 先 amdb_delete 再调用即可不留下任何值。已记录的提交根哈希不重算：
+ * first_version 到 last_version 的数据库版本仍承诺被删除的值，这些版本中该键读不到值
+ * @param handle 数据库句柄
+ * @param key 键
+ * @param key_len 键长度
+ * @param stats 输出结果
+ * @return 状态码
+ */
+amdb_status_t amdb_purge_key_history(amdb_handle_t handle, const uint8_t* key, size_t key_len,
+                                     amdb_purge_stats_t* stats);
+
+/**
+ * 读取所有保留的版本（包括各键的最新版本）中以 prefix 开头的不同的值，按字节序；
+ * 供绑定层回收不再被任何版本引用的外部数据。用 amdb_free_results 释放
+ * @param handle 数据库句柄
+ * @param prefix 值前缀（可为空）
+ * @param prefix_len 前缀长度
+ * @param results 输出结果数组
+ * @param result_count 输出结果数量
+ * @return 状态码
+ */
+amdb_status_t amdb_history_values(amdb_handle_t handle, const uint8_t* prefix, size_t prefix_len,
+                                  amdb_result_t** results, size_t* result_count);
+
+/**
+ * 持久化全部数据并合并LSM树的SSTable（分片LSM树只持久化，不合并）
+ * @param handle 数据库句柄
+ * @param result 输出合并前后的磁盘占用
+ * @return 状态码
+ */
+amdb_status_t amdb_compact(amdb_handle_t handle, amdb_compact_result_t* result);
+
+/**
+ * 持久化全部数据并把数据目录复制到 path，得到可用 amdb_init 直接打开的一致副本；复制期间阻塞写入
+ * @param handle 数据库句柄
+ * @param path 副本目录，须不存在或为空目录（否则返回AMDB_INVALID_ARG）
+ * @param version 输出副本的数据库版本（可为NULL）
+ * @param root_hash 输出副本的根哈希（32字节，可为NULL）
+ * @return 状态码
+ */
+amdb_status_t amdb_checkpoint(amdb_handle_t handle, const char* path,
+                              uint64_t* version, uint8_t* root_hash);
+
+/**
+ * 在 path 新建以数据库版本 version 的状态为初始状态的独立数据库
+ * version 为当前版本时与 amdb_checkpoint 相同但不完整复制：SSTable以硬链接共享，
+ * 其余文件在文件系统支持时以写时复制克隆，副本保留全部历史；
+ * 更早的版本以一次提交写入该版本的状态，副本的数据库版本为1，根哈希与该版本相同
+ * @param handle 数据库句柄
+ * @param path 副本目录，须不存在或为空目录（否则返回AMDB_INVALID_ARG）
+ * @param version 数据库版本（没有该版本或已被清理时返回AMDB_NOT_FOUND）
+ * @param fork_version 输出副本的数据库版本（可为NULL）
+ * @param root_hash 输出副本的根哈希（32字节，可为NULL）
+ * @return 状态码
+ */
+amdb_status_t amdb_fork(amdb_handle_t handle, const char* path, uint64_t version,
+                        uint64_t* fork_version, uint8_t* root_hash);
+
+/**
+ * 读取数据库版本 version 之后、timestamp（见 amdb_pin）及之前写入或删除过的键及其在 timestamp 时的值，
+ * 按键排序，删除的键的值状态为 AMDB_NOT_FOUND；格式同 amdb_range_query，用 amdb_free_results 释放
+ * @param handle 数据库句柄
+ * @param version 起始数据库版本（不含），0表示全部
+ * @param timestamp 时间点
+ * @param base_root 输出数据库版本 version 的根哈希（32字节，version 为0时为全零）
+ * @param version_at 输出 timestamp 时的数据库版本
+ * @param results 输出键值对数组
+ * @param result_count 输出数组长度（键值对数的2倍）
+ * @return 状态码（version 大于当前数据库版本时返回AMDB_NOT_FOUND）
+ */
+amdb_status_t amdb_changes_since(amdb_handle_t handle, uint64_t version, double timestamp,
+                                 uint8_t* base_root, uint64_t* version_at,
+                                 amdb_result_t** results, size_t* result_count);
+
+/**
+ * 一次提交的变更
+ * 读取数据库版本 version（从1开始）写入或删除的以 prefix 开头的键，及其提交前后的值，按键排序；
+ * 结果中键、旧值、新值依次排列（result_count 为3的倍数），不存在或已删除的值的状态为 AMDB_NOT_FOUND，
+ * 用 amdb_free_results 释放。没有该版本或已被清理时返回 AMDB_NOT_FOUND
+ * @param handle 数据库句柄
+ * @param version 数据库版本
+ * @param prefix 键前缀（可为空）
+ * @param prefix_len 前缀长度
+ * @param root_hash 输出该版本提交后的根哈希（32字节）
+ * @param results 输出结果数组
+ * @param result_count 输出结果数量
+ * @return 状态码
+ */
+amdb_status_t amdb_commit_changes(amdb_handle_t handle, uint64_t version,
+                                  const uint8_t* prefix, size_t prefix_len,
+                                  uint8_t* root_hash,
+                                  amdb_result_t** results, size_t* result_count);
+
+/**
+ * 统计不同前缀的个数
+ * [start_key, end_key) 内现存的键中不同的 prefix_len 字节前缀的个数，短于 prefix_len 的键不计；
+ * 遍历Merkle树到前缀所在的层即止，不读取值
+ * @param handle 数据库句柄
+ * @param prefix_len 前缀字节数
+ * @param start_key 起始键（包含，长度为0表示不限制）
+ * @param start_key_len 起始键长度
+ * @param end_key 结束键（不包含，长度为0表示不限制）
+ * @param end_key_len 结束键长度
+ * @param count 输出前缀个数
+ * @return 状态码
+ */
+amdb_status_t amdb_count_prefixes(amdb_handle_t handle, size_t prefix_len,
+                                  const uint8_t* start_key, size_t start_key_len,
+                                  const uint8_t* end_key, size_t end_key_len,
+                                  uint64_t* count);
+
+/**
+ * 分批按保留策略删除旧版本
+ * 按键序处理不小于 start_key 的前 limit 个键，规则同 amdb_prune_versions；
+ * 以 next_key 作为下一批的 start_key 即可遍历全部键
+ * @param handle 数据库句柄
+ * @param start_key 起始键（包含，长度为0表示从头开始）
+ * @param start_key_len 起始键长度
+ * @param limit 本批最多处理的键数（大于0）
+ * @param keep_recent 保留的最近版本数
+ * @param interval 额外保留的版本号间隔
+ * @param pinned 需保留可见版本的时间点数组
+ * @param pinned_count 时间点数量
+ * @param next_key 输出下一批的起始键，已处理完全部键时 data 为NULL（用 amdb_free_result 释放）
+ * @param scanned 输出本批处理的键数（可为NULL）
+ * @param removed 输出删除的版本数（可为NULL）
+ * @return 状态码
+ */
+amdb_status_t amdb_prune_batch(amdb_handle_t handle,
+                               const uint8_t* start_key, size_t start_key_len,
+                               size_t limit,
+                               size_t keep_recent, uint64_t interval,
+                               const double* pinned, size_t pinned_count,
+                               amdb_result_t* next_key,
+                               size_t* scanned, uint64_t* removed);
+
+/**
+ * 获取尚未刷新到磁盘的写入占用的内存（MemTable字节数），开销远小于 amdb_get_compaction_stats
+ * @param handle 数据库句柄
+ * @param bytes 输出字节数
+ * @return 状态码
+ */
+amdb_status_t amdb_get_pending_bytes(amdb_handle_t handle, uint64_t* bytes);
+
+/**
+ * 获取刷新与压缩统计
+ * @param handle 数据库句柄
+ * @param stats 输出统计（使用 amdb_free_compaction_stats 释放）
+ * @return 状态码
+ */
+amdb_status_t amdb_get_compaction_stats(amdb_handle_t handle, amdb_compaction_stats_t* stats);
+
+/**
+ * 获取进程内的前台/后台I/O统计（仅Linux，其他平台返回 AMDB_ERROR）
+ * @param handle 数据库句柄
+ * @param stats 输出统计
+ * @return 状态码
+ */
+amdb_status_t amdb_get_io_stats(amdb_handle_t handle, amdb_io_stats_t* stats);
+
+/**
+ * 获取读放大直方图：读取查找的数据结构数和证明访问的Merkle节点数。
+ * 证明的节点数随键的分布变化，原样使用带长公共前缀的键时明显高于哈希后的键
+ * @param handle 数据库句柄
+ * @param stats 输出统计
+ * @return 状态码
+ */
+amdb_status_t amdb_get_read_stats(amdb_handle_t handle, amdb_read_stats_t* stats);
+
+/**
+ * 获取数据库健康统计；需要遍历全部键和数据目录，开销与数据量成正比，适合按分钟级间隔采集
+ * @param handle 数据库句柄
+ * @param stats 输出统计
+ * @return 状态码
+ */
+amdb_status_t amdb_get_stats(amdb_handle_t handle, amdb_stats_t* stats);
+
+/**
+ * 获取打开数据库时恢复过程的报告：恢复的版本数、扫描的WAL记录数、耗时和警告。
+ * 警告为UTF-8文本，说明加载时跳过的内容、WAL末尾不完整的记录、根哈希与最后一次提交不符等问题
+ * @param handle 数据库句柄
+ * @param report 输出报告
+ * @param warnings 输出警告数组（没有警告时为NULL），用 amdb_free_results 释放
+ * @param warning_count 输出警告数
+ * @return 状态码
+ */
+amdb_status_t amdb_open_report(amdb_handle_t handle, amdb_open_report_t* report,
+                               amdb_result_t** warnings, size_t* warning_count);
+
+/**
+ * 标记调用线程为后台线程，此后它发起的API调用计入后台I/O
+ * @param background 是否为后台线程
+ */
+void amdb_set_background_thread(bool background);
+
+/**
+ * 注入故障（仅供测试）：进程内的引擎在故障点 point 第 countdown+1 次经过时触发 action，
+ * 同一故障点只保留最后一次注入。故障点为 "commit"（提交之后、持久化之前）和刷新的各个步骤之前的
+ * "flush.wal"、"flush.lsm"、"flush.merkle"、"flush.versions"、"flush.metadata"；
+ * 动作为 "io_error"（该步骤失败）、"torn_write"（该步骤的文件只写出一半后结束进程）、
+ * "kill"（直接结束进程）。注入的故障结束进程时退出码为86
+ * @return 状态码（未知的故障点或动作返回AMDB_INVALID_ARG）
+ */
+amdb_status_t amdb_fault_inject(const char* point, const char* action, uint32_t countdown);
+
+/**
+ * 撤销全部尚未触发的故障
+ * @return 状态码
+ */
+amdb_status_t amdb_fault_clear(void);
+
 /**
  * 获取版本历史
  * @param handle 数据库句柄
@@ -149,6 +1163,50 @@ amdb_status_t amdb_get_history(amdb_handle_t handle,
  */
 amdb_status_t amdb_get_root_hash(amdb_handle_t handle, uint8_t* root_hash);
 
+/**
+ * 获取键的最新值及其Merkle路径证明
+ * 证明的编码见引擎的 MerkleTree.get_path_proof，可在不打开数据库的情况下对根哈希验证
+ * @param handle 数据库句柄
+ * @param key 键
+ * @param key_len 键长度
+ * @param value 输出值；键不存在或已删除时为空
+ * @param version 输出最新版本号；键不存在时为0
+ * @param proof 输出路径证明；键不在Merkle树中时为空
+ * @param root_hash 输出证明所对应的根哈希（32字节）
+ * @return 状态码（最新版本号超过 UINT32_MAX 时返回AMDB_INVALID_ARG，应改用 amdb_get_with_proof64）
+ */
+amdb_status_t amdb_get_with_proof(amdb_handle_t handle,
+                                  const uint8_t* key, size_t key_len,
+                                  amdb_result_t* value, uint32_t* version,
+                                  amdb_result_t* proof, uint8_t* root_hash);
+
+/**
+ * 同 amdb_get_with_proof，但输出64位的版本号
+ */
+amdb_status_t amdb_get_with_proof64(amdb_handle_t handle,
+                                    const uint8_t* key, size_t key_len,
+                                    amdb_result_t* value, uint64_t* version,
+                                    amdb_result_t* proof, uint8_t* root_hash);
+
+/**
+ * 获取数据目录记录的Merkle树创建选项（见 amdb_init_with_options）
+ * @param handle 数据库句柄
+ * @param name 选项名
+ * @param result 输出选项值（UTF-8字符串，不含结尾的0）；未知选项返回 AMDB_NOT_FOUND
+ * @return 状态码
+ */
+amdb_status_t amdb_get_tree_option(amdb_handle_t handle, const char* name,
+                                   amdb_result_t* result);
+
+/**
+ * 获取引擎后台任务（异步WAL写入、MemTable刷新等）第一次失败的说明
+ * 后台失败不会让当时的调用返回错误；一旦出现就一直保留，直到重新打开数据库
+ * @param handle 数据库句柄
+ * @param result 输出错误说明（UTF-8字符串，不含结尾的0）；没有失败过时返回 AMDB_NOT_FOUND
+ * @return 状态码
+ */
+amdb_status_t amdb_background_error(amdb_handle_t handle, amdb_result_t* result);
+
 /**
  * 验证数据
  * @param handle 数据库句柄
@@ -204,6 +1262,32 @@ void amdb_free_result(amdb_result_t* result);
  */
 void amdb_free_results(amdb_result_t* results, size_t count);
 
+/**
+ * 释放 amdb_key_history 返回的版本数组
+ * @param entries 版本数组
+ * @param count 数量
+ */
+void amdb_free_history(amdb_history_entry_t* entries, size_t count);
+
+/**
+ * 释放 amdb_key_history64 返回的版本数组
+ * @param entries 版本数组
+ * @param count 数量
+ */
+void amdb_free_history64(amdb_history_entry64_t* entries, size_t count);
+
+/**
+ * 释放 amdb_key_versions 返回的版本号数组
+ * @param versions 版本号数组
+ */
+void amdb_free_versions(uint64_t* versions);
+
+/**
+ * 释放压缩统计中的文件列表
+ * @param stats 统计指针
+ */
+void amdb_free_compaction_stats(amdb_compaction_stats_t* stats);
+
 /**
  * 获取错误信息
  * @param status 状态码
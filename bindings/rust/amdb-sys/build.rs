@@ -0,0 +1,133 @@
+//! 链接C库
+//! 默认使用系统安装的 libamdb：先经 pkg-config 查找，找不到时链接 `-lamdb`，可用 `AMDB_LIB_DIR` 指定目录。
+//! `vendored` 特性改为编译 `bindings/c/amdb.c`（可用 `AMDB_SOURCE_DIR` 指定目录）并静态链接，不需要预先安装C库；
+//! C库内嵌Python，编译和链接参数取自 `python3-config`（可用 `PYTHON_CONFIG` 指定）。
+//! `static`/`dynamic` 选择系统库的链接方式，默认动态链接。特性会被依赖图合并，同时开启时按
+//! `vendored`、`static`、`dynamic` 的顺序取第一个，而不是构建失败（`--all-features` 得到 `vendored`）。
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=AMDB_LIB_DIR");
+    println!("cargo:rerun-if-env-changed=PYTHON_CONFIG");
+    println!("cargo:rerun-if-env-changed=AMDB_SOURCE_DIR");
+    if feature("vendored") {
+        build_vendored();
+    } else {
+        link_system(if feature("static") { "static" } else { "dylib" });
+    }
+}
+
+fn feature(name: &str) -> bool {
+    env::var_os(format!("CARGO_FEATURE_{}", name.to_uppercase())).is_some()
+}
+
+/// 运行命令并按空白切分输出；命令不存在或失败时返回 `None`
+fn flags(program: &str, args: &[&str]) -> Option<Vec<String>> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    Some(stdout.split_whitespace().map(str::to_string).collect())
+}
+
+/// 把 `-L`/`-l` 链接参数转为cargo指令，其余参数忽略
+fn emit_link_flags(flags: &[String], kind: &str) {
+    for flag in flags {
+        if let Some(dir) = flag.strip_prefix("-L") {
+            println!("cargo:rustc-link-search=native={}", dir);
+        } else if let Some(lib) = flag.strip_prefix("-l") {
+            if lib == "amdb" {
+                println!("cargo:rustc-link-lib={}=amdb", kind);
+            } else {
+                println!("cargo:rustc-link-lib={}", lib);
+            }
+        }
+    }
+}
+
+fn python_config() -> String {
+    env::var("PYTHON_CONFIG").unwrap_or_else(|_| "python3-config".to_string())
+}
+
+/// C库静态链接时须由最终产物链接Python
+fn link_python() {
+    let config = python_config();
+    let ldflags = flags(&config, &["--ldflags", "--embed"])
+        .or_else(|| flags(&config, &["--ldflags"]))
+        .unwrap_or_else(|| panic!("`{} --ldflags` failed; set PYTHON_CONFIG", config));
+    emit_link_flags(&ldflags, "dylib");
+}
+
+fn link_system(kind: &str) {
+    let mut args = vec!["--libs", "amdb"];
+    if kind == "static" {
+        args.insert(0, "--static");
+    }
+    if let Some(libs) = flags("pkg-config", &args) {
+        emit_link_flags(&libs, kind);
+        return;
+    }
+
+    if let Ok(dir) = env::var("AMDB_LIB_DIR") {
+        println!("cargo:rustc-link-search=native={}", dir);
+    }
+    println!("cargo:rustc-link-lib={}=amdb", kind);
+    if kind == "static" {
+        link_python();
+    }
+}
+
+fn build_vendored() {
+    let source_dir = match env::var_os("AMDB_SOURCE_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("../../c"),
+    };
+    let source = source_dir.join("amdb.c");
+    println!("cargo:rerun-if-changed={}", source.display());
+    println!(
+        "cargo:rerun-if-changed={}",
+        source_dir.join("amdb.h").display()
+    );
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let object = out_dir.join("amdb.o");
+    let config = python_config();
+    let includes = flags(&config, &["--includes"])
+        .unwrap_or_else(|| panic!("`{} --includes` failed; set PYTHON_CONFIG", config));
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    run(Command::new(&compiler)
+        .args(["-c", "-O2", "-fPIC"])
+        .args(&includes)
+        .arg(&source)
+        .arg("-o")
+        .arg(&object));
+    run(
+        Command::new(env::var("AR").unwrap_or_else(|_| "ar".to_string()))
+            .arg("crs")
+            .arg(out_dir.join("libamdb.a"))
+            .arg(&object),
+    );
+
+    println!("cargo:rustc-link-search=native={}", out_dir.display());
+    println!("cargo:rustc-link-lib=static=amdb");
+    link_python();
+    // 供依赖本crate的构建脚本找到头文件（DEP_AMDB_INCLUDE）
+    println!("cargo:include={}", canonical(&source_dir).display());
+}
+
+fn run(command: &mut Command) {
+    let status = command
+        .status()
+        .unwrap_or_else(|e| panic!("failed to run {:?}: {}", command, e));
+    if !status.success() {
+        panic!("{:?} exited with {}", command, status);
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
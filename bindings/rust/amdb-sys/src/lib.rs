@@ -0,0 +1,669 @@
+//! AmDb C API 的原始绑定
+//! `bindings/c/amdb.h` 的常量、类型和函数声明，不含任何安全封装；安全接口见 `amdb` crate。
+//! 链接方式由构建脚本决定，见 `build.rs`；`serde` 特性下 `IoStats` 等统计类型可序列化，由 `amdb` 的 `serde` 特性启用。
+//!
+//! 这里的函数都是 `unsafe` 的，调用方须自行遵守头文件中的约定（缓冲区长度、释放函数等）。
+//! 本crate随C API变化。
+
+use std::os::raw::{c_char, c_int, c_uint, c_void};
+
+pub const AMDB_OK: c_int = 0;
+pub const AMDB_ERROR: c_int = -1;
+pub const AMDB_NOT_FOUND: c_int = -2;
+pub const AMDB_INVALID_ARG: c_int = -3;
+pub const AMDB_IO_ERROR: c_int = -4;
+pub const AMDB_MEMORY_ERROR: c_int = -5;
+/// 数据损坏或后台任务失败，句柄不应再使用
+pub const AMDB_FATAL: c_int = -6;
+/// 暂时性错误，稍后可重试
+pub const AMDB_BUSY: c_int = -7;
+pub const AMDB_TIMED_OUT: c_int = -8;
+pub const AMDB_READ_ONLY: c_int = -9;
+
+/// `amdb_close_with` 的关闭方式
+pub const AMDB_CLOSE_FLUSH: c_int = 0;
+pub const AMDB_CLOSE_SYNC: c_int = 1;
+pub const AMDB_CLOSE_DETACH: c_int = 2;
+
+/// `AmdbValueFilter::kind` 的取值
+pub const AMDB_FILTER_LENGTH: u32 = 0;
+pub const AMDB_FILTER_PREFIX: u32 = 1;
+pub const AMDB_FILTER_BYTES_AT: u32 = 2;
+
+#[repr(C)]
+pub struct AmdbHandle {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct AmdbPutStream {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct AmdbCursor {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct AmdbSnapshot {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct AmdbResult {
+    pub status: c_int,
+    pub error_msg: *const c_char,
+    pub data: *mut c_void,
+    pub data_len: usize,
+}
+
+/// `AmdbCompactionStats::recent_compaction_secs` 的长度
+pub const AMDB_RECENT_COMPACTIONS: usize = 8;
+
+#[repr(C)]
+pub struct AmdbFileStats {
+    pub level: u32,
+    pub name: *mut c_char,
+    pub bytes: u64,
+}
+
+#[repr(C)]
+pub struct AmdbCompactionStats {
+    pub pending_bytes: u64,
+    pub bytes_ingested: u64,
+    pub bytes_flushed: u64,
+    pub bytes_compacted: u64,
+    pub compactions: u64,
+    pub recent_compaction_secs: [f64; AMDB_RECENT_COMPACTIONS],
+    pub recent_count: usize,
+    pub files: *mut AmdbFileStats,
+    pub file_count: usize,
+}
+
+#[repr(C)]
+#[derive(Default)]
+pub struct AmdbCommitStats {
+    pub inserted: u64,
+    pub updated: u64,
+    pub deleted: u64,
+    pub bytes_written: u64,
+    pub nodes_touched: u64,
+    pub hash_secs: f64,
+    pub io_secs: f64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+pub struct AmdbPruneStats {
+    pub versions_removed: u64,
+    pub keys_pruned: u64,
+    pub bytes_reclaimed: u64,
+    pub pinned: bool,
+    pub pinned_version: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+pub struct AmdbPurgeStats {
+    pub versions_removed: u64,
+    pub bytes_removed: u64,
+    pub first_version: u64,
+    pub last_version: u64,
+}
+
+#[repr(C)]
+pub struct AmdbValueFilter {
+    pub kind: u32,
+    pub min_len: u64,
+    pub max_len: u64,
+    pub offset: u64,
+    pub bytes: *const u8,
+    pub bytes_len: usize,
+}
+
+#[repr(C)]
+#[derive(Default)]
+pub struct AmdbCompactResult {
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    pub merges: u64,
+}
+
+#[repr(C)]
+pub struct AmdbHistoryEntry {
+    pub key_version: u32,
+    pub version: u64,
+    pub root_hash: [u8; 32],
+    pub deleted: bool,
+    pub value: AmdbResult,
+}
+
+#[repr(C)]
+pub struct AmdbHistoryEntry64 {
+    pub key_version: u64,
+    pub version: u64,
+    pub root_hash: [u8; 32],
+    pub deleted: bool,
+    pub value: AmdbResult,
+}
+
+/// I/O计数，取自 `/proc` 中的 rchar/wchar/syscr/syscw，包含命中页缓存的读写
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct IoCounters {
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    pub read_ops: u64,
+    pub write_ops: u64,
+}
+
+/// 进程内累计的前台/后台I/O
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct IoStats {
+    /// 调用线程在引擎调用内产生的I/O
+    pub foreground: IoCounters,
+    /// 进程内其余I/O：引擎后台线程（WAL写入、后台清理等），以及应用自身在引擎调用之外的I/O
+    pub background: IoCounters,
+}
+
+pub const AMDB_HISTOGRAM_BUCKETS: usize = 64;
+
+/// 非负整数观测值的直方图，见 `amdb_get_read_stats`
+#[repr(C)]
+pub struct AmdbHistogram {
+    pub count: u64,
+    pub sum: u64,
+    pub max: u64,
+    pub buckets: [u64; AMDB_HISTOGRAM_BUCKETS],
+}
+
+#[repr(C)]
+pub struct AmdbReadStats {
+    pub get: AmdbHistogram,
+    pub proof: AmdbHistogram,
+}
+
+/// 数据库健康统计，见 `amdb_get_stats`
+#[repr(C)]
+#[derive(Default)]
+pub struct AmdbStats {
+    pub key_count: u64,
+    pub state_version: u64,
+    pub merkle_nodes: u64,
+    pub disk_bytes: u64,
+    pub sstable_count: u64,
+    pub pending_bytes: u64,
+    pub bytes_ingested: u64,
+    pub bytes_flushed: u64,
+    pub bytes_compacted: u64,
+    pub compactions: u64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+}
+
+/// 打开数据库时恢复过程的报告，见 `amdb_open_report`
+#[repr(C)]
+#[derive(Default)]
+pub struct AmdbOpenReport {
+    pub wal_records: u64,
+    pub versions_recovered: u64,
+    pub key_versions: u64,
+    pub open_secs: f64,
+}
+
+extern "C" {
+    pub fn amdb_init(data_dir: *const c_char, handle: *mut *mut AmdbHandle) -> c_int;
+    pub fn amdb_init_with_options(
+        data_dir: *const c_char,
+        names: *const *const c_char,
+        values: *const *const c_char,
+        count: usize,
+        handle: *mut *mut AmdbHandle,
+    ) -> c_int;
+    pub fn amdb_close(handle: *mut AmdbHandle) -> c_int;
+    pub fn amdb_close_with(handle: *mut AmdbHandle, mode: c_int) -> c_int;
+    pub fn amdb_sync(handle: *mut AmdbHandle) -> c_int;
+    pub fn amdb_refresh(handle: *mut AmdbHandle, changed: *mut u8) -> c_int;
+    pub fn amdb_namespace_open(
+        handle: *mut AmdbHandle,
+        name: *const c_char,
+        ns_handle: *mut *mut AmdbHandle,
+    ) -> c_int;
+    pub fn amdb_put(
+        handle: *mut AmdbHandle,
+        key: *const u8,
+        key_len: usize,
+        value: *const u8,
+        value_len: usize,
+        root_hash: *mut u8,
+    ) -> c_int;
+    pub fn amdb_get(
+        handle: *mut AmdbHandle,
+        key: *const u8,
+        key_len: usize,
+        version: c_uint,
+        result: *mut AmdbResult,
+    ) -> c_int;
+    pub fn amdb_get64(
+        handle: *mut AmdbHandle,
+        key: *const u8,
+        key_len: usize,
+        version: u64,
+        result: *mut AmdbResult,
+    ) -> c_int;
+    pub fn amdb_multi_get(
+        handle: *mut AmdbHandle,
+        keys: *const *const u8,
+        key_lens: *const usize,
+        count: usize,
+        results: *mut *mut AmdbResult,
+    ) -> c_int;
+    pub fn amdb_multi_contains(
+        handle: *mut AmdbHandle,
+        keys: *const *const u8,
+        key_lens: *const usize,
+        count: usize,
+        exists: *mut u8,
+    ) -> c_int;
+    pub fn amdb_multi_written(
+        handle: *mut AmdbHandle,
+        keys: *const *const u8,
+        key_lens: *const usize,
+        count: usize,
+        written: *mut u8,
+    ) -> c_int;
+    pub fn amdb_put_stream_begin(
+        handle: *mut AmdbHandle,
+        key: *const u8,
+        key_len: usize,
+        len_hint: u64,
+        stream: *mut *mut AmdbPutStream,
+    ) -> c_int;
+    pub fn amdb_put_stream_write(
+        stream: *mut AmdbPutStream,
+        data: *const u8,
+        data_len: usize,
+    ) -> c_int;
+    pub fn amdb_put_stream_finish(stream: *mut AmdbPutStream, root_hash: *mut u8) -> c_int;
+    pub fn amdb_put_stream_abort(stream: *mut AmdbPutStream);
+    pub fn amdb_get_at_time(
+        handle: *mut AmdbHandle,
+        key: *const u8,
+        key_len: usize,
+        timestamp: f64,
+        result: *mut AmdbResult,
+    ) -> c_int;
+    pub fn amdb_get_chunk(
+        handle: *mut AmdbHandle,
+        key: *const u8,
+        key_len: usize,
+        version: c_uint,
+        offset: u64,
+        buf: *mut u8,
+        buf_len: usize,
+        read_len: *mut usize,
+        total_len: *mut u64,
+    ) -> c_int;
+    pub fn amdb_get_chunk64(
+        handle: *mut AmdbHandle,
+        key: *const u8,
+        key_len: usize,
+        version: u64,
+        offset: u64,
+        buf: *mut u8,
+        buf_len: usize,
+        read_len: *mut usize,
+        total_len: *mut u64,
+    ) -> c_int;
+    pub fn amdb_delete(handle: *mut AmdbHandle, key: *const u8, key_len: usize) -> c_int;
+    pub fn amdb_batch_put(
+        handle: *mut AmdbHandle,
+        keys: *const *const u8,
+        key_lens: *const usize,
+        values: *const *const u8,
+        value_lens: *const usize,
+        count: usize,
+        root_hash: *mut u8,
+    ) -> c_int;
+    pub fn amdb_batch_put_stats(
+        handle: *mut AmdbHandle,
+        keys: *const *const u8,
+        key_lens: *const usize,
+        values: *const *const u8,
+        value_lens: *const usize,
+        count: usize,
+        root_hash: *mut u8,
+        stats: *mut AmdbCommitStats,
+    ) -> c_int;
+    pub fn amdb_batch_put_versioned(
+        handle: *mut AmdbHandle,
+        keys: *const *const u8,
+        key_lens: *const usize,
+        values: *const *const u8,
+        value_lens: *const usize,
+        count: usize,
+        root_hash: *mut u8,
+        version: *mut u64,
+    ) -> c_int;
+    pub fn amdb_batch_root_hash(
+        handle: *mut AmdbHandle,
+        keys: *const *const u8,
+        key_lens: *const usize,
+        values: *const *const u8,
+        value_lens: *const usize,
+        count: usize,
+        root_hash: *mut u8,
+    ) -> c_int;
+    pub fn amdb_batch_root_hash_at(
+        handle: *mut AmdbHandle,
+        version: u64,
+        keys: *const *const u8,
+        key_lens: *const usize,
+        values: *const *const u8,
+        value_lens: *const usize,
+        count: usize,
+        root_hash: *mut u8,
+    ) -> c_int;
+    pub fn amdb_range_query(
+        handle: *mut AmdbHandle,
+        start_key: *const u8,
+        start_key_len: usize,
+        end_key: *const u8,
+        end_key_len: usize,
+        results: *mut *mut AmdbResult,
+        result_count: *mut usize,
+    ) -> c_int;
+    pub fn amdb_range_query_all(
+        handle: *mut AmdbHandle,
+        start_key: *const u8,
+        start_key_len: usize,
+        end_key: *const u8,
+        end_key_len: usize,
+        results: *mut *mut AmdbResult,
+        result_count: *mut usize,
+    ) -> c_int;
+    pub fn amdb_range_query_at(
+        handle: *mut AmdbHandle,
+        start_key: *const u8,
+        start_key_len: usize,
+        end_key: *const u8,
+        end_key_len: usize,
+        timestamp: f64,
+        results: *mut *mut AmdbResult,
+        result_count: *mut usize,
+    ) -> c_int;
+    pub fn amdb_pin(handle: *mut AmdbHandle, timestamp: *mut f64, root_hash: *mut u8) -> c_int;
+    pub fn amdb_range_query_page(
+        handle: *mut AmdbHandle,
+        start_key: *const u8,
+        start_key_len: usize,
+        end_key: *const u8,
+        end_key_len: usize,
+        max_entries: usize,
+        max_bytes: usize,
+        results: *mut *mut AmdbResult,
+        result_count: *mut usize,
+        next_key: *mut AmdbResult,
+    ) -> c_int;
+    pub fn amdb_range_query_filtered(
+        handle: *mut AmdbHandle,
+        start_key: *const u8,
+        start_key_len: usize,
+        end_key: *const u8,
+        end_key_len: usize,
+        filters: *const AmdbValueFilter,
+        filter_count: usize,
+        results: *mut *mut AmdbResult,
+        result_count: *mut usize,
+    ) -> c_int;
+    pub fn amdb_cursor_open(
+        handle: *mut AmdbHandle,
+        start_key: *const u8,
+        start_key_len: usize,
+        end_key: *const u8,
+        end_key_len: usize,
+        timestamp: f64,
+        cursor: *mut *mut AmdbCursor,
+    ) -> c_int;
+    pub fn amdb_cursor_next(
+        cursor: *mut AmdbCursor,
+        from_back: bool,
+        max_entries: usize,
+        results: *mut *mut AmdbResult,
+        result_count: *mut usize,
+    ) -> c_int;
+    pub fn amdb_cursor_close(cursor: *mut AmdbCursor) -> c_int;
+    pub fn amdb_commit(handle: *mut AmdbHandle, version: *mut u64, root_hash: *mut u8) -> c_int;
+    pub fn amdb_get_state_version(handle: *mut AmdbHandle, version: *mut u64) -> c_int;
+    pub fn amdb_get_commit_root(handle: *mut AmdbHandle, version: u64, root_hash: *mut u8)
+        -> c_int;
+    pub fn amdb_merkle_frontier(
+        handle: *mut AmdbHandle,
+        version: u64,
+        last_key: *mut AmdbResult,
+        leaf_hash: *mut u8,
+        path: *mut AmdbResult,
+        root_hash: *mut u8,
+    ) -> c_int;
+    pub fn amdb_key_history(
+        handle: *mut AmdbHandle,
+        key: *const u8,
+        key_len: usize,
+        after_version: u32,
+        max_entries: usize,
+        include_values: bool,
+        entries: *mut *mut AmdbHistoryEntry,
+        entry_count: *mut usize,
+    ) -> c_int;
+    pub fn amdb_free_history(entries: *mut AmdbHistoryEntry, count: usize);
+    pub fn amdb_key_history64(
+        handle: *mut AmdbHandle,
+        key: *const u8,
+        key_len: usize,
+        after_version: u64,
+        max_entries: usize,
+        include_values: bool,
+        entries: *mut *mut AmdbHistoryEntry64,
+        entry_count: *mut usize,
+    ) -> c_int;
+    pub fn amdb_free_history64(entries: *mut AmdbHistoryEntry64, count: usize);
+    pub fn amdb_key_versions(
+        handle: *mut AmdbHandle,
+        key: *const u8,
+        key_len: usize,
+        start_version: u64,
+        end_version: u64,
+        versions: *mut *mut u64,
+        version_count: *mut usize,
+    ) -> c_int;
+    pub fn amdb_free_versions(versions: *mut u64);
+    pub fn amdb_snapshot_open(
+        handle: *mut AmdbHandle,
+        version: u64,
+        snapshot: *mut *mut AmdbSnapshot,
+    ) -> c_int;
+    pub fn amdb_snapshot_open_at_root(
+        handle: *mut AmdbHandle,
+        root_hash: *const u8,
+        snapshot: *mut *mut AmdbSnapshot,
+    ) -> c_int;
+    pub fn amdb_snapshot_info(
+        snapshot: *mut AmdbSnapshot,
+        version: *mut u64,
+        timestamp: *mut f64,
+        root_hash: *mut u8,
+    ) -> c_int;
+    pub fn amdb_snapshot_get(
+        snapshot: *mut AmdbSnapshot,
+        key: *const u8,
+        key_len: usize,
+        result: *mut AmdbResult,
+    ) -> c_int;
+    pub fn amdb_snapshot_cursor_open(
+        snapshot: *mut AmdbSnapshot,
+        start_key: *const u8,
+        start_key_len: usize,
+        end_key: *const u8,
+        end_key_len: usize,
+        cursor: *mut *mut AmdbCursor,
+    ) -> c_int;
+    pub fn amdb_snapshot_close(snapshot: *mut AmdbSnapshot) -> c_int;
+    pub fn amdb_prune_versions(
+        handle: *mut AmdbHandle,
+        keys: *const *const u8,
+        key_lens: *const usize,
+        count: usize,
+        keep_recent: usize,
+        interval: u64,
+        pinned: *const f64,
+        pinned_count: usize,
+        removed: *mut u64,
+    ) -> c_int;
+    pub fn amdb_prune_batch(
+        handle: *mut AmdbHandle,
+        start_key: *const u8,
+        start_key_len: usize,
+        limit: usize,
+        keep_recent: usize,
+        interval: u64,
+        pinned: *const f64,
+        pinned_count: usize,
+        next_key: *mut AmdbResult,
+        scanned: *mut usize,
+        removed: *mut u64,
+    ) -> c_int;
+    pub fn amdb_prune_versions_before(
+        handle: *mut AmdbHandle,
+        version: u64,
+        pinned: *const f64,
+        pinned_count: usize,
+        stats: *mut AmdbPruneStats,
+    ) -> c_int;
+    pub fn amdb_purge_key_history(
+        handle: *mut AmdbHandle,
+        key: *const u8,
+        key_len: usize,
+        stats: *mut AmdbPurgeStats,
+    ) -> c_int;
+    pub fn amdb_history_values(
+        handle: *mut AmdbHandle,
+        prefix: *const u8,
+        prefix_len: usize,
+        results: *mut *mut AmdbResult,
+        result_count: *mut usize,
+    ) -> c_int;
+    pub fn amdb_compact(handle: *mut AmdbHandle, result: *mut AmdbCompactResult) -> c_int;
+    pub fn amdb_checkpoint(
+        handle: *mut AmdbHandle,
+        path: *const c_char,
+        version: *mut u64,
+        root_hash: *mut u8,
+    ) -> c_int;
+    pub fn amdb_fork(
+        handle: *mut AmdbHandle,
+        path: *const c_char,
+        version: u64,
+        fork_version: *mut u64,
+        root_hash: *mut u8,
+    ) -> c_int;
+    pub fn amdb_changes_since(
+        handle: *mut AmdbHandle,
+        version: u64,
+        timestamp: f64,
+        base_root: *mut u8,
+        version_at: *mut u64,
+        results: *mut *mut AmdbResult,
+        result_count: *mut usize,
+    ) -> c_int;
+    pub fn amdb_diff(
+        handle: *mut AmdbHandle,
+        from_version: u64,
+        to_version: u64,
+        results: *mut *mut AmdbResult,
+        result_count: *mut usize,
+    ) -> c_int;
+    pub fn amdb_changed_keys(
+        handle: *mut AmdbHandle,
+        from_version: u64,
+        to_version: u64,
+        prefix: *const u8,
+        prefix_len: usize,
+        results: *mut *mut AmdbResult,
+        result_count: *mut usize,
+    ) -> c_int;
+    pub fn amdb_partition_ranges(
+        handle: *mut AmdbHandle,
+        version: u64,
+        parts: usize,
+        results: *mut *mut AmdbResult,
+        result_count: *mut usize,
+    ) -> c_int;
+    pub fn amdb_commit_changes(
+        handle: *mut AmdbHandle,
+        version: u64,
+        prefix: *const u8,
+        prefix_len: usize,
+        root_hash: *mut u8,
+        results: *mut *mut AmdbResult,
+        result_count: *mut usize,
+    ) -> c_int;
+    pub fn amdb_count_prefixes(
+        handle: *mut AmdbHandle,
+        prefix_len: usize,
+        start_key: *const u8,
+        start_key_len: usize,
+        end_key: *const u8,
+        end_key_len: usize,
+        count: *mut u64,
+    ) -> c_int;
+    pub fn amdb_get_pending_bytes(handle: *mut AmdbHandle, bytes: *mut u64) -> c_int;
+    pub fn amdb_get_compaction_stats(
+        handle: *mut AmdbHandle,
+        stats: *mut AmdbCompactionStats,
+    ) -> c_int;
+    pub fn amdb_free_compaction_stats(stats: *mut AmdbCompactionStats);
+    pub fn amdb_get_io_stats(handle: *mut AmdbHandle, stats: *mut IoStats) -> c_int;
+    pub fn amdb_get_read_stats(handle: *mut AmdbHandle, stats: *mut AmdbReadStats) -> c_int;
+    pub fn amdb_get_stats(handle: *mut AmdbHandle, stats: *mut AmdbStats) -> c_int;
+    pub fn amdb_open_report(
+        handle: *mut AmdbHandle,
+        report: *mut AmdbOpenReport,
+        warnings: *mut *mut AmdbResult,
+        warning_count: *mut usize,
+    ) -> c_int;
+    pub fn amdb_set_background_thread(background: bool);
+    pub fn amdb_fault_inject(point: *const c_char, action: *const c_char, countdown: u32) -> c_int;
+    pub fn amdb_fault_clear() -> c_int;
+    pub fn amdb_get_root_hash(handle: *mut AmdbHandle, root_hash: *mut u8) -> c_int;
+    pub fn amdb_get_with_proof(
+        handle: *mut AmdbHandle,
+        key: *const u8,
+        key_len: usize,
+        value: *mut AmdbResult,
+        version: *mut u32,
+        proof: *mut AmdbResult,
+        root_hash: *mut u8,
+    ) -> c_int;
+    pub fn amdb_get_with_proof64(
+        handle: *mut AmdbHandle,
+        key: *const u8,
+        key_len: usize,
+        value: *mut AmdbResult,
+        version: *mut u64,
+        proof: *mut AmdbResult,
+        root_hash: *mut u8,
+    ) -> c_int;
+    pub fn amdb_get_tree_option(
+        handle: *mut AmdbHandle,
+        name: *const c_char,
+        result: *mut AmdbResult,
+    ) -> c_int;
+    pub fn amdb_background_error(handle: *mut AmdbHandle, result: *mut AmdbResult) -> c_int;
+    pub fn amdb_free_result(result: *mut AmdbResult);
+    pub fn amdb_free_results(results: *mut AmdbResult, count: usize);
+    pub fn amdb_error_string(status: c_int) -> *const c_char;
+}
@@ -0,0 +1,275 @@
+/**
+ * AmDb Rust层C接口
+ * 写批次、Merkle证明等Rust层类型，与 amdb.h 的数据库句柄配合使用
+ */
+
+#ifndef AMDB_RS_H
+#define AMDB_RS_H
+
+/* 此文件由 cbindgen 根据 src/capi.rs 生成，请勿手工修改 */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include "amdb.h"
+
+/**
+ * ABI版本，不兼容的变更时加1
+ */
+#define AMDB_RS_ABI_VERSION 1
+
+typedef struct amdb_rs_db_t amdb_rs_db_t;
+
+/**
+ * 导出给C的游标迭代器；借用的 `Database` 须比它活得更久
+ */
+typedef struct amdb_rs_iter_t amdb_rs_iter_t;
+
+typedef struct amdb_rs_batch_t amdb_rs_batch_t;
+
+typedef struct amdb_rs_proof_t amdb_rs_proof_t;
+
+/**
+ * Rust分配的字节串，用 `amdb_rs_bytes_free` 释放
+ */
+typedef struct amdb_rs_bytes_t {
+  uint8_t *data;
+  size_t len;
+} amdb_rs_bytes_t;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * 运行时的ABI版本，绑定可与编译时的 `AMDB_RS_ABI_VERSION` 比较
+ */
+uint32_t amdb_rs_abi_version(void);
+
+/**
+ * 释放Rust分配的字节串并清空；空字节串不做任何事
+ *
+ * # Safety
+ *
+ * `bytes` 必须为空指针，或由本模块的函数填充且未释放。
+ */
+void amdb_rs_bytes_free(amdb_rs_bytes_t *bytes);
+
+/**
+ * 以默认选项打开数据库（见 `Database::new`），用 `amdb_rs_db_close` 关闭
+ *
+ * # Safety
+ *
+ * `data_dir` 须是以NUL结尾的UTF-8路径；`db` 须可写。
+ */
+int amdb_rs_db_open(const char *data_dir, amdb_rs_db_t **db);
+
+/**
+ * 关闭并释放数据库（见 `Database::close`）；即使关闭失败也会释放，空指针不做任何事
+ *
+ * # Safety
+ *
+ * `db` 必须来自 `amdb_rs_db_open` 且未释放；其上的迭代器须已释放。
+ */
+int amdb_rs_db_close(amdb_rs_db_t *db);
+
+/**
+ * 读取键的最新值；键不存在或已删除时返回 `AMDB_NOT_FOUND`
+ *
+ * # Safety
+ *
+ * `db` 必须有效；`key` 须指向 `key_len` 字节的可读内存；`value` 须可写。
+ */
+int amdb_rs_db_get(const amdb_rs_db_t *db,
+                   const uint8_t *key,
+                   size_t key_len,
+                   amdb_rs_bytes_t *value);
+
+/**
+ * 写入键值对，`root_hash` 输出写入后的根哈希（32字节）
+ *
+ * # Safety
+ *
+ * `db` 必须有效；`key`/`value` 须指向对应长度的可读内存；`root_hash` 须可写32字节。
+ */
+int amdb_rs_db_put(const amdb_rs_db_t *db,
+                   const uint8_t *key,
+                   size_t key_len,
+                   const uint8_t *value,
+                   size_t value_len,
+                   uint8_t *root_hash);
+
+/**
+ * 删除键
+ *
+ * # Safety
+ *
+ * `db` 必须有效；`key` 须指向 `key_len` 字节的可读内存。
+ */
+int amdb_rs_db_delete(const amdb_rs_db_t *db, const uint8_t *key, size_t key_len);
+
+/**
+ * 同 `amdb_rs_batch_commit`，提交到 `amdb_rs_db_open` 打开的数据库
+ *
+ * # Safety
+ *
+ * `db` 必须有效；`batch` 必须有效；`root_hash` 须可写32字节。
+ */
+int amdb_rs_db_commit(const amdb_rs_db_t *db, const amdb_rs_batch_t *batch, uint8_t *root_hash);
+
+/**
+ * 同 `amdb_rs_proof_get`，读取 `amdb_rs_db_open` 打开的数据库
+ *
+ * # Safety
+ *
+ * `db` 必须有效；`key` 须指向 `key_len` 字节的可读内存；`proof` 须可写。
+ */
+int amdb_rs_db_proof_get(const amdb_rs_db_t *db,
+                         const uint8_t *key,
+                         size_t key_len,
+                         amdb_rs_proof_t **proof);
+
+/**
+ * 迭代 [start, end) 内的键值对（见 `Database::iter_with`），长度为0的边界表示无界；
+ * `reverse` 为真时按键的降序迭代。用 `amdb_rs_iter_free` 释放
+ *
+ * # Safety
+ *
+ * `db` 必须有效且在迭代器释放前不被关闭；`start`/`end` 须指向对应长度的可读内存；`iter` 须可写。
+ */
+int amdb_rs_iter_new(const amdb_rs_db_t *db,
+                     const uint8_t *start,
+                     size_t start_len,
+                     const uint8_t *end,
+                     size_t end_len,
+                     bool reverse,
+                     amdb_rs_iter_t **iter);
+
+/**
+ * 读取下一个键值对；迭代结束时返回 `AMDB_NOT_FOUND`
+ *
+ * # Safety
+ *
+ * `iter` 必须有效；`key`/`value` 须可写。
+ */
+int amdb_rs_iter_next(amdb_rs_iter_t *iter, amdb_rs_bytes_t *key, amdb_rs_bytes_t *value);
+
+/**
+ * 释放迭代器；空指针不做任何事
+ *
+ * # Safety
+ *
+ * `iter` 必须来自 `amdb_rs_iter_new` 且未释放。
+ */
+void amdb_rs_iter_free(amdb_rs_iter_t *iter);
+
+/**
+ * 创建空批次，用 `amdb_rs_batch_free` 释放
+ */
+amdb_rs_batch_t *amdb_rs_batch_new(void);
+
+/**
+ * 释放批次；空指针不做任何事
+ *
+ * # Safety
+ *
+ * `batch` 必须来自 `amdb_rs_batch_new` 且未释放。
+ */
+void amdb_rs_batch_free(amdb_rs_batch_t *batch);
+
+/**
+ * 向批次追加写入；空值表示删除
+ *
+ * # Safety
+ *
+ * `batch` 必须有效；`key`/`value` 须指向对应长度的可读内存。
+ */
+int amdb_rs_batch_put(amdb_rs_batch_t *batch,
+                      const uint8_t *key,
+                      size_t key_len,
+                      const uint8_t *value,
+                      size_t value_len);
+
+/**
+ * 向批次追加删除
+ *
+ * # Safety
+ *
+ * `batch` 必须有效；`key` 须指向 `key_len` 字节的可读内存。
+ */
+int amdb_rs_batch_delete(amdb_rs_batch_t *batch, const uint8_t *key, size_t key_len);
+
+/**
+ * 批次中的操作数
+ *
+ * # Safety
+ *
+ * `batch` 必须有效。
+ */
+size_t amdb_rs_batch_len(const amdb_rs_batch_t *batch);
+
+/**
+ * 原子地提交批次（见 `Database::write_batch`），`root_hash` 输出提交后的根哈希（32字节）；
+ * 批次保持不变，可继续使用或释放
+ *
+ * # Safety
+ *
+ * `handle` 必须是 `amdb_init` 得到的有效句柄；`batch` 必须有效；`root_hash` 须可写32字节。
+ */
+int amdb_rs_batch_commit(void *handle, const amdb_rs_batch_t *batch, uint8_t *root_hash);
+
+/**
+ * 读取键的最新证明（见 `Database::get_with_proof`），用 `amdb_rs_proof_free` 释放
+ *
+ * # Safety
+ *
+ * `handle` 必须是有效句柄；`key` 须指向 `key_len` 字节的可读内存；`proof` 须可写。
+ */
+int amdb_rs_proof_get(void *handle, const uint8_t *key, size_t key_len, amdb_rs_proof_t **proof);
+
+/**
+ * 解析 `amdb_rs_proof_encode` 的输出，格式不合法时返回 `AMDB_ERROR`
+ *
+ * # Safety
+ *
+ * `data` 须指向 `len` 字节的可读内存；`proof` 须可写。
+ */
+int amdb_rs_proof_decode(const uint8_t *data, size_t len, amdb_rs_proof_t **proof);
+
+/**
+ * 编码证明以便传输；`out_len` 输出所需长度，`out` 为空或 `cap` 不足时只输出长度并返回 `AMDB_INVALID_ARG`
+ *
+ * # Safety
+ *
+ * `proof` 必须有效；`out` 为空或可写 `cap` 字节；`out_len` 须可写。
+ */
+int amdb_rs_proof_encode(const amdb_rs_proof_t *proof, uint8_t *out, size_t cap, size_t *out_len);
+
+/**
+ * 不需要数据库句柄的验证（见 `Proof::verify`）
+ *
+ * # Safety
+ *
+ * `proof` 必须有效；`root_hash` 须可读32字节；`key`/`value` 须指向对应长度的可读内存。
+ */
+bool amdb_rs_proof_verify(const amdb_rs_proof_t *proof,
+                          const uint8_t *root_hash,
+                          const uint8_t *key,
+                          size_t key_len,
+                          const uint8_t *value,
+                          size_t value_len);
+
+/**
+ * 释放证明；空指针不做任何事
+ *
+ * # Safety
+ *
+ * `proof` 必须来自 `amdb_rs_proof_get` 或 `amdb_rs_proof_decode` 且未释放。
+ */
+void amdb_rs_proof_free(amdb_rs_proof_t *proof);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* AMDB_RS_H */
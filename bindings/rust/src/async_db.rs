@@ -0,0 +1,179 @@
+//! 异步接口（`async` 特性）
+//! `AsyncDatabase` 把每次引擎调用放到 tokio 的阻塞线程池（`spawn_blocking`）中执行，不阻塞异步执行器。
+//! 范围迭代在阻塞线程上读取游标，经有界通道以 `Stream` 交给调用方；
+//! 调用方读取慢时游标暂停，丢弃流后游标随之释放。
+
+use std::io;
+use std::ops::RangeBounds;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use tokio::sync::mpsc;
+use tokio::task;
+
+use crate::keys::prefix_successor;
+use crate::{
+    engine_bounds, CursorOptions, Database, Entry, Error, Iter, OpenOptions, Result, Root,
+    WriteBatch,
+};
+
+/// 流与读取游标的阻塞线程之间缓冲的键值对数
+const STREAM_BUFFER: usize = 64;
+
+/// `Database` 的异步包装，可廉价克隆并在任务间共享
+#[derive(Clone)]
+pub struct AsyncDatabase {
+    db: Arc<Database>,
+}
+
+impl AsyncDatabase {
+    pub fn new(db: Database) -> Self {
+        AsyncDatabase { db: Arc::new(db) }
+    }
+
+    pub async fn open(data_dir: &str, options: &OpenOptions) -> Result<Self> {
+        let (data_dir, options) = (data_dir.to_string(), options.clone());
+        let db = blocking(move || options.open(&data_dir)).await?;
+        Ok(Self::new(db))
+    }
+
+    /// 同步接口，用于尚无异步版本的操作；在异步上下文中调用会阻塞当前执行器线程
+    pub fn database(&self) -> &Database {
+        &self.db
+    }
+
+    /// 读取键的最新值，见 `Database::get`
+    pub async fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let key = key.to_vec();
+        self.run(move |db| db.get(&key, None)).await
+    }
+
+    pub async fn put(&self, key: &[u8], value: &[u8]) -> Result<Root> {
+        let (key, value) = (key.to_vec(), value.to_vec());
+        self.run(move |db| db.put(&key, &value)).await
+    }
+
+    pub async fn delete(&self, key: &[u8]) -> Result<()> {
+        let key = key.to_vec();
+        self.run(move |db| db.delete(&key)).await
+    }
+
+    /// 原子地提交批次，见 `Database::write_batch`
+    pub async fn write_batch(&self, batch: WriteBatch) -> Result<Root> {
+        self.run(move |db| db.write_batch(&batch)).await
+    }
+
+    /// 见 `Database::close`；等待其他任务进行中的调用结束
+    pub async fn close(&self) -> Result<()> {
+        self.run(|db| db.close()).await
+    }
+
+    /// 按键的升序迭代 `range` 内的键值对，见 `Database::iter`；须在 tokio 运行时中调用
+    pub fn iter(&self, range: impl RangeBounds<Vec<u8>>) -> EntryStream {
+        self.iter_with(range, &CursorOptions::new())
+    }
+
+    pub fn prefix_iter(&self, prefix: &[u8]) -> EntryStream {
+        self.prefix_iter_with(prefix, &CursorOptions::new())
+    }
+
+    pub fn iter_with(
+        &self,
+        range: impl RangeBounds<Vec<u8>>,
+        options: &CursorOptions,
+    ) -> EntryStream {
+        self.stream(engine_bounds(&range), options)
+    }
+
+    pub fn prefix_iter_with(&self, prefix: &[u8], options: &CursorOptions) -> EntryStream {
+        let bounds = (prefix.to_vec(), prefix_successor(prefix));
+        self.stream(Some(bounds), options)
+    }
+
+    async fn run<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Database) -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let db = self.db.clone();
+        blocking(move || f(&db)).await
+    }
+
+    fn stream(&self, bounds: Option<(Vec<u8>, Vec<u8>)>, options: &CursorOptions) -> EntryStream {
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        let (db, options) = (self.db.clone(), options.clone());
+        task::spawn_blocking(move || {
+            for entry in Iter::new(&db, bounds, &options) {
+                // 接收端已丢弃
+                if tx.blocking_send(entry).is_err() {
+                    break;
+                }
+            }
+        });
+        EntryStream { rx }
+    }
+}
+
+/// 在阻塞线程池中执行 `f`；闭包的panic在调用方重新抛出
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    match task::spawn_blocking(f).await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        // 运行时正在关闭
+        Err(e) => Err(Error::Io(io::Error::new(io::ErrorKind::Interrupted, e))),
+    }
+}
+
+/// 范围迭代的异步流，见 `AsyncDatabase::iter`
+pub struct EntryStream {
+    rx: mpsc::Receiver<Result<Entry>>,
+}
+
+impl Stream for EntryStream {
+    type Item = Result<Entry>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+
+    use super::*;
+
+    async fn next(stream: &mut EntryStream) -> Option<Result<Entry>> {
+        poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    #[tokio::test]
+    async fn test_async_database() {
+        let _ = std::fs::remove_dir_all("./test_data/async");
+        let db = AsyncDatabase::open("./test_data/async", &OpenOptions::new())
+            .await
+            .unwrap();
+        db.put(b"a/1", b"1").await.unwrap();
+        db.put(b"a/2", b"2").await.unwrap();
+        let mut batch = WriteBatch::new();
+        batch.put(b"a/3", b"3").delete(b"a/1");
+        let root = db.write_batch(batch).await.unwrap();
+        assert_eq!(db.database().get_root_hash().unwrap(), root);
+        assert_eq!(db.get(b"a/2").await.unwrap(), Some(b"2".to_vec()));
+        assert!(db.get(b"a/1").await.unwrap().is_none());
+
+        let mut stream = db.prefix_iter(b"a/");
+        let mut keys = Vec::new();
+        while let Some(entry) = next(&mut stream).await {
+            keys.push(entry.unwrap().0);
+        }
+        assert_eq!(keys, vec![b"a/2".to_vec(), b"a/3".to_vec()]);
+
+        // 丢弃未读完的流不影响之后的调用
+        drop(db.iter(..));
+        db.delete(b"a/2").await.unwrap();
+        db.close().await.unwrap();
+        assert!(matches!(db.get(b"a/3").await, Err(Error::Closed)));
+    }
+}
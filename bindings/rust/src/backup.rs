@@ -0,0 +1,905 @@
+//! 备份
+//! 把数据库在某一时间点的状态按键序分段导出到备份目标，中断后再次调用 `create` 会从检查点续传
+//!
+//! 备份开始时固定（pin）当前状态：短暂持有数据库锁，记下时间点与根哈希，之后按该时间点
+//! 读取各键的版本。备份期间写入照常进行，写入的新版本不会进入备份。保留策略
+//! （`OpenOptions::retention`）在备份期间不会删除该时间点可见的版本；但进程重启后
+//! 续传之前的写入可能已清理掉这些版本，此时应清空目标重新备份。
+//!
+//! 备份由目标（[`BackupTarget`]）中的若干对象组成：
+//!
+//! - `segment-NNNNNN.snap`：按键序切分的分段，每段都是完整的快照文件（格式见 `Database::write_snapshot_file`）
+//! - `progress`：未完成备份的检查点，每写完一段更新一次
+//! - `manifest`：备份完成后写入，存在即表示备份完整
+//!
+//! `progress` 与 `manifest` 是每行一个 `字段 值` 的文本文件，便于运维直接查看。
+//! 本地目录使用 [`DirTarget`]；启用 `s3` 特性后可用 `S3Target` 直接写入对象存储。
+//!
+//! 另有两种本地备份：`Database::checkpoint` 复制整个数据目录，得到可直接打开的副本
+//! （`Database::fork_to` 以硬链接和写时复制代替复制，也可以从较早的版本分叉）；
+//! `Database::backup_incremental` 只导出某个数据库版本之后改变的键，格式与上面相同，
+//! 清单中另记基准版本及其根哈希，只能导入到处于基准状态的数据库。
+//! `Database::restore` 从两者之一恢复出数据库，增量备份按生成的顺序依次导入。
+
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::snapshot::{decode_snapshot, encode_snapshot, unix_now};
+use crate::{
+    amdb_changes_since, amdb_checkpoint, amdb_fork, collect_writes, BatchItem, Database, Error, Result,
+    Root, SnapshotInfo, Version,
+};
+
+#[cfg(feature = "s3")]
+mod s3;
+#[cfg(feature = "s3")]
+pub use s3::S3Target;
+
+const FORMAT_LINE: &str = "amdb-backup 1";
+const MANIFEST_FILE: &str = "manifest";
+const PROGRESS_FILE: &str = "progress";
+
+/// 每个分段的目标大小（键、值字节数之和）
+const SEGMENT_BYTES: usize = 64 << 20;
+
+/// 备份的存放位置，按名称读写整个对象
+pub trait BackupTarget {
+    /// 写入对象，已存在时覆盖；写入必须是原子的，读者只能看到旧对象或完整的新对象
+    fn put(&self, name: &str, data: &[u8]) -> Result<()>;
+
+    /// 读取对象，不存在时返回 `None`
+    fn get(&self, name: &str) -> Result<Option<Vec<u8>>>;
+
+    /// 删除对象，不存在时什么也不做
+    fn delete(&self, name: &str) -> Result<()>;
+}
+
+/// 本地目录，每个对象是目录中的一个文件
+pub struct DirTarget {
+    dir: PathBuf,
+}
+
+impl DirTarget {
+    /// 目录不存在时创建
+    pub fn new(dir: impl AsRef<Path>) -> Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(DirTarget {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+}
+
+impl BackupTarget for DirTarget {
+    fn put(&self, name: &str, data: &[u8]) -> Result<()> {
+        // 先写临时文件再改名
+        let path = self.dir.join(name);
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, data)?;
+        fs::File::open(&tmp)?.sync_all()?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.dir.join(name)) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        match fs::remove_file(self.dir.join(name)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 备份清单
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    /// 固定时间点数据库的根哈希
+    pub root_hash: Root,
+    /// 备份固定的时间点（Unix秒，与版本时间戳同一时钟），备份内容是该时刻的状态
+    pub pinned_at: f64,
+    /// 开始备份的时间（Unix秒）
+    pub created_at: u64,
+    pub segments: u32,
+    pub entry_count: u64,
+    /// 增量备份的基准；完整备份为 `None`
+    pub base: Option<IncrementalBase>,
+}
+
+/// 增量备份的基准，见 `Database::backup_incremental`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IncrementalBase {
+    /// 备份包含该数据库版本之后的改变；0表示全部
+    pub since_version: Version,
+    /// 数据库版本 `since_version` 的根哈希，导入的目标须处于该状态；`since_version` 为0时全零
+    pub base_root: Root,
+    /// 备份固定的状态的数据库版本，可作为下一次增量备份的 `since_version`
+    pub version: Version,
+}
+
+/// `Database::checkpoint` 或 `Database::fork_to` 得到的副本
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub version: Version,
+    pub root_hash: Root,
+}
+
+/// 恢复演练的结果：将要导入的内容，不写入任何数据
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestorePlan {
+    pub manifest: Manifest,
+    pub segments: Vec<SegmentPlan>,
+    /// 导入的键、值字节数之和，即目标数据库至少需要的新增空间
+    pub required_bytes: u64,
+    /// 目标数据库中已存在、将被覆盖的键数
+    pub overwritten_keys: u64,
+}
+
+/// 一个分段将导入的内容
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentPlan {
+    pub entry_count: u64,
+    pub bytes: u64,
+    pub first_key: Vec<u8>,
+    pub last_key: Vec<u8>,
+}
+
+/// 恢复进度，每导入一个分段报告一次
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestoreProgress {
+    pub segments_done: u32,
+    pub segments_total: u32,
+    pub entries_done: u64,
+    pub entries_total: u64,
+    pub bytes_done: u64,
+}
+
+/// 未完成备份的检查点：已写完的分段及其中最后一个键
+struct Progress {
+    manifest: Manifest,
+    last_key: Option<Vec<u8>>,
+}
+
+impl Database {
+    /// 持久化全部写入并把数据目录复制到 `path`，得到可以直接打开的一致副本；复制期间阻塞写入。
+    /// `path` 须不存在或为空目录，否则返回 `Error::InvalidArgument`
+    pub fn checkpoint(&self, path: impl AsRef<Path>) -> Result<Checkpoint> {
+        let path = c_path(path.as_ref())?;
+        let mut checkpoint = Checkpoint {
+            version: Version(0),
+            root_hash: Root::default(),
+        };
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            amdb_checkpoint(
+                *handle,
+                path.as_ptr(),
+                &mut checkpoint.version.0,
+                checkpoint.root_hash.as_mut_ptr(),
+            )
+        });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        Ok(checkpoint)
+    }
+
+    /// 在 `path` 新建以数据库版本 `version` 的状态为初始状态的独立数据库，之后两者互不影响。
+    /// `version` 为当前版本时克隆数据目录并保留全部历史：SSTable以硬链接共享，其余文件在文件系统支持时
+    /// 以写时复制（reflink）克隆，否则复制，克隆期间阻塞写入；更早的版本由其状态以一次提交重建，
+    /// 副本的数据库版本为1，命名空间不随之分叉。两种情况下副本的根哈希都与该版本相同。
+    /// `path` 须不存在或为空目录，否则返回 `Error::InvalidArgument`；没有该版本或已被清理时返回 `Error::NotFound`
+    pub fn fork_to(&self, path: impl AsRef<Path>, version: Version) -> Result<Checkpoint> {
+        let c_path = c_path(path.as_ref())?;
+        let mut fork = Checkpoint {
+            version: Version(0),
+            root_hash: Root::default(),
+        };
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            amdb_fork(
+                *handle,
+                c_path.as_ptr(),
+                version.0,
+                &mut fork.version.0,
+                fork.root_hash.as_mut_ptr(),
+            )
+        });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        // 克隆的数据目录已含大值文件，重建的副本中的引用指向本库的文件
+        if let Some(blobs) = &self.blobs {
+            if !path.as_ref().join("blobs").exists() {
+                blobs.link_into(path.as_ref())?;
+            }
+        }
+        Ok(fork)
+    }
+
+    /// 把数据库版本 `since_version` 之后写入或删除过的键的当前值备份到目录 `path`，返回清单；
+    /// 清单的 `base.version` 可作为下一次增量备份的 `since_version`。
+    /// 备份期间不阻塞写入；中断后不续传，再次调用重新开始。目录中已有完整的备份时直接返回其清单。
+    /// `since_version` 大于当前数据库版本时返回 `Error::NotFound`
+    pub fn backup_incremental(
+        &self,
+        path: impl AsRef<Path>,
+        since_version: Version,
+    ) -> Result<Manifest> {
+        let target = DirTarget::new(path)?;
+        if target.get(MANIFEST_FILE)?.is_some() {
+            return read_manifest(&target);
+        }
+        target.delete(PROGRESS_FILE)?;
+
+        let (pin, root_hash) = self.pin_retained()?;
+        let (base, entries) = self.changes_since(since_version, pin.pinned_at)?;
+        let mut progress = Progress {
+            manifest: Manifest {
+                root_hash,
+                pinned_at: pin.pinned_at,
+                created_at: unix_now(),
+                segments: 0,
+                entry_count: 0,
+                base: Some(base),
+            },
+            last_key: None,
+        };
+        write_segments(&target, &mut progress, &entries, SEGMENT_BYTES, None)?;
+        finish(&target, progress)
+    }
+
+    /// 从 `path` 恢复到数据目录 `target_dir` 并打开：`path` 为检查点时复制到 `target_dir`
+    /// （须不存在或为空目录）；为备份时导入到 `target_dir` 中的数据库（不存在时创建），
+    /// 增量备份要求其处于基准状态，否则返回 `Error::RootMismatch`
+    pub fn restore(path: impl AsRef<Path>, target_dir: impl AsRef<Path>) -> Result<Database> {
+        let (path, target_dir) = (path.as_ref(), target_dir.as_ref());
+        let data_dir = target_dir
+            .to_str()
+            .ok_or_else(|| Error::InvalidArgument(format!("path {:?} is not UTF-8", target_dir)))?;
+        if path.join(MANIFEST_FILE).exists() {
+            let db = Database::new(data_dir)?;
+            restore(path, &db)?;
+            return Ok(db);
+        }
+        // 检查点是完整的数据目录，以引擎的版本目录识别
+        if !path.join("versions").is_dir() {
+            return Err(Error::InvalidArgument(format!(
+                "{:?} is neither a checkpoint nor a complete backup",
+                path
+            )));
+        }
+        if target_dir.exists() && fs::read_dir(target_dir)?.next().is_some() {
+            return Err(Error::InvalidArgument(format!(
+                "restore target {:?} is not empty",
+                target_dir
+            )));
+        }
+        copy_dir(path, target_dir)?;
+        Database::new(data_dir)
+    }
+
+    /// （基准, 改变的键及其在 `pinned_at` 时的值）
+    fn changes_since(
+        &self,
+        since_version: Version,
+        pinned_at: f64,
+    ) -> Result<(IncrementalBase, Vec<BatchItem>)> {
+        let mut base = IncrementalBase {
+            since_version,
+            base_root: Root::default(),
+            version: Version(0),
+        };
+        let handle = self.live_handle()?;
+        let entries = collect_writes(&self.state, |results, count| {
+            self.retry_status(|| unsafe {
+                amdb_changes_since(
+                    *handle,
+                    since_version.0,
+                    pinned_at,
+                    base.base_root.as_mut_ptr(),
+                    &mut base.version.0,
+                    results,
+                    count,
+                )
+            })
+        })?;
+        Ok((base, self.open_batch_items(entries)?))
+    }
+}
+
+/// 把 `db` 备份到目录 `dir`，等同于以 [`DirTarget`] 调用 [`create_to`]
+pub fn create(db: &Database, dir: impl AsRef<Path>) -> Result<Manifest> {
+    create_to(db, &DirTarget::new(dir)?)
+}
+
+/// 把 `db` 备份到 `target`，返回备份清单
+///
+/// 备份期间不阻塞写入。目标中有未完成的备份时从检查点续传，续传仍读取最初固定的
+/// 时间点，期间的写入不影响结果。目标中已有完整备份时直接返回其清单。
+pub fn create_to(db: &Database, target: &dyn BackupTarget) -> Result<Manifest> {
+    match run(db, target, SEGMENT_BYTES, None)? {
+        Some(manifest) => Ok(manifest),
+        None => unreachable!("backup without a segment limit always completes"),
+    }
+}
+
+/// 从目录 `dir` 恢复，等同于以 [`DirTarget`] 调用 [`restore_from`]
+pub fn restore(dir: impl AsRef<Path>, db: &Database) -> Result<Manifest> {
+    restore_from(&DirTarget::new(dir)?, db)
+}
+
+/// 把备份导入 `db`，返回备份清单；逐段导入，中途失败时已导入的分段保留在数据库中
+pub fn restore_from(target: &dyn BackupTarget, db: &Database) -> Result<Manifest> {
+    restore_with_progress(target, db, |_| {})
+}
+
+/// 同 [`restore_from`]，每导入一个分段后以当前进度调用 `progress`
+pub fn restore_with_progress(
+    target: &dyn BackupTarget,
+    db: &Database,
+    mut progress: impl FnMut(RestoreProgress),
+) -> Result<Manifest> {
+    let manifest = read_manifest(target)?;
+    if let Some(base) = manifest.base.filter(|base| base.since_version > Version(0)) {
+        let actual = db.get_root_hash()?;
+        if actual != base.base_root {
+            return Err(Error::RootMismatch { actual });
+        }
+    }
+    let mut done = RestoreProgress {
+        segments_done: 0,
+        segments_total: manifest.segments,
+        entries_done: 0,
+        entries_total: manifest.entry_count,
+        bytes_done: 0,
+    };
+    for segment in 0..manifest.segments {
+        let (info, entries) = read_segment(target, segment, &manifest)?;
+        db.batch_put(&entries)?;
+        done.segments_done += 1;
+        done.entries_done += info.entry_count;
+        done.bytes_done += entry_bytes(&entries);
+        progress(done);
+    }
+    check_entry_count(&manifest, done.entries_done)?;
+    Ok(manifest)
+}
+
+/// 恢复演练：读取并校验全部分段，统计将导入的内容以及 `db` 中将被覆盖的键，不写入任何数据
+pub fn dry_run(target: &dyn BackupTarget, db: &Database) -> Result<RestorePlan> {
+    let manifest = read_manifest(target)?;
+    let mut plan = RestorePlan {
+        manifest: manifest.clone(),
+        segments: Vec::with_capacity(manifest.segments as usize),
+        required_bytes: 0,
+        overwritten_keys: 0,
+    };
+    let mut entry_count = 0;
+    for segment in 0..manifest.segments {
+        let (info, entries) = read_segment(target, segment, &manifest)?;
+        let (Some(first), Some(last)) = (entries.first(), entries.last()) else {
+            continue;
+        };
+        let end = [last.0.as_slice(), &[0x00]].concat();
+        let existing = db.range_query(&first.0, &end)?;
+        let existing: HashSet<&[u8]> = existing.iter().map(|(key, _)| key.as_slice()).collect();
+        plan.overwritten_keys += entries
+            .iter()
+            .filter(|(key, _)| existing.contains(key.as_slice()))
+            .count() as u64;
+
+        let bytes = entry_bytes(&entries);
+        plan.required_bytes += bytes;
+        entry_count += info.entry_count;
+        plan.segments.push(SegmentPlan {
+            entry_count: info.entry_count,
+            bytes,
+            first_key: first.0.clone(),
+            last_key: last.0.clone(),
+        });
+    }
+    check_entry_count(&manifest, entry_count)?;
+    Ok(plan)
+}
+
+/// 校验 `db` 当前的根哈希在 `roots`（例如网络公布的各版本根哈希）之中，返回匹配的根哈希
+///
+/// 恢复到空数据库后，其根哈希等于备份清单中的 `root_hash`，据此可以证明恢复出的状态
+/// 与公布的状态一致，而不只是文件被完整复制。不一致时返回 `Error::RootMismatch`。
+pub fn verify_against(db: &Database, roots: &[Root]) -> Result<Root> {
+    let actual = db.get_root_hash()?;
+    if roots.contains(&actual) {
+        Ok(actual)
+    } else {
+        Err(Error::RootMismatch { actual })
+    }
+}
+
+fn entry_bytes(entries: &[BatchItem]) -> u64 {
+    entries.iter().map(|entry| item_len(entry) as u64).sum()
+}
+
+fn item_len((key, value): &BatchItem) -> usize {
+    key.len() + value.as_ref().map_or(0, Vec::len)
+}
+
+fn check_entry_count(manifest: &Manifest, entry_count: u64) -> Result<()> {
+    if entry_count != manifest.entry_count {
+        return Err(Error::Corruption(format!(
+            "backup segments hold {} entries, manifest records {}",
+            entry_count, manifest.entry_count
+        )));
+    }
+    Ok(())
+}
+
+/// 写入至多 `max_segments` 个分段；提前停止（备份未完成）时返回 `None`
+fn run(
+    db: &Database,
+    target: &dyn BackupTarget,
+    segment_bytes: usize,
+    max_segments: Option<usize>,
+) -> Result<Option<Manifest>> {
+    if target.get(MANIFEST_FILE)?.is_some() {
+        return read_manifest(target).map(Some);
+    }
+
+    // 备份期间登记固定的时间点，使保留策略不删除备份要读取的版本
+    let (_pin, mut progress) = match read_progress(target)? {
+        Some(progress) => (db.retain_pinned(progress.manifest.pinned_at), progress),
+        None => {
+            let (pin, root_hash) = db.pin_retained()?;
+            let progress = Progress {
+                manifest: Manifest {
+                    root_hash,
+                    pinned_at: pin.pinned_at,
+                    created_at: unix_now(),
+                    segments: 0,
+                    entry_count: 0,
+                    base: None,
+                },
+                last_key: None,
+            };
+            (pin, progress)
+        }
+    };
+
+    // 从上次写完的最后一个键之后继续；已删除的键也要备份，否则恢复后根哈希不同
+    let start = match &progress.last_key {
+        Some(key) => [key.as_slice(), &[0x00]].concat(),
+        None => Vec::new(),
+    };
+    let entries = db.range_query_at(&start, b"", progress.manifest.pinned_at)?;
+    if !write_segments(target, &mut progress, &entries, segment_bytes, max_segments)? {
+        return Ok(None);
+    }
+    finish(target, progress).map(Some)
+}
+
+/// 按 `segment_bytes` 切分写入 `entries`，写满 `max_segments` 个分段仍有剩余时返回 `false`
+fn write_segments(
+    target: &dyn BackupTarget,
+    progress: &mut Progress,
+    entries: &[BatchItem],
+    segment_bytes: usize,
+    max_segments: Option<usize>,
+) -> Result<bool> {
+    let mut written = 0;
+    let mut rest = entries;
+    while !rest.is_empty() {
+        if max_segments == Some(written) {
+            return Ok(false);
+        }
+        let mut bytes = 0;
+        let mut len = 0;
+        while len < rest.len() && (len == 0 || bytes < segment_bytes) {
+            bytes += item_len(&rest[len]);
+            len += 1;
+        }
+        let (segment, tail) = rest.split_at(len);
+        write_segment(target, progress, segment)?;
+        written += 1;
+        rest = tail;
+    }
+    Ok(true)
+}
+
+/// 写入清单并删除检查点，备份至此完整
+fn finish(target: &dyn BackupTarget, progress: Progress) -> Result<Manifest> {
+    let manifest = encode_manifest(&progress.manifest, None);
+    target.put(MANIFEST_FILE, manifest.as_bytes())?;
+    target.delete(PROGRESS_FILE)?;
+    Ok(progress.manifest)
+}
+
+fn c_path(path: &Path) -> Result<CString> {
+    path.to_str()
+        .and_then(|path| CString::new(path).ok())
+        .ok_or_else(|| Error::InvalidArgument(format!("invalid path {:?}", path)))
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let dest = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &dest)?;
+        } else {
+            fs::copy(entry.path(), dest)?;
+        }
+    }
+    Ok(())
+}
+
+/// 写入下一个分段并把检查点推进到该分段之后
+fn write_segment(
+    target: &dyn BackupTarget,
+    progress: &mut Progress,
+    entries: &[BatchItem],
+) -> Result<()> {
+    let manifest = &mut progress.manifest;
+    let info = SnapshotInfo {
+        root_hash: manifest.root_hash,
+        created_at: manifest.created_at,
+        entry_count: entries.len() as u64,
+    };
+    let mut data = Vec::new();
+    encode_snapshot(&mut data, &info, entries)?;
+    target.put(&segment_name(manifest.segments), &data)?;
+
+    manifest.segments += 1;
+    manifest.entry_count += entries.len() as u64;
+    progress.last_key = entries.last().map(|(key, _)| key.clone());
+    let text = encode_manifest(manifest, progress.last_key.as_deref());
+    target.put(PROGRESS_FILE, text.as_bytes())
+}
+
+fn read_segment(
+    target: &dyn BackupTarget,
+    segment: u32,
+    manifest: &Manifest,
+) -> Result<(SnapshotInfo, Vec<BatchItem>)> {
+    let data = target
+        .get(&segment_name(segment))?
+        .ok_or_else(|| Error::Corruption(format!("backup segment {} is missing", segment)))?;
+    let (info, entries) = decode_snapshot(&mut data.as_slice())?;
+    if info.root_hash != manifest.root_hash {
+        return Err(Error::Corruption(format!(
+            "segment {} belongs to a different backup",
+            segment
+        )));
+    }
+    Ok((info, entries))
+}
+
+fn segment_name(segment: u32) -> String {
+    format!("segment-{:06}.snap", segment)
+}
+
+fn read_manifest(target: &dyn BackupTarget) -> Result<Manifest> {
+    match target.get(MANIFEST_FILE)? {
+        Some(data) => decode_manifest(&text_of(data)?).map(|(manifest, _)| manifest),
+        None => Err(Error::Corruption(
+            "backup is incomplete: no manifest".to_string(),
+        )),
+    }
+}
+
+fn read_progress(target: &dyn BackupTarget) -> Result<Option<Progress>> {
+    match target.get(PROGRESS_FILE)? {
+        Some(data) => {
+            let (manifest, last_key) = decode_manifest(&text_of(data)?)?;
+            Ok(Some(Progress { manifest, last_key }))
+        }
+        None => Ok(None),
+    }
+}
+
+fn text_of(data: Vec<u8>) -> Result<String> {
+    String::from_utf8(data)
+        .map_err(|_| Error::Corruption("backup manifest is not UTF-8".to_string()))
+}
+
+fn encode_manifest(manifest: &Manifest, last_key: Option<&[u8]>) -> String {
+    let mut text = format!(
+        "{}\nroot_hash {}\npinned_at {}\ncreated_at {}\nsegments {}\nentry_count {}\n",
+        FORMAT_LINE,
+        to_hex(&*manifest.root_hash),
+        manifest.pinned_at,
+        manifest.created_at,
+        manifest.segments,
+        manifest.entry_count
+    );
+    if let Some(base) = &manifest.base {
+        text.push_str(&format!(
+            "since_version {}\nbase_root {}\nversion {}\n",
+            base.since_version,
+            to_hex(&*base.base_root),
+            base.version
+        ));
+    }
+    if let Some(key) = last_key {
+        text.push_str(&format!("last_key {}\n", to_hex(key)));
+    }
+    text
+}
+
+fn decode_manifest(text: &str) -> Result<(Manifest, Option<Vec<u8>>)> {
+    let corrupt = |what: &str| Error::Corruption(format!("backup manifest: {}", what));
+    let mut lines = text.lines();
+    if lines.next() != Some(FORMAT_LINE) {
+        return Err(corrupt("unsupported format"));
+    }
+    let mut root_hash = None;
+    let mut pinned_at = None;
+    let mut created_at = None;
+    let mut segments = None;
+    let mut entry_count = None;
+    let mut last_key = None;
+    let (mut since_version, mut base_root, mut version) = (None, None, None);
+    for line in lines {
+        let (field, value) = line.split_once(' ').ok_or_else(|| corrupt(line))?;
+        match field {
+            "root_hash" | "base_root" => {
+                let bytes = from_hex(value).ok_or_else(|| corrupt(line))?;
+                let hash = <[u8; 32]>::try_from(bytes.as_slice()).map_err(|_| corrupt(line))?;
+                let hash = Root(hash);
+                if field == "root_hash" {
+                    root_hash = Some(hash);
+                } else {
+                    base_root = Some(hash);
+                }
+            }
+            "pinned_at" => pinned_at = Some(value.parse().map_err(|_| corrupt(line))?),
+            "created_at" => created_at = Some(value.parse().map_err(|_| corrupt(line))?),
+            "segments" => segments = Some(value.parse().map_err(|_| corrupt(line))?),
+            "entry_count" => entry_count = Some(value.parse().map_err(|_| corrupt(line))?),
+            "since_version" => since_version = Some(value.parse().map_err(|_| corrupt(line))?),
+            "version" => version = Some(value.parse().map_err(|_| corrupt(line))?),
+            "last_key" => last_key = Some(from_hex(value).ok_or_else(|| corrupt(line))?),
+            // 未知字段留给更新的版本
+            _ => {}
+        }
+    }
+    let manifest = Manifest {
+        root_hash: root_hash.ok_or_else(|| corrupt("missing root_hash"))?,
+        pinned_at: pinned_at.ok_or_else(|| corrupt("missing pinned_at"))?,
+        created_at: created_at.ok_or_else(|| corrupt("missing created_at"))?,
+        segments: segments.ok_or_else(|| corrupt("missing segments"))?,
+        entry_count: entry_count.ok_or_else(|| corrupt("missing entry_count"))?,
+        base: match since_version {
+            Some(since_version) => Some(IncrementalBase {
+                since_version,
+                base_root: base_root.ok_or_else(|| corrupt("missing base_root"))?,
+                version: version.ok_or_else(|| corrupt("missing version"))?,
+            }),
+            None => None,
+        },
+    };
+    Ok((manifest, last_key))
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interrupted_backup_resumes() {
+        let _ = std::fs::remove_dir_all("./test_data/backup_src");
+        let _ = std::fs::remove_dir_all("./test_data/backup");
+        let _ = std::fs::remove_dir_all("./test_data/backup_dst");
+        let db = Database::new("./test_data/backup_src").unwrap();
+        for i in 0..5u8 {
+            db.put(&[b'k', i], &[i; 8]).unwrap();
+        }
+        let dir = Path::new("./test_data/backup");
+        let target = DirTarget::new(dir).unwrap();
+
+        // 每段一个条目，写完两段后模拟中断；续传时剩余条目写入同一段
+        assert!(run(&db, &target, 1, Some(2)).unwrap().is_none());
+        assert!(!dir.join(MANIFEST_FILE).exists());
+        let manifest = create(&db, dir).unwrap();
+        assert_eq!(manifest.entry_count, 5);
+        assert_eq!(manifest.segments, 3);
+        assert_eq!(create(&db, dir).unwrap(), manifest);
+
+        let restored = Database::new("./test_data/backup_dst").unwrap();
+        restore(dir, &restored).unwrap();
+        assert_eq!(restored.get(&[b'k', 4], None).unwrap(), Some(vec![4; 8]));
+        assert_eq!(restored.scan(..).count(), 5);
+    }
+
+    #[test]
+    fn test_dry_run_and_progress() {
+        let _ = std::fs::remove_dir_all("./test_data/backup_plan_src");
+        let _ = std::fs::remove_dir_all("./test_data/backup_plan");
+        let _ = std::fs::remove_dir_all("./test_data/backup_plan_dst");
+        let db = Database::new("./test_data/backup_plan_src").unwrap();
+        db.put(b"a", b"12").unwrap();
+        db.put(b"b", b"345").unwrap();
+        let target = DirTarget::new("./test_data/backup_plan").unwrap();
+        run(&db, &target, 1, None).unwrap();
+
+        let restored = Database::new("./test_data/backup_plan_dst").unwrap();
+        restored.put(b"b", b"old").unwrap();
+        let plan = dry_run(&target, &restored).unwrap();
+        assert_eq!(plan.segments.len(), 2);
+        assert_eq!(plan.segments[1].first_key, b"b".to_vec());
+        assert_eq!(plan.required_bytes, 1 + 2 + 1 + 3);
+        assert_eq!(plan.overwritten_keys, 1);
+        assert_eq!(restored.get(b"b", None).unwrap(), Some(b"old".to_vec()));
+
+        let mut reports = Vec::new();
+        restore_with_progress(&target, &restored, |p| reports.push(p)).unwrap();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[1].entries_done, reports[1].entries_total);
+        assert_eq!(reports[1].bytes_done, plan.required_bytes);
+    }
+
+    #[test]
+    fn test_verify_restored_state() {
+        let _ = std::fs::remove_dir_all("./test_data/backup_verify_src");
+        let _ = std::fs::remove_dir_all("./test_data/backup_verify");
+        let _ = std::fs::remove_dir_all("./test_data/backup_verify_dst");
+        let db = Database::new("./test_data/backup_verify_src").unwrap();
+        db.put(b"a", b"1").unwrap();
+        db.put(b"b", b"2").unwrap();
+        db.delete(b"b").unwrap();
+        let published = db.get_root_hash().unwrap();
+        let manifest = create(&db, "./test_data/backup_verify").unwrap();
+        assert_eq!(manifest.root_hash, published);
+
+        let restored = Database::new("./test_data/backup_verify_dst").unwrap();
+        restore("./test_data/backup_verify", &restored).unwrap();
+        assert!(restored.get(b"b", None).unwrap().is_none());
+        assert_eq!(verify_against(&restored, &[Root::default(), published]).unwrap(), published);
+        assert!(matches!(
+            verify_against(&restored, &[Root::default()]),
+            Err(Error::RootMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_writes_during_backup_are_excluded() {
+        let _ = std::fs::remove_dir_all("./test_data/backup_hot");
+        let _ = std::fs::remove_dir_all("./test_data/backup_hot_dir");
+        let _ = std::fs::remove_dir_all("./test_data/backup_hot_dst");
+        let db = Database::new("./test_data/backup_hot").unwrap();
+        db.put(b"a", b"1").unwrap();
+        db.put(b"b", b"2").unwrap();
+        db.put(b"c", b"3").unwrap();
+        let pinned_root = db.get_root_hash().unwrap();
+        let dir = Path::new("./test_data/backup_hot_dir");
+        assert!(run(&db, &DirTarget::new(dir).unwrap(), 1, Some(1))
+            .unwrap()
+            .is_none());
+
+        // 备份中断期间继续写入：改写已备份和未备份的键，并新增一个键
+        db.put(b"a", b"changed").unwrap();
+        db.put(b"c", b"changed").unwrap();
+        db.put(b"d", b"4").unwrap();
+        let manifest = create(&db, dir).unwrap();
+        assert_eq!(manifest.root_hash, pinned_root);
+        assert_eq!(manifest.entry_count, 3);
+
+        let restored = Database::new("./test_data/backup_hot_dst").unwrap();
+        restore(dir, &restored).unwrap();
+        assert_eq!(restored.get(b"c", None).unwrap(), Some(b"3".to_vec()));
+        assert!(restored.get(b"d", None).unwrap().is_none());
+        assert_eq!(verify_against(&restored, &[pinned_root]).unwrap(), pinned_root);
+    }
+
+    #[test]
+    fn test_checkpoint_and_incremental_restore() {
+        let _ = std::fs::remove_dir_all("./test_data/backup_incr_src");
+        let _ = std::fs::remove_dir_all("./test_data/backup_incr_checkpoint");
+        let _ = std::fs::remove_dir_all("./test_data/backup_incr_delta");
+        let _ = std::fs::remove_dir_all("./test_data/backup_incr_none");
+        let _ = std::fs::remove_dir_all("./test_data/backup_incr_dst");
+        let _ = std::fs::remove_dir_all("./test_data/backup_incr_other");
+        let db = Database::new("./test_data/backup_incr_src").unwrap();
+        db.put(b"a", b"1").unwrap();
+        db.put(b"b", b"2").unwrap();
+        let checkpoint = db.checkpoint("./test_data/backup_incr_checkpoint").unwrap();
+        assert_eq!(checkpoint.version, Version(2));
+        assert!(matches!(
+            db.checkpoint("./test_data/backup_incr_checkpoint"),
+            Err(Error::InvalidArgument(_))
+        ));
+
+        db.put(b"b", b"changed").unwrap();
+        db.delete(b"a").unwrap();
+        db.put(b"c", b"3").unwrap();
+        let dir = "./test_data/backup_incr_delta";
+        let manifest = db.backup_incremental(dir, checkpoint.version).unwrap();
+        assert_eq!(manifest.entry_count, 3);
+        let base = manifest.base.unwrap();
+        assert_eq!((base.base_root, base.version), (checkpoint.root_hash, Version(5)));
+        assert_eq!(manifest.root_hash, db.get_root_hash().unwrap());
+        assert!(matches!(
+            db.backup_incremental("./test_data/backup_incr_none", Version(9)),
+            Err(Error::NotFound)
+        ));
+
+        let target = "./test_data/backup_incr_dst";
+        let restored = Database::restore("./test_data/backup_incr_checkpoint", target).unwrap();
+        assert_eq!(restored.get_root_hash().unwrap(), checkpoint.root_hash);
+        // 增量备份只能导入到处于基准状态的数据库
+        let other = Database::new("./test_data/backup_incr_other").unwrap();
+        assert!(matches!(
+            restore(dir, &other),
+            Err(Error::RootMismatch { .. })
+        ));
+        drop(restored);
+
+        let restored = Database::restore(dir, target).unwrap();
+        assert_eq!(restored.get_root_hash().unwrap(), manifest.root_hash);
+        assert!(restored.get(b"a", None).unwrap().is_none());
+        assert_eq!(restored.get(b"b", None).unwrap(), Some(b"changed".to_vec()));
+    }
+
+    #[test]
+    fn test_fork_to() {
+        let _ = std::fs::remove_dir_all("./test_data/fork_src");
+        let _ = std::fs::remove_dir_all("./test_data/fork_latest");
+        let _ = std::fs::remove_dir_all("./test_data/fork_old");
+        let _ = std::fs::remove_dir_all("./test_data/fork_missing");
+        let db = Database::new("./test_data/fork_src").unwrap();
+        db.put(b"a", b"1").unwrap();
+        db.delete(b"a").unwrap();
+        let root = db.put(b"b", b"1").unwrap();
+        db.put(b"b", b"2").unwrap();
+        db.put(b"c", b"3").unwrap();
+
+        let latest = db.fork_to("./test_data/fork_latest", Version(5)).unwrap();
+        assert_eq!(latest.version, Version(5));
+        let old = db.fork_to("./test_data/fork_old", Version(3)).unwrap();
+        assert_eq!((old.version, old.root_hash), (Version(1), root));
+        assert!(matches!(
+            db.fork_to("./test_data/fork_old", Version(3)),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            db.fork_to("./test_data/fork_missing", Version(9)),
+            Err(Error::NotFound)
+        ));
+
+        // 分叉之后互不影响
+        db.put(b"c", b"src").unwrap();
+        let fork = Database::new("./test_data/fork_latest").unwrap();
+        assert_eq!(fork.root_hash_at(Version(5)).unwrap(), latest.root_hash);
+        fork.put(b"b", b"fork").unwrap();
+        assert_eq!(fork.get(b"c", None).unwrap(), Some(b"3".to_vec()));
+        assert_eq!(db.get(b"b", None).unwrap(), Some(b"2".to_vec()));
+
+        let fork = Database::new("./test_data/fork_old").unwrap();
+        assert_eq!(fork.get_root_hash().unwrap(), root);
+        assert!(fork.get(b"a", None).unwrap().is_none());
+        assert_eq!(fork.get(b"b", None).unwrap(), Some(b"1".to_vec()));
+        assert!(fork.get(b"c", None).unwrap().is_none());
+    }
+}
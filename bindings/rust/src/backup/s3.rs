@@ -0,0 +1,320 @@
+//! S3 兼容对象存储目标（`s3` 特性）
+//! 使用路径风格地址（`endpoint/bucket/key`）和 AWS Signature V4 签名，兼容 MinIO 等实现
+
+use std::io::{self, Read};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use super::{to_hex, BackupTarget};
+use crate::{Error, Result};
+
+/// 超过该大小的对象使用分片上传，也是每个分片的大小（S3要求除最后一片外不小于5 MiB）
+const PART_SIZE: usize = 8 << 20;
+
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+pub struct S3Target {
+    agent: ureq::Agent,
+    endpoint: String,
+    host: String,
+    bucket: String,
+    prefix: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+    max_retries: u32,
+}
+
+struct Reply {
+    status: u16,
+    etag: Option<String>,
+    body: Vec<u8>,
+}
+
+impl S3Target {
+    /// `endpoint` 形如 `https://s3.us-east-1.amazonaws.com` 或 `http://127.0.0.1:9000`
+    pub fn new(
+        endpoint: &str,
+        bucket: &str,
+        region: &str,
+        access_key: &str,
+        secret_key: &str,
+    ) -> Self {
+        let endpoint = endpoint.trim_end_matches('/').to_string();
+        let host = endpoint
+            .split_once("://")
+            .map_or(endpoint.as_str(), |(_, rest)| rest)
+            .to_string();
+        S3Target {
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(300))
+                .build(),
+            endpoint,
+            host,
+            bucket: bucket.to_string(),
+            prefix: String::new(),
+            region: region.to_string(),
+            access_key: access_key.to_string(),
+            secret_key: secret_key.to_string(),
+            max_retries: 3,
+        }
+    }
+
+    /// 对象名前缀，例如 `node-1/2026-10-14/`；不同备份应使用不同前缀
+    pub fn prefix(&mut self, prefix: &str) -> &mut Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// 请求遇到网络错误或5xx响应时的最大重试次数（默认3），重试间隔指数增长
+    pub fn max_retries(&mut self, retries: u32) -> &mut Self {
+        self.max_retries = retries;
+        self
+    }
+
+    fn upload_parts(&self, name: &str, upload_id: &str, data: &[u8]) -> Result<()> {
+        let mut parts = String::from("<CompleteMultipartUpload>");
+        for (i, chunk) in data.chunks(PART_SIZE).enumerate() {
+            let number = (i + 1).to_string();
+            let query = [("partNumber", number.as_str()), ("uploadId", upload_id)];
+            let reply = self.send("PUT", name, &query, chunk)?;
+            check(&reply, "UploadPart")?;
+            let etag = reply
+                .etag
+                .ok_or_else(|| s3_error("UploadPart response has no ETag"))?;
+            parts.push_str(&format!(
+                "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+                number, etag
+            ));
+        }
+        parts.push_str("</CompleteMultipartUpload>");
+
+        let reply = self.send("POST", name, &[("uploadId", upload_id)], parts.as_bytes())?;
+        check(&reply, "CompleteMultipartUpload")?;
+        // 完成分片上传的失败也可能以200返回，错误在响应体中
+        if xml_value(&reply.body, "Code").is_some() {
+            return Err(s3_error(&format!(
+                "CompleteMultipartUpload failed: {}",
+                String::from_utf8_lossy(&reply.body)
+            )));
+        }
+        Ok(())
+    }
+
+    fn send(&self, method: &str, name: &str, query: &[(&str, &str)], body: &[u8]) -> Result<Reply> {
+        let mut attempt = 0;
+        loop {
+            match self.send_once(method, name, query, body) {
+                Ok(reply) if reply.status < 500 => return Ok(reply),
+                result if attempt >= self.max_retries => return result,
+                _ => {}
+            }
+            attempt += 1;
+            thread::sleep(Duration::from_millis(100 << attempt.min(6)));
+        }
+    }
+
+    fn send_once(
+        &self,
+        method: &str,
+        name: &str,
+        query: &[(&str, &str)],
+        body: &[u8],
+    ) -> Result<Reply> {
+        let path = format!(
+            "/{}/{}",
+            self.bucket,
+            uri_encode(&format!("{}{}", self.prefix, name), false)
+        );
+        let mut pairs: Vec<String> = query
+            .iter()
+            .map(|(k, v)| format!("{}={}", uri_encode(k, true), uri_encode(v, true)))
+            .collect();
+        pairs.sort();
+        let query = pairs.join("&");
+
+        let payload_hash = to_hex(&Sha256::digest(body));
+        let amz_date = amz_date(SystemTime::now());
+        let date = &amz_date[..8];
+        let canonical = format!(
+            "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, path, query, self.host, payload_hash, amz_date, SIGNED_HEADERS, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            to_hex(&Sha256::digest(canonical.as_bytes()))
+        );
+        let mut key = hmac(
+            format!("AWS4{}", self.secret_key).as_bytes(),
+            date.as_bytes(),
+        );
+        for part in [self.region.as_str(), "s3", "aws4_request"] {
+            key = hmac(&key, part.as_bytes());
+        }
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key,
+            scope,
+            SIGNED_HEADERS,
+            to_hex(&hmac(&key, string_to_sign.as_bytes()))
+        );
+
+        let url = if query.is_empty() {
+            format!("{}{}", self.endpoint, path)
+        } else {
+            format!("{}{}?{}", self.endpoint, path, query)
+        };
+        let request = self
+            .agent
+            .request(method, &url)
+            .set("x-amz-date", &amz_date)
+            .set("x-amz-content-sha256", &payload_hash)
+            .set("Authorization", &authorization);
+        let response = match request.send_bytes(body) {
+            Ok(response) => response,
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(s3_error(&e.to_string())),
+        };
+        let status = response.status();
+        let etag = response.header("ETag").map(str::to_string);
+        let mut body = Vec::new();
+        response.into_reader().read_to_end(&mut body)?;
+        Ok(Reply { status, etag, body })
+    }
+}
+
+impl BackupTarget for S3Target {
+    /// 不超过一个分片的对象直接上传，更大的对象使用分片上传；失败时放弃未完成的分片上传
+    fn put(&self, name: &str, data: &[u8]) -> Result<()> {
+        if data.len() <= PART_SIZE {
+            return check(&self.send("PUT", name, &[], data)?, "PutObject");
+        }
+        let reply = self.send("POST", name, &[("uploads", "")], &[])?;
+        check(&reply, "CreateMultipartUpload")?;
+        let upload_id = xml_value(&reply.body, "UploadId")
+            .ok_or_else(|| s3_error("CreateMultipartUpload response has no UploadId"))?;
+        let result = self.upload_parts(name, &upload_id, data);
+        if result.is_err() {
+            let _ = self.send("DELETE", name, &[("uploadId", upload_id.as_str())], &[]);
+        }
+        result
+    }
+
+    fn get(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let reply = self.send("GET", name, &[], &[])?;
+        if reply.status == 404 {
+            return Ok(None);
+        }
+        check(&reply, "GetObject")?;
+        Ok(Some(reply.body))
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        let reply = self.send("DELETE", name, &[], &[])?;
+        if reply.status == 404 {
+            return Ok(());
+        }
+        check(&reply, "DeleteObject")
+    }
+}
+
+fn check(reply: &Reply, operation: &str) -> Result<()> {
+    if (200..300).contains(&reply.status) {
+        return Ok(());
+    }
+    Err(s3_error(&format!(
+        "{} failed with status {}: {}",
+        operation,
+        reply.status,
+        String::from_utf8_lossy(&reply.body)
+    )))
+}
+
+fn s3_error(message: &str) -> Error {
+    Error::Io(io::Error::other(format!("S3: {}", message)))
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// SigV4 的URI编码：只保留非保留字符，`encode_slash` 为假时保留 `/`
+fn uri_encode(text: &str, encode_slash: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    for &b in text.as_bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            b'/' if !encode_slash => out.push('/'),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+/// 取出第一个 `<tag>...</tag>` 的内容
+fn xml_value(body: &[u8], tag: &str) -> Option<String> {
+    let text = String::from_utf8_lossy(body);
+    let open = format!("<{}>", tag);
+    let start = text.find(&open)? + open.len();
+    let end = start + text[start..].find(&format!("</{}>", tag))?;
+    Some(text[start..end].to_string())
+}
+
+/// `YYYYMMDD'T'HHMMSS'Z'` 格式的UTC时间
+fn amz_date(now: SystemTime) -> String {
+    let secs = now.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let rem = secs % 86400;
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// 1970-01-01 起的天数换算为公历日期
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_helpers() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+        assert_eq!(
+            amz_date(UNIX_EPOCH + Duration::from_secs(1_440_938_160)),
+            "20150830T123600Z"
+        );
+        assert_eq!(uri_encode("a b/c~", false), "a%20b/c~");
+        assert_eq!(uri_encode("a/b", true), "a%2Fb");
+        let body = b"<InitiateMultipartUploadResult><UploadId>abc</UploadId></InitiateMultipartUploadResult>";
+        assert_eq!(xml_value(body, "UploadId").as_deref(), Some("abc"));
+    }
+}
@@ -0,0 +1,682 @@
+//! 写批次
+//! 在内存中暂存多个写操作，通过 `Database::write_batch` 一次性原子提交
+//!
+//! 带幂等令牌的批次会在同一次提交中写入保留键 `\0idem/<令牌>`，
+//! 令牌已存在时再次提交不做任何写入，返回该键的版本历史记下的首次提交的数据库版本和根哈希。
+//! 令牌记录计入根哈希，但不出现在范围扫描、游标和版本差异中。
+//! 同一个键在批次中被多次写入时，提交时只保留最后一个操作。
+//! 复制应用（`Database::apply_replicated`）把最后应用的序列号保存在保留键 `\0repl/seq`。
+
+use std::collections::HashSet;
+use std::slice;
+
+use crate::{
+    amdb_batch_root_hash, amdb_batch_root_hash_at, value_ptrs, BatchItem, CommitStats, Database,
+    Error, Result, Root, Version,
+};
+
+/// 每个操作在估算大小时额外计入的字节数（跨FFI传递的键、值长度）
+const OP_OVERHEAD: usize = 2 * std::mem::size_of::<usize>();
+
+pub(crate) const TOKEN_PREFIX: &[u8] = b"\0idem/";
+
+/// 幂等令牌记录的值
+const TOKEN_RECORD: &[u8] = b"\0";
+
+pub(crate) const REPL_SEQ_KEY: &[u8] = b"\0repl/seq";
+
+enum Op {
+    Put(Vec<u8>, Vec<u8>),
+    Delete(Vec<u8>),
+}
+
+/// 批次中暂存的一个操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchOp<'a> {
+    Put { key: &'a [u8], value: &'a [u8] },
+    Delete { key: &'a [u8] },
+}
+
+impl<'a> BatchOp<'a> {
+    pub fn key(&self) -> &'a [u8] {
+        match self {
+            BatchOp::Put { key, .. } | BatchOp::Delete { key } => key,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct WriteBatch {
+    ops: Vec<Op>,
+    size: usize,
+    max_size: Option<usize>,
+    token: Option<Vec<u8>>,
+    savepoints: Vec<(usize, usize)>,
+}
+
+impl WriteBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 由按键升序排列的 (键, 值) 序列构造批次，每项都是写入
+    ///
+    /// 引擎按键序接收操作时提交快得多。调用方负责排序；顺序不对时提交结果仍然正确，只是失去这一优势。
+    pub fn from_sorted_iter<K, V>(entries: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let mut batch = Self::new();
+        for (key, value) in entries {
+            batch.put(key.as_ref(), value.as_ref());
+        }
+        batch
+    }
+
+    /// 同 `from_sorted_iter`，但先按键排序；同一个键出现多次时保留最后一个值
+    pub fn from_unsorted_iter<K, V>(entries: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let mut entries: Vec<(K, V)> = entries.into_iter().collect();
+        // 稳定排序，同键的条目保持原有先后
+        entries.sort_by(|a, b| a.0.as_ref().cmp(b.0.as_ref()));
+        entries.dedup_by(|later, earlier| {
+            let duplicate = later.0.as_ref() == earlier.0.as_ref();
+            if duplicate {
+                std::mem::swap(later, earlier);
+            }
+            duplicate
+        });
+        Self::from_sorted_iter(entries)
+    }
+
+    /// 批次大小上限；`approximate_size` 超过上限时提交返回 `Error::BatchTooLarge`，不会进入引擎
+    pub fn max_size(&mut self, bytes: usize) -> &mut Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// 幂等令牌；带相同令牌的批次只会被应用一次，重复提交直接返回首次提交的版本和根哈希
+    pub fn idempotency_token(&mut self, token: &[u8]) -> &mut Self {
+        self.token = Some(token.to_vec());
+        self
+    }
+
+    pub fn put(&mut self, key: &[u8], value: &[u8]) -> &mut Self {
+        self.size += key.len() + value.len() + OP_OVERHEAD;
+        self.ops.push(Op::Put(key.to_vec(), value.to_vec()));
+        self
+    }
+
+    pub fn delete(&mut self, key: &[u8]) -> &mut Self {
+        self.size += key.len() + OP_OVERHEAD;
+        self.ops.push(Op::Delete(key.to_vec()));
+        self
+    }
+
+    /// 把 `other` 的全部操作追加到本批次之后，同一个键以后写入的为准
+    ///
+    /// `other` 的大小上限和幂等令牌被忽略。
+    pub fn append(&mut self, other: WriteBatch) -> &mut Self {
+        self.size += other.size;
+        self.ops.extend(other.ops);
+        self
+    }
+
+    /// 记录当前位置，供 `rollback_to_savepoint` 撤销之后暂存的操作；可以嵌套
+    pub fn set_savepoint(&mut self) {
+        self.savepoints.push((self.ops.len(), self.size));
+    }
+
+    /// 撤销最近一个保存点之后暂存的操作并移除该保存点；没有保存点时返回 `false`
+    pub fn rollback_to_savepoint(&mut self) -> bool {
+        match self.savepoints.pop() {
+            Some((len, size)) => {
+                self.ops.truncate(len);
+                self.size = size;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 读取键的最新值：先查找本批次中暂存的操作，没有时读取数据库
+    ///
+    /// 暂存的删除返回 `None`，空值写入返回空值，与提交后的读取结果一致。
+    pub fn get(&self, db: &Database, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        for op in self.ops.iter().rev() {
+            match op {
+                Op::Put(k, value) if k == key => return Ok(Some(value.clone())),
+                Op::Delete(k) if k == key => return Ok(None),
+                _ => {}
+            }
+        }
+        db.get(key, None)
+    }
+
+    /// 按暂存顺序遍历操作（不合并同键的操作）
+    pub fn iter(&self) -> BatchIter<'_> {
+        BatchIter {
+            ops: self.ops.iter(),
+        }
+    }
+
+    /// 已暂存的操作数（同一个键的多次写入分别计数）
+    pub fn op_count(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// 提交时跨FFI传输的大致字节数：键、值长度之和加上每个操作的固定开销
+    ///
+    /// 被同键后续操作覆盖的操作也计算在内，因此是上界。
+    pub fn approximate_size(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// 清空已暂存的操作、保存点和幂等令牌，保留大小上限
+    pub fn clear(&mut self) {
+        self.ops.clear();
+        self.size = 0;
+        self.token = None;
+        self.savepoints.clear();
+    }
+
+    /// 通过 `idempotency_token` 设置的幂等令牌
+    pub fn token(&self) -> Option<&[u8]> {
+        self.token.as_deref()
+    }
+
+    fn check_size(&self) -> Result<()> {
+        match self.max_size {
+            Some(max) if self.size > max => Err(Error::BatchTooLarge {
+                size: self.size,
+                max,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a WriteBatch {
+    type Item = BatchOp<'a>;
+    type IntoIter = BatchIter<'a>;
+
+    fn into_iter(self) -> BatchIter<'a> {
+        self.iter()
+    }
+}
+
+/// `WriteBatch::iter` 返回的迭代器
+pub struct BatchIter<'a> {
+    ops: slice::Iter<'a, Op>,
+}
+
+fn borrow_op(op: &Op) -> BatchOp<'_> {
+    match op {
+        Op::Put(key, value) => BatchOp::Put { key, value },
+        Op::Delete(key) => BatchOp::Delete { key },
+    }
+}
+
+impl<'a> Iterator for BatchIter<'a> {
+    type Item = BatchOp<'a>;
+
+    fn next(&mut self) -> Option<BatchOp<'a>> {
+        self.ops.next().map(borrow_op)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ops.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for BatchIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.ops.next_back().map(borrow_op)
+    }
+}
+
+impl ExactSizeIterator for BatchIter<'_> {}
+
+impl Database {
+    /// 原子地提交批次中的全部操作，返回提交后的根哈希；任一操作校验失败时不写入任何数据。
+    /// 令牌已记录的批次不写入任何数据，返回首次提交的根哈希
+    pub fn write_batch(&self, batch: &WriteBatch) -> Result<Root> {
+        Ok(self.commit_batch(batch, None, false)?.1)
+    }
+
+    /// 同 `write_batch`，并返回提交后的数据库版本；令牌已记录时返回首次提交的版本和根哈希
+    pub fn write_batch_versioned(&self, batch: &WriteBatch) -> Result<(Version, Root)> {
+        let (version, root_hash) = self.commit_batch(batch, None, true)?;
+        Ok((version.unwrap_or_default(), root_hash))
+    }
+
+    /// 同 `write_batch`，并返回本次提交的统计，可据此观察提交耗时花在哈希还是I/O上
+    ///
+    /// 统计按提交前的状态判断键是否存在，因此比 `write_batch` 多读一次每个键；
+    /// 不含幂等令牌的记录，重复提交（空操作）返回全零的统计。
+    pub fn write_batch_with_stats(&self, batch: &WriteBatch) -> Result<(Root, CommitStats)> {
+        let mut stats = CommitStats::default();
+        let (_, root_hash) = self.commit_batch(batch, Some(&mut stats), false)?;
+        Ok((root_hash, stats))
+    }
+
+    /// 按当前状态提交 `batch` 后的根哈希，不写入任何数据；校验与 `write_batch` 相同。
+    /// 不计入幂等令牌的记录
+    pub(crate) fn batch_root_hash(&self, batch: &WriteBatch) -> Result<Root> {
+        self.root_hash_after(batch, None)
+    }
+
+    /// 同 `batch_root_hash`，但在数据库版本 `version` 的状态之上计算；没有该版本时返回 `Error::NotFound`
+    pub(crate) fn batch_root_hash_at(&self, batch: &WriteBatch, version: Version) -> Result<Root> {
+        self.root_hash_after(batch, Some(version))
+    }
+
+    fn root_hash_after(&self, batch: &WriteBatch, version: Option<Version>) -> Result<Root> {
+        batch.check_size()?;
+        let items = self.batch_items(batch)?;
+        self.check_batch_values(&items)?;
+        let keys: Vec<*const u8> = items.iter().map(|(k, _)| k.as_ptr()).collect();
+        let key_lens: Vec<usize> = items.iter().map(|(k, _)| k.len()).collect();
+        let sealed = self.seal_batch_values(&items);
+        let (values, value_lens) = value_ptrs(&sealed);
+
+        let mut root_hash = Root::default();
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            match version {
+                None => amdb_batch_root_hash(
+                    *handle,
+                    keys.as_ptr(),
+                    key_lens.as_ptr(),
+                    values.as_ptr(),
+                    value_lens.as_ptr(),
+                    items.len(),
+                    root_hash.as_mut_ptr(),
+                ),
+                Some(version) => amdb_batch_root_hash_at(
+                    *handle,
+                    version.0,
+                    keys.as_ptr(),
+                    key_lens.as_ptr(),
+                    values.as_ptr(),
+                    value_lens.as_ptr(),
+                    items.len(),
+                    root_hash.as_mut_ptr(),
+                ),
+            }
+        });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        Ok(root_hash)
+    }
+
+    /// 提交批次，返回（`versioned` 时提交后的数据库版本, 根哈希）；令牌已记录时总是返回首次提交的版本
+    fn commit_batch(
+        &self,
+        batch: &WriteBatch,
+        mut stats: Option<&mut CommitStats>,
+        versioned: bool,
+    ) -> Result<(Option<Version>, Root)> {
+        batch.check_size()?;
+        let _writes = self.write_lock();
+        let token_key = batch.token().map(token_key);
+        if let Some(key) = &token_key {
+            if let Some((version, root_hash)) = self.token_commit(key)? {
+                return Ok((Some(version), root_hash));
+            }
+        }
+
+        let mut items = self.batch_items(batch)?;
+        if let Some(key) = &token_key {
+            items.push((key.clone(), Some(TOKEN_RECORD.to_vec())));
+        }
+        // 普通写入不持有 write_lock，版本须与提交在同一次引擎调用中读取
+        if versioned {
+            let (version, root_hash) = self.batch_put_versioned(&items)?;
+            return Ok((Some(version), root_hash));
+        }
+        let root_hash = self.batch_put_with(&items, stats.as_deref_mut())?;
+        if let (Some(stats), Some(key)) = (stats, &token_key) {
+            // 令牌记录总是新插入的键
+            stats.inserted -= 1;
+            stats.bytes_written -= (key.len() + self.seal_value(TOKEN_RECORD).len()) as u64;
+        }
+        Ok((None, root_hash))
+    }
+
+    /// 写入令牌记录的那次提交的数据库版本及其根哈希；令牌尚未记录时为 `None`
+    fn token_commit(&self, key: &[u8]) -> Result<Option<(Version, Root)>> {
+        match self.history_without_values(key).next() {
+            Some(entry) => entry.map(|entry| Some((entry.version, entry.root_hash))),
+            None => Ok(None),
+        }
+    }
+
+    /// 同 `write_batch`，供已持有 `write_lock` 的复合操作调用；幂等令牌在此不生效
+    pub(crate) fn write_batch_locked(&self, batch: &WriteBatch) -> Result<Root> {
+        batch.check_size()?;
+        self.batch_put(&self.batch_items(batch)?)
+    }
+
+    /// 按序列号应用从主节点复制来的批次，序列号与数据在同一次提交中持久化
+    ///
+    /// `seq` 必须恰好比上次应用的序列号大1（首个批次为1），否则返回
+    /// `Error::SequenceMismatch` 且不写入任何数据；批次的幂等令牌在此不生效。
+    pub fn apply_replicated(&self, batch: &WriteBatch, seq: u64) -> Result<Root> {
+        batch.check_size()?;
+        let _writes = self.write_lock();
+        let expected = self.last_applied_seq()? + 1;
+        if seq != expected {
+            return Err(Error::SequenceMismatch { expected, got: seq });
+        }
+        let mut items = self.batch_items(batch)?;
+        items.push((REPL_SEQ_KEY.to_vec(), Some(seq.to_be_bytes().to_vec())));
+        self.batch_put(&items)
+    }
+
+    /// 最后一次 `apply_replicated` 应用的序列号，从未应用过时为0
+    pub fn last_applied_seq(&self) -> Result<u64> {
+        match self.get(REPL_SEQ_KEY, None)? {
+            Some(record) => {
+                let bytes = <[u8; 8]>::try_from(record.as_slice()).map_err(|_| {
+                    Error::Corruption("malformed replication sequence record".to_string())
+                })?;
+                Ok(u64::from_be_bytes(bytes))
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// 把批次换算为引擎写入项：校验每个键，每个键只保留最后一个操作，保持原有顺序
+    fn batch_items(&self, batch: &WriteBatch) -> Result<Vec<BatchItem>> {
+        let mut seen = HashSet::new();
+        let mut items: Vec<BatchItem> = Vec::with_capacity(batch.ops.len() + 1);
+        for op in batch.ops.iter().rev() {
+            let (key, value) = match op {
+                Op::Put(key, value) => (key, Some(value.clone())),
+                Op::Delete(key) => (key, None),
+            };
+            self.options.check_key(key)?;
+            if seen.insert(key.as_slice()) {
+                items.push((key.clone(), value));
+            }
+        }
+        items.reverse();
+        Ok(items)
+    }
+}
+
+fn token_key(token: &[u8]) -> Vec<u8> {
+    [TOKEN_PREFIX, token].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyVersion;
+
+    #[test]
+    fn test_write_batch_size_guard() {
+        let _ = std::fs::remove_dir_all("./test_data/write_batch");
+        let db = Database::new("./test_data/write_batch").unwrap();
+        db.put(b"gone", b"v").unwrap();
+
+        let mut batch = WriteBatch::new();
+        batch.put(b"a", b"1").put(b"b", b"22").delete(b"gone");
+        assert_eq!(batch.op_count(), 3);
+        assert_eq!(batch.approximate_size(), 1 + 1 + 1 + 2 + 4 + 3 * OP_OVERHEAD);
+
+        batch.max_size(8);
+        assert!(matches!(db.write_batch(&batch), Err(Error::BatchTooLarge { .. })));
+        assert!(db.get(b"a", None).unwrap().is_none());
+
+        batch.max_size(1024);
+        let root_hash = db.write_batch(&batch).unwrap();
+        assert_eq!(root_hash, db.get_root_hash().unwrap());
+        assert_eq!(db.get(b"b", None).unwrap(), Some(b"22".to_vec()));
+        assert!(db.get(b"gone", None).unwrap().is_none());
+
+        batch.clear();
+        assert!(batch.is_empty());
+        assert_eq!(batch.approximate_size(), 0);
+    }
+
+    #[test]
+    fn test_idempotency_token() {
+        let dir = "./test_data/idempotency";
+        let _ = std::fs::remove_dir_all(dir);
+        let db = Database::new(dir).unwrap();
+        let mut batch = WriteBatch::new();
+        batch.idempotency_token(b"msg-1").put(b"counter", b"1");
+        let first = db.write_batch_versioned(&batch).unwrap();
+        // 令牌记录与数据在同一次提交中写入，只产生一个版本
+        assert_eq!(first, (Version(1), db.get_root_hash().unwrap()));
+
+        db.put(b"counter", b"2").unwrap();
+        assert_eq!(db.write_batch_versioned(&batch).unwrap(), first);
+        assert_eq!(db.write_batch(&batch).unwrap(), first.1);
+        assert_eq!(db.get(b"counter", None).unwrap(), Some(b"2".to_vec()));
+
+        // 重新打开后令牌仍然有效；令牌记录不出现在扫描和差异中
+        drop(db);
+        let db = Database::new(dir).unwrap();
+        assert_eq!(db.write_batch_versioned(&batch).unwrap(), first);
+        let keys: Vec<Vec<u8>> = db.iter(..).map(|e| e.unwrap().0).collect();
+        assert_eq!(keys, [b"counter".to_vec()]);
+        assert_eq!(db.scan(..).count(), 1);
+        let changed: Vec<Vec<u8>> = db
+            .changed_keys(Version(0), first.0, b"")
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(changed, [b"counter".to_vec()]);
+        assert_eq!(db.diff(Version(0), first.0).count(), 1);
+
+        // 调用方不能直接写入或删除令牌记录
+        let record = token_key(b"msg-1");
+        assert!(matches!(db.put(b"\0idem/x", b"1"), Err(Error::InvalidKey(_))));
+        assert!(matches!(db.delete(&record), Err(Error::InvalidKey(_))));
+        let mut forged = WriteBatch::new();
+        forged.put(b"\0idem/x", b"1");
+        assert!(matches!(db.write_batch(&forged), Err(Error::InvalidKey(_))));
+        forged.clear();
+        forged.delete(&record);
+        assert!(matches!(db.write_batch(&forged), Err(Error::InvalidKey(_))));
+        let tokens = TOKEN_PREFIX.to_vec()..b"\0idem0".to_vec();
+        assert!(matches!(db.delete_range(tokens), Err(Error::InvalidKey(_))));
+        assert_eq!(db.write_batch_versioned(&batch).unwrap(), first);
+
+        let mut fresh = WriteBatch::new();
+        fresh.idempotency_token(b"x").put(b"other", b"1");
+        db.write_batch(&fresh).unwrap();
+        assert_eq!(db.get(b"other", None).unwrap(), Some(b"1".to_vec()));
+    }
+
+    #[test]
+    fn test_versioned_batch_with_concurrent_puts() {
+        let dir = "./test_data/versioned_batch_concurrent";
+        let _ = std::fs::remove_dir_all(dir);
+        let db = Database::new(dir).unwrap();
+        let done = std::sync::atomic::AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                while !done.load(std::sync::atomic::Ordering::Relaxed) {
+                    db.put(b"noise", b"x").unwrap();
+                }
+            });
+            for i in 0..20u32 {
+                let mut batch = WriteBatch::new();
+                batch.put(b"batch", &i.to_be_bytes());
+                // 返回的版本正是本次提交：其根哈希与返回的根哈希一致
+                let (version, root_hash) = db.write_batch_versioned(&batch).unwrap();
+                assert_eq!(db.root_hash_at(version).unwrap(), root_hash);
+            }
+            done.store(true, std::sync::atomic::Ordering::Relaxed);
+        });
+    }
+
+    #[test]
+    fn test_nested_batches() {
+        let _ = std::fs::remove_dir_all("./test_data/nested_batch");
+        let db = Database::new("./test_data/nested_batch").unwrap();
+        let mut batch = WriteBatch::new();
+        batch.put(b"shared", b"outer").put(b"outer", b"1");
+
+        let mut module = WriteBatch::new();
+        module.put(b"shared", b"module").delete(b"outer");
+        batch.append(module);
+
+        batch.set_savepoint();
+        batch.put(b"failed", b"x");
+        assert!(batch.rollback_to_savepoint());
+        assert!(!batch.rollback_to_savepoint());
+        assert_eq!(batch.op_count(), 4);
+
+        db.write_batch(&batch).unwrap();
+        assert_eq!(db.get(b"shared", None).unwrap(), Some(b"module".to_vec()));
+        assert!(db.get(b"outer", None).unwrap().is_none());
+        assert!(db.get(b"failed", None).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_your_writes() {
+        let _ = std::fs::remove_dir_all("./test_data/batch_get");
+        let db = Database::new("./test_data/batch_get").unwrap();
+        db.put(b"stored", b"db").unwrap();
+        db.put(b"doomed", b"db").unwrap();
+
+        let mut batch = WriteBatch::new();
+        batch.put(b"staged", b"1").put(b"staged", b"2").delete(b"doomed");
+        assert_eq!(batch.get(&db, b"staged").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(batch.get(&db, b"stored").unwrap(), Some(b"db".to_vec()));
+        assert!(batch.get(&db, b"doomed").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_iter_ops() {
+        let mut batch = WriteBatch::new();
+        batch.put(b"k", b"v").delete(b"gone");
+        let ops: Vec<BatchOp> = batch.iter().collect();
+        assert_eq!(
+            ops,
+            vec![
+                BatchOp::Put { key: b"k", value: b"v" },
+                BatchOp::Delete { key: b"gone" },
+            ]
+        );
+        assert_eq!(batch.iter().len(), 2);
+        assert_eq!((&batch).into_iter().next_back().unwrap().key(), b"gone");
+    }
+
+    #[test]
+    fn test_apply_replicated() {
+        let _ = std::fs::remove_dir_all("./test_data/replicated");
+        let db = Database::new("./test_data/replicated").unwrap();
+        let mut batch = WriteBatch::new();
+        batch.put(b"r", b"1");
+        db.apply_replicated(&batch, 1).unwrap();
+        assert_eq!(db.last_applied_seq().unwrap(), 1);
+
+        for seq in [1, 3] {
+            let err = db.apply_replicated(&batch, seq).unwrap_err();
+            assert!(matches!(err, Error::SequenceMismatch { expected: 2, .. }));
+        }
+        batch.clear();
+        batch.put(b"r", b"2");
+        db.apply_replicated(&batch, 2).unwrap();
+        assert_eq!(db.get(b"r", None).unwrap(), Some(b"2".to_vec()));
+
+        // 调用方不能伪造序列号记录，扫描和游标也看不到它
+        let forged = 7u64.to_be_bytes();
+        assert!(matches!(db.put(REPL_SEQ_KEY, &forged), Err(Error::InvalidKey(_))));
+        batch.clear();
+        batch.put(REPL_SEQ_KEY, &forged);
+        assert!(matches!(db.write_batch(&batch), Err(Error::InvalidKey(_))));
+        assert_eq!(db.last_applied_seq().unwrap(), 2);
+        let scanned: Vec<_> = db.scan(..).map(|entry| entry.unwrap().0).collect();
+        assert_eq!(scanned, vec![b"r".to_vec().into_boxed_slice()]);
+        let iterated: Vec<_> = db.iter(..).map(|entry| entry.unwrap().0).collect();
+        assert_eq!(iterated, vec![b"r".to_vec()]);
+    }
+
+    #[test]
+    fn test_batch_from_iter() {
+        let _ = std::fs::remove_dir_all("./test_data/batch_from_iter");
+        let batch = WriteBatch::from_unsorted_iter(vec![
+            (b"c".to_vec(), b"1".to_vec()),
+            (b"a".to_vec(), b"1".to_vec()),
+            (b"c".to_vec(), b"2".to_vec()),
+            (b"b".to_vec(), Vec::new()),
+        ]);
+        let ops: Vec<BatchOp> = batch.iter().collect();
+        assert_eq!(
+            ops,
+            vec![
+                BatchOp::Put { key: b"a", value: b"1" },
+                BatchOp::Put { key: b"b", value: b"" },
+                BatchOp::Put { key: b"c", value: b"2" },
+            ]
+        );
+
+        let db = Database::new("./test_data/batch_from_iter").unwrap();
+        db.put(b"b", b"old").unwrap();
+        let sorted = WriteBatch::from_sorted_iter([(&b"a"[..], &b"x"[..]), (b"b", b"")]);
+        db.write_batch(&sorted).unwrap();
+        assert_eq!(db.get(b"a", None).unwrap(), Some(b"x".to_vec()));
+        assert_eq!(db.get(b"b", None).unwrap(), Some(Vec::new()));
+        db.write_batch(&batch).unwrap();
+        assert_eq!(db.get(b"c", None).unwrap(), Some(b"2".to_vec()));
+    }
+
+    #[test]
+    fn test_batch_single_root() {
+        let _ = std::fs::remove_dir_all("./test_data/batch_root");
+        let _ = std::fs::remove_dir_all("./test_data/batch_root_sequential");
+        let db = Database::new("./test_data/batch_root").unwrap();
+        let mut batch = WriteBatch::new();
+        batch.put(b"a", b"1").put(b"b", b"2").put(b"a", b"3");
+        let root = db.write_batch(&batch).unwrap();
+        assert_eq!(db.get_root_hash().unwrap(), root);
+        // 每个键只产生一个新版本
+        assert_eq!(db.get(b"a", Some(KeyVersion(1))).unwrap(), Some(b"3".to_vec()));
+
+        let sequential = Database::new("./test_data/batch_root_sequential").unwrap();
+        sequential.put(b"a", b"3").unwrap();
+        assert_eq!(sequential.put(b"b", b"2").unwrap(), root);
+    }
+
+    #[test]
+    fn test_write_batch_with_stats() {
+        let _ = std::fs::remove_dir_all("./test_data/batch_stats");
+        let db = Database::new("./test_data/batch_stats").unwrap();
+        db.put(b"a", b"1").unwrap();
+        db.put(b"b", b"2").unwrap();
+        let mut batch = WriteBatch::new();
+        batch
+            .put(b"a", b"10")
+            .put(b"c", b"3")
+            .delete(b"b")
+            .delete(b"missing");
+        let (root, stats) = db.write_batch_with_stats(&batch).unwrap();
+        assert_eq!(db.get_root_hash().unwrap(), root);
+        assert_eq!((stats.inserted, stats.updated, stats.deleted), (1, 1, 1));
+        assert_eq!(stats.bytes_written, 3 + 2 + 1 + 7);
+        assert!(stats.nodes_touched > 0);
+
+        // 幂等令牌的记录不计入，重复提交为空操作
+        let mut batch = WriteBatch::new();
+        batch.put(b"d", b"4").idempotency_token(b"t1");
+        let (_, stats) = db.write_batch_with_stats(&batch).unwrap();
+        assert_eq!((stats.inserted, stats.bytes_written), (1, 2));
+        let (_, stats) = db.write_batch_with_stats(&batch).unwrap();
+        assert_eq!(stats, CommitStats::default());
+    }
+}
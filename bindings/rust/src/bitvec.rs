@@ -0,0 +1,92 @@
+//! 位向量
+//! 按位存储的布尔序列，用于批量存在性检查等结果
+
+/// 按位存储的布尔序列
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BitVec {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitVec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 第 `index` 位；越界时返回 `None`
+    pub fn get(&self, index: usize) -> Option<bool> {
+        (index < self.len).then(|| self.words[index / 64] & (1 << (index % 64)) != 0)
+    }
+
+    /// 设置第 `index` 位；越界时 panic
+    pub fn set(&mut self, index: usize, value: bool) {
+        assert!(
+            index < self.len,
+            "bit index {index} out of range for length {}",
+            self.len
+        );
+        let mask = 1 << (index % 64);
+        if value {
+            self.words[index / 64] |= mask;
+        } else {
+            self.words[index / 64] &= !mask;
+        }
+    }
+
+    pub fn push(&mut self, value: bool) {
+        if self.len.is_multiple_of(64) {
+            self.words.push(0);
+        }
+        self.len += 1;
+        self.set(self.len - 1, value);
+    }
+
+    /// 为真的位数
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(|i| self.words[i / 64] & (1 << (i % 64)) != 0)
+    }
+}
+
+impl FromIterator<bool> for BitVec {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut bits = BitVec::new();
+        for value in iter {
+            bits.push(value);
+        }
+        bits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bitvec() {
+        let mut bits: BitVec = (0..130).map(|i| i % 3 == 0).collect();
+        assert_eq!(bits.len(), 130);
+        assert_eq!(bits.count_ones(), 44);
+        assert_eq!(bits.get(129), Some(true));
+        assert_eq!(bits.get(130), None);
+
+        bits.set(129, false);
+        bits.set(1, true);
+        assert_eq!(bits.count_ones(), 44);
+        assert_eq!(
+            bits.iter().take(4).collect::<Vec<_>>(),
+            vec![true, true, false, true]
+        );
+    }
+}
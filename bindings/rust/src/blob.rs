@@ -0,0 +1,555 @@
+//! 大值分离存储
+//! 设置 `OpenOptions::blob_threshold` 后，超过阈值的值写入数据目录下 `blobs/` 中以其SHA-256命名的文件，
+//! 引擎中只保存引用，树节点、WAL和SSTable压实只需处理几十字节；不超过阈值的值照常存入引擎。
+//! 此时每个值（在校验尾部之前）带有一个标记字节：
+//!
+//! ```text
+//! 0x00 | 值                               内联
+//! 0x01 | SHA-256 (32) | 值长度 (8, LE)    引用
+//! ```
+//!
+//! Merkle树对引用求哈希，同样绑定了值的内容；证明与右边缘按同一阈值还原存储形式。相同的值只存一份，
+//! 读取时校验文件的摘要和长度，不一致或文件缺失返回 `Error::Corruption`。
+//!
+//! 旧版本被保留策略或 `Database::purge_key_history` 清理后，其中的大值由 `Database::collect_blobs`
+//! 回收。回收期间本句柄的写入等待；其他句柄或进程同时写入同一数据目录时可能与回收竞争，应避免。
+//! 过滤扫描（`scan_filtered`）和 `get_pinned` 需要引擎中的值本身，开启分离后返回 `Error::InvalidArgument`；
+//! 命名空间中的值不分离。
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard};
+use std::time::SystemTime;
+
+use crate::backup::{from_hex, to_hex};
+use crate::envelope;
+use crate::sha256::{sha256, Sha256};
+use crate::{
+    amdb_free_results, amdb_history_values, result_bytes, AmdbResult, Database, Error, KeyVersion,
+    Result, Root, STREAM_CHUNK_SIZE,
+};
+
+const INLINE: u8 = 0;
+const REFERENCE: u8 = 1;
+
+/// 引用的字节数
+const REFERENCE_LEN: usize = 41;
+
+const TMP_SUFFIX: &str = ".tmp";
+
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// 一次回收的结果，见 `Database::collect_blobs`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlobGcStats {
+    /// 仍被引用的文件数
+    pub blobs_kept: u64,
+    /// 删除的文件数，包括中断的写入留下的临时文件
+    pub blobs_removed: u64,
+    pub bytes_removed: u64,
+}
+
+/// 值在引擎中的存储形式（不含校验尾部）
+pub(crate) fn encode(value: &[u8], threshold: u64) -> Vec<u8> {
+    if value.len() as u64 > threshold {
+        return reference(&sha256(&[value]), value.len() as u64);
+    }
+    let mut stored = Vec::with_capacity(value.len() + 1);
+    stored.push(INLINE);
+    stored.extend_from_slice(value);
+    stored
+}
+
+fn reference(hash: &[u8; 32], len: u64) -> Vec<u8> {
+    let mut stored = Vec::with_capacity(REFERENCE_LEN);
+    stored.push(REFERENCE);
+    stored.extend_from_slice(hash);
+    stored.extend_from_slice(&len.to_le_bytes());
+    stored
+}
+
+/// 解析引用，得到 (摘要, 值长度)
+fn parse_reference(stored: &[u8]) -> Option<([u8; 32], u64)> {
+    if stored.len() != REFERENCE_LEN || stored[0] != REFERENCE {
+        return None;
+    }
+    let hash = stored[1..33].try_into().ok()?;
+    let len = u64::from_le_bytes(stored[33..].try_into().ok()?);
+    Some((hash, len))
+}
+
+fn missing(hash: &[u8; 32]) -> Error {
+    Error::Corruption(format!("blob {} is missing", to_hex(hash)))
+}
+
+fn mismatch(hash: &[u8; 32]) -> Error {
+    Error::Corruption(format!(
+        "blob {} does not match its reference",
+        to_hex(hash)
+    ))
+}
+
+/// 写到一半的临时文件，未换名时析构即删除
+struct TmpFile {
+    path: PathBuf,
+    file: File,
+    kept: bool,
+}
+
+impl TmpFile {
+    fn persist(mut self, to: &Path) -> Result<()> {
+        self.file.sync_all()?;
+        fs::rename(&self.path, to)?;
+        self.kept = true;
+        Ok(())
+    }
+}
+
+impl Drop for TmpFile {
+    fn drop(&mut self) {
+        if !self.kept {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// 流式写入读完后的值
+enum Spooled {
+    Inline(Vec<u8>),
+    /// 文件已写好，引擎中保存的引用
+    Reference(Vec<u8>),
+}
+
+/// 数据目录下的 `blobs/`，由 `Database` 与其后台线程共享
+#[derive(Debug, Clone)]
+pub(crate) struct BlobStore {
+    dir: PathBuf,
+    threshold: u64,
+    /// 写入从存储文件到提交完成期间持读锁，回收持写锁
+    gc: Arc<RwLock<()>>,
+}
+
+impl BlobStore {
+    pub(crate) fn new(data_dir: &Path, threshold: u64) -> Self {
+        BlobStore {
+            dir: data_dir.join("blobs"),
+            threshold,
+            gc: Arc::default(),
+        }
+    }
+
+    fn path(&self, hash: &[u8; 32]) -> PathBuf {
+        self.dir.join(to_hex(hash))
+    }
+
+    fn write_guard(&self) -> RwLockReadGuard<'_, ()> {
+        self.gc.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn create_tmp(&self) -> Result<TmpFile> {
+        fs::create_dir_all(&self.dir)?;
+        let n = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = self
+            .dir
+            .join(format!("{}-{}{}", process::id(), n, TMP_SUFFIX));
+        let file = File::create(&path)?;
+        Ok(TmpFile {
+            path,
+            file,
+            kept: false,
+        })
+    }
+
+    /// 存储超过阈值的值；相同内容的文件已存在时不重写
+    fn store(&self, value: &[u8]) -> Result<()> {
+        if value.len() as u64 <= self.threshold {
+            return Ok(());
+        }
+        let path = self.path(&sha256(&[value]));
+        if path.exists() {
+            return Ok(());
+        }
+        let mut tmp = self.create_tmp()?;
+        tmp.file.write_all(value)?;
+        tmp.persist(&path)
+    }
+
+    /// 把 `reader` 读完：不超过阈值时留在内存中，否则边读边写入临时文件，最后换名为其摘要
+    fn spool(
+        &self,
+        reader: &mut impl Read,
+        check_size: impl Fn(u64) -> Result<()>,
+    ) -> Result<Spooled> {
+        let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
+        let (mut head, mut tmp) = (Vec::new(), None::<TmpFile>);
+        let mut hasher = Sha256::new();
+        let mut written: u64 = 0;
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Error::Io(e)),
+            };
+            written += n as u64;
+            check_size(written)?;
+            hasher.update(&buf[..n]);
+            match &mut tmp {
+                Some(tmp) => tmp.file.write_all(&buf[..n])?,
+                None => {
+                    head.extend_from_slice(&buf[..n]);
+                    if head.len() as u64 > self.threshold {
+                        let mut file = self.create_tmp()?;
+                        file.file.write_all(&head)?;
+                        head = Vec::new();
+                        tmp = Some(file);
+                    }
+                }
+            }
+        }
+        let Some(tmp) = tmp else {
+            return Ok(Spooled::Inline(head));
+        };
+        let hash = hasher.finish();
+        let path = self.path(&hash);
+        if !path.exists() {
+            tmp.persist(&path)?;
+        }
+        Ok(Spooled::Reference(reference(&hash, written)))
+    }
+
+    /// 还原去掉校验尾部后的存储形式
+    pub(crate) fn decode(&self, mut stored: Vec<u8>) -> Result<Vec<u8>> {
+        match stored.first() {
+            None => Err(Error::Corruption("missing blob tag".to_string())),
+            Some(&INLINE) => {
+                stored.remove(0);
+                Ok(stored)
+            }
+            Some(&REFERENCE) => {
+                let (hash, len) = parse_reference(&stored)
+                    .ok_or_else(|| Error::Corruption("invalid blob reference".to_string()))?;
+                let value = match fs::read(self.path(&hash)) {
+                    Ok(value) => value,
+                    Err(e) if e.kind() == ErrorKind::NotFound => return Err(missing(&hash)),
+                    Err(e) => return Err(e.into()),
+                };
+                if value.len() as u64 != len || sha256(&[&value]) != hash {
+                    return Err(mismatch(&hash));
+                }
+                Ok(value)
+            }
+            Some(_) => Err(Error::Corruption("invalid blob tag".to_string())),
+        }
+    }
+
+    /// 把全部大值文件放入数据目录 `data_dir` 的 `blobs/`：文件写好后不再修改，以硬链接共享，
+    /// 不支持时复制。用于 `Database::fork_to` 重建的副本，其中用不到的文件由副本的回收清除
+    pub(crate) fn link_into(&self, data_dir: &Path) -> Result<()> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let dir = data_dir.join("blobs");
+        fs::create_dir_all(&dir)?;
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name();
+            if name.to_string_lossy().ends_with(TMP_SUFFIX) {
+                continue;
+            }
+            let target = dir.join(&name);
+            if fs::hard_link(entry.path(), &target).is_err() {
+                fs::copy(entry.path(), &target)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 把值分块写入 `writer`，返回字节数；文件读完才能校验，校验失败时值已全部写入
+    fn copy_to(&self, stored: Vec<u8>, writer: &mut impl Write) -> Result<u64> {
+        let Some((hash, len)) = parse_reference(&stored) else {
+            let value = self.decode(stored)?;
+            writer.write_all(&value)?;
+            return Ok(value.len() as u64);
+        };
+        let mut file = match File::open(self.path(&hash)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Err(missing(&hash)),
+            Err(e) => return Err(e.into()),
+        };
+        let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
+        let mut hasher = Sha256::new();
+        let mut copied: u64 = 0;
+        loop {
+            let n = match file.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            hasher.update(&buf[..n]);
+            writer.write_all(&buf[..n])?;
+            copied += n as u64;
+        }
+        if copied != len || hasher.finish() != hash {
+            return Err(mismatch(&hash));
+        }
+        Ok(copied)
+    }
+}
+
+impl Database {
+    /// 存储 `values` 中超过阈值的值；返回的守卫须持有到写入提交完成，未开启分离时为 `None`
+    pub(crate) fn store_blobs<'v>(
+        &self,
+        values: impl IntoIterator<Item = &'v [u8]>,
+    ) -> Result<Option<RwLockReadGuard<'_, ()>>> {
+        let Some(blobs) = &self.blobs else {
+            return Ok(None);
+        };
+        let guard = blobs.write_guard();
+        for value in values {
+            blobs.store(value)?;
+        }
+        Ok(Some(guard))
+    }
+
+    /// 需要引擎中的值本身的操作在开启分离后不可用
+    pub(crate) fn check_inline(&self, operation: &str) -> Result<()> {
+        if self.blobs.is_some() {
+            return Err(Error::InvalidArgument(format!(
+                "{} is not supported with blob separation",
+                operation
+            )));
+        }
+        Ok(())
+    }
+
+    /// 开启分离时的 `put_from_reader`
+    pub(crate) fn put_blob_from_reader(
+        &self,
+        blobs: &BlobStore,
+        key: &[u8],
+        reader: &mut impl Read,
+    ) -> Result<Root> {
+        let _gc = blobs.write_guard();
+        match blobs.spool(reader, |written| self.options.check_value_size(written))? {
+            Spooled::Inline(value) => {
+                blobs.store(&value)?;
+                self.put_sealed(key, &self.seal_value(&value))
+            }
+            Spooled::Reference(stored) if self.options.value_checksums => {
+                self.put_sealed(key, &envelope::seal(&stored))
+            }
+            Spooled::Reference(stored) => self.put_sealed(key, &stored),
+        }
+    }
+
+    /// 开启分离时的 `get_to_writer`
+    pub(crate) fn blob_to_writer(
+        &self,
+        blobs: &BlobStore,
+        key: &[u8],
+        version: Option<KeyVersion>,
+        writer: &mut impl Write,
+    ) -> Result<Option<u64>> {
+        let Some(stored) = self.get_stored(key, version)? else {
+            return Ok(None);
+        };
+        let stored = if self.options.value_checksums {
+            envelope::open(stored)?
+        } else {
+            stored
+        };
+        blobs.copy_to(stored, writer).map(Some)
+    }
+
+    /// 删除不再被任何保留版本引用的大值文件，以及中断的写入在回收开始前留下的临时文件；
+    /// 未开启分离时返回 `Error::InvalidArgument`
+    pub fn collect_blobs(&self) -> Result<BlobGcStats> {
+        let Some(blobs) = &self.blobs else {
+            return Err(Error::InvalidArgument(
+                "blob separation is not enabled".to_string(),
+            ));
+        };
+        let _gc = blobs.gc.write().unwrap_or_else(PoisonError::into_inner);
+        let started = SystemTime::now();
+        let referenced = self.blob_references()?;
+
+        let mut stats = BlobGcStats {
+            blobs_kept: 0,
+            blobs_removed: 0,
+            bytes_removed: 0,
+        };
+        let entries = match fs::read_dir(&blobs.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(stats),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if !metadata.is_file() {
+                continue;
+            }
+            let hash = from_hex(&name).and_then(|hash| <[u8; 32]>::try_from(hash).ok());
+            match hash {
+                Some(hash) if referenced.contains(&hash) => {
+                    stats.blobs_kept += 1;
+                    continue;
+                }
+                Some(_) => {}
+                // 其他进程可能正在写入回收开始之后的临时文件
+                None if name.ends_with(TMP_SUFFIX) => {
+                    if metadata.modified().map_or(true, |time| time >= started) {
+                        continue;
+                    }
+                }
+                None => continue,
+            }
+            fs::remove_file(entry.path())?;
+            stats.blobs_removed += 1;
+            stats.bytes_removed += metadata.len();
+        }
+        Ok(stats)
+    }
+
+    /// 保留的版本中引用的全部文件的摘要
+    fn blob_references(&self) -> Result<HashSet<[u8; 32]>> {
+        let prefix = [REFERENCE];
+        let (mut results, mut count) = (ptr::null_mut::<AmdbResult>(), 0);
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            amdb_history_values(
+                *handle,
+                prefix.as_ptr(),
+                prefix.len(),
+                &mut results,
+                &mut count,
+            )
+        });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        if results.is_null() {
+            return Ok(HashSet::new());
+        }
+        let values: Vec<Vec<u8>> = unsafe { std::slice::from_raw_parts(results, count) }
+            .iter()
+            .map(result_bytes)
+            .collect();
+        unsafe { amdb_free_results(results, count) };
+
+        let mut referenced = HashSet::new();
+        for value in values {
+            // 引擎直接写入的记录可能恰好以同一字节开头，解析不出引用的跳过
+            let stored = if self.options.value_checksums {
+                match envelope::open(value) {
+                    Ok(stored) => stored,
+                    Err(_) => continue,
+                }
+            } else {
+                value
+            };
+            if let Some((hash, _)) = parse_reference(&stored) {
+                referenced.insert(hash);
+            }
+        }
+        Ok(referenced)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenOptions;
+
+    fn blob_files(dir: &str) -> usize {
+        fs::read_dir(Path::new(dir).join("blobs")).map_or(0, |entries| entries.count())
+    }
+
+    #[test]
+    fn test_blob_separation() {
+        let _ = std::fs::remove_dir_all("./test_data/blobs");
+        let dir = "./test_data/blobs";
+        let db = OpenOptions::new().blob_threshold(16).open(dir).unwrap();
+        let large = vec![7u8; 1000];
+        db.put(b"small", b"v").unwrap();
+        db.put(b"large", &large).unwrap();
+        let root = db.put(b"copy", &large).unwrap();
+        // 相同的值只存一份
+        assert_eq!(blob_files(dir), 1);
+        assert_eq!(db.get(b"small", None).unwrap(), Some(b"v".to_vec()));
+        assert_eq!(db.get(b"large", None).unwrap(), Some(large.clone()));
+
+        let (value, proof) = db.get_with_proof(b"large", None).unwrap();
+        assert_eq!(value, Some(large.clone()));
+        assert!(proof.verify(&root, b"large", &large));
+        let mut out = Vec::new();
+        assert_eq!(
+            db.get_to_writer(b"large", None, &mut out).unwrap(),
+            Some(1000)
+        );
+        assert_eq!(out, large);
+
+        let streamed = vec![9u8; 3 * STREAM_CHUNK_SIZE / 2];
+        db.put_from_reader(b"streamed", &mut &streamed[..], None)
+            .unwrap();
+        assert_eq!(db.get(b"streamed", None).unwrap(), Some(streamed));
+        assert_eq!(blob_files(dir), 2);
+        assert!(matches!(
+            db.get_pinned(b"small", None),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_collect_blobs() {
+        let _ = std::fs::remove_dir_all("./test_data/blobs_gc");
+        let _ = std::fs::remove_dir_all("./test_data/blobs_disabled");
+        let dir = "./test_data/blobs_gc";
+        let db = OpenOptions::new()
+            .blob_threshold(16)
+            .value_checksums(true)
+            .open(dir)
+            .unwrap();
+        db.put(b"k", &[1u8; 100]).unwrap();
+        db.put(b"k", &[2u8; 100]).unwrap();
+        db.put(b"other", &[3u8; 100]).unwrap();
+        db.delete(b"other").unwrap();
+
+        // 历史版本仍引用旧值
+        let stats = db.collect_blobs().unwrap();
+        assert_eq!((stats.blobs_kept, stats.blobs_removed), (3, 0));
+
+        db.purge_key_history(b"k").unwrap();
+        db.purge_key_history(b"other").unwrap();
+        let stats = db.collect_blobs().unwrap();
+        assert_eq!((stats.blobs_kept, stats.blobs_removed), (1, 2));
+        assert_eq!(stats.bytes_removed, 200);
+        assert_eq!(db.get(b"k", None).unwrap(), Some(vec![2u8; 100]));
+
+        // 文件被改动或缺失时读取报告损坏
+        let hash = sha256(&[&[2u8; 100]]);
+        let path = Path::new(dir).join("blobs").join(to_hex(&hash));
+        fs::write(&path, [0u8; 100]).unwrap();
+        assert!(matches!(db.get(b"k", None), Err(Error::Corruption(_))));
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(db.get(b"k", None), Err(Error::Corruption(_))));
+
+        let plain = Database::new("./test_data/blobs_disabled").unwrap();
+        assert!(matches!(
+            plain.collect_blobs(),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+}
@@ -0,0 +1,345 @@
+//! Borsh 编码（`borsh` 特性）
+//! 为写批次、证明、变更集（`Database::diff` 给出的 `DiffEntry`）和版本元数据实现
+//! `BorshSerialize`/`BorshDeserialize`，布局与 `proto` 中的消息对应：
+//!
+//! ```text
+//! WriteBatch   = 操作数 u32 | 每个操作：标签 u8（0 写入，1 删除）| 键 Vec<u8> | [值 Vec<u8>]
+//!                | 幂等令牌 Option<Vec<u8>>
+//! SnapshotInfo = 根哈希 [u8; 32] | 导出时间 u64 | 条目数 u64
+//! Proof        = 校验和 bool | 大值阈值 Option<u64> | 根哈希 [u8; 32] | 叶子前缀 Vec<u8>
+//!                | 内部节点前缀 Vec<u8> | 键编码 u8 | 路径 Vec<u8>
+//! DiffEntry    = 标签 u8（0 新增，1 修改，2 删除）| 键 Vec<u8> | [旧值 Vec<u8>] | [新值 Vec<u8>]
+//! VersionEntry = 数据库版本 u64 | 键的版本号 u64 | 根哈希 [u8; 32] | 操作 u8（0 写入，1 删除）
+//!                | 值 Option<Vec<u8>>
+//! ```
+//!
+//! `Root` 编码为 `[u8; 32]`，`Version`、`KeyVersion` 编码为 `u64`。证明中键编码的取值和路径的格式
+//! 与 `Proof::to_bytes` 相同，解码时同样校验路径。与 `proto` 相同，批次的大小上限不属于编码内容。
+
+use std::io;
+
+use ::borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::merkle::HashScheme;
+use crate::{
+    BatchOp, DiffEntry, KeyFraming, KeyVersion, Proof, Root, SnapshotInfo, Version, VersionEntry,
+    VersionOp, WriteBatch,
+};
+
+const TAG_PUT: u8 = 0;
+const TAG_DELETE: u8 = 1;
+
+const TAG_ADDED: u8 = 0;
+const TAG_MODIFIED: u8 = 1;
+const TAG_DELETED: u8 = 2;
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl BorshSerialize for BatchOp<'_> {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        match *self {
+            BatchOp::Put { key, value } => {
+                TAG_PUT.serialize(writer)?;
+                key.serialize(writer)?;
+                value.serialize(writer)
+            }
+            BatchOp::Delete { key } => {
+                TAG_DELETE.serialize(writer)?;
+                key.serialize(writer)
+            }
+        }
+    }
+}
+
+impl BorshSerialize for WriteBatch {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let count = u32::try_from(self.op_count())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many batch ops"))?;
+        count.serialize(writer)?;
+        for op in self {
+            op.serialize(writer)?;
+        }
+        self.token().serialize(writer)
+    }
+}
+
+impl BorshDeserialize for WriteBatch {
+    fn deserialize_reader<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let count = u32::deserialize_reader(reader)?;
+        let mut batch = WriteBatch::new();
+        for _ in 0..count {
+            let tag = u8::deserialize_reader(reader)?;
+            let key = Vec::<u8>::deserialize_reader(reader)?;
+            match tag {
+                TAG_PUT => batch.put(&key, &Vec::<u8>::deserialize_reader(reader)?),
+                TAG_DELETE => batch.delete(&key),
+                _ => return Err(invalid_data(format!("unknown batch op tag {}", tag))),
+            };
+        }
+        if let Some(token) = Option::<Vec<u8>>::deserialize_reader(reader)? {
+            batch.idempotency_token(&token);
+        }
+        Ok(batch)
+    }
+}
+
+impl BorshSerialize for SnapshotInfo {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.root_hash.0.serialize(writer)?;
+        self.created_at.serialize(writer)?;
+        self.entry_count.serialize(writer)
+    }
+}
+
+impl BorshDeserialize for SnapshotInfo {
+    fn deserialize_reader<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        Ok(SnapshotInfo {
+            root_hash: Root(<[u8; 32]>::deserialize_reader(reader)?),
+            created_at: u64::deserialize_reader(reader)?,
+            entry_count: u64::deserialize_reader(reader)?,
+        })
+    }
+}
+
+impl BorshSerialize for Root {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.0.serialize(writer)
+    }
+}
+
+impl BorshDeserialize for Root {
+    fn deserialize_reader<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        <[u8; 32]>::deserialize_reader(reader).map(Root)
+    }
+}
+
+impl BorshSerialize for Version {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.0.serialize(writer)
+    }
+}
+
+impl BorshDeserialize for Version {
+    fn deserialize_reader<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        u64::deserialize_reader(reader).map(Version)
+    }
+}
+
+impl BorshSerialize for KeyVersion {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.0.serialize(writer)
+    }
+}
+
+impl BorshDeserialize for KeyVersion {
+    fn deserialize_reader<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        u64::deserialize_reader(reader).map(KeyVersion)
+    }
+}
+
+impl BorshSerialize for VersionOp {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            VersionOp::Put => TAG_PUT,
+            VersionOp::Delete => TAG_DELETE,
+        }
+        .serialize(writer)
+    }
+}
+
+impl BorshDeserialize for VersionOp {
+    fn deserialize_reader<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        match u8::deserialize_reader(reader)? {
+            TAG_PUT => Ok(VersionOp::Put),
+            TAG_DELETE => Ok(VersionOp::Delete),
+            tag => Err(invalid_data(format!("unknown version op tag {}", tag))),
+        }
+    }
+}
+
+impl BorshSerialize for VersionEntry {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.version.serialize(writer)?;
+        self.key_version.serialize(writer)?;
+        self.root_hash.serialize(writer)?;
+        self.op.serialize(writer)?;
+        self.value.serialize(writer)
+    }
+}
+
+impl BorshDeserialize for VersionEntry {
+    fn deserialize_reader<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        Ok(VersionEntry {
+            version: Version::deserialize_reader(reader)?,
+            key_version: KeyVersion::deserialize_reader(reader)?,
+            root_hash: Root::deserialize_reader(reader)?,
+            op: VersionOp::deserialize_reader(reader)?,
+            value: Option::<Vec<u8>>::deserialize_reader(reader)?,
+        })
+    }
+}
+
+impl BorshSerialize for DiffEntry {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            DiffEntry::Added { key, value } => {
+                TAG_ADDED.serialize(writer)?;
+                key.serialize(writer)?;
+                value.serialize(writer)
+            }
+            DiffEntry::Modified {
+                key,
+                old_value,
+                new_value,
+            } => {
+                TAG_MODIFIED.serialize(writer)?;
+                key.serialize(writer)?;
+                old_value.serialize(writer)?;
+                new_value.serialize(writer)
+            }
+            DiffEntry::Deleted { key, old_value } => {
+                TAG_DELETED.serialize(writer)?;
+                key.serialize(writer)?;
+                old_value.serialize(writer)
+            }
+        }
+    }
+}
+
+impl BorshDeserialize for DiffEntry {
+    fn deserialize_reader<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let tag = u8::deserialize_reader(reader)?;
+        let key = Vec::<u8>::deserialize_reader(reader)?;
+        let mut value = || Vec::<u8>::deserialize_reader(reader);
+        match tag {
+            TAG_ADDED => Ok(DiffEntry::Added { key, value: value()? }),
+            TAG_MODIFIED => Ok(DiffEntry::Modified {
+                key,
+                old_value: value()?,
+                new_value: value()?,
+            }),
+            TAG_DELETED => Ok(DiffEntry::Deleted {
+                key,
+                old_value: value()?,
+            }),
+            _ => Err(invalid_data(format!("unknown diff entry tag {}", tag))),
+        }
+    }
+}
+
+impl BorshSerialize for Proof {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.checksums.serialize(writer)?;
+        self.blob_threshold.serialize(writer)?;
+        self.root_hash.serialize(writer)?;
+        self.scheme.leaf_prefix.serialize(writer)?;
+        self.scheme.node_prefix.serialize(writer)?;
+        self.scheme.key_framing.as_byte().serialize(writer)?;
+        self.path.serialize(writer)
+    }
+}
+
+impl BorshDeserialize for Proof {
+    fn deserialize_reader<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let checksums = bool::deserialize_reader(reader)?;
+        let blob_threshold = Option::<u64>::deserialize_reader(reader)?;
+        let root_hash = Root::deserialize_reader(reader)?;
+        let leaf_prefix = Vec::<u8>::deserialize_reader(reader)?;
+        let node_prefix = Vec::<u8>::deserialize_reader(reader)?;
+        let framing = u8::deserialize_reader(reader)?;
+        let key_framing = KeyFraming::from_byte(framing)
+            .ok_or_else(|| invalid_data(format!("unknown key framing {}", framing)))?;
+        let path = Vec::<u8>::deserialize_reader(reader)?;
+        let scheme = HashScheme {
+            leaf_prefix,
+            node_prefix,
+            key_framing,
+        };
+        Proof::new(checksums, blob_threshold, root_hash, scheme, path)
+            .map_err(|e| invalid_data(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_borsh_round_trip() {
+        let mut batch = WriteBatch::new();
+        batch
+            .idempotency_token(b"t")
+            .put(b"k", b"v")
+            .delete(b"gone");
+
+        let bytes = ::borsh::to_vec(&batch).unwrap();
+        let decoded = WriteBatch::try_from_slice(&bytes).unwrap();
+        assert!(decoded.iter().eq(batch.iter()));
+        assert_eq!(decoded.token(), Some(&b"t"[..]));
+
+        let mut bad = bytes.clone();
+        bad[4] = 9;
+        assert!(WriteBatch::try_from_slice(&bad).is_err());
+
+        let info = SnapshotInfo {
+            root_hash: Root([7; 32]),
+            created_at: 1_700_000_000,
+            entry_count: 3,
+        };
+        let bytes = ::borsh::to_vec(&info).unwrap();
+        assert_eq!(bytes.len(), 48);
+        assert_eq!(SnapshotInfo::try_from_slice(&bytes).unwrap(), info);
+    }
+
+    #[test]
+    fn test_borsh_proof_and_metadata() {
+        let _ = std::fs::remove_dir_all("./test_data/borsh_codec");
+        let db = crate::Database::new("./test_data/borsh_codec").unwrap();
+        db.put(b"a", b"1").unwrap();
+        db.put(b"b", b"2").unwrap();
+        db.delete(b"a").unwrap();
+        let root = db.get_root_hash().unwrap();
+
+        let (_, proof) = db.get_with_proof(b"b", None).unwrap();
+        let bytes = ::borsh::to_vec(&proof).unwrap();
+        let decoded = Proof::try_from_slice(&bytes).unwrap();
+        assert_eq!(decoded, proof);
+        assert!(decoded.verify(&root, b"b", b"2"));
+        // 路径被截断时解码失败
+        assert!(Proof::try_from_slice(&bytes[..bytes.len() - 1]).is_err());
+
+        let changes: Vec<DiffEntry> = db
+            .diff(Version(1), Version(3))
+            .collect::<crate::Result<_>>()
+            .unwrap();
+        let changes = [
+            changes,
+            vec![DiffEntry::Modified {
+                key: b"k".to_vec(),
+                old_value: b"old".to_vec(),
+                new_value: b"new".to_vec(),
+            }],
+        ]
+        .concat();
+        let bytes = ::borsh::to_vec(&changes).unwrap();
+        assert_eq!(Vec::<DiffEntry>::try_from_slice(&bytes).unwrap(), changes);
+
+        let history: Vec<VersionEntry> = db.history(b"a").collect::<crate::Result<_>>().unwrap();
+        assert_eq!(history.len(), 2);
+        let bytes = ::borsh::to_vec(&history).unwrap();
+        assert_eq!(Vec::<VersionEntry>::try_from_slice(&bytes).unwrap(), history);
+        let mut bad = ::borsh::to_vec(&history[1]).unwrap();
+        bad[48] = 7;
+        assert!(VersionEntry::try_from_slice(&bad).is_err());
+
+        for version in [Version(3), Version(u64::MAX)] {
+            let bytes = ::borsh::to_vec(&version).unwrap();
+            assert_eq!(Version::try_from_slice(&bytes).unwrap(), version);
+        }
+        let bytes = ::borsh::to_vec(&KeyVersion(2)).unwrap();
+        assert_eq!(KeyVersion::try_from_slice(&bytes).unwrap(), KeyVersion(2));
+        assert_eq!(Root::try_from_slice(&::borsh::to_vec(&root).unwrap()).unwrap(), root);
+    }
+}
@@ -0,0 +1,322 @@
+//! 临时分支
+//! `Database::branch` 从某个数据库版本开出一条可写的版本线：分支上的写入只暂存在内存中，
+//! 读取先查分支的写入，其余读取起始版本的快照，不复制起始版本的状态。每次写入是分支上的一个新版本，
+//! 根哈希与同样的写入提交到起始版本之上的结果相同，可用于试执行或从某个高度分叉出测试网。
+//! 分支最终 `discard` 丢弃，或在数据库没有新提交时 `promote` 为最新状态。
+//! 数据库已有新提交时用 `merge_into_head` 把分支的写入合并到最新状态：起始版本之后两边都写过、
+//! 且结果不同的键作为冲突返回，不覆盖数据库中的值。
+//!
+//! 分支持有起始版本的快照，存活期间保留策略不会删除该版本可见的值。
+
+use std::collections::BTreeMap;
+
+use crate::{BatchOp, Database, Error, Result, Root, Snapshot, Version, WriteBatch};
+
+/// 分支与数据库最新状态都改变过且结果不同的键；各值为 `None` 表示不存在或已删除
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    pub key: Vec<u8>,
+    /// 分支起始版本中的值
+    pub base: Option<Vec<u8>>,
+    /// 数据库最新状态中的值
+    pub head: Option<Vec<u8>>,
+    /// 分支上的值
+    pub branch: Option<Vec<u8>>,
+}
+
+/// `Branch::merge_into_head` 的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeOutcome {
+    /// 已提交合并，分支改为从合并后的数据库版本开始；两边写入相同时不产生新版本
+    Merged { version: Version, root_hash: Root },
+    /// 有冲突，没有写入任何数据，分支不变；按键排序
+    Conflicts(Vec<MergeConflict>),
+}
+
+/// 从某个数据库版本开出的可写分支，见 `Database::branch`；析构时同 `discard`
+pub struct Branch<'a> {
+    db: &'a Database,
+    /// 起始版本的快照；版本0是空状态，没有快照
+    base: Option<Snapshot<'a>>,
+    base_version: Version,
+    /// 分支上的全部写入，`None` 表示删除
+    writes: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+    /// 第i个是分支版本 `base_version + i` 的根哈希
+    roots: Vec<Root>,
+}
+
+impl Database {
+    /// 从数据库版本 `from_version` 开出一个分支；版本0是新数据库的空状态。
+    /// 没有该版本或已被清理时返回 `Error::NotFound`
+    pub fn branch(&self, from_version: Version) -> Result<Branch<'_>> {
+        let base = match from_version {
+            Version(0) => None,
+            version => Some(self.snapshot_at(version)?),
+        };
+        let root = match &base {
+            Some(snapshot) => snapshot.root_hash(),
+            None => self.batch_root_hash_at(&WriteBatch::new(), Version(0))?,
+        };
+        Ok(Branch {
+            db: self,
+            base,
+            base_version: from_version,
+            writes: BTreeMap::new(),
+            roots: vec![root],
+        })
+    }
+}
+
+impl<'a> Branch<'a> {
+    /// 分支的起始版本
+    pub fn base_version(&self) -> Version {
+        self.base_version
+    }
+
+    /// 分支的最新版本：起始版本加上分支上的写入次数
+    pub fn version(&self) -> Version {
+        Version(self.base_version.0 + self.roots.len() as u64 - 1)
+    }
+
+    /// 分支最新版本的根哈希
+    pub fn root_hash(&self) -> Root {
+        self.roots[self.roots.len() - 1]
+    }
+
+    /// 分支版本 `version` 的根哈希；不在起始版本到最新版本之间时返回 `None`
+    pub fn root_hash_at(&self, version: Version) -> Option<Root> {
+        let index = version.0.checked_sub(self.base_version.0)?;
+        self.roots.get(usize::try_from(index).ok()?).copied()
+    }
+
+    /// 读取键在分支最新版本中的值；键尚未写入或已删除时返回 `None`
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        match self.writes.get(key) {
+            Some(staged) => Ok(staged.clone()),
+            None => self.base_get(key),
+        }
+    }
+
+    /// 在分支上写入一个键，作为分支的一个新版本；返回写入后的根哈希
+    pub fn put(&mut self, key: &[u8], value: &[u8]) -> Result<Root> {
+        let mut batch = WriteBatch::new();
+        batch.put(key, value);
+        self.write_batch(&batch)
+    }
+
+    pub fn delete(&mut self, key: &[u8]) -> Result<Root> {
+        let mut batch = WriteBatch::new();
+        batch.delete(key);
+        self.write_batch(&batch)
+    }
+
+    /// 把 `batch` 作为分支的一个新版本写入，返回写入后的根哈希；校验错误与 `Database::write_batch`
+    /// 相同，失败时分支不变。幂等令牌被忽略
+    pub fn write_batch(&mut self, batch: &WriteBatch) -> Result<Root> {
+        let mut writes = self.writes.clone();
+        for op in batch.iter() {
+            let (key, value) = match op {
+                BatchOp::Put { key, value } => (key, Some(value.to_vec())),
+                BatchOp::Delete { key } => (key, None),
+            };
+            writes.insert(key.to_vec(), value);
+        }
+        let root = self
+            .db
+            .batch_root_hash_at(&changeset(&writes), self.base_version)?;
+        self.writes = writes;
+        self.roots.push(root);
+        Ok(root)
+    }
+
+    /// 分支上相对起始版本写入过的键及其最新值，按键排序；`None` 表示删除
+    pub fn changes(&self) -> impl Iterator<Item = (&[u8], Option<&[u8]>)> {
+        self.writes
+            .iter()
+            .map(|(key, value)| (key.as_slice(), value.as_deref()))
+    }
+
+    /// 丢弃分支，数据库不变
+    pub fn discard(self) {}
+
+    /// 把分支上的全部写入作为一个批次提交到数据库，返回提交后的根哈希，与分支的 `root_hash` 相同。
+    /// 只在数据库最新版本仍是分支的起始版本时提交，否则返回 `Error::Diverged`，不写入任何数据；
+    /// 失败时分支同样结束
+    pub fn promote(self) -> Result<Root> {
+        let _writes = self.db.write_lock();
+        let head = self.db.state_version()?;
+        if head != self.base_version {
+            return Err(Error::Diverged {
+                base: self.base_version,
+                head,
+            });
+        }
+        self.db.write_batch_locked(&changeset(&self.writes))
+    }
+
+    /// 把分支上的写入合并到数据库的最新状态并提交为一个批次。起始版本之后数据库也写过的键，
+    /// 最新值与分支相同的不再写入，与起始版本相同的（写回原值）按分支的值写入，其余的是冲突：
+    /// 有冲突时全部返回而不写入任何数据，可在分支上改写这些键后重试
+    pub fn merge_into_head(&mut self) -> Result<MergeOutcome> {
+        let _writes = self.db.write_lock();
+        let head = self.db.state_version()?;
+        let mut changed = Vec::new();
+        for key in self.db.changed_keys(self.base_version, head, b"") {
+            changed.push(key?);
+        }
+        let mut pending = self.writes.clone();
+        let mut conflicts = Vec::new();
+        for key in changed {
+            let Some(branch) = self.writes.get(&key) else {
+                continue;
+            };
+            let head_value = self.db.get(&key, None)?;
+            if head_value == *branch {
+                pending.remove(&key);
+                continue;
+            }
+            let base = self.base_get(&key)?;
+            if head_value != base {
+                conflicts.push(MergeConflict {
+                    key,
+                    base,
+                    head: head_value,
+                    branch: branch.clone(),
+                });
+            }
+        }
+        if !conflicts.is_empty() {
+            return Ok(MergeOutcome::Conflicts(conflicts));
+        }
+
+        let root_hash = if pending.is_empty() {
+            self.db.get_root_hash()?
+        } else {
+            self.db.write_batch_locked(&changeset(&pending))?
+        };
+        let version = self.db.state_version()?;
+        self.base = match version {
+            Version(0) => None,
+            version => Some(self.db.snapshot_at(version)?),
+        };
+        self.base_version = version;
+        self.writes.clear();
+        self.roots = vec![root_hash];
+        Ok(MergeOutcome::Merged { version, root_hash })
+    }
+
+    /// 键在起始版本中的值
+    fn base_get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        match &self.base {
+            Some(snapshot) => snapshot.get(key),
+            None => Ok(None),
+        }
+    }
+}
+
+/// 分支全部写入的批次
+fn changeset(writes: &BTreeMap<Vec<u8>, Option<Vec<u8>>>) -> WriteBatch {
+    let mut batch = WriteBatch::new();
+    for (key, value) in writes {
+        match value {
+            Some(value) => batch.put(key, value),
+            None => batch.delete(key),
+        };
+    }
+    batch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_branch() {
+        let _ = std::fs::remove_dir_all("./test_data/branch");
+        let db = Database::new("./test_data/branch").unwrap();
+        db.put(b"a", b"1").unwrap();
+        db.put(b"b", b"2").unwrap();
+        db.put(b"a", b"3").unwrap();
+
+        // 从旧版本开出分支，读取起始版本的状态
+        let mut branch = db.branch(Version(2)).unwrap();
+        assert_eq!(branch.get(b"a").unwrap(), Some(b"1".to_vec()));
+        branch.put(b"c", b"x").unwrap();
+        let root = branch.delete(b"b").unwrap();
+        assert_eq!((branch.version(), branch.root_hash()), (Version(4), root));
+        assert_eq!(branch.root_hash_at(Version(2)), Some(db.root_hash_at(Version(2)).unwrap()));
+        assert_eq!(branch.root_hash_at(Version(5)), None);
+        assert_eq!(branch.get(b"b").unwrap(), None);
+        assert_eq!(branch.changes().count(), 2);
+        branch.discard();
+        assert_eq!(db.get(b"c", None).unwrap(), None);
+
+        // 数据库已有新提交时拒绝提升
+        let mut branch = db.branch(Version(2)).unwrap();
+        branch.put(b"c", b"x").unwrap();
+        assert!(matches!(
+            branch.promote(),
+            Err(Error::Diverged { base: Version(2), head: Version(3) })
+        ));
+
+        let mut branch = db.branch(Version(3)).unwrap();
+        let mut batch = WriteBatch::new();
+        batch.put(b"c", b"x").put(b"d", b"");
+        let root = branch.write_batch(&batch).unwrap();
+        assert_eq!(branch.get(b"d").unwrap(), Some(Vec::new()));
+        assert_eq!(branch.promote().unwrap(), root);
+        assert_eq!(db.get_root_hash().unwrap(), root);
+        assert_eq!(db.get(b"c", None).unwrap(), Some(b"x".to_vec()));
+
+        let mut empty = db.branch(Version(0)).unwrap();
+        assert_eq!(empty.get(b"a").unwrap(), None);
+        empty.put(b"a", b"1").unwrap();
+        assert_eq!(empty.root_hash(), db.root_hash_at(Version(1)).unwrap());
+        assert!(matches!(db.branch(Version(100)), Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn test_merge_into_head() {
+        let _ = std::fs::remove_dir_all("./test_data/branch_merge");
+        let db = Database::new("./test_data/branch_merge").unwrap();
+        db.put(b"a", b"1").unwrap();
+        db.put(b"b", b"1").unwrap();
+        let mut branch = db.branch(Version(2)).unwrap();
+        branch.put(b"a", b"2").unwrap();
+        branch.put(b"b", b"2").unwrap();
+        branch.put(b"c", b"2").unwrap();
+        // 数据库在分支之后改写 a，写回 b 的原值，并写入与分支相同的 c
+        db.put(b"a", b"3").unwrap();
+        db.put(b"b", b"1").unwrap();
+        db.put(b"c", b"2").unwrap();
+
+        let outcome = branch.merge_into_head().unwrap();
+        assert_eq!(
+            outcome,
+            MergeOutcome::Conflicts(vec![MergeConflict {
+                key: b"a".to_vec(),
+                base: Some(b"1".to_vec()),
+                head: Some(b"3".to_vec()),
+                branch: Some(b"2".to_vec()),
+            }])
+        );
+        assert_eq!(db.state_version().unwrap(), 5);
+
+        // 在分支上解决冲突后重试
+        branch.put(b"a", b"3").unwrap();
+        let MergeOutcome::Merged { version, root_hash } = branch.merge_into_head().unwrap() else {
+            panic!("merge should succeed");
+        };
+        assert_eq!((version, root_hash), (Version(6), db.get_root_hash().unwrap()));
+        assert_eq!(db.get(b"b", None).unwrap(), Some(b"2".to_vec()));
+        assert_eq!((branch.base_version(), branch.changes().count()), (Version(6), 0));
+
+        // 两边写入相同时不产生新版本
+        branch.put(b"d", b"1").unwrap();
+        db.put(b"d", b"1").unwrap();
+        assert!(matches!(
+            branch.merge_into_head().unwrap(),
+            MergeOutcome::Merged { version: Version(7), .. }
+        ));
+    }
+}
@@ -0,0 +1,750 @@
+//! Rust层类型的C接口（`capi` 特性）
+//! 把 `WriteBatch` 和 `Proof` 以不透明指针导出给C/C++，与 `amdb.h` 的句柄配合使用。
+//! `amdb_rs_db_*`/`amdb_rs_iter_*` 另外导出 `Database` 和游标迭代器本身，供 Python（cffi）、
+//! Node（N-API）等绑定直接建立在Rust层之上；ABI的不兼容变更会增加 `AMDB_RS_ABI_VERSION`。
+//! 头文件 `include/amdb_rs.h` 由 cbindgen 按 `cbindgen.toml` 从本模块生成，修改导出函数后需重新生成：
+//!
+//! ```text
+//! cbindgen --config cbindgen.toml --crate amdb --output include/amdb_rs.h
+//! ```
+//!
+//! 函数返回 `amdb.h` 中的状态码；只在Rust侧产生的错误（如 `Error::InvalidKey`）返回 `AMDB_ERROR`。
+//! Rust侧的panic不会展开越过C边界：返回状态码的函数此时返回 `AMDB_ERROR`，借用的句柄不会被关闭。
+//! 传入的 `amdb.h` 句柄按默认的 `OpenOptions` 使用，不会被关闭。
+//! 输出的字节串由Rust分配，须用 `amdb_rs_bytes_free` 释放，不能用 `amdb_free_result`。
+
+use std::ffi::CStr;
+use std::mem::ManuallyDrop;
+use std::ops::Bound;
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use crate::ffi::{AMDB_ERROR, AMDB_INVALID_ARG, AMDB_NOT_FOUND, AMDB_OK};
+use crate::{AmdbHandle, CursorOptions, Database, Iter, Proof, Result, Root, WriteBatch};
+
+/// ABI版本，不兼容的变更时加1
+pub const AMDB_RS_ABI_VERSION: u32 = 1;
+
+/// Rust分配的字节串，用 `amdb_rs_bytes_free` 释放
+#[repr(C)]
+pub struct AmdbRsBytes {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl AmdbRsBytes {
+    fn empty() -> Self {
+        AmdbRsBytes {
+            data: ptr::null_mut(),
+            len: 0,
+        }
+    }
+
+    fn new(bytes: Vec<u8>) -> Self {
+        let len = bytes.len();
+        AmdbRsBytes {
+            data: Box::into_raw(bytes.into_boxed_slice()) as *mut u8,
+            len,
+        }
+    }
+}
+
+/// 导出给C的游标迭代器；借用的 `Database` 须比它活得更久
+pub struct DbIter(Iter<'static>);
+
+fn status(result: Result<()>) -> c_int {
+    match result {
+        Ok(()) => AMDB_OK,
+        Err(e) => e.raw_code().unwrap_or(AMDB_ERROR),
+    }
+}
+
+/// 空指针只允许与长度0一起出现
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    if data.is_null() {
+        return (len == 0).then_some(&[]);
+    }
+    Some(slice::from_raw_parts(data, len))
+}
+
+/// 捕获 `f` 中的panic，不让其展开越过C边界；panic时返回 `on_panic`
+fn guarded<T>(on_panic: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(on_panic)
+}
+
+/// 借用C句柄的 `Database`；析构时（包括panic展开时）交还句柄而不关闭
+struct Borrowed(ManuallyDrop<Database>);
+
+impl Drop for Borrowed {
+    fn drop(&mut self) {
+        let db = unsafe { ManuallyDrop::take(&mut self.0) };
+        let _ = db.into_raw();
+    }
+}
+
+/// 以借用方式在C句柄上执行 `f`，不关闭句柄
+unsafe fn with_database<T>(handle: *mut AmdbHandle, f: impl FnOnce(&Database) -> T) -> T {
+    let db = Borrowed(ManuallyDrop::new(Database::from_raw(handle)));
+    f(&db.0)
+}
+
+fn commit(db: &Database, batch: &WriteBatch, root_hash: *mut u8) -> Result<()> {
+    let root = db.write_batch(batch)?;
+    unsafe { slice::from_raw_parts_mut(root_hash, 32) }.copy_from_slice(&*root);
+    Ok(())
+}
+
+fn latest_proof(db: &Database, key: &[u8], proof: *mut *mut Proof) -> Result<()> {
+    let (_, found) = db.get_with_proof(key, None)?;
+    unsafe { *proof = Box::into_raw(Box::new(found)) };
+    Ok(())
+}
+
+/// 运行时的ABI版本，绑定可与编译时的 `AMDB_RS_ABI_VERSION` 比较
+#[no_mangle]
+pub extern "C" fn amdb_rs_abi_version() -> u32 {
+    AMDB_RS_ABI_VERSION
+}
+
+/// 释放Rust分配的字节串并清空；空字节串不做任何事
+///
+/// # Safety
+///
+/// `bytes` 必须为空指针，或由本模块的函数填充且未释放。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_bytes_free(bytes: *mut AmdbRsBytes) {
+    let Some(bytes) = bytes.as_mut() else {
+        return;
+    };
+    if !bytes.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            bytes.data, bytes.len,
+        )));
+    }
+    *bytes = AmdbRsBytes::empty();
+}
+
+/// 以默认选项打开数据库（见 `Database::new`），用 `amdb_rs_db_close` 关闭
+///
+/// # Safety
+///
+/// `data_dir` 须是以NUL结尾的UTF-8路径；`db` 须可写。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_db_open(data_dir: *const c_char, db: *mut *mut Database) -> c_int {
+    guarded(AMDB_ERROR, || {
+        if data_dir.is_null() || db.is_null() {
+            return AMDB_INVALID_ARG;
+        }
+        let Ok(data_dir) = CStr::from_ptr(data_dir).to_str() else {
+            return AMDB_INVALID_ARG;
+        };
+        status(Database::new(data_dir).map(|opened| {
+            *db = Box::into_raw(Box::new(opened));
+        }))
+    })
+}
+
+/// 关闭并释放数据库（见 `Database::close`）；即使关闭失败也会释放，空指针不做任何事
+///
+/// # Safety
+///
+/// `db` 必须来自 `amdb_rs_db_open` 且未释放；其上的迭代器须已释放。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_db_close(db: *mut Database) -> c_int {
+    guarded(AMDB_ERROR, || {
+        if db.is_null() {
+            return AMDB_OK;
+        }
+        let db = Box::from_raw(db);
+        status(db.close())
+    })
+}
+
+/// 读取键的最新值；键不存在或已删除时返回 `AMDB_NOT_FOUND`
+///
+/// # Safety
+///
+/// `db` 必须有效；`key` 须指向 `key_len` 字节的可读内存；`value` 须可写。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_db_get(
+    db: *const Database,
+    key: *const u8,
+    key_len: usize,
+    value: *mut AmdbRsBytes,
+) -> c_int {
+    guarded(AMDB_ERROR, || {
+        let (Some(db), Some(key), Some(value)) = (db.as_ref(), bytes(key, key_len), value.as_mut())
+        else {
+            return AMDB_INVALID_ARG;
+        };
+        *value = AmdbRsBytes::empty();
+        match db.get(key, None) {
+            Ok(Some(found)) => {
+                *value = AmdbRsBytes::new(found);
+                AMDB_OK
+            }
+            Ok(None) => AMDB_NOT_FOUND,
+            Err(e) => status(Err(e)),
+        }
+    })
+}
+
+/// 写入键值对，`root_hash` 输出写入后的根哈希（32字节）
+///
+/// # Safety
+///
+/// `db` 必须有效；`key`/`value` 须指向对应长度的可读内存；`root_hash` 须可写32字节。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_db_put(
+    db: *const Database,
+    key: *const u8,
+    key_len: usize,
+    value: *const u8,
+    value_len: usize,
+    root_hash: *mut u8,
+) -> c_int {
+    guarded(AMDB_ERROR, || {
+        let (Some(db), Some(key), Some(value)) =
+            (db.as_ref(), bytes(key, key_len), bytes(value, value_len))
+        else {
+            return AMDB_INVALID_ARG;
+        };
+        if root_hash.is_null() {
+            return AMDB_INVALID_ARG;
+        }
+        status(db.put(key, value).map(|root| {
+            slice::from_raw_parts_mut(root_hash, 32).copy_from_slice(&*root);
+        }))
+    })
+}
+
+/// 删除键
+///
+/// # Safety
+///
+/// `db` 必须有效；`key` 须指向 `key_len` 字节的可读内存。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_db_delete(
+    db: *const Database,
+    key: *const u8,
+    key_len: usize,
+) -> c_int {
+    guarded(AMDB_ERROR, || {
+        let (Some(db), Some(key)) = (db.as_ref(), bytes(key, key_len)) else {
+            return AMDB_INVALID_ARG;
+        };
+        status(db.delete(key))
+    })
+}
+
+/// 同 `amdb_rs_batch_commit`，提交到 `amdb_rs_db_open` 打开的数据库
+///
+/// # Safety
+///
+/// `db` 必须有效；`batch` 必须有效；`root_hash` 须可写32字节。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_db_commit(
+    db: *const Database,
+    batch: *const WriteBatch,
+    root_hash: *mut u8,
+) -> c_int {
+    guarded(AMDB_ERROR, || {
+        let (Some(db), Some(batch)) = (db.as_ref(), batch.as_ref()) else {
+            return AMDB_INVALID_ARG;
+        };
+        if root_hash.is_null() {
+            return AMDB_INVALID_ARG;
+        }
+        status(commit(db, batch, root_hash))
+    })
+}
+
+/// 同 `amdb_rs_proof_get`，读取 `amdb_rs_db_open` 打开的数据库
+///
+/// # Safety
+///
+/// `db` 必须有效；`key` 须指向 `key_len` 字节的可读内存；`proof` 须可写。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_db_proof_get(
+    db: *const Database,
+    key: *const u8,
+    key_len: usize,
+    proof: *mut *mut Proof,
+) -> c_int {
+    guarded(AMDB_ERROR, || {
+        let (Some(db), Some(key)) = (db.as_ref(), bytes(key, key_len)) else {
+            return AMDB_INVALID_ARG;
+        };
+        if proof.is_null() {
+            return AMDB_INVALID_ARG;
+        }
+        status(latest_proof(db, key, proof))
+    })
+}
+
+/// 迭代 [start, end) 内的键值对（见 `Database::iter_with`），长度为0的边界表示无界；
+/// `reverse` 为真时按键的降序迭代。用 `amdb_rs_iter_free` 释放
+///
+/// # Safety
+///
+/// `db` 必须有效且在迭代器释放前不被关闭；`start`/`end` 须指向对应长度的可读内存；`iter` 须可写。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_iter_new(
+    db: *const Database,
+    start: *const u8,
+    start_len: usize,
+    end: *const u8,
+    end_len: usize,
+    reverse: bool,
+    iter: *mut *mut DbIter,
+) -> c_int {
+    guarded(AMDB_ERROR, || {
+        let (Some(db), Some(start), Some(end)) =
+            (db.as_ref(), bytes(start, start_len), bytes(end, end_len))
+        else {
+            return AMDB_INVALID_ARG;
+        };
+        if iter.is_null() {
+            return AMDB_INVALID_ARG;
+        }
+        let bound = |key: &[u8], bound: fn(Vec<u8>) -> Bound<Vec<u8>>| {
+            if key.is_empty() {
+                Bound::Unbounded
+            } else {
+                bound(key.to_vec())
+            }
+        };
+        let range = (bound(start, Bound::Included), bound(end, Bound::Excluded));
+        let mut options = CursorOptions::new();
+        options.reverse(reverse);
+        *iter = Box::into_raw(Box::new(DbIter(db.iter_with(range, &options))));
+        AMDB_OK
+    })
+}
+
+/// 读取下一个键值对；迭代结束时返回 `AMDB_NOT_FOUND`
+///
+/// # Safety
+///
+/// `iter` 必须有效；`key`/`value` 须可写。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_iter_next(
+    iter: *mut DbIter,
+    key: *mut AmdbRsBytes,
+    value: *mut AmdbRsBytes,
+) -> c_int {
+    guarded(AMDB_ERROR, || {
+        let (Some(iter), Some(key), Some(value)) = (iter.as_mut(), key.as_mut(), value.as_mut())
+        else {
+            return AMDB_INVALID_ARG;
+        };
+        (*key, *value) = (AmdbRsBytes::empty(), AmdbRsBytes::empty());
+        match iter.0.next() {
+            Some(Ok((k, v))) => {
+                (*key, *value) = (AmdbRsBytes::new(k), AmdbRsBytes::new(v));
+                AMDB_OK
+            }
+            Some(Err(e)) => status(Err(e)),
+            None => AMDB_NOT_FOUND,
+        }
+    })
+}
+
+/// 释放迭代器；空指针不做任何事
+///
+/// # Safety
+///
+/// `iter` 必须来自 `amdb_rs_iter_new` 且未释放。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_iter_free(iter: *mut DbIter) {
+    if !iter.is_null() {
+        drop(Box::from_raw(iter));
+    }
+}
+
+/// 创建空批次，用 `amdb_rs_batch_free` 释放
+#[no_mangle]
+pub extern "C" fn amdb_rs_batch_new() -> *mut WriteBatch {
+    Box::into_raw(Box::new(WriteBatch::new()))
+}
+
+/// 释放批次；空指针不做任何事
+///
+/// # Safety
+///
+/// `batch` 必须来自 `amdb_rs_batch_new` 且未释放。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_batch_free(batch: *mut WriteBatch) {
+    if !batch.is_null() {
+        drop(Box::from_raw(batch));
+    }
+}
+
+/// 向批次追加写入；空值写入为空值，删除用 `amdb_rs_batch_delete`
+///
+/// # Safety
+///
+/// `batch` 必须有效；`key`/`value` 须指向对应长度的可读内存。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_batch_put(
+    batch: *mut WriteBatch,
+    key: *const u8,
+    key_len: usize,
+    value: *const u8,
+    value_len: usize,
+) -> c_int {
+    guarded(AMDB_ERROR, || {
+        let (Some(batch), Some(key), Some(value)) =
+            (batch.as_mut(), bytes(key, key_len), bytes(value, value_len))
+        else {
+            return AMDB_INVALID_ARG;
+        };
+        batch.put(key, value);
+        AMDB_OK
+    })
+}
+
+/// 向批次追加删除
+///
+/// # Safety
+///
+/// `batch` 必须有效；`key` 须指向 `key_len` 字节的可读内存。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_batch_delete(
+    batch: *mut WriteBatch,
+    key: *const u8,
+    key_len: usize,
+) -> c_int {
+    guarded(AMDB_ERROR, || {
+        let (Some(batch), Some(key)) = (batch.as_mut(), bytes(key, key_len)) else {
+            return AMDB_INVALID_ARG;
+        };
+        batch.delete(key);
+        AMDB_OK
+    })
+}
+
+/// 批次中的操作数
+///
+/// # Safety
+///
+/// `batch` 必须有效。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_batch_len(batch: *const WriteBatch) -> usize {
+    batch.as_ref().map_or(0, WriteBatch::op_count)
+}
+
+/// 原子地提交批次（见 `Database::write_batch`），`root_hash` 输出提交后的根哈希（32字节）；
+/// 批次保持不变，可继续使用或释放
+///
+/// # Safety
+///
+/// `handle` 必须是 `amdb_init` 得到的有效句柄；`batch` 必须有效；`root_hash` 须可写32字节。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_batch_commit(
+    handle: *mut AmdbHandle,
+    batch: *const WriteBatch,
+    root_hash: *mut u8,
+) -> c_int {
+    guarded(AMDB_ERROR, || {
+        let Some(batch) = batch.as_ref() else {
+            return AMDB_INVALID_ARG;
+        };
+        if handle.is_null() || root_hash.is_null() {
+            return AMDB_INVALID_ARG;
+        }
+        status(with_database(handle, |db| commit(db, batch, root_hash)))
+    })
+}
+
+/// 读取键的最新证明（见 `Database::get_with_proof`），用 `amdb_rs_proof_free` 释放
+///
+/// # Safety
+///
+/// `handle` 必须是有效句柄；`key` 须指向 `key_len` 字节的可读内存；`proof` 须可写。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_proof_get(
+    handle: *mut AmdbHandle,
+    key: *const u8,
+    key_len: usize,
+    proof: *mut *mut Proof,
+) -> c_int {
+    guarded(AMDB_ERROR, || {
+        let Some(key) = bytes(key, key_len) else {
+            return AMDB_INVALID_ARG;
+        };
+        if handle.is_null() || proof.is_null() {
+            return AMDB_INVALID_ARG;
+        }
+        status(with_database(handle, |db| latest_proof(db, key, proof)))
+    })
+}
+
+/// 解析 `amdb_rs_proof_encode` 的输出，格式不合法时返回 `AMDB_ERROR`
+///
+/// # Safety
+///
+/// `data` 须指向 `len` 字节的可读内存；`proof` 须可写。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_proof_decode(
+    data: *const u8,
+    len: usize,
+    proof: *mut *mut Proof,
+) -> c_int {
+    guarded(AMDB_ERROR, || {
+        let Some(data) = bytes(data, len) else {
+            return AMDB_INVALID_ARG;
+        };
+        if proof.is_null() {
+            return AMDB_INVALID_ARG;
+        }
+        status(Proof::from_bytes(data).map(|decoded| {
+            *proof = Box::into_raw(Box::new(decoded));
+        }))
+    })
+}
+
+/// 编码证明以便传输；`out_len` 输出所需长度，`out` 为空或 `cap` 不足时只输出长度并返回 `AMDB_INVALID_ARG`
+///
+/// # Safety
+///
+/// `proof` 必须有效；`out` 为空或可写 `cap` 字节；`out_len` 须可写。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_proof_encode(
+    proof: *const Proof,
+    out: *mut u8,
+    cap: usize,
+    out_len: *mut usize,
+) -> c_int {
+    guarded(AMDB_ERROR, || {
+        let (Some(proof), Some(out_len)) = (proof.as_ref(), out_len.as_mut()) else {
+            return AMDB_INVALID_ARG;
+        };
+        let encoded = proof.to_bytes();
+        *out_len = encoded.len();
+        if out.is_null() || cap < encoded.len() {
+            return AMDB_INVALID_ARG;
+        }
+        slice::from_raw_parts_mut(out, encoded.len()).copy_from_slice(&encoded);
+        AMDB_OK
+    })
+}
+
+/// 不需要数据库句柄的验证（见 `Proof::verify`）
+///
+/// # Safety
+///
+/// `proof` 必须有效；`root_hash` 须可读32字节；`key`/`value` 须指向对应长度的可读内存。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_proof_verify(
+    proof: *const Proof,
+    root_hash: *const u8,
+    key: *const u8,
+    key_len: usize,
+    value: *const u8,
+    value_len: usize,
+) -> bool {
+    guarded(false, || {
+        let (Some(proof), Some(key), Some(value)) =
+            (proof.as_ref(), bytes(key, key_len), bytes(value, value_len))
+        else {
+            return false;
+        };
+        if root_hash.is_null() {
+            return false;
+        }
+        let root = Root(*(root_hash as *const [u8; 32]));
+        proof.verify(&root, key, value)
+    })
+}
+
+/// 释放证明；空指针不做任何事
+///
+/// # Safety
+///
+/// `proof` 必须来自 `amdb_rs_proof_get` 或 `amdb_rs_proof_decode` 且未释放。
+#[no_mangle]
+pub unsafe extern "C" fn amdb_rs_proof_free(proof: *mut Proof) {
+    if !proof.is_null() {
+        drop(Box::from_raw(proof));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use super::*;
+
+    #[test]
+    fn test_batch_and_proof_through_capi() {
+        let _ = std::fs::remove_dir_all("./test_data/capi");
+        let db = Database::new("./test_data/capi").unwrap();
+        let handle = db.as_raw().unwrap();
+        unsafe {
+            let batch = amdb_rs_batch_new();
+            assert_eq!(
+                amdb_rs_batch_put(batch, b"a".as_ptr(), 1, b"1".as_ptr(), 1),
+                AMDB_OK
+            );
+            assert_eq!(
+                amdb_rs_batch_put(batch, b"b".as_ptr(), 1, b"2".as_ptr(), 1),
+                AMDB_OK
+            );
+            assert_eq!(
+                amdb_rs_batch_put(batch, ptr::null(), 1, ptr::null(), 0),
+                AMDB_INVALID_ARG
+            );
+            assert_eq!(amdb_rs_batch_len(batch), 2);
+            let mut root = [0u8; 32];
+            assert_eq!(
+                amdb_rs_batch_commit(handle, batch, root.as_mut_ptr()),
+                AMDB_OK
+            );
+            amdb_rs_batch_free(batch);
+            assert_eq!(root, db.get_root_hash().unwrap());
+
+            let mut proof = ptr::null_mut();
+            assert_eq!(
+                amdb_rs_proof_get(handle, b"b".as_ptr(), 1, &mut proof),
+                AMDB_OK
+            );
+            let mut len = 0;
+            assert_eq!(
+                amdb_rs_proof_encode(proof, ptr::null_mut(), 0, &mut len),
+                AMDB_INVALID_ARG
+            );
+            let mut encoded = vec![0u8; len];
+            assert_eq!(
+                amdb_rs_proof_encode(proof, encoded.as_mut_ptr(), len, &mut len),
+                AMDB_OK
+            );
+            amdb_rs_proof_free(proof);
+
+            let mut decoded = ptr::null_mut();
+            assert_eq!(
+                amdb_rs_proof_decode(encoded.as_ptr(), len, &mut decoded),
+                AMDB_OK
+            );
+            assert!(amdb_rs_proof_verify(
+                decoded,
+                root.as_ptr(),
+                b"b".as_ptr(),
+                1,
+                b"2".as_ptr(),
+                1
+            ));
+            assert!(!amdb_rs_proof_verify(
+                decoded,
+                root.as_ptr(),
+                b"b".as_ptr(),
+                1,
+                b"3".as_ptr(),
+                1
+            ));
+            amdb_rs_proof_free(decoded);
+        }
+        // 借用句柄后数据库仍可使用
+        assert_eq!(db.get(b"a", None).unwrap(), Some(b"1".to_vec()));
+    }
+
+    #[test]
+    fn test_database_and_iter_through_capi() {
+        let _ = std::fs::remove_dir_all("./test_data/capi_db");
+        let dir = CString::new("./test_data/capi_db").unwrap();
+        let mut db = ptr::null_mut();
+        let mut root = [0u8; 32];
+        unsafe {
+            assert_eq!(amdb_rs_db_open(dir.as_ptr(), &mut db), AMDB_OK);
+            for (key, value) in [(b"k1", b"1"), (b"k2", b"2"), (b"k3", b"3")] {
+                assert_eq!(
+                    amdb_rs_db_put(db, key.as_ptr(), 2, value.as_ptr(), 1, root.as_mut_ptr()),
+                    AMDB_OK
+                );
+            }
+            assert_eq!(amdb_rs_db_delete(db, b"k2".as_ptr(), 2), AMDB_OK);
+
+            let mut value = AmdbRsBytes::empty();
+            assert_eq!(amdb_rs_db_get(db, b"k1".as_ptr(), 2, &mut value), AMDB_OK);
+            assert_eq!(slice::from_raw_parts(value.data, value.len), b"1");
+            amdb_rs_bytes_free(&mut value);
+            assert!(value.data.is_null());
+            assert_eq!(
+                amdb_rs_db_get(db, b"k2".as_ptr(), 2, &mut value),
+                AMDB_NOT_FOUND
+            );
+
+            let mut iter = ptr::null_mut();
+            assert_eq!(
+                amdb_rs_iter_new(db, ptr::null(), 0, ptr::null(), 0, true, &mut iter),
+                AMDB_OK
+            );
+            let (mut key, mut value) = (AmdbRsBytes::empty(), AmdbRsBytes::empty());
+            let mut keys = Vec::new();
+            while amdb_rs_iter_next(iter, &mut key, &mut value) == AMDB_OK {
+                keys.push(slice::from_raw_parts(key.data, key.len).to_vec());
+                amdb_rs_bytes_free(&mut key);
+                amdb_rs_bytes_free(&mut value);
+            }
+            assert_eq!(keys, vec![b"k3".to_vec(), b"k1".to_vec()]);
+            amdb_rs_iter_free(iter);
+
+            let mut proof = ptr::null_mut();
+            assert_eq!(
+                amdb_rs_db_proof_get(db, b"k3".as_ptr(), 2, &mut proof),
+                AMDB_OK
+            );
+            assert!((*proof).verify(&(*db).get_root_hash().unwrap(), b"k3", b"3"));
+            amdb_rs_proof_free(proof);
+            assert_eq!(amdb_rs_db_close(db), AMDB_OK);
+        }
+        assert_eq!(amdb_rs_abi_version(), AMDB_RS_ABI_VERSION);
+    }
+
+    #[test]
+    fn test_capi_failure_paths() {
+        let _ = std::fs::remove_dir_all("./test_data/capi_failures");
+        let _ = std::fs::remove_dir_all("./test_data/capi_borrowed");
+        let dir = CString::new("./test_data/capi_failures").unwrap();
+        let mut db = ptr::null_mut();
+        let mut value = AmdbRsBytes::empty();
+        unsafe {
+            // 空句柄与空输出指针
+            assert_eq!(
+                amdb_rs_db_get(ptr::null(), b"k".as_ptr(), 1, &mut value),
+                AMDB_INVALID_ARG
+            );
+            assert_eq!(
+                amdb_rs_db_open(dir.as_ptr(), ptr::null_mut()),
+                AMDB_INVALID_ARG
+            );
+            assert_eq!(amdb_rs_db_close(ptr::null_mut()), AMDB_OK);
+            let mut root = [0u8; 32];
+            assert_eq!(
+                amdb_rs_batch_commit(ptr::null_mut(), ptr::null(), root.as_mut_ptr()),
+                AMDB_INVALID_ARG
+            );
+
+            // 中毒后的调用返回错误而不进入引擎
+            assert_eq!(amdb_rs_db_open(dir.as_ptr(), &mut db), AMDB_OK);
+            let _ = (*db).engine_error(-6);
+            assert_ne!(amdb_rs_db_get(db, b"k".as_ptr(), 1, &mut value), AMDB_OK);
+            assert_ne!(
+                amdb_rs_db_put(db, b"k".as_ptr(), 1, b"v".as_ptr(), 1, root.as_mut_ptr()),
+                AMDB_OK
+            );
+            amdb_rs_db_close(db);
+        }
+        assert_eq!(guarded(AMDB_ERROR, || panic!("boom")), AMDB_ERROR);
+
+        // panic展开时不关闭借用的句柄
+        let owner = Database::new("./test_data/capi_borrowed").unwrap();
+        let handle = owner.as_raw().unwrap();
+        let unwound =
+            panic::catch_unwind(|| unsafe { with_database(handle, |_| -> () { panic!("boom") }) });
+        assert!(unwound.is_err());
+        owner.put(b"k", b"v").unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+//! 提交顺序
+//! 同一进程中有多个写入组件（例如复制应用、定时任务与请求处理）时，`Database::commit_guard` 让它们按
+//! 请求的先后依次提交，代替各自在句柄外加的互斥锁：每个调用方取得一个递增的序号，按序号先后持有守卫，
+//! 先到者先得，不会有调用方一直抢不到。守卫解引用为 `Database`，持有期间的写入在两次交出之间不会与其他
+//! 持有守卫的组件交错；`Database::commit_queue_depth` 返回正在等待的调用方数，可用于监控或限流。
+//!
+//! 守卫只约束取得守卫的调用方，不取守卫直接写入的调用仍按原来的方式并发执行。守卫不可重入：
+//! 持有期间在同一线程再次调用 `commit_guard` 会死锁。
+
+use std::ops::Deref;
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+
+use crate::Database;
+
+/// 序号分配的状态
+#[derive(Default)]
+struct Tickets {
+    /// 下一个调用方取得的序号
+    next: u64,
+    /// 当前可以持有守卫的序号
+    serving: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct CommitQueue {
+    tickets: Mutex<Tickets>,
+    turn: Condvar,
+}
+
+impl CommitQueue {
+    fn lock(&self) -> MutexGuard<'_, Tickets> {
+        self.tickets.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// 按序持有的提交守卫，见 `Database::commit_guard`；析构时交给下一个等待的调用方
+pub struct CommitGuard<'a> {
+    db: &'a Database,
+    ticket: u64,
+}
+
+impl Database {
+    /// 排队等待提交守卫，按调用的先后顺序取得
+    pub fn commit_guard(&self) -> CommitGuard<'_> {
+        let queue = &self.commit_queue;
+        let mut tickets = queue.lock();
+        let ticket = tickets.next;
+        tickets.next += 1;
+        while tickets.serving != ticket {
+            tickets = queue
+                .turn
+                .wait(tickets)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        CommitGuard { db: self, ticket }
+    }
+
+    /// 守卫空闲且无人等待时立即取得，否则返回 `None`，不插队
+    pub fn try_commit_guard(&self) -> Option<CommitGuard<'_>> {
+        let mut tickets = self.commit_queue.lock();
+        if tickets.next != tickets.serving {
+            return None;
+        }
+        let ticket = tickets.next;
+        tickets.next += 1;
+        Some(CommitGuard { db: self, ticket })
+    }
+
+    /// 正在等待提交守卫的调用方数，不含当前持有者
+    pub fn commit_queue_depth(&self) -> usize {
+        let tickets = self.commit_queue.lock();
+        (tickets.next - tickets.serving).saturating_sub(1) as usize
+    }
+}
+
+impl CommitGuard<'_> {
+    /// 本守卫的序号：同一个 `Database` 上按取得的顺序从0递增
+    pub fn ticket(&self) -> u64 {
+        self.ticket
+    }
+}
+
+impl Deref for CommitGuard<'_> {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        self.db
+    }
+}
+
+impl Drop for CommitGuard<'_> {
+    fn drop(&mut self) {
+        let queue = &self.db.commit_queue;
+        queue.lock().serving += 1;
+        queue.turn.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_commit_guard_order() {
+        let _ = std::fs::remove_dir_all("./test_data/commit_guard");
+        let db = Arc::new(Database::new("./test_data/commit_guard").unwrap());
+        let guard = db.commit_guard();
+        assert_eq!(guard.ticket(), 0);
+        assert!(db.try_commit_guard().is_none());
+
+        // 依次排队的写入组件按到达的顺序提交
+        let mut writers = Vec::new();
+        for i in 0..3u8 {
+            let writer = {
+                let db = Arc::clone(&db);
+                thread::spawn(move || {
+                    let guard = db.commit_guard();
+                    let order = guard.get(b"order", None).unwrap().unwrap_or_default();
+                    guard.put(b"order", &[order, vec![i]].concat()).unwrap();
+                    guard.ticket()
+                })
+            };
+            writers.push(writer);
+            while db.commit_queue_depth() != usize::from(i) + 1 {
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+        guard.put(b"order", b"").unwrap();
+        drop(guard);
+        let tickets: Vec<u64> = writers.into_iter().map(|w| w.join().unwrap()).collect();
+        assert_eq!(tickets, [1, 2, 3]);
+        assert_eq!(db.get(b"order", None).unwrap(), Some(vec![0, 1, 2]));
+
+        assert_eq!(db.commit_queue_depth(), 0);
+        assert_eq!(db.try_commit_guard().unwrap().ticket(), 4);
+    }
+}
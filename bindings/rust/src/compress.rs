@@ -0,0 +1,100 @@
+//! 值压缩，供开启 `TreeOptions::compression` 的命名树使用
+//! 简单的LZ77变体，不依赖外部库。压缩数据是一串记号：控制字节最高位为0时，其后跟
+//! （低7位 + 1）个原样字节；最高位为1时复制已输出的数据，长度为（低7位 + 4），
+//! 其后跟2字节大端的回溯距离（1..=65535），距离小于长度时按字节重叠复制。
+
+const MIN_MATCH: usize = 4;
+const MAX_MATCH: usize = 0x7f + MIN_MATCH;
+const MAX_LITERALS: usize = 0x80;
+const MAX_DISTANCE: usize = u16::MAX as usize;
+const HASH_BITS: u32 = 12;
+
+pub(crate) fn compress(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len() / 2 + 8);
+    // 以4字节前缀的哈希记录最近一次出现的位置
+    let mut table = vec![usize::MAX; 1 << HASH_BITS];
+    let mut literals = 0;
+    let mut i = 0;
+    while i + MIN_MATCH <= input.len() {
+        let slot = &mut table[hash(&input[i..i + MIN_MATCH])];
+        let candidate = std::mem::replace(slot, i);
+        let matched = candidate != usize::MAX
+            && i - candidate <= MAX_DISTANCE
+            && input[candidate..candidate + MIN_MATCH] == input[i..i + MIN_MATCH];
+        if !matched {
+            i += 1;
+            continue;
+        }
+        let mut len = MIN_MATCH;
+        while len < MAX_MATCH && i + len < input.len() && input[candidate + len] == input[i + len] {
+            len += 1;
+        }
+        push_literals(&mut out, &input[literals..i]);
+        out.push(0x80 | (len - MIN_MATCH) as u8);
+        out.extend_from_slice(&((i - candidate) as u16).to_be_bytes());
+        i += len;
+        literals = i;
+    }
+    push_literals(&mut out, &input[literals..]);
+    out
+}
+
+/// 还原 `compress` 的输出；数据不完整或回溯越界时返回 `None`
+pub(crate) fn decompress(input: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(input.len() * 2);
+    let mut i = 0;
+    while let Some(&control) = input.get(i) {
+        i += 1;
+        if control & 0x80 == 0 {
+            let len = control as usize + 1;
+            out.extend_from_slice(input.get(i..i + len)?);
+            i += len;
+            continue;
+        }
+        let len = (control & 0x7f) as usize + MIN_MATCH;
+        let distance = u16::from_be_bytes(input.get(i..i + 2)?.try_into().ok()?) as usize;
+        i += 2;
+        if distance == 0 || distance > out.len() {
+            return None;
+        }
+        let start = out.len() - distance;
+        for k in start..start + len {
+            out.push(out[k]);
+        }
+    }
+    Some(out)
+}
+
+fn push_literals(out: &mut Vec<u8>, literals: &[u8]) {
+    for chunk in literals.chunks(MAX_LITERALS) {
+        out.push((chunk.len() - 1) as u8);
+        out.extend_from_slice(chunk);
+    }
+}
+
+fn hash(prefix: &[u8]) -> usize {
+    let word = u32::from_le_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]);
+    (word.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let repetitive = b"amdb ".repeat(200);
+        let mut mixed: Vec<u8> = (0..5000u32).map(|i| (i * 7919 % 251) as u8).collect();
+        mixed.extend_from_slice(&repetitive);
+        for input in [&b""[..], b"a", b"abcabcabcabc", &[0; 1000], &repetitive, &mixed] {
+            let compressed = compress(input);
+            assert_eq!(decompress(&compressed).unwrap(), input);
+        }
+        assert!(compress(&repetitive).len() < repetitive.len() / 10);
+
+        // 截断或越界的回溯不能还原
+        assert!(decompress(&[0x05, b'a']).is_none());
+        assert!(decompress(&[0x80, 0x00]).is_none());
+        assert!(decompress(&[0x00, b'a', 0x80, 0x00, 0x02]).is_none());
+    }
+}
@@ -0,0 +1,402 @@
+//! 游标迭代器
+//! `Database::iter`/`prefix_iter` 在首次读取时打开引擎游标，按批从两端读取键值对，析构时释放游标。
+//! 游标打开时确定范围内的键集合；设置 `CursorOptions::pinned` 后所有值都读自打开时刻的状态，
+//! 期间的写入和保留策略都不会改变读到的结果。固定状态的游标可以 `Iter::save` 为令牌，见 `resume`。
+
+use std::collections::VecDeque;
+use std::ops::RangeBounds;
+use std::ptr;
+
+use crate::keys::prefix_successor;
+use crate::reserved::ReservedFilter;
+use crate::retention::PinGuard;
+use crate::versioned::Snapshot;
+use crate::{
+    amdb_cursor_close, amdb_cursor_next, amdb_cursor_open, amdb_snapshot_cursor_open, collect_range,
+    engine_bounds, successor, AmdbCursor, AmdbSnapshot, Database, Entry, Error, Result, Root,
+    Version,
+};
+
+/// 默认每次引擎调用读取的键值对数
+const DEFAULT_BATCH_SIZE: usize = 64;
+
+/// 游标选项
+#[derive(Debug, Clone)]
+pub struct CursorOptions {
+    pub(crate) reverse: bool,
+    pinned: bool,
+    pub(crate) batch_size: usize,
+}
+
+impl Default for CursorOptions {
+    fn default() -> Self {
+        CursorOptions {
+            reverse: false,
+            pinned: false,
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+}
+
+impl CursorOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 按键的降序迭代（默认升序）；`next_back` 相应地从最小的键开始
+    pub fn reverse(&mut self, reverse: bool) -> &mut Self {
+        self.reverse = reverse;
+        self
+    }
+
+    /// 读取打开游标时刻的状态（默认读取各键的最新值）
+    pub fn pinned(&mut self, pinned: bool) -> &mut Self {
+        self.pinned = pinned;
+        self
+    }
+
+    /// 每次引擎调用读取的键值对数（默认64），0 按 1 处理
+    pub fn batch_size(&mut self, entries: usize) -> &mut Self {
+        self.batch_size = entries.max(1);
+        self
+    }
+}
+
+/// 游标迭代器，见 `Database::iter`
+pub struct Iter<'a> {
+    db: &'a Database,
+    /// 未打开时的引擎区间，`None` 表示空区间或已打开
+    bounds: Option<(Vec<u8>, Vec<u8>)>,
+    cursor: *mut AmdbCursor,
+    /// 在该快照上打开游标（见 `Snapshot::iter`），为空时按 `options` 打开
+    snapshot: *mut AmdbSnapshot,
+    /// 引擎游标已读完（两端相遇）
+    exhausted: bool,
+    pin: Option<(PinGuard<'a>, Root)>,
+    /// 所读状态的数据库版本和根哈希；设置 `CursorOptions::pinned` 时在保存时才确定
+    state: Option<(Version, Root)>,
+    /// 尚未返回的键所在的引擎区间，`None` 表示没有剩余的键
+    remaining: Option<(Vec<u8>, Vec<u8>)>,
+    /// `Database::resume_cursor` 打开的快照，随迭代器释放
+    owned_snapshot: Option<Snapshot<'a>>,
+    options: CursorOptions,
+    reserved: ReservedFilter,
+    /// 从前端读到的项（升序）和从末端读到的项（降序）
+    front: VecDeque<Entry>,
+    back: VecDeque<Entry>,
+}
+
+impl Database {
+    /// 按键的升序迭代 `range` 内的键值对，跳过已删除的键
+    pub fn iter(&self, range: impl RangeBounds<Vec<u8>>) -> Iter<'_> {
+        self.iter_with(range, &CursorOptions::new())
+    }
+
+    /// 迭代以 `prefix` 开头的键值对
+    pub fn prefix_iter(&self, prefix: &[u8]) -> Iter<'_> {
+        self.prefix_iter_with(prefix, &CursorOptions::new())
+    }
+
+    pub fn iter_with(&self, range: impl RangeBounds<Vec<u8>>, options: &CursorOptions) -> Iter<'_> {
+        Iter::new(self, engine_bounds(&range), options)
+    }
+
+    pub fn prefix_iter_with(&self, prefix: &[u8], options: &CursorOptions) -> Iter<'_> {
+        let bounds = (prefix.to_vec(), prefix_successor(prefix));
+        Iter::new(self, Some(bounds), options)
+    }
+}
+
+impl<'a> Iter<'a> {
+    pub(crate) fn new(
+        db: &'a Database,
+        bounds: Option<(Vec<u8>, Vec<u8>)>,
+        options: &CursorOptions,
+    ) -> Self {
+        Self::on_snapshot(db, ptr::null_mut(), None, bounds, options)
+    }
+
+    /// `snapshot` 须在迭代器存活期间有效，`state` 为其版本和根哈希；此时忽略 `CursorOptions::pinned`
+    pub(crate) fn on_snapshot(
+        db: &'a Database,
+        snapshot: *mut AmdbSnapshot,
+        state: Option<(Version, Root)>,
+        bounds: Option<(Vec<u8>, Vec<u8>)>,
+        options: &CursorOptions,
+    ) -> Self {
+        let reserved =
+            ReservedFilter::from_start(bounds.as_ref().map_or(&[][..], |(start, _)| start));
+        Iter {
+            db,
+            exhausted: bounds.is_none(),
+            remaining: bounds.clone(),
+            bounds,
+            cursor: ptr::null_mut(),
+            snapshot,
+            pin: None,
+            state,
+            owned_snapshot: None,
+            options: options.clone(),
+            reserved,
+            front: VecDeque::new(),
+            back: VecDeque::new(),
+        }
+    }
+
+    /// 设置 `CursorOptions::pinned` 时迭代所读状态的根哈希；游标尚未打开时为 `None`
+    pub fn pinned_root(&self) -> Option<Root> {
+        self.pin.as_ref().map(|(_, root)| *root)
+    }
+
+    /// 迭代器释放时一并释放 `snapshot`（须是游标所在的快照）
+    pub(crate) fn owning(mut self, snapshot: Snapshot<'a>) -> Self {
+        self.owned_snapshot = Some(snapshot);
+        self
+    }
+
+    /// 同时返回保留前缀下的内部记录，供需要完整状态的导出使用
+    pub(crate) fn with_reserved(mut self) -> Self {
+        self.reserved = ReservedFilter::none();
+        self
+    }
+
+    /// 所读状态的数据库版本和根哈希；未固定状态的游标返回 `Error::InvalidArgument`
+    pub(crate) fn fixed_state(&mut self) -> Result<(Version, Root)> {
+        if let Some(state) = self.state {
+            return Ok(state);
+        }
+        if !self.options.pinned {
+            return Err(Error::InvalidArgument(
+                "only pinned or snapshot cursors read a fixed version".to_string(),
+            ));
+        }
+        self.open()?;
+        if self.pin.is_none() {
+            // 空区间不打开游标，直接固定当前状态
+            self.pin = Some(self.db.pin_retained()?);
+        }
+        let root = self.pin.as_ref().map(|(_, root)| *root).unwrap();
+        // 固定期间该根哈希的状态不会被清理；根哈希相同的版本状态也相同
+        let version = self.db.snapshot_at_root(&root)?.version();
+        self.state = Some((version, root));
+        Ok((version, root))
+    }
+
+    /// 尚未返回的键所在的引擎区间
+    pub(crate) fn remaining(&self) -> Option<&(Vec<u8>, Vec<u8>)> {
+        self.remaining.as_ref()
+    }
+
+    pub(crate) fn options(&self) -> &CursorOptions {
+        &self.options
+    }
+
+    fn open(&mut self) -> Result<()> {
+        let Some((start, end)) = self.bounds.take() else {
+            return Ok(());
+        };
+        let handle = self.db.live_handle()?;
+        let status = if !self.snapshot.is_null() {
+            unsafe {
+                amdb_snapshot_cursor_open(
+                    self.snapshot,
+                    start.as_ptr(),
+                    start.len(),
+                    end.as_ptr(),
+                    end.len(),
+                    &mut self.cursor,
+                )
+            }
+        } else {
+            let timestamp = if self.options.pinned {
+                let (guard, root) = self.db.pin_retained()?;
+                let at = guard.pinned_at;
+                self.pin = Some((guard, root));
+                at
+            } else {
+                -1.0
+            };
+            unsafe {
+                amdb_cursor_open(
+                    *handle,
+                    start.as_ptr(),
+                    start.len(),
+                    end.as_ptr(),
+                    end.len(),
+                    timestamp,
+                    &mut self.cursor,
+                )
+            }
+        };
+        if status != 0 {
+            // 下次调用重新打开
+            self.bounds = Some((start, end));
+            return Err(self.db.engine_error(status));
+        }
+        Ok(())
+    }
+
+    /// 从一端取下一项；该端的缓冲区为空时读取下一批，游标读完后取另一端缓冲区中剩余的项
+    fn take(&mut self, from_back: bool) -> Option<Result<Entry>> {
+        // 一批可能只含被跳过的令牌记录，继续读取直到取得一项或读完
+        loop {
+            let near = if from_back { &self.back } else { &self.front };
+            if !near.is_empty() || self.exhausted {
+                break;
+            }
+            if let Err(e) = self.fill(from_back) {
+                return Some(Err(e));
+            }
+        }
+        let (near, far) = if from_back {
+            (&mut self.back, &mut self.front)
+        } else {
+            (&mut self.front, &mut self.back)
+        };
+        let Some(entry) = near.pop_front().or_else(|| far.pop_back()) else {
+            // 两端都已读完
+            self.remaining = None;
+            return None;
+        };
+        self.advance_past(&entry.0, from_back);
+        Some(Ok(entry))
+    }
+
+    /// 从一端返回 `key` 之后收窄剩余区间
+    fn advance_past(&mut self, key: &[u8], from_back: bool) {
+        let Some((start, end)) = self.remaining.as_mut() else {
+            return;
+        };
+        if from_back {
+            *end = key.to_vec();
+        } else {
+            *start = successor(key);
+        }
+        if (from_back && key.is_empty()) || (!end.is_empty() && start >= end) {
+            self.remaining = None;
+        }
+    }
+
+    fn fill(&mut self, from_back: bool) -> Result<()> {
+        self.open()?;
+        let _alive = self.db.state.enter()?;
+        let (cursor, batch_size) = (self.cursor, self.options.batch_size);
+        let entries = collect_range(&self.db.state, |results, count| {
+            self.db.retry_status(|| unsafe {
+                amdb_cursor_next(cursor, from_back, batch_size, results, count)
+            })
+        })?;
+        if entries.is_empty() {
+            self.exhausted = true;
+        }
+        let mut entries = self.db.open_entries(entries)?;
+        self.reserved.retain(&mut entries);
+        if from_back {
+            self.back.extend(entries);
+        } else {
+            self.front.extend(entries);
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for Iter<'_> {
+    type Item = Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.take(self.options.reverse)
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.take(!self.options.reverse)
+    }
+}
+
+impl Drop for Iter<'_> {
+    fn drop(&mut self) {
+        // 游标持有自己的引用，数据库关闭后同样可以释放
+        if !self.cursor.is_null() {
+            unsafe { amdb_cursor_close(self.cursor) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(iter: Iter<'_>) -> Vec<Vec<u8>> {
+        iter.map(|entry| entry.unwrap().0).collect()
+    }
+
+    #[test]
+    fn test_iter_both_directions() {
+        let _ = std::fs::remove_dir_all("./test_data/cursor");
+        let db = Database::new("./test_data/cursor").unwrap();
+        for key in [&b"a/1"[..], b"a/2", b"a/3", b"b/1", b"c"] {
+            db.put(key, key).unwrap();
+        }
+        db.delete(b"a/2").unwrap();
+
+        assert_eq!(
+            keys(db.prefix_iter(b"a/")),
+            vec![b"a/1".to_vec(), b"a/3".to_vec()]
+        );
+        assert_eq!(
+            keys(db.iter(b"b".to_vec()..)),
+            vec![b"b/1".to_vec(), b"c".to_vec()]
+        );
+
+        // 小批量时两端交替读取，相遇后不重复
+        let mut options = CursorOptions::new();
+        options.batch_size(1);
+        let mut iter = db.iter_with(.., &options);
+        assert_eq!(iter.next().unwrap().unwrap().0, b"a/1");
+        assert_eq!(iter.next_back().unwrap().unwrap().0, b"c");
+        assert_eq!(iter.next().unwrap().unwrap().0, b"a/3");
+        assert_eq!(iter.next_back().unwrap().unwrap().0, b"b/1");
+        assert!(iter.next().is_none());
+        assert!(iter.next_back().is_none());
+
+        options.reverse(true).batch_size(2);
+        assert_eq!(
+            keys(db.iter_with(.., &options)),
+            vec![
+                b"c".to_vec(),
+                b"b/1".to_vec(),
+                b"a/3".to_vec(),
+                b"a/1".to_vec()
+            ]
+        );
+        assert!(db.iter(b"z".to_vec()..b"a".to_vec()).next().is_none());
+    }
+
+    #[test]
+    fn test_pinned_iter() {
+        let _ = std::fs::remove_dir_all("./test_data/cursor_pinned");
+        let db = Database::new("./test_data/cursor_pinned").unwrap();
+        db.put(b"k1", b"old").unwrap();
+        db.put(b"k2", b"old").unwrap();
+
+        let mut options = CursorOptions::new();
+        options.pinned(true).batch_size(1);
+        let mut iter = db.iter_with(.., &options);
+        assert_eq!(
+            iter.next().unwrap().unwrap(),
+            (b"k1".to_vec(), b"old".to_vec())
+        );
+        let root = iter.pinned_root().unwrap();
+        db.put(b"k2", b"new").unwrap();
+        assert_eq!(
+            iter.next().unwrap().unwrap(),
+            (b"k2".to_vec(), b"old".to_vec())
+        );
+        assert!(iter.next().is_none());
+        assert_ne!(db.get_root_hash().unwrap(), root);
+
+        let latest: Vec<Entry> = db.iter(..).map(Result::unwrap).collect();
+        assert_eq!(latest[1].1, b"new");
+    }
+}
@@ -0,0 +1,263 @@
+//! 版本间的差异
+//! `Database::diff` 同时遍历两个版本的Merkle树，哈希相同的子树整棵跳过，
+//! 开销与两个版本间变化的键数成正比，不需要重放其间的提交记录。
+//!
+//! `Database::changed_keys` 只给出版本区间内写入或删除过的键，不读取也不比较值，
+//! 供索引等派生数据按区间增量更新。两者都不含幂等令牌的记录（见 `WriteBatch::idempotency_token`）。
+
+use std::ptr;
+
+use crate::reserved::is_reserved_key;
+use crate::{
+    amdb_changed_keys, amdb_diff, amdb_free_results, result_bytes, result_value, AmdbResult,
+    Database, Error, Result, Version,
+};
+
+/// 键、起始版本的值、目标版本的值，不存在的值为 `None`
+type Triple = (Vec<u8>, Option<Vec<u8>>, Option<Vec<u8>>);
+
+/// 一个键在两个版本间的差异；已删除的键与不存在的键相同
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffEntry {
+    /// 只在目标版本中存在
+    Added { key: Vec<u8>, value: Vec<u8> },
+    Modified {
+        key: Vec<u8>,
+        old_value: Vec<u8>,
+        new_value: Vec<u8>,
+    },
+    /// 只在起始版本中存在
+    Deleted { key: Vec<u8>, old_value: Vec<u8> },
+}
+
+impl DiffEntry {
+    pub fn key(&self) -> &[u8] {
+        match self {
+            DiffEntry::Added { key, .. }
+            | DiffEntry::Modified { key, .. }
+            | DiffEntry::Deleted { key, .. } => key,
+        }
+    }
+}
+
+impl Database {
+    /// 从数据库版本 `from_version` 到 `to_version` 值不同的键，按键排序；版本0是新数据库的空状态，
+    /// `to_version` 可早于 `from_version`。任一版本不存在或已被清理时只产生一个 `Error::NotFound`
+    pub fn diff(
+        &self,
+        from_version: Version,
+        to_version: Version,
+    ) -> impl Iterator<Item = Result<DiffEntry>> + '_ {
+        let (changes, error) = match self.diff_triples(from_version, to_version) {
+            Ok(changes) => (changes, None),
+            Err(e) => (Vec::new(), Some(Err(e))),
+        };
+        error
+            .into_iter()
+            .chain(changes.into_iter().map(move |(key, old, new)| {
+                let entry = match (old, new) {
+                    (Some(old), Some(new)) => DiffEntry::Modified {
+                        key,
+                        old_value: self.open_value(old)?,
+                        new_value: self.open_value(new)?,
+                    },
+                    (Some(old), None) => DiffEntry::Deleted {
+                        key,
+                        old_value: self.open_value(old)?,
+                    },
+                    // 两个版本中都不存在的键不在结果中
+                    (None, new) => DiffEntry::Added {
+                        key,
+                        value: self.open_value(new.unwrap_or_default())?,
+                    },
+                };
+                Ok(entry)
+            }))
+    }
+
+    /// 数据库版本 `from_version` 之后到 `to_version`（含）之间写入或删除过的以 `prefix` 开头的键，
+    /// 按键排序；写回原值的键同样在内。版本0是新数据库的空状态。`to_version` 早于 `from_version` 时
+    /// 只产生一个 `Error::InvalidArgument`，任一版本不存在或已被清理时只产生一个 `Error::NotFound`
+    pub fn changed_keys(
+        &self,
+        from_version: Version,
+        to_version: Version,
+        prefix: &[u8],
+    ) -> impl Iterator<Item = Result<Vec<u8>>> {
+        let (keys, error) = match self.changed_key_list(from_version, to_version, prefix) {
+            Ok(keys) => (keys, None),
+            Err(e) => (Vec::new(), Some(Err(e))),
+        };
+        error.into_iter().chain(keys.into_iter().map(Ok))
+    }
+
+    fn changed_key_list(
+        &self,
+        from_version: Version,
+        to_version: Version,
+        prefix: &[u8],
+    ) -> Result<Vec<Vec<u8>>> {
+        if to_version < from_version {
+            return Err(Error::InvalidArgument(format!(
+                "version window ends at {} before it starts at {}",
+                to_version, from_version
+            )));
+        }
+        let (mut results, mut count) = (ptr::null_mut::<AmdbResult>(), 0);
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            amdb_changed_keys(
+                *handle,
+                from_version.0,
+                to_version.0,
+                prefix.as_ptr(),
+                prefix.len(),
+                &mut results,
+                &mut count,
+            )
+        });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        if results.is_null() {
+            return Ok(Vec::new());
+        }
+        let keys = unsafe { std::slice::from_raw_parts(results, count) }
+            .iter()
+            .map(result_bytes)
+            .filter(|key| !is_reserved_key(key))
+            .collect();
+        unsafe { amdb_free_results(results, count) };
+        Ok(keys)
+    }
+
+    fn diff_triples(&self, from_version: Version, to_version: Version) -> Result<Vec<Triple>> {
+        let (mut results, mut count) = (ptr::null_mut::<AmdbResult>(), 0);
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            amdb_diff(*handle, from_version.0, to_version.0, &mut results, &mut count)
+        });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        if results.is_null() {
+            return Ok(Vec::new());
+        }
+        let changes = unsafe { std::slice::from_raw_parts(results, count) }
+            .chunks_exact(3)
+            .map(|change| {
+                (
+                    result_bytes(&change[0]),
+                    result_value(&change[1]),
+                    result_value(&change[2]),
+                )
+            })
+            .filter(|(key, old, new)| !is_reserved_key(key) && (old.is_some() || new.is_some()))
+            .collect();
+        unsafe { amdb_free_results(results, count) };
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenOptions;
+
+    #[test]
+    fn test_diff() {
+        let _ = std::fs::remove_dir_all("./test_data/diff");
+        let db = Database::new("./test_data/diff").unwrap();
+        for key in [b"k0", b"k1", b"k2", b"k3"] {
+            db.put(key, &[key, &b"-v"[..]].concat()).unwrap();
+        }
+        db.put(b"k1", b"x").unwrap();
+        db.delete(b"k2").unwrap();
+        db.put(b"z", b"new").unwrap();
+
+        let diff: Vec<DiffEntry> = db.diff(Version(4), Version(7)).map(|e| e.unwrap()).collect();
+        assert_eq!(
+            diff,
+            vec![
+                DiffEntry::Modified {
+                    key: b"k1".to_vec(),
+                    old_value: b"k1-v".to_vec(),
+                    new_value: b"x".to_vec(),
+                },
+                DiffEntry::Deleted {
+                    key: b"k2".to_vec(),
+                    old_value: b"k2-v".to_vec(),
+                },
+                DiffEntry::Added {
+                    key: b"z".to_vec(),
+                    value: b"new".to_vec(),
+                },
+            ]
+        );
+        // 反向比较得到相反的差异
+        let reverse: Vec<DiffEntry> = db.diff(Version(7), Version(4)).map(|e| e.unwrap()).collect();
+        assert!(matches!(&reverse[1], DiffEntry::Added { key, .. } if key == b"k2"));
+        assert!(matches!(&reverse[2], DiffEntry::Deleted { key, .. } if key == b"z"));
+
+        assert_eq!(db.diff(Version(0), Version(2)).count(), 2);
+        assert_eq!(db.diff(Version(5), Version(5)).count(), 0);
+        let missing: Vec<Result<DiffEntry>> = db.diff(Version(1), Version(100)).collect();
+        assert_eq!(missing.len(), 1);
+        assert!(matches!(missing[0], Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn test_changed_keys() {
+        let _ = std::fs::remove_dir_all("./test_data/changed_keys");
+        let db = Database::new("./test_data/changed_keys").unwrap();
+        db.put(b"user/a", b"1").unwrap();
+        db.put(b"user/b", b"1").unwrap();
+        db.put(b"other", b"1").unwrap();
+        db.put(b"user/b", b"2").unwrap();
+        db.put(b"user/b", b"1").unwrap();
+        db.delete(b"user/a").unwrap();
+
+        let changed = |from, to, prefix: &[u8]| -> Vec<Vec<u8>> {
+            db.changed_keys(from, to, prefix)
+                .map(|k| k.unwrap())
+                .collect()
+        };
+        assert_eq!(
+            changed(Version(0), Version(6), b"user/"),
+            vec![b"user/a".to_vec(), b"user/b".to_vec()]
+        );
+        // 写回原值的键在内，而 diff 中没有
+        assert_eq!(changed(Version(3), Version(5), b""), vec![b"user/b".to_vec()]);
+        assert_eq!(db.diff(Version(3), Version(5)).count(), 0);
+        assert_eq!(changed(Version(2), Version(3), b"user/"), Vec::<Vec<u8>>::new());
+        assert_eq!(changed(Version(4), Version(4), b""), Vec::<Vec<u8>>::new());
+
+        let missing: Vec<Result<Vec<u8>>> = db.changed_keys(Version(1), Version(100), b"").collect();
+        assert_eq!(missing.len(), 1);
+        assert!(matches!(missing[0], Err(Error::NotFound)));
+        assert!(matches!(
+            db.changed_keys(Version(5), Version(3), b"").next(),
+            Some(Err(Error::InvalidArgument(_)))
+        ));
+    }
+
+    #[test]
+    fn test_diff_with_checksums() {
+        let _ = std::fs::remove_dir_all("./test_data/diff_checksums");
+        let db = OpenOptions::new()
+            .value_checksums(true)
+            .open("./test_data/diff_checksums")
+            .unwrap();
+        db.put(b"k", b"v1").unwrap();
+        db.put(b"k", b"v2").unwrap();
+        let diff: Vec<DiffEntry> = db.diff(Version(1), Version(2)).map(|e| e.unwrap()).collect();
+        assert_eq!(
+            diff,
+            vec![DiffEntry::Modified {
+                key: b"k".to_vec(),
+                old_value: b"v1".to_vec(),
+                new_value: b"v2".to_vec(),
+            }]
+        );
+    }
+}
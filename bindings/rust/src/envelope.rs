@@ -0,0 +1,325 @@
+//! 带校验的值封装
+//! 开启 `OpenOptions::value_checksums` 后，每个值写入引擎前追加一个尾部：
+//!
+//! ```text
+//! 值 | crc32c (4, LE) | 值长度 (8, LE) | 编码 (1) | 魔数 0xAE (1)
+//! ```
+//!
+//! crc32c 覆盖值、长度和编码字节；读取时校验，不一致返回 `Error::Corruption`，即使树节点哈希
+//! 尚未发现值日志中的位翻转。尾部放在值之后，流式写入时无需预先知道长度。空值同样封装。
+//!
+//! 同一数据目录每次打开都须使用相同的设置：开启后读到没有尾部的值同样视为损坏。
+
+use std::borrow::Cow;
+
+use crate::blob::{self, BlobStore};
+use crate::{Database, Entry, Error, Result};
+
+/// 尾部字节数
+pub(crate) const TRAILER_LEN: usize = 14;
+
+const MAGIC: u8 = 0xAE;
+
+/// 值按原样存储；其他编码保留给以后的压缩格式
+const CODEC_RAW: u8 = 0;
+
+/// crc32c（Castagnoli）多项式的反射形式
+const POLY: u32 = 0x82f6_3b78;
+
+const TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ POLY
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// 逐块计算值的校验和
+#[derive(Clone)]
+pub(crate) struct Checksum {
+    crc: u32,
+    len: u64,
+}
+
+impl Checksum {
+    pub(crate) fn new() -> Self {
+        Checksum { crc: !0, len: 0 }
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        self.len += data.len() as u64;
+        self.crc = crc32c(self.crc, data);
+    }
+
+    /// 已写入部分对应的尾部
+    pub(crate) fn trailer(&self) -> [u8; TRAILER_LEN] {
+        let mut trailer = [0u8; TRAILER_LEN];
+        trailer[..4].copy_from_slice(&self.finish(CODEC_RAW).to_le_bytes());
+        trailer[4..12].copy_from_slice(&self.len.to_le_bytes());
+        trailer[12] = CODEC_RAW;
+        trailer[13] = MAGIC;
+        trailer
+    }
+
+    /// 用已读取部分校验尾部
+    pub(crate) fn verify(&self, trailer: &[u8]) -> Result<()> {
+        if trailer.len() != TRAILER_LEN || trailer[13] != MAGIC {
+            return Err(corruption("missing value envelope"));
+        }
+        let codec = trailer[12];
+        if codec != CODEC_RAW {
+            return Err(corruption(&format!("unknown value codec {}", codec)));
+        }
+        let len = u64::from_le_bytes(trailer[4..12].try_into().unwrap());
+        if len != self.len {
+            return Err(corruption(&format!(
+                "value length {} does not match envelope length {}",
+                self.len, len
+            )));
+        }
+        let crc = u32::from_le_bytes(trailer[..4].try_into().unwrap());
+        if crc != self.finish(codec) {
+            return Err(corruption("value checksum mismatch"));
+        }
+        Ok(())
+    }
+
+    fn finish(&self, codec: u8) -> u32 {
+        let crc = crc32c(self.crc, &self.len.to_le_bytes());
+        !crc32c(crc, &[codec])
+    }
+}
+
+fn crc32c(mut crc: u32, data: &[u8]) -> u32 {
+    for &b in data {
+        crc = TABLE[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    crc
+}
+
+fn corruption(msg: &str) -> Error {
+    Error::Corruption(msg.to_string())
+}
+
+/// 封装一个值
+pub(crate) fn seal(value: &[u8]) -> Vec<u8> {
+    let mut checksum = Checksum::new();
+    checksum.update(value);
+    let mut sealed = Vec::with_capacity(value.len() + TRAILER_LEN);
+    sealed.extend_from_slice(value);
+    sealed.extend_from_slice(&checksum.trailer());
+    sealed
+}
+
+/// 校验并去掉尾部
+pub(crate) fn open(mut sealed: Vec<u8>) -> Result<Vec<u8>> {
+    let payload_len = verify(&sealed)?;
+    sealed.truncate(payload_len);
+    Ok(sealed)
+}
+
+/// 校验尾部但不复制，返回值本身的长度
+pub(crate) fn verify(sealed: &[u8]) -> Result<usize> {
+    if sealed.len() < TRAILER_LEN {
+        return Err(corruption("missing value envelope"));
+    }
+    let payload_len = sealed.len() - TRAILER_LEN;
+    let mut checksum = Checksum::new();
+    checksum.update(&sealed[..payload_len]);
+    checksum.verify(&sealed[payload_len..])?;
+    Ok(payload_len)
+}
+
+/// 值在引擎中的完整形式：开启大值分离时先换成引用或加上标记（见 `blob`），再按需追加尾部
+pub(crate) fn seal_with(value: &[u8], checksums: bool, blob_threshold: Option<u64>) -> Vec<u8> {
+    let value = match blob_threshold {
+        Some(threshold) => Cow::Owned(blob::encode(value, threshold)),
+        None => Cow::Borrowed(value),
+    };
+    if checksums {
+        seal(&value)
+    } else {
+        value.into_owned()
+    }
+}
+
+/// `seal_with` 的逆过程，引用的值从 `blobs` 读取
+pub(crate) fn open_with(
+    value: Vec<u8>,
+    checksums: bool,
+    blobs: Option<&BlobStore>,
+) -> Result<Vec<u8>> {
+    let value = if checksums { open(value)? } else { value };
+    match blobs {
+        Some(blobs) => blobs.decode(value),
+        None => Ok(value),
+    }
+}
+
+impl Database {
+    /// 按 `OpenOptions::value_checksums` 和 `OpenOptions::blob_threshold` 封装写入的值；
+    /// 不存储大值文件，见 `store_blobs`
+    pub(crate) fn seal_value<'v>(&self, value: &'v [u8]) -> Cow<'v, [u8]> {
+        if !self.options.value_checksums && self.options.blob_threshold.is_none() {
+            return Cow::Borrowed(value);
+        }
+        Cow::Owned(seal_with(
+            value,
+            self.options.value_checksums,
+            self.options.blob_threshold,
+        ))
+    }
+
+    /// 校验并还原读到的值
+    pub(crate) fn open_value(&self, value: Vec<u8>) -> Result<Vec<u8>> {
+        open_with(value, self.options.value_checksums, self.blobs.as_ref())
+    }
+
+    pub(crate) fn open_entries(&self, entries: Vec<Entry>) -> Result<Vec<Entry>> {
+        if !self.options.value_checksums && self.blobs.is_none() {
+            return Ok(entries);
+        }
+        entries
+            .into_iter()
+            .map(|(key, value)| Ok((key, self.open_value(value)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{KeyVersion, OpenOptions};
+
+    use super::*;
+
+    #[test]
+    fn test_crc32c() {
+        // RFC 3720 附录 B.4 的测试向量
+        assert_eq!(!crc32c(!0, &[0u8; 32]), 0x8a91_36aa);
+        assert_eq!(!crc32c(!0, b"123456789"), 0xe306_9283);
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let sealed = seal(b"value");
+        assert_eq!(sealed.len(), 5 + TRAILER_LEN);
+        assert_eq!(open(sealed.clone()).unwrap(), b"value");
+        assert_eq!(seal(b"").len(), TRAILER_LEN);
+        assert!(open(seal(b"")).unwrap().is_empty());
+        assert!(matches!(open(Vec::new()), Err(Error::Corruption(_))));
+
+        let mut flipped = sealed.clone();
+        flipped[1] ^= 0x04;
+        assert!(matches!(open(flipped), Err(Error::Corruption(_))));
+        assert!(matches!(open(b"plain".to_vec()), Err(Error::Corruption(_))));
+    }
+
+    #[test]
+    fn test_checksummed_database() {
+        let _ = std::fs::remove_dir_all("./test_data/value_checksums");
+        let dir = "./test_data/value_checksums";
+        let db = OpenOptions::new().value_checksums(true).open(dir).unwrap();
+        db.put(b"a", b"1").unwrap();
+        db.put_from_reader(b"b", &mut &[7u8; 100][..], Some(100))
+            .unwrap();
+        db.delete(b"a").unwrap();
+        assert!(db.get(b"a", None).unwrap().is_none());
+        assert_eq!(db.get(b"a", Some(KeyVersion(1))).unwrap(), Some(b"1".to_vec()));
+
+        let mut out = Vec::new();
+        assert_eq!(db.get_to_writer(b"b", None, &mut out).unwrap(), Some(100));
+        assert_eq!(out, vec![7u8; 100]);
+        let values: Vec<usize> = db.scan(..).map(|e| e.unwrap().1.len()).collect();
+        assert_eq!(values, vec![100]);
+        drop(db);
+
+        // 未开启时读到的是带尾部的原始值；反过来没有尾部的值视为损坏
+        let raw = Database::new(dir).unwrap();
+        assert_eq!(
+            raw.get(b"b", None).unwrap().unwrap().len(),
+            100 + TRAILER_LEN
+        );
+        raw.put(b"plain", b"v").unwrap();
+        drop(raw);
+        let db = OpenOptions::new().value_checksums(true).open(dir).unwrap();
+        assert!(matches!(db.get(b"plain", None), Err(Error::Corruption(_))));
+    }
+
+    #[test]
+    fn test_empty_values() {
+        for checksums in [false, true] {
+            let dir = format!("./test_data/empty_values_{}", checksums);
+            let _ = std::fs::remove_dir_all(&dir);
+            let db = OpenOptions::new()
+                .value_checksums(checksums)
+                .open(&dir)
+                .unwrap();
+            db.put(b"a", b"").unwrap();
+            db.put_from_reader(b"b", &mut &[][..], None).unwrap();
+            let mut batch = crate::WriteBatch::new();
+            batch.put(b"c", b"").put(b"gone", b"v").delete(b"gone");
+            let root = db.write_batch(&batch).unwrap();
+
+            // 值为空的键存在，删除的键不存在
+            for key in [b"a", b"b", b"c"] {
+                assert_eq!(db.get(key, None).unwrap(), Some(Vec::new()));
+            }
+            assert!(db.get(b"gone", None).unwrap().is_none());
+            let exists = db.multi_contains(&[&b"a"[..], b"gone"]).unwrap();
+            assert_eq!(exists.iter().collect::<Vec<_>>(), vec![true, false]);
+            let mut out = Vec::new();
+            assert_eq!(db.get_to_writer(b"b", None, &mut out).unwrap(), Some(0));
+            assert!(out.is_empty());
+            let keys: Vec<Vec<u8>> = db.scan(..).map(|e| e.unwrap().0.into_vec()).collect();
+            assert_eq!(keys, vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]);
+
+            // 证明同样区分两者
+            let (value, proof) = db.get_with_proof(b"c", None).unwrap();
+            assert_eq!(value, Some(Vec::new()));
+            assert!(proof.verify(&root, b"c", b""));
+            let (value, proof) = db.get_with_proof(b"gone", None).unwrap();
+            assert!(value.is_none());
+            assert!(!proof.verify(&root, b"gone", b""));
+
+            db.delete(b"a").unwrap();
+            assert!(db.get(b"a", None).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn test_empty_value_in_engine() {
+        let _ = std::fs::remove_dir_all("./test_data/empty_value_engine");
+        let db = Database::new("./test_data/empty_value_engine").unwrap();
+
+        // 空值按原样存入引擎，根哈希与直接写入空值的引擎状态一致
+        let root = db.put(b"k", b"").unwrap();
+        assert_eq!(db.get_stored(b"k", None).unwrap(), Some(Vec::new()));
+        let (_, proof) = db.get_with_proof(b"k", None).unwrap();
+        assert!(proof.verify(&root, b"k", b""));
+        assert!(!proof.verify(&root, b"k", b"\0amdb/empty"));
+
+        // 任何值都能写入，包括以前用作空值编码的字节串
+        for value in [&b"\0amdb/empty"[..], b"\0"] {
+            db.put(b"k", value).unwrap();
+            assert_eq!(db.get(b"k", None).unwrap(), Some(value.to_vec()));
+        }
+
+        // 删除与写入空值是不同的状态
+        let empty = db.put(b"k", b"").unwrap();
+        db.delete(b"k").unwrap();
+        assert!(db.get(b"k", None).unwrap().is_none());
+        assert_ne!(db.get_root_hash().unwrap(), empty);
+    }
+}
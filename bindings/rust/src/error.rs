@@ -0,0 +1,217 @@
+//! 错误类型
+//! C侧的每个状态码都有对应的变体，`Error::raw_code` 返回原始状态码；之后可能增加变体
+
+use std::ffi::CStr;
+use std::fmt;
+use std::io;
+use std::os::raw::c_int;
+
+use crate::ffi::{
+    amdb_error_string, AMDB_BUSY, AMDB_FATAL, AMDB_INVALID_ARG, AMDB_IO_ERROR, AMDB_MEMORY_ERROR,
+    AMDB_NOT_FOUND, AMDB_READ_ONLY, AMDB_TIMED_OUT,
+};
+use crate::{Root, Version};
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// 引擎返回的一般错误（`AMDB_ERROR`）或未知状态码，及其说明
+    Engine { code: i32, message: String },
+    /// 引擎找不到请求的对象（`AMDB_NOT_FOUND`）；读取不存在的键返回 `Ok(None)` 而不是此错误
+    NotFound,
+    /// 引擎内存分配失败（`AMDB_MEMORY_ERROR`）
+    OutOfMemory,
+    /// 引擎返回致命错误（`AMDB_FATAL`），数据库随即中毒，之后的调用返回 `Error::Poisoned`
+    Fatal(String),
+    /// 值的长度超过 `OpenOptions::max_value_size`
+    ValueTooLarge { size: u64, max: u64 },
+    /// 写批次的估算大小超过 `WriteBatch::max_size`
+    BatchTooLarge { size: usize, max: usize },
+    /// 复制批次的序列号不连续（重复或有缺口）
+    SequenceMismatch { expected: u64, got: u64 },
+    /// 要清理的版本仍被快照等固定，`version` 为最早的固定状态的数据库版本
+    VersionPinned { version: Version },
+    /// 同名的树已存在
+    TreeExists(String),
+    /// 树不存在
+    TreeNotFound(String),
+    /// 树已冻结（见 `Database::freeze_tree`），拒绝写入
+    Frozen(String),
+    /// 键未通过长度限制或自定义校验
+    InvalidKey(String),
+    /// 值未通过树的校验钩子（见 `TreeHooks::validate_values`）
+    InvalidValue(String),
+    /// 类型化访问的编码或解码失败（见 `Codec`）
+    Codec(String),
+    /// 数据库的根哈希不在给定的根哈希之中
+    RootMismatch { actual: Root },
+    /// 分支起始版本之后数据库已有新的提交（见 `Branch::promote`）
+    Diverged { base: Version, head: Version },
+    /// 持久化的数据（快照文件、保留记录等）格式不正确
+    Corruption(String),
+    /// 参数不合法（例如数据目录路径中含NUL字节），或引擎返回 `AMDB_INVALID_ARG`
+    InvalidArgument(String),
+    /// 流式读写时底层 reader/writer 的I/O错误，或引擎返回 `AMDB_IO_ERROR`
+    Io(io::Error),
+    /// 引擎此前返回过致命错误，数据库已不可用，需要重新打开
+    Poisoned,
+    /// 数据库已通过 `Database::close` 关闭
+    Closed,
+    /// 引擎暂时无法处理请求，稍后重试可能成功（见 `RetryPolicy`）
+    Busy,
+    /// 等待引擎内部资源超时，稍后重试可能成功（见 `RetryPolicy`）
+    TimedOut,
+    /// 数据库以只读方式打开（见 `OpenOptions::read_only`），拒绝写入
+    ReadOnly,
+    /// 引擎的后台任务此前失败过（见 `Database::background_error`），`message` 为其说明，
+    /// `error` 为本次调用本身的错误（也可通过 `Error::inner` 取得）
+    Background { error: Box<Error>, message: String },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// `Error` 的别名，便于与其他库的错误类型并列导入
+pub type AmdbError = Error;
+
+impl Error {
+    pub(crate) fn from_status(status: c_int) -> Self {
+        let message = unsafe { CStr::from_ptr(amdb_error_string(status)) }
+            .to_string_lossy()
+            .into_owned();
+        match status {
+            AMDB_NOT_FOUND => Error::NotFound,
+            AMDB_INVALID_ARG => Error::InvalidArgument(message),
+            AMDB_IO_ERROR => Error::Io(io::Error::other(message)),
+            AMDB_MEMORY_ERROR => Error::OutOfMemory,
+            AMDB_FATAL => Error::Fatal(message),
+            AMDB_BUSY => Error::Busy,
+            AMDB_TIMED_OUT => Error::TimedOut,
+            AMDB_READ_ONLY => Error::ReadOnly,
+            _ => Error::Engine {
+                code: status,
+                message,
+            },
+        }
+    }
+
+    /// 对应的C侧状态码（见 `ffi`）；只在Rust侧产生的错误返回 `None`
+    pub fn raw_code(&self) -> Option<i32> {
+        match self {
+            Error::Background { error, .. } => error.raw_code(),
+            Error::Engine { code, .. } => Some(*code),
+            Error::NotFound => Some(AMDB_NOT_FOUND),
+            Error::InvalidArgument(_) => Some(AMDB_INVALID_ARG),
+            Error::Io(_) => Some(AMDB_IO_ERROR),
+            Error::OutOfMemory => Some(AMDB_MEMORY_ERROR),
+            Error::Fatal(_) => Some(AMDB_FATAL),
+            Error::Busy => Some(AMDB_BUSY),
+            Error::TimedOut => Some(AMDB_TIMED_OUT),
+            Error::ReadOnly => Some(AMDB_READ_ONLY),
+            _ => None,
+        }
+    }
+
+    /// 是否为稍后重试可能成功的暂时性错误
+    pub fn is_transient(&self) -> bool {
+        matches!(self.inner(), Error::Busy | Error::TimedOut)
+    }
+
+    /// 去掉后台错误说明后的错误本身，按变体匹配时使用
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::Background { error, .. } => error.inner(),
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Engine { code, message } => write!(f, "{} (status {})", message, code),
+            Error::NotFound => write!(f, "not found"),
+            Error::OutOfMemory => write!(f, "engine out of memory"),
+            Error::Fatal(msg) => write!(f, "fatal engine error: {}", msg),
+            Error::ValueTooLarge { size, max } => {
+                write!(f, "value of {} bytes exceeds the {} byte limit", size, max)
+            }
+            Error::BatchTooLarge { size, max } => {
+                write!(f, "write batch of about {} bytes exceeds the {} byte limit", size, max)
+            }
+            Error::SequenceMismatch { expected, got } => {
+                write!(f, "expected replication sequence {}, got {}", expected, got)
+            }
+            Error::VersionPinned { version } => {
+                write!(f, "database version {} is still pinned", version)
+            }
+            Error::TreeExists(name) => write!(f, "tree {:?} already exists", name),
+            Error::TreeNotFound(name) => write!(f, "tree {:?} not found", name),
+            Error::Frozen(name) => write!(f, "tree {:?} is frozen", name),
+            Error::InvalidKey(reason) => write!(f, "invalid key: {}", reason),
+            Error::InvalidValue(reason) => write!(f, "invalid value: {}", reason),
+            Error::Codec(reason) => write!(f, "codec error: {}", reason),
+            Error::RootMismatch { actual } => {
+                write!(f, "root hash {} matches none of the expected roots", actual)
+            }
+            Error::Diverged { base, head } => write!(
+                f,
+                "head moved to version {} since the branch started at version {}",
+                head, base
+            ),
+            Error::Corruption(msg) => write!(f, "corruption: {}", msg),
+            Error::InvalidArgument(msg) => write!(f, "invalid argument: {}", msg),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Poisoned => write!(f, "database is poisoned by an earlier fatal error"),
+            Error::Closed => write!(f, "database is closed"),
+            Error::Busy => write!(f, "engine is busy"),
+            Error::TimedOut => write!(f, "engine operation timed out"),
+            Error::ReadOnly => write!(f, "database is opened read-only"),
+            Error::Background { error, message } => {
+                write!(f, "{} (after background error: {})", error, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Background { error, .. } => Some(&**error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_codes() {
+        for status in -9..=-1 {
+            let error = Error::from_status(status);
+            assert_eq!(error.raw_code(), Some(status), "{}", error);
+        }
+        assert!(matches!(Error::from_status(-2), Error::NotFound));
+        assert!(matches!(Error::from_status(-4), Error::Io(_)));
+        assert!(matches!(Error::from_status(-6), Error::Fatal(_)));
+        assert!(matches!(Error::from_status(-99), Error::Engine { code: -99, .. }));
+        assert_eq!(Error::Closed.raw_code(), None);
+
+        let error = Error::Background {
+            error: Box::new(Error::Busy),
+            message: "flush failed".to_string(),
+        };
+        assert_eq!(error.raw_code(), Some(AMDB_BUSY));
+        assert!(error.is_transient());
+        assert!(matches!(error.inner(), Error::Busy));
+        assert_eq!(error.to_string(), "engine is busy (after background error: flush failed)");
+    }
+}
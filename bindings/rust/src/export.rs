@@ -0,0 +1,386 @@
+//! 流式导入导出
+//! `Database::export` 按键序把某个数据库版本的全部键值写入任意 `Write`，边读边写，不把整个状态读入内存；
+//! `Database::import` 从 `Read` 读回，用于把已有链状态迁移进来，比逐个 `put` 快得多。
+//!
+//! 流格式（整数均为小端序）：
+//!
+//! ```text
+//! 魔数 "AMDBSTRM" | 格式版本 u32 | 标志 u8 | 数据库版本 u64 | 根哈希 32字节
+//! 每个条目：标记 1 (u8) | 键长度 u32 | 键 | 值长度 u64 | 值 [| 证明长度 u32 | 证明]
+//! 结尾：标记 0 (u8) | 条目数 u64
+//! ```
+//!
+//! 标志的最低位表示每个条目带有相对头部根哈希的证明（`Proof::to_bytes`），导入时逐条验证，
+//! 接收方无需信任导出方即可确认收到的是该根哈希下的状态。条目数放在结尾，导出前不必知道条目总数。
+//!
+//! 导入默认每 `ImportOptions::batch_entries` 个条目提交一次，内存占用有界；`ingest` 模式把全部条目放进
+//! 一次提交，引擎只在最后由全部键自底向上构建一次Merkle树。导入不会删除数据库中已有的其他键；
+//! 源数据库中删除的键在Merkle树中留有墓碑而流中没有，因此只有源状态不含删除、且导入空数据库时
+//! 最终的根哈希才与导出时一致。
+
+use std::io::{BufReader, BufWriter, Read, Write};
+
+use crate::{BatchItem, Database, Error, Proof, Result, Root, Version};
+
+const MAGIC: &[u8; 8] = b"AMDBSTRM";
+const FORMAT_VERSION: u32 = 1;
+const FLAG_PROOFS: u8 = 1;
+
+const TAG_END: u8 = 0;
+const TAG_ENTRY: u8 = 1;
+
+/// 导出的选项
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    proofs: bool,
+}
+
+impl ExportOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 为每个条目附带证明（默认不附带）；只能导出最新版本，导出期间阻塞本句柄的写入
+    pub fn proofs(&mut self, enabled: bool) -> &mut Self {
+        self.proofs = enabled;
+        self
+    }
+}
+
+/// 导入的选项
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    batch_entries: usize,
+    ingest: bool,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        ImportOptions {
+            batch_entries: 10_000,
+            ingest: false,
+        }
+    }
+}
+
+impl ImportOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 每次提交的条目数（默认10000），`ingest` 模式下不起作用
+    pub fn batch_entries(&mut self, entries: usize) -> &mut Self {
+        self.batch_entries = entries;
+        self
+    }
+
+    /// 把全部条目放进一次提交（默认关闭）：只产生一个数据库版本，Merkle树只构建一次，
+    /// 但需要把整个流读入内存
+    pub fn ingest(&mut self, enabled: bool) -> &mut Self {
+        self.ingest = enabled;
+        self
+    }
+}
+
+/// 流头部与结尾中的元数据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportInfo {
+    /// 导出的数据库版本
+    pub version: Version,
+    /// 该版本的根哈希
+    pub root_hash: Root,
+    pub entry_count: u64,
+    /// 条目是否带有证明
+    pub proofs: bool,
+}
+
+impl Database {
+    /// 把数据库版本 `version`（`None` 表示最新版本）的全部键值写入 `writer`
+    pub fn export<W: Write>(&self, writer: W, version: Option<Version>) -> Result<ExportInfo> {
+        self.export_with(writer, version, &ExportOptions::new())
+    }
+
+    /// 同 `export`，按 `options` 导出；要求证明时 `version` 不是最新版本则返回 `Error::InvalidArgument`
+    pub fn export_with<W: Write>(
+        &self,
+        writer: W,
+        version: Option<Version>,
+        options: &ExportOptions,
+    ) -> Result<ExportInfo> {
+        let mut out = BufWriter::new(writer);
+        // 证明只对应最新状态，导出期间不能有写入
+        let _writes = options.proofs.then(|| self.write_lock());
+        let latest = self.state_version()?;
+        let version = version.unwrap_or(latest);
+        if options.proofs && version != latest {
+            return Err(Error::InvalidArgument(format!(
+                "proofs can only be exported for the latest version {}, not {}",
+                latest, version
+            )));
+        }
+
+        let mut info = ExportInfo {
+            version,
+            root_hash: self.get_root_hash()?,
+            entry_count: 0,
+            proofs: options.proofs,
+        };
+        // 版本0是空数据库，没有快照
+        let snapshot = match version {
+            Version(0) => None,
+            _ => Some(self.snapshot_at(version)?),
+        };
+        if let Some(snapshot) = &snapshot {
+            info.root_hash = snapshot.root_hash();
+        }
+        write_header(&mut out, &info)?;
+        if let Some(snapshot) = &snapshot {
+            for entry in snapshot.iter(..).with_reserved() {
+                let (key, value) = entry?;
+                write_entry(&mut out, &key, &value)?;
+                if options.proofs {
+                    let (_, proof) = self.get_with_proof(&key, None)?;
+                    if proof.root_hash() != info.root_hash {
+                        return Err(Error::RootMismatch {
+                            actual: proof.root_hash(),
+                        });
+                    }
+                    let proof = proof.to_bytes();
+                    out.write_all(&len_u32(proof.len(), "proof")?.to_le_bytes())?;
+                    out.write_all(&proof)?;
+                }
+                info.entry_count += 1;
+            }
+        }
+        out.write_all(&[TAG_END])?;
+        out.write_all(&info.entry_count.to_le_bytes())?;
+        out.flush()?;
+        Ok(info)
+    }
+
+    /// 导入 `export` 写出的流，返回流中的元数据和导入后的根哈希
+    pub fn import<R: Read>(&self, reader: R) -> Result<(ExportInfo, Root)> {
+        self.import_with(reader, &ImportOptions::new())
+    }
+
+    /// 同 `import`，按 `options` 导入；`batch_entries` 为0时返回 `Error::InvalidArgument`。
+    /// 证明验证失败或流损坏时返回错误，此前已提交的批次不会撤销
+    pub fn import_with<R: Read>(
+        &self,
+        reader: R,
+        options: &ImportOptions,
+    ) -> Result<(ExportInfo, Root)> {
+        if options.batch_entries == 0 {
+            return Err(Error::InvalidArgument(
+                "import batch size must be positive".to_string(),
+            ));
+        }
+        let mut input = BufReader::new(reader);
+        let mut info = read_header(&mut input)?;
+
+        let mut pending: Vec<BatchItem> = Vec::new();
+        let mut root_hash = self.get_root_hash()?;
+        let mut entry_count = 0u64;
+        loop {
+            let [tag] = read_array(&mut input)?;
+            match tag {
+                TAG_ENTRY => {}
+                TAG_END => break,
+                _ => return Err(Error::Corruption(format!("invalid stream tag {}", tag))),
+            }
+            let key_len = u32::from_le_bytes(read_array(&mut input)?);
+            let key = read_vec(&mut input, key_len as u64)?;
+            let value_len = u64::from_le_bytes(read_array(&mut input)?);
+            let value = read_vec(&mut input, value_len)?;
+            if info.proofs {
+                let proof_len = u32::from_le_bytes(read_array(&mut input)?);
+                let proof = Proof::from_bytes(&read_vec(&mut input, proof_len as u64)?)?;
+                if !proof.verify(&info.root_hash, &key, &value) {
+                    return Err(Error::Corruption(format!(
+                        "proof for key {:?} does not match the stream root hash",
+                        String::from_utf8_lossy(&key)
+                    )));
+                }
+            }
+            self.options.check_key_format(&key)?;
+            pending.push((key, Some(value)));
+            entry_count += 1;
+            if !options.ingest && pending.len() >= options.batch_entries {
+                root_hash = self.batch_put(&pending)?;
+                pending.clear();
+            }
+        }
+
+        info.entry_count = u64::from_le_bytes(read_array(&mut input)?);
+        if info.entry_count != entry_count {
+            return Err(Error::Corruption(format!(
+                "stream has {} entries, trailer says {}",
+                entry_count, info.entry_count
+            )));
+        }
+        if input.read(&mut [0u8; 1])? != 0 {
+            return Err(Error::Corruption(
+                "trailing data after the stream trailer".to_string(),
+            ));
+        }
+        if !pending.is_empty() {
+            root_hash = self.batch_put(&pending)?;
+        }
+        Ok((info, root_hash))
+    }
+}
+
+fn write_header(out: &mut impl Write, info: &ExportInfo) -> Result<()> {
+    out.write_all(MAGIC)?;
+    out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    out.write_all(&[if info.proofs { FLAG_PROOFS } else { 0 }])?;
+    out.write_all(&info.version.0.to_le_bytes())?;
+    out.write_all(&*info.root_hash)?;
+    Ok(())
+}
+
+fn write_entry(out: &mut impl Write, key: &[u8], value: &[u8]) -> Result<()> {
+    out.write_all(&[TAG_ENTRY])?;
+    out.write_all(&len_u32(key.len(), "key")?.to_le_bytes())?;
+    out.write_all(key)?;
+    out.write_all(&(value.len() as u64).to_le_bytes())?;
+    out.write_all(value)?;
+    Ok(())
+}
+
+fn len_u32(len: usize, what: &str) -> Result<u32> {
+    u32::try_from(len).map_err(|_| Error::InvalidArgument(format!("{} longer than 4 GiB", what)))
+}
+
+fn read_header(input: &mut impl Read) -> Result<ExportInfo> {
+    let magic: [u8; 8] = read_array(input)?;
+    if &magic != MAGIC {
+        return Err(Error::Corruption("not an AmDb export stream".to_string()));
+    }
+    let format = u32::from_le_bytes(read_array(input)?);
+    if format != FORMAT_VERSION {
+        return Err(Error::Corruption(format!(
+            "unsupported stream format version {}",
+            format
+        )));
+    }
+    let [flags] = read_array(input)?;
+    if flags & !FLAG_PROOFS != 0 {
+        return Err(Error::Corruption(format!(
+            "unknown stream flags {:#x}",
+            flags
+        )));
+    }
+    Ok(ExportInfo {
+        proofs: flags & FLAG_PROOFS != 0,
+        version: Version(u64::from_le_bytes(read_array(input)?)),
+        root_hash: Root(read_array(input)?),
+        entry_count: 0,
+    })
+}
+
+fn read_array<const N: usize>(input: &mut impl Read) -> Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    input.read_exact(&mut buf).map_err(|e| match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => truncated(),
+        _ => Error::Io(e),
+    })?;
+    Ok(buf)
+}
+
+fn read_vec(input: &mut impl Read, len: u64) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    input.take(len).read_to_end(&mut buf)?;
+    if (buf.len() as u64) < len {
+        return Err(truncated());
+    }
+    Ok(buf)
+}
+
+fn truncated() -> Error {
+    Error::Corruption("truncated export stream".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let _ = std::fs::remove_dir_all("./test_data/export_src");
+        let _ = std::fs::remove_dir_all("./test_data/export_dst");
+        let _ = std::fs::remove_dir_all("./test_data/export_ingest");
+        let source = Database::new("./test_data/export_src").unwrap();
+        source.put(b"a", b"1").unwrap();
+        source.put(b"b", &[7u8; 1000]).unwrap();
+        let old_root = source.put(b"c", b"3").unwrap();
+        source.delete(b"a").unwrap();
+
+        let mut stream = Vec::new();
+        let info = source.export(&mut stream, None).unwrap();
+        assert_eq!(info.entry_count, 2);
+        assert_eq!(info.root_hash, source.get_root_hash().unwrap());
+
+        let target = Database::new("./test_data/export_dst").unwrap();
+        let (read, root) = target
+            .import_with(&stream[..], ImportOptions::new().batch_entries(1))
+            .unwrap();
+        assert_eq!(read, info);
+        // 源数据库中 a 的墓碑计入根哈希，流中没有
+        assert_ne!(root, info.root_hash);
+        assert_eq!(target.get(b"b", None).unwrap(), Some(vec![7u8; 1000]));
+        assert!(target.get(b"a", None).unwrap().is_none());
+
+        // 历史版本，以 ingest 模式一次导入
+        let mut stream = Vec::new();
+        let info = source.export(&mut stream, Some(Version(3))).unwrap();
+        assert_eq!((info.entry_count, info.root_hash), (3, old_root));
+        let ingested = Database::new("./test_data/export_ingest").unwrap();
+        let (_, root) = ingested
+            .import_with(&stream[..], ImportOptions::new().ingest(true))
+            .unwrap();
+        assert_eq!(root, old_root);
+        assert_eq!(ingested.state_version().unwrap(), 1);
+
+        stream.truncate(stream.len() - 1);
+        assert!(matches!(
+            target.import(&stream[..]),
+            Err(Error::Corruption(_))
+        ));
+    }
+
+    #[test]
+    fn test_export_with_proofs() {
+        let _ = std::fs::remove_dir_all("./test_data/export_proofs");
+        let _ = std::fs::remove_dir_all("./test_data/export_proofs_dst");
+        let _ = std::fs::remove_dir_all("./test_data/export_proofs_tampered");
+        let source = Database::new("./test_data/export_proofs").unwrap();
+        let mut stream = Vec::new();
+        let empty = source.export(&mut stream, None).unwrap();
+        assert_eq!((empty.version, empty.entry_count), (Version(0), 0));
+
+        source.put(b"k1", b"v1").unwrap();
+        source.put(b"k2", b"v2").unwrap();
+        let options = ExportOptions::new().proofs(true).clone();
+        assert!(matches!(
+            source.export_with(Vec::new(), Some(Version(1)), &options),
+            Err(Error::InvalidArgument(_))
+        ));
+
+        let mut stream = Vec::new();
+        let info = source.export_with(&mut stream, None, &options).unwrap();
+        assert!(info.proofs);
+        let target = Database::new("./test_data/export_proofs_dst").unwrap();
+        let (_, root) = target.import(&stream[..]).unwrap();
+        assert_eq!(root, info.root_hash);
+
+        // 篡改值后证明验证失败
+        let pos = stream.windows(2).position(|w| w == b"v2").unwrap();
+        stream[pos + 1] = b'X';
+        let tampered = Database::new("./test_data/export_proofs_tampered").unwrap();
+        assert!(matches!(
+            tampered.import(&stream[..]),
+            Err(Error::Corruption(_))
+        ));
+    }
+}
@@ -0,0 +1,198 @@
+//! 读取回退
+//! 迁移期间新库中没有的键到旧库（任意 `ReadStore`）中查找，可选地把查到的值回填到新库，
+//! 从而不停机地把数据按需迁入新库。新库中写入过又删除的键不回退，已删除的值不会从旧库复活。
+//! `Fallback` 本身也实现 `ReadStore`，可以作为另一个 `Fallback` 的次级库组成回退链。
+
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::ops::RangeBounds;
+
+use crate::{amdb_multi_written, Database, KeyValue, ReadStore, Result, Scan};
+
+/// 先读新库、再读次级库的只读视图，见 `Database::with_fallback`
+pub struct Fallback<'a, S> {
+    db: &'a Database,
+    secondary: S,
+    backfill: bool,
+}
+
+impl Database {
+    /// 键在本库中从未写入时到 `secondary` 中查找
+    pub fn with_fallback<S: ReadStore>(&self, secondary: S) -> Fallback<'_, S> {
+        Fallback {
+            db: self,
+            secondary,
+            backfill: false,
+        }
+    }
+
+    /// 键是否写入过，已删除的键同样为真；供回退判断键是否属于本库
+    pub(crate) fn key_written(&self, key: &[u8]) -> Result<bool> {
+        let mut written = 0u8;
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            amdb_multi_written(*handle, &key.as_ptr(), &key.len(), 1, &mut written)
+        });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        Ok(written != 0)
+    }
+}
+
+impl<S: ReadStore> Fallback<'_, S> {
+    /// 从次级库读到的值是否写回本库（默认否）。回填是普通的写入，会改变根哈希；
+    /// 与同一个键的并发写入之间没有互斥，回填可能覆盖刚写入的值
+    pub fn backfill(&mut self, enabled: bool) -> &mut Self {
+        self.backfill = enabled;
+        self
+    }
+
+    pub fn secondary(&self) -> &S {
+        &self.secondary
+    }
+
+    /// 键的值：本库中存在时取本库的值，本库中从未写入时取次级库的值
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        if let Some(value) = self.db.get(key, None)? {
+            return Ok(Some(value));
+        }
+        if self.db.key_written(key)? {
+            return Ok(None);
+        }
+        let value = self.secondary.get(key)?;
+        if let (true, Some(value)) = (self.backfill, &value) {
+            self.db.put(key, value)?;
+        }
+        Ok(value)
+    }
+
+    /// 合并本库和次级库的范围扫描，键相同时以本库为准；扫描不回填
+    pub fn scan(&self, range: impl RangeBounds<Vec<u8>>) -> FallbackScan<'_, S> {
+        let bounds = (range.start_bound().cloned(), range.end_bound().cloned());
+        FallbackScan {
+            db: self.db,
+            primary: self.db.scan(bounds.clone()).peekable(),
+            secondary: self.secondary.scan(bounds).peekable(),
+        }
+    }
+}
+
+impl<S: ReadStore> ReadStore for Fallback<'_, S> {
+    type Scan<'a>
+        = FallbackScan<'a, S>
+    where
+        Self: 'a;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Fallback::get(self, key)
+    }
+
+    fn scan(&self, range: impl RangeBounds<Vec<u8>>) -> FallbackScan<'_, S> {
+        Fallback::scan(self, range)
+    }
+}
+
+/// `Fallback::scan` 的迭代器；两边的错误原样返回
+pub struct FallbackScan<'a, S: ReadStore + 'a> {
+    db: &'a Database,
+    primary: Peekable<Scan<'a>>,
+    secondary: Peekable<S::Scan<'a>>,
+}
+
+impl<S: ReadStore> Iterator for FallbackScan<'_, S> {
+    type Item = Result<KeyValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let order = match (self.primary.peek(), self.secondary.peek()) {
+                (None, None) => return None,
+                (Some(Err(_)), _) | (Some(_), None) => Ordering::Less,
+                (_, Some(Err(_))) | (None, Some(_)) => Ordering::Greater,
+                (Some(Ok((primary, _))), Some(Ok((secondary, _)))) => primary.cmp(secondary),
+            };
+            match order {
+                Ordering::Less => return self.primary.next(),
+                Ordering::Equal => {
+                    self.secondary.next();
+                    return self.primary.next();
+                }
+                Ordering::Greater => {
+                    let entry = match self.secondary.next()? {
+                        Ok(entry) => entry,
+                        Err(e) => return Some(Err(e)),
+                    };
+                    // 只在次级库中存在的键，除非已在本库中删除
+                    match self.db.key_written(&entry.0) {
+                        Ok(true) => continue,
+                        Ok(false) => return Some(Ok(entry)),
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_get() {
+        let _ = std::fs::remove_dir_all("./test_data/fallback_old");
+        let _ = std::fs::remove_dir_all("./test_data/fallback_new");
+        let old = Database::new("./test_data/fallback_old").unwrap();
+        old.put(b"a", b"old").unwrap();
+        old.put(b"b", b"old").unwrap();
+        old.put(b"c", b"old").unwrap();
+        let db = Database::new("./test_data/fallback_new").unwrap();
+        db.put(b"a", b"new").unwrap();
+        db.put(b"b", b"new").unwrap();
+        db.delete(b"b").unwrap();
+
+        let mut fallback = db.with_fallback(&old);
+        assert_eq!(fallback.get(b"a").unwrap(), Some(b"new".to_vec()));
+        // 本库中删除的键不回退
+        assert!(fallback.get(b"b").unwrap().is_none());
+        assert_eq!(fallback.get(b"c").unwrap(), Some(b"old".to_vec()));
+        assert!(db.get(b"c", None).unwrap().is_none());
+        assert!(fallback.get(b"missing").unwrap().is_none());
+
+        fallback.backfill(true);
+        assert_eq!(fallback.get(b"c").unwrap(), Some(b"old".to_vec()));
+        assert_eq!(db.get(b"c", None).unwrap(), Some(b"old".to_vec()));
+    }
+
+    #[test]
+    fn test_fallback_chain_scan() {
+        let _ = std::fs::remove_dir_all("./test_data/fallback_chain_oldest");
+        let _ = std::fs::remove_dir_all("./test_data/fallback_chain_old");
+        let _ = std::fs::remove_dir_all("./test_data/fallback_chain_new");
+        let oldest = Database::new("./test_data/fallback_chain_oldest").unwrap();
+        oldest.put(b"k1", b"0").unwrap();
+        oldest.put(b"k4", b"0").unwrap();
+        let old = Database::new("./test_data/fallback_chain_old").unwrap();
+        old.put(b"k2", b"1").unwrap();
+        old.put(b"k4", b"1").unwrap();
+        let db = Database::new("./test_data/fallback_chain_new").unwrap();
+        db.put(b"k3", b"2").unwrap();
+        db.delete(b"k1").unwrap();
+
+        let chain = db.with_fallback(old.with_fallback(&oldest));
+        let entries: Vec<(Vec<u8>, Vec<u8>)> = chain
+            .scan(..)
+            .map(|e| e.map(|(k, v)| (k.into_vec(), v.into_vec())).unwrap())
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (b"k2".to_vec(), b"1".to_vec()),
+                (b"k3".to_vec(), b"2".to_vec()),
+                (b"k4".to_vec(), b"1".to_vec()),
+            ]
+        );
+        assert_eq!(chain.get(b"k4").unwrap(), Some(b"1".to_vec()));
+        assert!(chain.get(b"k1").unwrap().is_none());
+    }
+}
@@ -0,0 +1,227 @@
+//! 故障注入（`faults` 特性，仅供测试）
+//! `inject` 让进程内的引擎在提交和刷新路径上的故障点按计数触发I/O错误、撕裂写入或直接结束进程；
+//! 故障对进程内所有打开的数据库生效。结束进程的故障须在子进程中触发：`run_in_child` 以只运行当前测试的方式
+//! 重新启动测试程序，在其中执行工作负载，父进程随后重新打开数据目录，用 `check_consistent`
+//! 断言恢复到了一致的版本。
+//!
+//! ```ignore
+//! #[test]
+//! fn crash_during_flush() {
+//!     let exit = faults::run_in_child("crash_during_flush", || {
+//!         let db = Database::new("./test_data/crash").unwrap();
+//!         faults::inject(FaultPoint::FlushVersions, FaultAction::Kill, 3).unwrap();
+//!         // 写入直到进程被结束
+//!     })
+//!     .unwrap();
+//!     assert_eq!(exit, ChildExit::Killed);
+//!     faults::check_consistent(&Database::new("./test_data/crash").unwrap()).unwrap();
+//! }
+//! ```
+
+use std::env;
+use std::ffi::CString;
+use std::process::{self, Command};
+
+use crate::backup::verify_against;
+use crate::{amdb_fault_clear, amdb_fault_inject, Database, Error, Result, Version, WriteBatch};
+
+/// 注入的故障结束进程时的退出码
+pub const KILL_EXIT_CODE: i32 = 86;
+
+/// 标记子进程要执行的工作负载，值为 `run_in_child` 的 `test_name`
+const CHILD_ENV: &str = "AMDB_FAULT_CHILD";
+
+/// 故障点
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultPoint {
+    /// 提交之后、按同步模式持久化之前
+    Commit,
+    /// 刷新WAL之前
+    FlushWal,
+    /// 把MemTable刷新到SSTable之前
+    FlushLsm,
+    /// 保存Merkle树之前
+    FlushMerkle,
+    /// 保存版本记录之前
+    FlushVersions,
+    /// 保存数据库元数据之前
+    FlushMetadata,
+}
+
+impl FaultPoint {
+    fn as_str(self) -> &'static str {
+        match self {
+            FaultPoint::Commit => "commit",
+            FaultPoint::FlushWal => "flush.wal",
+            FaultPoint::FlushLsm => "flush.lsm",
+            FaultPoint::FlushMerkle => "flush.merkle",
+            FaultPoint::FlushVersions => "flush.versions",
+            FaultPoint::FlushMetadata => "flush.metadata",
+        }
+    }
+}
+
+/// 故障点触发时的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultAction {
+    /// 该步骤失败：提交处的错误返回给调用方，刷新各步骤的错误由引擎记录后继续
+    IoError,
+    /// 该步骤的文件只写出一半，随即结束进程
+    TornWrite,
+    /// 直接结束进程，退出码为 `KILL_EXIT_CODE`
+    Kill,
+}
+
+impl FaultAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            FaultAction::IoError => "io_error",
+            FaultAction::TornWrite => "torn_write",
+            FaultAction::Kill => "kill",
+        }
+    }
+}
+
+/// 在故障点 `point` 第 `countdown + 1` 次经过时触发 `action`；同一故障点只保留最后一次注入
+pub fn inject(point: FaultPoint, action: FaultAction, countdown: u32) -> Result<()> {
+    let point = CString::new(point.as_str()).expect("fault point names contain no NUL");
+    let action = CString::new(action.as_str()).expect("fault action names contain no NUL");
+    let status = unsafe { amdb_fault_inject(point.as_ptr(), action.as_ptr(), countdown) };
+    if status != 0 {
+        return Err(Error::from_status(status));
+    }
+    Ok(())
+}
+
+/// 撤销全部尚未触发的故障
+pub fn clear() -> Result<()> {
+    let status = unsafe { amdb_fault_clear() };
+    if status != 0 {
+        return Err(Error::from_status(status));
+    }
+    Ok(())
+}
+
+/// 子进程的结束方式，见 `run_in_child`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChildExit {
+    /// 工作负载正常结束
+    Finished,
+    /// 被注入的故障结束
+    Killed,
+    /// 其他失败（例如工作负载panic），带退出码；被信号结束时为 `None`
+    Failed(Option<i32>),
+}
+
+/// 在子进程中执行 `workload`，返回子进程的结束方式。子进程重新运行当前测试程序中名为 `test_name`
+/// 的测试（按完整路径或能唯一匹配的部分），其中对同一 `test_name` 的调用执行 `workload` 后退出，不返回
+pub fn run_in_child(test_name: &str, workload: impl FnOnce()) -> Result<ChildExit> {
+    if env::var(CHILD_ENV).is_ok_and(|name| name == test_name) {
+        workload();
+        process::exit(0);
+    }
+    let output = Command::new(env::current_exe()?)
+        .args([test_name, "--test-threads=1", "--nocapture"])
+        .env(CHILD_ENV, test_name)
+        .output()?;
+    Ok(match output.status.code() {
+        Some(0) => ChildExit::Finished,
+        Some(KILL_EXIT_CODE) => ChildExit::Killed,
+        code => ChildExit::Failed(code),
+    })
+}
+
+/// 检查重新打开的数据库处于一致的版本：当前根哈希等于最近一次提交记录的根哈希，
+/// 且Merkle树的节点能重算出该根哈希。返回恢复到的数据库版本，不一致时返回 `Error::RootMismatch`
+pub fn check_consistent(db: &Database) -> Result<Version> {
+    let version = db.state_version()?;
+    let expected = match version {
+        Version(0) => db.batch_root_hash_at(&WriteBatch::new(), Version(0))?,
+        version => db.root_hash_at(version)?,
+    };
+    verify_against(db, &[expected])?;
+    db.frontier(version)?;
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OpenOptions, SyncMode};
+
+    /// 逐次提交并持久化，直到进程被注入的故障结束
+    fn write_until_killed(dir: &str, point: FaultPoint, action: FaultAction) {
+        let db = OpenOptions::new()
+            .sync_mode(SyncMode::EveryCommit)
+            .open(dir)
+            .unwrap();
+        inject(point, action, 3).unwrap();
+        for i in 0..20u64 {
+            db.put(&[b'k', (i % 7) as u8], &i.to_be_bytes()).unwrap();
+        }
+    }
+
+    fn crash_and_reopen(test_name: &str, dir: &str, point: FaultPoint, action: FaultAction) {
+        let _ = std::fs::remove_dir_all(dir);
+        let exit = run_in_child(test_name, || write_until_killed(dir, point, action)).unwrap();
+        assert_eq!(exit, ChildExit::Killed);
+        let db = Database::new(dir).unwrap();
+        // 第4次提交的持久化途中被结束，之前的提交都已落盘
+        assert!(check_consistent(&db).unwrap() >= Version(3));
+        assert_eq!(
+            db.get(b"k\x00", None).unwrap(),
+            Some(0u64.to_be_bytes().to_vec())
+        );
+    }
+
+    #[test]
+    fn test_kill_between_flush_steps() {
+        let _ = std::fs::remove_dir_all("./test_data/faults_kill");
+        crash_and_reopen(
+            "test_kill_between_flush_steps",
+            "./test_data/faults_kill",
+            FaultPoint::FlushVersions,
+            FaultAction::Kill,
+        );
+    }
+
+    #[test]
+    fn test_torn_write_merkle() {
+        let _ = std::fs::remove_dir_all("./test_data/faults_torn_merkle");
+        crash_and_reopen(
+            "test_torn_write_merkle",
+            "./test_data/faults_torn_merkle",
+            FaultPoint::FlushMerkle,
+            FaultAction::TornWrite,
+        );
+    }
+
+    #[test]
+    fn test_torn_write_versions() {
+        let _ = std::fs::remove_dir_all("./test_data/faults_torn_versions");
+        crash_and_reopen(
+            "test_torn_write_versions",
+            "./test_data/faults_torn_versions",
+            FaultPoint::FlushVersions,
+            FaultAction::TornWrite,
+        );
+    }
+
+    #[test]
+    fn test_io_error_at_commit() {
+        let _ = std::fs::remove_dir_all("./test_data/faults_io_error");
+        let db = OpenOptions::new()
+            .sync_mode(SyncMode::EveryCommit)
+            .open("./test_data/faults_io_error")
+            .unwrap();
+        inject(FaultPoint::Commit, FaultAction::IoError, 1).unwrap();
+        db.put(b"a", b"1").unwrap();
+        assert!(db.put(b"b", b"2").is_err());
+        db.put(b"c", b"3").unwrap();
+        // 撤销后不再触发
+        inject(FaultPoint::Commit, FaultAction::Kill, 0).unwrap();
+        clear().unwrap();
+        db.put(b"d", b"4").unwrap();
+        assert_eq!(check_consistent(&db).unwrap(), 4);
+    }
+}
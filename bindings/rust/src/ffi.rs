@@ -0,0 +1,43 @@
+//! 底层FFI
+//! 重新导出 `amdb-sys` 中C API（`bindings/c/amdb.h`）的原始声明，供调用安全封装尚未覆盖的引擎入口，或与其他语言共享句柄
+//! （见 `Database::into_raw`）。
+//!
+//! 这里的函数都是 `unsafe` 的，调用方须自行遵守头文件中的约定（缓冲区长度、释放函数等）。
+//! 本模块随C API变化，不受本crate的语义化版本约束。
+
+pub use amdb_sys::*;
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+    use std::slice;
+
+    use super::*;
+    use crate::Database;
+
+    #[test]
+    fn test_call_through_ffi() {
+        let _ = std::fs::remove_dir_all("./test_data/ffi");
+        let db = Database::new("./test_data/ffi").unwrap();
+        db.put(b"k", b"v").unwrap();
+        let handle = db.as_raw().unwrap();
+
+        let mut result = AmdbResult {
+            status: 0,
+            error_msg: ptr::null(),
+            data: ptr::null_mut(),
+            data_len: 0,
+        };
+        unsafe {
+            assert_eq!(amdb_get(handle, b"k".as_ptr(), 1, 0, &mut result), AMDB_OK);
+            assert_eq!(
+                slice::from_raw_parts(result.data as *const u8, result.data_len),
+                b"v"
+            );
+            amdb_free_result(&mut result);
+        }
+
+        db.close().unwrap();
+        assert!(db.as_raw().is_err());
+    }
+}
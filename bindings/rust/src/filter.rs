@@ -0,0 +1,216 @@
+//! 引擎侧的值过滤
+//! `Database::scan_filtered` 把简单的值条件（长度、前缀、固定偏移处的字节）交给引擎，在复制结果之前求值，
+//! 不满足条件的键值对不跨越FFI，适合只命中少量键的分析型扫描。
+//! 开启 `OpenOptions::value_checksums` 时引擎看到的值带有尾部，条件在这里换算后下发，结果与未开启时相同。
+
+use std::ops::{Bound, RangeBounds};
+
+use crate::{
+    amdb_range_query_filtered, collect_range, AmdbValueFilter, Database, Entry, Result, Scan,
+    AMDB_FILTER_BYTES_AT, AMDB_FILTER_LENGTH, AMDB_FILTER_PREFIX, TRAILER_LEN,
+};
+
+/// 值过滤条件，见 `Database::scan_filtered`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueFilter {
+    /// 值长度在 [min, max] 内
+    Length { min: u64, max: u64 },
+    /// 值以给定字节开头
+    Prefix(Vec<u8>),
+    /// 值从 `offset` 起的字节等于 `bytes`；值不够长时不满足
+    BytesAt { offset: u64, bytes: Vec<u8> },
+    /// 同时满足全部条件；为空时总是满足
+    All(Vec<ValueFilter>),
+}
+
+impl ValueFilter {
+    /// 值长度在 `range` 内
+    pub fn length(range: impl RangeBounds<u64>) -> Self {
+        let min = match range.start_bound() {
+            Bound::Included(&min) => min,
+            Bound::Excluded(&min) => min.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let max = match range.end_bound() {
+            Bound::Included(&max) => max,
+            Bound::Excluded(&0) => return ValueFilter::Length { min: 1, max: 0 },
+            Bound::Excluded(&max) => max - 1,
+            Bound::Unbounded => u64::MAX,
+        };
+        ValueFilter::Length { min, max }
+    }
+
+    /// 同时满足本条件和 `other`
+    pub fn and(self, other: ValueFilter) -> Self {
+        match self {
+            ValueFilter::All(mut filters) => {
+                filters.push(other);
+                ValueFilter::All(filters)
+            }
+            filter => ValueFilter::All(vec![filter, other]),
+        }
+    }
+
+    /// 展开为引擎的条件列表；`trailer` 为引擎中值尾部的字节数
+    fn flatten<'f>(&'f self, trailer: u64, out: &mut Vec<AmdbValueFilter>) {
+        let raw = |kind, min_len, max_len, offset, bytes: &'f [u8]| AmdbValueFilter {
+            kind,
+            min_len,
+            max_len,
+            offset,
+            bytes: bytes.as_ptr(),
+            bytes_len: bytes.len(),
+        };
+        // 比较范围不能伸入尾部
+        let covering = |end: u64| {
+            raw(
+                AMDB_FILTER_LENGTH,
+                end.saturating_add(trailer),
+                u64::MAX,
+                0,
+                &[],
+            )
+        };
+        match self {
+            ValueFilter::Length { min, max } => out.push(raw(
+                AMDB_FILTER_LENGTH,
+                min.saturating_add(trailer),
+                max.saturating_add(trailer),
+                0,
+                &[],
+            )),
+            ValueFilter::Prefix(prefix) => {
+                if trailer > 0 {
+                    out.push(covering(prefix.len() as u64));
+                }
+                out.push(raw(AMDB_FILTER_PREFIX, 0, 0, 0, prefix));
+            }
+            ValueFilter::BytesAt { offset, bytes } => {
+                if trailer > 0 {
+                    out.push(covering(offset.saturating_add(bytes.len() as u64)));
+                }
+                out.push(raw(AMDB_FILTER_BYTES_AT, 0, 0, *offset, bytes));
+            }
+            ValueFilter::All(filters) => {
+                for filter in filters {
+                    filter.flatten(trailer, out);
+                }
+            }
+        }
+    }
+}
+
+impl Database {
+    /// 同 `scan`，但只返回值满足 `filter` 的键值对；条件由引擎求值，不满足的值不复制给调用方。
+    /// 过滤扫描不分页，首次迭代时一次读出整个范围中满足条件的部分。
+    /// 开启大值分离时迭代产生 `Error::InvalidArgument`
+    pub fn scan_filtered(&self, range: impl RangeBounds<Vec<u8>>, filter: ValueFilter) -> Scan<'_> {
+        self.scan(range).filtered(filter)
+    }
+
+    pub(crate) fn range_query_filtered(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        filter: &ValueFilter,
+    ) -> Result<Vec<Entry>> {
+        self.check_inline("scan_filtered")?;
+        let trailer = if self.options.value_checksums {
+            TRAILER_LEN as u64
+        } else {
+            0
+        };
+        let mut filters = Vec::new();
+        filter.flatten(trailer, &mut filters);
+        let handle = self.live_handle()?;
+        let entries = collect_range(&self.state, |results, count| {
+            self.retry_status(|| unsafe {
+                amdb_range_query_filtered(
+                    *handle,
+                    start.as_ptr(),
+                    start.len(),
+                    end.as_ptr(),
+                    end.len(),
+                    filters.as_ptr(),
+                    filters.len(),
+                    results,
+                    count,
+                )
+            })
+        })?;
+        self.open_entries(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenOptions;
+
+    fn keys(scan: Scan<'_>) -> Vec<Vec<u8>> {
+        scan.map(|e| e.unwrap().0.into_vec()).collect()
+    }
+
+    #[test]
+    fn test_scan_filtered() {
+        let _ = std::fs::remove_dir_all("./test_data/scan_filtered");
+        let db = Database::new("./test_data/scan_filtered").unwrap();
+        db.put(b"a", b"user:alice").unwrap();
+        db.put(b"b", b"user:bob").unwrap();
+        db.put(b"c", b"group:admins").unwrap();
+        db.put(b"d", b"x").unwrap();
+
+        let users = ValueFilter::Prefix(b"user:".to_vec());
+        assert_eq!(
+            keys(db.scan_filtered(.., users.clone())),
+            vec![b"a".to_vec(), b"b".to_vec()]
+        );
+        assert_eq!(
+            keys(db.scan_filtered(b"b".to_vec().., users.clone())),
+            vec![b"b".to_vec()]
+        );
+        assert_eq!(
+            keys(db.scan_filtered(.., ValueFilter::length(..2))),
+            vec![b"d".to_vec()]
+        );
+        let bob = ValueFilter::BytesAt {
+            offset: 5,
+            bytes: b"bob".to_vec(),
+        };
+        assert_eq!(keys(db.scan_filtered(.., bob)), vec![b"b".to_vec()]);
+        let long_users = users.and(ValueFilter::length(9..));
+        assert_eq!(keys(db.scan_filtered(.., long_users)), vec![b"a".to_vec()]);
+        assert_eq!(
+            keys(db.scan_filtered(.., ValueFilter::All(Vec::new()))).len(),
+            4
+        );
+    }
+
+    #[test]
+    fn test_scan_filtered_with_checksums() {
+        let _ = std::fs::remove_dir_all("./test_data/scan_filtered_checksums");
+        let db = OpenOptions::new()
+            .value_checksums(true)
+            .open("./test_data/scan_filtered_checksums")
+            .unwrap();
+        db.put(b"a", b"abc").unwrap();
+        db.put(b"b", b"abcdef").unwrap();
+
+        // 条件不能匹配到尾部的字节
+        let tail = ValueFilter::BytesAt {
+            offset: 3,
+            bytes: b"d".to_vec(),
+        };
+        assert_eq!(keys(db.scan_filtered(.., tail)), vec![b"b".to_vec()]);
+        assert_eq!(
+            keys(db.scan_filtered(.., ValueFilter::length(3..=3))),
+            vec![b"a".to_vec()]
+        );
+        let (_, value) = db
+            .scan_filtered(.., ValueFilter::Prefix(b"abcd".to_vec()))
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(&*value, b"abcdef");
+    }
+}
@@ -0,0 +1,342 @@
+//! Merkle树右边缘
+//! `Database::frontier` 返回某个数据库版本的Merkle树从根到最大键所在叶子的路径上的全部节点，
+//! 分支节点带有全部16个子节点的哈希。只按递增顺序追加键的工作负载中，路径左侧的子树此后不再改变，
+//! 外部系统保存右边缘即可用 `Frontier::append` 继续计算追加更大的键之后的根哈希，
+//! 不需要整棵树，也可以据此核对另一方给出的根哈希只来自追加。
+
+use std::ptr;
+
+use crate::envelope::seal_with;
+use crate::merkle::HashScheme;
+use crate::proof::key_nibble;
+use crate::{
+    amdb_free_result, amdb_merkle_frontier, result_bytes, AmdbResult, Database, Error, Result, Root,
+    Version,
+};
+
+const STEP_EXTENSION: u8 = 1;
+const STEP_BRANCH: u8 = 2;
+
+/// 右边缘上的一个节点
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrontierNode {
+    Extension {
+        nibble: u8,
+        hash: [u8; 32],
+    },
+    /// 空位为 `None`
+    Branch {
+        children: Box<[Option<[u8; 32]>; 16]>,
+        hash: [u8; 32],
+    },
+    Leaf {
+        hash: [u8; 32],
+    },
+}
+
+impl FrontierNode {
+    pub fn hash(&self) -> [u8; 32] {
+        match self {
+            FrontierNode::Extension { hash, .. }
+            | FrontierNode::Branch { hash, .. }
+            | FrontierNode::Leaf { hash } => *hash,
+        }
+    }
+}
+
+/// Merkle树的右边缘，见 `Database::frontier`
+#[derive(Debug, Clone)]
+pub struct Frontier {
+    version: Version,
+    root_hash: Root,
+    last_key: Option<Vec<u8>>,
+    /// 第i个节点位于键的第i个nibble处，最后一个是叶子
+    nodes: Vec<FrontierNode>,
+    scheme: HashScheme,
+    /// 分支节点空位的占位哈希
+    empty: Vec<u8>,
+    checksums: bool,
+    blob_threshold: Option<u64>,
+}
+
+impl Frontier {
+    /// 读取右边缘时的数据库版本
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// 当前的根哈希，包括 `append` 追加的键
+    pub fn root_hash(&self) -> Root {
+        self.root_hash
+    }
+
+    /// 树中最大的键；空树为 `None`
+    pub fn last_key(&self) -> Option<&[u8]> {
+        self.last_key.as_deref()
+    }
+
+    /// 从根到最大键所在叶子的节点；空树为空
+    pub fn nodes(&self) -> &[FrontierNode] {
+        &self.nodes
+    }
+
+    /// 追加一个大于 `last_key` 的键，返回之后的根哈希，与数据库写入同一键值后的根哈希相同。
+    /// 键不大于 `last_key`，或与它只差末尾的零字节时返回 `Error::InvalidKey`
+    pub fn append(&mut self, key: &[u8], value: &[u8]) -> Result<Root> {
+        let value = seal_with(value, self.checksums, self.blob_threshold);
+        let leaf = self.scheme.leaf(key, &value);
+        let Some(last) = self.last_key.as_deref() else {
+            self.nodes = vec![FrontierNode::Leaf { hash: leaf }];
+            self.last_key = Some(key.to_vec());
+            self.root_hash = Root(leaf);
+            return Ok(self.root_hash);
+        };
+        if key <= last {
+            return Err(Error::InvalidKey(format!(
+                "{:?} is not greater than the last key {:?}",
+                String::from_utf8_lossy(key),
+                String::from_utf8_lossy(last)
+            )));
+        }
+        // 两个键第一个不同的nibble；键用完后按0处理
+        let depth = (0..2 * key.len())
+            .find(|&pos| key_nibble(key, pos) != key_nibble(last, pos))
+            .ok_or_else(|| {
+                Error::InvalidKey(
+                    "key differs from the last key only in trailing zero bytes".into(),
+                )
+            })?;
+        let (old, new) = (
+            key_nibble(last, depth) as usize,
+            key_nibble(key, depth) as usize,
+        );
+
+        let leaf_depth = self.nodes.len() - 1;
+        if depth < leaf_depth {
+            // 在路径上的内部节点处分叉，其下的右边缘成为左侧的子树
+            let below = self.nodes[depth + 1].hash();
+            self.nodes.truncate(depth + 1);
+            let mut children = match &self.nodes[depth] {
+                FrontierNode::Branch { children, .. } => children.clone(),
+                _ => Box::new([None; 16]),
+            };
+            children[old] = Some(below);
+            children[new] = Some(leaf);
+            self.nodes[depth] = FrontierNode::Branch {
+                children,
+                hash: [0; 32],
+            };
+        } else {
+            // 在原来的叶子之下分叉：共同的nibble成为扩展节点
+            let old_leaf = self.nodes.pop().map_or([0; 32], |node| node.hash());
+            for pos in leaf_depth..depth {
+                self.nodes.push(FrontierNode::Extension {
+                    nibble: key_nibble(key, pos),
+                    hash: [0; 32],
+                });
+            }
+            let mut children = Box::new([None; 16]);
+            children[old] = Some(old_leaf);
+            children[new] = Some(leaf);
+            self.nodes.push(FrontierNode::Branch {
+                children,
+                hash: [0; 32],
+            });
+        }
+        self.nodes.push(FrontierNode::Leaf { hash: leaf });
+        self.last_key = Some(key.to_vec());
+        self.rehash();
+        Ok(self.root_hash)
+    }
+
+    /// 从叶子开始自下而上重算路径上的节点哈希和根哈希
+    fn rehash(&mut self) {
+        let Some(key) = self.last_key.as_deref() else {
+            return;
+        };
+        let Some(mut hash) = self.nodes.last().map(FrontierNode::hash) else {
+            return;
+        };
+        for (pos, node) in self.nodes.iter_mut().enumerate().rev().skip(1) {
+            match node {
+                FrontierNode::Extension { nibble, hash: own } => {
+                    *own = self.scheme.extension(*nibble, &hash);
+                    hash = *own;
+                }
+                FrontierNode::Branch {
+                    children,
+                    hash: own,
+                } => {
+                    children[key_nibble(key, pos) as usize] = Some(hash);
+                    let refs: Vec<&[u8]> = children
+                        .iter()
+                        .map(|child| child.as_ref().map_or(&self.empty[..], |h| &h[..]))
+                        .collect();
+                    *own = self.scheme.branch(&refs);
+                    hash = *own;
+                }
+                FrontierNode::Leaf { .. } => {}
+            }
+        }
+        self.root_hash = Root(hash);
+    }
+}
+
+impl Database {
+    /// 数据库版本 `version` 的Merkle树右边缘；版本0是新数据库的空状态。
+    /// 没有该版本或已被清理时返回 `Error::NotFound`
+    pub fn frontier(&self, version: Version) -> Result<Frontier> {
+        let empty = || AmdbResult {
+            status: 0,
+            error_msg: ptr::null(),
+            data: ptr::null_mut(),
+            data_len: 0,
+        };
+        let (mut last_key, mut path) = (empty(), empty());
+        let (mut leaf_hash, mut root_hash) = ([0u8; 32], Root::default());
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            amdb_merkle_frontier(
+                *handle,
+                version.0,
+                &mut last_key,
+                leaf_hash.as_mut_ptr(),
+                &mut path,
+                root_hash.as_mut_ptr(),
+            )
+        });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        let is_empty = last_key.data.is_null();
+        let (key, path_bytes) = (result_bytes(&last_key), result_bytes(&path));
+        unsafe {
+            amdb_free_result(&mut last_key);
+            amdb_free_result(&mut path);
+        }
+
+        let empty_hash = self
+            .empty_subtree_hash()?
+            .map_or(Vec::new(), |h| h.to_vec());
+        let mut frontier = Frontier {
+            version,
+            root_hash,
+            last_key: None,
+            nodes: Vec::new(),
+            scheme: self.hash_scheme()?,
+            empty: empty_hash,
+            checksums: self.options.value_checksums,
+            blob_threshold: self.options.blob_threshold,
+        };
+        if is_empty {
+            return Ok(frontier);
+        }
+        frontier.nodes = parse_path(&path_bytes, &frontier.empty)
+            .ok_or_else(|| Error::Corruption("invalid frontier path".to_string()))?;
+        frontier.nodes.push(FrontierNode::Leaf { hash: leaf_hash });
+        frontier.last_key = Some(key);
+        frontier.rehash();
+        if frontier.root_hash != root_hash {
+            return Err(Error::RootMismatch {
+                actual: frontier.root_hash,
+            });
+        }
+        Ok(frontier)
+    }
+}
+
+/// 解析引擎给出的路径，节点哈希由 `Frontier::rehash` 填入
+fn parse_path(mut path: &[u8], empty: &[u8]) -> Option<Vec<FrontierNode>> {
+    let mut nodes = Vec::new();
+    while let Some((&tag, rest)) = path.split_first() {
+        path = rest;
+        match tag {
+            STEP_EXTENSION => {
+                let (&nibble, rest) = path.split_first()?;
+                if nibble > 0xf {
+                    return None;
+                }
+                nodes.push(FrontierNode::Extension {
+                    nibble,
+                    hash: [0; 32],
+                });
+                path = rest;
+            }
+            STEP_BRANCH => {
+                let mut children = Box::new([None; 16]);
+                for child in children.iter_mut() {
+                    let (&len, rest) = path.split_first()?;
+                    let (hash, rest) = rest.split_at_checked(len as usize)?;
+                    if hash != empty {
+                        *child = Some(hash.try_into().ok()?);
+                    }
+                    path = rest;
+                }
+                nodes.push(FrontierNode::Branch {
+                    children,
+                    hash: [0; 32],
+                });
+            }
+            _ => return None,
+        }
+    }
+    Some(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenOptions;
+
+    const KEYS: [&[u8]; 6] = [b"a", b"b", b"ba", b"c0", b"c1", b"d"];
+
+    #[test]
+    fn test_frontier_append() {
+        let _ = std::fs::remove_dir_all("./test_data/frontier");
+        let db = Database::new("./test_data/frontier").unwrap();
+        for key in KEYS {
+            db.put(key, &[key, b"-v"].concat()).unwrap();
+        }
+
+        // 从每个历史版本的右边缘出发追加之后的键，得到与数据库相同的根哈希
+        for version in 0..KEYS.len() as u64 {
+            let mut frontier = db.frontier(Version(version)).unwrap();
+            if version > 0 {
+                assert_eq!(frontier.root_hash(), db.root_hash_at(Version(version)).unwrap());
+                assert_eq!(frontier.last_key(), Some(KEYS[version as usize - 1]));
+            }
+            for (i, key) in KEYS.iter().enumerate().skip(version as usize) {
+                let root = frontier.append(key, &[key, &b"-v"[..]].concat()).unwrap();
+                assert_eq!(root, db.root_hash_at(Version(i as u64 + 1)).unwrap());
+            }
+        }
+
+        let mut frontier = db.frontier(Version(KEYS.len() as u64)).unwrap();
+        assert!(matches!(
+            frontier.nodes().last(),
+            Some(FrontierNode::Leaf { .. })
+        ));
+        assert!(matches!(
+            frontier.append(b"c", b"v"),
+            Err(Error::InvalidKey(_))
+        ));
+        assert!(matches!(
+            frontier.append(b"d\0", b"v"),
+            Err(Error::InvalidKey(_))
+        ));
+        assert!(matches!(db.frontier(Version(100)), Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn test_frontier_with_checksums() {
+        let _ = std::fs::remove_dir_all("./test_data/frontier_checksums");
+        let db = OpenOptions::new()
+            .value_checksums(true)
+            .open("./test_data/frontier_checksums")
+            .unwrap();
+        db.put(b"k1", b"v1").unwrap();
+        let mut frontier = db.frontier(Version(1)).unwrap();
+        let root = db.put(b"k2", b"v2").unwrap();
+        assert_eq!(frontier.append(b"k2", b"v2").unwrap(), root);
+    }
+}
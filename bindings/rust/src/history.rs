@@ -0,0 +1,303 @@
+//! 键的版本历史
+//! 每次写入或删除键都产生该键的一个新版本（`KeyVersion`，从1开始，即 `Database::get` 的 `version` 参数），
+//! 并属于某个数据库版本（见 `Database::state_version`）。历史按键的版本号升序分页读取，
+//! 已被保留策略删除的版本不在其中。
+//!
+//! 删除不会抹去键：它写入一个墓碑版本（`VersionOp::Delete`），墓碑同样计入Merkle树，
+//! 任何保留策略都保留最新版本，因此墓碑一直可见（见 `Database::is_tombstone`）。
+//! 需要抹去历史值时用 `Database::purge_key_history`。
+
+use std::ops::{Bound, RangeBounds};
+use std::ptr;
+
+use crate::{
+    amdb_free_history64, amdb_free_versions, amdb_get_commit_root, amdb_key_history64,
+    amdb_key_versions, result_bytes, AmdbHistoryEntry64, Database, KeyVersion, Result, Root,
+    Version,
+};
+
+/// 每次引擎调用读取的版本数
+const HISTORY_PAGE: usize = 256;
+
+/// 版本对应的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionOp {
+    Put,
+    Delete,
+}
+
+/// 键的一个版本，见 `Database::history`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionEntry {
+    /// 写入该版本的数据库版本
+    pub version: Version,
+    /// 键的版本号，可传给 `Database::get` 读取该版本的值
+    pub key_version: KeyVersion,
+    /// 数据库版本 `version` 提交后的根哈希
+    pub root_hash: Root,
+    pub op: VersionOp,
+    /// 写入的值；删除或经 `history_without_values` 读取时为 `None`
+    pub value: Option<Vec<u8>>,
+}
+
+impl Database {
+    /// 按键的版本号升序迭代键的每个版本，包括删除
+    pub fn history(&self, key: &[u8]) -> History<'_> {
+        History::new(self, key, true)
+    }
+
+    /// 同 `history`，但不读取值，适合值较大而只需要审计版本和根哈希的场合
+    pub fn history_without_values(&self, key: &[u8]) -> History<'_> {
+        History::new(self, key, false)
+    }
+
+    /// 键在 `versions` 范围内仍保留的版本号，按升序；已被保留策略删除的版本不在其中
+    pub fn key_versions(
+        &self,
+        key: &[u8],
+        versions: impl RangeBounds<KeyVersion>,
+    ) -> Result<Vec<KeyVersion>> {
+        let start = match versions.start_bound() {
+            Bound::Included(&start) => Some(start.0),
+            Bound::Excluded(&start) => start.0.checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let end = match versions.end_bound() {
+            Bound::Included(&end) => Some(end.0),
+            Bound::Excluded(&end) => end.0.checked_sub(1),
+            Bound::Unbounded => Some(u64::MAX),
+        };
+        let (Some(start), Some(end)) = (start, end) else {
+            return Ok(Vec::new());
+        };
+        if end < start {
+            return Ok(Vec::new());
+        }
+        let (mut raw, mut count) = (ptr::null_mut::<u64>(), 0);
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            amdb_key_versions(
+                *handle,
+                key.as_ptr(),
+                key.len(),
+                start,
+                end,
+                &mut raw,
+                &mut count,
+            )
+        });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        if raw.is_null() {
+            return Ok(Vec::new());
+        }
+        let versions = unsafe { std::slice::from_raw_parts(raw, count) }
+            .iter()
+            .map(|&version| KeyVersion(version))
+            .collect();
+        unsafe { amdb_free_versions(raw) };
+        Ok(versions)
+    }
+
+    /// 键的最新版本是否为删除的墓碑；从未写入的键返回 `false`
+    pub fn is_tombstone(&self, key: &[u8]) -> Result<bool> {
+        Ok(self.key_written(key)? && self.get(key, None)?.is_none())
+    }
+
+    /// 最近一次提交的数据库版本，同 `state_version`；新数据库为0
+    pub fn latest_version(&self) -> Result<Version> {
+        self.state_version()
+    }
+
+    /// 数据库版本 `version`（从1开始）提交后的根哈希；没有该版本时返回 `Error::NotFound`
+    pub fn root_hash_at(&self, version: Version) -> Result<Root> {
+        let mut root_hash = Root::default();
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            amdb_get_commit_root(*handle, version.0, root_hash.as_mut_ptr())
+        });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        Ok(root_hash)
+    }
+}
+
+/// `Database::history` 返回的迭代器；出错后不再返回版本
+pub struct History<'a> {
+    db: &'a Database,
+    key: Vec<u8>,
+    include_values: bool,
+    /// 已读到的最大键版本号
+    after: u64,
+    page: std::vec::IntoIter<VersionEntry>,
+    done: bool,
+}
+
+impl<'a> History<'a> {
+    fn new(db: &'a Database, key: &[u8], include_values: bool) -> Self {
+        History {
+            db,
+            key: key.to_vec(),
+            include_values,
+            after: 0,
+            page: Vec::new().into_iter(),
+            done: false,
+        }
+    }
+
+    fn fetch(&mut self) -> Result<Vec<VersionEntry>> {
+        let (mut raw, mut count) = (ptr::null_mut::<AmdbHistoryEntry64>(), 0);
+        let handle = self.db.live_handle()?;
+        let status = self.db.retry_status(|| unsafe {
+            amdb_key_history64(
+                *handle,
+                self.key.as_ptr(),
+                self.key.len(),
+                self.after,
+                HISTORY_PAGE,
+                self.include_values,
+                &mut raw,
+                &mut count,
+            )
+        });
+        if status != 0 {
+            return Err(self.db.engine_error(status));
+        }
+        if raw.is_null() {
+            return Ok(Vec::new());
+        }
+
+        let raw_entries = unsafe { std::slice::from_raw_parts(raw, count) };
+        let entries = raw_entries
+            .iter()
+            .map(|entry| {
+                let value = if entry.value.data.is_null() {
+                    None
+                } else {
+                    Some(self.db.open_value(result_bytes(&entry.value))?)
+                };
+                Ok(VersionEntry {
+                    version: Version(entry.version),
+                    key_version: KeyVersion(entry.key_version),
+                    root_hash: Root(entry.root_hash),
+                    op: if entry.deleted {
+                        VersionOp::Delete
+                    } else {
+                        VersionOp::Put
+                    },
+                    value,
+                })
+            })
+            .collect();
+        unsafe { amdb_free_history64(raw, count) };
+        entries
+    }
+}
+
+impl Iterator for History<'_> {
+    type Item = Result<VersionEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.page.next() {
+                self.after = entry.key_version.0;
+                return Some(Ok(entry));
+            }
+            if self.done {
+                return None;
+            }
+            match self.fetch() {
+                Ok(page) => {
+                    self.done = page.len() < HISTORY_PAGE;
+                    self.page = page.into_iter();
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, WriteBatch};
+
+    #[test]
+    fn test_key_history() {
+        let _ = std::fs::remove_dir_all("./test_data/history");
+        let db = Database::new("./test_data/history").unwrap();
+        let first = db.put(b"k", b"1").unwrap();
+        db.put(b"other", b"x").unwrap();
+        db.delete(b"k").unwrap();
+        let mut batch = WriteBatch::new();
+        batch.put(b"k", b"2").put(b"other", b"y");
+        let last = db.write_batch(&batch).unwrap();
+
+        let history: Vec<VersionEntry> = db.history(b"k").map(|e| e.unwrap()).collect();
+        assert_eq!(history.len(), 3);
+        assert_eq!(
+            history[0],
+            VersionEntry {
+                version: Version(1),
+                key_version: KeyVersion(1),
+                root_hash: first,
+                op: VersionOp::Put,
+                value: Some(b"1".to_vec()),
+            }
+        );
+        assert_eq!(
+            (history[1].version, history[1].op),
+            (Version(3), VersionOp::Delete)
+        );
+        assert!(history[1].value.is_none());
+        assert_eq!(
+            (history[2].version, history[2].root_hash),
+            (Version(4), last)
+        );
+        assert_eq!(
+            db.get(b"k", Some(history[2].key_version)).unwrap(),
+            Some(b"2".to_vec())
+        );
+
+        assert!(db
+            .history_without_values(b"k")
+            .all(|e| e.unwrap().value.is_none()));
+        assert_eq!(db.history(b"missing").count(), 0);
+
+        assert_eq!(db.latest_version().unwrap(), 4);
+        assert_eq!(db.root_hash_at(Version(1)).unwrap(), first);
+        assert_eq!(db.root_hash_at(Version(4)).unwrap(), last);
+        assert!(matches!(db.root_hash_at(Version(5)), Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn test_key_versions() {
+        let _ = std::fs::remove_dir_all("./test_data/key_versions");
+        let db = Database::new("./test_data/key_versions").unwrap();
+        for i in 1..=4u8 {
+            db.put(b"k", &[i]).unwrap();
+        }
+        assert_eq!(db.key_versions(b"k", ..).unwrap(), [1, 2, 3, 4]);
+        let v = KeyVersion;
+        assert_eq!(db.key_versions(b"k", v(2)..v(4)).unwrap(), [2, 3]);
+        assert_eq!(db.key_versions(b"k", v(3)..).unwrap(), [3, 4]);
+        assert_eq!(db.key_versions(b"k", ..=v(1)).unwrap(), [v(1)]);
+        assert!(db.key_versions(b"k", v(3)..v(3)).unwrap().is_empty());
+        assert!(db.key_versions(b"k", v(u64::MAX - 1)..).unwrap().is_empty());
+        assert!(db.key_versions(b"missing", ..).unwrap().is_empty());
+
+        // 超过32位的版本号不会被截断为另一个版本
+        let wide = v((1u64 << 32) + 1);
+        assert_eq!(db.get(b"k", Some(v(1))).unwrap(), Some(vec![1]));
+        assert_eq!(db.get(b"k", Some(wide)).unwrap(), None);
+        assert!(db.key_versions(b"k", v(1u64 << 32)..).unwrap().is_empty());
+        let mut out = Vec::new();
+        assert_eq!(db.get_to_writer(b"k", Some(wide), &mut out).unwrap(), None);
+        assert_eq!(db.history(b"k").last().unwrap().unwrap().key_version, 4);
+    }
+}
@@ -0,0 +1,170 @@
+//! 命名树的键值钩子
+//! 经由树的键空间（`Database::open_tree` 等）写入的键先按顺序规范化（例如转小写、定宽补齐），
+//! 值在送入引擎之前校验，不合法时分别返回 `Error::InvalidKey` 和 `Error::InvalidValue`。
+//! 读取与删除同样规范化键；扫描的范围按原样使用。钩子只在本进程内生效，不随数据持久化。
+
+use std::fmt;
+use std::sync::{Arc, PoisonError};
+
+use crate::{Database, Error, Result};
+
+/// 键规范化函数：返回规范化后的键，或 `Err(原因)` 拒绝该键
+pub type KeyNormalizer = dyn Fn(&[u8]) -> std::result::Result<Vec<u8>, String> + Send + Sync;
+
+/// 值校验函数：返回 `Err(原因)` 表示拒绝该值
+pub type ValueValidator = dyn Fn(&[u8]) -> std::result::Result<(), String> + Send + Sync;
+
+/// 一棵树的钩子，见 `Database::set_tree_hooks`
+#[derive(Clone, Default)]
+pub struct TreeHooks {
+    normalizers: Vec<Arc<KeyNormalizer>>,
+    validators: Vec<Arc<ValueValidator>>,
+}
+
+impl TreeHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 追加键规范化函数，按追加的顺序执行
+    pub fn normalize_keys<F>(&mut self, normalizer: F) -> &mut Self
+    where
+        F: Fn(&[u8]) -> std::result::Result<Vec<u8>, String> + Send + Sync + 'static,
+    {
+        self.normalizers.push(Arc::new(normalizer));
+        self
+    }
+
+    /// 把键中的ASCII大写字母转为小写
+    pub fn lowercase_keys(&mut self) -> &mut Self {
+        self.normalize_keys(|key| Ok(key.to_ascii_lowercase()))
+    }
+
+    /// 在键的左侧以 `pad` 补齐到 `width` 字节，拒绝更长的键；数字键补 `b'0'` 后按数值排序
+    pub fn pad_keys(&mut self, width: usize, pad: u8) -> &mut Self {
+        self.normalize_keys(move |key| {
+            if key.len() > width {
+                return Err(format!(
+                    "key of {} bytes exceeds the fixed width of {}",
+                    key.len(),
+                    width
+                ));
+            }
+            let mut padded = vec![pad; width - key.len()];
+            padded.extend_from_slice(key);
+            Ok(padded)
+        })
+    }
+
+    /// 追加值校验函数，按追加的顺序执行
+    pub fn validate_values<F>(&mut self, validator: F) -> &mut Self
+    where
+        F: Fn(&[u8]) -> std::result::Result<(), String> + Send + Sync + 'static,
+    {
+        self.validators.push(Arc::new(validator));
+        self
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.normalizers.is_empty() && self.validators.is_empty()
+    }
+
+    pub(crate) fn normalize_key(&self, key: &[u8]) -> Result<Vec<u8>> {
+        let mut key = key.to_vec();
+        for normalizer in &self.normalizers {
+            key = normalizer(&key).map_err(Error::InvalidKey)?;
+        }
+        Ok(key)
+    }
+
+    pub(crate) fn check_value(&self, value: &[u8]) -> Result<()> {
+        for validator in &self.validators {
+            validator(value).map_err(Error::InvalidValue)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for TreeHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TreeHooks")
+            .field("normalizers", &self.normalizers.len())
+            .field("validators", &self.validators.len())
+            .finish()
+    }
+}
+
+impl Database {
+    /// 为树安装钩子，替换已安装的钩子；之后打开的该树的键空间使用新钩子。
+    /// 树不存在时返回 `Error::TreeNotFound`
+    pub fn set_tree_hooks(&self, name: &str, hooks: TreeHooks) -> Result<()> {
+        // 确认树存在
+        self.open_tree(name)?;
+        let mut installed = self
+            .tree_hooks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if hooks.is_empty() {
+            installed.remove(name);
+        } else {
+            installed.insert(name.to_string(), Arc::new(hooks));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn hooks_of(&self, name: &str) -> Option<Arc<TreeHooks>> {
+        let installed = self
+            .tree_hooks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        installed.get(name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_hooks() {
+        let _ = std::fs::remove_dir_all("./test_data/tree_hooks");
+        let db = Database::new("./test_data/tree_hooks").unwrap();
+        db.create_tree("users").unwrap();
+        let mut hooks = TreeHooks::new();
+        hooks
+            .lowercase_keys()
+            .pad_keys(6, b'_')
+            .validate_values(|value| {
+                std::str::from_utf8(value)
+                    .map(|_| ())
+                    .map_err(|_| "value is not UTF-8".to_string())
+            });
+        db.set_tree_hooks("users", hooks).unwrap();
+        assert!(matches!(
+            db.set_tree_hooks("missing", TreeHooks::new()),
+            Err(Error::TreeNotFound(_))
+        ));
+
+        let users = db.open_tree("users").unwrap();
+        users.put(b"Bob", b"builder").unwrap();
+        assert_eq!(users.get(b"BOB", None).unwrap(), Some(b"builder".to_vec()));
+        let keys: Vec<Vec<u8>> = users.scan(..).map(|e| e.unwrap().0.into_vec()).collect();
+        assert_eq!(keys, vec![b"___bob".to_vec()]);
+        assert!(matches!(
+            users.put(b"too-long", b"x"),
+            Err(Error::InvalidKey(_))
+        ));
+        assert!(matches!(
+            users.put(b"eve", &[0xff]),
+            Err(Error::InvalidValue(_))
+        ));
+        users.delete(b"bOb").unwrap();
+        assert!(users.get(b"bob", None).unwrap().is_none());
+
+        // 清除钩子后按原样写入
+        db.set_tree_hooks("users", TreeHooks::new()).unwrap();
+        let users = db.open_tree("users").unwrap();
+        users.put(b"Bob", &[0xff]).unwrap();
+        assert!(users.get(b"bob", None).unwrap().is_none());
+    }
+}
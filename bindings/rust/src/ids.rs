@@ -0,0 +1,254 @@
+//! 根哈希、数据库版本与键的版本号
+//! 公开API中的根哈希都是 `Root`，数据库版本（见 `versioned`）都是 `Version`，单个键的版本号
+//! （`Database::get` 的 `version`、`VersionEntry::key_version`）都是 `KeyVersion`。三者不能互换，
+//! 也不能与长度、计数等其他整数或字节数组互换，传错参数在编译时就会被发现。
+//!
+//! `Root` 以64位小写十六进制显示和解析，可解引用为 `[u8; 32]` 交给需要字节的接口；`Version` 和
+//! `KeyVersion` 按十进制显示。`serde` 特性下 `Root` 序列化为十六进制字符串，另外两者序列化为整数。
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
+
+use crate::backup::{from_hex, to_hex};
+use crate::Error;
+
+/// Merkle树的根哈希
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Root(pub [u8; 32]);
+
+/// 数据库版本：写入提交的序号，新数据库为0
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(::serde::Serialize, ::serde::Deserialize),
+    serde(transparent)
+)]
+#[repr(transparent)]
+pub struct Version(pub u64);
+
+/// 键的版本号：该键第几次被写入（含删除），从1开始，与数据库版本无关
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(::serde::Serialize, ::serde::Deserialize),
+    serde(transparent)
+)]
+#[repr(transparent)]
+pub struct KeyVersion(pub u64);
+
+impl Root {
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl Deref for Root {
+    type Target = [u8; 32];
+
+    fn deref(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl DerefMut for Root {
+    fn deref_mut(&mut self) -> &mut [u8; 32] {
+        &mut self.0
+    }
+}
+
+impl AsRef<[u8]> for Root {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<[u8; 32]> for Root {
+    fn from(bytes: [u8; 32]) -> Self {
+        Root(bytes)
+    }
+}
+
+impl From<Root> for [u8; 32] {
+    fn from(root: Root) -> Self {
+        root.0
+    }
+}
+
+impl PartialEq<[u8; 32]> for Root {
+    fn eq(&self, other: &[u8; 32]) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<Root> for [u8; 32] {
+    fn eq(&self, other: &Root) -> bool {
+        *self == other.0
+    }
+}
+
+impl fmt::Display for Root {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&to_hex(&self.0))
+    }
+}
+
+impl fmt::Debug for Root {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Root({})", self)
+    }
+}
+
+impl FromStr for Root {
+    type Err = Error;
+
+    /// 解析64位十六进制（大小写均可）
+    fn from_str(text: &str) -> Result<Self, Error> {
+        from_hex(text)
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .map(Root)
+            .ok_or_else(|| Error::InvalidArgument(format!("invalid root hash {:?}", text)))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ::serde::Serialize for Root {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> ::serde::Deserialize<'de> for Root {
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = <String as ::serde::Deserialize>::deserialize(deserializer)?;
+        text.parse().map_err(::serde::de::Error::custom)
+    }
+}
+
+impl Version {
+    pub fn get(self) -> u64 {
+        self.0
+    }
+
+    /// 下一次提交产生的版本
+    pub fn next(self) -> Version {
+        Version(self.0 + 1)
+    }
+}
+
+impl From<u64> for Version {
+    fn from(version: u64) -> Self {
+        Version(version)
+    }
+}
+
+impl From<Version> for u64 {
+    fn from(version: Version) -> Self {
+        version.0
+    }
+}
+
+impl PartialEq<u64> for Version {
+    fn eq(&self, other: &u64) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Version {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Error> {
+        text.parse()
+            .map(Version)
+            .map_err(|_| Error::InvalidArgument(format!("invalid database version {:?}", text)))
+    }
+}
+
+impl KeyVersion {
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for KeyVersion {
+    fn from(version: u64) -> Self {
+        KeyVersion(version)
+    }
+}
+
+impl From<KeyVersion> for u64 {
+    fn from(version: KeyVersion) -> Self {
+        version.0
+    }
+}
+
+impl PartialEq<u64> for KeyVersion {
+    fn eq(&self, other: &u64) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for KeyVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for KeyVersion {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Error> {
+        text.parse()
+            .map(KeyVersion)
+            .map_err(|_| Error::InvalidArgument(format!("invalid key version {:?}", text)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+
+    #[test]
+    fn test_root_and_version() {
+        let _ = std::fs::remove_dir_all("./test_data/ids");
+        let db = Database::new("./test_data/ids").unwrap();
+        let root = db.put(b"k", b"v").unwrap();
+        assert_eq!(root, db.get_root_hash().unwrap());
+        let text = root.to_string();
+        assert_eq!(text.len(), 64);
+        assert_eq!(text.parse::<Root>().unwrap(), root);
+        assert_eq!(text.to_uppercase().parse::<Root>().unwrap(), root);
+        assert!(text[..62].parse::<Root>().is_err());
+        assert!("zz".repeat(32).parse::<Root>().is_err());
+        assert_eq!(format!("{:?}", root), format!("Root({})", text));
+        assert_eq!(<[u8; 32]>::from(root), *root);
+
+        let version = db.state_version().unwrap();
+        assert_eq!(version, Version(1));
+        assert!(version < version.next());
+        assert_eq!("1".parse::<Version>().unwrap(), version);
+        assert!("-1".parse::<Version>().is_err());
+        assert_eq!(db.snapshot_at(version).unwrap().root_hash(), root);
+
+        // 键的版本号与数据库版本各自计数
+        db.put(b"other", b"v").unwrap();
+        assert_eq!(db.state_version().unwrap(), Version(2));
+        let entry = db.history(b"other").next().unwrap().unwrap();
+        assert_eq!(entry.key_version, KeyVersion(1));
+        assert_eq!(
+            db.get(b"other", Some(entry.key_version)).unwrap(),
+            Some(b"v".to_vec())
+        );
+        assert_eq!("1".parse::<KeyVersion>().unwrap(), entry.key_version);
+        assert_eq!(entry.key_version.to_string(), "1");
+    }
+}
@@ -0,0 +1,187 @@
+//! 二级索引
+//! 由用户提供的提取函数从值中派生索引键，索引条目与主数据在同一次批量写入中更新
+//!
+//! 索引条目存放在保留前缀 `\0idx/<name>/` 下，键为
+//! `前缀 + 转义后的索引键（见 [`crate::keys`]）+ 主键`，值为主键。
+//! 转义保证条目按索引键的字节序排列，便于范围查询。
+
+use std::ops::{Bound, RangeBounds};
+
+use crate::keys::{escape_into, prefix_successor, unescape};
+use crate::{Database, Entry, Result, Root};
+
+pub(crate) const INDEX_PREFIX: &[u8] = b"\0idx/";
+
+pub struct SecondaryIndex<'a, F> {
+    db: &'a Database,
+    prefix: Vec<u8>,
+    extractor: F,
+}
+
+impl Database {
+    /// 创建名为 `name` 的二级索引，`extractor` 从值中提取零个或多个索引键
+    ///
+    /// 被索引的键必须经由该索引写入和删除，否则索引条目不会随之更新。
+    pub fn secondary_index<F>(&self, name: &str, extractor: F) -> SecondaryIndex<'_, F>
+    where
+        F: Fn(&[u8]) -> Vec<Vec<u8>>,
+    {
+        let mut prefix = INDEX_PREFIX.to_vec();
+        prefix.extend_from_slice(name.as_bytes());
+        prefix.push(b'/');
+        SecondaryIndex {
+            db: self,
+            prefix,
+            extractor,
+        }
+    }
+}
+
+impl<'a, F> SecondaryIndex<'a, F>
+where
+    F: Fn(&[u8]) -> Vec<Vec<u8>>,
+{
+    /// 写入主数据并同步更新索引，返回写入后的根哈希
+    pub fn put(&self, key: &[u8], value: &[u8]) -> Result<Root> {
+        self.db.options.check_key(key)?;
+        let _writes = self.db.write_lock();
+        let old_index_keys = self.index_keys_of(key)?;
+        let new_index_keys = self.extract(value);
+
+        let mut items = vec![(key.to_vec(), Some(value.to_vec()))];
+        for index_key in old_index_keys.iter().filter(|k| !new_index_keys.contains(k)) {
+            items.push((self.entry_key(index_key, key), None));
+        }
+        for index_key in &new_index_keys {
+            items.push((self.entry_key(index_key, key), Some(key.to_vec())));
+        }
+
+        self.db.batch_put(&items)
+    }
+
+    /// 删除主数据及其全部索引条目，返回删除后的根哈希
+    pub fn delete(&self, key: &[u8]) -> Result<Root> {
+        self.db.options.check_key(key)?;
+        let _writes = self.db.write_lock();
+        let mut items = vec![(key.to_vec(), None)];
+        for index_key in self.index_keys_of(key)? {
+            items.push((self.entry_key(&index_key, key), None));
+        }
+        self.db.batch_put(&items)
+    }
+
+    /// 查找索引键等于 `index_key` 的全部主键（按主键升序）
+    pub fn lookup(&self, index_key: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let start = self.entry_key(index_key, b"");
+        let end = prefix_successor(&start);
+        let entries = self.db.range_query(&start, &end)?;
+        Ok(entries.into_iter().map(|(_, primary)| primary).collect())
+    }
+
+    /// 按索引键范围查询，返回（索引键, 主键）
+    pub fn range(&self, range: impl RangeBounds<Vec<u8>>) -> Result<Vec<Entry>> {
+        // 索引键为 k 的条目都以 entry_key(k, "") 开头：
+        // 不带终止符的 entry_key 落在它们之前，其后继落在它们之后
+        let scan_start = match range.start_bound() {
+            Bound::Included(key) => self.index_key_prefix(key),
+            Bound::Excluded(key) => prefix_successor(&self.entry_key(key, b"")),
+            Bound::Unbounded => self.prefix.clone(),
+        };
+        let scan_end = match range.end_bound() {
+            Bound::Included(key) => prefix_successor(&self.entry_key(key, b"")),
+            Bound::Excluded(key) => self.index_key_prefix(key),
+            Bound::Unbounded => prefix_successor(&self.prefix),
+        };
+        if scan_start >= scan_end {
+            return Ok(Vec::new());
+        }
+
+        let entries = self.db.range_query(&scan_start, &scan_end)?;
+        Ok(entries
+            .into_iter()
+            .filter_map(|(entry, primary)| {
+                let (index_key, _) = unescape(&entry[self.prefix.len()..])?;
+                Some((index_key, primary))
+            })
+            .collect())
+    }
+
+    fn index_keys_of(&self, key: &[u8]) -> Result<Vec<Vec<u8>>> {
+        Ok(self
+            .db
+            .get(key, None)?
+            .map(|value| self.extract(&value))
+            .unwrap_or_default())
+    }
+
+    fn extract(&self, value: &[u8]) -> Vec<Vec<u8>> {
+        let mut index_keys = (self.extractor)(value);
+        index_keys.sort();
+        index_keys.dedup();
+        index_keys
+    }
+
+    fn entry_key(&self, index_key: &[u8], primary_key: &[u8]) -> Vec<u8> {
+        let mut entry = self.prefix.clone();
+        escape_into(&mut entry, index_key);
+        entry.extend_from_slice(primary_key);
+        entry
+    }
+
+    fn index_key_prefix(&self, index_key: &[u8]) -> Vec<u8> {
+        let mut entry = self.entry_key(index_key, b"");
+        entry.truncate(entry.len() - 2);
+        entry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn city_of(value: &[u8]) -> Vec<Vec<u8>> {
+        value
+            .split(|&b| b == b',')
+            .nth(1)
+            .map(|city| vec![city.to_vec()])
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_secondary_index_follows_updates() {
+        let _ = std::fs::remove_dir_all("./test_data/index");
+        let db = Database::new("./test_data/index").unwrap();
+        let by_city = db.secondary_index("city", city_of);
+
+        by_city.put(b"user/1", b"alice,paris").unwrap();
+        by_city.put(b"user/2", b"bob,oslo").unwrap();
+        by_city.put(b"user/3", b"carol,paris").unwrap();
+        assert_eq!(
+            by_city.lookup(b"paris").unwrap(),
+            vec![b"user/1".to_vec(), b"user/3".to_vec()]
+        );
+
+        by_city.put(b"user/1", b"alice,rome").unwrap();
+        assert_eq!(by_city.lookup(b"paris").unwrap(), vec![b"user/3".to_vec()]);
+
+        by_city.delete(b"user/2").unwrap();
+        assert!(by_city.lookup(b"oslo").unwrap().is_empty());
+        assert_eq!(db.get(b"user/2", None).unwrap(), None);
+
+        let cities: Vec<Vec<u8>> = by_city
+            .range(b"p".to_vec()..b"s".to_vec())
+            .unwrap()
+            .into_iter()
+            .map(|(city, _)| city)
+            .collect();
+        assert_eq!(cities, vec![b"paris".to_vec(), b"rome".to_vec()]);
+        assert_eq!(by_city.range(b"paris".to_vec()..=b"paris".to_vec()).unwrap().len(), 1);
+
+        // 索引条目不出现在主数据的扫描中，也不能直接伪造
+        let keys: Vec<Vec<u8>> = db.iter(..).map(|e| e.unwrap().0).collect();
+        assert_eq!(keys, vec![b"user/1".to_vec(), b"user/3".to_vec()]);
+        let forged = by_city.entry_key(b"oslo", b"user/1");
+        assert!(matches!(db.put(&forged, b"user/1"), Err(crate::Error::InvalidKey(_))));
+        assert!(by_city.lookup(b"oslo").unwrap().is_empty());
+    }
+}
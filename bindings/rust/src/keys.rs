@@ -0,0 +1,228 @@
+//! 保序键编码
+//! 编码结果的字节序与原值的自然顺序一致，使结构化键的范围扫描得到正确结果
+//!
+//! - 整数使用大端序，有符号整数翻转符号位（sign bias），负数排在正数之前
+//! - 时间戳编码为相对UNIX纪元的有符号纳秒数
+//! - 复合键逐个拼接各分量；定长分量直接拼接，变长分量（字节串/字符串）
+//!   转义后以 `0x00 0x00` 结尾。这里不用长度前缀：长度前缀会让 `b"b"`
+//!   排在 `b"aa"` 之前，破坏按分量的字典序
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub fn encode_u64(value: u64) -> [u8; 8] {
+    value.to_be_bytes()
+}
+
+pub fn decode_u64(bytes: &[u8]) -> Option<u64> {
+    Some(u64::from_be_bytes(bytes.get(..8)?.try_into().ok()?))
+}
+
+pub fn encode_i64(value: i64) -> [u8; 8] {
+    ((value as u64) ^ (1 << 63)).to_be_bytes()
+}
+
+pub fn decode_i64(bytes: &[u8]) -> Option<i64> {
+    decode_u64(bytes).map(|v| (v ^ (1 << 63)) as i64)
+}
+
+/// 时间戳编码为纳秒精度，可表示纪元前后约292年
+pub fn encode_timestamp(time: SystemTime) -> [u8; 8] {
+    let nanos = match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => i64::try_from(after.as_nanos()).unwrap_or(i64::MAX),
+        Err(before) => i64::try_from(before.duration().as_nanos())
+            .map(|n| -n)
+            .unwrap_or(i64::MIN),
+    };
+    encode_i64(nanos)
+}
+
+pub fn decode_timestamp(bytes: &[u8]) -> Option<SystemTime> {
+    let nanos = decode_i64(bytes)?;
+    let offset = Duration::from_nanos(nanos.unsigned_abs());
+    if nanos >= 0 {
+        UNIX_EPOCH.checked_add(offset)
+    } else {
+        UNIX_EPOCH.checked_sub(offset)
+    }
+}
+
+/// 大于所有以 `prefix` 开头的键的最小键；前缀为空或全为0xFF时返回空（无上界）
+pub fn prefix_successor(prefix: &[u8]) -> Vec<u8> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < 0xFF {
+            end.push(last + 1);
+            return end;
+        }
+    }
+    Vec::new()
+}
+
+/// 复合键构造器
+#[derive(Debug, Default, Clone)]
+pub struct KeyBuilder {
+    buf: Vec<u8>,
+}
+
+impl KeyBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn u64(mut self, value: u64) -> Self {
+        self.buf.extend_from_slice(&encode_u64(value));
+        self
+    }
+
+    pub fn i64(mut self, value: i64) -> Self {
+        self.buf.extend_from_slice(&encode_i64(value));
+        self
+    }
+
+    pub fn timestamp(mut self, time: SystemTime) -> Self {
+        self.buf.extend_from_slice(&encode_timestamp(time));
+        self
+    }
+
+    pub fn bytes(mut self, value: &[u8]) -> Self {
+        escape_into(&mut self.buf, value);
+        self
+    }
+
+    pub fn str(self, value: &str) -> Self {
+        self.bytes(value.as_bytes())
+    }
+
+    /// 追加原始字节，不做转义；只应用于最后一个分量
+    pub fn raw(mut self, value: &[u8]) -> Self {
+        self.buf.extend_from_slice(value);
+        self
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// 复合键解码器，按构造时的分量顺序依次读取
+#[derive(Debug, Clone)]
+pub struct KeyReader<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> KeyReader<'a> {
+    pub fn new(key: &'a [u8]) -> Self {
+        KeyReader { rest: key }
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        let value = decode_u64(self.rest)?;
+        self.rest = &self.rest[8..];
+        Some(value)
+    }
+
+    pub fn i64(&mut self) -> Option<i64> {
+        let value = decode_i64(self.rest)?;
+        self.rest = &self.rest[8..];
+        Some(value)
+    }
+
+    pub fn timestamp(&mut self) -> Option<SystemTime> {
+        let value = decode_timestamp(self.rest)?;
+        self.rest = &self.rest[8..];
+        Some(value)
+    }
+
+    pub fn bytes(&mut self) -> Option<Vec<u8>> {
+        let (value, consumed) = unescape(self.rest)?;
+        self.rest = &self.rest[consumed..];
+        Some(value)
+    }
+
+    pub fn str(&mut self) -> Option<String> {
+        String::from_utf8(self.bytes()?).ok()
+    }
+
+    /// 剩余未读取的字节
+    pub fn rest(&self) -> &'a [u8] {
+        self.rest
+    }
+}
+
+/// 转义（0x00 → 0x00 0xFF）并追加 `0x00 0x00` 终止符
+pub(crate) fn escape_into(out: &mut Vec<u8>, bytes: &[u8]) {
+    for &b in bytes {
+        out.push(b);
+        if b == 0x00 {
+            out.push(0xFF);
+        }
+    }
+    out.extend_from_slice(&[0x00, 0x00]);
+}
+
+/// 解码一个转义分量，返回（原值, 消耗的字节数）
+pub(crate) fn unescape(bytes: &[u8]) -> Option<(Vec<u8>, usize)> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == 0x00 {
+            match bytes.get(i + 1) {
+                Some(0x00) => return Some((out, i + 2)),
+                Some(0xFF) => out.push(0x00),
+                _ => return None,
+            }
+            i += 2;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integer_order() {
+        let values = [i64::MIN, -2, -1, 0, 1, 300, i64::MAX];
+        let encoded: Vec<[u8; 8]> = values.iter().map(|&v| encode_i64(v)).collect();
+        assert!(encoded.windows(2).all(|w| w[0] < w[1]));
+        for (&v, e) in values.iter().zip(&encoded) {
+            assert_eq!(decode_i64(e), Some(v));
+        }
+        assert!(encode_u64(255) < encode_u64(256));
+    }
+
+    #[test]
+    fn test_timestamp_round_trip() {
+        let before = UNIX_EPOCH - Duration::from_secs(10);
+        let after = UNIX_EPOCH + Duration::from_nanos(1_700_000_000_123_456_789);
+        assert!(encode_timestamp(before) < encode_timestamp(after));
+        assert_eq!(decode_timestamp(&encode_timestamp(before)), Some(before));
+        assert_eq!(decode_timestamp(&encode_timestamp(after)), Some(after));
+    }
+
+    #[test]
+    fn test_prefix_successor() {
+        assert_eq!(prefix_successor(b"acc/"), b"acc0".to_vec());
+        assert_eq!(prefix_successor(&[0x01, 0xFF]), vec![0x02]);
+        assert_eq!(prefix_successor(&[0xFF, 0xFF]), Vec::<u8>::new());
+        assert_eq!(prefix_successor(b""), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_composite_order_and_decode() {
+        let key = |name: &[u8], n: i64| KeyBuilder::new().bytes(name).i64(n).finish();
+        assert!(key(b"aa", 5) < key(b"b", 0));
+        assert!(key(b"a", 9) < key(b"a\0", 0));
+        assert!(key(b"a", -1) < key(b"a", 1));
+
+        let encoded = KeyBuilder::new().str("acc\0x").u64(7).raw(b"tail").finish();
+        let mut reader = KeyReader::new(&encoded);
+        assert_eq!(reader.str().as_deref(), Some("acc\0x"));
+        assert_eq!(reader.u64(), Some(7));
+        assert_eq!(reader.rest(), b"tail");
+    }
+}
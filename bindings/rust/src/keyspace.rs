@@ -0,0 +1,184 @@
+//! 键空间：固定前缀下的读写视图
+//! 写入时自动拼接前缀，读取与扫描时自动去除前缀，扫描范围限定在前缀之内
+//!
+//! 命名树的键空间在每次写入前检查树是否已冻结（见 `Database::freeze_tree`），
+//! 并按树的选项转换键和值（见 `tree_options`）。
+
+use std::ops::RangeBounds;
+use std::sync::Arc;
+
+use crate::keys::prefix_successor;
+use crate::{
+    engine_bounds, Database, Error, IterOptions, KeyVersion, Result, Root, Scan, TreeHooks,
+    TreeOptions,
+};
+
+pub struct Keyspace<'a> {
+    db: &'a Database,
+    prefix: Vec<u8>,
+    /// 命名树的钩子，见 `hooks`
+    hooks: Option<Arc<TreeHooks>>,
+    /// 所属命名树的名字
+    tree: Option<String>,
+    /// 命名树的选项
+    options: Option<Arc<TreeOptions>>,
+}
+
+impl Database {
+    /// 以 `prefix` 为前缀创建键空间
+    pub fn keyspace(&self, prefix: &[u8]) -> Keyspace<'_> {
+        Keyspace {
+            db: self,
+            prefix: prefix.to_vec(),
+            hooks: None,
+            tree: None,
+            options: None,
+        }
+    }
+
+    pub(crate) fn tree_keyspace(
+        &self,
+        name: &str,
+        prefix: &[u8],
+        hooks: Option<Arc<TreeHooks>>,
+        options: TreeOptions,
+    ) -> Keyspace<'_> {
+        Keyspace {
+            hooks,
+            tree: Some(name.to_string()),
+            options: Some(Arc::new(options)),
+            ..self.keyspace(prefix)
+        }
+    }
+}
+
+impl<'a> Keyspace<'a> {
+    pub fn prefix(&self) -> &[u8] {
+        &self.prefix
+    }
+
+    pub fn put(&self, key: &[u8], value: &[u8]) -> Result<Root> {
+        self.check_writable()?;
+        if let Some(hooks) = &self.hooks {
+            hooks.check_value(value)?;
+        }
+        let key = self.hooked_key(key)?;
+        // 命名树的数据位于保留前缀下，经由树的键空间写入
+        match &self.options {
+            Some(options) => self.db.put_reserved(&key, &options.encode_value(value)),
+            None => self.db.put(&key, value),
+        }
+    }
+
+    pub fn get(&self, key: &[u8], version: Option<KeyVersion>) -> Result<Option<Vec<u8>>> {
+        let value = self.db.get(&self.hooked_key(key)?, version)?;
+        match (&self.options, value) {
+            (Some(options), Some(stored)) => options.decode_value(stored),
+            (_, value) => Ok(value),
+        }
+    }
+
+    pub fn delete(&self, key: &[u8]) -> Result<()> {
+        self.check_writable()?;
+        let key = self.hooked_key(key)?;
+        match self.tree {
+            Some(_) => self.db.delete_reserved(&key),
+            None => self.db.delete(&key),
+        }
+    }
+
+    /// 冻结所属的命名树，见 `Database::freeze_tree`；不是命名树的键空间返回 `Error::InvalidArgument`
+    pub fn freeze(&self) -> Result<Root> {
+        match &self.tree {
+            Some(name) => self.db.freeze_tree(name),
+            None => Err(Error::InvalidArgument(
+                "only named trees can be frozen".to_string(),
+            )),
+        }
+    }
+
+    /// 所属的命名树已冻结时返回 `Error::Frozen`
+    fn check_writable(&self) -> Result<()> {
+        match &self.tree {
+            Some(name) if self.db.is_frozen(name) => Err(Error::Frozen(name.clone())),
+            _ => Ok(()),
+        }
+    }
+
+    /// 在键空间内按（不含前缀的）键范围扫描；返回的键不含前缀
+    pub fn scan(&self, range: impl RangeBounds<Vec<u8>>) -> Scan<'a> {
+        self.scan_with(range, &IterOptions::new())
+    }
+
+    /// 同 `scan`，按 `options` 读取
+    pub fn scan_with(&self, range: impl RangeBounds<Vec<u8>>, options: &IterOptions) -> Scan<'a> {
+        let bounds = engine_bounds(&range).map(|(start, end)| {
+            let end = if end.is_empty() {
+                prefix_successor(&self.prefix)
+            } else {
+                self.full_key(&end)
+            };
+            (self.full_key(&start), end)
+        });
+        let scan = Scan::new(self.db, bounds, self.prefix.len(), options);
+        match &self.options {
+            Some(options) => scan.tree_values(options.clone()),
+            None => scan,
+        }
+    }
+
+    /// 命名树的选项；不是命名树的键空间为 `None`
+    pub fn tree_options(&self) -> Option<&TreeOptions> {
+        self.options.as_deref()
+    }
+
+    /// 经钩子规范化、按树的选项转换后的完整键
+    fn hooked_key(&self, key: &[u8]) -> Result<Vec<u8>> {
+        let key = match &self.hooks {
+            Some(hooks) => hooks.normalize_key(key)?,
+            None => key.to_vec(),
+        };
+        match &self.options {
+            Some(options) => Ok(self.full_key(&options.stored_key(key))),
+            None => Ok(self.full_key(&key)),
+        }
+    }
+
+    fn full_key(&self, key: &[u8]) -> Vec<u8> {
+        let mut full = Vec::with_capacity(self.prefix.len() + key.len());
+        full.extend_from_slice(&self.prefix);
+        full.extend_from_slice(key);
+        full
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyValue;
+
+    #[test]
+    fn test_keyspace_scan_is_bounded() {
+        let _ = std::fs::remove_dir_all("./test_data/keyspace");
+        let db = Database::new("./test_data/keyspace").unwrap();
+        let accounts = db.keyspace(b"acc/");
+        accounts.put(b"alice", b"1").unwrap();
+        accounts.put(b"bob", b"2").unwrap();
+        db.put(b"acc0", b"outside").unwrap();
+        db.put(b"acb/zed", b"outside").unwrap();
+
+        assert_eq!(accounts.get(b"alice", None).unwrap(), Some(b"1".to_vec()));
+        assert_eq!(db.get(b"acc/bob", None).unwrap(), Some(b"2".to_vec()));
+
+        let entries: Vec<KeyValue> = accounts.scan(..).map(Result::unwrap).collect();
+        assert_eq!(
+            entries,
+            vec![
+                (b"alice"[..].into(), b"1"[..].into()),
+                (b"bob"[..].into(), b"2"[..].into()),
+            ]
+        );
+        assert_eq!(accounts.scan(b"b".to_vec()..).count(), 1);
+        assert_eq!(accounts.scan(..=b"alice".to_vec()).count(), 1);
+    }
+}
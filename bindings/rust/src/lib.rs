@@ -1,91 +1,535 @@
-/**
- * AmDb Rust绑定
- * 使用FFI调用C API
- */
+//! AmDb Rust绑定
+//! 使用FFI调用C API
 
-use std::ffi::{CStr, CString};
-use std::os::raw::{c_char, c_int, c_uint, c_void};
+#[cfg(feature = "async")]
+mod async_db;
+pub mod backup;
+mod batch;
+mod bitvec;
+mod blob;
+mod branch;
+#[cfg(feature = "capi")]
+mod capi;
+mod commit_guard;
+mod compress;
+mod cursor;
+mod diff;
+#[cfg(feature = "borsh")]
+mod borsh_codec;
+mod envelope;
+mod error;
+mod export;
+mod fallback;
+#[cfg(feature = "faults")]
+pub mod faults;
+mod filter;
+mod frontier;
+pub mod ffi;
+mod history;
+mod hooks;
+mod ids;
+mod index;
+pub mod keys;
+mod keyspace;
+mod merkle;
+mod namespace;
+#[cfg(feature = "mobile")]
+mod mobile;
+mod options;
+mod order;
+mod partition;
+mod proof;
+mod proof_cache;
+mod prover;
+mod pinned;
+mod prefixes;
+mod pruner;
+#[cfg(feature = "proto")]
+pub mod proto;
+mod read;
+mod refresh;
+mod reserved;
+mod resume;
+mod retention;
+mod retry;
+mod scan;
+mod sha256;
+mod shadow;
+mod shutdown;
+mod snapshot;
+#[cfg(feature = "ssz")]
+mod ssz;
+mod state;
+mod stats;
+mod subscribe;
+mod store;
+mod transaction;
+mod tree;
+mod tree_options;
+mod typed;
+mod updates;
+mod versioned;
+mod view;
+
+pub use batch::{BatchIter, BatchOp, WriteBatch};
+pub use bitvec::BitVec;
+pub use blob::BlobGcStats;
+pub use branch::{Branch, MergeConflict, MergeOutcome};
+pub use commit_guard::CommitGuard;
+pub use cursor::{CursorOptions, Iter};
+pub use diff::DiffEntry;
+pub use error::{AmdbError, Error, Result};
+pub use export::{ExportInfo, ExportOptions, ImportOptions};
+pub use fallback::{Fallback, FallbackScan};
+pub use filter::ValueFilter;
+pub use frontier::{Frontier, FrontierNode};
+pub use ffi::{AmdbHandle, AmdbResult};
+pub use history::{History, VersionEntry, VersionOp};
+pub use hooks::{KeyNormalizer, TreeHooks, ValueValidator};
+pub use ids::{KeyVersion, Root, Version};
+pub use index::SecondaryIndex;
+pub use keyspace::Keyspace;
+pub use merkle::KeyFraming;
+pub use namespace::{namespace_record_key, Namespace};
+pub use options::{DropBehavior, KeyValidator, OpenOptions, SyncMode, Versioning};
+pub use order::IterOrder;
+pub use partition::KeyRange;
+pub use proof::Proof;
+pub use proof_cache::ProofCacheStats;
+pub use prover::{PendingProof, Prover};
+pub use pinned::PinnedValue;
+pub use pruner::{PruneOptions, PruneReport, Pruner};
+pub use read::ReadOptions;
+pub use resume::CursorToken;
+pub use retention::{PruneStats, PurgeStats, Retention};
+pub use retry::RetryPolicy;
+pub use scan::{IterOptions, KeyValue, Scan};
+pub use shadow::{Divergence, ShadowReport, ShadowWriter};
+pub use shutdown::ShutdownReport;
+pub use snapshot::SnapshotInfo;
+use state::{CallGuard, HandleState};
+pub use stats::{
+    CommitStats, CompactionReport, CompactionStats, FileStats, Histogram, IoCounters, IoStats,
+    LevelStats, MetricsSnapshot, OpenReport, ReadStats, Stats,
+};
+pub use store::ReadStore;
+pub use subscribe::{ChangeEvent, SubscribeOptions, Subscription};
+pub use transaction::{PreparedTransaction, Transaction};
+pub use tree::{TreeIter, TreeView, TreesView};
+pub use tree_options::{CachePriority, TreeOptions};
+pub use typed::{Bytes, Codec, TypedDatabase};
+#[cfg(feature = "serde")]
+pub use typed::{Bincode, Json};
+#[cfg(feature = "borsh")]
+pub use typed::Borsh;
+pub use updates::{KeyUpdate, UpdateCallback, UpdateCallbackId};
+pub use versioned::Snapshot;
+pub use view::{HistoricalIter, HistoricalView};
+#[cfg(feature = "async")]
+pub use async_db::{AsyncDatabase, EntryStream};
+
+#[cfg(feature = "mobile")]
+uniffi::setup_scaffolding!();
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ffi::CString;
+use std::io::{ErrorKind, Read, Write};
+use std::ops::{Bound, Deref, RangeBounds};
+use std::os::raw::c_int;
+use std::panic;
+use std::path::Path;
 use std::ptr;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Duration;
 
-#[repr(C)]
-pub struct AmdbHandle {
-    _private: [u8; 0],
-}
+use blob::BlobStore;
+use commit_guard::CommitQueue;
+use proof_cache::ProofCache;
+use updates::KeyUpdates;
+use envelope::{Checksum, TRAILER_LEN};
+use ffi::*;
+
+/// 流式读写时每次跨FFI传输的块大小
+const STREAM_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// 键值对（键, 值）
+pub type Entry = (Vec<u8>, Vec<u8>);
+
+/// 批量写入的一项（键, 值），值为 `None` 表示删除
+pub(crate) type BatchItem = (Vec<u8>, Option<Vec<u8>>);
+
+/// C API在内部获取GIL，句柄可跨线程使用；持有者须保证线程在句柄关闭前退出
+struct SendHandle(*mut AmdbHandle);
+
+unsafe impl Send for SendHandle {}
 
-#[repr(C)]
-pub struct AmdbResult {
-    status: c_int,
-    error_msg: *const c_char,
-    data: *mut c_void,
-    data_len: usize,
+/// 一次调用期间借用的引擎句柄，存活期间 `close` 等待，见 `state`
+struct LiveHandle<'a> {
+    handle: *mut AmdbHandle,
+    _call: CallGuard<'a>,
 }
 
-#[link(name = "amdb")]
-extern "C" {
-    fn amdb_init(data_dir: *const c_char, handle: *mut *mut AmdbHandle) -> c_int;
-    fn amdb_close(handle: *mut AmdbHandle) -> c_int;
-    fn amdb_put(
-        handle: *mut AmdbHandle,
-        key: *const u8,
-        key_len: usize,
-        value: *const u8,
-        value_len: usize,
-        root_hash: *mut u8,
-    ) -> c_int;
-    fn amdb_get(
-        handle: *mut AmdbHandle,
-        key: *const u8,
-        key_len: usize,
-        version: c_uint,
-        result: *mut AmdbResult,
-    ) -> c_int;
-    fn amdb_delete(handle: *mut AmdbHandle, key: *const u8, key_len: usize) -> c_int;
-    fn amdb_get_root_hash(handle: *mut AmdbHandle, root_hash: *mut u8) -> c_int;
-    fn amdb_free_result(result: *mut AmdbResult);
-    fn amdb_error_string(status: c_int) -> *const c_char;
+impl Deref for LiveHandle<'_> {
+    type Target = *mut AmdbHandle;
+
+    fn deref(&self) -> &*mut AmdbHandle {
+        &self.handle
+    }
 }
 
+/// 数据库句柄，可放在 `Arc` 中由多个线程共享
+///
+/// 线程安全性：
+/// - C API的每个函数在内部获取GIL，引擎对写入持有自己的锁，`put`/`delete`/`batch_put` 等单次提交本身是原子的；
+/// - 读取不加锁，各自看到某次提交之后的状态；需要跨多次读取的一致视图时使用 `snapshot_at` 或固定的游标；
+/// - 先读后写的复合操作（带幂等令牌的 `write_batch`、`apply_replicated`、`delete_range`、
+///   命名树的创建和删除、二级索引的写入）在Rust侧互斥执行，不会与另一个复合操作交错；
+/// - `close` 可与其他线程上的调用并发，它等待进行中的调用结束后才释放句柄，之后的调用返回 `Error::Closed`。
 pub struct Database {
     handle: *mut AmdbHandle,
+    options: OpenOptions,
+    /// 进行中的备份等登记的固定时间点，见 `retention`
+    pins: Arc<Mutex<Vec<f64>>>,
+    /// 后台清理运行期间为真，此时写入提交时不再清理，见 `pruner`
+    background_pruning: AtomicBool,
+    /// 句柄生命周期，与后台线程共享，见 `state`
+    state: HandleState,
+    /// 复合写入操作的互斥锁，见 `write_lock`
+    writes: Mutex<()>,
+    /// 各命名树安装的钩子，见 `hooks`
+    tree_hooks: Mutex<HashMap<String, Arc<TreeHooks>>>,
+    /// 已打开的命名空间，见 `namespace`
+    namespaces: Mutex<HashMap<String, Arc<Database>>>,
+    /// 已知冻结的命名树，见 `tree`
+    frozen_trees: Mutex<HashSet<String>>,
+    /// 开启 `OpenOptions::blob_threshold` 时的大值文件目录，见 `blob`
+    blobs: Option<BlobStore>,
+    /// 开启 `OpenOptions::proof_cache` 时的证明缓存，与证明线程共享
+    proof_cache: Option<Arc<ProofCache>>,
+    /// 登记的键更新回调，见 `updates`
+    key_updates: KeyUpdates,
+    /// 提交守卫的排队状态，见 `commit_guard`
+    commit_queue: CommitQueue,
 }
 
+// 句柄只经由C API使用，C API可从任意线程调用（见 `amdb.h`）；句柄的释放由 `state` 与进行中的调用同步。
+// 其余字段都是 Send + Sync 的。
+unsafe impl Send for Database {}
+unsafe impl Sync for Database {}
+
 impl Database {
-    pub fn new(data_dir: &str) -> Result<Self, String> {
-        let c_data_dir = CString::new(data_dir).map_err(|e| e.to_string())?;
+    pub fn new(data_dir: &str) -> Result<Self> {
+        OpenOptions::new().open(data_dir)
+    }
+
+    pub(crate) fn open_with(data_dir: &str, options: OpenOptions) -> Result<Self> {
+        match options.open_timeout {
+            Some(timeout) => Self::open_within(data_dir, options, timeout),
+            None => Self::open_now(data_dir, options),
+        }
+    }
+
+    /// 在后台线程中打开，最多等待 `timeout`；超时后由该线程在打开完成时关闭
+    fn open_within(data_dir: &str, mut options: OpenOptions, timeout: Duration) -> Result<Self> {
+        let cap = timeout.as_millis();
+        if let Some(wait) = options.engine_options.get_mut("lock_wait_ms") {
+            if wait.parse::<u128>().is_ok_and(|ms| ms > cap) {
+                *wait = cap.to_string();
+            }
+        }
+        let (opened_tx, opened) = mpsc::channel();
+        let data_dir = data_dir.to_string();
+        let thread = thread::Builder::new()
+            .name("amdb-open".to_string())
+            .spawn(move || {
+                let _ = opened_tx.send(Self::open_now(&data_dir, options));
+            })?;
+        match opened.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => Err(Error::TimedOut),
+            Err(RecvTimeoutError::Disconnected) => panic::resume_unwind(
+                thread
+                    .join()
+                    .expect_err("open thread exited without a result"),
+            ),
+        }
+    }
+
+    fn open_now(data_dir: &str, options: OpenOptions) -> Result<Self> {
+        let c_data_dir =
+            CString::new(data_dir).map_err(|e| Error::InvalidArgument(e.to_string()))?;
         let mut handle: *mut AmdbHandle = ptr::null_mut();
         
-        let status = unsafe { amdb_init(c_data_dir.as_ptr(), &mut handle) };
+        let status = if options.tree_options.is_empty() && options.engine_options.is_empty() {
+            unsafe { amdb_init(c_data_dir.as_ptr(), &mut handle) }
+        } else {
+            let mut engine_options = options.tree_options.clone();
+            engine_options.extend(options.engine_options.clone());
+            merkle::init_with_options(&c_data_dir, &engine_options, &mut handle)?
+        };
         if status != 0 {
-            let error_msg = unsafe { CStr::from_ptr(amdb_error_string(status)) };
-            return Err(error_msg.to_string_lossy().into_owned());
+            return Err(Error::from_status(status));
         }
         
-        Ok(Database { handle })
+        let blobs = options
+            .blob_threshold
+            .map(|threshold| BlobStore::new(Path::new(data_dir), threshold));
+        let mut db = Self::with_handle(handle, options);
+        db.blobs = blobs;
+        Ok(db)
+    }
+
+    fn with_handle(handle: *mut AmdbHandle, options: OpenOptions) -> Self {
+        let proof_cache = (options.proof_cache > 0)
+            .then(|| Arc::new(ProofCache::new(options.proof_cache)));
+        Database {
+            handle,
+            options,
+            pins: Arc::default(),
+            background_pruning: AtomicBool::new(false),
+            state: HandleState::default(),
+            writes: Mutex::new(()),
+            tree_hooks: Mutex::default(),
+            namespaces: Mutex::default(),
+            frozen_trees: Mutex::default(),
+            blobs: None,
+            proof_cache,
+            key_updates: KeyUpdates::default(),
+            commit_queue: CommitQueue::default(),
+        }
+    }
+
+    /// 复合写入操作（先读取再据此写入）持有的互斥锁；持有期间不能调用另一个复合操作
+    pub(crate) fn write_lock(&self) -> MutexGuard<'_, ()> {
+        self.writes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// 借出引擎句柄，供调用 `ffi` 中安全封装尚未覆盖的函数；仍由 `Database` 负责关闭，
+    /// 不能在 `Database` 关闭或析构之后使用
+    pub fn as_raw(&self) -> Result<*mut AmdbHandle> {
+        self.live_handle().map(|handle| *handle)
+    }
+
+    /// 交出引擎句柄而不关闭，之后由调用方负责（例如通过C API的 `amdb_close`）；
+    /// 数据库已关闭或中毒时返回空指针
+    pub fn into_raw(self) -> *mut AmdbHandle {
+        // 转入关闭状态，析构时不再关闭句柄
+        match self.state.close() {
+            Ok(true) => self.handle,
+            _ => ptr::null_mut(),
+        }
+    }
+
+    /// 接管 `into_raw` 或C API `amdb_init` 得到的句柄，使用默认选项；析构时关闭句柄
+    ///
+    /// # Safety
+    ///
+    /// `handle` 必须是有效且未关闭的引擎句柄，且此后不能再在别处关闭。
+    pub unsafe fn from_raw(handle: *mut AmdbHandle) -> Self {
+        Self::with_handle(handle, OpenOptions::new())
+    }
+
+    /// 引擎返回过致命错误（数据损坏、后台任务失败等）时为真，此后所有调用返回 `Error::Poisoned`；
+    /// 需要重新打开数据库才能继续使用
+    pub fn is_poisoned(&self) -> bool {
+        self.state.is_poisoned()
+    }
+
+    /// 引擎后台任务（异步WAL写入、MemTable刷新等）第一次失败的说明，没有失败过时为 `None`。
+    /// 后台失败不会让当时的调用出错，但数据可能没有完整落盘；一旦出现就一直保留（关闭后仍可读取），
+    /// 之后的调用返回的错误都包装为 `Error::Background`
+    pub fn background_error(&self) -> Option<String> {
+        self.fetch_background_error();
+        self.state.background_error().map(str::to_string)
+    }
+
+    /// 向引擎查询后台错误并记录；数据库不再打开或查询失败时不做处理
+    fn fetch_background_error(&self) {
+        if self.state.background_error().is_some() {
+            return;
+        }
+        let Ok(handle) = self.live_handle() else {
+            return;
+        };
+        let mut result = AmdbResult {
+            status: 0,
+            error_msg: ptr::null(),
+            data: ptr::null_mut(),
+            data_len: 0,
+        };
+        if unsafe { amdb_background_error(*handle, &mut result) } != 0 {
+            return;
+        }
+        let message = String::from_utf8_lossy(&result_bytes(&result)).into_owned();
+        unsafe { amdb_free_result(&mut result) };
+        self.state.set_background_error(message);
+    }
+
+    /// 刷新并释放引擎句柄；之后的调用（包括再次关闭）返回 `Error::Closed`
+    ///
+    /// 会等待后台线程进行中的引擎调用结束。中毒的数据库不再调用引擎，直接转入关闭状态。
+    pub fn close(&self) -> Result<()> {
+        if !self.state.close()? {
+            return Ok(());
+        }
+        let status = unsafe { amdb_close(self.handle) };
+        if status != 0 {
+            return Err(Error::from_status(status));
+        }
+        Ok(())
+    }
+
+    /// 处于打开状态时返回引擎句柄；返回值存活期间 `close` 等待，不会释放句柄
+    fn live_handle(&self) -> Result<LiveHandle<'_>> {
+        Ok(LiveHandle {
+            handle: self.handle,
+            _call: self.state.enter()?,
+        })
+    }
+
+    /// 把引擎的非零状态码转换为错误，致命错误时标记中毒；引擎报告过后台错误时附上其说明
+    fn engine_error(&self, status: c_int) -> Error {
+        self.fetch_background_error();
+        self.state.error(status)
     }
     
-    pub fn put(&self, key: &[u8], value: &[u8]) -> Result<[u8; 32], String> {
-        let mut root_hash = [0u8; 32];
-        let status = unsafe {
+    pub fn put(&self, key: &[u8], value: &[u8]) -> Result<Root> {
+        self.options.check_key(key)?;
+        self.put_value(key, value)
+    }
+
+    /// 写入保留前缀下的键（命名树的数据），只校验键的格式
+    pub(crate) fn put_reserved(&self, key: &[u8], value: &[u8]) -> Result<Root> {
+        self.options.check_key_format(key)?;
+        self.put_value(key, value)
+    }
+
+    fn put_value(&self, key: &[u8], value: &[u8]) -> Result<Root> {
+        self.options.check_value_size(value.len() as u64)?;
+        let _blobs = self.store_blobs([value])?;
+        self.put_sealed(key, &self.seal_value(value))
+    }
+
+    /// 写入已封装的值
+    fn put_sealed(&self, key: &[u8], value: &[u8]) -> Result<Root> {
+        let mut root_hash = Root::default();
+        let updates = self.begin_updates([key])?;
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
             amdb_put(
-                self.handle,
+                *handle,
                 key.as_ptr(),
                 key.len(),
                 value.as_ptr(),
                 value.len(),
                 root_hash.as_mut_ptr(),
             )
-        };
+        });
         
         if status != 0 {
-            let error_msg = unsafe { CStr::from_ptr(amdb_error_string(status)) };
-            return Err(error_msg.to_string_lossy().into_owned());
+            return Err(self.engine_error(status));
         }
+        self.finish_updates(updates)?;
+        self.enforce_retention(&[key])?;
         
         Ok(root_hash)
     }
     
-    pub fn get(&self, key: &[u8], version: Option<u32>) -> Result<Option<Vec<u8>>, String> {
-        let version = version.unwrap_or(0);
+    /// 从 `reader` 分块读取值并流式写入引擎，Rust侧不缓存完整的值；返回写入后的根哈希
+    ///
+    /// `len_hint` 为预计的值长度，仅用于引擎侧预分配。开启 `OpenOptions::value_checksums` 时
+    /// 边读边计算校验和，读完后追加尾部。
+    pub fn put_from_reader(
+        &self,
+        key: &[u8],
+        reader: &mut impl Read,
+        len_hint: Option<u64>,
+    ) -> Result<Root> {
+        self.options.check_key(key)?;
+        if let Some(hint) = len_hint {
+            self.options.check_value_size(hint)?;
+        }
+        if let Some(blobs) = &self.blobs {
+            return self.put_blob_from_reader(blobs, key, reader);
+        }
+
+        let mut checksum = self.options.value_checksums.then(Checksum::new);
+        let reserve = if checksum.is_some() { TRAILER_LEN as u64 } else { 0 };
+
+        let updates = self.begin_updates([key])?;
+        // 整个流式写入期间持有句柄
+        let handle = self.live_handle()?;
+        let mut stream: *mut AmdbPutStream = ptr::null_mut();
+        let status = unsafe {
+            amdb_put_stream_begin(
+                *handle,
+                key.as_ptr(),
+                key.len(),
+                len_hint.map_or(0, |hint| hint + reserve),
+                &mut stream,
+            )
+        };
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+
+        let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
+        let mut written: u64 = 0;
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    unsafe { amdb_put_stream_abort(stream) };
+                    return Err(Error::Io(e));
+                }
+            };
+            written += n as u64;
+            if let Err(e) = self.options.check_value_size(written) {
+                unsafe { amdb_put_stream_abort(stream) };
+                return Err(e);
+            }
+            if let Some(checksum) = &mut checksum {
+                checksum.update(&buf[..n]);
+            }
+            let status = unsafe { amdb_put_stream_write(stream, buf.as_ptr(), n) };
+            if status != 0 {
+                unsafe { amdb_put_stream_abort(stream) };
+                return Err(self.engine_error(status));
+            }
+        }
+        if let Some(checksum) = checksum {
+            let trailer = checksum.trailer();
+            let status = unsafe { amdb_put_stream_write(stream, trailer.as_ptr(), trailer.len()) };
+            if status != 0 {
+                unsafe { amdb_put_stream_abort(stream) };
+                return Err(self.engine_error(status));
+            }
+        }
+
+        let mut root_hash = Root::default();
+        let status = unsafe { amdb_put_stream_finish(stream, root_hash.as_mut_ptr()) };
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        self.finish_updates(updates)?;
+        self.enforce_retention(&[key])?;
+        Ok(root_hash)
+    }
+
+    /// 读取键的最新值或 `version` 版本的值，按 `OpenOptions::read_options` 读取，见 `get_with`
+    pub fn get(&self, key: &[u8], version: Option<KeyVersion>) -> Result<Option<Vec<u8>>> {
+        self.get_with(key, version, &self.options.read)
+    }
+
+    /// 引擎中按原样存储的值，不存在或已删除时为 `None`
+    pub(crate) fn get_stored(&self, key: &[u8], version: Option<KeyVersion>) -> Result<Option<Vec<u8>>> {
+        let version = version.map_or(0, KeyVersion::get);
         let mut result = AmdbResult {
             status: 0,
             error_msg: ptr::null(),
@@ -93,15 +537,10 @@ impl Database {
             data_len: 0,
         };
         
-        let status = unsafe {
-            amdb_get(
-                self.handle,
-                key.as_ptr(),
-                key.len(),
-                version,
-                &mut result,
-            )
-        };
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            amdb_get64(*handle, key.as_ptr(), key.len(), version, &mut result)
+        });
         
         if status == -2 {
             // AMDB_NOT_FOUND
@@ -109,48 +548,485 @@ impl Database {
         }
         
         if status != 0 {
-            let error_msg = unsafe { CStr::from_ptr(amdb_error_string(status)) };
-            return Err(error_msg.to_string_lossy().into_owned());
-        }
-        
-        if result.data.is_null() || result.data_len == 0 {
-            unsafe { amdb_free_result(&mut result) };
-            return Ok(None);
+            return Err(self.engine_error(status));
         }
         
-        let data = unsafe {
-            std::slice::from_raw_parts(result.data as *const u8, result.data_len)
-        }.to_vec();
-        
+        let data = result_value(&result);
         unsafe { amdb_free_result(&mut result) };
-        Ok(Some(data))
+        Ok(data)
     }
     
-    pub fn delete(&self, key: &[u8]) -> Result<(), String> {
-        let status = unsafe { amdb_delete(self.handle, key.as_ptr(), key.len()) };
+    /// 在一次引擎调用中读取多个键的最新值，结果与 `keys` 按位置一一对应，不存在的键为 `None`
+    ///
+    /// 重复的键只读取一次。
+    pub fn multi_get<K: AsRef<[u8]>>(&self, keys: &[K]) -> Result<Vec<Option<Vec<u8>>>> {
+        let mut unique: Vec<&[u8]> = Vec::new();
+        let mut positions: HashMap<&[u8], usize> = HashMap::new();
+        let slots: Vec<usize> = keys
+            .iter()
+            .map(|key| {
+                let key = key.as_ref();
+                *positions.entry(key).or_insert_with(|| {
+                    unique.push(key);
+                    unique.len() - 1
+                })
+            })
+            .collect();
+
+        let ptrs: Vec<*const u8> = unique.iter().map(|k| k.as_ptr()).collect();
+        let lens: Vec<usize> = unique.iter().map(|k| k.len()).collect();
+        let mut results: *mut AmdbResult = ptr::null_mut();
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            amdb_multi_get(*handle, ptrs.as_ptr(), lens.as_ptr(), unique.len(), &mut results)
+        });
         if status != 0 {
-            let error_msg = unsafe { CStr::from_ptr(amdb_error_string(status)) };
-            return Err(error_msg.to_string_lossy().into_owned());
+            return Err(self.engine_error(status));
         }
-        Ok(())
+        if results.is_null() {
+            return Ok(vec![None; keys.len()]);
+        }
+
+        let values: Vec<Option<Vec<u8>>> =
+            unsafe { std::slice::from_raw_parts(results, unique.len()) }
+                .iter()
+                .map(|result| (!result.data.is_null()).then(|| result_bytes(result)))
+                .collect();
+        unsafe { amdb_free_results(results, unique.len()) };
+        let values = values
+            .into_iter()
+            .map(|value| value.map(|value| self.open_value(value)).transpose())
+            .collect::<Result<Vec<_>>>()?;
+        Ok(slots.into_iter().map(|slot| values[slot].clone()).collect())
+    }
+
+    /// 在一次引擎调用中检查多个键是否存在，第 i 位对应 `keys[i]`；不跨FFI传输任何值
+    pub fn multi_contains<K: AsRef<[u8]>>(&self, keys: &[K]) -> Result<BitVec> {
+        let ptrs: Vec<*const u8> = keys.iter().map(|k| k.as_ref().as_ptr()).collect();
+        let lens: Vec<usize> = keys.iter().map(|k| k.as_ref().len()).collect();
+        let mut exists = vec![0u8; keys.len()];
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            amdb_multi_contains(*handle, ptrs.as_ptr(), lens.as_ptr(), keys.len(), exists.as_mut_ptr())
+        });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        Ok(exists.into_iter().map(|e| e != 0).collect())
+    }
+
+    /// 把值分块写入 `writer`，不在内存中整体保留；返回写入的字节数，键不存在时返回 `None`
+    ///
+    /// 读取最新版本期间若该键被并发改写，各块可能来自不同的值；需要一致性时请指定版本。
+    /// 开启 `OpenOptions::value_checksums` 时读完才能校验，校验失败时值已全部写入 `writer`。
+    pub fn get_to_writer(
+        &self,
+        key: &[u8],
+        version: Option<KeyVersion>,
+        writer: &mut impl Write,
+    ) -> Result<Option<u64>> {
+        if let Some(blobs) = &self.blobs {
+            return self.blob_to_writer(blobs, key, version, writer);
+        }
+        let version = version.map_or(0, KeyVersion::get);
+        let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
+        let mut offset: u64 = 0;
+        let mut checksum = self.options.value_checksums.then(Checksum::new);
+        let mut trailer = Vec::new();
+
+        loop {
+            let mut read_len: usize = 0;
+            let mut total_len: u64 = 0;
+            let status = unsafe {
+                amdb_get_chunk64(
+                    *self.live_handle()?,
+                    key.as_ptr(),
+                    key.len(),
+                    version,
+                    offset,
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                    &mut read_len,
+                    &mut total_len,
+                )
+            };
+
+            if status == -2 {
+                // AMDB_NOT_FOUND
+                return Ok(None);
+            }
+            if status != 0 {
+                return Err(self.engine_error(status));
+            }
+
+            let value_len = match checksum {
+                Some(_) => total_len.saturating_sub(TRAILER_LEN as u64),
+                None => total_len,
+            };
+            // 本块中属于值的部分，其余为尾部
+            let take = value_len.saturating_sub(offset).min(read_len as u64) as usize;
+            writer.write_all(&buf[..take])?;
+            if let Some(checksum) = &mut checksum {
+                checksum.update(&buf[..take]);
+                trailer.extend_from_slice(&buf[take..read_len]);
+            }
+            offset += read_len as u64;
+            if read_len == 0 || offset >= total_len {
+                if let Some(checksum) = &checksum {
+                    checksum.verify(&trailer)?;
+                }
+                return Ok(Some(offset.min(value_len)));
+            }
+        }
+    }
+
+    pub fn delete(&self, key: &[u8]) -> Result<()> {
+        self.options.check_key(key)?;
+        self.delete_key(key)
+    }
+
+    /// 删除保留前缀下的键，见 `put_reserved`
+    pub(crate) fn delete_reserved(&self, key: &[u8]) -> Result<()> {
+        self.options.check_key_format(key)?;
+        self.delete_key(key)
+    }
+
+    fn delete_key(&self, key: &[u8]) -> Result<()> {
+        let updates = self.begin_updates([key])?;
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe { amdb_delete(*handle, key.as_ptr(), key.len()) });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        self.finish_updates(updates)?;
+        self.enforce_retention(&[key])
     }
     
-    pub fn get_root_hash(&self) -> Result<[u8; 32], String> {
-        let mut root_hash = [0u8; 32];
-        let status = unsafe { amdb_get_root_hash(self.handle, root_hash.as_mut_ptr()) };
+    /// 在一次引擎调用中写入多个键值对，返回写入后的根哈希；值为 `None` 表示删除
+    ///
+    /// 只检查值的大小；调用方负责校验由用户传入的键（派生出的内部键不受校验约束）。
+    pub(crate) fn batch_put(&self, items: &[BatchItem]) -> Result<Root> {
+        self.batch_put_with(items, None)
+    }
+
+    /// 同 `batch_put`，传入 `stats` 时填入本次提交的统计
+    pub(crate) fn batch_put_with(
+        &self,
+        items: &[BatchItem],
+        stats: Option<&mut CommitStats>,
+    ) -> Result<Root> {
+        self.commit_items(items, stats, None)
+    }
+
+    /// 同 `batch_put`，并返回本次提交后的数据库版本；版本由引擎在提交时一并读取，
+    /// 不会是并发写入产生的版本
+    pub(crate) fn batch_put_versioned(&self, items: &[BatchItem]) -> Result<(Version, Root)> {
+        let mut version = Version::default();
+        let root_hash = self.commit_items(items, None, Some(&mut version))?;
+        Ok((version, root_hash))
+    }
+
+    fn commit_items(
+        &self,
+        items: &[BatchItem],
+        stats: Option<&mut CommitStats>,
+        version: Option<&mut Version>,
+    ) -> Result<Root> {
+        if items.is_empty() {
+            if let Some(stats) = stats {
+                *stats = CommitStats::default();
+            }
+            if let Some(version) = version {
+                *version = self.state_version()?;
+            }
+            return self.get_root_hash();
+        }
+        self.check_batch_values(items)?;
+
+        let keys: Vec<*const u8> = items.iter().map(|(k, _)| k.as_ptr()).collect();
+        let key_lens: Vec<usize> = items.iter().map(|(k, _)| k.len()).collect();
+        let _blobs = self.store_blobs(items.iter().filter_map(|(_, v)| v.as_deref()))?;
+        let sealed = self.seal_batch_values(items);
+        let (values, value_lens) = value_ptrs(&sealed);
+
+        let mut root_hash = Root::default();
+        let mut raw = AmdbCommitStats::default();
+        let mut raw_version = 0u64;
+        let updates = self.begin_updates(items.iter().map(|(k, _)| k.as_slice()))?;
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            if stats.is_some() {
+                amdb_batch_put_stats(
+                    *handle,
+                    keys.as_ptr(),
+                    key_lens.as_ptr(),
+                    values.as_ptr(),
+                    value_lens.as_ptr(),
+                    items.len(),
+                    root_hash.as_mut_ptr(),
+                    &mut raw,
+                )
+            } else if version.is_some() {
+                amdb_batch_put_versioned(
+                    *handle,
+                    keys.as_ptr(),
+                    key_lens.as_ptr(),
+                    values.as_ptr(),
+                    value_lens.as_ptr(),
+                    items.len(),
+                    root_hash.as_mut_ptr(),
+                    &mut raw_version,
+                )
+            } else {
+                amdb_batch_put(
+                    *handle,
+                    keys.as_ptr(),
+                    key_lens.as_ptr(),
+                    values.as_ptr(),
+                    value_lens.as_ptr(),
+                    items.len(),
+                    root_hash.as_mut_ptr(),
+                )
+            }
+        });
+
         if status != 0 {
-            let error_msg = unsafe { CStr::from_ptr(amdb_error_string(status)) };
-            return Err(error_msg.to_string_lossy().into_owned());
+            return Err(self.engine_error(status));
+        }
+        if let Some(stats) = stats {
+            *stats = CommitStats::from_raw(&raw);
+        }
+        if let Some(version) = version {
+            *version = Version(raw_version);
+        }
+        self.finish_updates(updates)?;
+        let written: Vec<&[u8]> = items.iter().map(|(k, _)| k.as_slice()).collect();
+        self.enforce_retention(&written)?;
+
+        Ok(root_hash)
+    }
+
+    /// 检查批量写入项中每个值的大小
+    pub(crate) fn check_batch_values(&self, items: &[BatchItem]) -> Result<()> {
+        for value in items.iter().filter_map(|(_, v)| v.as_ref()) {
+            self.options.check_value_size(value.len() as u64)?;
+        }
+        Ok(())
+    }
+
+    /// 封装批量写入项的值，删除保持 `None`
+    pub(crate) fn seal_batch_values<'v>(
+        &self,
+        items: &'v [BatchItem],
+    ) -> Vec<Option<Cow<'v, [u8]>>> {
+        items
+            .iter()
+            .map(|(_, v)| v.as_deref().map(|v| self.seal_value(v)))
+            .collect()
+    }
+
+    /// 按键升序扫描范围内的最新键值对，支持 `..`、`a..b`、`a..=b` 等任意边界
+    pub fn scan(&self, range: impl RangeBounds<Vec<u8>>) -> Scan<'_> {
+        self.scan_with(range, &IterOptions::new())
+    }
+
+    /// 同 `scan`，按 `options` 读取
+    pub fn scan_with(&self, range: impl RangeBounds<Vec<u8>>, options: &IterOptions) -> Scan<'_> {
+        Scan::new(self, engine_bounds(&range), 0, options)
+    }
+
+    /// 在一次批量写入中原子地删除多个键，返回删除后的根哈希；任一键校验失败时不删除任何键
+    pub fn multi_delete<K: AsRef<[u8]>>(&self, keys: &[K]) -> Result<Root> {
+        let mut items = Vec::with_capacity(keys.len());
+        for key in keys {
+            let key = key.as_ref();
+            self.options.check_key(key)?;
+            items.push((key.to_vec(), None));
+        }
+        self.batch_put(&items)
+    }
+
+    /// 在一次批量写入中删除范围内的全部键，返回删除后的根哈希；
+    /// 范围内有保留前缀下的内部记录时返回 `Error::InvalidKey`，不删除任何键
+    pub fn delete_range(&self, range: impl RangeBounds<Vec<u8>>) -> Result<Root> {
+        let _writes = self.write_lock();
+        let mut items = Vec::new();
+        for item in self.scan(range) {
+            let (key, _) = item?;
+            reserved::check_unreserved(&key)?;
+            items.push((key.into_vec(), None));
+        }
+        self.batch_put(&items)
+    }
+
+    /// 引擎范围查询 [start, end)；`end` 为空表示无上界
+    pub(crate) fn range_query(&self, start: &[u8], end: &[u8]) -> Result<Vec<Entry>> {
+        let handle = self.live_handle()?;
+        let entries = collect_range(&self.state, |results, count| {
+            self.retry_status(|| unsafe {
+                amdb_range_query(
+                    *handle,
+                    start.as_ptr(),
+                    start.len(),
+                    end.as_ptr(),
+                    end.len(),
+                    results,
+                    count,
+                )
+            })
+        })?;
+        self.open_entries(entries)
+    }
+
+    /// 读取 `pinned_at` 时刻（见 `pin`）范围内各键的值，包括已删除的键（值为 `None`）；
+    /// 备份需要它们才能复现根哈希
+    pub(crate) fn range_query_at(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        pinned_at: f64,
+    ) -> Result<Vec<BatchItem>> {
+        let handle = self.live_handle()?;
+        let entries = collect_writes(&self.state, |results, count| {
+            self.retry_status(|| unsafe {
+                amdb_range_query_at(
+                    *handle,
+                    start.as_ptr(),
+                    start.len(),
+                    end.as_ptr(),
+                    end.len(),
+                    pinned_at,
+                    results,
+                    count,
+                )
+            })
+        })?;
+        self.open_batch_items(entries)
+    }
+
+    /// 校验并还原批量写入项中的值
+    pub(crate) fn open_batch_items(&self, items: Vec<BatchItem>) -> Result<Vec<BatchItem>> {
+        items
+            .into_iter()
+            .map(|(key, value)| Ok((key, value.map(|v| self.open_value(v)).transpose()?)))
+            .collect()
+    }
+
+    /// 原子地固定当前状态，返回（时间点, 根哈希）；此后的写入都晚于该时间点
+    pub(crate) fn pin(&self) -> Result<(f64, Root)> {
+        let mut pinned_at = 0.0;
+        let mut root_hash = Root::default();
+        let handle = self.live_handle()?;
+        let status = unsafe { amdb_pin(*handle, &mut pinned_at, root_hash.as_mut_ptr()) };
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        Ok((pinned_at, root_hash))
+    }
+
+    pub fn get_root_hash(&self) -> Result<Root> {
+        let mut root_hash = Root::default();
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe { amdb_get_root_hash(*handle, root_hash.as_mut_ptr()) });
+        if status != 0 {
+            return Err(self.engine_error(status));
         }
         Ok(root_hash)
     }
 }
 
+/// 调用引擎范围查询并取出结果；`query` 接收结果数组和数量的输出指针
+fn collect_range(
+    state: &HandleState,
+    query: impl FnOnce(&mut *mut AmdbResult, &mut usize) -> c_int,
+) -> Result<Vec<Entry>> {
+    let entries = collect_writes(state, query)?;
+    Ok(entries
+        .into_iter()
+        .map(|(key, value)| (key, value.unwrap_or_default()))
+        .collect())
+}
+
+/// 同 `collect_range`，但已删除的键（值的状态为 `AMDB_NOT_FOUND`）的值为 `None`
+fn collect_writes(
+    state: &HandleState,
+    query: impl FnOnce(&mut *mut AmdbResult, &mut usize) -> c_int,
+) -> Result<Vec<BatchItem>> {
+    let mut results: *mut AmdbResult = ptr::null_mut();
+    let mut count: usize = 0;
+    let status = query(&mut results, &mut count);
+    if status != 0 {
+        return Err(state.error(status));
+    }
+    if results.is_null() {
+        return Ok(Vec::new());
+    }
+
+    // 结果数组中键与值交替排列
+    let entries = unsafe { std::slice::from_raw_parts(results, count) }
+        .chunks_exact(2)
+        .map(|pair| (result_bytes(&pair[0]), result_value(&pair[1])))
+        .collect();
+
+    unsafe { amdb_free_results(results, count) };
+    Ok(entries)
+}
+
+/// 把任意边界换算为引擎的半开区间 [start, end)（`end` 为空表示无上界）；
+/// 区间为空时返回 `None`。`a` 之后的最小键是 `a || 0x00`
+pub(crate) fn engine_bounds(range: &impl RangeBounds<Vec<u8>>) -> Option<(Vec<u8>, Vec<u8>)> {
+    let start = match range.start_bound() {
+        Bound::Included(key) => key.clone(),
+        Bound::Excluded(key) => successor(key),
+        Bound::Unbounded => Vec::new(),
+    };
+    let end = match range.end_bound() {
+        Bound::Included(key) => successor(key),
+        Bound::Excluded(key) if key.is_empty() => return None,
+        Bound::Excluded(key) => key.clone(),
+        Bound::Unbounded => Vec::new(),
+    };
+    if !end.is_empty() && start >= end {
+        return None;
+    }
+    Some((start, end))
+}
+
+fn successor(key: &[u8]) -> Vec<u8> {
+    let mut next = key.to_vec();
+    next.push(0x00);
+    next
+}
+
+fn result_bytes(result: &AmdbResult) -> Vec<u8> {
+    if result.data.is_null() || result.data_len == 0 {
+        return Vec::new();
+    }
+    unsafe { std::slice::from_raw_parts(result.data as *const u8, result.data_len) }.to_vec()
+}
+
+/// 可能不存在的值：状态为 `AMDB_NOT_FOUND`（-2）时为 `None`
+fn result_value(result: &AmdbResult) -> Option<Vec<u8>> {
+    (result.status != -2).then(|| result_bytes(result))
+}
+
+/// 批量写入项的值指针和长度，删除传空指针
+fn value_ptrs(sealed: &[Option<Cow<'_, [u8]>>]) -> (Vec<*const u8>, Vec<usize>) {
+    sealed
+        .iter()
+        .map(|v| v.as_deref().map_or((ptr::null(), 0), |v| (v.as_ptr(), v.len())))
+        .unzip()
+}
+
 impl Drop for Database {
     fn drop(&mut self) {
-        unsafe {
-            amdb_close(self.handle);
+        // 已关闭，或中毒后句柄可能已失效，都不再交回引擎
+        if !matches!(self.state.close(), Ok(true)) {
+            return;
         }
+        let mode = self.options.on_drop.close_mode();
+        let status = unsafe { amdb_close_with(self.handle, mode) };
+        state::report_close_error("drop", status);
     }
 }
 
@@ -161,9 +1037,416 @@ mod tests {
     #[test]
     fn test_database() {
         let db = Database::new("./test_data").unwrap();
-        let root_hash = db.put(b"key", b"value").unwrap();
+        let _root_hash = db.put(b"key", b"value").unwrap();
         let value = db.get(b"key", None).unwrap();
         assert_eq!(value, Some(b"value".to_vec()));
     }
+
+    #[test]
+    fn test_get_to_writer() {
+        let _ = std::fs::remove_dir_all("./test_data/stream");
+        let db = Database::new("./test_data/stream").unwrap();
+        let value: Vec<u8> = (0..STREAM_CHUNK_SIZE * 2 + 17).map(|i| i as u8).collect();
+        db.put(b"blob", &value).unwrap();
+
+        let mut out = Vec::new();
+        let written = db.get_to_writer(b"blob", None, &mut out).unwrap();
+        assert_eq!(written, Some(value.len() as u64));
+        assert_eq!(out, value);
+        assert_eq!(db.get_to_writer(b"missing", None, &mut out).unwrap(), None);
+    }
+
+    #[test]
+    fn test_put_from_reader() {
+        let _ = std::fs::remove_dir_all("./test_data/put_from_reader");
+        let db = Database::new("./test_data/put_from_reader").unwrap();
+        let value: Vec<u8> = (0..STREAM_CHUNK_SIZE + 5).map(|i| (i * 7) as u8).collect();
+        let root = db
+            .put_from_reader(b"streamed", &mut value.as_slice(), Some(value.len() as u64))
+            .unwrap();
+        assert_eq!(root, db.get_root_hash().unwrap());
+        assert_eq!(db.get(b"streamed", None).unwrap(), Some(value));
+    }
+
+    #[test]
+    fn test_max_value_size() {
+        let _ = std::fs::remove_dir_all("./test_data/limits");
+        let db = OpenOptions::new()
+            .max_value_size(8)
+            .open("./test_data/limits")
+            .unwrap();
+        db.put(b"small", b"12345678").unwrap();
+        assert!(matches!(
+            db.put(b"big", b"123456789"),
+            Err(Error::ValueTooLarge { size: 9, max: 8 })
+        ));
+        assert!(matches!(
+            db.put_from_reader(b"big", &mut &[0u8; 64][..], None),
+            Err(Error::ValueTooLarge { .. })
+        ));
+        assert_eq!(db.get(b"big", None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_key_validation() {
+        let _ = std::fs::remove_dir_all("./test_data/key_validation");
+        let db = OpenOptions::new()
+            .min_key_len(1)
+            .max_key_len(4)
+            .key_validator(|key| match key.first() {
+                Some(b'_') => Err("reserved prefix".to_string()),
+                _ => Ok(()),
+            })
+            .open("./test_data/key_validation")
+            .unwrap();
+        db.put(b"ok", b"v").unwrap();
+        for key in [&b""[..], b"toolong", b"_abc"] {
+            assert!(matches!(db.put(key, b"v"), Err(Error::InvalidKey(_))));
+        }
+        assert!(matches!(db.delete(b""), Err(Error::InvalidKey(_))));
+    }
+
+    #[test]
+    fn test_multi_get() {
+        let _ = std::fs::remove_dir_all("./test_data/multi_get");
+        let db = Database::new("./test_data/multi_get").unwrap();
+        db.put(b"a", b"1").unwrap();
+        db.put(b"b", b"2").unwrap();
+        db.put(b"gone", b"3").unwrap();
+        db.delete(b"gone").unwrap();
+
+        let values = db
+            .multi_get(&[&b"b"[..], b"missing", b"a", b"gone", b"b"])
+            .unwrap();
+        assert_eq!(
+            values,
+            vec![Some(b"2".to_vec()), None, Some(b"1".to_vec()), None, Some(b"2".to_vec())]
+        );
+        assert!(db.multi_get::<&[u8]>(&[]).unwrap().is_empty());
+
+        let exists = db.multi_contains(&[&b"a"[..], b"missing", b"gone", b"b"]).unwrap();
+        assert_eq!(exists.iter().collect::<Vec<_>>(), vec![true, false, false, true]);
+    }
+
+    #[test]
+    fn test_poisoned_database() {
+        let _ = std::fs::remove_dir_all("./test_data/poisoned");
+        let db = Database::new("./test_data/poisoned").unwrap();
+        db.put(b"k", b"v").unwrap();
+        assert!(!db.is_poisoned());
+
+        // 致命状态码使数据库中毒，之后的调用不再进入引擎
+        assert!(matches!(db.engine_error(-6), Error::Fatal(_)));
+        assert!(db.is_poisoned());
+        assert!(matches!(db.get(b"k", None), Err(Error::Poisoned)));
+        assert!(matches!(db.put(b"k", b"w"), Err(Error::Poisoned)));
+        assert!(matches!(db.scan(..).next(), Some(Err(Error::Poisoned))));
+        assert!(db.start_pruner(&PruneOptions::new()).is_err());
+
+        // 关闭时不再调用引擎
+        db.close().unwrap();
+        assert!(matches!(db.get(b"k", None), Err(Error::Closed)));
+    }
+
+    #[test]
+    fn test_background_error() {
+        let _ = std::fs::remove_dir_all("./test_data/background_error");
+        let db = Database::new("./test_data/background_error").unwrap();
+        db.put(b"k", b"v").unwrap();
+        assert!(db.background_error().is_none());
+
+        // 之后的错误都附上后台错误，成功的调用不受影响
+        db.state.set_background_error("WAL写入失败: disk full".to_string());
+        db.state.set_background_error("ignored".to_string());
+        assert_eq!(db.background_error().as_deref(), Some("WAL写入失败: disk full"));
+        assert_eq!(db.get(b"k", None).unwrap(), Some(b"v".to_vec()));
+        let error = db.engine_error(-5);
+        assert!(matches!(error.inner(), Error::OutOfMemory));
+        assert!(error.to_string().contains("disk full"), "{}", error);
+
+        db.close().unwrap();
+        assert!(matches!(
+            db.get(b"k", None),
+            Err(Error::Background { ref error, .. }) if matches!(**error, Error::Closed)
+        ));
+        assert!(db.background_error().is_some());
+    }
+
+    #[test]
+    fn test_on_drop() {
+        let _ = std::fs::remove_dir_all("./test_data/on_drop");
+        for behavior in [DropBehavior::Sync, DropBehavior::Flush] {
+            let db = OpenOptions::new()
+                .on_drop(behavior)
+                .open("./test_data/on_drop")
+                .unwrap();
+            db.put(b"k", format!("{:?}", behavior).as_bytes()).unwrap();
+            drop(db);
+            let db = Database::new("./test_data/on_drop").unwrap();
+            assert_eq!(
+                db.get(b"k", None).unwrap(),
+                Some(format!("{:?}", behavior).into_bytes())
+            );
+        }
+
+        let db = OpenOptions::new()
+            .on_drop(DropBehavior::Detach)
+            .open("./test_data/on_drop")
+            .unwrap();
+        db.put(b"detached", b"v").unwrap();
+        drop(db);
+    }
+
+    #[test]
+    fn test_engine_open_options() {
+        let _ = std::fs::remove_dir_all("./test_data/open_options_missing");
+        assert!(matches!(
+            OpenOptions::new()
+                .create_if_missing(false)
+                .open("./test_data/open_options_missing"),
+            Err(Error::NotFound)
+        ));
+        assert!(matches!(
+            OpenOptions::new()
+                .read_only(true)
+                .open("./test_data/open_options_missing"),
+            Err(Error::NotFound)
+        ));
+
+        let db = OpenOptions::new()
+            .cache_size(1 << 20)
+            .node_cache_size(64)
+            .sync_mode(SyncMode::EveryCommit)
+            .compression(false)
+            .open("./test_data/open_options")
+            .unwrap();
+        db.put(b"k", b"v").unwrap();
+        db.close().unwrap();
+
+        let db = OpenOptions::new()
+            .read_only(true)
+            .open("./test_data/open_options")
+            .unwrap();
+        assert_eq!(db.get(b"k", None).unwrap(), Some(b"v".to_vec()));
+        assert!(matches!(db.put(b"k", b"w"), Err(Error::ReadOnly)));
+        assert!(matches!(db.delete(b"k"), Err(Error::ReadOnly)));
+        db.close().unwrap();
+
+        assert!(matches!(
+            OpenOptions::new()
+                .node_cache_size(0)
+                .open("./test_data/open_options"),
+            Err(Error::InvalidArgument(_))
+        ));
+
+        let db = OpenOptions::new()
+            .sync_mode(SyncMode::Batched(std::time::Duration::from_millis(2)))
+            .open("./test_data/open_options")
+            .unwrap();
+        for i in 0..10u8 {
+            db.put(&[i], b"v").unwrap();
+        }
+        db.close().unwrap();
+        assert!(matches!(
+            OpenOptions::new()
+                .sync_mode(SyncMode::Batched(std::time::Duration::ZERO))
+                .open("./test_data/open_options"),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_file_limits() {
+        let _ = std::fs::remove_dir_all("./test_data/file_limits");
+        let open = || {
+            OpenOptions::new()
+                .max_open_files(1)
+                .target_file_size(1024)
+                .sync_mode(SyncMode::EveryCommit)
+                .open("./test_data/file_limits")
+        };
+        let db = open().unwrap();
+        let mut batch = WriteBatch::new();
+        for i in 0..100u8 {
+            batch.put(&[b'k', i], &[i; 512]);
+        }
+        db.write_batch(&batch).unwrap();
+        // 每个文件只容得下一个条目
+        assert_eq!(db.compaction_stats().unwrap().files.len(), 100);
+        db.close().unwrap();
+
+        let db = open().unwrap();
+        for i in 0..100u8 {
+            assert_eq!(db.get(&[b'k', i], None).unwrap(), Some(vec![i; 512]));
+        }
+        db.close().unwrap();
+
+        // 0为不限制，与配置文件的含义一致
+        let db = OpenOptions::new()
+            .max_open_files(0)
+            .open("./test_data/file_limits")
+            .unwrap();
+        assert_eq!(db.get(&[b'k', 7], None).unwrap(), Some(vec![7; 512]));
+        db.close().unwrap();
+
+        assert!(matches!(
+            OpenOptions::new()
+                .target_file_size(0)
+                .open("./test_data/file_limits"),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_lock_wait() {
+        let _ = std::fs::remove_dir_all("./test_data/lock_wait");
+        let db = Database::new("./test_data/lock_wait").unwrap();
+        assert!(matches!(
+            Database::new("./test_data/lock_wait"),
+            Err(Error::Busy)
+        ));
+        // 只读打开不加锁
+        let reader = OpenOptions::new()
+            .read_only(true)
+            .open("./test_data/lock_wait")
+            .unwrap();
+        drop(reader);
+        assert!(matches!(
+            OpenOptions::new()
+                .lock_wait(Duration::from_secs(10))
+                .open_timeout(Duration::from_millis(100))
+                .open("./test_data/lock_wait"),
+            Err(Error::TimedOut)
+        ));
+
+        let waiter = thread::spawn(|| {
+            OpenOptions::new()
+                .lock_wait(Duration::from_secs(10))
+                .open("./test_data/lock_wait")
+        });
+        thread::sleep(Duration::from_millis(50));
+        db.close().unwrap();
+        waiter.join().unwrap().unwrap().put(b"k", b"v").unwrap();
+    }
+
+    #[test]
+    fn test_raw_handle() {
+        let _ = std::fs::remove_dir_all("./test_data/raw_handle");
+        let db = Database::new("./test_data/raw_handle").unwrap();
+        db.put(b"k", b"v").unwrap();
+        let handle = db.into_raw();
+        assert!(!handle.is_null());
+
+        let db = unsafe { Database::from_raw(handle) };
+        assert_eq!(db.get(b"k", None).unwrap(), Some(b"v".to_vec()));
+        db.close().unwrap();
+        assert!(db.into_raw().is_null());
+    }
+
+    #[test]
+    fn test_use_after_close() {
+        let _ = std::fs::remove_dir_all("./test_data/close");
+        let db = Database::new("./test_data/close").unwrap();
+        db.put(b"k", b"v").unwrap();
+        db.close().unwrap();
+        assert!(matches!(db.get(b"k", None), Err(Error::Closed)));
+        assert!(matches!(db.put(b"k", b"w"), Err(Error::Closed)));
+        assert!(matches!(db.scan(..).next(), Some(Err(Error::Closed))));
+        assert!(matches!(db.close(), Err(Error::Closed)));
+
+        let db = Database::new("./test_data/close").unwrap();
+        assert_eq!(db.get(b"k", None).unwrap(), Some(b"v".to_vec()));
+    }
+
+    #[test]
+    fn test_engine_bounds() {
+        let k = |s: &[u8]| s.to_vec();
+        assert_eq!(engine_bounds(&(..)), Some((k(b""), k(b""))));
+        assert_eq!(engine_bounds(&(k(b"a")..k(b"c"))), Some((k(b"a"), k(b"c"))));
+        assert_eq!(engine_bounds(&(k(b"a")..=k(b"c"))), Some((k(b"a"), k(b"c\0"))));
+        assert_eq!(
+            engine_bounds(&(Bound::Excluded(k(b"a")), Bound::Unbounded)),
+            Some((k(b"a\0"), k(b"")))
+        );
+        assert_eq!(engine_bounds(&(..k(b""))), None);
+        assert_eq!(engine_bounds(&(k(b"c")..k(b"a"))), None);
+    }
+
+    #[test]
+    fn test_multi_delete() {
+        let _ = std::fs::remove_dir_all("./test_data/multi_delete");
+        let db = OpenOptions::new()
+            .min_key_len(1)
+            .open("./test_data/multi_delete")
+            .unwrap();
+        for key in [b"m1", b"m2", b"m3"] {
+            db.put(key, b"v").unwrap();
+        }
+        assert!(matches!(db.multi_delete(&[&b"m1"[..], b""]), Err(Error::InvalidKey(_))));
+        assert!(db.get(b"m1", None).unwrap().is_some());
+
+        let root_hash = db.multi_delete(&[&b"m1"[..], b"m3"]).unwrap();
+        assert_eq!(root_hash, db.get_root_hash().unwrap());
+        let keys: Vec<Box<[u8]>> = db.scan(..).map(|item| item.unwrap().0).collect();
+        assert_eq!(keys, vec![b"m2"[..].into()]);
+    }
+
+    #[test]
+    fn test_delete_range() {
+        let _ = std::fs::remove_dir_all("./test_data/delete_range");
+        let db = Database::new("./test_data/delete_range").unwrap();
+        for key in [b"r/1", b"r/2", b"r/3", b"s/1"] {
+            db.put(key, b"v").unwrap();
+        }
+        db.delete_range(b"r/2".to_vec()..).unwrap();
+        let keys: Vec<Box<[u8]>> = db.scan(..).map(|item| item.unwrap().0).collect();
+        assert_eq!(keys, vec![b"r/1"[..].into()]);
+    }
+
+    #[test]
+    fn test_shared_across_threads() {
+        let _ = std::fs::remove_dir_all("./test_data/shared");
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Database>();
+
+        let db = Arc::new(Database::new("./test_data/shared").unwrap());
+        std::thread::scope(|scope| {
+            for t in 0..4u8 {
+                let db = Arc::clone(&db);
+                scope.spawn(move || {
+                    for i in 0..20u8 {
+                        db.put(&[b'w', t, i], &[i]).unwrap();
+                        assert_eq!(db.get(&[b'w', t, i], None).unwrap(), Some(vec![i]));
+                    }
+                });
+            }
+            // 同一个幂等令牌的批次只应用一次
+            for _ in 0..4 {
+                let db = Arc::clone(&db);
+                scope.spawn(move || {
+                    let mut batch = WriteBatch::new();
+                    batch.idempotency_token(b"once").put(b"counter", b"1");
+                    db.write_batch(&batch).unwrap();
+                });
+            }
+        });
+        assert_eq!(db.scan(b"w".to_vec()..b"x".to_vec()).count(), 80);
+        assert_eq!(db.get(b"counter", Some(KeyVersion(1))).unwrap(), Some(b"1".to_vec()));
+        assert!(db.get(b"counter", Some(KeyVersion(2))).unwrap().is_none());
+
+        // 关闭与进行中的读取并发：读取要么成功，要么返回 `Error::Closed`
+        std::thread::scope(|scope| {
+            let reader = scope.spawn(|| loop {
+                match db.get(b"counter", None) {
+                    Ok(value) => assert_eq!(value, Some(b"1".to_vec())),
+                    Err(Error::Closed) => break,
+                    Err(e) => panic!("unexpected error: {}", e),
+                }
+            });
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            db.close().unwrap();
+            reader.join().unwrap();
+        });
+    }
 }
 
@@ -0,0 +1,276 @@
+//! Merkle树创建选项
+//! 引擎在新建数据目录时把选项记录在 `merkle/tree_options.json` 中，之后每次打开都沿用记录的值；
+//! 打开时给出的选项与记录不一致返回 `Error::InvalidArgument`，不会改变已有的树。
+//!
+//! 节点哈希的域分隔方式同样是创建选项，设置 `node_prefix` 后扩展和分支节点都以它代替默认标签：
+//!
+//! ```text
+//! 叶子   sha256(leaf_prefix | 按 KeyFraming 编码的键 | 值)，leaf_prefix 默认为 "leaf:"
+//! 扩展   sha256("ext:" | nibble | ":" | 子节点哈希)   或 sha256(node_prefix | nibble | 子节点哈希)
+//! 分支   sha256("branch:" | 16个子节点哈希)           或 sha256(node_prefix | 16个子节点哈希)
+//! ```
+
+use std::collections::BTreeMap;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+use crate::backup::from_hex;
+use crate::sha256::sha256;
+use crate::{
+    amdb_free_result, amdb_get_tree_option, amdb_init_with_options, result_bytes, AmdbHandle,
+    AmdbResult, Database, Error, Result,
+};
+
+/// 以打开选项和创建选项调用 `amdb_init_with_options`，返回状态码
+pub(crate) fn init_with_options(
+    data_dir: &CStr,
+    options: &BTreeMap<&'static str, String>,
+    handle: &mut *mut AmdbHandle,
+) -> Result<c_int> {
+    let pairs = options
+        .iter()
+        .map(|(name, value)| Ok((CString::new(*name)?, CString::new(value.as_str())?)))
+        .collect::<std::result::Result<Vec<_>, std::ffi::NulError>>()
+        .map_err(|e| Error::InvalidArgument(e.to_string()))?;
+    let names: Vec<*const c_char> = pairs.iter().map(|(name, _)| name.as_ptr()).collect();
+    let values: Vec<*const c_char> = pairs.iter().map(|(_, value)| value.as_ptr()).collect();
+    Ok(unsafe {
+        amdb_init_with_options(
+            data_dir.as_ptr(),
+            names.as_ptr(),
+            values.as_ptr(),
+            pairs.len(),
+            handle,
+        )
+    })
+}
+
+/// 叶子节点哈希中键的编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyFraming {
+    /// 键后接一个 `:`（默认）
+    #[default]
+    Separator,
+    /// 键与值直接拼接
+    Bare,
+    /// 键前加4字节大端长度
+    LengthPrefixed,
+}
+
+impl KeyFraming {
+    pub(crate) fn as_option(self) -> &'static str {
+        match self {
+            KeyFraming::Separator => "separator",
+            KeyFraming::Bare => "none",
+            KeyFraming::LengthPrefixed => "u32be",
+        }
+    }
+
+    fn from_option(value: &str) -> Option<Self> {
+        [Self::Separator, Self::Bare, Self::LengthPrefixed]
+            .into_iter()
+            .find(|framing| framing.as_option() == value)
+    }
+
+    /// 证明编码中的键编码字节
+    pub(crate) fn as_byte(self) -> u8 {
+        match self {
+            KeyFraming::Separator => 0,
+            KeyFraming::Bare => 1,
+            KeyFraming::LengthPrefixed => 2,
+        }
+    }
+
+    pub(crate) fn from_byte(byte: u8) -> Option<Self> {
+        [Self::Separator, Self::Bare, Self::LengthPrefixed]
+            .into_iter()
+            .find(|framing| framing.as_byte() == byte)
+    }
+}
+
+/// 重算节点哈希所需的域分隔方案
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct HashScheme {
+    pub(crate) leaf_prefix: Vec<u8>,
+    /// 为空时使用默认标签
+    pub(crate) node_prefix: Vec<u8>,
+    pub(crate) key_framing: KeyFraming,
+}
+
+impl Default for HashScheme {
+    fn default() -> Self {
+        HashScheme {
+            leaf_prefix: b"leaf:".to_vec(),
+            node_prefix: Vec::new(),
+            key_framing: KeyFraming::Separator,
+        }
+    }
+}
+
+impl HashScheme {
+    pub(crate) fn leaf(&self, key: &[u8], value: &[u8]) -> [u8; 32] {
+        let len = (key.len() as u32).to_be_bytes();
+        match self.key_framing {
+            KeyFraming::Separator => sha256(&[&self.leaf_prefix, key, b":", value]),
+            KeyFraming::Bare => sha256(&[&self.leaf_prefix, key, value]),
+            KeyFraming::LengthPrefixed => sha256(&[&self.leaf_prefix, &len, key, value]),
+        }
+    }
+
+    pub(crate) fn extension(&self, nibble: u8, child: &[u8]) -> [u8; 32] {
+        if self.node_prefix.is_empty() {
+            sha256(&[b"ext:", &[nibble], b":", child])
+        } else {
+            sha256(&[&self.node_prefix, &[nibble], child])
+        }
+    }
+
+    pub(crate) fn branch(&self, children: &[&[u8]]) -> [u8; 32] {
+        let tag: &[u8] = if self.node_prefix.is_empty() {
+            b"branch:"
+        } else {
+            &self.node_prefix
+        };
+        let mut parts = Vec::with_capacity(1 + children.len());
+        parts.push(tag);
+        parts.extend_from_slice(children);
+        sha256(&parts)
+    }
+}
+
+impl Database {
+    /// 数据目录创建时记录的节点哈希方案
+    pub(crate) fn hash_scheme(&self) -> Result<HashScheme> {
+        let mut scheme = HashScheme::default();
+        let hex_option = |name: &str| -> Result<Option<Vec<u8>>> {
+            self.tree_option(name)?
+                .map(|hex| {
+                    from_hex(&hex).ok_or_else(|| {
+                        Error::Corruption(format!("invalid {} option {:?}", name, hex))
+                    })
+                })
+                .transpose()
+        };
+        if let Some(prefix) = hex_option("leaf_prefix")? {
+            scheme.leaf_prefix = prefix;
+        }
+        if let Some(prefix) = hex_option("node_prefix")? {
+            scheme.node_prefix = prefix;
+        }
+        if let Some(framing) = self.tree_option("key_framing")? {
+            scheme.key_framing = KeyFraming::from_option(&framing).ok_or_else(|| {
+                Error::Corruption(format!("invalid key_framing option {:?}", framing))
+            })?;
+        }
+        Ok(scheme)
+    }
+
+    /// 数据目录创建时记录的空子树占位哈希；`None` 表示默认的空字节串
+    pub fn empty_subtree_hash(&self) -> Result<Option<[u8; 32]>> {
+        let hex = self.tree_option("empty_hash")?.unwrap_or_default();
+        if hex.is_empty() {
+            return Ok(None);
+        }
+        from_hex(&hex)
+            .and_then(|hash| hash.try_into().ok())
+            .map(Some)
+            .ok_or_else(|| Error::Corruption(format!("invalid empty_hash option {:?}", hex)))
+    }
+
+    /// 引擎记录的创建选项，未知选项返回 `None`
+    pub(crate) fn tree_option(&self, name: &str) -> Result<Option<String>> {
+        let c_name = CString::new(name).map_err(|e| Error::InvalidArgument(e.to_string()))?;
+        let mut result = AmdbResult {
+            status: 0,
+            error_msg: ptr::null(),
+            data: ptr::null_mut(),
+            data_len: 0,
+        };
+        let handle = self.live_handle()?;
+        let status = unsafe { amdb_get_tree_option(*handle, c_name.as_ptr(), &mut result) };
+        if status == -2 {
+            // AMDB_NOT_FOUND
+            return Ok(None);
+        }
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        let data = result_bytes(&result);
+        unsafe { amdb_free_result(&mut result) };
+        String::from_utf8(data)
+            .map(Some)
+            .map_err(|e| Error::Corruption(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::OpenOptions;
+
+    use super::*;
+
+    #[test]
+    fn test_empty_subtree_hash() {
+        let _ = std::fs::remove_dir_all("./test_data/empty_subtree_hash");
+        let _ = std::fs::remove_dir_all("./test_data/empty_subtree_default");
+        let dir = "./test_data/empty_subtree_hash";
+        let zero = [0x5au8; 32];
+        let db = OpenOptions::new()
+            .empty_subtree_hash(&zero)
+            .open(dir)
+            .unwrap();
+        assert_eq!(db.empty_subtree_hash().unwrap(), Some(zero));
+        assert_eq!(db.get_root_hash().unwrap(), zero);
+        let root = db.put(b"k", b"v").unwrap();
+        drop(db);
+
+        // 重新打开沿用记录的选项；给出不同的选项会被拒绝
+        let db = Database::new(dir).unwrap();
+        assert_eq!(db.empty_subtree_hash().unwrap(), Some(zero));
+        assert_eq!(db.get_root_hash().unwrap(), root);
+        drop(db);
+        assert!(matches!(
+            OpenOptions::new().empty_subtree_hash(&[0; 32]).open(dir),
+            Err(Error::InvalidArgument(_))
+        ));
+
+        let db = Database::new("./test_data/empty_subtree_default").unwrap();
+        assert_eq!(db.empty_subtree_hash().unwrap(), None);
+        assert_eq!(db.get_root_hash().unwrap(), [0; 32]);
+    }
+
+    #[test]
+    fn test_hash_scheme_options() {
+        let _ = std::fs::remove_dir_all("./test_data/hash_scheme");
+        let _ = std::fs::remove_dir_all("./test_data/hash_scheme_default");
+        let db = OpenOptions::new()
+            .leaf_prefix(&[0x00])
+            .node_prefix(&[0x01])
+            .key_framing(KeyFraming::LengthPrefixed)
+            .open("./test_data/hash_scheme")
+            .unwrap();
+        // 只有一个键时根哈希就是叶子哈希
+        let root = db.put(b"key", b"value").unwrap();
+        assert_eq!(root, sha256(&[&[0x00], &[0, 0, 0, 3], b"key", b"value"]));
+
+        db.put(b"other", b"v").unwrap();
+        let root = db.get_root_hash().unwrap();
+        let (_, proof) = db.get_with_proof(b"key", None).unwrap();
+        let proof = crate::Proof::from_bytes(&proof.to_bytes()).unwrap();
+        assert!(proof.verify(&root, b"key", b"value"));
+        assert_eq!(
+            db.hash_scheme().unwrap(),
+            HashScheme {
+                leaf_prefix: vec![0x00],
+                node_prefix: vec![0x01],
+                key_framing: KeyFraming::LengthPrefixed,
+            }
+        );
+
+        let db = Database::new("./test_data/hash_scheme_default").unwrap();
+        assert_eq!(db.hash_scheme().unwrap(), HashScheme::default());
+        let root = db.put(b"key", b"value").unwrap();
+        assert_eq!(root, sha256(&[b"leaf:key:value"]));
+    }
+}
@@ -0,0 +1,187 @@
+//! 移动端接口（`mobile` 特性）
+//! 以 UniFFI 导出给 Kotlin/Swift，供 iOS/Android 轻客户端嵌入证明验证和一个小型本地库。
+//! 数据库不以指针导出，而是登记在进程内的表中、以整数句柄ID引用：
+//! 宿主语言的垃圾回收顺序或重复关闭只会得到 `MobileError::InvalidHandle`，不会访问已释放的内存。
+//! 生成绑定：
+//!
+//! ```text
+//! cargo build --release --features mobile
+//! uniffi-bindgen generate --library target/release/libamdb.so --language kotlin --out-dir out
+//! ```
+//!
+//! 验证证明不需要打开数据库，见 `verify_proof`。
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+use crate::{Database, Error, OpenOptions, Proof, Root};
+
+/// 已打开的数据库，键为句柄ID；ID从1开始递增，不会复用
+static DATABASES: OnceLock<Mutex<HashMap<u64, Arc<Database>>>> = OnceLock::new();
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn databases() -> std::sync::MutexGuard<'static, HashMap<u64, Arc<Database>>> {
+    DATABASES
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// 取出句柄ID对应的数据库；调用期间持有引用，不阻塞其他句柄的开关
+fn lookup(id: u64) -> Result<Arc<Database>, MobileError> {
+    databases()
+        .get(&id)
+        .cloned()
+        .ok_or(MobileError::InvalidHandle(id))
+}
+
+/// 导出给宿主语言的错误，只传递说明文字
+#[derive(Debug, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum MobileError {
+    /// 句柄ID不存在或已关闭
+    InvalidHandle(u64),
+    /// 数据库返回的错误
+    Database(Error),
+}
+
+impl fmt::Display for MobileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MobileError::InvalidHandle(id) => write!(f, "invalid database handle {}", id),
+            MobileError::Database(e) => e.fmt(f),
+        }
+    }
+}
+
+impl From<Error> for MobileError {
+    fn from(e: Error) -> Self {
+        MobileError::Database(e)
+    }
+}
+
+/// 带证明的读取结果，见 `db_get_with_proof`
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct ProvenValue {
+    /// 键不存在或已删除时为空
+    pub value: Option<Vec<u8>>,
+    /// `Proof::to_bytes` 的编码
+    pub proof: Vec<u8>,
+    pub root_hash: Vec<u8>,
+}
+
+/// 打开数据库并返回句柄ID，用 `db_close` 关闭
+#[uniffi::export]
+pub fn db_open(data_dir: String, read_only: bool) -> Result<u64, MobileError> {
+    let db = OpenOptions::new().read_only(read_only).open(&data_dir)?;
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    databases().insert(id, Arc::new(db));
+    Ok(id)
+}
+
+/// 注销并关闭数据库；等待其他线程上进行中的调用结束，之后该ID返回 `MobileError::InvalidHandle`
+#[uniffi::export]
+pub fn db_close(id: u64) -> Result<(), MobileError> {
+    let db = databases()
+        .remove(&id)
+        .ok_or(MobileError::InvalidHandle(id))?;
+    db.close()?;
+    Ok(())
+}
+
+#[uniffi::export]
+pub fn db_get(id: u64, key: Vec<u8>) -> Result<Option<Vec<u8>>, MobileError> {
+    Ok(lookup(id)?.get(&key, None)?)
+}
+
+/// 写入键值对，返回写入后的根哈希
+#[uniffi::export]
+pub fn db_put(id: u64, key: Vec<u8>, value: Vec<u8>) -> Result<Vec<u8>, MobileError> {
+    Ok(lookup(id)?.put(&key, &value)?.to_vec())
+}
+
+#[uniffi::export]
+pub fn db_delete(id: u64, key: Vec<u8>) -> Result<(), MobileError> {
+    lookup(id)?.delete(&key)?;
+    Ok(())
+}
+
+#[uniffi::export]
+pub fn db_root_hash(id: u64) -> Result<Vec<u8>, MobileError> {
+    Ok(lookup(id)?.get_root_hash()?.to_vec())
+}
+
+/// 读取键的最新值及其证明，见 `Database::get_with_proof`
+#[uniffi::export]
+pub fn db_get_with_proof(id: u64, key: Vec<u8>) -> Result<ProvenValue, MobileError> {
+    let (value, proof) = lookup(id)?.get_with_proof(&key, None)?;
+    Ok(ProvenValue {
+        value,
+        proof: proof.to_bytes(),
+        root_hash: proof.root_hash().to_vec(),
+    })
+}
+
+/// 验证 `proof`（`Proof::to_bytes` 的编码）：键在 `root_hash` 下的值是否为 `value`；
+/// 编码不合法或根哈希不是32字节时返回 `false`
+#[uniffi::export]
+pub fn verify_proof(proof: Vec<u8>, root_hash: Vec<u8>, key: Vec<u8>, value: Vec<u8>) -> bool {
+    let (Ok(proof), Ok(root_hash)) = (Proof::from_bytes(&proof), <[u8; 32]>::try_from(root_hash))
+    else {
+        return false;
+    };
+    proof.verify(&Root(root_hash), &key, &value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_registry() {
+        let _ = std::fs::remove_dir_all("./test_data/mobile");
+        let id = db_open("./test_data/mobile".to_string(), false).unwrap();
+        let root = db_put(id, b"k".to_vec(), b"v".to_vec()).unwrap();
+        assert_eq!(db_root_hash(id).unwrap(), root);
+        assert_eq!(db_get(id, b"k".to_vec()).unwrap(), Some(b"v".to_vec()));
+
+        let proven = db_get_with_proof(id, b"k".to_vec()).unwrap();
+        assert_eq!(proven.value, Some(b"v".to_vec()));
+        assert!(verify_proof(
+            proven.proof.clone(),
+            root.clone(),
+            b"k".to_vec(),
+            b"v".to_vec()
+        ));
+        assert!(!verify_proof(
+            proven.proof,
+            root,
+            b"k".to_vec(),
+            b"w".to_vec()
+        ));
+        assert!(!verify_proof(
+            vec![1, 2, 3],
+            vec![0; 32],
+            b"k".to_vec(),
+            b"v".to_vec()
+        ));
+
+        // 关闭后ID失效，重复关闭不会访问已释放的数据库
+        db_close(id).unwrap();
+        assert!(matches!(
+            db_get(id, b"k".to_vec()),
+            Err(MobileError::InvalidHandle(_))
+        ));
+        assert!(matches!(db_close(id), Err(MobileError::InvalidHandle(_))));
+
+        let reopened = db_open("./test_data/mobile".to_string(), true).unwrap();
+        assert_ne!(reopened, id);
+        assert!(matches!(
+            db_delete(reopened, b"k".to_vec()),
+            Err(MobileError::Database(Error::ReadOnly))
+        ));
+        db_close(reopened).unwrap();
+    }
+}
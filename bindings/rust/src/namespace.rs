@@ -0,0 +1,211 @@
+//! 命名空间
+//! 每个命名空间是与本库一起打开的独立数据库（数据目录 `namespaces/<name>`），有自己的Merkle树、
+//! 根哈希和证明，键不需要手工加前缀，证明中也不会混入其他命名空间的键。
+//! 命名空间每次提交后，引擎把它的根哈希写入本库的保留键 `\0ns/<name>`，本库的根哈希由此承诺
+//! 全部命名空间的根哈希：验证方先用 `Namespace::root_proof` 对本库的根哈希验证命名空间的根哈希，
+//! 再用 `Namespace::get_with_proof` 对命名空间的根哈希验证键值。
+
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::ops::RangeBounds;
+use std::ptr;
+use std::sync::{Arc, PoisonError};
+
+use crate::keys::prefix_successor;
+use crate::{
+    amdb_namespace_open, amdb_range_query, collect_range, Database, Error, Iter, KeyVersion, Proof,
+    Result, Root, WriteBatch,
+};
+
+/// 引擎登记命名空间根哈希的键前缀
+pub(crate) const RECORD_PREFIX: &[u8] = b"\0ns/";
+
+/// 一个命名空间，见 `Database::namespace`
+pub struct Namespace<'a> {
+    db: &'a Database,
+    name: String,
+    inner: Arc<Database>,
+}
+
+impl Database {
+    /// 打开名为 `name` 的命名空间，不存在时创建；名称只能包含字母、数字、`_` 和 `-`，
+    /// 否则返回 `Error::InvalidArgument`。命名空间沿用本库的打开选项
+    pub fn namespace(&self, name: &str) -> Result<Namespace<'_>> {
+        let mut opened = self
+            .namespaces
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let inner = match opened.get(name) {
+            Some(inner) => inner.clone(),
+            None => {
+                let c_name =
+                    CString::new(name).map_err(|e| Error::InvalidArgument(e.to_string()))?;
+                let mut raw = ptr::null_mut();
+                let handle = self.live_handle()?;
+                let status = self.retry_status(|| unsafe {
+                    amdb_namespace_open(*handle, c_name.as_ptr(), &mut raw)
+                });
+                if status != 0 {
+                    return Err(self.engine_error(status));
+                }
+                // 父库的大值回收看不到命名空间中的引用，命名空间中的值总是内联
+                let mut options = self.options.clone();
+                options.blob_threshold = None;
+                let inner = Arc::new(Database::with_handle(raw, options));
+                opened.insert(name.to_string(), inner.clone());
+                inner
+            }
+        };
+        Ok(Namespace {
+            db: self,
+            name: name.to_string(),
+            inner,
+        })
+    }
+
+    /// 本库当前承诺的各命名空间的根哈希，按名称排序；包括本次打开以来未使用过的命名空间
+    pub fn namespace_roots(&self) -> Result<BTreeMap<String, Root>> {
+        let end = prefix_successor(RECORD_PREFIX);
+        let handle = self.live_handle()?;
+        // 记录由引擎写入，不经值的封装，按原样读取
+        let records = collect_range(&self.state, |results, count| {
+            self.retry_status(|| unsafe {
+                amdb_range_query(
+                    *handle,
+                    RECORD_PREFIX.as_ptr(),
+                    RECORD_PREFIX.len(),
+                    end.as_ptr(),
+                    end.len(),
+                    results,
+                    count,
+                )
+            })
+        })?;
+        records
+            .into_iter()
+            .map(|(key, root)| {
+                let name = String::from_utf8_lossy(&key[RECORD_PREFIX.len()..]).into_owned();
+                Ok((name, to_root(&root)?))
+            })
+            .collect()
+    }
+}
+
+impl Namespace<'_> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 命名空间当前的根哈希
+    pub fn root_hash(&self) -> Result<Root> {
+        self.inner.get_root_hash()
+    }
+
+    /// 写入键值对，返回命名空间写入后的根哈希
+    pub fn put(&self, key: &[u8], value: &[u8]) -> Result<Root> {
+        self.inner.put(key, value)
+    }
+
+    /// 见 `Database::get`
+    pub fn get(&self, key: &[u8], version: Option<KeyVersion>) -> Result<Option<Vec<u8>>> {
+        self.inner.get(key, version)
+    }
+
+    pub fn delete(&self, key: &[u8]) -> Result<()> {
+        self.inner.delete(key)
+    }
+
+    /// 在命名空间内原子地提交批次，见 `Database::write_batch`
+    pub fn write_batch(&self, batch: &WriteBatch) -> Result<Root> {
+        self.inner.write_batch(batch)
+    }
+
+    /// 按键的升序迭代命名空间内 `range` 中的键值对，见 `Database::iter`
+    pub fn iter(&self, range: impl RangeBounds<Vec<u8>>) -> Iter<'_> {
+        self.inner.iter(range)
+    }
+
+    pub fn prefix_iter(&self, prefix: &[u8]) -> Iter<'_> {
+        self.inner.prefix_iter(prefix)
+    }
+
+    /// 键的最新值及其相对命名空间根哈希的证明，见 `Database::get_with_proof`
+    pub fn get_with_proof(&self, key: &[u8]) -> Result<(Option<Vec<u8>>, Proof)> {
+        self.inner.get_with_proof(key, None)
+    }
+
+    /// 本库记录的该命名空间根哈希，及其相对本库根哈希的证明；
+    /// 以记录的根哈希为期望值调用 `Proof::verify`，键为 `namespace_record_key(name)`
+    pub fn root_proof(&self) -> Result<(Root, Proof)> {
+        let (root, proof) = self
+            .db
+            .get_unsealed_with_proof(&namespace_record_key(&self.name))?;
+        Ok((to_root(&root)?, proof))
+    }
+}
+
+/// 本库中登记命名空间 `name` 的根哈希的键
+pub fn namespace_record_key(name: &str) -> Vec<u8> {
+    [RECORD_PREFIX, name.as_bytes()].concat()
+}
+
+fn to_root(bytes: &[u8]) -> Result<Root> {
+    <[u8; 32]>::try_from(bytes)
+        .map(Root)
+        .map_err(|_| Error::Corruption(format!("namespace root of {} bytes", bytes.len())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_namespaces() {
+        let _ = std::fs::remove_dir_all("./test_data/namespaces");
+        let db = Database::new("./test_data/namespaces").unwrap();
+        let accounts = db.namespace("accounts").unwrap();
+        let storage = db.namespace("storage").unwrap();
+        let root = accounts.put(b"alice", b"10").unwrap();
+        storage.put(b"alice", b"code").unwrap();
+        assert_ne!(accounts.root_hash().unwrap(), storage.root_hash().unwrap());
+        assert!(db.get(b"alice", None).unwrap().is_none());
+        assert_eq!(
+            db.namespace("accounts")
+                .unwrap()
+                .get(b"alice", None)
+                .unwrap(),
+            Some(b"10".to_vec())
+        );
+
+        let roots = db.namespace_roots().unwrap();
+        assert_eq!(roots.keys().collect::<Vec<_>>(), ["accounts", "storage"]);
+        assert_eq!(roots["accounts"], root);
+
+        // 两级证明：命名空间的根哈希在本库的根哈希之下，键值在命名空间的根哈希之下
+        let global = db.get_root_hash().unwrap();
+        let (committed, root_proof) = accounts.root_proof().unwrap();
+        assert_eq!(committed, root);
+        assert!(root_proof.verify(&global, &namespace_record_key("accounts"), &*committed));
+        let (value, proof) = accounts.get_with_proof(b"alice").unwrap();
+        assert_eq!(value, Some(b"10".to_vec()));
+        assert!(proof.verify(&committed, b"alice", b"10"));
+
+        // 根哈希记录是保留键：不能伪造，也不出现在本库的扫描中
+        let forged = namespace_record_key("accounts");
+        assert!(matches!(db.put(&forged, &[0; 32]), Err(Error::InvalidKey(_))));
+        assert!(matches!(db.delete(&forged), Err(Error::InvalidKey(_))));
+        assert_eq!(db.iter(..).count(), 0);
+        assert_eq!(db.scan(..).count(), 0);
+        assert_eq!(db.namespace_roots().unwrap()["accounts"], root);
+
+        accounts.delete(b"alice").unwrap();
+        assert_eq!(
+            db.namespace_roots().unwrap()["accounts"],
+            accounts.root_hash().unwrap()
+        );
+        assert!(matches!(
+            db.namespace("bad/name"),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+}
@@ -0,0 +1,351 @@
+//! 打开选项
+//! 用法与 `std::fs::OpenOptions` 相同：先设置选项，再调用 `open`
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::os::raw::c_int;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::ffi::{AMDB_CLOSE_DETACH, AMDB_CLOSE_FLUSH, AMDB_CLOSE_SYNC};
+use crate::backup::to_hex;
+use crate::reserved::check_unreserved;
+use crate::{Database, Error, KeyFraming, ReadOptions, Result, Retention, RetryPolicy};
+
+/// 键校验函数：返回 `Err(原因)` 表示拒绝该键
+pub type KeyValidator = dyn Fn(&[u8]) -> std::result::Result<(), String> + Send + Sync;
+
+/// `Database` 析构时的关闭方式；显式调用 `Database::close` 时总是刷新
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DropBehavior {
+    /// 刷新内存中的数据到磁盘（默认）
+    #[default]
+    Flush,
+    /// 刷新并fsync数据目录下的文件
+    Sync,
+    /// 不刷新，只释放句柄，适合进程退出前快速析构；未刷新的数据依赖WAL恢复
+    Detach,
+}
+
+impl DropBehavior {
+    pub(crate) fn close_mode(self) -> c_int {
+        match self {
+            DropBehavior::Flush => AMDB_CLOSE_FLUSH,
+            DropBehavior::Sync => AMDB_CLOSE_SYNC,
+            DropBehavior::Detach => AMDB_CLOSE_DETACH,
+        }
+    }
+}
+
+/// 引擎持久化写入提交的方式，见 `OpenOptions::sync_mode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyncMode {
+    /// 按引擎的刷新策略持久化，提交先写入内存和WAL（默认）
+    #[default]
+    Normal,
+    /// 每次提交后刷新并fsync数据目录，提交返回时已落盘；写入吞吐明显下降
+    EveryCommit,
+    /// 提交立即返回，至多经过给定的窗口（例如2毫秒）后fsync，窗口内的提交共用一次fsync；
+    /// 进程崩溃最多丢失一个窗口内的提交。窗口按微秒计，不足1微秒时打开失败
+    Batched(Duration),
+}
+
+impl SyncMode {
+    fn as_option(self) -> &'static str {
+        match self {
+            SyncMode::Normal => "normal",
+            SyncMode::EveryCommit => "commit",
+            SyncMode::Batched(_) => "batched",
+        }
+    }
+}
+
+/// 什么写入产生新的数据库版本，见 `OpenOptions::versioning`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Versioning {
+    /// 每次 `put`/`delete` 都是一次提交（默认）
+    #[default]
+    PerPut,
+    /// `put`/`delete` 只改变当前状态（`get`、`get_root_hash` 立即可见），
+    /// 由 `Database::commit` 或 `write_batch` 等批量写入一并提交；适合写入频繁但只需按块留存版本的场景
+    PerCommit,
+}
+
+#[derive(Clone, Default)]
+pub struct OpenOptions {
+    pub(crate) max_value_size: Option<u64>,
+    pub(crate) min_key_len: usize,
+    pub(crate) max_key_len: Option<usize>,
+    pub(crate) key_validator: Option<Arc<KeyValidator>>,
+    pub(crate) retention: Retention,
+    pub(crate) on_drop: DropBehavior,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) value_checksums: bool,
+    pub(crate) blob_threshold: Option<u64>,
+    /// 传给引擎的Merkle树创建选项，见 `merkle`
+    pub(crate) tree_options: BTreeMap<&'static str, String>,
+    /// 传给引擎的打开选项（只读、缓存大小等），每次打开可以不同
+    pub(crate) engine_options: BTreeMap<&'static str, String>,
+    pub(crate) open_timeout: Option<Duration>,
+    pub(crate) proof_cache: usize,
+    /// `Database::get` 使用的读取选项
+    pub(crate) read: ReadOptions,
+}
+
+impl OpenOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 单个值的最大字节数；超过时写入返回 `Error::ValueTooLarge`，不会进入引擎
+    pub fn max_value_size(&mut self, bytes: u64) -> &mut Self {
+        self.max_value_size = Some(bytes);
+        self
+    }
+
+    /// 键的最小字节数（默认0）；设为1即可拒绝空键
+    pub fn min_key_len(&mut self, len: usize) -> &mut Self {
+        self.min_key_len = len;
+        self
+    }
+
+    /// 键的最大字节数（默认不限制）
+    pub fn max_key_len(&mut self, len: usize) -> &mut Self {
+        self.max_key_len = Some(len);
+        self
+    }
+
+    /// 自定义键校验，在长度检查之后执行
+    pub fn key_validator<F>(&mut self, validator: F) -> &mut Self
+    where
+        F: Fn(&[u8]) -> std::result::Result<(), String> + Send + Sync + 'static,
+    {
+        self.key_validator = Some(Arc::new(validator));
+        self
+    }
+
+    /// 历史版本的保留策略（默认 `Retention::KeepAll`），在每次写入提交后对所写的键执行
+    pub fn retention(&mut self, retention: Retention) -> &mut Self {
+        self.retention = retention;
+        self
+    }
+
+    /// 析构时的关闭方式（默认 `DropBehavior::Flush`）；析构中的关闭错误无法返回，
+    /// 计入 `metrics` 计数 `amdb_close_errors_total`（`metrics` 特性），需要处理错误时显式调用 `Database::close`
+    pub fn on_drop(&mut self, behavior: DropBehavior) -> &mut Self {
+        self.on_drop = behavior;
+        self
+    }
+
+    /// 暂时性错误（`Error::Busy`、`Error::TimedOut`）的重试策略（默认不重试），
+    /// 可用 `Database::with_retry` 对单次调用覆盖
+    pub fn retry(&mut self, policy: RetryPolicy) -> &mut Self {
+        self.retry = policy;
+        self
+    }
+
+    /// 为每个值附加 crc32c 校验尾部并在读取时校验（默认关闭），校验失败返回 `Error::Corruption`；
+    /// 同一数据目录每次打开须使用相同的设置，见 `envelope`
+    pub fn value_checksums(&mut self, enabled: bool) -> &mut Self {
+        self.value_checksums = enabled;
+        self
+    }
+
+    /// 超过 `bytes` 字节的值存入数据目录下单独的文件，引擎中只保存引用（默认不分离）；
+    /// 同一数据目录每次打开须使用相同的设置，见 `blob`
+    pub fn blob_threshold(&mut self, bytes: u64) -> &mut Self {
+        self.blob_threshold = Some(bytes);
+        self
+    }
+
+    /// 空子树（分支节点的空位和空树的根）的占位哈希，默认为空字节串；与已有的树实现互通时设为对方的零哈希。
+    /// 只在新建数据目录时生效，重新打开时与创建时记录的不一致返回 `Error::InvalidArgument`
+    pub fn empty_subtree_hash(&mut self, hash: &[u8; 32]) -> &mut Self {
+        self.tree_options.insert("empty_hash", to_hex(hash));
+        self
+    }
+
+    /// 叶子节点哈希的前缀（默认 `b"leaf:"`），例如RFC 6962的 `&[0x00]`；只在新建数据目录时生效
+    pub fn leaf_prefix(&mut self, prefix: &[u8]) -> &mut Self {
+        self.tree_options.insert("leaf_prefix", to_hex(prefix));
+        self
+    }
+
+    /// 扩展和分支节点哈希的前缀，例如RFC 6962的 `&[0x01]`；默认分别使用 `ext:`、`branch:` 标签。
+    /// 只在新建数据目录时生效，空前缀表示默认标签
+    pub fn node_prefix(&mut self, prefix: &[u8]) -> &mut Self {
+        self.tree_options.insert("node_prefix", to_hex(prefix));
+        self
+    }
+
+    /// 叶子节点哈希中键的编码（默认 `KeyFraming::Separator`）；只在新建数据目录时生效
+    pub fn key_framing(&mut self, framing: KeyFraming) -> &mut Self {
+        self.tree_options
+            .insert("key_framing", framing.as_option().to_string());
+        self
+    }
+
+    /// 以只读方式打开（默认读写）：引擎拒绝写入并返回 `Error::ReadOnly`，关闭时不刷新；
+    /// 数据目录不存在时返回 `Error::NotFound`。不加锁，可与另一进程中的写入方同时打开，见 `Database::refresh`
+    pub fn read_only(&mut self, read_only: bool) -> &mut Self {
+        self.engine_options
+            .insert("read_only", read_only.to_string());
+        self
+    }
+
+    /// 数据目录不存在时新建（默认）；设为 `false` 时返回 `Error::NotFound`
+    pub fn create_if_missing(&mut self, create: bool) -> &mut Self {
+        self.engine_options
+            .insert("create_if_missing", create.to_string());
+        self
+    }
+
+    /// 引擎缓存的字节数（默认取引擎配置文件的 `[cache] size`），须大于0
+    pub fn cache_size(&mut self, bytes: u64) -> &mut Self {
+        self.engine_options.insert("cache_size", bytes.to_string());
+        self
+    }
+
+    /// B+树节点缓存的节点数（默认1000），须大于0
+    pub fn node_cache_size(&mut self, nodes: usize) -> &mut Self {
+        self.engine_options
+            .insert("node_cache_size", nodes.to_string());
+        self
+    }
+
+    /// 写入提交的持久化方式（默认 `SyncMode::Normal`）
+    pub fn sync_mode(&mut self, mode: SyncMode) -> &mut Self {
+        self.engine_options
+            .insert("sync", mode.as_option().to_string());
+        match mode {
+            SyncMode::Batched(window) => self
+                .engine_options
+                .insert("sync_window_us", window.as_micros().to_string()),
+            _ => self.engine_options.remove("sync_window_us"),
+        };
+        self
+    }
+
+    /// 数据库版本的粒度（默认 `Versioning::PerPut`），每次打开可以不同
+    pub fn versioning(&mut self, versioning: Versioning) -> &mut Self {
+        let mode = match versioning {
+            Versioning::PerPut => "put",
+            Versioning::PerCommit => "commit",
+        };
+        self.engine_options.insert("versioning", mode.to_string());
+        self
+    }
+
+    /// 引擎的数据压缩开关（默认取引擎配置文件的 `[compression] enable`）
+    pub fn compression(&mut self, enabled: bool) -> &mut Self {
+        self.engine_options
+            .insert("compression", enabled.to_string());
+        self
+    }
+
+    /// 同时打开的SSTable文件数上限（默认取引擎配置文件的 `[database] max_open_files`），0为不限制；
+    /// 达到上限时读写和合并等待其他文件关闭，而不是因进程的文件描述符耗尽而失败
+    pub fn max_open_files(&mut self, files: usize) -> &mut Self {
+        self.engine_options
+            .insert("max_open_files", files.to_string());
+        self
+    }
+
+    /// SSTable的目标字节数（默认取引擎配置文件的 `[database] max_file_size`，256MB），须大于0：
+    /// 分片存储按该大小分割文件，合并不产生超过该大小的文件。调小会增加文件数，须与 `max_open_files` 一起考虑
+    pub fn target_file_size(&mut self, bytes: u64) -> &mut Self {
+        self.engine_options
+            .insert("target_file_size", bytes.to_string());
+        self
+    }
+
+    /// 数据目录已被另一个进程（或本进程的另一个 `Database`）锁定时，等待其释放的时长（默认0）；
+    /// 为0时立即返回 `Error::Busy`，等待超时返回 `Error::TimedOut`。只读打开不加锁也不等待
+    pub fn lock_wait(&mut self, wait: Duration) -> &mut Self {
+        self.engine_options
+            .insert("lock_wait_ms", wait.as_millis().to_string());
+        self
+    }
+
+    /// 整个打开过程（等锁、加载数据）的时限（默认不限制），超时返回 `Error::TimedOut`；
+    /// `lock_wait` 不会超过该时限。超时后后台仍会完成加载，随即按 `on_drop` 关闭
+    pub fn open_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.open_timeout = Some(timeout);
+        self
+    }
+
+    /// 按 (根哈希, 键) 缓存最近生成的证明的条数（默认0，不缓存），见 `proof_cache`
+    pub fn proof_cache(&mut self, entries: usize) -> &mut Self {
+        self.proof_cache = entries;
+        self
+    }
+
+    /// `Database::get` 使用的读取选项（默认不验证），例如 `ReadOptions::verify_against_root`；
+    /// 单次读取可用 `Database::get_with` 另行指定
+    pub fn read_options(&mut self, options: &ReadOptions) -> &mut Self {
+        self.read = options.clone();
+        self
+    }
+
+    pub fn open(&self, data_dir: &str) -> Result<Database> {
+        Database::open_with(data_dir, self.clone())
+    }
+
+    /// 校验调用方传入的键；写入路径在调用引擎之前执行，不合法或落在保留前缀下时返回 `Error::InvalidKey`
+    pub(crate) fn check_key(&self, key: &[u8]) -> Result<()> {
+        check_unreserved(key)?;
+        self.check_key_format(key)
+    }
+
+    /// 只校验长度和 `key_validator`；导入按原样恢复导出流中的内部记录
+    pub(crate) fn check_key_format(&self, key: &[u8]) -> Result<()> {
+        if key.len() < self.min_key_len {
+            return Err(Error::InvalidKey(format!(
+                "key of {} bytes is shorter than the {} byte minimum",
+                key.len(),
+                self.min_key_len
+            )));
+        }
+        if let Some(max) = self.max_key_len {
+            if key.len() > max {
+                return Err(Error::InvalidKey(format!(
+                    "key of {} bytes exceeds the {} byte limit",
+                    key.len(),
+                    max
+                )));
+            }
+        }
+        if let Some(validator) = &self.key_validator {
+            validator(key).map_err(Error::InvalidKey)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_value_size(&self, size: u64) -> Result<()> {
+        match self.max_value_size {
+            Some(max) if size > max => Err(Error::ValueTooLarge { size, max }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for OpenOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OpenOptions")
+            .field("max_value_size", &self.max_value_size)
+            .field("min_key_len", &self.min_key_len)
+            .field("max_key_len", &self.max_key_len)
+            .field("key_validator", &self.key_validator.is_some())
+            .field("retention", &self.retention)
+            .field("on_drop", &self.on_drop)
+            .field("retry", &self.retry)
+            .field("value_checksums", &self.value_checksums)
+            .field("blob_threshold", &self.blob_threshold)
+            .field("tree_options", &self.tree_options)
+            .field("engine_options", &self.engine_options)
+            .field("open_timeout", &self.open_timeout)
+            .field("proof_cache", &self.proof_cache)
+            .field("read", &self.read)
+            .finish()
+    }
+}
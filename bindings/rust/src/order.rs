@@ -0,0 +1,124 @@
+//! 迭代顺序
+//! 本库的全部遍历（`scan`、`iter`、`export`、`diff`、`changed_keys`、快照文件以及命名树和命名空间中的扫描）
+//! 都按 `IterOrder::Key` 返回：键的字节序升序。这是API保证的一部分，与分片、Merkle树创建选项、
+//! `Versioning` 和 `SyncMode` 无关，因此按上一页最后一个键继续读取的分页总是稳定的。
+//!
+//! 与按 sha256(键) 排列状态的外部系统（例如以键哈希为路径的状态树）对账或分页时，
+//! 用 `IterOrder::Hash` 的辅助函数把按键序读出的条目换算为哈希序。
+
+use std::cmp::Ordering;
+
+use crate::sha256::sha256;
+use crate::Database;
+
+/// 条目的排列顺序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum IterOrder {
+    /// 键的字节序升序，本库遍历的顺序
+    #[default]
+    Key,
+    /// sha256(键) 的字节序升序
+    Hash,
+}
+
+impl IterOrder {
+    /// 键在该顺序下的位置：`Key` 为键本身，`Hash` 为 sha256(键)；位置的字节序即该顺序，可用作分页令牌
+    pub fn position(self, key: &[u8]) -> Vec<u8> {
+        match self {
+            IterOrder::Key => key.to_vec(),
+            IterOrder::Hash => sha256(&[key]).to_vec(),
+        }
+    }
+
+    /// 按该顺序比较两个键
+    pub fn compare(self, a: &[u8], b: &[u8]) -> Ordering {
+        match self {
+            IterOrder::Key => a.cmp(b),
+            IterOrder::Hash => sha256(&[a]).cmp(&sha256(&[b])),
+        }
+    }
+
+    /// 把条目重排为该顺序
+    pub fn sort<K: AsRef<[u8]>, V>(self, entries: &mut [(K, V)]) {
+        match self {
+            IterOrder::Key => entries.sort_by(|a, b| a.0.as_ref().cmp(b.0.as_ref())),
+            IterOrder::Hash => entries.sort_by_cached_key(|(key, _)| sha256(&[key.as_ref()])),
+        }
+    }
+
+    /// 条目是否已按该顺序严格递增
+    pub fn is_sorted<K: AsRef<[u8]>, V>(self, entries: &[(K, V)]) -> bool {
+        entries
+            .windows(2)
+            .all(|pair| self.compare(pair[0].0.as_ref(), pair[1].0.as_ref()) == Ordering::Less)
+    }
+
+    /// 已按该顺序排列的条目中，位置在 `after`（上一页最后一个条目的 `position`，首页为 `None`）之后的
+    /// 最多 `limit` 个
+    pub fn page_after<'e, K: AsRef<[u8]>, V>(
+        self,
+        entries: &'e [(K, V)],
+        after: Option<&[u8]>,
+        limit: usize,
+    ) -> &'e [(K, V)] {
+        let start = match after {
+            Some(after) => {
+                entries.partition_point(|(key, _)| self.position(key.as_ref()).as_slice() <= after)
+            }
+            None => 0,
+        };
+        &entries[start..entries.len().min(start.saturating_add(limit))]
+    }
+}
+
+impl Database {
+    /// 遍历返回条目的顺序，总是 `IterOrder::Key`，见 `IterOrder`
+    pub fn iter_order(&self) -> IterOrder {
+        IterOrder::Key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KeyValue, WriteBatch};
+
+    #[test]
+    fn test_iter_order() {
+        let _ = std::fs::remove_dir_all("./test_data/iter_order");
+        let db = Database::new("./test_data/iter_order").unwrap();
+        let mut batch = WriteBatch::new();
+        for i in [7u8, 2, 9, 0, 5, 3, 8, 1, 6, 4] {
+            batch.put(&[b'k', i], &[i]);
+        }
+        batch.put(b"l", b"short");
+        db.write_batch(&batch).unwrap();
+        assert_eq!(db.iter_order(), IterOrder::Key);
+
+        let scanned: Vec<KeyValue> = db.scan(..).collect::<Result<_, _>>().unwrap();
+        assert_eq!(scanned.len(), 11);
+        assert!(IterOrder::Key.is_sorted(&scanned));
+        let iterated: Vec<_> = db.iter(..).collect::<Result<_, _>>().unwrap();
+        assert!(IterOrder::Key.is_sorted(&iterated));
+
+        // 换算为哈希序后按位置分页，每个条目恰好出现一次
+        let mut hashed = scanned.clone();
+        IterOrder::Hash.sort(&mut hashed);
+        assert!(IterOrder::Hash.is_sorted(&hashed));
+        assert!(!IterOrder::Key.is_sorted(&hashed));
+        let mut paged = Vec::new();
+        let mut token = None;
+        loop {
+            let page = IterOrder::Hash.page_after(&hashed, token.as_deref(), 4);
+            let Some((last, _)) = page.last() else { break };
+            token = Some(IterOrder::Hash.position(last));
+            paged.extend_from_slice(page);
+        }
+        assert_eq!(paged, hashed);
+
+        IterOrder::Key.sort(&mut hashed);
+        assert_eq!(hashed, scanned);
+        let page = IterOrder::Key.page_after(&scanned, Some(b"k\x04"), 100);
+        assert_eq!(page.first().unwrap().0.as_ref(), b"k\x05");
+    }
+}
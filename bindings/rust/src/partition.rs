@@ -0,0 +1,105 @@
+//! 并行处理的键区间划分
+//! `Database::partition_ranges` 把某个数据库版本的键空间分为条目数大致相等的相邻区间，并行的导出和验证任务
+//! 各取一个区间扫描即可均分工作。分界点由引擎按该版本Merkle树的结构定位：子树的条目数按节点哈希缓存，
+//! 不遍历全部键，之后的版本只需重新计算变化的路径。结果只由该版本的状态决定，各进程独立计算得到相同的划分。
+
+use std::ops::Bound;
+use std::ptr;
+
+use crate::{
+    amdb_free_results, amdb_partition_ranges, result_bytes, AmdbResult, Database, Error, Result,
+    Version,
+};
+
+/// 键区间，可直接传给 `Database::scan`、`iter` 等
+pub type KeyRange = (Bound<Vec<u8>>, Bound<Vec<u8>>);
+
+impl Database {
+    /// 把数据库版本 `version` 的键空间分为至多 `n` 个条目数大致相等的区间，按键序相邻排列并覆盖全部键：
+    /// 第一个区间没有下界，最后一个没有上界。条目少于 `n` 时区间相应减少，空数据库得到一个不设界的区间。
+    /// `n` 为0时返回 `Error::InvalidArgument`，版本不存在或已被清理时返回 `Error::NotFound`
+    pub fn partition_ranges(&self, n: usize, version: Version) -> Result<Vec<KeyRange>> {
+        if n == 0 {
+            return Err(Error::InvalidArgument(
+                "cannot partition into 0 ranges".to_string(),
+            ));
+        }
+        let (mut results, mut count) = (ptr::null_mut::<AmdbResult>(), 0);
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            amdb_partition_ranges(*handle, version.0, n, &mut results, &mut count)
+        });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        let mut starts: Vec<Vec<u8>> = Vec::new();
+        if !results.is_null() {
+            starts = unsafe { std::slice::from_raw_parts(results, count) }
+                .iter()
+                .map(result_bytes)
+                .collect();
+            unsafe { amdb_free_results(results, count) };
+        }
+
+        let mut ranges = Vec::with_capacity(starts.len() + 1);
+        let mut lower = Bound::Unbounded;
+        for start in starts {
+            ranges.push((lower, Bound::Excluded(start.clone())));
+            lower = Bound::Included(start);
+        }
+        ranges.push((lower, Bound::Unbounded));
+        Ok(ranges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WriteBatch;
+
+    #[test]
+    fn test_partition_ranges() {
+        let _ = std::fs::remove_dir_all("./test_data/partition_ranges");
+        let db = Database::new("./test_data/partition_ranges").unwrap();
+        assert_eq!(
+            db.partition_ranges(4, Version(0)).unwrap(),
+            [(Bound::Unbounded, Bound::Unbounded)]
+        );
+        let mut batch = WriteBatch::new();
+        for i in 0..1000u32 {
+            batch.put(format!("key{:04}", i * 7919 % 1000).as_bytes(), b"v");
+        }
+        db.write_batch(&batch).unwrap();
+        let version = db.state_version().unwrap();
+
+        let ranges = db.partition_ranges(4, version).unwrap();
+        assert_eq!(ranges.len(), 4);
+        assert_eq!(ranges[0].0, Bound::Unbounded);
+        assert_eq!(ranges[3].1, Bound::Unbounded);
+        let mut total = 0;
+        for range in &ranges {
+            let count = db.scan(range.clone()).count();
+            assert!(
+                (200..=300).contains(&count),
+                "{} entries in {:?}",
+                count,
+                range
+            );
+            total += count;
+        }
+        assert_eq!(total, 1000);
+
+        // 之后的写入不改变该版本的划分
+        db.multi_delete(&[b"key0000", b"key0001"]).unwrap();
+        assert_eq!(db.partition_ranges(4, version).unwrap(), ranges);
+        assert_eq!(db.partition_ranges(2000, version).unwrap().len(), 1000);
+        assert!(matches!(
+            db.partition_ranges(0, version),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            db.partition_ranges(4, Version(version.0 + 10)),
+            Err(Error::NotFound)
+        ));
+    }
+}
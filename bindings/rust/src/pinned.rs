@@ -0,0 +1,129 @@
+//! 零拷贝读取
+//! `Database::get_pinned` 返回的 `PinnedValue` 直接借用C库分配的结果缓冲区，解引用为 `&[u8]`，
+//! 释放时才调用 `amdb_free_result`，读取大值时省去一次复制。缓冲区独立于数据库句柄，
+//! `PinnedValue` 可以比 `Database` 活得更久。
+
+use std::fmt;
+use std::ops::Deref;
+use std::ptr;
+
+use crate::{
+    amdb_free_result, amdb_get64, envelope, AmdbResult, Database, KeyVersion, Result, AMDB_NOT_FOUND,
+};
+
+/// C库持有的值，见 `Database::get_pinned`
+pub struct PinnedValue {
+    result: AmdbResult,
+    /// 值本身的长度，开启 `value_checksums` 时不含尾部
+    len: usize,
+}
+
+// 缓冲区由 malloc 分配，只在释放时交还C库，与线程无关
+unsafe impl Send for PinnedValue {}
+unsafe impl Sync for PinnedValue {}
+
+impl Deref for PinnedValue {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.result.data as *const u8, self.len) }
+    }
+}
+
+impl AsRef<[u8]> for PinnedValue {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Drop for PinnedValue {
+    fn drop(&mut self) {
+        unsafe { amdb_free_result(&mut self.result) };
+    }
+}
+
+impl fmt::Debug for PinnedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PinnedValue").field(&&**self).finish()
+    }
+}
+
+impl Database {
+    /// 同 `get`，但不复制值：返回的守卫借用C库的缓冲区，释放时交还。
+    /// 开启大值分离时返回 `Error::InvalidArgument`
+    pub fn get_pinned(&self, key: &[u8], version: Option<KeyVersion>) -> Result<Option<PinnedValue>> {
+        self.check_inline("get_pinned")?;
+        let mut result = AmdbResult {
+            status: 0,
+            error_msg: ptr::null(),
+            data: ptr::null_mut(),
+            data_len: 0,
+        };
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            amdb_get64(
+                *handle,
+                key.as_ptr(),
+                key.len(),
+                version.map_or(0, KeyVersion::get),
+                &mut result,
+            )
+        });
+        if status == AMDB_NOT_FOUND {
+            return Ok(None);
+        }
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        // 先交给守卫，之后的错误返回同样释放缓冲区
+        let mut pinned = PinnedValue {
+            len: result.data_len,
+            result,
+        };
+        if pinned.result.data.is_null() {
+            return Ok(None);
+        }
+        if self.options.value_checksums {
+            pinned.len = envelope::verify(&pinned)?;
+        }
+        Ok(Some(pinned))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenOptions;
+
+    #[test]
+    fn test_get_pinned() {
+        let _ = std::fs::remove_dir_all("./test_data/get_pinned");
+        let db = Database::new("./test_data/get_pinned").unwrap();
+        db.put(b"k", b"first").unwrap();
+        db.put(b"k", b"second").unwrap();
+
+        let value = db.get_pinned(b"k", None).unwrap().unwrap();
+        assert_eq!(&*value, b"second");
+        assert_eq!(&*db.get_pinned(b"k", Some(KeyVersion(1))).unwrap().unwrap(), b"first");
+        assert!(db.get_pinned(b"missing", None).unwrap().is_none());
+        db.delete(b"k").unwrap();
+        assert!(db.get_pinned(b"k", None).unwrap().is_none());
+
+        // 守卫不依赖数据库句柄
+        drop(db);
+        assert_eq!(value.to_vec(), b"second".to_vec());
+    }
+
+    #[test]
+    fn test_get_pinned_with_checksums() {
+        let _ = std::fs::remove_dir_all("./test_data/get_pinned_checksums");
+        let db = OpenOptions::new()
+            .value_checksums(true)
+            .open("./test_data/get_pinned_checksums")
+            .unwrap();
+        db.put(b"k", b"value").unwrap();
+        let value = db.get_pinned(b"k", None).unwrap().unwrap();
+        assert_eq!(value.len(), 5);
+        assert_eq!(value.as_ref(), b"value");
+    }
+}
@@ -0,0 +1,62 @@
+//! 前缀基数
+//! 例如统计每个分片字节下的账户数这类看板，只需要知道有多少个不同的前缀，不需要读出全部键值。
+//! 统计由引擎遍历Merkle树完成，到前缀所在的层即止，哈希相同的子树只计算一次。
+
+use std::ops::RangeBounds;
+
+use crate::{amdb_count_prefixes, engine_bounds, Database, Result};
+
+impl Database {
+    /// 范围内现存的键中不同的 `prefix_len` 字节前缀的个数；短于 `prefix_len` 的键不计，
+    /// `prefix_len` 为0时范围内有键即为1
+    pub fn count_prefixes(
+        &self,
+        prefix_len: usize,
+        range: impl RangeBounds<Vec<u8>>,
+    ) -> Result<u64> {
+        let Some((start, end)) = engine_bounds(&range) else {
+            return Ok(0);
+        };
+        let mut count = 0;
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            amdb_count_prefixes(
+                *handle,
+                prefix_len,
+                start.as_ptr(),
+                start.len(),
+                end.as_ptr(),
+                end.len(),
+                &mut count,
+            )
+        });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_prefixes() {
+        let _ = std::fs::remove_dir_all("./test_data/count_prefixes");
+        let db = Database::new("./test_data/count_prefixes").unwrap();
+        for key in [b"a1x", b"a1y", b"a2x", b"b1x", b"c9z"] {
+            db.put(key, b"v").unwrap();
+        }
+        db.put(b"a", b"short").unwrap();
+        db.delete(b"c9z").unwrap();
+
+        assert_eq!(db.count_prefixes(1, ..).unwrap(), 2);
+        assert_eq!(db.count_prefixes(2, ..).unwrap(), 3);
+        assert_eq!(db.count_prefixes(3, ..).unwrap(), 4);
+        assert_eq!(db.count_prefixes(2, b"a2".to_vec()..).unwrap(), 2);
+        assert_eq!(db.count_prefixes(2, ..b"a2".to_vec()).unwrap(), 1);
+        assert_eq!(db.count_prefixes(0, ..).unwrap(), 1);
+        assert_eq!(db.count_prefixes(4, ..).unwrap(), 0);
+    }
+}
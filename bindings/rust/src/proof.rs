@@ -0,0 +1,380 @@
+//! Merkle证明
+//! `Database::get_with_proof` 返回键的最新值和从根到其叶子的路径证明；`Proof::verify` 只用SHA-256重算
+//! 路径上的节点哈希，不需要打开数据库，轻客户端拿到可信的根哈希即可验证收到的值。
+//!
+//! 证明只对应当前状态，即最新版本；`to_bytes`/`from_bytes` 用于传输：
+//!
+//! ```text
+//! 格式版本 1 (1) | 标志 (1) | 根哈希 (32) | 叶子前缀 | 内部节点前缀 | 键编码 (1) | 路径
+//! ```
+//!
+//! 标志的最低位表示值带有 crc32c 尾部（`OpenOptions::value_checksums`），验证时按同样方式封装期望值；
+//! 次低位表示开启了大值分离（`OpenOptions::blob_threshold`），根哈希之后另有 8 字节（LE）的阈值。
+//! 两个前缀各为 长度 (1) + 字节，与键编码一起记录数据目录的节点哈希方案（见 `merkle`），
+//! 验证方无需知道创建选项。路径的编码见C API的 `amdb_get_with_proof`。
+
+use std::ptr;
+
+use crate::envelope::seal_with;
+use crate::merkle::HashScheme;
+use crate::{
+    amdb_free_result, amdb_get_with_proof64, result_bytes, AmdbResult, Database, Error, KeyFraming,
+    KeyVersion, Result, Root,
+};
+
+const FORMAT_VERSION: u8 = 1;
+pub(crate) const FLAG_CHECKSUMS: u8 = 1;
+pub(crate) const FLAG_BLOBS: u8 = 2;
+
+const STEP_EXTENSION: u8 = 1;
+const STEP_BRANCH: u8 = 2;
+
+/// 路径上的一个节点（不含叶子）
+enum Step<'a> {
+    Extension(u8),
+    /// 16个子节点的哈希，路径上的位置由验证方填入
+    Branch(Box<[&'a [u8]; 16]>),
+}
+
+/// 一个键相对某个根哈希的路径证明
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proof {
+    pub(crate) checksums: bool,
+    pub(crate) blob_threshold: Option<u64>,
+    pub(crate) root_hash: Root,
+    pub(crate) scheme: HashScheme,
+    pub(crate) path: Vec<u8>,
+}
+
+impl Proof {
+    /// 生成证明时的根哈希；验证时应使用独立得到的可信根哈希，而不是这个值
+    pub fn root_hash(&self) -> Root {
+        self.root_hash
+    }
+
+    /// 键在 `root_hash` 下的值是否为 `expected_value`；键不存在或已删除时得到的证明对任何值都验证失败，
+    /// 包括空值：值为空的键与不存在的键可以区分
+    pub fn verify(&self, root_hash: &Root, key: &[u8], expected_value: &[u8]) -> bool {
+        let Some(steps) = parse_path(&self.path) else {
+            return false;
+        };
+        let value = seal_with(expected_value, self.checksums, self.blob_threshold);
+
+        let mut hash = self.scheme.leaf(key, &value);
+        for (pos, step) in steps.iter().enumerate().rev() {
+            let nibble = key_nibble(key, pos);
+            hash = match step {
+                Step::Extension(prefix) if *prefix == nibble => {
+                    self.scheme.extension(nibble, &hash)
+                }
+                Step::Extension(_) => return false,
+                Step::Branch(children) => {
+                    let mut children = **children;
+                    children[nibble as usize] = &hash;
+                    self.scheme.branch(&children)
+                }
+            };
+        }
+        hash == *root_hash
+    }
+
+    /// `to_bytes` 编码的字节数
+    pub fn byte_len(&self) -> usize {
+        let threshold = if self.blob_threshold.is_some() { 8 } else { 0 };
+        37 + threshold + self.scheme.leaf_prefix.len() + self.scheme.node_prefix.len() + self.path.len()
+    }
+
+    /// 路径上的内部节点数（不含叶子），即叶子在树中的深度
+    pub fn depth(&self) -> usize {
+        parse_path(&self.path).map_or(0, |steps| steps.len())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let scheme = &self.scheme;
+        let mut bytes = Vec::with_capacity(self.byte_len());
+        bytes.push(FORMAT_VERSION);
+        bytes.push(self.flags());
+        bytes.extend_from_slice(&*self.root_hash);
+        if let Some(threshold) = self.blob_threshold {
+            bytes.extend_from_slice(&threshold.to_le_bytes());
+        }
+        for prefix in [&scheme.leaf_prefix, &scheme.node_prefix] {
+            bytes.push(prefix.len() as u8);
+            bytes.extend_from_slice(prefix);
+        }
+        bytes.push(scheme.key_framing.as_byte());
+        bytes.extend_from_slice(&self.path);
+        bytes
+    }
+
+    /// 解析 `to_bytes` 的输出；格式不合法时返回 `Error::Corruption`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let header = || Error::Corruption("invalid proof header".to_string());
+        let flags = FLAG_CHECKSUMS | FLAG_BLOBS;
+        if bytes.len() < 34 || bytes[0] != FORMAT_VERSION || bytes[1] & !flags != 0 {
+            return Err(header());
+        }
+        let mut rest = &bytes[34..];
+        let mut blob_threshold = None;
+        if bytes[1] & FLAG_BLOBS != 0 {
+            let (threshold, tail) = rest.split_at_checked(8).ok_or_else(header)?;
+            blob_threshold = Some(u64::from_le_bytes(threshold.try_into().unwrap()));
+            rest = tail;
+        }
+        let mut prefix = || -> Option<Vec<u8>> {
+            let (&len, tail) = rest.split_first()?;
+            let (prefix, tail) = tail.split_at_checked(len as usize)?;
+            rest = tail;
+            Some(prefix.to_vec())
+        };
+        let (leaf_prefix, node_prefix) =
+            (prefix().ok_or_else(header)?, prefix().ok_or_else(header)?);
+        let (&framing, path) = rest.split_first().ok_or_else(header)?;
+        let key_framing = KeyFraming::from_byte(framing).ok_or_else(header)?;
+        let scheme = HashScheme {
+            leaf_prefix,
+            node_prefix,
+            key_framing,
+        };
+        Self::new(
+            bytes[1] & FLAG_CHECKSUMS != 0,
+            blob_threshold,
+            Root(bytes[2..34].try_into().unwrap()),
+            scheme,
+            path.to_vec(),
+        )
+    }
+
+    pub(crate) fn flags(&self) -> u8 {
+        let mut flags = if self.checksums { FLAG_CHECKSUMS } else { 0 };
+        if self.blob_threshold.is_some() {
+            flags |= FLAG_BLOBS;
+        }
+        flags
+    }
+
+    pub(crate) fn new(
+        checksums: bool,
+        blob_threshold: Option<u64>,
+        root_hash: Root,
+        scheme: HashScheme,
+        path: Vec<u8>,
+    ) -> Result<Self> {
+        if parse_path(&path).is_none() {
+            return Err(Error::Corruption("invalid proof path".to_string()));
+        }
+        Ok(Proof {
+            checksums,
+            blob_threshold,
+            root_hash,
+            scheme,
+            path,
+        })
+    }
+}
+
+/// 键的第 `pos` 个nibble，键用完后为0（与引擎建树时一致）
+pub(crate) fn key_nibble(key: &[u8], pos: usize) -> u8 {
+    match key.get(pos / 2) {
+        Some(b) if pos.is_multiple_of(2) => b >> 4,
+        Some(b) => b & 0xf,
+        None => 0,
+    }
+}
+
+fn parse_path(mut path: &[u8]) -> Option<Vec<Step<'_>>> {
+    let mut steps = Vec::new();
+    while let Some((&tag, rest)) = path.split_first() {
+        path = rest;
+        match tag {
+            STEP_EXTENSION => {
+                let (&nibble, rest) = path.split_first()?;
+                if nibble > 0xf {
+                    return None;
+                }
+                steps.push(Step::Extension(nibble));
+                path = rest;
+            }
+            STEP_BRANCH => {
+                let mut children: [&[u8]; 16] = [&[]; 16];
+                for child in &mut children {
+                    let (&len, rest) = path.split_first()?;
+                    if rest.len() < len as usize {
+                        return None;
+                    }
+                    (*child, path) = rest.split_at(len as usize);
+                }
+                steps.push(Step::Branch(Box::new(children)));
+            }
+            _ => return None,
+        }
+    }
+    Some(steps)
+}
+
+impl Database {
+    /// 读取键的最新值及其证明；键不存在或已删除时值为 `None`。
+    /// 证明只对应当前状态，`version` 给出的不是最新版本时返回 `Error::InvalidArgument`。
+    /// 开启 `OpenOptions::proof_cache` 时，`version` 为 `None` 的请求先查缓存
+    pub fn get_with_proof(
+        &self,
+        key: &[u8],
+        version: Option<KeyVersion>,
+    ) -> Result<(Option<Vec<u8>>, Proof)> {
+        let (data, proof) = match (&self.proof_cache, version) {
+            (Some(cache), None) => {
+                let root_hash = self.get_root_hash()?;
+                match cache.get(&root_hash, key) {
+                    Some(cached) => cached,
+                    None => {
+                        let (data, _, proof) = self.proof_of(key, true)?;
+                        cache.insert(key, data.clone(), proof.clone());
+                        (data, proof)
+                    }
+                }
+            }
+            _ => {
+                let (data, current, proof) = self.proof_of(key, true)?;
+                if let Some(version) = version {
+                    if version != current {
+                        return Err(Error::InvalidArgument(format!(
+                            "proofs cover only the latest version {} of the key, not {}",
+                            current, version
+                        )));
+                    }
+                }
+                (data, proof)
+            }
+        };
+        Ok((data.map(|data| self.open_value(data)).transpose()?, proof))
+    }
+
+    /// 当前状态下 `key` 的证明经 `Proof::to_bytes` 编码后的字节数，用于预估嵌入证明所需的空间；
+    /// 键不存在时为其不存在证明的大小。之后的写入可能改变树的形状，结果只对当前根哈希准确
+    pub fn estimate_proof_size(&self, key: &[u8]) -> Result<usize> {
+        Ok(self.get_with_proof(key, None)?.1.byte_len())
+    }
+
+    /// 由引擎直接写入、不带校验和尾部的键（例如命名空间的根哈希记录）的值及其证明
+    pub(crate) fn get_unsealed_with_proof(&self, key: &[u8]) -> Result<(Vec<u8>, Proof)> {
+        let (data, _, proof) = self.proof_of(key, false)?;
+        Ok((data.unwrap_or_default(), proof))
+    }
+
+    /// （引擎中的原始值，不存在或已删除时为 `None`, 键的最新版本号, 证明）；`sealed` 表示值经过封装
+    pub(crate) fn proof_of(
+        &self,
+        key: &[u8],
+        sealed: bool,
+    ) -> Result<(Option<Vec<u8>>, u64, Proof)> {
+        let empty = || AmdbResult {
+            status: 0,
+            error_msg: ptr::null(),
+            data: ptr::null_mut(),
+            data_len: 0,
+        };
+        let (mut value, mut path) = (empty(), empty());
+        let mut current = 0u64;
+        let mut root_hash = Root::default();
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            amdb_get_with_proof64(
+                *handle,
+                key.as_ptr(),
+                key.len(),
+                &mut value,
+                &mut current,
+                &mut path,
+                root_hash.as_mut_ptr(),
+            )
+        });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        let data = (!value.data.is_null()).then(|| result_bytes(&value));
+        let path_bytes = result_bytes(&path);
+        unsafe {
+            amdb_free_result(&mut value);
+            amdb_free_result(&mut path);
+        }
+        let (checksums, blob_threshold) = match sealed {
+            true => (self.options.value_checksums, self.options.blob_threshold),
+            false => (false, None),
+        };
+        let proof = Proof::new(
+            checksums,
+            blob_threshold,
+            root_hash,
+            self.hash_scheme()?,
+            path_bytes,
+        )?;
+        Ok((data, current, proof))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::OpenOptions;
+
+    use super::*;
+
+    #[test]
+    fn test_get_with_proof() {
+        let _ = std::fs::remove_dir_all("./test_data/proof");
+        let db = Database::new("./test_data/proof").unwrap();
+        for key in [&b"a"[..], b"b", b"ab", b"k1", b"k2"] {
+            db.put(key, &[key, b"-v"].concat()).unwrap();
+        }
+        db.put(b"k1", b"k1-v2").unwrap();
+        let root = db.get_root_hash().unwrap();
+
+        let (value, proof) = db.get_with_proof(b"ab", None).unwrap();
+        assert_eq!(value, Some(b"ab-v".to_vec()));
+        assert_eq!(proof.root_hash(), root);
+        assert!(proof.verify(&root, b"ab", b"ab-v"));
+        assert!(!proof.verify(&root, b"ab", b"ab-x"));
+        assert!(!proof.verify(&root, b"a", b"ab-v"));
+        assert!(!proof.verify(&Root::default(), b"ab", b"ab-v"));
+
+        // 往返编码后仍可验证
+        let decoded = Proof::from_bytes(&proof.to_bytes()).unwrap();
+        assert!(decoded.verify(&root, b"ab", b"ab-v"));
+        assert_eq!(proof.byte_len(), proof.to_bytes().len());
+        assert_eq!(db.estimate_proof_size(b"ab").unwrap(), proof.byte_len());
+        assert_eq!(decoded.depth(), proof.depth());
+        // 五个键的首个nibble相同，"ab" 的叶子至少在两层内部节点之下
+        assert!(proof.depth() >= 2);
+        assert!(Proof::from_bytes(&proof.to_bytes()[..40]).is_err());
+
+        let (value, proof) = db.get_with_proof(b"k1", Some(KeyVersion(2))).unwrap();
+        assert!(proof.verify(&root, b"k1", value.as_deref().unwrap()));
+        assert!(matches!(
+            db.get_with_proof(b"k1", Some(KeyVersion(1))),
+            Err(Error::InvalidArgument(_))
+        ));
+
+        let (value, proof) = db.get_with_proof(b"missing", None).unwrap();
+        assert!(value.is_none());
+        assert!(!proof.verify(&root, b"missing", b""));
+        assert_eq!(db.estimate_proof_size(b"missing").unwrap(), proof.byte_len());
+    }
+
+    #[test]
+    fn test_proof_with_checksums() {
+        let _ = std::fs::remove_dir_all("./test_data/proof_checksums");
+        let db = OpenOptions::new()
+            .value_checksums(true)
+            .open("./test_data/proof_checksums")
+            .unwrap();
+        db.put(b"x", b"1").unwrap();
+        db.put(b"y", b"2").unwrap();
+        let (value, proof) = db.get_with_proof(b"y", None).unwrap();
+        assert_eq!(value, Some(b"2".to_vec()));
+        let root = db.get_root_hash().unwrap();
+        assert!(Proof::from_bytes(&proof.to_bytes())
+            .unwrap()
+            .verify(&root, b"y", b"2"));
+        assert_eq!(proof.byte_len(), proof.to_bytes().len());
+        assert!(proof.depth() >= 1);
+    }
+}
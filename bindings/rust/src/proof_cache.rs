@@ -0,0 +1,132 @@
+//! 证明缓存
+//! 开启 `OpenOptions::proof_cache` 时，`Database::get_with_proof` 和 `Prover` 按 (根哈希, 键) 缓存生成的证明：
+//! 根哈希相同时状态相同，证明也相同，热点键的重复请求直接返回缓存的结果，不再遍历树；
+//! 每次请求仍向引擎读取一次当前的根哈希。写入之后旧根哈希的条目不再命中，
+//! 条目数达到上限时淘汰最早加入的。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use crate::{Database, Proof, Root};
+
+type CacheKey = (Root, Vec<u8>);
+
+/// 证明缓存的统计，见 `Database::proof_cache_stats`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct ProofCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// 当前缓存的条目数
+    pub entries: usize,
+}
+
+pub(crate) struct ProofCache {
+    capacity: usize,
+    inner: Mutex<Inner>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// 引擎中的原始值（不存在时为 `None`）及其证明
+    entries: HashMap<CacheKey, (Option<Vec<u8>>, Proof)>,
+    /// 加入顺序，用于淘汰
+    order: VecDeque<CacheKey>,
+}
+
+impl ProofCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        ProofCache {
+            capacity,
+            inner: Mutex::default(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn get(&self, root_hash: &Root, key: &[u8]) -> Option<(Option<Vec<u8>>, Proof)> {
+        let inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        let cached = inner.entries.get(&(*root_hash, key.to_vec())).cloned();
+        let counter = match cached {
+            Some(_) => &self.hits,
+            None => &self.misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        cached
+    }
+
+    /// 按证明的根哈希加入缓存
+    pub(crate) fn insert(&self, key: &[u8], data: Option<Vec<u8>>, proof: Proof) {
+        let entry = (proof.root_hash(), key.to_vec());
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        if inner.entries.contains_key(&entry) {
+            return;
+        }
+        while inner.order.len() >= self.capacity {
+            match inner.order.pop_front() {
+                Some(oldest) => inner.entries.remove(&oldest),
+                None => break,
+            };
+        }
+        inner.order.push_back(entry.clone());
+        inner.entries.insert(entry, (data, proof));
+    }
+
+    fn stats(&self) -> ProofCacheStats {
+        let inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        ProofCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: inner.entries.len(),
+        }
+    }
+}
+
+impl Database {
+    /// 证明缓存的命中统计；没有开启 `OpenOptions::proof_cache` 时返回 `None`
+    pub fn proof_cache_stats(&self) -> Option<ProofCacheStats> {
+        self.proof_cache.as_ref().map(|cache| cache.stats())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::OpenOptions;
+
+    use super::*;
+
+    #[test]
+    fn test_proof_cache() {
+        let _ = std::fs::remove_dir_all("./test_data/proof_cache");
+        let _ = std::fs::remove_dir_all("./test_data/proof_cache_off");
+        let db = OpenOptions::new()
+            .proof_cache(2)
+            .open("./test_data/proof_cache")
+            .unwrap();
+        db.put(b"a", b"1").unwrap();
+        let root = db.put(b"b", b"2").unwrap();
+
+        let (value, proof) = db.get_with_proof(b"a", None).unwrap();
+        assert_eq!(db.get_with_proof(b"a", None).unwrap(), (value, proof));
+        let stats = db.proof_cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+        let (value, proof) = db.get_with_proof(b"a", None).unwrap();
+        assert_eq!(value, Some(b"1".to_vec()));
+        assert!(proof.verify(&root, b"a", b"1"));
+
+        // 根哈希改变后不再命中；超过上限时淘汰最早的条目
+        db.put(b"a", b"3").unwrap();
+        let (value, _) = db.get_with_proof(b"a", None).unwrap();
+        assert_eq!(value, Some(b"3".to_vec()));
+        let (value, _) = db.get_with_proof(b"missing", None).unwrap();
+        assert!(value.is_none());
+        let stats = db.proof_cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (2, 3, 2));
+
+        let plain = Database::new("./test_data/proof_cache_off").unwrap();
+        assert!(plain.proof_cache_stats().is_none());
+    }
+}
@@ -0,0 +1,342 @@
+//! Protobuf 类型（`proto` 特性）
+//! 与 `bindings/proto/amdb.proto` 一一对应，并提供与本地类型之间的转换
+
+use crate::merkle::HashScheme;
+use crate::{Error, Root};
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Put {
+    #[prost(bytes = "vec", tag = "1")]
+    pub key: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub value: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Delete {
+    #[prost(bytes = "vec", tag = "1")]
+    pub key: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BatchOp {
+    #[prost(oneof = "batch_op::Op", tags = "1, 2")]
+    pub op: Option<batch_op::Op>,
+}
+
+pub mod batch_op {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Op {
+        #[prost(message, tag = "1")]
+        Put(super::Put),
+        #[prost(message, tag = "2")]
+        Delete(super::Delete),
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WriteBatch {
+    #[prost(message, repeated, tag = "1")]
+    pub ops: Vec<BatchOp>,
+    #[prost(bytes = "vec", optional, tag = "2")]
+    pub idempotency_token: Option<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RootHash {
+    #[prost(bytes = "vec", tag = "1")]
+    pub hash: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum KeyFraming {
+    Separator = 0,
+    Bare = 1,
+    LengthPrefixed = 2,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Proof {
+    #[prost(message, optional, tag = "1")]
+    pub root_hash: Option<RootHash>,
+    #[prost(bool, tag = "2")]
+    pub checksums: bool,
+    #[prost(uint64, optional, tag = "3")]
+    pub blob_threshold: Option<u64>,
+    #[prost(bytes = "vec", tag = "4")]
+    pub leaf_prefix: Vec<u8>,
+    #[prost(bytes = "vec", tag = "5")]
+    pub node_prefix: Vec<u8>,
+    #[prost(enumeration = "KeyFraming", tag = "6")]
+    pub key_framing: i32,
+    #[prost(bytes = "vec", tag = "7")]
+    pub path: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiffEntry {
+    #[prost(bytes = "vec", tag = "1")]
+    pub key: Vec<u8>,
+    #[prost(bytes = "vec", optional, tag = "2")]
+    pub old_value: Option<Vec<u8>>,
+    #[prost(bytes = "vec", optional, tag = "3")]
+    pub new_value: Option<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ChangeSet {
+    #[prost(message, repeated, tag = "1")]
+    pub entries: Vec<DiffEntry>,
+}
+
+impl From<crate::BatchOp<'_>> for BatchOp {
+    fn from(op: crate::BatchOp<'_>) -> Self {
+        let op = match op {
+            crate::BatchOp::Put { key, value } => batch_op::Op::Put(Put {
+                key: key.to_vec(),
+                value: value.to_vec(),
+            }),
+            crate::BatchOp::Delete { key } => batch_op::Op::Delete(Delete { key: key.to_vec() }),
+        };
+        BatchOp { op: Some(op) }
+    }
+}
+
+impl From<&crate::WriteBatch> for WriteBatch {
+    fn from(batch: &crate::WriteBatch) -> Self {
+        WriteBatch {
+            ops: batch.iter().map(BatchOp::from).collect(),
+            idempotency_token: batch.token().map(<[u8]>::to_vec),
+        }
+    }
+}
+
+/// 大小上限不属于序列化内容，转换得到的批次没有上限
+impl TryFrom<WriteBatch> for crate::WriteBatch {
+    type Error = Error;
+
+    fn try_from(message: WriteBatch) -> Result<Self, Error> {
+        let mut batch = crate::WriteBatch::new();
+        for op in message.ops {
+            match op.op {
+                Some(batch_op::Op::Put(put)) => batch.put(&put.key, &put.value),
+                Some(batch_op::Op::Delete(delete)) => batch.delete(&delete.key),
+                None => {
+                    return Err(Error::InvalidArgument(
+                        "batch op without an operation".to_string(),
+                    ))
+                }
+            };
+        }
+        if let Some(token) = message.idempotency_token {
+            batch.idempotency_token(&token);
+        }
+        Ok(batch)
+    }
+}
+
+impl From<Root> for RootHash {
+    fn from(hash: Root) -> Self {
+        RootHash {
+            hash: hash.to_vec(),
+        }
+    }
+}
+
+impl TryFrom<RootHash> for Root {
+    type Error = Error;
+
+    fn try_from(message: RootHash) -> Result<Self, Error> {
+        <[u8; 32]>::try_from(message.hash.as_slice())
+            .map(Root)
+            .map_err(|_| {
+                Error::InvalidArgument(format!(
+                    "root hash must be 32 bytes, got {}",
+                    message.hash.len()
+                ))
+            })
+    }
+}
+
+impl From<crate::KeyFraming> for KeyFraming {
+    fn from(framing: crate::KeyFraming) -> Self {
+        match framing {
+            crate::KeyFraming::Separator => KeyFraming::Separator,
+            crate::KeyFraming::Bare => KeyFraming::Bare,
+            crate::KeyFraming::LengthPrefixed => KeyFraming::LengthPrefixed,
+        }
+    }
+}
+
+impl From<KeyFraming> for crate::KeyFraming {
+    fn from(framing: KeyFraming) -> Self {
+        match framing {
+            KeyFraming::Separator => crate::KeyFraming::Separator,
+            KeyFraming::Bare => crate::KeyFraming::Bare,
+            KeyFraming::LengthPrefixed => crate::KeyFraming::LengthPrefixed,
+        }
+    }
+}
+
+impl From<&crate::Proof> for Proof {
+    fn from(proof: &crate::Proof) -> Self {
+        Proof {
+            root_hash: Some(proof.root_hash.into()),
+            checksums: proof.checksums,
+            blob_threshold: proof.blob_threshold,
+            leaf_prefix: proof.scheme.leaf_prefix.clone(),
+            node_prefix: proof.scheme.node_prefix.clone(),
+            key_framing: KeyFraming::from(proof.scheme.key_framing) as i32,
+            path: proof.path.clone(),
+        }
+    }
+}
+
+/// 路径不合法时返回 `Error::Corruption`，与 `Proof::from_bytes` 相同
+impl TryFrom<Proof> for crate::Proof {
+    type Error = Error;
+
+    fn try_from(message: Proof) -> Result<Self, Error> {
+        let root_hash = message
+            .root_hash
+            .ok_or_else(|| Error::InvalidArgument("proof without a root hash".to_string()))?;
+        let key_framing = KeyFraming::try_from(message.key_framing).map_err(|_| {
+            Error::InvalidArgument(format!("unknown key framing {}", message.key_framing))
+        })?;
+        let scheme = HashScheme {
+            leaf_prefix: message.leaf_prefix,
+            node_prefix: message.node_prefix,
+            key_framing: key_framing.into(),
+        };
+        crate::Proof::new(
+            message.checksums,
+            message.blob_threshold,
+            root_hash.try_into()?,
+            scheme,
+            message.path,
+        )
+    }
+}
+
+impl From<crate::DiffEntry> for DiffEntry {
+    fn from(entry: crate::DiffEntry) -> Self {
+        let (key, old_value, new_value) = match entry {
+            crate::DiffEntry::Added { key, value } => (key, None, Some(value)),
+            crate::DiffEntry::Modified {
+                key,
+                old_value,
+                new_value,
+            } => (key, Some(old_value), Some(new_value)),
+            crate::DiffEntry::Deleted { key, old_value } => (key, Some(old_value), None),
+        };
+        DiffEntry {
+            key,
+            old_value,
+            new_value,
+        }
+    }
+}
+
+impl TryFrom<DiffEntry> for crate::DiffEntry {
+    type Error = Error;
+
+    fn try_from(message: DiffEntry) -> Result<Self, Error> {
+        let key = message.key;
+        match (message.old_value, message.new_value) {
+            (None, Some(value)) => Ok(crate::DiffEntry::Added { key, value }),
+            (Some(old_value), Some(new_value)) => Ok(crate::DiffEntry::Modified {
+                key,
+                old_value,
+                new_value,
+            }),
+            (Some(old_value), None) => Ok(crate::DiffEntry::Deleted { key, old_value }),
+            (None, None) => Err(Error::InvalidArgument(
+                "diff entry without old or new value".to_string(),
+            )),
+        }
+    }
+}
+
+impl FromIterator<crate::DiffEntry> for ChangeSet {
+    fn from_iter<I: IntoIterator<Item = crate::DiffEntry>>(entries: I) -> Self {
+        ChangeSet {
+            entries: entries.into_iter().map(DiffEntry::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<ChangeSet> for Vec<crate::DiffEntry> {
+    type Error = Error;
+
+    fn try_from(message: ChangeSet) -> Result<Self, Error> {
+        message.entries.into_iter().map(crate::DiffEntry::try_from).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+
+    #[test]
+    fn test_write_batch_round_trip() {
+        let mut batch = crate::WriteBatch::new();
+        batch
+            .idempotency_token(b"t")
+            .put(b"k", b"v")
+            .delete(b"gone");
+
+        let bytes = WriteBatch::from(&batch).encode_to_vec();
+        let message = WriteBatch::decode(bytes.as_slice()).unwrap();
+        let decoded = crate::WriteBatch::try_from(message).unwrap();
+        assert!(decoded.iter().eq(batch.iter()));
+        assert_eq!(decoded.token(), Some(&b"t"[..]));
+
+        let short = RootHash { hash: vec![0; 4] };
+        assert!(Root::try_from(short).is_err());
+    }
+
+    #[test]
+    fn test_proof_and_change_set_round_trip() {
+        let _ = std::fs::remove_dir_all("./test_data/proto");
+        let db = crate::Database::new("./test_data/proto").unwrap();
+        db.put(b"a", b"1").unwrap();
+        db.put(b"b", b"2").unwrap();
+        db.put(b"a", b"3").unwrap();
+        let root = db.get_root_hash().unwrap();
+
+        let (_, proof) = db.get_with_proof(b"a", None).unwrap();
+        let bytes = Proof::from(&proof).encode_to_vec();
+        let message = Proof::decode(bytes.as_slice()).unwrap();
+        let decoded = crate::Proof::try_from(message.clone()).unwrap();
+        assert_eq!(decoded, proof);
+        assert!(decoded.verify(&root, b"a", b"3"));
+        let mut bad = message.clone();
+        bad.key_framing = 9;
+        assert!(matches!(crate::Proof::try_from(bad), Err(Error::InvalidArgument(_))));
+        let mut bad = message;
+        bad.path.truncate(bad.path.len() - 1);
+        assert!(matches!(crate::Proof::try_from(bad), Err(Error::Corruption(_))));
+
+        db.delete(b"b").unwrap();
+        db.put(b"c", b"4").unwrap();
+        let changes: Vec<crate::DiffEntry> = db
+            .diff(crate::Version(2), crate::Version(5))
+            .collect::<crate::Result<_>>()
+            .unwrap();
+        assert_eq!(changes.len(), 3);
+        let bytes = changes.iter().cloned().collect::<ChangeSet>().encode_to_vec();
+        let message = ChangeSet::decode(bytes.as_slice()).unwrap();
+        assert_eq!(Vec::<crate::DiffEntry>::try_from(message).unwrap(), changes);
+
+        let empty = ChangeSet {
+            entries: vec![DiffEntry {
+                key: b"k".to_vec(),
+                old_value: None,
+                new_value: None,
+            }],
+        };
+        assert!(Vec::<crate::DiffEntry>::try_from(empty).is_err());
+    }
+}
@@ -0,0 +1,286 @@
+//! 证明生成线程池
+//! `Database::prover` 启动固定数量的工作线程，从内部队列中按提交顺序取出证明请求，
+//! 使耗时的证明生成不占用对延迟敏感的读取线程。每个请求带有截止时间：
+//! 工作线程取到时已过期的请求不再计算，等待结果的一方到期即返回 `Error::TimedOut`。
+//!
+//! 证明与 `Database::get_with_proof` 相同，只对应计算时的最新状态，并共用同一个证明缓存（见 `proof_cache`）。
+
+use std::marker::PhantomData;
+use std::ptr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::blob::BlobStore;
+use crate::merkle::HashScheme;
+use crate::proof_cache::ProofCache;
+use crate::{
+    amdb_free_result, amdb_get_root_hash, amdb_get_with_proof64, envelope, result_bytes,
+    AmdbResult, Database, Error, HandleState, Proof, Result, Root, SendHandle,
+};
+
+/// 键的最新值（不存在或已删除时为 `None`）及其证明
+type Answer = Result<(Option<Vec<u8>>, Proof)>;
+
+struct Request {
+    key: Vec<u8>,
+    deadline: Instant,
+    reply: SyncSender<Answer>,
+}
+
+/// 运行中的证明线程池；析构时等待队列中的请求处理完（已过期的直接丢弃）后线程退出
+pub struct Prover<'a> {
+    /// 借用 `Database`，保证线程在句柄关闭前退出
+    _db: PhantomData<&'a Database>,
+    queue: Option<Sender<Request>>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+/// 已提交的证明请求，见 `Prover::submit`
+pub struct PendingProof {
+    deadline: Instant,
+    reply: Receiver<Answer>,
+}
+
+impl PendingProof {
+    /// 等待结果直到截止时间，到期返回 `Error::TimedOut`
+    pub fn wait(self) -> Result<(Option<Vec<u8>>, Proof)> {
+        let timeout = self.deadline.saturating_duration_since(Instant::now());
+        match self.reply.recv_timeout(timeout) {
+            Ok(answer) => answer,
+            Err(RecvTimeoutError::Timeout) => Err(Error::TimedOut),
+            // 工作线程未作答即退出
+            Err(RecvTimeoutError::Disconnected) => Err(Error::Closed),
+        }
+    }
+}
+
+impl Prover<'_> {
+    /// 提交一个证明请求，须在 `timeout` 内完成
+    pub fn submit(&self, key: &[u8], timeout: Duration) -> PendingProof {
+        self.enqueue(key, Instant::now() + timeout)
+    }
+
+    /// 提交一批证明请求，共用同一截止时间，按顺序返回
+    pub fn submit_all<K: AsRef<[u8]>>(&self, keys: &[K], timeout: Duration) -> Vec<PendingProof> {
+        let deadline = Instant::now() + timeout;
+        keys.iter()
+            .map(|key| self.enqueue(key.as_ref(), deadline))
+            .collect()
+    }
+
+    fn enqueue(&self, key: &[u8], deadline: Instant) -> PendingProof {
+        let (reply, answer) = mpsc::sync_channel(1);
+        let request = Request {
+            key: key.to_vec(),
+            deadline,
+            reply,
+        };
+        // 队列只在析构时关闭；工作线程全部退出时请求被丢弃，`wait` 返回 `Error::Closed`
+        if let Some(queue) = &self.queue {
+            let _ = queue.send(request);
+        }
+        PendingProof {
+            deadline,
+            reply: answer,
+        }
+    }
+}
+
+impl Drop for Prover<'_> {
+    fn drop(&mut self) {
+        // 关闭队列，工作线程取完剩余的请求后退出
+        self.queue.take();
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Database {
+    /// 启动 `pool_size` 个证明生成线程；`pool_size` 为0时返回 `Error::InvalidArgument`
+    pub fn prover(&self, pool_size: usize) -> Result<Prover<'_>> {
+        if pool_size == 0 {
+            return Err(Error::InvalidArgument(
+                "prover pool size must be positive".to_string(),
+            ));
+        }
+        let (queue, requests) = mpsc::channel();
+        let requests = Arc::new(Mutex::new(requests));
+        let mut prover = Prover {
+            _db: PhantomData,
+            queue: Some(queue),
+            threads: Vec::with_capacity(pool_size),
+        };
+        for i in 0..pool_size {
+            let worker = Worker {
+                handle: SendHandle(*self.live_handle()?),
+                state: self.state.clone(),
+                checksums: self.options.value_checksums,
+                blob_threshold: self.options.blob_threshold,
+                blobs: self.blobs.clone(),
+                cache: self.proof_cache.clone(),
+                scheme: self.hash_scheme()?,
+                requests: Arc::clone(&requests),
+            };
+            let thread = thread::Builder::new()
+                .name(format!("amdb-prover-{}", i))
+                .spawn(move || worker.run())?;
+            prover.threads.push(thread);
+        }
+        Ok(prover)
+    }
+}
+
+struct Worker {
+    handle: SendHandle,
+    state: HandleState,
+    checksums: bool,
+    blob_threshold: Option<u64>,
+    blobs: Option<BlobStore>,
+    cache: Option<Arc<ProofCache>>,
+    scheme: HashScheme,
+    requests: Arc<Mutex<Receiver<Request>>>,
+}
+
+impl Worker {
+    fn run(self) {
+        loop {
+            let next = self
+                .requests
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .recv();
+            let Ok(request) = next else {
+                return;
+            };
+            let answer = if Instant::now() >= request.deadline {
+                Err(Error::TimedOut)
+            } else {
+                self.prove(&request.key)
+            };
+            let _ = request.reply.send(answer);
+        }
+    }
+
+    fn prove(&self, key: &[u8]) -> Answer {
+        let _alive = self.state.enter()?;
+        let (data, proof) = match &self.cache {
+            Some(cache) => {
+                let mut root_hash = Root::default();
+                let status = unsafe { amdb_get_root_hash(self.handle.0, root_hash.as_mut_ptr()) };
+                if status != 0 {
+                    return Err(self.state.error(status));
+                }
+                match cache.get(&root_hash, key) {
+                    Some(cached) => cached,
+                    None => {
+                        let (data, proof) = self.raw_proof(key)?;
+                        cache.insert(key, data.clone(), proof.clone());
+                        (data, proof)
+                    }
+                }
+            }
+            None => self.raw_proof(key)?,
+        };
+        let value = data
+            .map(|data| envelope::open_with(data, self.checksums, self.blobs.as_ref()))
+            .transpose()?;
+        Ok((value, proof))
+    }
+
+    /// 引擎中的原始值及其证明；调用方已进入 `state`
+    fn raw_proof(&self, key: &[u8]) -> Result<(Option<Vec<u8>>, Proof)> {
+        let empty = || AmdbResult {
+            status: 0,
+            error_msg: ptr::null(),
+            data: ptr::null_mut(),
+            data_len: 0,
+        };
+        let (mut value, mut path) = (empty(), empty());
+        let mut version = 0u64;
+        let mut root_hash = Root::default();
+        let status = unsafe {
+            amdb_get_with_proof64(
+                self.handle.0,
+                key.as_ptr(),
+                key.len(),
+                &mut value,
+                &mut version,
+                &mut path,
+                root_hash.as_mut_ptr(),
+            )
+        };
+        if status != 0 {
+            return Err(self.state.error(status));
+        }
+        let data = (!value.data.is_null()).then(|| result_bytes(&value));
+        let path_bytes = result_bytes(&path);
+        unsafe {
+            amdb_free_result(&mut value);
+            amdb_free_result(&mut path);
+        }
+        let proof = Proof::new(
+            self.checksums,
+            self.blob_threshold,
+            root_hash,
+            self.scheme.clone(),
+            path_bytes,
+        )?;
+        Ok((data, proof))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenOptions;
+
+    #[test]
+    fn test_prover() {
+        let _ = std::fs::remove_dir_all("./test_data/prover");
+        let db = Database::new("./test_data/prover").unwrap();
+        for i in 0..20u8 {
+            db.put(&[b'k', i], &[i]).unwrap();
+        }
+        let root = db.get_root_hash().unwrap();
+        let prover = db.prover(3).unwrap();
+
+        let keys: Vec<Vec<u8>> = (0..20u8).map(|i| vec![b'k', i]).collect();
+        let pending = prover.submit_all(&keys, Duration::from_secs(10));
+        for (i, request) in pending.into_iter().enumerate() {
+            let (value, proof) = request.wait().unwrap();
+            assert_eq!(value, Some(vec![i as u8]));
+            assert!(proof.verify(&root, &keys[i], &[i as u8]));
+        }
+        let (value, proof) = prover
+            .submit(b"missing", Duration::from_secs(10))
+            .wait()
+            .unwrap();
+        assert!(value.is_none());
+        assert_eq!(proof.root_hash(), root);
+
+        // 截止时间已过的请求不再计算
+        assert!(matches!(
+            prover.submit(b"k", Duration::ZERO).wait(),
+            Err(Error::TimedOut)
+        ));
+        drop(prover);
+        assert!(matches!(db.prover(0), Err(Error::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn test_prover_with_checksums() {
+        let _ = std::fs::remove_dir_all("./test_data/prover_checksums");
+        let db = OpenOptions::new()
+            .value_checksums(true)
+            .open("./test_data/prover_checksums")
+            .unwrap();
+        let root = db.put(b"k", b"v").unwrap();
+        let prover = db.prover(1).unwrap();
+        let (value, proof) = prover.submit(b"k", Duration::from_secs(10)).wait().unwrap();
+        assert_eq!(value, Some(b"v".to_vec()));
+        assert!(proof.verify(&root, b"k", b"v"));
+    }
+}
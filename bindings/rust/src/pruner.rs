@@ -0,0 +1,316 @@
+//! 后台清理
+//! 在后台线程中按保留策略分批清理全部键的旧版本，并通过通道报告每一轮的结果
+//!
+//! 清理运行期间，写入提交时不再清理所写的键，全部交给后台按批次和限速进行，避免写入路径上的延迟尖峰。
+
+use std::ptr;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::{
+    amdb_free_result, amdb_prune_batch, amdb_set_background_thread, result_bytes, AmdbResult,
+    Database, Error, HandleState, Result, SendHandle,
+};
+
+/// 后台清理的选项
+#[derive(Debug, Clone)]
+pub struct PruneOptions {
+    interval: Duration,
+    batch_size: usize,
+    max_keys_per_sec: Option<u64>,
+}
+
+impl Default for PruneOptions {
+    fn default() -> Self {
+        PruneOptions {
+            interval: Duration::from_secs(60),
+            batch_size: 1000,
+            max_keys_per_sec: None,
+        }
+    }
+}
+
+impl PruneOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 两轮清理之间的间隔（默认60秒）
+    pub fn interval(&mut self, interval: Duration) -> &mut Self {
+        self.interval = interval;
+        self
+    }
+
+    /// 每次引擎调用处理的键数（默认1000）
+    pub fn batch_size(&mut self, keys: usize) -> &mut Self {
+        self.batch_size = keys;
+        self
+    }
+
+    /// 每秒最多处理的键数（默认不限制），用于限制清理占用的引擎与磁盘带宽
+    pub fn max_keys_per_sec(&mut self, keys: u64) -> &mut Self {
+        self.max_keys_per_sec = Some(keys);
+        self
+    }
+}
+
+/// 一轮清理的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PruneReport {
+    pub keys_scanned: u64,
+    pub versions_removed: u64,
+    pub duration: Duration,
+}
+
+/// 运行中的后台清理；`stop` 或析构时停止并等待线程退出
+pub struct Pruner<'a> {
+    db: &'a Database,
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+    reports: Receiver<Result<PruneReport>>,
+}
+
+impl Pruner<'_> {
+    /// 每轮结束后收到一份报告；某一轮失败时收到其错误，下一轮照常进行，
+    /// 但数据库已关闭或中毒时收到该错误后清理停止
+    pub fn reports(&self) -> &Receiver<Result<PruneReport>> {
+        &self.reports
+    }
+
+    /// 停止清理，等待当前批次完成
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        // 关闭通道即唤醒线程
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        self.db.background_pruning.store(false, Ordering::SeqCst);
+    }
+}
+
+impl Drop for Pruner<'_> {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl Database {
+    /// 启动后台清理，按 `OpenOptions::retention` 的策略遍历全部键
+    ///
+    /// 保留策略为 `Retention::KeepAll`、`batch_size` 为0或已有清理在运行时返回
+    /// `Error::InvalidArgument`。
+    pub fn start_pruner(&self, options: &PruneOptions) -> Result<Pruner<'_>> {
+        let Some(limits) = self.options.retention.limits() else {
+            return Err(Error::InvalidArgument(
+                "retention policy keeps all versions; nothing to prune".to_string(),
+            ));
+        };
+        if options.batch_size == 0 {
+            return Err(Error::InvalidArgument(
+                "prune batch size must be positive".to_string(),
+            ));
+        }
+        if self.background_pruning.swap(true, Ordering::SeqCst) {
+            return Err(Error::InvalidArgument(
+                "a pruner is already running".to_string(),
+            ));
+        }
+
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let (report_tx, reports) = mpsc::channel();
+        let task = Task {
+            handle: SendHandle(*self.live_handle()?),
+            state: self.state.clone(),
+            pins: Arc::clone(&self.pins),
+            limits,
+            options: options.clone(),
+            stop: stop_rx,
+        };
+        let spawned = thread::Builder::new()
+            .name("amdb-pruner".to_string())
+            .spawn(move || task.run(report_tx));
+        let thread = match spawned {
+            Ok(thread) => thread,
+            Err(e) => {
+                self.background_pruning.store(false, Ordering::SeqCst);
+                return Err(e.into());
+            }
+        };
+        Ok(Pruner {
+            db: self,
+            stop: Some(stop_tx),
+            thread: Some(thread),
+            reports,
+        })
+    }
+}
+
+struct Task {
+    /// `Pruner` 借用 `Database`，保证线程在句柄关闭前退出
+    handle: SendHandle,
+    state: HandleState,
+    pins: Arc<Mutex<Vec<f64>>>,
+    limits: (usize, u64),
+    options: PruneOptions,
+    stop: Receiver<()>,
+}
+
+impl Task {
+    fn run(self, reports: Sender<Result<PruneReport>>) {
+        // 清理产生的I/O计入后台
+        unsafe { amdb_set_background_thread(true) };
+        loop {
+            match self.pass() {
+                Ok(Some(report)) => {
+                    let _ = reports.send(Ok(report));
+                }
+                Ok(None) => return,
+                // 数据库已关闭或中毒，不再继续
+                Err(e) if matches!(e.inner(), Error::Closed | Error::Poisoned) => {
+                    let _ = reports.send(Err(e));
+                    return;
+                }
+                Err(e) => {
+                    let _ = reports.send(Err(e));
+                }
+            }
+            if !self.sleep(self.options.interval) {
+                return;
+            }
+        }
+    }
+
+    /// 遍历一轮全部键；中途被停止时返回 `None`
+    fn pass(&self) -> Result<Option<PruneReport>> {
+        let started = Instant::now();
+        let mut report = PruneReport {
+            keys_scanned: 0,
+            versions_removed: 0,
+            duration: Duration::ZERO,
+        };
+        let mut start = Vec::new();
+        loop {
+            let batch_started = Instant::now();
+            let (scanned, removed, next) = self.batch(&start)?;
+            report.keys_scanned += scanned as u64;
+            report.versions_removed += removed;
+            let Some(next) = next else {
+                break;
+            };
+            start = next;
+
+            if let Some(rate) = self.options.max_keys_per_sec {
+                let budget = Duration::from_secs_f64(scanned as f64 / rate.max(1) as f64);
+                if !self.sleep(budget.saturating_sub(batch_started.elapsed())) {
+                    return Ok(None);
+                }
+            } else if !self.sleep(Duration::ZERO) {
+                return Ok(None);
+            }
+        }
+        report.duration = started.elapsed();
+        Ok(Some(report))
+    }
+
+    /// 清理从 `start` 开始的一批键，返回（处理的键数, 删除的版本数, 下一批的起始键）
+    fn batch(&self, start: &[u8]) -> Result<(usize, u64, Option<Vec<u8>>)> {
+        let (recent, interval) = self.limits;
+        let mut next_key = AmdbResult {
+            status: 0,
+            error_msg: ptr::null(),
+            data: ptr::null_mut(),
+            data_len: 0,
+        };
+        let mut scanned = 0;
+        let mut removed = 0;
+
+        // 调用期间持有守卫，`Database::close` 会等待本批完成
+        let _alive = self.state.enter()?;
+        // 与写入路径相同，持有登记表的锁，避免清理掉刚固定的版本
+        let pins = self.pins.lock().unwrap_or_else(PoisonError::into_inner);
+        let status = unsafe {
+            amdb_prune_batch(
+                self.handle.0,
+                start.as_ptr(),
+                start.len(),
+                self.options.batch_size,
+                recent,
+                interval,
+                pins.as_ptr(),
+                pins.len(),
+                &mut next_key,
+                &mut scanned,
+                &mut removed,
+            )
+        };
+        drop(pins);
+        if status != 0 {
+            return Err(self.state.error(status));
+        }
+
+        let next = (!next_key.data.is_null()).then(|| result_bytes(&next_key));
+        unsafe { amdb_free_result(&mut next_key) };
+        Ok((scanned, removed, next))
+    }
+
+    /// 等待 `timeout`；期间被要求停止时返回 `false`
+    fn sleep(&self, timeout: Duration) -> bool {
+        matches!(
+            self.stop.recv_timeout(timeout),
+            Err(RecvTimeoutError::Timeout)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{KeyVersion, OpenOptions, Retention};
+
+    use super::*;
+
+    #[test]
+    fn test_background_pruning() {
+        let _ = std::fs::remove_dir_all("./test_data/pruner");
+        let _ = std::fs::remove_dir_all("./test_data/pruner_keep_all");
+        let db = OpenOptions::new()
+            .retention(Retention::KeepLast(1))
+            .open("./test_data/pruner")
+            .unwrap();
+        assert!(matches!(
+            Database::new("./test_data/pruner_keep_all")
+                .unwrap()
+                .start_pruner(&PruneOptions::new()),
+            Err(Error::InvalidArgument(_))
+        ));
+
+        let pruner = db
+            .start_pruner(
+                PruneOptions::new()
+                    .interval(Duration::from_millis(10))
+                    .batch_size(1),
+            )
+            .unwrap();
+        assert!(db.start_pruner(&PruneOptions::new()).is_err());
+        for i in 1..=3u8 {
+            db.put(b"a", &[i]).unwrap();
+            db.put(b"b", &[i]).unwrap();
+        }
+
+        // 提交时不清理，等待后台清理掉旧版本
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while db.get(b"a", Some(KeyVersion(1))).unwrap().is_some() || db.get(b"b", Some(KeyVersion(2))).unwrap().is_some() {
+            assert!(Instant::now() < deadline, "old versions were not pruned");
+            pruner.reports().recv_timeout(Duration::from_secs(1)).ok();
+        }
+        assert_eq!(db.get(b"b", Some(KeyVersion(3))).unwrap(), Some(vec![3]));
+        pruner.stop();
+        assert!(!db.background_pruning.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,116 @@
+//! 读取选项
+//! `ReadOptions::verify_against_root` 让读取在返回之前为读到的值生成证明，并对当前的根哈希验证：
+//! 值取自存储引擎的常规读取路径，证明取自Merkle树，两者不符（例如存储文件损坏或被改动）时返回
+//! `Error::Corruption`，而不是把未经证明的值交给调用方。每次读取多一次证明生成和验证，
+//! 适合愿意承担这一开销的高可信部署；经 `OpenOptions::read_options` 设置后对每次 `Database::get` 生效。
+
+use crate::{Database, Error, KeyVersion, Result};
+
+/// 并发写入改变了根哈希时重新读取的次数
+const VERIFY_ATTEMPTS: usize = 3;
+
+/// 单次读取的选项，见 `Database::get_with`
+#[derive(Debug, Clone, Default)]
+pub struct ReadOptions {
+    pub(crate) verify_against_root: bool,
+}
+
+impl ReadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 返回之前用证明对当前根哈希验证读到的值（默认关闭）。证明只对应最新版本，
+    /// 同时给出其他版本时返回 `Error::InvalidArgument`；键不存在时确认Merkle树中也没有该键
+    pub fn verify_against_root(&mut self, verify: bool) -> &mut Self {
+        self.verify_against_root = verify;
+        self
+    }
+}
+
+impl Database {
+    /// 同 `get`，按 `options` 读取
+    pub fn get_with(
+        &self,
+        key: &[u8],
+        version: Option<KeyVersion>,
+        options: &ReadOptions,
+    ) -> Result<Option<Vec<u8>>> {
+        if !options.verify_against_root {
+            return self
+                .get_stored(key, version)?
+                .map(|data| self.open_value(data))
+                .transpose();
+        }
+        for _ in 0..VERIFY_ATTEMPTS {
+            let root_hash = self.get_root_hash()?;
+            let stored = self.get_stored(key, version)?;
+            let (proven, current, proof) = self.proof_of(key, true)?;
+            if let Some(version) = version {
+                if version != current {
+                    return Err(Error::InvalidArgument(format!(
+                        "proofs cover only the latest version {} of the key, not {}",
+                        current, version
+                    )));
+                }
+            }
+            if proof.root_hash() != root_hash {
+                // 读取期间有新的提交，重新读取
+                continue;
+            }
+            let Some(data) = stored else {
+                if proven.is_some() {
+                    return Err(Error::Corruption(format!(
+                        "key {:02x?} is missing from storage but present under root {:02x?}",
+                        key, root_hash
+                    )));
+                }
+                return Ok(None);
+            };
+            let value = self.open_value(data)?;
+            if !proof.verify(&root_hash, key, &value) {
+                return Err(Error::Corruption(format!(
+                    "value of key {:02x?} does not verify against root {:02x?}",
+                    key, root_hash
+                )));
+            }
+            return Ok(Some(value));
+        }
+        Err(Error::Busy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenOptions;
+
+    #[test]
+    fn test_verify_against_root() {
+        let _ = std::fs::remove_dir_all("./test_data/read_verified");
+        let db = OpenOptions::new()
+            .value_checksums(true)
+            .read_options(ReadOptions::new().verify_against_root(true))
+            .open("./test_data/read_verified")
+            .unwrap();
+        db.put(b"a", b"1").unwrap();
+        db.put(b"b", b"x").unwrap();
+        db.put(b"a", b"2").unwrap();
+        assert_eq!(db.get(b"a", None).unwrap(), Some(b"2".to_vec()));
+        assert_eq!(db.get(b"b", None).unwrap(), Some(b"x".to_vec()));
+        assert_eq!(db.get(b"missing", None).unwrap(), None);
+        db.delete(b"b").unwrap();
+        assert_eq!(db.get(b"b", None).unwrap(), None);
+        assert!(matches!(
+            db.get(b"a", Some(KeyVersion(1))),
+            Err(Error::InvalidArgument(_))
+        ));
+
+        // 单次读取可以不验证
+        let plain = ReadOptions::new();
+        assert_eq!(
+            db.get_with(b"a", Some(KeyVersion(1)), &plain).unwrap(),
+            Some(b"1".to_vec())
+        );
+    }
+}
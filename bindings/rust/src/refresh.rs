@@ -0,0 +1,55 @@
+//! 多进程读者
+//! 写入进程持有数据目录的锁时，其他进程仍可以 `OpenOptions::read_only` 打开同一目录读取，
+//! 例如旁路的分析任务，不必经由网络访问写入方。读者看到写入方已持久化的状态，落后多少取决于写入方的
+//! `SyncMode`：`EveryCommit` 为每次提交，`Batched(window)` 至多 `window`，`Normal` 为写入方的下一次刷新。
+//! `Database::get` 在读取前自动检查新的状态；根哈希、证明、遍历等其余读取使用最近一次
+//! `Database::refresh` 加载的状态，需要一致的视图时先调用一次 `refresh`。
+
+use crate::{amdb_refresh, Database, Result};
+
+impl Database {
+    /// 只读打开时重新加载写入方已持久化的状态，返回是否转到了新的状态。
+    /// 写入方正在刷新（文件来自不同的提交）时保持原来的状态并返回 `false`；读写打开时总是返回 `false`
+    pub fn refresh(&self) -> Result<bool> {
+        let mut changed = 0u8;
+        let handle = self.live_handle()?;
+        let status = unsafe { amdb_refresh(*handle, &mut changed) };
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        Ok(changed != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{OpenOptions, SyncMode};
+
+    #[test]
+    fn test_refresh() {
+        let dir = "./test_data/refresh";
+        let _ = std::fs::remove_dir_all(dir);
+        let writer = OpenOptions::new()
+            .sync_mode(SyncMode::EveryCommit)
+            .open(dir)
+            .unwrap();
+        writer.put(b"a", b"1").unwrap();
+        let reader = OpenOptions::new().read_only(true).open(dir).unwrap();
+        assert!(!reader.refresh().unwrap());
+        assert_eq!(reader.state_version().unwrap(), 1);
+
+        let root = writer.put(b"b", b"2").unwrap();
+        // 根哈希在重新加载之前仍是旧的状态
+        assert_ne!(reader.get_root_hash().unwrap(), root);
+        assert!(reader.refresh().unwrap());
+        assert_eq!(reader.get_root_hash().unwrap(), root);
+        assert_eq!(reader.state_version().unwrap(), 2);
+        assert!(!reader.refresh().unwrap());
+
+        // get 自动检查
+        writer.put(b"c", b"3").unwrap();
+        assert_eq!(reader.get(b"c", None).unwrap(), Some(b"3".to_vec()));
+        assert_eq!(reader.state_version().unwrap(), 3);
+        assert!(!writer.refresh().unwrap());
+    }
+}
@@ -0,0 +1,99 @@
+//! 保留键空间
+//! 绑定层把内部记录写在以 `\0` 开头的保留前缀下：幂等令牌 `\0idem/`、复制序列号 `\0repl/seq`、
+//! 命名树的登记 `\0tree/` 和数据 `\0tdata/`、二级索引条目 `\0idx/`、命名空间的根哈希记录 `\0ns/`。
+//! 调用方传入的键落在这些前缀下时，写入返回 `Error::InvalidKey`（见 `OpenOptions::check_key`）；
+//! 范围扫描、游标和版本差异跳过它们，整个数据库的扫描只包含调用方写入的键。
+//!
+//! 从某个保留前缀之内开始的扫描仍可看到该前缀下的键，内部功能由此读取自己的记录；其他保留前缀
+//! 照样被跳过。备份、快照文件和导出按原样包含全部键，恢复后根哈希不变。
+
+use crate::batch::{REPL_SEQ_KEY, TOKEN_PREFIX};
+use crate::index::INDEX_PREFIX;
+use crate::namespace::RECORD_PREFIX;
+use crate::tree::{DATA_PREFIX, REGISTRY_PREFIX};
+use crate::{Error, Result};
+
+const PREFIXES: &[&[u8]] = &[
+    TOKEN_PREFIX,
+    REPL_SEQ_KEY,
+    REGISTRY_PREFIX,
+    DATA_PREFIX,
+    INDEX_PREFIX,
+    RECORD_PREFIX,
+];
+
+/// `key` 所在的保留前缀
+fn reserved_prefix(key: &[u8]) -> Option<&'static [u8]> {
+    PREFIXES.iter().copied().find(|prefix| key.starts_with(prefix))
+}
+
+/// 是否为内部记录的键
+pub(crate) fn is_reserved_key(key: &[u8]) -> bool {
+    reserved_prefix(key).is_some()
+}
+
+/// 调用方传入的键落在保留前缀下时返回 `Error::InvalidKey`
+pub(crate) fn check_unreserved(key: &[u8]) -> Result<()> {
+    if is_reserved_key(key) {
+        return Err(Error::InvalidKey(
+            "key falls under a reserved internal prefix".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// 扫描结果中应跳过的保留键
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ReservedFilter {
+    /// 仍然可见的保留前缀；空前缀表示全部可见
+    visible: Option<&'static [u8]>,
+}
+
+impl ReservedFilter {
+    /// 从 `start` 开始的扫描：只有 `start` 所在的保留前缀可见
+    pub(crate) fn from_start(start: &[u8]) -> Self {
+        ReservedFilter {
+            visible: reserved_prefix(start),
+        }
+    }
+
+    /// 不跳过任何键，用于需要完整状态的导出
+    pub(crate) fn none() -> Self {
+        ReservedFilter { visible: Some(b"") }
+    }
+
+    pub(crate) fn hides(&self, key: &[u8]) -> bool {
+        match reserved_prefix(key) {
+            None => false,
+            Some(prefix) => !self.visible.is_some_and(|visible| prefix.starts_with(visible)),
+        }
+    }
+
+    pub(crate) fn retain<V>(&self, entries: &mut Vec<(Vec<u8>, V)>) {
+        entries.retain(|(key, _)| !self.hides(key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserved_filter() {
+        assert!(is_reserved_key(b"\0idem/t"));
+        assert!(is_reserved_key(b"\0repl/seq"));
+        assert!(!is_reserved_key(b"\0"));
+        assert!(!is_reserved_key(b"\0\0\0\x01"));
+        assert!(!is_reserved_key(b"user"));
+
+        let global = ReservedFilter::from_start(b"");
+        assert!(global.hides(b"\0idem/t") && global.hides(b"\0repl/seq"));
+        assert!(!global.hides(b"\0\0\0\x01"));
+
+        let tokens = ReservedFilter::from_start(b"\0idem/");
+        assert!(!tokens.hides(b"\0idem/t"));
+        assert!(tokens.hides(b"\0repl/seq"));
+
+        assert!(!ReservedFilter::none().hides(b"\0repl/seq"));
+    }
+}
@@ -0,0 +1,201 @@
+//! 可续传游标
+//! 读取固定状态的游标（`CursorOptions::pinned` 或 `Snapshot::iter`）可以随时 `Iter::save` 为 `CursorToken`，
+//! 令牌记录所读的数据库版本及其根哈希、尚未返回的键区间和迭代方向。令牌可以写入文件，进程重启后
+//! `Database::resume_cursor` 在同一版本上打开快照，从中断处继续读取，长时间运行的导出、建索引等任务
+//! 不必从头再来。
+//!
+//! ```text
+//! 格式版本 1 (1) | 标志 (1) | 数据库版本 (8) | 根哈希 (32) | 批大小 (4) | 起始键长度 (4) | 起始键 | 结束键长度 (4) | 结束键
+//! ```
+//!
+//! 整数均为LE；标志的最低位表示降序，次低位表示已没有剩余的键（此时没有两个键）。结束键为空表示不设上界。
+//! 保留策略只在游标或快照存活期间保留其版本，进程重启后续传之前的清理可能已删除该版本，
+//! 此时 `resume_cursor` 返回 `Error::NotFound`，应重新开始任务。
+
+use crate::{CursorOptions, Database, Error, Iter, Result, Root, Version};
+
+const FORMAT_VERSION: u8 = 1;
+const FLAG_REVERSE: u8 = 1;
+const FLAG_DONE: u8 = 2;
+
+/// 游标的续传位置，见 `Iter::save`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorToken {
+    version: Version,
+    root_hash: Root,
+    remaining: Option<(Vec<u8>, Vec<u8>)>,
+    reverse: bool,
+    batch_size: usize,
+}
+
+impl CursorToken {
+    /// 游标所读的数据库版本
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// 该版本的根哈希
+    pub fn root_hash(&self) -> Root {
+        self.root_hash
+    }
+
+    /// 保存时是否已读完
+    pub fn is_done(&self) -> bool {
+        self.remaining.is_none()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(58);
+        bytes.push(FORMAT_VERSION);
+        let mut flags = if self.reverse { FLAG_REVERSE } else { 0 };
+        if self.remaining.is_none() {
+            flags |= FLAG_DONE;
+        }
+        bytes.push(flags);
+        bytes.extend_from_slice(&self.version.0.to_le_bytes());
+        bytes.extend_from_slice(&*self.root_hash);
+        bytes.extend_from_slice(&(self.batch_size.min(u32::MAX as usize) as u32).to_le_bytes());
+        if let Some((start, end)) = &self.remaining {
+            for key in [start, end] {
+                bytes.extend_from_slice(&(key.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(key);
+            }
+        }
+        bytes
+    }
+
+    /// 解析 `to_bytes` 的输出；格式不合法时返回 `Error::Corruption`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let invalid = || Error::Corruption("invalid cursor token".to_string());
+        let flags = FLAG_REVERSE | FLAG_DONE;
+        if bytes.len() < 46 || bytes[0] != FORMAT_VERSION || bytes[1] & !flags != 0 {
+            return Err(invalid());
+        }
+        let batch_size = u32::from_le_bytes(bytes[42..46].try_into().unwrap()) as usize;
+        let mut rest = &bytes[46..];
+        let remaining = if bytes[1] & FLAG_DONE != 0 {
+            None
+        } else {
+            let mut key = || -> Option<Vec<u8>> {
+                let (len, tail) = rest.split_at_checked(4)?;
+                let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+                let (key, tail) = tail.split_at_checked(len)?;
+                rest = tail;
+                Some(key.to_vec())
+            };
+            Some((key().ok_or_else(invalid)?, key().ok_or_else(invalid)?))
+        };
+        if !rest.is_empty() || batch_size == 0 {
+            return Err(invalid());
+        }
+        Ok(CursorToken {
+            version: Version(u64::from_le_bytes(bytes[2..10].try_into().unwrap())),
+            root_hash: Root(bytes[10..42].try_into().unwrap()),
+            remaining,
+            reverse: bytes[1] & FLAG_REVERSE != 0,
+            batch_size,
+        })
+    }
+}
+
+impl Iter<'_> {
+    /// 保存续传位置：之后 `Database::resume_cursor` 返回的迭代器从下一个尚未返回的键继续。
+    /// 已从两端读取时，令牌只覆盖两端之间尚未返回的键；未固定状态的游标返回 `Error::InvalidArgument`
+    pub fn save(&mut self) -> Result<CursorToken> {
+        let (version, root_hash) = self.fixed_state()?;
+        Ok(CursorToken {
+            version,
+            root_hash,
+            remaining: self.remaining().cloned(),
+            reverse: self.options().reverse,
+            batch_size: self.options().batch_size,
+        })
+    }
+}
+
+impl Database {
+    /// 在令牌记录的版本上继续迭代；该版本已被清理时返回 `Error::NotFound`，
+    /// 其根哈希与令牌不符（例如令牌来自另一个数据库）时返回 `Error::RootMismatch`
+    pub fn resume_cursor(&self, token: &CursorToken) -> Result<Iter<'_>> {
+        let snapshot = self.snapshot_at(token.version)?;
+        let actual = snapshot.root_hash();
+        if actual != token.root_hash {
+            return Err(Error::RootMismatch { actual });
+        }
+        let mut options = CursorOptions::new();
+        options.reverse(token.reverse).batch_size(token.batch_size);
+        Ok(snapshot.into_iter_bounds(token.remaining.clone(), &options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Entry, WriteBatch};
+
+    #[test]
+    fn test_resume_cursor() {
+        let dir = "./test_data/resume_cursor";
+        let _ = std::fs::remove_dir_all(dir);
+        let db = Database::new(dir).unwrap();
+        let mut batch = WriteBatch::new();
+        for i in 0..10u8 {
+            batch.put(&[b'k', i], &[i]);
+        }
+        db.write_batch(&batch).unwrap();
+        let expected: Vec<Entry> = db.iter(..).map(Result::unwrap).collect();
+
+        let mut options = CursorOptions::new();
+        options.pinned(true).batch_size(3);
+        let mut iter = db.iter_with(.., &options);
+        let mut exported: Vec<Entry> = iter.by_ref().take(4).map(Result::unwrap).collect();
+        let token = CursorToken::from_bytes(&iter.save().unwrap().to_bytes()).unwrap();
+        assert_eq!(token.version(), 1);
+        assert!(!token.is_done());
+        drop(iter);
+
+        // 重启前后的写入不影响续传读到的状态
+        db.put(b"k\x05", b"changed").unwrap();
+        db.delete(b"k\x06").unwrap();
+        drop(db);
+        let db = Database::new(dir).unwrap();
+        db.put(b"k\x07", b"changed").unwrap();
+        let mut resumed = db.resume_cursor(&token).unwrap();
+        exported.extend(resumed.by_ref().take(3).map(Result::unwrap));
+        // 续传的游标可以再次保存
+        let token = resumed.save().unwrap();
+        drop(resumed);
+        exported.extend(db.resume_cursor(&token).unwrap().map(Result::unwrap));
+        assert_eq!(exported, expected);
+
+        // 两端读取后只剩中间的键；读完后令牌为空
+        let snapshot = db.snapshot_at(Version(1)).unwrap();
+        let mut options = CursorOptions::new();
+        options.reverse(true);
+        let mut iter = snapshot.iter_with(.., &options);
+        assert_eq!(iter.next().unwrap().unwrap().0, b"k\x09");
+        assert_eq!(iter.next_back().unwrap().unwrap().0, b"k\x00");
+        let token = iter.save().unwrap();
+        let middle: Vec<Entry> = db
+            .resume_cursor(&token)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert!(middle.iter().rev().eq(&expected[1..9]));
+        iter.by_ref().for_each(drop);
+        let done = iter.save().unwrap();
+        assert!(done.is_done());
+        assert_eq!(CursorToken::from_bytes(&done.to_bytes()).unwrap(), done);
+        assert!(db.resume_cursor(&done).unwrap().next().is_none());
+
+        assert!(matches!(db.iter(..).save(), Err(Error::InvalidArgument(_))));
+        let mut bytes = token.to_bytes();
+        bytes[10] ^= 1;
+        let forged = CursorToken::from_bytes(&bytes).unwrap();
+        assert!(matches!(
+            db.resume_cursor(&forged),
+            Err(Error::RootMismatch { .. })
+        ));
+        assert!(CursorToken::from_bytes(&bytes[..45]).is_err());
+    }
+}
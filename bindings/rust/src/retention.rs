@@ -0,0 +1,323 @@
+//! 版本保留策略
+//! 每次写入提交后按 `OpenOptions::retention` 删除所写键的旧版本，不改变当前状态和根哈希；
+//! `Database::prune_versions_before` 按数据库版本一次清理全部键。
+//! `Database::purge_key_history` 用于合规删除，清除单个键的全部历史值。
+
+use std::ops::RangeInclusive;
+use std::sync::atomic::Ordering;
+use std::sync::PoisonError;
+
+use crate::{
+    amdb_prune_versions, amdb_prune_versions_before, amdb_purge_key_history, AmdbPruneStats,
+    AmdbPurgeStats, Database, Error, Result, Root, Version,
+};
+
+/// 每个键保留哪些历史版本；任何策略都至少保留最新版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Retention {
+    /// 保留全部版本（默认）
+    #[default]
+    KeepAll,
+    /// 只保留最近 n 个版本
+    KeepLast(usize),
+    /// 保留最近 `recent` 个版本，更早的只保留版本号为 `interval` 整数倍的版本
+    KeepEvery { recent: usize, interval: u64 },
+}
+
+impl Retention {
+    /// （保留的最近版本数, 版本号间隔）；`KeepAll` 返回 `None`
+    pub(crate) fn limits(&self) -> Option<(usize, u64)> {
+        match *self {
+            Retention::KeepAll => None,
+            Retention::KeepLast(n) => Some((n, 0)),
+            Retention::KeepEvery { recent, interval } => Some((recent, interval)),
+        }
+    }
+}
+
+/// 一次按数据库版本清理的结果，见 `Database::prune_versions_before`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PruneStats {
+    /// 删除的键版本数
+    pub versions_removed: u64,
+    /// 删除了版本的键数
+    pub keys_pruned: u64,
+    /// 删除的版本的值字节数，下次持久化（或 `Database::compact`）时从磁盘释放
+    pub bytes_reclaimed: u64,
+}
+
+/// 一次合规删除的结果，见 `Database::purge_key_history`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PurgeStats {
+    /// 删除的键版本数
+    pub versions_removed: u64,
+    /// 删除的版本的值字节数
+    pub bytes_removed: u64,
+    /// 状态中含有被删除的值的数据库版本；这些版本的根哈希仍承诺这些值。没有删除时为 `None`
+    pub affected_versions: Option<RangeInclusive<Version>>,
+}
+
+/// 固定时间点的登记，存活期间保留策略不会删除该时刻可见的版本
+pub(crate) struct PinGuard<'a> {
+    db: &'a Database,
+    pub(crate) pinned_at: f64,
+}
+
+impl Drop for PinGuard<'_> {
+    fn drop(&mut self) {
+        let mut pins = self.db.pins.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(i) = pins.iter().position(|&at| at == self.pinned_at) {
+            pins.swap_remove(i);
+        }
+    }
+}
+
+impl Database {
+    /// 固定当前状态（见 `pin`）并登记该时间点，返回守卫和根哈希
+    pub(crate) fn pin_retained(&self) -> Result<(PinGuard<'_>, Root)> {
+        self.retain_from(|| self.pin())
+    }
+
+    /// 调用 `pin` 得到时间点并登记，返回守卫和 `pin` 的其余结果
+    pub(crate) fn retain_from<T>(
+        &self,
+        pin: impl FnOnce() -> Result<(f64, T)>,
+    ) -> Result<(PinGuard<'_>, T)> {
+        // 持有登记表的锁完成固定，保证固定之后的写入在清理时都能看到这个时间点
+        let mut pins = self.pins.lock().unwrap_or_else(PoisonError::into_inner);
+        let (pinned_at, value) = pin()?;
+        pins.push(pinned_at);
+        Ok((
+            PinGuard {
+                db: self,
+                pinned_at,
+            },
+            value,
+        ))
+    }
+
+    /// 登记此前固定的时间点（例如续传备份时）
+    pub(crate) fn retain_pinned(&self, pinned_at: f64) -> PinGuard<'_> {
+        let mut pins = self.pins.lock().unwrap_or_else(PoisonError::into_inner);
+        pins.push(pinned_at);
+        PinGuard {
+            db: self,
+            pinned_at,
+        }
+    }
+
+    /// 删除只有早于数据库版本 `version` 的状态才需要的键版本，`version` 及之后的状态不变，
+    /// 之后 `snapshot_at`、`root_hash_at` 对更早的版本返回 `Error::NotFound`。
+    /// 仍有快照、游标或备份固定着更早的状态时不删除任何版本，返回 `Error::VersionPinned`；
+    /// 没有该版本时返回 `Error::NotFound`
+    pub fn prune_versions_before(&self, version: Version) -> Result<PruneStats> {
+        let mut raw = AmdbPruneStats::default();
+        let handle = self.live_handle()?;
+        // 持有登记表的锁，清理期间不会有新的快照固定更早的状态
+        let pins = self.pins.lock().unwrap_or_else(PoisonError::into_inner);
+        let status = self.retry_status(|| unsafe {
+            amdb_prune_versions_before(*handle, version.0, pins.as_ptr(), pins.len(), &mut raw)
+        });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        if raw.pinned {
+            return Err(Error::VersionPinned {
+                version: Version(raw.pinned_version),
+            });
+        }
+        Ok(PruneStats {
+            versions_removed: raw.versions_removed,
+            keys_pruned: raw.keys_pruned,
+            bytes_reclaimed: raw.bytes_reclaimed,
+        })
+    }
+
+    /// 合规删除：删除键除最新版本外的全部历史版本及其WAL条目，不改变当前状态和根哈希。
+    /// 先 `delete` 再调用，键就只剩下删除的墓碑
+    ///
+    /// 已记录的根哈希不重算：`PurgeStats::affected_versions` 中的版本仍承诺被删除的值，
+    /// 其快照中该键读不到值，也无法再为其生成证明；已持有这些根哈希的验证方不受影响。
+    /// 与保留策略不同，强制删除不理会固定的快照。删除的值在下次持久化时从版本文件释放，
+    /// SSTable中的旧值在 `Database::compact` 时释放
+    pub fn purge_key_history(&self, key: &[u8]) -> Result<PurgeStats> {
+        let mut raw = AmdbPurgeStats::default();
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            amdb_purge_key_history(*handle, key.as_ptr(), key.len(), &mut raw)
+        });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        Ok(PurgeStats {
+            versions_removed: raw.versions_removed,
+            bytes_removed: raw.bytes_removed,
+            affected_versions: (raw.versions_removed > 0)
+                .then_some(Version(raw.first_version)..=Version(raw.last_version)),
+        })
+    }
+
+    /// 对刚写入的键执行保留策略
+    pub(crate) fn enforce_retention(&self, keys: &[&[u8]]) -> Result<()> {
+        let Some((recent, interval)) = self.options.retention.limits() else {
+            return Ok(());
+        };
+        if keys.is_empty() || self.background_pruning.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        let ptrs: Vec<*const u8> = keys.iter().map(|k| k.as_ptr()).collect();
+        let lens: Vec<usize> = keys.iter().map(|k| k.len()).collect();
+
+        let pins = self.pins.lock().unwrap_or_else(PoisonError::into_inner);
+        let status = unsafe {
+            amdb_prune_versions(
+                *self.live_handle()?,
+                ptrs.as_ptr(),
+                lens.as_ptr(),
+                keys.len(),
+                recent,
+                interval,
+                pins.as_ptr(),
+                pins.len(),
+                std::ptr::null_mut(),
+            )
+        };
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{KeyVersion, OpenOptions};
+
+    use super::*;
+
+    #[test]
+    fn test_retention_policies() {
+        let _ = std::fs::remove_dir_all("./test_data/retention_last");
+        let _ = std::fs::remove_dir_all("./test_data/retention_every");
+        let db = OpenOptions::new()
+            .retention(Retention::KeepLast(2))
+            .open("./test_data/retention_last")
+            .unwrap();
+        for i in 1..=5u8 {
+            db.put(b"k", &[i]).unwrap();
+        }
+        assert_eq!(db.get(b"k", Some(KeyVersion(5))).unwrap(), Some(vec![5]));
+        assert_eq!(db.get(b"k", Some(KeyVersion(4))).unwrap(), Some(vec![4]));
+        assert!(db.get(b"k", Some(KeyVersion(3))).unwrap().is_none());
+
+        let db = OpenOptions::new()
+            .retention(Retention::KeepEvery {
+                recent: 1,
+                interval: 2,
+            })
+            .open("./test_data/retention_every")
+            .unwrap();
+        let root = (1..=5u8).map(|i| db.put(b"k", &[i]).unwrap()).last();
+        assert_eq!(Some(db.get_root_hash().unwrap()), root);
+        let kept: Vec<u64> = (1..=5)
+            .filter(|&v| db.get(b"k", Some(KeyVersion(v))).unwrap().is_some())
+            .collect();
+        assert_eq!(kept, vec![2, 4, 5]);
+    }
+
+    #[test]
+    fn test_purge_key_history() {
+        let _ = std::fs::remove_dir_all("./test_data/purge_history");
+        let db = Database::new("./test_data/purge_history").unwrap();
+        db.put(b"user/1", b"alice@example.com").unwrap();
+        db.put(b"user/1", b"alice@example.org").unwrap();
+        db.put(b"other", b"x").unwrap();
+        db.delete(b"user/1").unwrap();
+        let root = db.get_root_hash().unwrap();
+        assert!(db.is_tombstone(b"user/1").unwrap());
+        assert!(!db.is_tombstone(b"other").unwrap());
+        assert!(!db.is_tombstone(b"missing").unwrap());
+
+        let stats = db.purge_key_history(b"user/1").unwrap();
+        assert_eq!(stats.versions_removed, 2);
+        assert_eq!(stats.bytes_removed, 34);
+        assert_eq!(stats.affected_versions, Some(Version(1)..=Version(3)));
+        assert_eq!(db.get_root_hash().unwrap(), root);
+        assert!(db.get(b"user/1", Some(KeyVersion(1))).unwrap().is_none());
+        assert!(db
+            .snapshot_at(Version(2))
+            .unwrap()
+            .get(b"user/1")
+            .unwrap()
+            .is_none());
+        assert_eq!(db.history(b"user/1").count(), 1);
+        assert!(db.is_tombstone(b"user/1").unwrap());
+
+        assert_eq!(
+            db.purge_key_history(b"user/1").unwrap(),
+            PurgeStats::default()
+        );
+    }
+
+    #[test]
+    fn test_prune_versions_before() {
+        let _ = std::fs::remove_dir_all("./test_data/prune_before");
+        let db = Database::new("./test_data/prune_before").unwrap();
+        for i in 1..=3u8 {
+            db.put(b"k", &[i]).unwrap();
+        }
+        db.put(b"other", b"x").unwrap();
+        let root = db.get_root_hash().unwrap();
+
+        let snapshot = db.snapshot_at(Version(1)).unwrap();
+        assert!(matches!(
+            db.prune_versions_before(Version(3)),
+            Err(Error::VersionPinned {
+                version: Version(1)
+            })
+        ));
+        assert_eq!(db.get(b"k", Some(KeyVersion(1))).unwrap(), Some(vec![1]));
+        drop(snapshot);
+
+        let stats = db.prune_versions_before(Version(3)).unwrap();
+        assert_eq!(stats.versions_removed, 2);
+        assert_eq!(stats.keys_pruned, 1);
+        assert_eq!(stats.bytes_reclaimed, 2);
+        assert_eq!(db.get_root_hash().unwrap(), root);
+        assert!(db.get(b"k", Some(KeyVersion(2))).unwrap().is_none());
+        assert_eq!(
+            db.snapshot_at(Version(3)).unwrap().get(b"k").unwrap(),
+            Some(vec![3])
+        );
+        assert!(matches!(db.snapshot_at(Version(2)), Err(Error::NotFound)));
+        assert!(matches!(db.root_hash_at(Version(1)), Err(Error::NotFound)));
+        assert!(matches!(
+            db.prune_versions_before(Version(9)),
+            Err(Error::NotFound)
+        ));
+
+        let report = db.compact().unwrap();
+        assert!(report.bytes_after > 0);
+        assert!(report.reclaimed() < report.bytes_before);
+        assert_eq!(db.get(b"k", None).unwrap(), Some(vec![3]));
+    }
+
+    #[test]
+    fn test_pinned_version_is_retained() {
+        let _ = std::fs::remove_dir_all("./test_data/retention_pinned");
+        let db = OpenOptions::new()
+            .retention(Retention::KeepLast(1))
+            .open("./test_data/retention_pinned")
+            .unwrap();
+        db.put(b"k", b"old").unwrap();
+        let (guard, _) = db.pin_retained().unwrap();
+        db.put(b"k", b"new").unwrap();
+        db.put(b"k", b"newer").unwrap();
+        assert_eq!(db.get(b"k", Some(KeyVersion(1))).unwrap(), Some(b"old".to_vec()));
+        assert!(db.get(b"k", Some(KeyVersion(2))).unwrap().is_none());
+
+        drop(guard);
+        db.put(b"k", b"latest").unwrap();
+        assert!(db.get(b"k", Some(KeyVersion(1))).unwrap().is_none());
+    }
+}
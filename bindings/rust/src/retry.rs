@@ -0,0 +1,211 @@
+//! 暂时性错误的重试
+//! 引擎返回 `Error::Busy`/`Error::TimedOut` 时按 `RetryPolicy` 退避后重试同一次引擎调用，默认不重试。
+//! 策略由 `OpenOptions::retry` 对整个数据库设置，`Database::with_retry` 可在单次调用期间覆盖。
+//!
+//! 只重试单次引擎调用；流式读写（`put_from_reader`、`get_to_writer`）已消费的数据无法重放，不重试。
+
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::os::raw::c_int;
+use std::thread;
+use std::time::Duration;
+
+use crate::ffi::{AMDB_BUSY, AMDB_TIMED_OUT};
+use crate::Database;
+
+/// 重试策略：第 n 次重试前等待 `initial_backoff * 2^(n-1)`，不超过 `max_backoff`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+            jitter: 0.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 最多调用 `max_attempts` 次（含首次）；1 表示不重试
+    pub fn new(max_attempts: u32) -> Self {
+        let mut policy = Self::default();
+        policy.max_attempts(max_attempts);
+        policy
+    }
+
+    /// 不重试（默认）
+    pub fn never() -> Self {
+        Self::default()
+    }
+
+    /// 最多调用次数（含首次），0 按 1 处理
+    pub fn max_attempts(&mut self, attempts: u32) -> &mut Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// 首次重试前的等待时间（默认10毫秒），之后每次翻倍
+    pub fn initial_backoff(&mut self, backoff: Duration) -> &mut Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// 单次等待的上限（默认1秒）
+    pub fn max_backoff(&mut self, backoff: Duration) -> &mut Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// 随机缩短等待时间的比例，取值 0.0（默认，不随机）到 1.0，避免多个调用方同时重试
+    pub fn jitter(&mut self, fraction: f64) -> &mut Self {
+        self.jitter = fraction.clamp(0.0, 1.0);
+        self
+    }
+
+    /// 执行一次引擎调用，返回暂时性错误时退避后重试；返回最后一次调用的状态码
+    pub(crate) fn run(&self, mut call: impl FnMut() -> c_int) -> c_int {
+        let mut status = call();
+        for retry in 1..self.max_attempts {
+            if !is_transient(status) {
+                break;
+            }
+            thread::sleep(self.backoff(retry));
+            status = call();
+        }
+        status
+    }
+
+    /// 第 `retry` 次重试（从1开始）前的等待时间
+    fn backoff(&self, retry: u32) -> Duration {
+        let factor = 1u32.checked_shl(retry - 1).unwrap_or(u32::MAX);
+        let delay = self
+            .initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff);
+        if self.jitter == 0.0 {
+            return delay;
+        }
+        delay.mul_f64(1.0 - self.jitter * random_unit())
+    }
+}
+
+thread_local! {
+    /// `with_retry` 设置的当前线程的策略
+    static OVERRIDE: Cell<Option<RetryPolicy>> = const { Cell::new(None) };
+}
+
+/// [0, 1) 内的随机数，只用于抖动
+fn random_unit() -> f64 {
+    let bits = RandomState::new().build_hasher().finish() >> 11;
+    bits as f64 / (1u64 << 53) as f64
+}
+
+fn is_transient(status: c_int) -> bool {
+    status == AMDB_BUSY || status == AMDB_TIMED_OUT
+}
+
+/// 离开作用域时恢复外层的策略
+struct Restore(Option<RetryPolicy>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        OVERRIDE.with(|policy| policy.set(self.0));
+    }
+}
+
+impl Database {
+    /// 在 `f` 执行期间用 `policy` 代替数据库的重试策略（仅当前线程），
+    /// 例如 `db.with_retry(&RetryPolicy::new(5), |db| db.get(b"k", None))`
+    pub fn with_retry<T>(&self, policy: &RetryPolicy, f: impl FnOnce(&Database) -> T) -> T {
+        let _restore = Restore(OVERRIDE.with(|current| current.replace(Some(*policy))));
+        f(self)
+    }
+
+    /// 当前生效的重试策略：`with_retry` 设置的优先，否则为数据库的策略
+    pub(crate) fn retry_policy(&self) -> RetryPolicy {
+        OVERRIDE.with(Cell::get).unwrap_or(self.options.retry)
+    }
+
+    /// 按当前策略执行一次引擎调用，见 `RetryPolicy::run`
+    pub(crate) fn retry_status(&self, call: impl FnMut() -> c_int) -> c_int {
+        self.retry_policy().run(call)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::OpenOptions;
+
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let mut policy = RetryPolicy::new(10);
+        policy
+            .initial_backoff(Duration::from_millis(10))
+            .max_backoff(Duration::from_millis(50));
+        let delays: Vec<u64> = (1..=4)
+            .map(|retry| policy.backoff(retry).as_millis() as u64)
+            .collect();
+        assert_eq!(delays, vec![10, 20, 40, 50]);
+        assert_eq!(policy.backoff(40), Duration::from_millis(50));
+
+        policy.jitter(0.5);
+        for retry in 1..=4 {
+            let delay = policy.backoff(retry);
+            assert!(delay >= Duration::from_millis(5) && delay <= Duration::from_millis(50));
+        }
+    }
+
+    #[test]
+    fn test_retry_transient_status() {
+        let _ = std::fs::remove_dir_all("./test_data/retry");
+        let mut policy = RetryPolicy::new(3);
+        policy.initial_backoff(Duration::from_millis(1));
+        let db = OpenOptions::new()
+            .retry(policy)
+            .open("./test_data/retry")
+            .unwrap();
+
+        let mut calls = 0;
+        let status = db.retry_status(|| {
+            calls += 1;
+            if calls < 3 {
+                AMDB_BUSY
+            } else {
+                0
+            }
+        });
+        assert_eq!((status, calls), (0, 3));
+
+        // 非暂时性错误不重试
+        calls = 0;
+        assert_eq!(
+            db.retry_status(|| {
+                calls += 1;
+                -1
+            }),
+            -1
+        );
+        assert_eq!(calls, 1);
+
+        // 单次调用覆盖数据库的策略
+        calls = 0;
+        let status = db.with_retry(&RetryPolicy::never(), |db| {
+            db.retry_status(|| {
+                calls += 1;
+                AMDB_TIMED_OUT
+            })
+        });
+        assert_eq!((status, calls), (AMDB_TIMED_OUT, 1));
+    }
+}
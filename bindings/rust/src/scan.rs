@@ -0,0 +1,362 @@
+//! 范围扫描迭代器
+//! 首次调用 `next`/`next_back` 时才向引擎发起查询，查询错误作为迭代项返回
+//!
+//! 默认一次读出整个范围。设置 `IterOptions::batch_size` 或 `readahead` 后按页读取，每页一次跨FFI调用，
+//! 读入复用的缓冲区；设置 `readahead` 时消费当前页的同时由后台线程预取下一页，适合导出全部状态这类
+//! 长顺序扫描。各页分别读取，扫描期间的写入可能出现在后续页中。
+
+use std::collections::VecDeque;
+use std::panic;
+use std::ptr;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::reserved::ReservedFilter;
+use crate::{
+    amdb_free_result, amdb_range_query_page, collect_range, result_bytes, AmdbHandle, AmdbResult,
+    Database, Entry, HandleState, Result, RetryPolicy, SendHandle, TreeOptions, ValueFilter,
+};
+
+/// 扫描得到的键值对
+pub type KeyValue = (Box<[u8]>, Box<[u8]>);
+
+/// 扫描选项
+#[derive(Debug, Clone, Default)]
+pub struct IterOptions {
+    batch_size: usize,
+    readahead: usize,
+}
+
+impl IterOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 每次引擎调用最多读取的项数；0（默认）表示不限制
+    ///
+    /// 与 `readahead` 一样只作用于正向迭代，`next_back` 会一次读出剩余部分。
+    pub fn batch_size(&mut self, entries: usize) -> &mut Self {
+        self.batch_size = entries;
+        self
+    }
+
+    /// 每页读取的键值字节数，并在后台预取下一页；0（默认）表示不按字节分页也不预取
+    ///
+    /// 只作用于正向迭代，`next_back` 会一次读出剩余部分。
+    pub fn readahead(&mut self, bytes: usize) -> &mut Self {
+        self.readahead = bytes;
+        self
+    }
+}
+
+/// 一页结果和下一页的起始键
+type Page = (Vec<Entry>, Option<Vec<u8>>);
+
+pub struct Scan<'a> {
+    db: &'a Database,
+    /// 尚未读取部分的起始键，`None` 表示已全部读取或已交给预取
+    cursor: Option<Vec<u8>>,
+    /// 区间上界（不包含），为空表示无上界
+    end: Vec<u8>,
+    /// 后台预取中的下一页
+    prefetch: Option<JoinHandle<Result<Page>>>,
+    strip: usize,
+    batch_size: usize,
+    readahead: usize,
+    /// 引擎侧的值过滤条件，见 `Database::scan_filtered`
+    filter: Option<ValueFilter>,
+    reserved: ReservedFilter,
+    /// 命名树的选项，扫描到的值按其还原
+    tree: Option<Arc<TreeOptions>>,
+    entries: VecDeque<Entry>,
+}
+
+impl<'a> Scan<'a> {
+    /// `bounds` 为引擎半开区间，`None` 表示空区间；`strip` 为需要从键头部去除的字节数
+    pub(crate) fn new(
+        db: &'a Database,
+        bounds: Option<(Vec<u8>, Vec<u8>)>,
+        strip: usize,
+        options: &IterOptions,
+    ) -> Self {
+        let (cursor, end) = match bounds {
+            Some((start, end)) => (Some(start), end),
+            None => (None, Vec::new()),
+        };
+        let reserved = ReservedFilter::from_start(cursor.as_deref().unwrap_or_default());
+        Scan {
+            db,
+            cursor,
+            end,
+            prefetch: None,
+            strip,
+            batch_size: options.batch_size,
+            readahead: options.readahead,
+            filter: None,
+            reserved,
+            tree: None,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// 只返回值满足 `filter` 的键；过滤扫描不分页，一次读出整个范围
+    pub(crate) fn filtered(mut self, filter: ValueFilter) -> Self {
+        self.batch_size = 0;
+        self.readahead = 0;
+        self.filter = Some(filter);
+        self
+    }
+
+    /// 按命名树的选项还原值，跳过已过期的键
+    pub(crate) fn tree_values(mut self, options: Arc<TreeOptions>) -> Self {
+        self.tree = Some(options);
+        self
+    }
+
+    /// 一次读出 [start, end) 中的全部键值对
+    fn read_rest(&self, start: &[u8]) -> Result<Vec<Entry>> {
+        let entries = match &self.filter {
+            Some(filter) => self.db.range_query_filtered(start, &self.end, filter)?,
+            None => self.db.range_query(start, &self.end)?,
+        };
+        self.finish(entries)
+    }
+
+    /// 当前页读完时取下一页
+    fn fill(&mut self) -> Result<()> {
+        while self.entries.is_empty() {
+            let (entries, next) = if let Some(prefetch) = self.prefetch.take() {
+                self.join(prefetch)?
+            } else if let Some(start) = self.cursor.take() {
+                if self.batch_size == 0 && self.readahead == 0 {
+                    (self.read_rest(&start)?, None)
+                } else {
+                    let (entries, next) = range_page(
+                        &self.db.state,
+                        self.db.handle,
+                        self.db.retry_policy(),
+                        &start,
+                        &self.end,
+                        self.batch_size,
+                        self.readahead,
+                    )?;
+                    (self.open_page(entries)?, next)
+                }
+            } else {
+                return Ok(());
+            };
+            // 缓冲区已空，沿用其容量
+            self.entries.extend(entries);
+            match next {
+                Some(next) if self.readahead > 0 => self.start_prefetch(next),
+                next => self.cursor = next,
+            }
+        }
+        Ok(())
+    }
+
+    /// 读出剩余的全部键值对，供反向迭代使用
+    fn fill_rest(&mut self) -> Result<()> {
+        if let Some(prefetch) = self.prefetch.take() {
+            let (entries, next) = self.join(prefetch)?;
+            self.entries.extend(entries);
+            self.cursor = next;
+        }
+        if let Some(start) = self.cursor.take() {
+            self.entries.extend(self.read_rest(&start)?);
+        }
+        Ok(())
+    }
+
+    fn start_prefetch(&mut self, start: Vec<u8>) {
+        let handle = SendHandle(self.db.handle);
+        let state = self.db.state.clone();
+        // 在当前线程上取策略，`with_retry` 的覆盖同样作用于预取
+        let retry = self.db.retry_policy();
+        let end = self.end.clone();
+        let (max_entries, max_bytes) = (self.batch_size, self.readahead);
+        let cursor = start.clone();
+        let spawned = thread::Builder::new()
+            .name("amdb-prefetch".to_string())
+            .spawn(move || {
+                let handle = handle;
+                range_page(
+                    &state,
+                    handle.0,
+                    retry,
+                    &start,
+                    &end,
+                    max_entries,
+                    max_bytes,
+                )
+            });
+        match spawned {
+            Ok(thread) => self.prefetch = Some(thread),
+            // 无法创建线程时退回到同步读取
+            Err(_) => self.cursor = Some(cursor),
+        }
+    }
+
+    /// 等待预取的页，并校验其中的值
+    fn join(&self, prefetch: JoinHandle<Result<Page>>) -> Result<Page> {
+        let (entries, next) = prefetch.join().unwrap_or_else(|e| panic::resume_unwind(e))?;
+        Ok((self.open_page(entries)?, next))
+    }
+
+    /// 校验一页中的值，并跳过本次扫描不应看到的保留键
+    fn open_page(&self, entries: Vec<Entry>) -> Result<Vec<Entry>> {
+        let entries = self.db.open_entries(entries)?;
+        self.finish(entries)
+    }
+
+    fn finish(&self, mut entries: Vec<Entry>) -> Result<Vec<Entry>> {
+        self.reserved.retain(&mut entries);
+        match &self.tree {
+            Some(options) => options.decode_entries(entries),
+            None => Ok(entries),
+        }
+    }
+
+    fn convert(&self, (key, value): Entry) -> KeyValue {
+        (key[self.strip..].into(), value.into_boxed_slice())
+    }
+}
+
+/// 读取 [start, end) 中最多 `max_entries` 项、约 `max_bytes` 字节的一页（0表示不限制）；
+/// 调用期间持有守卫，数据库关闭或中毒后不再调用引擎
+fn range_page(
+    state: &HandleState,
+    handle: *mut AmdbHandle,
+    retry: RetryPolicy,
+    start: &[u8],
+    end: &[u8],
+    max_entries: usize,
+    max_bytes: usize,
+) -> Result<Page> {
+    let mut next_key = AmdbResult {
+        status: 0,
+        error_msg: ptr::null(),
+        data: ptr::null_mut(),
+        data_len: 0,
+    };
+    let _alive = state.enter()?;
+    let entries = collect_range(state, |results, count| {
+        retry.run(|| unsafe {
+            amdb_range_query_page(
+                handle,
+                start.as_ptr(),
+                start.len(),
+                end.as_ptr(),
+                end.len(),
+                max_entries,
+                max_bytes,
+                results,
+                count,
+                &mut next_key,
+            )
+        })
+    })?;
+    let next = (!next_key.data.is_null()).then(|| result_bytes(&next_key));
+    unsafe { amdb_free_result(&mut next_key) };
+    Ok((entries, next))
+}
+
+impl Iterator for Scan<'_> {
+    type Item = Result<KeyValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(e) = self.fill() {
+            return Some(Err(e));
+        }
+        self.entries
+            .pop_front()
+            .map(|entry| Ok(self.convert(entry)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.cursor.is_some() || self.prefetch.is_some() {
+            (self.entries.len(), None)
+        } else {
+            (self.entries.len(), Some(self.entries.len()))
+        }
+    }
+}
+
+impl DoubleEndedIterator for Scan<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if let Err(e) = self.fill_rest() {
+            return Some(Err(e));
+        }
+        self.entries.pop_back().map(|entry| Ok(self.convert(entry)))
+    }
+}
+
+impl Drop for Scan<'_> {
+    fn drop(&mut self) {
+        // 预取线程使用数据库句柄，须在借用结束前退出
+        if let Some(prefetch) = self.prefetch.take() {
+            let _ = prefetch.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_both_directions() {
+        let _ = std::fs::remove_dir_all("./test_data/scan");
+        let db = Database::new("./test_data/scan").unwrap();
+        for key in [b"k1", b"k2", b"k3"] {
+            db.put(key, b"v").unwrap();
+        }
+
+        let keys: Vec<Box<[u8]>> = db.scan(..).map(|item| item.unwrap().0).collect();
+        assert_eq!(keys, vec![b"k1"[..].into(), b"k2"[..].into(), b"k3"[..].into()]);
+
+        let last = db.scan(..).next_back().unwrap().unwrap();
+        assert_eq!(&*last.0, b"k3");
+
+        let mut scan = db.scan(b"k2".to_vec()..);
+        assert_eq!(&*scan.next().unwrap().unwrap().0, b"k2");
+        assert_eq!(&*scan.next_back().unwrap().unwrap().0, b"k3");
+        assert!(scan.next().is_none());
+    }
+
+    #[test]
+    fn test_scan_with_readahead() {
+        let _ = std::fs::remove_dir_all("./test_data/scan_readahead");
+        let db = Database::new("./test_data/scan_readahead").unwrap();
+        for i in 0..10u8 {
+            db.put(&[b'k', i], &[i; 8]).unwrap();
+        }
+        db.delete(&[b'k', 4]).unwrap();
+
+        // 每页约两项
+        let mut options = IterOptions::new();
+        options.readahead(16);
+        let paged: Vec<KeyValue> = db.scan_with(.., &options).map(Result::unwrap).collect();
+        let whole: Vec<KeyValue> = db.scan(..).map(Result::unwrap).collect();
+        assert_eq!(paged.len(), 9);
+        assert_eq!(paged, whole);
+
+        let mut scan = db.scan_with(vec![b'k', 2].., &options);
+        assert_eq!(&*scan.next().unwrap().unwrap().0, &[b'k', 2]);
+        assert_eq!(&*scan.next_back().unwrap().unwrap().0, &[b'k', 9]);
+        assert_eq!(scan.count(), 5);
+
+        let mut options = IterOptions::new();
+        options.batch_size(4);
+        let mut scan = db.scan_with(.., &options);
+        assert_eq!(scan.by_ref().take(4).count(), 4);
+        assert_eq!(scan.size_hint(), (0, None));
+        assert_eq!(scan.count(), 5);
+
+        // 提前丢弃时等待预取线程退出
+        options.readahead(16);
+        let mut scan = db.scan_with(.., &options);
+        assert!(scan.next().is_some());
+        drop(scan);
+    }
+}
@@ -0,0 +1,191 @@
+//! 影子写入
+//! 迁移期间把每次写入同时提交到另一个数据库（例如旧数据目录或旧的树选项），逐次比较两边提交后的根哈希。
+//! 主库的结果原样返回；影子库的失败和根哈希不一致只记入 `ShadowReport`，不影响主库的写入。
+//! 只有经由 `ShadowWriter` 的写入会被复制，比较的前提是两个库从相同的状态开始。
+
+use std::mem;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::{BatchOp, Database, Result, Root, WriteBatch};
+
+/// 报告中最多保留的不一致记录数，更早的记录被丢弃
+const MAX_DIVERGENCES: usize = 100;
+
+/// 一次根哈希不一致的提交
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// 经由 `ShadowWriter` 的第几次提交（从1开始）
+    pub commit: u64,
+    /// 该次提交写入的键
+    pub keys: Vec<Vec<u8>>,
+    pub primary_root: Root,
+    pub shadow_root: Root,
+}
+
+/// 影子写入的累计统计
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShadowReport {
+    /// 主库成功的提交数
+    pub commits: u64,
+    /// 根哈希不一致的提交数
+    pub divergent: u64,
+    /// 最近的不一致记录，最多保留100条
+    pub divergences: Vec<Divergence>,
+    /// 影子库写入失败的次数及最近一次的错误
+    pub shadow_errors: u64,
+    pub last_shadow_error: Option<String>,
+    /// 两边写入各自的累计耗时
+    pub primary_time: Duration,
+    pub shadow_time: Duration,
+}
+
+/// 把写入复制到影子库的包装，见 `Database::shadow_writes`
+pub struct ShadowWriter<'a> {
+    primary: &'a Database,
+    shadow: &'a Database,
+    /// 同时串行化经由本包装的写入，使两边的提交一一对应
+    report: Mutex<ShadowReport>,
+}
+
+impl Database {
+    /// 经由返回的包装写入时，同时写入 `shadow` 并比较根哈希
+    pub fn shadow_writes<'a>(&'a self, shadow: &'a Database) -> ShadowWriter<'a> {
+        ShadowWriter {
+            primary: self,
+            shadow,
+            report: Mutex::default(),
+        }
+    }
+}
+
+impl ShadowWriter<'_> {
+    pub fn put(&self, key: &[u8], value: &[u8]) -> Result<Root> {
+        self.mirror(vec![key.to_vec()], |db| db.put(key, value))
+    }
+
+    pub fn delete(&self, key: &[u8]) -> Result<Root> {
+        self.mirror(vec![key.to_vec()], |db| {
+            db.delete(key)?;
+            db.get_root_hash()
+        })
+    }
+
+    pub fn write_batch(&self, batch: &WriteBatch) -> Result<Root> {
+        let keys = batch
+            .iter()
+            .map(|op| match op {
+                BatchOp::Put { key, .. } | BatchOp::Delete { key } => key.to_vec(),
+            })
+            .collect();
+        self.mirror(keys, |db| db.write_batch(batch))
+    }
+
+    /// 目前为止的统计
+    pub fn report(&self) -> ShadowReport {
+        self.lock().clone()
+    }
+
+    /// 取出统计并清零
+    pub fn take_report(&self) -> ShadowReport {
+        mem::take(&mut *self.lock())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ShadowReport> {
+        self.report.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn mirror(
+        &self,
+        keys: Vec<Vec<u8>>,
+        write: impl Fn(&Database) -> Result<Root>,
+    ) -> Result<Root> {
+        let mut report = self.lock();
+        let started = Instant::now();
+        let primary_root = write(self.primary)?;
+        report.primary_time += started.elapsed();
+        report.commits += 1;
+
+        let started = Instant::now();
+        let shadow = write(self.shadow);
+        report.shadow_time += started.elapsed();
+        match shadow {
+            Ok(shadow_root) if shadow_root != primary_root => {
+                report.divergent += 1;
+                if report.divergences.len() == MAX_DIVERGENCES {
+                    report.divergences.remove(0);
+                }
+                let commit = report.commits;
+                report.divergences.push(Divergence {
+                    commit,
+                    keys,
+                    primary_root,
+                    shadow_root,
+                });
+            }
+            Ok(_) => {}
+            Err(e) => {
+                report.shadow_errors += 1;
+                report.last_shadow_error = Some(e.to_string());
+            }
+        }
+        Ok(primary_root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::OpenOptions;
+
+    use super::*;
+
+    #[test]
+    fn test_shadow_writes() {
+        let _ = std::fs::remove_dir_all("./test_data/shadow_primary");
+        let _ = std::fs::remove_dir_all("./test_data/shadow_secondary");
+        let primary = Database::new("./test_data/shadow_primary").unwrap();
+        let shadow = Database::new("./test_data/shadow_secondary").unwrap();
+        let writer = primary.shadow_writes(&shadow);
+        writer.put(b"a", b"1").unwrap();
+        let mut batch = WriteBatch::new();
+        batch.put(b"b", b"2").put(b"c", b"3");
+        writer.write_batch(&batch).unwrap();
+        let root = writer.delete(b"a").unwrap();
+        assert_eq!(shadow.get_root_hash().unwrap(), root);
+
+        let report = writer.take_report();
+        assert_eq!(report.commits, 3);
+        assert_eq!((report.divergent, report.shadow_errors), (0, 0));
+        assert_eq!(writer.report(), ShadowReport::default());
+
+        // 影子库独自多出的写入使之后的提交不一致
+        shadow.put(b"stray", b"x").unwrap();
+        writer.put(b"d", b"4").unwrap();
+        let report = writer.report();
+        assert_eq!(report.divergent, 1);
+        assert_eq!(report.divergences[0].commit, 1);
+        assert_eq!(report.divergences[0].keys, vec![b"d".to_vec()]);
+    }
+
+    #[test]
+    fn test_shadow_errors_do_not_fail_primary() {
+        let _ = std::fs::remove_dir_all("./test_data/shadow_errors");
+        let _ = std::fs::remove_dir_all("./test_data/shadow_errors_secondary");
+        let primary = Database::new("./test_data/shadow_errors").unwrap();
+        let shadow = OpenOptions::new()
+            .max_value_size(1)
+            .open("./test_data/shadow_errors_secondary")
+            .unwrap();
+        let writer = primary.shadow_writes(&shadow);
+        writer.put(b"k", b"too large").unwrap();
+        assert_eq!(
+            primary.get(b"k", None).unwrap(),
+            Some(b"too large".to_vec())
+        );
+
+        let report = writer.report();
+        assert_eq!(report.shadow_errors, 1);
+        assert!(report.last_shadow_error.unwrap().contains("exceeds"));
+        assert_eq!(report.divergent, 0);
+    }
+}
@@ -0,0 +1,110 @@
+//! 限时关闭
+//! `Database::shutdown` 供服务的信号处理一次调用：先拒绝新的调用，等进行中的引擎调用结束，
+//! 再刷新、fsync并释放句柄。时限到期时仍有调用未结束，就先把已提交的数据刷新并fsync后返回，
+//! 句柄由这些调用中最后结束的一个关闭。
+
+use std::time::Instant;
+
+use crate::{amdb_close_with, amdb_sync, Database, Error, Result, AMDB_CLOSE_SYNC};
+
+/// `Database::shutdown` 的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// 时限到期时仍未结束的引擎调用数；为0时返回前已释放句柄，否则由其中最后结束的调用释放
+    pub outstanding_calls: usize,
+}
+
+impl Database {
+    /// 在 `deadline` 之前关闭数据库：
+    ///
+    /// 1. 转入已关闭状态，之后的调用返回 `Error::Closed`；扫描、导出、后台清理等由多次引擎调用组成的操作
+    ///    在下一次调用时中止；
+    /// 2. 等待进行中的引擎调用结束，最多到 `deadline`；
+    /// 3. 刷新并fsync数据目录（同 `DropBehavior::Sync`），引擎取消已安排的批量fsync并等待后台刷新完成；
+    /// 4. 释放句柄和数据目录的锁。到期时仍有调用未结束则不等待，见 `ShutdownReport::outstanding_calls`。
+    ///
+    /// 已关闭时返回 `Error::Closed`；中毒的数据库不再调用引擎。刷新失败时返回错误，句柄仍会释放
+    pub fn shutdown(&self, deadline: Instant) -> Result<ShutdownReport> {
+        let drain = self.state.close_by(deadline, self.handle)?;
+        let report = ShutdownReport {
+            outstanding_calls: drain.outstanding,
+        };
+        if !drain.open {
+            return Ok(report);
+        }
+        let status = match drain.guard {
+            None => unsafe { amdb_close_with(self.handle, AMDB_CLOSE_SYNC) },
+            Some(guard) => {
+                // 进行中的调用仍在使用句柄，只持久化已提交的数据；守卫释放时若已是最后一个调用则关闭句柄
+                let status = unsafe { amdb_sync(self.handle) };
+                drop(guard);
+                status
+            }
+        };
+        if status != 0 {
+            return Err(Error::from_status(status));
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::WriteBatch;
+
+    #[test]
+    fn test_shutdown() {
+        let _ = std::fs::remove_dir_all("./test_data/shutdown");
+        let dir = "./test_data/shutdown";
+        let db = Database::new(dir).unwrap();
+        db.put(b"k", b"v").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        assert_eq!(db.shutdown(deadline).unwrap().outstanding_calls, 0);
+        assert!(matches!(db.put(b"k", b"w"), Err(Error::Closed)));
+        assert!(matches!(db.shutdown(deadline), Err(Error::Closed)));
+        assert!(matches!(db.close(), Err(Error::Closed)));
+        drop(db);
+
+        let db = Database::new(dir).unwrap();
+        assert_eq!(db.get(b"k", None).unwrap(), Some(b"v".to_vec()));
+    }
+
+    #[test]
+    fn test_shutdown_deadline() {
+        let _ = std::fs::remove_dir_all("./test_data/shutdown_deadline");
+        let dir = "./test_data/shutdown_deadline";
+        let db = Arc::new(Database::new(dir).unwrap());
+        let mut batch = WriteBatch::new();
+        for i in 0..20_000u32 {
+            batch.put(&i.to_be_bytes(), b"v");
+        }
+        let (started, wait_started) = mpsc::channel();
+        let writer = {
+            let db = Arc::clone(&db);
+            thread::spawn(move || {
+                started.send(()).unwrap();
+                db.write_batch(&batch)
+            })
+        };
+        wait_started.recv().unwrap();
+        thread::sleep(Duration::from_millis(50));
+
+        // 到期时批量写入仍在进行，由它结束时关闭句柄
+        let report = db.shutdown(Instant::now()).unwrap();
+        assert_eq!(report.outstanding_calls, 1);
+        assert!(matches!(db.get(b"k", None), Err(Error::Closed)));
+        writer.join().unwrap().unwrap();
+
+        drop(db);
+        let db = Database::new(dir).unwrap();
+        assert_eq!(
+            db.get(&19_999u32.to_be_bytes(), None).unwrap(),
+            Some(b"v".to_vec())
+        );
+    }
+}
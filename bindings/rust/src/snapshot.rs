@@ -0,0 +1,208 @@
+//! 快照文件
+//! 把数据库的最新状态导出为单个文件，可在其他机器上导入
+//!
+//! 文件格式（整数均为小端序）：
+//!
+//! ```text
+//! 魔数 "AMDBSNAP" | 格式版本 u32 | 创建时间 u64（Unix秒） | 根哈希 32字节 | 条目数 u64
+//! 每个条目：键长度 u32 | 键 | 值长度 u64 | 值
+//! ```
+//!
+//! 值长度为 `u64::MAX` 的条目没有值，表示该键已删除；只出现在备份的分段中。
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{BatchItem, Database, Error, Result, Root};
+
+const MAGIC: &[u8; 8] = b"AMDBSNAP";
+const FORMAT_VERSION: u32 = 2;
+
+/// 已删除的键的值长度
+const DELETED_LEN: u64 = u64::MAX;
+
+/// 快照文件头中的元数据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotInfo {
+    /// 导出时源数据库的根哈希
+    pub root_hash: Root,
+    /// 导出时间（Unix秒）
+    pub created_at: u64,
+    pub entry_count: u64,
+}
+
+impl Database {
+    /// 把全部键的最新值写入快照文件 `path`（已存在时覆盖）
+    pub fn write_snapshot_file(&self, path: impl AsRef<Path>) -> Result<SnapshotInfo> {
+        let entries: Vec<BatchItem> = self
+            .range_query(b"", b"")?
+            .into_iter()
+            .map(|(key, value)| (key, Some(value)))
+            .collect();
+        let info = SnapshotInfo {
+            root_hash: self.get_root_hash()?,
+            created_at: unix_now(),
+            entry_count: entries.len() as u64,
+        };
+        write_snapshot(path.as_ref(), &info, &entries)?;
+        Ok(info)
+    }
+
+    /// 在一次批量写入中导入快照文件中的全部条目，返回文件头中的元数据
+    ///
+    /// 导入不会删除数据库中已有的其他键；导入后的根哈希取决于目标数据库的写入历史，
+    /// 只有导入空数据库时才与 `SnapshotInfo::root_hash` 一致。
+    pub fn read_snapshot_file(&self, path: impl AsRef<Path>) -> Result<SnapshotInfo> {
+        let (info, entries) = read_snapshot(path.as_ref())?;
+        self.batch_put(&entries)?;
+        Ok(info)
+    }
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// 按快照格式写入 `entries`，`info.entry_count` 必须等于条目数；返回前把文件刷到磁盘
+pub(crate) fn write_snapshot(
+    path: &Path,
+    info: &SnapshotInfo,
+    entries: &[BatchItem],
+) -> Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    encode_snapshot(&mut out, info, entries)?;
+    out.flush()?;
+    out.get_ref().sync_all()?;
+    Ok(())
+}
+
+pub(crate) fn encode_snapshot(
+    out: &mut impl Write,
+    info: &SnapshotInfo,
+    entries: &[BatchItem],
+) -> Result<()> {
+    out.write_all(MAGIC)?;
+    out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    out.write_all(&info.created_at.to_le_bytes())?;
+    out.write_all(&*info.root_hash)?;
+    out.write_all(&info.entry_count.to_le_bytes())?;
+    for (key, value) in entries {
+        let key_len = u32::try_from(key.len())
+            .map_err(|_| Error::InvalidArgument("key longer than 4 GiB".to_string()))?;
+        out.write_all(&key_len.to_le_bytes())?;
+        out.write_all(key)?;
+        match value {
+            Some(value) => {
+                out.write_all(&(value.len() as u64).to_le_bytes())?;
+                out.write_all(value)?;
+            }
+            None => out.write_all(&DELETED_LEN.to_le_bytes())?,
+        }
+    }
+    Ok(())
+}
+
+/// 读取并校验快照文件，返回文件头和全部条目
+pub(crate) fn read_snapshot(path: &Path) -> Result<(SnapshotInfo, Vec<BatchItem>)> {
+    decode_snapshot(&mut BufReader::new(File::open(path)?))
+}
+
+pub(crate) fn decode_snapshot(input: &mut impl Read) -> Result<(SnapshotInfo, Vec<BatchItem>)> {
+    let info = read_header(input)?;
+
+    let mut entries: Vec<BatchItem> = Vec::new();
+    for _ in 0..info.entry_count {
+        let key_len = u32::from_le_bytes(read_array(input)?);
+        let key = read_vec(input, key_len as u64)?;
+        let value = match u64::from_le_bytes(read_array(input)?) {
+            DELETED_LEN => None,
+            value_len => Some(read_vec(input, value_len)?),
+        };
+        entries.push((key, value));
+    }
+    if input.read(&mut [0u8; 1])? != 0 {
+        return Err(Error::Corruption(
+            "trailing data after the last snapshot entry".to_string(),
+        ));
+    }
+    Ok((info, entries))
+}
+
+fn read_header(input: &mut impl Read) -> Result<SnapshotInfo> {
+    let magic: [u8; 8] = read_array(input)?;
+    if &magic != MAGIC {
+        return Err(Error::Corruption("not an AmDb snapshot file".to_string()));
+    }
+    let format = u32::from_le_bytes(read_array(input)?);
+    if format != FORMAT_VERSION {
+        return Err(Error::Corruption(format!(
+            "unsupported snapshot format version {}",
+            format
+        )));
+    }
+    Ok(SnapshotInfo {
+        created_at: u64::from_le_bytes(read_array(input)?),
+        root_hash: Root(read_array(input)?),
+        entry_count: u64::from_le_bytes(read_array(input)?),
+    })
+}
+
+/// 与 `Read::read_exact` 相同，但把提前结束的文件报告为损坏
+fn read_exact(input: &mut impl Read, buf: &mut [u8]) -> Result<()> {
+    input.read_exact(buf).map_err(|e| match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => {
+            Error::Corruption("truncated snapshot file".to_string())
+        }
+        _ => Error::Io(e),
+    })
+}
+
+fn read_array<const N: usize>(input: &mut impl Read) -> Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    read_exact(input, &mut buf)?;
+    Ok(buf)
+}
+
+fn read_vec(input: &mut impl Read, len: u64) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    input.take(len).read_to_end(&mut buf)?;
+    if (buf.len() as u64) < len {
+        return Err(Error::Corruption("truncated snapshot file".to_string()));
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_file_round_trip() {
+        let _ = std::fs::remove_dir_all("./test_data/snapshot_src");
+        let _ = std::fs::remove_dir_all("./test_data/state.snap");
+        let _ = std::fs::remove_dir_all("./test_data/snapshot_dst");
+        let source = Database::new("./test_data/snapshot_src").unwrap();
+        source.put(b"a", b"1").unwrap();
+        source.put(b"b", &[7u8; 1000]).unwrap();
+        std::fs::create_dir_all("./test_data").unwrap();
+        let path = "./test_data/state.snap";
+        let written = source.write_snapshot_file(path).unwrap();
+        assert_eq!(written.entry_count, 2);
+
+        let target = Database::new("./test_data/snapshot_dst").unwrap();
+        let read = target.read_snapshot_file(path).unwrap();
+        assert_eq!(read, written);
+        assert_eq!(target.get(b"b", None).unwrap(), Some(vec![7u8; 1000]));
+
+        let bytes = std::fs::read(path).unwrap();
+        std::fs::write(path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(matches!(
+            target.read_snapshot_file(path),
+            Err(Error::Corruption(_))
+        ));
+    }
+}
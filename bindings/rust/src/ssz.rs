@@ -0,0 +1,223 @@
+//! SSZ 编码（`ssz` 特性）
+//! 证明和根哈希的 SimpleSerialize 编码与 `hash_tree_root`，供以太坊共识层工具直接传输和默克尔化。
+//! `Root` 即 `Bytes32`，编码为原样的32字节；证明对应以下容器：
+//!
+//! ```text
+//! class AmdbProof(Container):
+//!     flags: uint8                   # 同 Proof::to_bytes 的标志
+//!     root_hash: Bytes32
+//!     blob_threshold: uint64         # 未开启大值分离时为0
+//!     leaf_prefix: List[uint8, 255]
+//!     node_prefix: List[uint8, 255]
+//!     key_framing: uint8             # 同 Proof::to_bytes 的键编码
+//!     path: List[uint8, 2**24]       # 同 Proof::to_bytes 的路径
+//! ```
+//!
+//! 按SSZ规范，定长字段依次排列，变长字段在定长部分中以4字节（LE）的偏移代替，内容按字段顺序附在其后。
+
+use crate::merkle::HashScheme;
+use crate::proof::{FLAG_BLOBS, FLAG_CHECKSUMS};
+use crate::sha256::sha256;
+use crate::{Error, KeyFraming, Proof, Result, Root};
+
+const MAX_PREFIX_LEN: usize = 255;
+const MAX_PATH_LEN: usize = 1 << 24;
+/// 定长部分：flags、root_hash、blob_threshold、两个前缀的偏移、key_framing、path的偏移
+const FIXED_LEN: usize = 1 + 32 + 8 + 4 + 4 + 1 + 4;
+
+impl Root {
+    /// `Bytes32` 的SSZ编码
+    pub fn to_ssz(&self) -> [u8; 32] {
+        self.0
+    }
+
+    /// 长度不是32字节时返回 `Error::Corruption`
+    pub fn from_ssz(bytes: &[u8]) -> Result<Self> {
+        bytes
+            .try_into()
+            .map(Root)
+            .map_err(|_| Error::Corruption("invalid SSZ root length".to_string()))
+    }
+
+    /// `Bytes32` 的 `hash_tree_root` 就是其本身
+    pub fn hash_tree_root(&self) -> [u8; 32] {
+        self.0
+    }
+}
+
+impl Proof {
+    /// 路径超过 `List` 的上限（2^24 字节）时返回 `Error::InvalidArgument`
+    pub fn to_ssz(&self) -> Result<Vec<u8>> {
+        if self.path.len() > MAX_PATH_LEN {
+            return Err(Error::InvalidArgument(
+                "proof path exceeds the SSZ list limit".to_string(),
+            ));
+        }
+        let scheme = &self.scheme;
+        let variable = [&scheme.leaf_prefix, &scheme.node_prefix, &self.path];
+        let mut offsets = [0u32; 3];
+        let mut offset = FIXED_LEN;
+        for (slot, field) in offsets.iter_mut().zip(variable) {
+            *slot = offset as u32;
+            offset += field.len();
+        }
+
+        let mut bytes = Vec::with_capacity(offset);
+        bytes.push(self.flags());
+        bytes.extend_from_slice(&*self.root_hash);
+        bytes.extend_from_slice(&self.blob_threshold.unwrap_or(0).to_le_bytes());
+        bytes.extend_from_slice(&offsets[0].to_le_bytes());
+        bytes.extend_from_slice(&offsets[1].to_le_bytes());
+        bytes.push(scheme.key_framing.as_byte());
+        bytes.extend_from_slice(&offsets[2].to_le_bytes());
+        for field in variable {
+            bytes.extend_from_slice(field);
+        }
+        Ok(bytes)
+    }
+
+    /// 解析 `to_ssz` 的输出；偏移、长度或字段不合法时返回 `Error::Corruption`
+    pub fn from_ssz(bytes: &[u8]) -> Result<Self> {
+        let invalid = || Error::Corruption("invalid SSZ proof".to_string());
+        if bytes.len() < FIXED_LEN {
+            return Err(invalid());
+        }
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize;
+        let offsets = [u32_at(41), u32_at(45), u32_at(50), bytes.len()];
+        if offsets[0] != FIXED_LEN || offsets.windows(2).any(|pair| pair[0] > pair[1]) {
+            return Err(invalid());
+        }
+        let field = |i: usize| &bytes[offsets[i]..offsets[i + 1]];
+        let (leaf_prefix, node_prefix, path) = (field(0), field(1), field(2));
+        if leaf_prefix.len() > MAX_PREFIX_LEN
+            || node_prefix.len() > MAX_PREFIX_LEN
+            || path.len() > MAX_PATH_LEN
+        {
+            return Err(invalid());
+        }
+
+        let flags = bytes[0];
+        let threshold = u64::from_le_bytes(bytes[33..41].try_into().unwrap());
+        if flags & !(FLAG_CHECKSUMS | FLAG_BLOBS) != 0 || (flags & FLAG_BLOBS == 0 && threshold != 0)
+        {
+            return Err(invalid());
+        }
+        let scheme = HashScheme {
+            leaf_prefix: leaf_prefix.to_vec(),
+            node_prefix: node_prefix.to_vec(),
+            key_framing: KeyFraming::from_byte(bytes[49]).ok_or_else(invalid)?,
+        };
+        Proof::new(
+            flags & FLAG_CHECKSUMS != 0,
+            (flags & FLAG_BLOBS != 0).then_some(threshold),
+            Root(bytes[1..33].try_into().unwrap()),
+            scheme,
+            path.to_vec(),
+        )
+    }
+
+    /// `AmdbProof` 容器的 `hash_tree_root`
+    pub fn hash_tree_root(&self) -> [u8; 32] {
+        let scheme = &self.scheme;
+        let fields = [
+            uint_chunk(self.flags() as u64),
+            self.root_hash.0,
+            uint_chunk(self.blob_threshold.unwrap_or(0)),
+            byte_list_root(&scheme.leaf_prefix, MAX_PREFIX_LEN),
+            byte_list_root(&scheme.node_prefix, MAX_PREFIX_LEN),
+            uint_chunk(scheme.key_framing.as_byte() as u64),
+            byte_list_root(&self.path, MAX_PATH_LEN),
+        ];
+        merkleize(&fields, fields.len())
+    }
+}
+
+/// 无符号整数打包成的块：小端，右侧补零
+fn uint_chunk(value: u64) -> [u8; 32] {
+    let mut chunk = [0u8; 32];
+    chunk[..8].copy_from_slice(&value.to_le_bytes());
+    chunk
+}
+
+/// `List[uint8, limit]`：按最大块数默克尔化后混入长度
+fn byte_list_root(bytes: &[u8], limit: usize) -> [u8; 32] {
+    let chunks: Vec<[u8; 32]> = bytes
+        .chunks(32)
+        .map(|part| {
+            let mut chunk = [0u8; 32];
+            chunk[..part.len()].copy_from_slice(part);
+            chunk
+        })
+        .collect();
+    let root = merkleize(&chunks, limit.div_ceil(32));
+    sha256(&[&root, &uint_chunk(bytes.len() as u64)])
+}
+
+/// 以 `limit` 个叶子（补到2的幂）构成的树的根，缺少的叶子为全零块
+fn merkleize(chunks: &[[u8; 32]], limit: usize) -> [u8; 32] {
+    let mut layer = chunks.to_vec();
+    let mut zero = [0u8; 32];
+    for _ in 0..limit.next_power_of_two().trailing_zeros() {
+        if layer.len() % 2 == 1 {
+            layer.push(zero);
+        }
+        layer = layer
+            .chunks(2)
+            .map(|pair| sha256(&[&pair[0], &pair[1]]))
+            .collect();
+        zero = sha256(&[&zero, &zero]);
+    }
+    layer.first().copied().unwrap_or(zero)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+
+    #[test]
+    fn test_ssz_round_trip() {
+        let _ = std::fs::remove_dir_all("./test_data/ssz");
+        let db = Database::new("./test_data/ssz").unwrap();
+        db.put(b"a", b"1").unwrap();
+        db.put(b"b", b"2").unwrap();
+        let root = db.get_root_hash().unwrap();
+        let (_, proof) = db.get_with_proof(b"b", None).unwrap();
+
+        let bytes = proof.to_ssz().unwrap();
+        assert_eq!(
+            bytes.len(),
+            FIXED_LEN + proof.scheme.leaf_prefix.len() + proof.scheme.node_prefix.len() + proof.path.len()
+        );
+        let decoded = Proof::from_ssz(&bytes).unwrap();
+        assert_eq!(decoded, proof);
+        assert!(decoded.verify(&root, b"b", b"2"));
+        assert_eq!(decoded.hash_tree_root(), proof.hash_tree_root());
+        assert_eq!(Root::from_ssz(&root.to_ssz()).unwrap(), root);
+        assert_eq!(root.hash_tree_root(), root.0);
+
+        // 偏移越界、截断或多出未知标志都不能解析
+        let mut bad = bytes.clone();
+        bad[45..49].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Proof::from_ssz(&bad).is_err());
+        assert!(Proof::from_ssz(&bytes[..FIXED_LEN - 1]).is_err());
+        let mut bad = bytes.clone();
+        bad[0] |= 0x80;
+        assert!(Proof::from_ssz(&bad).is_err());
+        assert!(Root::from_ssz(&[0; 31]).is_err());
+    }
+
+    #[test]
+    fn test_merkleize() {
+        fn pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+            sha256(&[a, b])
+        }
+        let one = uint_chunk(1);
+        let zero = [0u8; 32];
+        assert_eq!(merkleize(&[one], 1), one);
+        assert_eq!(merkleize(&[], 4), pair(&pair(&zero, &zero), &pair(&zero, &zero)));
+        assert_eq!(merkleize(&[one, one, one], 3), pair(&pair(&one, &one), &pair(&one, &zero)));
+        // 空列表：零树的根混入长度0
+        assert_eq!(byte_list_root(b"", 64), pair(&pair(&zero, &zero), &zero));
+    }
+}
@@ -0,0 +1,192 @@
+//! 句柄生命周期
+//! 打开 → 中毒（引擎返回致命错误）或已关闭；离开打开状态后不再把句柄传回C侧
+//!
+//! 每次引擎调用期间（包括后台清理、扫描预取等后台线程的调用）都持有 `enter` 返回的守卫，
+//! `close` 等待这些调用结束后才释放句柄，因此可以与其他线程上进行中的调用并发。
+//! 守卫只是计数，同一线程可以嵌套持有。`close_by` 只等到时限，到期时仍有调用未结束则把句柄留给
+//! 其中最后结束的调用交回引擎。
+//!
+//! 引擎报告过后台错误后，之后产生的错误都包装为 `Error::Background`，附上该说明。
+
+use std::os::raw::c_int;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicU8, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock, PoisonError};
+use std::time::Instant;
+
+use crate::ffi::{amdb_close_with, AmdbHandle, AMDB_CLOSE_SYNC, AMDB_FATAL};
+use crate::{Error, Result};
+
+const OPEN: u8 = 0;
+const POISONED: u8 = 1;
+const CLOSED: u8 = 2;
+
+/// 句柄状态，由 `Database` 与其后台线程共享
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HandleState(Arc<Inner>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    state: AtomicU8,
+    /// 进行中的引擎调用数
+    calls: Mutex<usize>,
+    /// 调用数归零时通知等待关闭的线程
+    idle: Condvar,
+    /// 引擎报告的第一个后台错误
+    background: OnceLock<String>,
+    /// `close_by` 到期时尚未交回引擎的句柄，由最后结束的调用关闭
+    orphan: AtomicPtr<AmdbHandle>,
+}
+
+/// `HandleState::close_by` 的结果
+pub(crate) struct Drain<'a> {
+    /// 此前处于打开状态，句柄需要交回引擎
+    pub(crate) open: bool,
+    /// 到期时仍在进行的调用数
+    pub(crate) outstanding: usize,
+    /// `outstanding` 不为0时关闭方自己的调用；它与进行中的调用里最后结束的一个关闭句柄
+    pub(crate) guard: Option<CallGuard<'a>>,
+}
+
+/// 一次进行中的引擎调用，见 `HandleState::enter`
+pub(crate) struct CallGuard<'a>(&'a Inner);
+
+impl Drop for CallGuard<'_> {
+    fn drop(&mut self) {
+        let orphan = {
+            let mut calls = self.0.calls.lock().unwrap_or_else(PoisonError::into_inner);
+            *calls -= 1;
+            if *calls > 0 {
+                return;
+            }
+            self.0.idle.notify_all();
+            self.0.orphan.swap(ptr::null_mut(), Ordering::SeqCst)
+        };
+        if orphan.is_null() {
+            return;
+        }
+        let status = unsafe { amdb_close_with(orphan, AMDB_CLOSE_SYNC) };
+        report_close_error("shutdown", status);
+    }
+}
+
+/// 析构或延迟关闭时无法返回给调用方的关闭错误，计入 `metrics` 计数 `amdb_close_errors_total`
+/// （`metrics` 特性），标签 `stage` 为关闭发生的位置、`status` 为引擎状态码
+pub(crate) fn report_close_error(stage: &'static str, status: c_int) {
+    if status == 0 {
+        return;
+    }
+    #[cfg(feature = "metrics")]
+    metrics::counter!(
+        "amdb_close_errors_total",
+        "stage" => stage,
+        "status" => status.to_string()
+    )
+    .increment(1);
+    #[cfg(not(feature = "metrics"))]
+    let _ = stage;
+}
+
+impl HandleState {
+    pub(crate) fn is_poisoned(&self) -> bool {
+        self.0.state.load(Ordering::SeqCst) == POISONED
+    }
+
+    /// 不处于打开状态时返回 `Error::Poisoned` 或 `Error::Closed`
+    pub(crate) fn check(&self) -> Result<()> {
+        match self.0.state.load(Ordering::SeqCst) {
+            OPEN => Ok(()),
+            POISONED => Err(self.with_background(Error::Poisoned)),
+            _ => Err(self.with_background(Error::Closed)),
+        }
+    }
+
+    /// 检查状态并返回守卫；守卫存活期间句柄不会被关闭
+    pub(crate) fn enter(&self) -> Result<CallGuard<'_>> {
+        let mut calls = self.0.calls.lock().unwrap_or_else(PoisonError::into_inner);
+        self.check()?;
+        *calls += 1;
+        Ok(CallGuard(&self.0))
+    }
+
+    /// 把非零状态码转换为错误；致命错误时转入中毒状态
+    pub(crate) fn error(&self, status: c_int) -> Error {
+        if status == AMDB_FATAL {
+            let _ =
+                self.0
+                    .state
+                    .compare_exchange(OPEN, POISONED, Ordering::SeqCst, Ordering::SeqCst);
+        }
+        self.with_background(Error::from_status(status))
+    }
+
+    /// 记录引擎报告的后台错误；只保留第一个
+    pub(crate) fn set_background_error(&self, message: String) {
+        let _ = self.0.background.set(message);
+    }
+
+    pub(crate) fn background_error(&self) -> Option<&str> {
+        self.0.background.get().map(String::as_str)
+    }
+
+    /// 出现过后台错误时把 `error` 包装为 `Error::Background`
+    fn with_background(&self, error: Error) -> Error {
+        match self.background_error() {
+            Some(message) => Error::Background {
+                error: Box::new(error),
+                message: message.to_string(),
+            },
+            None => error,
+        }
+    }
+
+    /// 转入已关闭状态并等待进行中的调用结束；返回句柄是否需要交回引擎关闭
+    /// （此前处于打开状态），已关闭时返回 `Error::Closed`。调用方不能持有守卫
+    pub(crate) fn close(&self) -> Result<bool> {
+        let calls = self.0.calls.lock().unwrap_or_else(PoisonError::into_inner);
+        let previous = self.0.state.swap(CLOSED, Ordering::SeqCst);
+        if previous == CLOSED {
+            return Err(Error::Closed);
+        }
+        let _idle = self
+            .0
+            .idle
+            .wait_while(calls, |calls| *calls > 0)
+            .unwrap_or_else(PoisonError::into_inner);
+        Ok(previous == OPEN)
+    }
+
+    /// 同 `close`，但最多等到 `deadline`。到期时仍有调用未结束则登记一个关闭方自己的调用并返回，
+    /// 此前处于打开状态时 `handle` 由这些调用中最后结束的一个刷新并关闭
+    pub(crate) fn close_by(&self, deadline: Instant, handle: *mut AmdbHandle) -> Result<Drain<'_>> {
+        let calls = self.0.calls.lock().unwrap_or_else(PoisonError::into_inner);
+        let previous = self.0.state.swap(CLOSED, Ordering::SeqCst);
+        if previous == CLOSED {
+            return Err(Error::Closed);
+        }
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        let (mut calls, _) = self
+            .0
+            .idle
+            .wait_timeout_while(calls, timeout, |calls| *calls > 0)
+            .unwrap_or_else(PoisonError::into_inner);
+        let open = previous == OPEN;
+        let outstanding = *calls;
+        if outstanding == 0 {
+            return Ok(Drain {
+                open,
+                outstanding,
+                guard: None,
+            });
+        }
+        *calls += 1;
+        if open {
+            self.0.orphan.store(handle, Ordering::SeqCst);
+        }
+        Ok(Drain {
+            open,
+            outstanding,
+            guard: Some(CallGuard(&self.0)),
+        })
+    }
+}
@@ -0,0 +1,652 @@
+//! 引擎统计
+//! `Database::stats` 汇总键数、磁盘占用、缓存命中和写放大等健康指标；
+//! `Database::read_stats` 给出读取和证明的读放大直方图，用于比较不同键分布的树深度；
+//! `metrics` 特性下 `Stats::record_metrics` 把它们发布到 `metrics` 门面，供 Prometheus 等导出器采集。
+//! `Database::metrics_snapshot` 一次取得全部统计；`serde` 特性下各统计类型可序列化，便于按需导出诊断用的JSON。
+
+use std::collections::BTreeMap;
+use std::ffi::CStr;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::time::Duration;
+
+pub use amdb_sys::{IoCounters, IoStats};
+
+use crate::ProofCacheStats;
+
+use crate::{
+    amdb_compact, amdb_free_compaction_stats, amdb_free_results, amdb_get_compaction_stats,
+    amdb_get_io_stats, amdb_get_pending_bytes, amdb_get_read_stats, amdb_get_stats,
+    amdb_open_report, result_bytes, AmdbCommitStats, AmdbCompactResult, AmdbCompactionStats,
+    AmdbHistogram, AmdbOpenReport, AmdbReadStats, AmdbResult, AmdbStats, Database, Result, Version,
+};
+
+/// 数据库健康统计，见 `Database::stats`；计数类字段为引擎进程内的累计值，重新打开后清零
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct Stats {
+    /// 有效（未删除）的键数
+    pub key_count: u64,
+    /// 数据库版本，见 `Database::state_version`
+    pub state_version: Version,
+    /// 内存中的Merkle节点数
+    pub merkle_nodes: u64,
+    /// 数据目录占用的字节数
+    pub disk_bytes: u64,
+    /// LSM树的数据文件数
+    pub sstable_count: u64,
+    /// 尚未刷新到磁盘的MemTable字节数
+    pub pending_bytes: u64,
+    /// 写入的键值字节数
+    pub bytes_ingested: u64,
+    /// 刷新写入磁盘的字节数
+    pub bytes_flushed: u64,
+    /// 压缩写入磁盘的字节数
+    pub bytes_compacted: u64,
+    pub compactions: u64,
+    /// B+树节点缓存的命中次数
+    pub cache_hits: u64,
+    /// B+树节点缓存未命中、从磁盘加载的次数
+    pub cache_misses: u64,
+}
+
+impl Stats {
+    fn from_raw(raw: &AmdbStats) -> Self {
+        Stats {
+            key_count: raw.key_count,
+            state_version: Version(raw.state_version),
+            merkle_nodes: raw.merkle_nodes,
+            disk_bytes: raw.disk_bytes,
+            sstable_count: raw.sstable_count,
+            pending_bytes: raw.pending_bytes,
+            bytes_ingested: raw.bytes_ingested,
+            bytes_flushed: raw.bytes_flushed,
+            bytes_compacted: raw.bytes_compacted,
+            compactions: raw.compactions,
+            cache_hits: raw.cache_hits,
+            cache_misses: raw.cache_misses,
+        }
+    }
+
+    /// 节点缓存命中率；尚无访问时为0
+    pub fn cache_hit_rate(&self) -> f64 {
+        let lookups = self.cache_hits + self.cache_misses;
+        if lookups == 0 {
+            return 0.0;
+        }
+        self.cache_hits as f64 / lookups as f64
+    }
+
+    /// 写放大，同 `CompactionStats::write_amplification`
+    pub fn write_amplification(&self) -> f64 {
+        if self.bytes_ingested == 0 {
+            return 0.0;
+        }
+        (self.bytes_flushed + self.bytes_compacted) as f64 / self.bytes_ingested as f64
+    }
+
+    /// 发布到 `metrics` 门面（`metrics` 特性）：规模类字段为 gauge，累计计数为 counter，
+    /// 名称以 `amdb_` 开头；同一进程打开多个数据库时用 `labels` 区分
+    #[cfg(feature = "metrics")]
+    pub fn record_metrics(&self, labels: &[metrics::Label]) {
+        let gauges = [
+            ("amdb_keys", self.key_count),
+            ("amdb_state_version", self.state_version.0),
+            ("amdb_merkle_nodes", self.merkle_nodes),
+            ("amdb_disk_bytes", self.disk_bytes),
+            ("amdb_sstables", self.sstable_count),
+            ("amdb_pending_bytes", self.pending_bytes),
+        ];
+        for (name, value) in gauges {
+            metrics::gauge!(name, labels.to_vec()).set(value as f64);
+        }
+        metrics::gauge!("amdb_write_amplification", labels.to_vec())
+            .set(self.write_amplification());
+        let counters = [
+            ("amdb_bytes_ingested_total", self.bytes_ingested),
+            ("amdb_bytes_flushed_total", self.bytes_flushed),
+            ("amdb_bytes_compacted_total", self.bytes_compacted),
+            ("amdb_compactions_total", self.compactions),
+            ("amdb_cache_hits_total", self.cache_hits),
+            ("amdb_cache_misses_total", self.cache_misses),
+        ];
+        for (name, value) in counters {
+            metrics::counter!(name, labels.to_vec()).absolute(value);
+        }
+    }
+}
+
+/// 单个数据文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct FileStats {
+    /// 0层为MemTable刷新产生的文件，1层为压缩合并产生的文件
+    pub level: u32,
+    /// 文件名（相对LSM目录）
+    pub name: String,
+    pub bytes: u64,
+}
+
+/// 一层的汇总
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelStats {
+    pub level: u32,
+    pub file_count: usize,
+    pub bytes: u64,
+}
+
+/// 刷新与压缩统计；字节计数为引擎进程内的累计值，重新打开后清零
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct CompactionStats {
+    /// 尚未刷新到磁盘的MemTable字节数
+    pub pending_bytes: u64,
+    /// 写入的键值字节数
+    pub bytes_ingested: u64,
+    /// 刷新写入磁盘的字节数
+    pub bytes_flushed: u64,
+    /// 压缩写入磁盘的字节数
+    pub bytes_compacted: u64,
+    pub compactions: u64,
+    /// 最近几次压缩的耗时，由旧到新
+    pub recent_compactions: Vec<Duration>,
+    pub files: Vec<FileStats>,
+}
+
+impl CompactionStats {
+    /// 按层汇总文件，层号升序
+    pub fn levels(&self) -> Vec<LevelStats> {
+        let mut levels = BTreeMap::new();
+        for file in &self.files {
+            let level = levels.entry(file.level).or_insert(LevelStats {
+                level: file.level,
+                file_count: 0,
+                bytes: 0,
+            });
+            level.file_count += 1;
+            level.bytes += file.bytes;
+        }
+        levels.into_values().collect()
+    }
+
+    /// 写放大：刷新与压缩写入磁盘的字节数除以写入的键值字节数；尚无写入时为0
+    pub fn write_amplification(&self) -> f64 {
+        if self.bytes_ingested == 0 {
+            return 0.0;
+        }
+        (self.bytes_flushed + self.bytes_compacted) as f64 / self.bytes_ingested as f64
+    }
+}
+
+/// 非负整数观测值的直方图：`buckets[i]` 为等于 `i` 的观测次数，最后一个桶计入不小于其下标的观测值
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct Histogram {
+    pub count: u64,
+    pub sum: u64,
+    pub max: u64,
+    pub buckets: Vec<u64>,
+}
+
+impl Histogram {
+    fn from_raw(raw: &AmdbHistogram) -> Self {
+        Histogram {
+            count: raw.count,
+            sum: raw.sum,
+            max: raw.max,
+            buckets: raw.buckets.to_vec(),
+        }
+    }
+
+    /// 平均值；尚无观测时为0
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        self.sum as f64 / self.count as f64
+    }
+
+    /// 不小于 `q`（0到1）比例的观测值都不超过的最小值；落在最后一个桶时为该桶的下标，尚无观测时为0
+    pub fn quantile(&self, q: f64) -> u64 {
+        let target = (q.clamp(0.0, 1.0) * self.count as f64).ceil() as u64;
+        let mut seen = 0;
+        for (value, &n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= target.max(1) {
+                return value as u64;
+            }
+        }
+        0
+    }
+}
+
+/// 读放大统计，见 `Database::read_stats`；进程内累计，重新打开后清零
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct ReadStats {
+    /// 每次读取依次查找过的数据结构数：引擎先查版本索引，未命中时再查B+树、MemTable和SSTable
+    pub get: Histogram,
+    /// 每个证明从根到叶子访问的Merkle节点数（含叶子）
+    pub proof: Histogram,
+}
+
+/// 某一时刻的全部统计，见 `Database::metrics_snapshot`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct MetricsSnapshot {
+    pub stats: Stats,
+    pub compaction: CompactionStats,
+    /// 不支持I/O统计的平台上为 `None`，见 `Database::io_stats`
+    pub io: Option<IoStats>,
+    /// 没有开启 `OpenOptions::proof_cache` 时为 `None`
+    pub proof_cache: Option<ProofCacheStats>,
+    pub read: ReadStats,
+}
+
+/// 一次批量提交的统计，见 `Database::write_batch_with_stats`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CommitStats {
+    /// 提交前不存在的键
+    pub inserted: u64,
+    /// 提交前已存在的键
+    pub updated: u64,
+    /// 删除的已存在的键，删除不存在的键不计入
+    pub deleted: u64,
+    /// 写入引擎的键值字节数（含值校验和等封装）
+    pub bytes_written: u64,
+    /// 新产生的Merkle节点数
+    pub nodes_touched: u64,
+    /// 计算根哈希的耗时
+    pub hash_time: Duration,
+    /// 写入存储、WAL及同步的耗时
+    pub io_time: Duration,
+}
+
+impl CommitStats {
+    pub(crate) fn from_raw(raw: &AmdbCommitStats) -> Self {
+        CommitStats {
+            inserted: raw.inserted,
+            updated: raw.updated,
+            deleted: raw.deleted,
+            bytes_written: raw.bytes_written,
+            nodes_touched: raw.nodes_touched,
+            hash_time: Duration::try_from_secs_f64(raw.hash_secs).unwrap_or_default(),
+            io_time: Duration::try_from_secs_f64(raw.io_secs).unwrap_or_default(),
+        }
+    }
+}
+
+/// 一次手动合并的结果，见 `Database::compact`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompactionReport {
+    /// 合并前数据目录的字节数，此时全部写入已持久化
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    /// 合并SSTable的次数
+    pub merges: u64,
+}
+
+impl CompactionReport {
+    /// 合并释放的磁盘空间
+    pub fn reclaimed(&self) -> u64 {
+        self.bytes_before.saturating_sub(self.bytes_after)
+    }
+}
+
+/// 打开数据库时恢复过程的报告，见 `Database::open_report`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpenReport {
+    /// 扫描到的完整WAL记录数；状态以版本记录为准，打开时不重放WAL，只检查其完整性
+    pub wal_records: u64,
+    /// 从磁盘恢复的数据库版本数
+    pub versions_recovered: u64,
+    /// 恢复的键版本数
+    pub key_versions: u64,
+    /// 引擎打开数据目录的耗时
+    pub duration: Duration,
+    /// 加载时跳过的内容、WAL末尾不完整的记录、根哈希与最后一次提交不符等问题
+    pub warnings: Vec<String>,
+}
+
+impl OpenReport {
+    /// 没有任何警告
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
+
+impl Database {
+    /// 读取健康统计；需要遍历全部键和数据目录，开销与数据量成正比，适合按分钟级间隔采集
+    pub fn stats(&self) -> Result<Stats> {
+        let mut raw = AmdbStats::default();
+        let status = unsafe { amdb_get_stats(*self.live_handle()?, &mut raw) };
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        Ok(Stats::from_raw(&raw))
+    }
+
+    /// 读取健康统计并发布到 `metrics` 门面（`metrics` 特性），见 `Stats::record_metrics`
+    #[cfg(feature = "metrics")]
+    pub fn record_metrics(&self, labels: &[metrics::Label]) -> Result<Stats> {
+        let stats = self.stats()?;
+        stats.record_metrics(labels);
+        Ok(stats)
+    }
+
+    /// 打开时恢复过程的报告，可在启动时检查而不必解析日志；同一句柄每次返回相同的内容
+    pub fn open_report(&self) -> Result<OpenReport> {
+        let mut raw = AmdbOpenReport::default();
+        let (mut results, mut count) = (ptr::null_mut::<AmdbResult>(), 0);
+        let handle = self.live_handle()?;
+        let status = unsafe { amdb_open_report(*handle, &mut raw, &mut results, &mut count) };
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        let warnings = if results.is_null() {
+            Vec::new()
+        } else {
+            let warnings = unsafe { std::slice::from_raw_parts(results, count) }
+                .iter()
+                .map(|result| String::from_utf8_lossy(&result_bytes(result)).into_owned())
+                .collect();
+            unsafe { amdb_free_results(results, count) };
+            warnings
+        };
+        Ok(OpenReport {
+            wal_records: raw.wal_records,
+            versions_recovered: raw.versions_recovered,
+            key_versions: raw.key_versions,
+            duration: Duration::try_from_secs_f64(raw.open_secs).unwrap_or_default(),
+            warnings,
+        })
+    }
+
+    /// 尚未刷新到磁盘的写入占用的内存字节数，可据此对上游生产者施加背压
+    ///
+    /// 与 `CompactionStats::pending_bytes` 相同，但不列出数据文件，适合频繁调用。
+    pub fn pending_bytes(&self) -> Result<u64> {
+        let mut bytes = 0;
+        let status = unsafe { amdb_get_pending_bytes(*self.live_handle()?, &mut bytes) };
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        Ok(bytes)
+    }
+
+    /// 读取进程内的I/O统计，所有打开的数据库共用一份计数
+    ///
+    /// 仅支持Linux，其他平台返回错误。
+    pub fn io_stats(&self) -> Result<IoStats> {
+        let mut stats = IoStats::default();
+        let status = unsafe { amdb_get_io_stats(*self.live_handle()?, &mut stats) };
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        Ok(stats)
+    }
+
+    /// 读放大直方图：每次读取查找的数据结构数和每个证明访问的Merkle节点数。
+    /// 证明的节点数取决于键在树中的分布：带长公共前缀的原始键路径明显长于哈希后的键。
+    /// 命中证明缓存的请求不经过引擎，不计入
+    pub fn read_stats(&self) -> Result<ReadStats> {
+        let mut raw = MaybeUninit::<AmdbReadStats>::zeroed();
+        let handle = self.live_handle()?;
+        let status = unsafe { amdb_get_read_stats(*handle, raw.as_mut_ptr()) };
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        let raw = unsafe { raw.assume_init() };
+        Ok(ReadStats {
+            get: Histogram::from_raw(&raw.get),
+            proof: Histogram::from_raw(&raw.proof),
+        })
+    }
+
+    /// 一次取得 `stats`、`compaction_stats`、`io_stats`、`read_stats` 和证明缓存的统计；开销同 `stats`
+    pub fn metrics_snapshot(&self) -> Result<MetricsSnapshot> {
+        Ok(MetricsSnapshot {
+            stats: self.stats()?,
+            compaction: self.compaction_stats()?,
+            io: self.io_stats().ok(),
+            proof_cache: self.proof_cache_stats(),
+            read: self.read_stats()?,
+        })
+    }
+
+    /// 持久化全部写入并合并LSM树的SSTable（合并结果不超过 `OpenOptions::target_file_size`），返回合并前后的磁盘占用；
+    /// 持久化时重写版本文件，`prune_versions_before` 清理的版本随之从磁盘删除。分片存储只持久化不合并
+    pub fn compact(&self) -> Result<CompactionReport> {
+        let mut raw = AmdbCompactResult::default();
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe { amdb_compact(*handle, &mut raw) });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        Ok(CompactionReport {
+            bytes_before: raw.bytes_before,
+            bytes_after: raw.bytes_after,
+            merges: raw.merges,
+        })
+    }
+
+    /// 读取刷新与压缩统计
+    pub fn compaction_stats(&self) -> Result<CompactionStats> {
+        let mut raw = MaybeUninit::<AmdbCompactionStats>::zeroed();
+        let handle = self.live_handle()?;
+        let status = unsafe { amdb_get_compaction_stats(*handle, raw.as_mut_ptr()) };
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        let mut raw = unsafe { raw.assume_init() };
+
+        let files = if raw.files.is_null() {
+            Vec::new()
+        } else {
+            unsafe { std::slice::from_raw_parts(raw.files, raw.file_count) }
+                .iter()
+                .map(|file| FileStats {
+                    level: file.level,
+                    name: unsafe { CStr::from_ptr(file.name) }
+                        .to_string_lossy()
+                        .into_owned(),
+                    bytes: file.bytes,
+                })
+                .collect()
+        };
+        let recent = raw.recent_count.min(raw.recent_compaction_secs.len());
+        let stats = CompactionStats {
+            pending_bytes: raw.pending_bytes,
+            bytes_ingested: raw.bytes_ingested,
+            bytes_flushed: raw.bytes_flushed,
+            bytes_compacted: raw.bytes_compacted,
+            compactions: raw.compactions,
+            recent_compactions: raw.recent_compaction_secs[..recent]
+                .iter()
+                .map(|&secs| Duration::try_from_secs_f64(secs).unwrap_or_default())
+                .collect(),
+            files,
+        };
+        unsafe { amdb_free_compaction_stats(&mut raw) };
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compaction_stats() {
+        let _ = std::fs::remove_dir_all("./test_data/compaction_stats");
+        let db = Database::new("./test_data/compaction_stats").unwrap();
+        let before = db.compaction_stats().unwrap();
+        db.put(b"stats-key", b"stats-value").unwrap();
+        let after = db.compaction_stats().unwrap();
+        assert!(after.bytes_ingested >= before.bytes_ingested + 20);
+        assert!(after.pending_bytes > 0);
+        assert!(db.pending_bytes().unwrap() >= after.pending_bytes);
+
+        let stats = CompactionStats {
+            pending_bytes: 0,
+            bytes_ingested: 100,
+            bytes_flushed: 120,
+            bytes_compacted: 80,
+            compactions: 1,
+            recent_compactions: vec![Duration::from_millis(5)],
+            files: vec![
+                FileStats {
+                    level: 1,
+                    name: "c".into(),
+                    bytes: 80,
+                },
+                FileStats {
+                    level: 0,
+                    name: "a".into(),
+                    bytes: 70,
+                },
+                FileStats {
+                    level: 0,
+                    name: "b".into(),
+                    bytes: 50,
+                },
+            ],
+        };
+        let levels = stats.levels();
+        assert_eq!(levels.len(), 2);
+        assert_eq!(
+            (levels[0].level, levels[0].file_count, levels[0].bytes),
+            (0, 2, 120)
+        );
+        assert_eq!(stats.write_amplification(), 2.0);
+    }
+
+    #[test]
+    fn test_stats() {
+        let _ = std::fs::remove_dir_all("./test_data/stats");
+        let db = Database::new("./test_data/stats").unwrap();
+        let before = db.stats().unwrap();
+        db.put(b"a", b"1").unwrap();
+        db.put(b"b", b"2").unwrap();
+        db.delete(b"a").unwrap();
+        let after = db.stats().unwrap();
+        assert_eq!(after.key_count, before.key_count + 1);
+        assert_eq!(after.state_version.0, before.state_version.0 + 3);
+        assert!(after.merkle_nodes > 0 && after.disk_bytes > 0);
+        assert!(after.bytes_ingested > before.bytes_ingested);
+
+        let stats = Stats {
+            cache_hits: 3,
+            cache_misses: 1,
+            bytes_ingested: 10,
+            bytes_flushed: 15,
+            ..Stats::default()
+        };
+        assert_eq!(stats.cache_hit_rate(), 0.75);
+        assert_eq!(stats.write_amplification(), 1.5);
+        assert_eq!(Stats::default().cache_hit_rate(), 0.0);
+    }
+
+    #[test]
+    fn test_metrics_snapshot() {
+        let _ = std::fs::remove_dir_all("./test_data/metrics_snapshot");
+        let db = Database::new("./test_data/metrics_snapshot").unwrap();
+        db.put(b"a", b"1").unwrap();
+        let snapshot = db.metrics_snapshot().unwrap();
+        assert_eq!(snapshot.stats.key_count, 1);
+        assert_eq!(
+            snapshot.compaction.pending_bytes,
+            snapshot.stats.pending_bytes
+        );
+        assert!(snapshot.proof_cache.is_none());
+    }
+
+    #[test]
+    fn test_read_stats() {
+        let _ = std::fs::remove_dir_all("./test_data/read_stats");
+        let db = Database::new("./test_data/read_stats").unwrap();
+        // 长公共前缀的原始键与哈希分布的键
+        for i in 0..64u8 {
+            db.put(&[&b"user/0000/"[..], &[i]].concat(), b"v").unwrap();
+            db.put(&[i.wrapping_mul(37), i], b"v").unwrap();
+        }
+        for i in 0..64u8 {
+            db.get(&[&b"user/0000/"[..], &[i]].concat(), None).unwrap();
+        }
+        let stats = db.read_stats().unwrap();
+        assert_eq!(stats.get.count, 64);
+        assert_eq!(stats.get.buckets.iter().sum::<u64>(), 64);
+        assert!(stats.get.quantile(0.5) >= 1);
+
+        db.get_with_proof(b"user/0000/\x00", None).unwrap();
+        let prefixed = db.read_stats().unwrap().proof;
+        db.get_with_proof(&[37, 1], None).unwrap();
+        let spread = db.read_stats().unwrap().proof;
+        assert_eq!((prefixed.count, spread.count), (1, 2));
+        // 公共前缀每个nibble都是路径上的一个节点
+        assert!(prefixed.max >= 20);
+        assert!(spread.sum - prefixed.sum < prefixed.sum);
+        assert_eq!(prefixed.quantile(1.0), prefixed.max);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_metrics_snapshot_json() {
+        let _ = std::fs::remove_dir_all("./test_data/metrics_snapshot_json");
+        let db = Database::new("./test_data/metrics_snapshot_json").unwrap();
+        db.put(b"a", b"1").unwrap();
+        let json: ::serde_json::Value =
+            ::serde_json::to_value(db.metrics_snapshot().unwrap()).unwrap();
+        assert_eq!(json["stats"]["key_count"], 1);
+        assert!(json["compaction"]["files"].is_array());
+    }
+
+    #[test]
+    fn test_io_stats() {
+        let _ = std::fs::remove_dir_all("./test_data/io_stats_observer");
+        let _ = std::fs::remove_dir_all("./test_data/io_stats");
+        let observer = Database::new("./test_data/io_stats_observer").unwrap();
+        let before = observer.io_stats().unwrap();
+        {
+            let db = Database::new("./test_data/io_stats").unwrap();
+            db.put(b"io-key", &[7; 4096]).unwrap();
+        }
+        let after = observer.io_stats().unwrap();
+
+        // 关闭时的刷新在调用线程上进行，计入前台
+        assert!(after.foreground.bytes_written >= before.foreground.bytes_written + 4096);
+        assert!(after.foreground.write_ops > before.foreground.write_ops);
+    }
+
+    #[test]
+    fn test_open_report() {
+        let _ = std::fs::remove_dir_all("./test_data/open_report");
+        let dir = "./test_data/open_report";
+        let db = Database::new(dir).unwrap();
+        assert_eq!(db.open_report().unwrap().versions_recovered, 0);
+        db.put(b"a", b"1").unwrap();
+        db.put(b"b", b"2").unwrap();
+        drop(db);
+
+        let db = Database::new(dir).unwrap();
+        let report = db.open_report().unwrap();
+        assert!(report.is_clean(), "{:?}", report.warnings);
+        assert_eq!((report.versions_recovered, report.key_versions), (2, 2));
+        assert!(report.wal_records >= 2);
+        drop(db);
+
+        // WAL末尾不完整的记录给出警告
+        let wal = std::fs::read_dir(format!("{}/wal", dir))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .max()
+            .unwrap();
+        let mut bytes = std::fs::read(&wal).unwrap();
+        bytes.extend_from_slice(&[1, 0, 0]);
+        std::fs::write(&wal, bytes).unwrap();
+        let db = Database::new(dir).unwrap();
+        let report = db.open_report().unwrap();
+        assert_eq!(report.versions_recovered, 2);
+        assert!(report.warnings.iter().any(|w| w.contains("incomplete")));
+    }
+}
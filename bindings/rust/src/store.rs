@@ -0,0 +1,133 @@
+//! 只读接口
+//! `ReadStore` 由 `Database`、`Keyspace`、`HistoricalView` 和 `Fallback` 实现（引用同样实现），应用的读取路径可以只针对它编写一次，
+//! 再分别用于最新状态、键空间或历史时刻
+//!
+//! 各类型的同名固有方法（例如 `Database::get` 的版本参数）不受影响；通过泛型约束调用时使用这里的签名。
+
+use std::ops::RangeBounds;
+use std::vec;
+
+use crate::{Database, HistoricalView, KeyValue, Keyspace, Result, Scan};
+
+pub trait ReadStore {
+    /// 范围扫描的迭代器
+    type Scan<'a>: Iterator<Item = Result<KeyValue>>
+    where
+        Self: 'a;
+
+    /// 键的当前值；不存在或已删除时返回 `None`
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    /// 键是否存在
+    fn contains(&self, key: &[u8]) -> Result<bool> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// 按键升序扫描范围内的键值对
+    fn scan(&self, range: impl RangeBounds<Vec<u8>>) -> Self::Scan<'_>;
+}
+
+impl<T: ReadStore + ?Sized> ReadStore for &T {
+    type Scan<'a>
+        = T::Scan<'a>
+    where
+        Self: 'a;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        (**self).get(key)
+    }
+
+    fn contains(&self, key: &[u8]) -> Result<bool> {
+        (**self).contains(key)
+    }
+
+    fn scan(&self, range: impl RangeBounds<Vec<u8>>) -> Self::Scan<'_> {
+        (**self).scan(range)
+    }
+}
+
+impl ReadStore for Database {
+    type Scan<'a> = Scan<'a>;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Database::get(self, key, None)
+    }
+
+    /// 不跨FFI传输值，见 `multi_contains`
+    fn contains(&self, key: &[u8]) -> Result<bool> {
+        Ok(self.multi_contains(&[key])?.get(0) == Some(true))
+    }
+
+    fn scan(&self, range: impl RangeBounds<Vec<u8>>) -> Scan<'_> {
+        Database::scan(self, range)
+    }
+}
+
+impl ReadStore for Keyspace<'_> {
+    type Scan<'a>
+        = Scan<'a>
+    where
+        Self: 'a;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Keyspace::get(self, key, None)
+    }
+
+    fn scan(&self, range: impl RangeBounds<Vec<u8>>) -> Scan<'_> {
+        Keyspace::scan(self, range)
+    }
+}
+
+impl ReadStore for HistoricalView<'_> {
+    /// 视图一次读出整个范围，查询错误作为唯一一项返回
+    type Scan<'a>
+        = vec::IntoIter<Result<KeyValue>>
+    where
+        Self: 'a;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        HistoricalView::get(self, key)
+    }
+
+    fn scan(&self, range: impl RangeBounds<Vec<u8>>) -> Self::Scan<'_> {
+        match HistoricalView::scan(self, range) {
+            Ok(entries) => entries.into_iter().map(Ok).collect::<Vec<_>>().into_iter(),
+            Err(e) => vec![Err(e)].into_iter(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::*;
+
+    /// 只依赖 `ReadStore` 的读取逻辑
+    fn total(store: &impl ReadStore) -> usize {
+        store.scan(..).map(|entry| entry.unwrap().1.len()).sum()
+    }
+
+    #[test]
+    fn test_read_store_impls() {
+        let _ = std::fs::remove_dir_all("./test_data/read_store");
+        let db = Database::new("./test_data/read_store").unwrap();
+        db.put(b"acc/a", b"12").unwrap();
+        db.put(b"acc/b", b"345").unwrap();
+        db.put(b"other", b"6").unwrap();
+        let view = db.view_as_of(SystemTime::now()).unwrap();
+        db.delete(b"acc/b").unwrap();
+
+        assert_eq!(total(&db), 3);
+        assert_eq!(total(&db.keyspace(b"acc/")), 2);
+        assert_eq!(total(&view), 6);
+
+        assert!(ReadStore::contains(&db, b"other").unwrap());
+        assert!(!ReadStore::contains(&db, b"acc/b").unwrap());
+        assert!(ReadStore::contains(&view, b"acc/b").unwrap());
+        assert_eq!(
+            ReadStore::get(&db.keyspace(b"acc/"), b"a").unwrap(),
+            Some(b"12".to_vec())
+        );
+    }
+}
@@ -0,0 +1,362 @@
+//! 变更订阅
+//! 后台线程跟随提交记录，把每次提交中以给定前缀开头的键的变更按提交顺序、同一提交内按键排序发送到有界通道，
+//! 用于失效缓存或向客户端推送更新。任何句柄或进程经由本数据库目录的提交都会被看到。
+//!
+//! 通道满时跟随线程等待消费者，不阻塞写入；落后过多以致所需版本已被清理时收到 `Error::NotFound`，
+//! 跳过这些版本后继续。`Subscription` 析构即取消订阅。
+
+use std::marker::PhantomData;
+use std::ptr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::blob::BlobStore;
+use crate::{
+    amdb_commit_changes, amdb_free_results, amdb_get_state_version, amdb_set_background_thread,
+    envelope, result_bytes, result_value, AmdbResult, Database, Error, HandleState, Result, Root,
+    SendHandle, Version,
+};
+
+/// 订阅的选项
+#[derive(Debug, Clone)]
+pub struct SubscribeOptions {
+    capacity: usize,
+    poll_interval: Duration,
+}
+
+impl Default for SubscribeOptions {
+    fn default() -> Self {
+        SubscribeOptions {
+            capacity: 1024,
+            poll_interval: Duration::from_millis(10),
+        }
+    }
+}
+
+impl SubscribeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 通道中最多缓存的事件数（默认1024），满时跟随线程等待消费者
+    pub fn capacity(&mut self, events: usize) -> &mut Self {
+        self.capacity = events;
+        self
+    }
+
+    /// 检查新提交的间隔（默认10毫秒）
+    pub fn poll_interval(&mut self, interval: Duration) -> &mut Self {
+        self.poll_interval = interval;
+        self
+    }
+}
+
+/// 一个键在一次提交中的变更
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeEvent {
+    pub key: Vec<u8>,
+    /// 提交前的值；此前不存在或已删除时为 `None`
+    pub old_value: Option<Vec<u8>>,
+    /// 提交后的值；删除时为 `None`
+    pub new_value: Option<Vec<u8>>,
+    /// 该提交的数据库版本
+    pub version: Version,
+    /// 该提交后的根哈希
+    pub root_hash: Root,
+}
+
+/// 运行中的订阅；`unsubscribe` 或析构时停止并等待线程退出
+pub struct Subscription<'a> {
+    /// 借用 `Database`，保证线程在句柄关闭前退出
+    _db: PhantomData<&'a Database>,
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+    events: Receiver<Result<ChangeEvent>>,
+}
+
+impl Subscription<'_> {
+    /// 订阅之后的变更；数据库已关闭或中毒时收到该错误后订阅停止
+    pub fn events(&self) -> &Receiver<Result<ChangeEvent>> {
+        &self.events
+    }
+
+    /// 取消订阅，丢弃尚未读取的事件
+    pub fn unsubscribe(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        // 关闭通道即唤醒线程
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Subscription<'_> {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl Database {
+    /// 订阅之后的提交中以 `prefix` 开头的键的变更，`prefix` 为空时订阅全部键
+    pub fn subscribe(&self, prefix: &[u8]) -> Result<Subscription<'_>> {
+        self.subscribe_with(prefix, &SubscribeOptions::new())
+    }
+
+    /// 同 `subscribe`，按 `options` 订阅；`capacity` 为0时返回 `Error::InvalidArgument`
+    pub fn subscribe_with(
+        &self,
+        prefix: &[u8],
+        options: &SubscribeOptions,
+    ) -> Result<Subscription<'_>> {
+        if options.capacity == 0 {
+            return Err(Error::InvalidArgument(
+                "subscription capacity must be positive".to_string(),
+            ));
+        }
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let (event_tx, events) = mpsc::sync_channel(options.capacity);
+        let mut tailer = Tailer {
+            handle: SendHandle(*self.live_handle()?),
+            state: self.state.clone(),
+            prefix: prefix.to_vec(),
+            checksums: self.options.value_checksums,
+            blobs: self.blobs.clone(),
+            poll_interval: options.poll_interval,
+            stop: stop_rx,
+            next: 0,
+        };
+        tailer.next = tailer.latest()? + 1;
+        let thread = thread::Builder::new()
+            .name("amdb-subscription".to_string())
+            .spawn(move || tailer.run(event_tx))?;
+        Ok(Subscription {
+            _db: PhantomData,
+            stop: Some(stop_tx),
+            thread: Some(thread),
+            events,
+        })
+    }
+}
+
+struct Tailer {
+    handle: SendHandle,
+    state: HandleState,
+    prefix: Vec<u8>,
+    checksums: bool,
+    blobs: Option<BlobStore>,
+    poll_interval: Duration,
+    stop: Receiver<()>,
+    /// 下一个要读取的数据库版本
+    next: u64,
+}
+
+impl Tailer {
+    fn run(mut self, events: SyncSender<Result<ChangeEvent>>) {
+        // 跟随产生的I/O计入后台
+        unsafe { amdb_set_background_thread(true) };
+        loop {
+            match self.poll(&events) {
+                Ok(true) => {}
+                Ok(false) => return,
+                // 数据库已关闭或中毒，不再继续
+                Err(e) if matches!(e.inner(), Error::Closed | Error::Poisoned) => {
+                    self.send(&events, Err(e));
+                    return;
+                }
+                Err(e) => {
+                    if !self.send(&events, Err(e)) {
+                        return;
+                    }
+                }
+            }
+            if !self.sleep(self.poll_interval) {
+                return;
+            }
+        }
+    }
+
+    /// 发送到目前为止的全部提交的变更；被停止时返回 `false`
+    fn poll(&mut self, events: &SyncSender<Result<ChangeEvent>>) -> Result<bool> {
+        let latest = self.latest()?;
+        while self.next <= latest {
+            let changes = match self.changes(self.next) {
+                Ok(changes) => changes,
+                Err(e) if matches!(e.inner(), Error::NotFound) => {
+                    self.next = latest + 1;
+                    return Err(e);
+                }
+                Err(e) => return Err(e),
+            };
+            for event in changes {
+                if !self.send(events, Ok(event)) {
+                    return Ok(false);
+                }
+            }
+            self.next += 1;
+        }
+        Ok(true)
+    }
+
+    fn latest(&self) -> Result<u64> {
+        let mut version = 0;
+        let _alive = self.state.enter()?;
+        let status = unsafe { amdb_get_state_version(self.handle.0, &mut version) };
+        if status != 0 {
+            return Err(self.state.error(status));
+        }
+        Ok(version)
+    }
+
+    /// 数据库版本 `version` 中以前缀开头的键的变更
+    fn changes(&self, version: u64) -> Result<Vec<ChangeEvent>> {
+        let mut root_hash = Root::default();
+        let (mut results, mut count) = (ptr::null_mut::<AmdbResult>(), 0);
+        let _alive = self.state.enter()?;
+        let status = unsafe {
+            amdb_commit_changes(
+                self.handle.0,
+                version,
+                self.prefix.as_ptr(),
+                self.prefix.len(),
+                root_hash.as_mut_ptr(),
+                &mut results,
+                &mut count,
+            )
+        };
+        if status != 0 {
+            return Err(self.state.error(status));
+        }
+        if results.is_null() {
+            return Ok(Vec::new());
+        }
+
+        // 结果中键、旧值、新值依次排列
+        let events = unsafe { std::slice::from_raw_parts(results, count) }
+            .chunks_exact(3)
+            .map(|change| {
+                Ok(ChangeEvent {
+                    key: result_bytes(&change[0]),
+                    old_value: self.value(&change[1])?,
+                    new_value: self.value(&change[2])?,
+                    version: Version(version),
+                    root_hash,
+                })
+            })
+            .collect();
+        unsafe { amdb_free_results(results, count) };
+        events
+    }
+
+    fn value(&self, result: &AmdbResult) -> Result<Option<Vec<u8>>> {
+        result_value(result)
+            .map(|value| envelope::open_with(value, self.checksums, self.blobs.as_ref()))
+            .transpose()
+    }
+
+    /// 发送一个事件，通道满时等待；被停止或订阅已释放时返回 `false`
+    fn send(&self, events: &SyncSender<Result<ChangeEvent>>, event: Result<ChangeEvent>) -> bool {
+        let mut event = event;
+        loop {
+            match events.try_send(event) {
+                Ok(()) => return true,
+                Err(TrySendError::Full(back)) => {
+                    event = back;
+                    if !self.sleep(self.poll_interval) {
+                        return false;
+                    }
+                }
+                Err(TrySendError::Disconnected(_)) => return false,
+            }
+        }
+    }
+
+    /// 等待 `timeout`；期间被要求停止时返回 `false`
+    fn sleep(&self, timeout: Duration) -> bool {
+        matches!(
+            self.stop.recv_timeout(timeout),
+            Err(RecvTimeoutError::Timeout)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WriteBatch;
+
+    fn next(subscription: &Subscription<'_>) -> ChangeEvent {
+        subscription
+            .events()
+            .recv_timeout(Duration::from_secs(10))
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let _ = std::fs::remove_dir_all("./test_data/subscribe");
+        let db = Database::new("./test_data/subscribe").unwrap();
+        db.put(b"user/a", b"before").unwrap();
+        let subscription = db.subscribe(b"user/").unwrap();
+
+        let root = db.put(b"user/a", b"1").unwrap();
+        db.put(b"other", b"x").unwrap();
+        let mut batch = WriteBatch::new();
+        batch.put(b"user/c", b"3").put(b"user/b", b"2");
+        db.write_batch(&batch).unwrap();
+        db.delete(b"user/a").unwrap();
+
+        assert_eq!(
+            next(&subscription),
+            ChangeEvent {
+                key: b"user/a".to_vec(),
+                old_value: Some(b"before".to_vec()),
+                new_value: Some(b"1".to_vec()),
+                version: Version(2),
+                root_hash: root,
+            }
+        );
+        let (b, c) = (next(&subscription), next(&subscription));
+        assert_eq!((b.key, c.key), (b"user/b".to_vec(), b"user/c".to_vec()));
+        assert_eq!((b.version, b.old_value), (Version(4), None));
+        let deleted = next(&subscription);
+        assert_eq!(deleted.old_value, Some(b"1".to_vec()));
+        assert!(deleted.new_value.is_none());
+        subscription.unsubscribe();
+
+        assert!(matches!(
+            db.subscribe_with(b"", SubscribeOptions::new().capacity(0)),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_subscribe_backpressure() {
+        let _ = std::fs::remove_dir_all("./test_data/subscribe_backpressure");
+        let db = Database::new("./test_data/subscribe_backpressure").unwrap();
+        let subscription = db
+            .subscribe_with(
+                b"",
+                SubscribeOptions::new()
+                    .capacity(1)
+                    .poll_interval(Duration::from_millis(1)),
+            )
+            .unwrap();
+        for i in 0..5u8 {
+            db.put(&[i], &[i]).unwrap();
+        }
+        // 通道满时不丢事件，按提交顺序依次收到
+        for i in 0..5u8 {
+            let event = next(&subscription);
+            assert_eq!((event.key, event.version), (vec![i], Version(i as u64 + 1)));
+        }
+        // 析构时未读的事件被丢弃，线程退出
+        db.put(b"late", b"x").unwrap();
+        drop(subscription);
+    }
+}
@@ -0,0 +1,212 @@
+//! 显式事务
+//! `Database::transaction` 在内存中暂存写入，`get` 能读到本事务暂存的写入；`commit` 把全部写入作为一个批次
+//! 原子提交，`rollback` 或析构时丢弃。`staged_root_hash` 计算提交后的根哈希而不写入任何数据，
+//! 可在提交前与共识给出的根哈希比对。
+//!
+//! 事务不加锁：暂存期间其他写入照常进行，`staged_root_hash` 只在此后没有其他写入时与 `commit` 的结果一致。
+//!
+//! 参与协调者驱动的两阶段提交时，`prepare` 校验全部写入并计算提交后的根哈希，得到 `PreparedTransaction`；
+//! 协调者随后决定 `commit_prepared` 或 `rollback_prepared`。准备好的事务持有复合写入操作的锁
+//! （见 `Database::write_lock`），其他事务的提交和复合写入等到它结束；普通写入不受影响。
+//! 准备状态只在内存中，进程退出后不会保留。
+
+use std::collections::BTreeMap;
+use std::sync::MutexGuard;
+
+use crate::{Database, Result, Root, WriteBatch};
+
+/// 未提交的事务，见 `Database::transaction`；析构时自动回滚
+pub struct Transaction<'a> {
+    db: &'a Database,
+    /// 暂存的写入，`None` 表示删除
+    writes: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+/// 已准备、等待协调者决定的事务，见 `Transaction::prepare`；析构时自动回滚
+///
+/// 持有期间本线程不能再调用复合写入操作或提交其他事务。
+pub struct PreparedTransaction<'a> {
+    db: &'a Database,
+    batch: WriteBatch,
+    root_hash: Root,
+    _writes: MutexGuard<'a, ()>,
+}
+
+impl Database {
+    /// 开始一个事务
+    pub fn transaction(&self) -> Transaction<'_> {
+        Transaction {
+            db: self,
+            writes: BTreeMap::new(),
+        }
+    }
+}
+
+impl<'a> Transaction<'a> {
+    /// 读取键的值：本事务写入过的键返回暂存的值（删除时为 `None`），否则读取数据库的最新值
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        match self.writes.get(key) {
+            Some(staged) => Ok(staged.clone()),
+            None => self.db.get(key, None),
+        }
+    }
+
+    /// 暂存写入
+    pub fn put(&mut self, key: &[u8], value: &[u8]) -> &mut Self {
+        self.writes.insert(key.to_vec(), Some(value.to_vec()));
+        self
+    }
+
+    pub fn delete(&mut self, key: &[u8]) -> &mut Self {
+        self.writes.insert(key.to_vec(), None);
+        self
+    }
+
+    /// 暂存了写入的键数
+    pub fn len(&self) -> usize {
+        self.writes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    /// 按当前数据库状态提交本事务后的根哈希；不写入任何数据，键或值的校验错误与 `commit` 相同
+    pub fn staged_root_hash(&self) -> Result<Root> {
+        self.db.batch_root_hash(&self.batch())
+    }
+
+    /// 原子地提交全部暂存的写入，返回提交后的根哈希；失败时不写入任何数据，事务同样结束
+    pub fn commit(self) -> Result<Root> {
+        self.db.write_batch(&self.batch())
+    }
+
+    /// 丢弃全部暂存的写入
+    pub fn rollback(self) {}
+
+    /// 两阶段提交的第一阶段：取得复合写入操作的锁，校验全部暂存的写入并计算提交后的根哈希；
+    /// 键或值的校验错误与 `commit` 相同，失败时不持有锁，事务同样结束
+    pub fn prepare(self) -> Result<PreparedTransaction<'a>> {
+        let writes = self.db.write_lock();
+        let batch = self.batch();
+        let root_hash = self.db.batch_root_hash(&batch)?;
+        Ok(PreparedTransaction {
+            db: self.db,
+            batch,
+            root_hash,
+            _writes: writes,
+        })
+    }
+
+    fn batch(&self) -> WriteBatch {
+        let mut batch = WriteBatch::new();
+        for (key, value) in &self.writes {
+            match value {
+                Some(value) => batch.put(key, value),
+                None => batch.delete(key),
+            };
+        }
+        batch
+    }
+}
+
+impl PreparedTransaction<'_> {
+    /// 准备时计算的提交后的根哈希；准备之后没有普通写入时与 `commit_prepared` 的结果一致
+    pub fn root_hash(&self) -> Root {
+        self.root_hash
+    }
+
+    /// 两阶段提交的第二阶段：原子地提交全部写入，返回提交后的根哈希
+    pub fn commit_prepared(self) -> Result<Root> {
+        self.db.write_batch_locked(&self.batch)
+    }
+
+    /// 放弃提交，丢弃全部写入并释放锁
+    pub fn rollback_prepared(self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenOptions;
+
+    #[test]
+    fn test_transaction() {
+        let _ = std::fs::remove_dir_all("./test_data/transaction");
+        let db = Database::new("./test_data/transaction").unwrap();
+        db.put(b"a", b"1").unwrap();
+        db.put(b"b", b"2").unwrap();
+
+        let mut tx = db.transaction();
+        tx.put(b"a", b"10").put(b"c", b"3").delete(b"b");
+        assert_eq!(tx.get(b"a").unwrap(), Some(b"10".to_vec()));
+        assert!(tx.get(b"b").unwrap().is_none());
+        assert_eq!(db.get(b"b", None).unwrap(), Some(b"2".to_vec()));
+
+        let before = db.get_root_hash().unwrap();
+        let staged = tx.staged_root_hash().unwrap();
+        assert_ne!(staged, before);
+        assert_eq!(db.get_root_hash().unwrap(), before);
+        assert_eq!(tx.commit().unwrap(), staged);
+        assert_eq!(db.get_root_hash().unwrap(), staged);
+        assert_eq!(db.get(b"a", None).unwrap(), Some(b"10".to_vec()));
+        assert!(db.get(b"b", None).unwrap().is_none());
+
+        // 回滚与析构都不写入
+        let mut tx = db.transaction();
+        tx.put(b"d", b"4");
+        tx.rollback();
+        {
+            let mut tx = db.transaction();
+            tx.put(b"e", b"5");
+        }
+        assert!(db.get(b"d", None).unwrap().is_none());
+        assert!(db.get(b"e", None).unwrap().is_none());
+        assert_eq!(db.get_root_hash().unwrap(), staged);
+        assert_eq!(db.transaction().staged_root_hash().unwrap(), staged);
+    }
+
+    #[test]
+    fn test_two_phase_commit() {
+        let _ = std::fs::remove_dir_all("./test_data/transaction_2pc");
+        let db = Database::new("./test_data/transaction_2pc").unwrap();
+        db.put(b"a", b"1").unwrap();
+
+        let mut tx = db.transaction();
+        tx.put(b"a", b"2").put(b"offset", b"42");
+        let staged = tx.staged_root_hash().unwrap();
+        let prepared = tx.prepare().unwrap();
+        assert_eq!(prepared.root_hash(), staged);
+        // 准备不写入任何数据
+        assert_eq!(db.get(b"a", None).unwrap(), Some(b"1".to_vec()));
+        assert_eq!(prepared.commit_prepared().unwrap(), staged);
+        assert_eq!(db.get(b"offset", None).unwrap(), Some(b"42".to_vec()));
+
+        let mut tx = db.transaction();
+        tx.put(b"offset", b"43");
+        tx.prepare().unwrap().rollback_prepared();
+        assert_eq!(db.get(b"offset", None).unwrap(), Some(b"42".to_vec()));
+        assert_eq!(db.get_root_hash().unwrap(), staged);
+
+        // 校验失败时不进入准备状态，锁随之释放
+        let mut tx = db.transaction();
+        tx.put(b"\0idem/x", b"1");
+        assert!(matches!(tx.prepare(), Err(crate::Error::InvalidKey(_))));
+        let mut tx = db.transaction();
+        tx.put(b"b", b"1");
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_transaction_with_checksums() {
+        let _ = std::fs::remove_dir_all("./test_data/transaction_checksums");
+        let db = OpenOptions::new()
+            .value_checksums(true)
+            .open("./test_data/transaction_checksums")
+            .unwrap();
+        let mut tx = db.transaction();
+        tx.put(b"k", b"v");
+        let staged = tx.staged_root_hash().unwrap();
+        assert_eq!(tx.commit().unwrap(), staged);
+    }
+}
@@ -0,0 +1,518 @@
+//! 命名树
+//! 每棵树是一个带保留前缀的键空间，树名登记在保留前缀 `\0tree/` 下
+//!
+//! 树的数据存放在 `\0tdata/ + 转义后的树名` 下，转义保证不同树的前缀互不包含。
+//! 所有树共用一个数据库版本序列，`Database::view_all_at` 在同一个版本上读取全部树。
+//!
+//! 冻结的树登记记录为 `FROZEN`，登记记录计入根哈希，冻结本身因此可被验证。冻结不可撤销，
+//! 之后经由键空间的写入和 `drop_tree` 返回 `Error::Frozen`。两个前缀都是保留前缀（见 `reserved`），
+//! 树的数据只能经由树的键空间写入，也不出现在整个数据库的扫描中。
+//!
+//! 树的选项（见 `tree_options`）附加在登记记录的状态之后，冻结时保留。
+
+use std::ops::RangeBounds;
+use std::sync::{Arc, PoisonError};
+
+use crate::keys::{escape_into, prefix_successor};
+use crate::{
+    engine_bounds, BatchItem, Database, Entry, Error, Iter, Keyspace, Result, Root, Snapshot,
+    TreeOptions, Version,
+};
+
+pub(crate) const REGISTRY_PREFIX: &[u8] = b"\0tree/";
+pub(crate) const DATA_PREFIX: &[u8] = b"\0tdata/";
+
+/// 登记记录的值
+const REGISTERED: &[u8] = b"1";
+/// 已冻结的树的登记记录
+const FROZEN: &[u8] = b"frozen";
+
+impl Database {
+    /// 创建名为 `name` 的树并返回其键空间；同名的树已存在时返回 `Error::TreeExists`
+    pub fn create_tree(&self, name: &str) -> Result<Keyspace<'_>> {
+        self.create_tree_with(name, &TreeOptions::new())
+    }
+
+    /// 同 `create_tree`，按 `options` 创建，选项随登记记录持久化
+    pub fn create_tree_with(&self, name: &str, options: &TreeOptions) -> Result<Keyspace<'_>> {
+        options.check()?;
+        let _writes = self.write_lock();
+        if self.tree_record(name)?.is_some() {
+            return Err(Error::TreeExists(name.to_string()));
+        }
+        let record = encode_record(false, options);
+        self.batch_put(&[(registry_key(name), Some(record))])?;
+        Ok(self.tree_keyspace(name, &data_prefix(name), self.hooks_of(name), options.clone()))
+    }
+
+    /// 打开已存在的树，沿用创建时记录的选项；不存在时返回 `Error::TreeNotFound`
+    pub fn open_tree(&self, name: &str) -> Result<Keyspace<'_>> {
+        let options = self.tree_options(name)?;
+        Ok(self.tree_keyspace(name, &data_prefix(name), self.hooks_of(name), options))
+    }
+
+    /// 同 `open_tree`，`options` 与创建时记录的选项不一致时返回 `Error::InvalidArgument`
+    pub fn open_tree_with(&self, name: &str, options: &TreeOptions) -> Result<Keyspace<'_>> {
+        let recorded = self.tree_options(name)?;
+        if recorded != *options {
+            return Err(Error::InvalidArgument(format!(
+                "tree {} was created with {:?}, not {:?}",
+                name, recorded, options
+            )));
+        }
+        Ok(self.tree_keyspace(name, &data_prefix(name), self.hooks_of(name), recorded))
+    }
+
+    /// 树创建时记录的选项；树不存在时返回 `Error::TreeNotFound`
+    pub fn tree_options(&self, name: &str) -> Result<TreeOptions> {
+        let (frozen, options) = self.tree_state(name)?;
+        if frozen {
+            self.mark_frozen(name);
+        }
+        Ok(options)
+    }
+
+    /// 冻结树：之后经由键空间的写入返回 `Error::Frozen`，其他树照常可写；返回冻结后的根哈希。
+    /// 已冻结时不做写入，返回当前根哈希；树不存在时返回 `Error::TreeNotFound`
+    pub fn freeze_tree(&self, name: &str) -> Result<Root> {
+        let _writes = self.write_lock();
+        let (frozen, options) = self.tree_state(name)?;
+        if frozen {
+            self.mark_frozen(name);
+            return self.get_root_hash();
+        }
+        let record = encode_record(true, &options);
+        let root_hash = self.batch_put(&[(registry_key(name), Some(record))])?;
+        self.mark_frozen(name);
+        Ok(root_hash)
+    }
+
+    /// 树是否已冻结；树不存在时返回 `Error::TreeNotFound`
+    pub fn is_tree_frozen(&self, name: &str) -> Result<bool> {
+        let (frozen, _) = self.tree_state(name)?;
+        if frozen {
+            self.mark_frozen(name);
+        }
+        Ok(frozen)
+    }
+
+    /// 在一次批量写入中删除树的登记和全部最新数据，返回删除后的根哈希
+    ///
+    /// 引擎保留每个键的历史版本，删除后仍可按版本号读取旧值。
+    pub fn drop_tree(&self, name: &str) -> Result<Root> {
+        let _writes = self.write_lock();
+        if self.is_tree_frozen(name)? {
+            return Err(Error::Frozen(name.to_string()));
+        }
+        let prefix = data_prefix(name);
+        let mut items: Vec<BatchItem> = vec![(registry_key(name), None)];
+        for item in self.keyspace(&prefix).scan(..) {
+            let (key, _) = item?;
+            let mut full = prefix.clone();
+            full.extend_from_slice(&key);
+            items.push((full, None));
+        }
+        self.batch_put(&items)
+    }
+
+    /// 按字节序列出全部已创建的树名
+    pub fn tree_names(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for item in self.keyspace(REGISTRY_PREFIX).scan(..) {
+            let (name, _) = item?;
+            names.push(String::from_utf8_lossy(&name).into_owned());
+        }
+        Ok(names)
+    }
+
+    /// 数据库版本 `version` 时全部树的一致视图，跨树的读取不会看到其他版本的写入；
+    /// 视图存活期间固定该版本（同 `snapshot_at`）。没有该版本时返回 `Error::NotFound`
+    pub fn view_all_at(&self, version: Version) -> Result<TreesView<'_>> {
+        let snapshot = self.snapshot_at(version)?;
+        let (mut names, mut options) = (Vec::new(), Vec::new());
+        for item in snapshot.prefix_iter(REGISTRY_PREFIX) {
+            let (key, record) = item?;
+            names.push(String::from_utf8_lossy(&key[REGISTRY_PREFIX.len()..]).into_owned());
+            options.push(Arc::new(decode_record(&record)?.1));
+        }
+        Ok(TreesView {
+            snapshot,
+            names,
+            options,
+        })
+    }
+
+    fn tree_record(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        self.get(&registry_key(name), None)
+    }
+
+    /// 树是否已冻结及其选项；树不存在时返回 `Error::TreeNotFound`
+    fn tree_state(&self, name: &str) -> Result<(bool, TreeOptions)> {
+        match self.tree_record(name)? {
+            Some(record) => decode_record(&record),
+            None => Err(Error::TreeNotFound(name.to_string())),
+        }
+    }
+
+    /// 记录进程内已知冻结的树，供键空间写入前检查
+    fn mark_frozen(&self, name: &str) {
+        let mut frozen = self.frozen_trees.lock().unwrap_or_else(PoisonError::into_inner);
+        frozen.insert(name.to_string());
+    }
+
+    pub(crate) fn is_frozen(&self, name: &str) -> bool {
+        let frozen = self.frozen_trees.lock().unwrap_or_else(PoisonError::into_inner);
+        frozen.contains(name)
+    }
+}
+
+/// 全部命名树在同一个数据库版本的只读视图，见 `Database::view_all_at`
+pub struct TreesView<'a> {
+    snapshot: Snapshot<'a>,
+    names: Vec<String>,
+    /// 与 `names` 一一对应的选项
+    options: Vec<Arc<TreeOptions>>,
+}
+
+impl<'a> TreesView<'a> {
+    pub fn version(&self) -> Version {
+        self.snapshot.version()
+    }
+
+    pub fn root_hash(&self) -> Root {
+        self.snapshot.root_hash()
+    }
+
+    /// 该版本中已创建的树名，按字节序
+    pub fn tree_names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// 该版本中名为 `name` 的树；该版本中不存在时返回 `Error::TreeNotFound`
+    pub fn tree(&self, name: &str) -> Result<TreeView<'_>> {
+        match self.names.iter().position(|n| n == name) {
+            Some(i) => Ok(self.tree_at(i)),
+            None => Err(Error::TreeNotFound(name.to_string())),
+        }
+    }
+
+    /// 按树名顺序迭代全部树
+    pub fn trees(&self) -> impl Iterator<Item = (&str, TreeView<'_>)> {
+        (0..self.names.len()).map(|i| (self.names[i].as_str(), self.tree_at(i)))
+    }
+
+    fn tree_at(&self, i: usize) -> TreeView<'_> {
+        TreeView {
+            snapshot: &self.snapshot,
+            prefix: data_prefix(&self.names[i]),
+            options: self.options[i].clone(),
+        }
+    }
+
+    /// 底层快照，可读取不属于任何树的键
+    pub fn snapshot(&self) -> &Snapshot<'a> {
+        &self.snapshot
+    }
+}
+
+/// `TreesView` 中的一棵树，键不含树的前缀；按树的选项转换键和值，不经过钩子
+pub struct TreeView<'v> {
+    snapshot: &'v Snapshot<'v>,
+    prefix: Vec<u8>,
+    options: Arc<TreeOptions>,
+}
+
+impl<'v> TreeView<'v> {
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let stored = self.options.stored_key(key.to_vec());
+        match self.snapshot.get(&self.full_key(&stored))? {
+            Some(value) => self.options.decode_value(value),
+            None => Ok(None),
+        }
+    }
+
+    /// 按键的升序迭代树中 `range` 内的键值对
+    pub fn iter(&self, range: impl RangeBounds<Vec<u8>>) -> TreeIter<'v> {
+        let full = engine_bounds(&range).map(|(start, end)| {
+            let end = if end.is_empty() {
+                prefix_successor(&self.prefix)
+            } else {
+                self.full_key(&end)
+            };
+            (self.full_key(&start), end)
+        });
+        let inner = match full {
+            Some(bounds) => self.snapshot.iter(bounds.0..bounds.1),
+            // 空区间
+            None => self.snapshot.iter(Vec::new()..Vec::new()),
+        };
+        TreeIter {
+            inner,
+            strip: self.prefix.len(),
+            options: self.options.clone(),
+        }
+    }
+
+    fn full_key(&self, key: &[u8]) -> Vec<u8> {
+        let mut full = self.prefix.clone();
+        full.extend_from_slice(key);
+        full
+    }
+}
+
+impl<'v> IntoIterator for &TreeView<'v> {
+    type Item = Result<Entry>;
+    type IntoIter = TreeIter<'v>;
+
+    fn into_iter(self) -> TreeIter<'v> {
+        self.iter(..)
+    }
+}
+
+/// `TreeView::iter` 返回的迭代器，键不含树的前缀，跳过已过期的值
+pub struct TreeIter<'v> {
+    inner: Iter<'v>,
+    strip: usize,
+    options: Arc<TreeOptions>,
+}
+
+impl TreeIter<'_> {
+    /// 还原一项；已过期时为 `None`
+    fn convert(&self, item: Result<Entry>) -> Option<Result<Entry>> {
+        let (key, stored) = match item {
+            Ok(entry) => entry,
+            Err(e) => return Some(Err(e)),
+        };
+        let value = self.options.decode_value(stored).transpose()?;
+        Some(value.map(|value| (key[self.strip..].to_vec(), value)))
+    }
+}
+
+impl Iterator for TreeIter<'_> {
+    type Item = Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let item = self.inner.next()?;
+            if let Some(item) = self.convert(item) {
+                return Some(item);
+            }
+        }
+    }
+}
+
+impl DoubleEndedIterator for TreeIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            let item = self.inner.next_back()?;
+            if let Some(item) = self.convert(item) {
+                return Some(item);
+            }
+        }
+    }
+}
+
+/// 登记记录：状态，其后附加树的选项
+fn encode_record(frozen: bool, options: &TreeOptions) -> Vec<u8> {
+    let mut record = if frozen { FROZEN } else { REGISTERED }.to_vec();
+    options.write_record(&mut record);
+    record
+}
+
+/// 登记记录中的冻结状态和选项
+fn decode_record(record: &[u8]) -> Result<(bool, TreeOptions)> {
+    let (state, fields) = match record.iter().position(|&b| b == b'\n') {
+        Some(i) => (&record[..i], &record[i + 1..]),
+        None => (record, &[][..]),
+    };
+    let frozen = match state {
+        REGISTERED => false,
+        FROZEN => true,
+        _ => {
+            return Err(Error::Corruption(
+                "malformed tree registry record".to_string(),
+            ))
+        }
+    };
+    Ok((frozen, TreeOptions::read_record(fields)?))
+}
+
+fn registry_key(name: &str) -> Vec<u8> {
+    let mut key = REGISTRY_PREFIX.to_vec();
+    key.extend_from_slice(name.as_bytes());
+    key
+}
+
+fn data_prefix(name: &str) -> Vec<u8> {
+    let mut prefix = DATA_PREFIX.to_vec();
+    escape_into(&mut prefix, name.as_bytes());
+    prefix
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::KeyValue;
+
+    #[test]
+    fn test_create_and_drop_tree() {
+        let _ = std::fs::remove_dir_all("./test_data/trees");
+        let db = Database::new("./test_data/trees").unwrap();
+        let tenant = db.create_tree("tenant").unwrap();
+        tenant.put(b"k", b"v").unwrap();
+        db.create_tree("tenant-2").unwrap().put(b"k", b"other").unwrap();
+        assert!(matches!(db.create_tree("tenant"), Err(Error::TreeExists(_))));
+
+        db.drop_tree("tenant").unwrap();
+        assert!(matches!(db.open_tree("tenant"), Err(Error::TreeNotFound(_))));
+        assert!(db.keyspace(&data_prefix("tenant")).get(b"k", None).unwrap().is_none());
+
+        assert_eq!(db.tree_names().unwrap(), vec!["tenant-2".to_string()]);
+        let other = db.open_tree("tenant-2").unwrap();
+        assert_eq!(other.get(b"k", None).unwrap(), Some(b"other".to_vec()));
+
+        // 树的登记和数据不出现在整个数据库的扫描中，只能经由树的键空间写入
+        db.put(b"plain", b"1").unwrap();
+        let keys: Vec<Vec<u8>> = db.iter(..).map(|e| e.unwrap().0).collect();
+        assert_eq!(keys, vec![b"plain".to_vec()]);
+        assert_eq!(db.scan(..).count(), 1);
+        assert_eq!(other.scan(..).count(), 1);
+        let data_key = [data_prefix("tenant-2"), b"k".to_vec()].concat();
+        assert!(matches!(db.put(&data_key, b"forged"), Err(Error::InvalidKey(_))));
+        assert!(matches!(db.delete(&data_key), Err(Error::InvalidKey(_))));
+        let forged = db.keyspace(&data_prefix("tenant-2"));
+        assert!(matches!(forged.put(b"k", b"forged"), Err(Error::InvalidKey(_))));
+        assert!(matches!(db.put(&registry_key("ghost"), REGISTERED), Err(Error::InvalidKey(_))));
+        assert_eq!(db.tree_names().unwrap(), vec!["tenant-2".to_string()]);
+        other.delete(b"k").unwrap();
+        assert_eq!(other.scan(..).count(), 0);
+    }
+
+    #[test]
+    fn test_view_all_at() {
+        let _ = std::fs::remove_dir_all("./test_data/trees_view");
+        let db = Database::new("./test_data/trees_view").unwrap();
+        let accounts = db.create_tree("accounts").unwrap();
+        let ledger = db.create_tree("ledger").unwrap();
+        accounts.put(b"alice", b"10").unwrap();
+        ledger.put(b"1", b"+10").unwrap();
+        let version = db.state_version().unwrap();
+
+        accounts.put(b"alice", b"5").unwrap();
+        ledger.put(b"2", b"-5").unwrap();
+        db.create_tree("later").unwrap();
+
+        let view = db.view_all_at(version).unwrap();
+        assert_eq!(view.version(), version);
+        assert_eq!(view.tree_names(), ["accounts", "ledger"]);
+        assert!(matches!(view.tree("later"), Err(Error::TreeNotFound(_))));
+        let accounts = view.tree("accounts").unwrap();
+        assert_eq!(accounts.get(b"alice").unwrap(), Some(b"10".to_vec()));
+        let ledger = view.tree("ledger").unwrap();
+        let entries: Vec<Entry> = ledger.iter(..).map(|e| e.unwrap()).collect();
+        assert_eq!(entries, vec![(b"1".to_vec(), b"+10".to_vec())]);
+        assert_eq!(view.trees().count(), 2);
+        for (name, tree) in view.trees() {
+            let entries: Vec<Entry> = (&tree).into_iter().map(|e| e.unwrap()).collect();
+            assert_eq!(entries.len(), 1, "{name}");
+        }
+        let mut keys = Vec::new();
+        for entry in &accounts {
+            keys.push(entry.unwrap().0);
+        }
+        assert_eq!(keys, vec![b"alice".to_vec()]);
+        assert_eq!((&ledger).into_iter().next_back().unwrap().unwrap().0, b"1");
+    }
+
+    #[test]
+    fn test_freeze_tree() {
+        let _ = std::fs::remove_dir_all("./test_data/trees_freeze");
+        let dir = "./test_data/trees_freeze";
+        {
+            let db = Database::new(dir).unwrap();
+            let epoch = db.create_tree("epoch-1").unwrap();
+            let live = db.create_tree("live").unwrap();
+            epoch.put(b"k", b"v").unwrap();
+            assert!(!db.is_tree_frozen("epoch-1").unwrap());
+
+            let before = db.get_root_hash().unwrap();
+            let root = epoch.freeze().unwrap();
+            assert_ne!(root, before);
+            assert_eq!(db.freeze_tree("epoch-1").unwrap(), root);
+            assert!(matches!(epoch.put(b"k", b"w"), Err(Error::Frozen(_))));
+            assert!(matches!(epoch.delete(b"k"), Err(Error::Frozen(_))));
+            assert!(matches!(db.drop_tree("epoch-1"), Err(Error::Frozen(_))));
+            assert_eq!(epoch.get(b"k", None).unwrap(), Some(b"v".to_vec()));
+            live.put(b"k", b"w").unwrap();
+
+            assert!(matches!(db.freeze_tree("missing"), Err(Error::TreeNotFound(_))));
+            assert!(matches!(db.keyspace(b"p/").freeze(), Err(Error::InvalidArgument(_))));
+        }
+        // 冻结随数据库持久化
+        let db = Database::new(dir).unwrap();
+        let epoch = db.open_tree("epoch-1").unwrap();
+        assert!(matches!(epoch.put(b"k", b"w"), Err(Error::Frozen(_))));
+        assert_eq!(db.tree_names().unwrap(), vec!["epoch-1".to_string(), "live".to_string()]);
+    }
+
+    #[test]
+    fn test_tree_options() {
+        let dir = "./test_data/trees_options";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut options = TreeOptions::new();
+        options
+            .compression(true)
+            .hash_keys(true)
+            .cache_priority(crate::CachePriority::High);
+        let value = b"payload ".repeat(64);
+        {
+            let db = Database::new(dir).unwrap();
+            let tree = db.create_tree_with("blobs", &options).unwrap();
+            tree.put(b"k", &value).unwrap();
+            assert_eq!(tree.get(b"k", None).unwrap(), Some(value.clone()));
+            // 存放的是哈希后的键和压缩后的值
+            let raw = db.keyspace(&data_prefix("blobs"));
+            let (stored_key, stored_value) = raw.scan(..).next().unwrap().unwrap();
+            assert_eq!(stored_key.len(), 32);
+            assert!(stored_value.len() < value.len());
+            let scanned: Vec<KeyValue> = tree.scan(..).map(Result::unwrap).collect();
+            assert_eq!(scanned, vec![(stored_key, value[..].into())]);
+
+            let mut expiring = TreeOptions::new();
+            expiring.default_ttl(Duration::from_millis(50));
+            let sessions = db.create_tree_with("sessions", &expiring).unwrap();
+            sessions.put(b"s", b"token").unwrap();
+            assert_eq!(sessions.get(b"s", None).unwrap(), Some(b"token".to_vec()));
+            std::thread::sleep(Duration::from_millis(80));
+            assert!(sessions.get(b"s", None).unwrap().is_none());
+            assert_eq!(sessions.scan(..).count(), 0);
+            assert!(matches!(
+                db.create_tree_with("bad", TreeOptions::new().default_ttl(Duration::ZERO)),
+                Err(Error::InvalidArgument(_))
+            ));
+        }
+
+        // 选项随登记记录持久化，冻结后保留
+        let db = Database::new(dir).unwrap();
+        assert_eq!(db.tree_options("blobs").unwrap(), options);
+        let tree = db.open_tree("blobs").unwrap();
+        assert_eq!(tree.tree_options(), Some(&options));
+        assert_eq!(tree.get(b"k", None).unwrap(), Some(value.clone()));
+        assert!(db.open_tree_with("blobs", &options).is_ok());
+        assert!(matches!(
+            db.open_tree_with("blobs", &TreeOptions::new()),
+            Err(Error::InvalidArgument(_))
+        ));
+        db.freeze_tree("blobs").unwrap();
+        assert_eq!(db.tree_options("blobs").unwrap(), options);
+        assert!(db.is_tree_frozen("blobs").unwrap());
+
+        let view = db.view_all_at(db.state_version().unwrap()).unwrap();
+        let blobs = view.tree("blobs").unwrap();
+        assert_eq!(blobs.get(b"k").unwrap(), Some(value.clone()));
+        assert_eq!((&blobs).into_iter().next().unwrap().unwrap().1, value);
+        assert_eq!((&view.tree("sessions").unwrap()).into_iter().count(), 0);
+    }
+}
@@ -0,0 +1,288 @@
+//! 命名树的选项
+//! 创建树时给出，随树的登记记录持久化（登记记录计入根哈希），之后打开树沿用记录的值；
+//! `Database::open_tree_with` 给出的选项与记录不一致时返回 `Error::InvalidArgument`，不会改变已有的树。
+//!
+//! 选项在树的键空间和 `TreeView` 中生效：
+//! - `hash_keys`：键（经钩子规范化后）以其SHA-256存放，扫描按哈希后的键排序并返回哈希后的键，
+//!   扫描范围同样按哈希后的键给出；
+//! - `compression`：值压缩后写入（见 `compress`），压缩后不更短时原样存放；
+//! - `default_ttl`：写入的值在给定时长后过期，过期的值在读取和扫描中视为不存在，直到被覆盖或删除；
+//! - `cache_priority`：只记录在登记中供调用方的缓存层参考，引擎的缓存不区分优先级。
+//!
+//! 开启 `compression` 或 `default_ttl` 时，树中的值存为 `标志 | [过期时间] | 数据`：标志字节的第0位表示
+//! 数据已压缩、第1位表示其后有8字节大端的过期时间（Unix毫秒）。Merkle证明覆盖的是这一编码。
+//! 登记记录中，非默认的选项以 `\n名称=值` 逐项附加在状态之后。
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::compress::{compress, decompress};
+use crate::sha256::sha256;
+use crate::{Entry, Error, Result};
+
+const COMPRESSED: u8 = 1;
+const EXPIRES: u8 = 2;
+
+/// 命名树的缓存优先级，见 `TreeOptions::cache_priority`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CachePriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl CachePriority {
+    fn as_option(self) -> &'static str {
+        match self {
+            CachePriority::Low => "low",
+            CachePriority::Normal => "normal",
+            CachePriority::High => "high",
+        }
+    }
+
+    fn from_option(value: &str) -> Option<Self> {
+        [Self::Low, Self::Normal, Self::High]
+            .into_iter()
+            .find(|priority| priority.as_option() == value)
+    }
+}
+
+/// 命名树的选项，见 `Database::create_tree_with`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TreeOptions {
+    compression: bool,
+    hash_keys: bool,
+    default_ttl: Option<Duration>,
+    cache_priority: CachePriority,
+}
+
+impl TreeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 压缩写入的值（默认关闭）
+    pub fn compression(&mut self, enabled: bool) -> &mut Self {
+        self.compression = enabled;
+        self
+    }
+
+    /// 以键的SHA-256代替键存放（默认关闭），键长因此固定，但扫描不再按原键排序
+    pub fn hash_keys(&mut self, enabled: bool) -> &mut Self {
+        self.hash_keys = enabled;
+        self
+    }
+
+    /// 写入的值的存活时长（默认不过期），按毫秒记录，不足1毫秒时创建失败
+    pub fn default_ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.default_ttl = Some(ttl);
+        self
+    }
+
+    /// 缓存优先级（默认 `CachePriority::Normal`）
+    pub fn cache_priority(&mut self, priority: CachePriority) -> &mut Self {
+        self.cache_priority = priority;
+        self
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.compression
+    }
+
+    pub fn is_key_hashed(&self) -> bool {
+        self.hash_keys
+    }
+
+    pub fn ttl(&self) -> Option<Duration> {
+        self.default_ttl
+    }
+
+    pub fn priority(&self) -> CachePriority {
+        self.cache_priority
+    }
+
+    pub(crate) fn check(&self) -> Result<()> {
+        match self.default_ttl {
+            Some(ttl) if ttl.as_millis() == 0 => Err(Error::InvalidArgument(
+                "default TTL must be at least 1 millisecond".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// 追加到登记记录的选项
+    pub(crate) fn write_record(&self, record: &mut Vec<u8>) {
+        let mut fields = Vec::new();
+        if self.compression {
+            fields.push("compression=true".to_string());
+        }
+        if self.hash_keys {
+            fields.push("hash_keys=true".to_string());
+        }
+        if let Some(ttl) = self.default_ttl {
+            fields.push(format!("default_ttl_ms={}", ttl.as_millis()));
+        }
+        if self.cache_priority != CachePriority::Normal {
+            fields.push(format!("cache_priority={}", self.cache_priority.as_option()));
+        }
+        for field in fields {
+            record.push(b'\n');
+            record.extend_from_slice(field.as_bytes());
+        }
+    }
+
+    /// 从登记记录中状态之后的部分读出选项
+    pub(crate) fn read_record(fields: &[u8]) -> Result<Self> {
+        let mut options = TreeOptions::new();
+        if fields.is_empty() {
+            return Ok(options);
+        }
+        for field in fields.split(|&b| b == b'\n') {
+            let field = std::str::from_utf8(field).ok();
+            let parsed = field.and_then(|field| field.split_once('=')).and_then(|(name, value)| {
+                match name {
+                    "compression" => options.compression = value.parse().ok()?,
+                    "hash_keys" => options.hash_keys = value.parse().ok()?,
+                    "default_ttl_ms" => {
+                        options.default_ttl = Some(Duration::from_millis(value.parse().ok()?))
+                    }
+                    "cache_priority" => {
+                        options.cache_priority = CachePriority::from_option(value)?
+                    }
+                    _ => return None,
+                }
+                Some(())
+            });
+            if parsed.is_none() {
+                return Err(Error::Corruption(format!(
+                    "malformed tree option {:?}",
+                    field.unwrap_or_default()
+                )));
+            }
+        }
+        Ok(options)
+    }
+
+    /// 规范化后的键在树中存放的形式
+    pub(crate) fn stored_key(&self, key: Vec<u8>) -> Vec<u8> {
+        match self.hash_keys {
+            true => sha256(&[&key]).to_vec(),
+            false => key,
+        }
+    }
+
+    fn encodes_values(&self) -> bool {
+        self.compression || self.default_ttl.is_some()
+    }
+
+    /// 值在树中存放的形式
+    pub(crate) fn encode_value(&self, value: &[u8]) -> Vec<u8> {
+        if !self.encodes_values() {
+            return value.to_vec();
+        }
+        let mut flags = 0;
+        let mut header = Vec::with_capacity(9);
+        if let Some(ttl) = self.default_ttl {
+            flags |= EXPIRES;
+            let expires = now_millis().saturating_add(ttl.as_millis() as u64);
+            header.extend_from_slice(&expires.to_be_bytes());
+        }
+        let compressed = self.compression.then(|| compress(value));
+        let data = match &compressed {
+            Some(compressed) if compressed.len() < value.len() => {
+                flags |= COMPRESSED;
+                compressed.as_slice()
+            }
+            _ => value,
+        };
+        let mut encoded = Vec::with_capacity(1 + header.len() + data.len());
+        encoded.push(flags);
+        encoded.extend_from_slice(&header);
+        encoded.extend_from_slice(data);
+        encoded
+    }
+
+    /// 还原 `encode_value` 的结果；已过期时返回 `None`
+    pub(crate) fn decode_value(&self, stored: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        if !self.encodes_values() {
+            return Ok(Some(stored));
+        }
+        let malformed = || Error::Corruption("malformed tree value".to_string());
+        let (&flags, mut data) = stored.split_first().ok_or_else(malformed)?;
+        if flags & !(COMPRESSED | EXPIRES) != 0 {
+            return Err(malformed());
+        }
+        if flags & EXPIRES != 0 {
+            let (expires, rest) = data.split_first_chunk::<8>().ok_or_else(malformed)?;
+            if u64::from_be_bytes(*expires) <= now_millis() {
+                return Ok(None);
+            }
+            data = rest;
+        }
+        match flags & COMPRESSED {
+            0 => Ok(Some(data.to_vec())),
+            _ => decompress(data).map(Some).ok_or_else(malformed),
+        }
+    }
+
+    /// 还原扫描得到的值，去掉已过期的键
+    pub(crate) fn decode_entries(&self, entries: Vec<Entry>) -> Result<Vec<Entry>> {
+        if !self.encodes_values() {
+            return Ok(entries);
+        }
+        let mut decoded = Vec::with_capacity(entries.len());
+        for (key, stored) in entries {
+            if let Some(value) = self.decode_value(stored)? {
+                decoded.push((key, value));
+            }
+        }
+        Ok(decoded)
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_value_encoding() {
+        let mut options = TreeOptions::new();
+        options
+            .compression(true)
+            .hash_keys(true)
+            .default_ttl(Duration::from_secs(60))
+            .cache_priority(CachePriority::High);
+        let mut record = b"1".to_vec();
+        options.write_record(&mut record);
+        assert_eq!(TreeOptions::read_record(&record[2..]).unwrap(), options);
+        assert!(matches!(
+            TreeOptions::read_record(b"ttl=1"),
+            Err(Error::Corruption(_))
+        ));
+        let mut plain = b"1".to_vec();
+        TreeOptions::new().write_record(&mut plain);
+        assert_eq!(plain, b"1");
+
+        let value = b"value ".repeat(50);
+        let encoded = options.encode_value(&value);
+        assert!(encoded.len() < value.len());
+        assert_eq!(options.decode_value(encoded).unwrap(), Some(value.clone()));
+        assert_eq!(options.stored_key(b"k".to_vec()).len(), 32);
+
+        // 已过期的值视为不存在
+        let mut expired = vec![EXPIRES];
+        expired.extend_from_slice(&1u64.to_be_bytes());
+        assert_eq!(options.decode_value(expired).unwrap(), None);
+        assert!(matches!(options.decode_value(vec![8]), Err(Error::Corruption(_))));
+        assert!(TreeOptions::new()
+            .default_ttl(Duration::from_micros(10))
+            .check()
+            .is_err());
+    }
+}
@@ -0,0 +1,250 @@
+//! 类型化访问
+//! `Database::typed` 用一对 `Codec` 把键和值编码为字节后读写，调用方不必在每次 `put` 前自行序列化。
+//! `serde` 特性提供 `Json`、`Bincode` 编码，`borsh` 特性提供 `Borsh`；`Bytes` 原样存储，总是可用。
+//!
+//! 证明针对编码后的字节：`Proof::verify_typed` 用同一编码重算叶子，轻客户端拿到可信的根哈希和编码方式
+//! 即可验证类型化的值。键的编码决定字节序，需要按范围扫描时应选择保序的键编码（例如 `Bytes`）。
+
+use std::marker::PhantomData;
+
+use crate::{Database, Error, Proof, Result, Root};
+
+/// 类型 `T` 与字节之间的编码
+pub trait Codec<T> {
+    fn encode(&self, value: &T) -> Result<Vec<u8>>;
+    fn decode(&self, bytes: &[u8]) -> Result<T>;
+}
+
+/// 原样存储字节或UTF-8字符串，保持字节序
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bytes;
+
+impl Codec<Vec<u8>> for Bytes {
+    fn encode(&self, value: &Vec<u8>) -> Result<Vec<u8>> {
+        Ok(value.clone())
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        Ok(bytes.to_vec())
+    }
+}
+
+impl Codec<String> for Bytes {
+    fn encode(&self, value: &String) -> Result<Vec<u8>> {
+        Ok(value.as_bytes().to_vec())
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<String> {
+        String::from_utf8(bytes.to_vec()).map_err(|e| Error::Codec(e.to_string()))
+    }
+}
+
+/// JSON 编码（`serde` 特性）
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+#[cfg(feature = "serde")]
+impl<T: ::serde::Serialize + ::serde::de::DeserializeOwned> Codec<T> for Json {
+    fn encode(&self, value: &T) -> Result<Vec<u8>> {
+        ::serde_json::to_vec(value).map_err(|e| Error::Codec(e.to_string()))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<T> {
+        ::serde_json::from_slice(bytes).map_err(|e| Error::Codec(e.to_string()))
+    }
+}
+
+/// bincode 编码（`serde` 特性）
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bincode;
+
+#[cfg(feature = "serde")]
+impl<T: ::serde::Serialize + ::serde::de::DeserializeOwned> Codec<T> for Bincode {
+    fn encode(&self, value: &T) -> Result<Vec<u8>> {
+        ::bincode::serialize(value).map_err(|e| Error::Codec(e.to_string()))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<T> {
+        ::bincode::deserialize(bytes).map_err(|e| Error::Codec(e.to_string()))
+    }
+}
+
+/// Borsh 编码（`borsh` 特性）
+#[cfg(feature = "borsh")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Borsh;
+
+#[cfg(feature = "borsh")]
+impl<T: ::borsh::BorshSerialize + ::borsh::BorshDeserialize> Codec<T> for Borsh {
+    fn encode(&self, value: &T) -> Result<Vec<u8>> {
+        ::borsh::to_vec(value).map_err(|e| Error::Codec(e.to_string()))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<T> {
+        T::try_from_slice(bytes).map_err(|e| Error::Codec(e.to_string()))
+    }
+}
+
+/// 按类型读写的视图，见 `Database::typed`
+pub struct TypedDatabase<'a, K, V, KC, VC> {
+    db: &'a Database,
+    keys: KC,
+    values: VC,
+    _types: PhantomData<fn() -> (K, V)>,
+}
+
+impl Database {
+    /// 以 `keys` 编码键、`values` 编码值的类型化视图
+    pub fn typed<K, V, KC, VC>(&self, keys: KC, values: VC) -> TypedDatabase<'_, K, V, KC, VC>
+    where
+        KC: Codec<K>,
+        VC: Codec<V>,
+    {
+        TypedDatabase {
+            db: self,
+            keys,
+            values,
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<K, V, KC: Codec<K>, VC: Codec<V>> TypedDatabase<'_, K, V, KC, VC> {
+    pub fn database(&self) -> &Database {
+        self.db
+    }
+
+    pub fn put(&self, key: &K, value: &V) -> Result<Root> {
+        self.db
+            .put(&self.keys.encode(key)?, &self.values.encode(value)?)
+    }
+
+    pub fn get(&self, key: &K) -> Result<Option<V>> {
+        match self.db.get(&self.keys.encode(key)?, None)? {
+            Some(bytes) => self.values.decode(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn delete(&self, key: &K) -> Result<()> {
+        self.db.delete(&self.keys.encode(key)?)
+    }
+
+    /// 键的最新值及编码后字节的证明，见 `Database::get_with_proof`
+    pub fn get_with_proof(&self, key: &K) -> Result<(Option<V>, Proof)> {
+        let (bytes, proof) = self.db.get_with_proof(&self.keys.encode(key)?, None)?;
+        let value = bytes.map(|bytes| self.values.decode(&bytes)).transpose()?;
+        Ok((value, proof))
+    }
+
+    /// 读取键的值，并按 `root_hash` 验证后才解码；验证失败返回 `Error::RootMismatch`。
+    /// 证明无法表明键不存在，键不存在或已删除时返回 `None`
+    pub fn get_verified(&self, key: &K, root_hash: &Root) -> Result<Option<V>> {
+        let encoded_key = self.keys.encode(key)?;
+        let (bytes, proof) = self.db.get_with_proof(&encoded_key, None)?;
+        let Some(bytes) = bytes else {
+            return Ok(None);
+        };
+        if !proof.verify(root_hash, &encoded_key, &bytes) {
+            return Err(Error::RootMismatch {
+                actual: proof.root_hash(),
+            });
+        }
+        self.values.decode(&bytes).map(Some)
+    }
+}
+
+impl Proof {
+    /// 同 `verify`，先以生成证明时的编码把键和期望值编码为字节
+    pub fn verify_typed<K, V>(
+        &self,
+        root_hash: &Root,
+        keys: &impl Codec<K>,
+        values: &impl Codec<V>,
+        key: &K,
+        expected_value: &V,
+    ) -> Result<bool> {
+        Ok(self.verify(
+            root_hash,
+            &keys.encode(key)?,
+            &values.encode(expected_value)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 定长小端编码，用于不依赖可选特性的测试
+    struct LittleEndian;
+
+    impl Codec<u64> for LittleEndian {
+        fn encode(&self, value: &u64) -> Result<Vec<u8>> {
+            Ok(value.to_le_bytes().to_vec())
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<u64> {
+            let bytes = bytes
+                .try_into()
+                .map_err(|_| Error::Codec(format!("expected 8 bytes, got {}", bytes.len())))?;
+            Ok(u64::from_le_bytes(bytes))
+        }
+    }
+
+    #[test]
+    fn test_typed_access() {
+        let _ = std::fs::remove_dir_all("./test_data/typed");
+        let db = Database::new("./test_data/typed").unwrap();
+        let balances = db.typed::<String, u64, _, _>(Bytes, LittleEndian);
+        balances.put(&"alice".to_string(), &100).unwrap();
+        let root = balances.put(&"bob".to_string(), &7).unwrap();
+
+        assert_eq!(balances.get(&"alice".to_string()).unwrap(), Some(100));
+        assert_eq!(
+            db.get(b"bob", None).unwrap(),
+            Some(7u64.to_le_bytes().to_vec())
+        );
+        assert!(balances.get(&"carol".to_string()).unwrap().is_none());
+
+        let (value, proof) = balances.get_with_proof(&"bob".to_string()).unwrap();
+        assert_eq!(value, Some(7));
+        assert!(proof
+            .verify_typed(&root, &Bytes, &LittleEndian, &"bob".to_string(), &7)
+            .unwrap());
+        assert!(!proof
+            .verify_typed(&root, &Bytes, &LittleEndian, &"bob".to_string(), &8)
+            .unwrap());
+        assert_eq!(
+            balances.get_verified(&"bob".to_string(), &root).unwrap(),
+            Some(7)
+        );
+        assert!(matches!(
+            balances.get_verified(&"bob".to_string(), &Root::default()),
+            Err(Error::RootMismatch { .. })
+        ));
+
+        db.put(b"broken", b"short").unwrap();
+        assert!(matches!(
+            balances.get(&"broken".to_string()),
+            Err(Error::Codec(_))
+        ));
+        balances.delete(&"alice".to_string()).unwrap();
+        assert!(balances.get(&"alice".to_string()).unwrap().is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_codecs() {
+        let _ = std::fs::remove_dir_all("./test_data/typed_serde");
+        let db = Database::new("./test_data/typed_serde").unwrap();
+        let json = db.typed::<String, Vec<u32>, _, _>(Bytes, Json);
+        json.put(&"k".to_string(), &vec![1, 2]).unwrap();
+        assert_eq!(db.get(b"k", None).unwrap(), Some(b"[1,2]".to_vec()));
+        let bincode = db.typed::<(u8, u8), String, _, _>(Bincode, Bincode);
+        bincode.put(&(1, 2), &"v".to_string()).unwrap();
+        assert_eq!(bincode.get(&(1, 2)).unwrap(), Some("v".to_string()));
+    }
+}
@@ -0,0 +1,216 @@
+//! 键更新回调
+//! `Database::on_key_update` 登记的回调在写入提交之后、写入调用返回之前同步执行，参数为以给定前缀开头的
+//! 每个被写入键的旧值和新值，进程内的缓存可以据此与数据库一同更新，不必再比较版本间的差异。
+//!
+//! 有回调匹配写入的键时，写入持有回调锁依次读出旧值、提交、读回新值并执行回调，同一个键的回调按提交的顺序执行；
+//! 没有回调匹配时不加锁，也不多读。回调只覆盖经由本 `Database` 的写入（包括命名树、二级索引等内部键），
+//! 不包括其他进程中的写入；回调中不能写入本数据库，否则死锁。值没有变化的键不调用回调。
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::{Database, ReadOptions, Result};
+
+/// 键更新回调，见 `Database::on_key_update`
+pub type UpdateCallback = dyn Fn(&KeyUpdate<'_>) + Send + Sync;
+
+/// 一个键在一次提交中的变化；不存在或已删除的值为 `None`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyUpdate<'a> {
+    pub key: &'a [u8],
+    pub old: Option<&'a [u8]>,
+    pub new: Option<&'a [u8]>,
+}
+
+/// 登记的回调，用于 `Database::remove_key_update`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UpdateCallbackId(u64);
+
+type Registration = (UpdateCallbackId, Vec<u8>, Arc<UpdateCallback>);
+
+/// 键、旧值、匹配的回调
+type MatchedKey = (Vec<u8>, Option<Vec<u8>>, Vec<Arc<UpdateCallback>>);
+
+#[derive(Default)]
+pub(crate) struct KeyUpdates {
+    callbacks: Mutex<Vec<Registration>>,
+    next_id: AtomicU64,
+    /// 有回调匹配的写入持有，读旧值、提交和回调不与其他这样的写入交错
+    commit: Mutex<()>,
+}
+
+/// 匹配回调的一次写入，见 `Database::begin_updates`
+pub(crate) struct PendingUpdates<'a> {
+    _commit: MutexGuard<'a, ()>,
+    keys: Vec<MatchedKey>,
+}
+
+impl Database {
+    /// 登记回调：此后以 `prefix` 开头的键每次被写入并提交，都以其旧值和新值调用 `callback`
+    pub fn on_key_update<F>(&self, prefix: &[u8], callback: F) -> UpdateCallbackId
+    where
+        F: Fn(&KeyUpdate<'_>) + Send + Sync + 'static,
+    {
+        let updates = &self.key_updates;
+        let id = UpdateCallbackId(updates.next_id.fetch_add(1, Ordering::Relaxed));
+        updates
+            .callbacks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push((id, prefix.to_vec(), Arc::new(callback)));
+        id
+    }
+
+    /// 撤销回调，返回它是否仍在登记中；正在执行的写入仍可能调用它一次
+    pub fn remove_key_update(&self, id: UpdateCallbackId) -> bool {
+        let mut callbacks = self
+            .key_updates
+            .callbacks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let before = callbacks.len();
+        callbacks.retain(|(registered, _, _)| *registered != id);
+        callbacks.len() != before
+    }
+
+    /// 写入之前调用：有回调匹配 `keys` 中的键时持有回调锁并读出这些键的旧值
+    pub(crate) fn begin_updates<'k>(
+        &self,
+        keys: impl IntoIterator<Item = &'k [u8]>,
+    ) -> Result<Option<PendingUpdates<'_>>> {
+        let matched: Vec<_> = {
+            let callbacks = self
+                .key_updates
+                .callbacks
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if callbacks.is_empty() {
+                return Ok(None);
+            }
+            let mut seen = HashSet::new();
+            keys.into_iter()
+                .filter(|key| seen.insert(*key))
+                .filter_map(|key| {
+                    let matching: Vec<_> = callbacks
+                        .iter()
+                        .filter(|(_, prefix, _)| key.starts_with(prefix))
+                        .map(|(_, _, callback)| Arc::clone(callback))
+                        .collect();
+                    (!matching.is_empty()).then(|| (key.to_vec(), matching))
+                })
+                .collect()
+        };
+        if matched.is_empty() {
+            return Ok(None);
+        }
+        let commit = self
+            .key_updates
+            .commit
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut keys = Vec::with_capacity(matched.len());
+        for (key, callbacks) in matched {
+            let old = self.get_with(&key, None, &ReadOptions::new())?;
+            keys.push((key, old, callbacks));
+        }
+        Ok(Some(PendingUpdates {
+            _commit: commit,
+            keys,
+        }))
+    }
+
+    /// 提交成功之后调用：读回新值并执行回调，返回后释放回调锁
+    pub(crate) fn finish_updates(&self, pending: Option<PendingUpdates<'_>>) -> Result<()> {
+        let Some(pending) = pending else {
+            return Ok(());
+        };
+        for (key, old, callbacks) in &pending.keys {
+            let new = self.get_with(key, None, &ReadOptions::new())?;
+            if *old == new {
+                continue;
+            }
+            let update = KeyUpdate {
+                key,
+                old: old.as_deref(),
+                new: new.as_deref(),
+            };
+            for callback in callbacks {
+                callback(&update);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::WriteBatch;
+
+    #[test]
+    fn test_key_updates() {
+        let _ = std::fs::remove_dir_all("./test_data/key_updates");
+        let db = Database::new("./test_data/key_updates").unwrap();
+        db.put(b"acct/alice", b"1").unwrap();
+
+        // 进程内缓存随提交更新
+        let cache = Arc::new(Mutex::new(BTreeMap::<Vec<u8>, Vec<u8>>::new()));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let id = {
+            let (cache, seen) = (Arc::clone(&cache), Arc::clone(&seen));
+            db.on_key_update(b"acct/", move |update| {
+                let mut cache = cache.lock().unwrap();
+                match update.new {
+                    Some(value) => cache.insert(update.key.to_vec(), value.to_vec()),
+                    None => cache.remove(update.key),
+                };
+                seen.lock().unwrap().push((
+                    update.key.to_vec(),
+                    update.old.map(<[u8]>::to_vec),
+                    update.new.map(<[u8]>::to_vec),
+                ));
+            })
+        };
+
+        db.put(b"acct/alice", b"2").unwrap();
+        db.put(b"other", b"x").unwrap();
+        let mut batch = WriteBatch::new();
+        batch
+            .put(b"acct/bob", b"5")
+            .put(b"acct/alice", b"2")
+            .put(b"misc", b"y");
+        db.write_batch(&batch).unwrap();
+        db.delete(b"acct/bob").unwrap();
+        db.delete(b"acct/nobody").unwrap();
+        db.put_from_reader(b"acct/carol", &mut &b"7"[..], None)
+            .unwrap();
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                (
+                    b"acct/alice".to_vec(),
+                    Some(b"1".to_vec()),
+                    Some(b"2".to_vec())
+                ),
+                (b"acct/bob".to_vec(), None, Some(b"5".to_vec())),
+                (b"acct/bob".to_vec(), Some(b"5".to_vec()), None),
+                (b"acct/carol".to_vec(), None, Some(b"7".to_vec())),
+            ]
+        );
+        let expected: BTreeMap<_, _> = db.prefix_iter(b"acct/").collect::<Result<_>>().unwrap();
+        // 缓存只含登记之后写入的键
+        assert_eq!(cache.lock().unwrap().len(), 2);
+        for (key, value) in cache.lock().unwrap().iter() {
+            assert_eq!(expected.get(key), Some(value));
+        }
+
+        assert!(db.remove_key_update(id));
+        assert!(!db.remove_key_update(id));
+        db.put(b"acct/alice", b"3").unwrap();
+        assert_eq!(seen.lock().unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,298 @@
+//! 版本快照
+//! 数据库版本是写入提交的序号：每次 `put`/`delete`/`write_batch` 等引擎提交后加1，新数据库为0；
+//! 以 `Versioning::PerCommit` 打开时 `put`/`delete` 不单独提交，由 `Database::commit` 一并提交。
+//! `Snapshot` 持有引擎的快照句柄，其上的所有读取都返回同一个版本提交后的状态，之后的写入不会改变读到的结果；
+//! 快照存活期间登记其时间点，保留策略不会删除该版本可见的值。快照析构时释放引擎句柄。
+
+use std::ops::RangeBounds;
+use std::os::raw::c_int;
+use std::ptr;
+
+use crate::keys::prefix_successor;
+use crate::retention::PinGuard;
+use crate::{
+    amdb_commit, amdb_free_result, amdb_get_state_version, amdb_snapshot_close, amdb_snapshot_get,
+    amdb_snapshot_info, amdb_snapshot_open, amdb_snapshot_open_at_root, engine_bounds, result_bytes,
+    AmdbResult, AmdbSnapshot, CursorOptions, Database, Entry, Iter, Result, Root, Version,
+};
+
+/// 某个数据库版本的一致只读视图，见 `Database::snapshot_at`
+pub struct Snapshot<'a> {
+    db: &'a Database,
+    raw: *mut AmdbSnapshot,
+    version: Version,
+    root_hash: Root,
+    _pin: PinGuard<'a>,
+}
+
+impl Database {
+    /// 最近一次提交的数据库版本
+    pub fn state_version(&self) -> Result<Version> {
+        let mut version = 0;
+        let handle = self.live_handle()?;
+        let status = unsafe { amdb_get_state_version(*handle, &mut version) };
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        Ok(Version(version))
+    }
+
+    /// 把上次提交之后的 `put`/`delete` 提交为一个新的数据库版本（`Versioning::PerCommit`），
+    /// 返回提交后的数据库版本和根哈希；没有这样的写入时（包括 `Versioning::PerPut`）不产生新版本
+    pub fn commit(&self) -> Result<(Version, Root)> {
+        let (mut version, mut root_hash) = (0, Root::default());
+        let handle = self.live_handle()?;
+        let status = self
+            .retry_status(|| unsafe { amdb_commit(*handle, &mut version, root_hash.as_mut_ptr()) });
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        Ok((Version(version), root_hash))
+    }
+
+    /// 数据库版本 `version`（从1开始）的快照；没有该版本时返回 `Error::NotFound`
+    pub fn snapshot_at(&self, version: Version) -> Result<Snapshot<'_>> {
+        let handle = self.live_handle()?;
+        self.open_snapshot(|raw| unsafe { amdb_snapshot_open(*handle, version.0, raw) })
+    }
+
+    /// 根哈希为 `root_hash` 的最近一个数据库版本的快照；没有提交产生过该根哈希时返回 `Error::NotFound`
+    pub fn snapshot_at_root(&self, root_hash: &Root) -> Result<Snapshot<'_>> {
+        let handle = self.live_handle()?;
+        self.open_snapshot(|raw| unsafe {
+            amdb_snapshot_open_at_root(*handle, root_hash.as_ptr(), raw)
+        })
+    }
+
+    fn open_snapshot(
+        &self,
+        open: impl Fn(&mut *mut AmdbSnapshot) -> c_int,
+    ) -> Result<Snapshot<'_>> {
+        let (pin, (raw, version, root_hash)) = self.retain_from(|| {
+            let mut raw = ptr::null_mut();
+            let status = self.retry_status(|| open(&mut raw));
+            if status != 0 {
+                return Err(self.engine_error(status));
+            }
+            let (mut version, mut at, mut root_hash) = (0, 0.0, Root::default());
+            let status =
+                unsafe { amdb_snapshot_info(raw, &mut version, &mut at, root_hash.as_mut_ptr()) };
+            if status != 0 {
+                unsafe { amdb_snapshot_close(raw) };
+                return Err(self.engine_error(status));
+            }
+            Ok((at, (raw, Version(version), root_hash)))
+        })?;
+        Ok(Snapshot {
+            db: self,
+            raw,
+            version,
+            root_hash,
+            _pin: pin,
+        })
+    }
+}
+
+impl<'a> Snapshot<'a> {
+    /// 快照对应的数据库版本
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// 该版本提交后的根哈希
+    pub fn root_hash(&self) -> Root {
+        self.root_hash
+    }
+
+    /// 读取键在该版本中的值；键尚未写入或已删除时返回 `None`
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let mut result = AmdbResult {
+            status: 0,
+            error_msg: ptr::null(),
+            data: ptr::null_mut(),
+            data_len: 0,
+        };
+        let _handle = self.db.live_handle()?;
+        let status = self.db.retry_status(|| unsafe {
+            amdb_snapshot_get(self.raw, key.as_ptr(), key.len(), &mut result)
+        });
+        if status == -2 {
+            // AMDB_NOT_FOUND
+            return Ok(None);
+        }
+        if status != 0 {
+            return Err(self.db.engine_error(status));
+        }
+        let data = result_bytes(&result);
+        unsafe { amdb_free_result(&mut result) };
+        self.db.open_value(data).map(Some)
+    }
+
+    /// 按键的升序迭代该版本中 `range` 内的键值对，见 `Database::iter`
+    pub fn iter(&self, range: impl RangeBounds<Vec<u8>>) -> Iter<'_> {
+        self.iter_with(range, &CursorOptions::new())
+    }
+
+    pub fn prefix_iter(&self, prefix: &[u8]) -> Iter<'_> {
+        self.prefix_iter_with(prefix, &CursorOptions::new())
+    }
+
+    /// `CursorOptions::pinned` 在快照上没有作用
+    pub fn iter_with(&self, range: impl RangeBounds<Vec<u8>>, options: &CursorOptions) -> Iter<'_> {
+        self.iter_bounds(engine_bounds(&range), options)
+    }
+
+    pub fn prefix_iter_with(&self, prefix: &[u8], options: &CursorOptions) -> Iter<'_> {
+        let bounds = (prefix.to_vec(), prefix_successor(prefix));
+        self.iter_bounds(Some(bounds), options)
+    }
+
+    /// 迭代器拥有快照，见 `Database::resume_cursor`
+    pub(crate) fn into_iter_bounds(
+        self,
+        bounds: Option<(Vec<u8>, Vec<u8>)>,
+        options: &CursorOptions,
+    ) -> Iter<'a> {
+        let state = Some((self.version, self.root_hash));
+        Iter::on_snapshot(self.db, self.raw, state, bounds, options).owning(self)
+    }
+
+    fn iter_bounds(&self, bounds: Option<(Vec<u8>, Vec<u8>)>, options: &CursorOptions) -> Iter<'_> {
+        let state = Some((self.version, self.root_hash));
+        Iter::on_snapshot(self.db, self.raw, state, bounds, options)
+    }
+}
+
+impl<'s> IntoIterator for &'s Snapshot<'_> {
+    type Item = Result<Entry>;
+    type IntoIter = Iter<'s>;
+
+    /// 迭代该版本中的全部键值对
+    fn into_iter(self) -> Iter<'s> {
+        self.iter(..)
+    }
+}
+
+impl Drop for Snapshot<'_> {
+    fn drop(&mut self) {
+        // 快照持有自己的引用，数据库关闭后同样可以释放
+        unsafe { amdb_snapshot_close(self.raw) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, OpenOptions, Versioning, WriteBatch};
+
+    use super::*;
+
+    #[test]
+    fn test_snapshot_at_version() {
+        let _ = std::fs::remove_dir_all("./test_data/versioned");
+        let db = Database::new("./test_data/versioned").unwrap();
+        assert_eq!(db.state_version().unwrap(), 0);
+        db.put(b"a", b"1").unwrap();
+        let root = db.put(b"b", b"1").unwrap();
+        let version = db.state_version().unwrap();
+        assert_eq!(version, 2);
+
+        let mut batch = WriteBatch::new();
+        batch.put(b"a", b"2").delete(b"b").put(b"c", b"2");
+        db.write_batch(&batch).unwrap();
+        assert_eq!(db.state_version().unwrap(), 3);
+
+        let snapshot = db.snapshot_at(version).unwrap();
+        assert_eq!(snapshot.version(), version);
+        assert_eq!(snapshot.root_hash(), root);
+        // 快照打开后的写入不影响读到的结果
+        db.put(b"b", b"3").unwrap();
+        assert_eq!(snapshot.get(b"a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(snapshot.get(b"b").unwrap(), Some(b"1".to_vec()));
+        assert!(snapshot.get(b"c").unwrap().is_none());
+        let entries: Vec<Entry> = snapshot.iter(..).map(|e| e.unwrap()).collect();
+        assert_eq!(
+            entries,
+            vec![
+                (b"a".to_vec(), b"1".to_vec()),
+                (b"b".to_vec(), b"1".to_vec())
+            ]
+        );
+
+        let mut keys = Vec::new();
+        for entry in &snapshot {
+            keys.push(entry.unwrap().0);
+        }
+        assert_eq!(keys, vec![b"a".to_vec(), b"b".to_vec()]);
+
+        let first = db.snapshot_at(Version(1)).unwrap();
+        assert!(first.get(b"b").unwrap().is_none());
+        assert_eq!(first.prefix_iter(b"a").count(), 1);
+
+        let latest = db.snapshot_at(db.state_version().unwrap()).unwrap();
+        assert!(latest.iter(..).map(|e| e.unwrap().0).eq([
+            b"a".to_vec(),
+            b"b".to_vec(),
+            b"c".to_vec()
+        ]));
+        assert!(matches!(db.snapshot_at(Version(0)), Err(Error::NotFound)));
+        assert!(matches!(db.snapshot_at(Version(99)), Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn test_snapshot_at_root() {
+        let _ = std::fs::remove_dir_all("./test_data/versioned_root");
+        let db = Database::new("./test_data/versioned_root").unwrap();
+        let root = db.put(b"k", b"old").unwrap();
+        db.put(b"k", b"new").unwrap();
+
+        let snapshot = db.snapshot_at_root(&root).unwrap();
+        assert_eq!(snapshot.version(), 1);
+        assert_eq!(snapshot.get(b"k").unwrap(), Some(b"old".to_vec()));
+        assert!(matches!(
+            db.snapshot_at_root(&Root([7; 32])),
+            Err(Error::NotFound)
+        ));
+
+        // 快照可以比数据库活得更久，之后的读取返回 `Error::Closed`
+        db.close().unwrap();
+        assert!(matches!(snapshot.get(b"k"), Err(Error::Closed)));
+    }
+
+    #[test]
+    fn test_per_commit_versioning() {
+        let _ = std::fs::remove_dir_all("./test_data/versioned_commit");
+        let db = OpenOptions::new()
+            .versioning(Versioning::PerCommit)
+            .open("./test_data/versioned_commit")
+            .unwrap();
+        db.put(b"a", b"1").unwrap();
+        db.put(b"a", b"2").unwrap();
+        db.put(b"b", b"1").unwrap();
+        db.delete(b"a").unwrap();
+        let root = db.get_root_hash().unwrap();
+        // 未提交的写入立即可读，但不产生数据库版本
+        assert!(db.get(b"a", None).unwrap().is_none());
+        assert_eq!(db.state_version().unwrap(), 0);
+
+        assert_eq!(db.commit().unwrap(), (Version(1), root));
+        assert_eq!(db.commit().unwrap(), (Version(1), root));
+        let snapshot = db.snapshot_at(Version(1)).unwrap();
+        assert!(snapshot.get(b"a").unwrap().is_none());
+        assert_eq!(snapshot.get(b"b").unwrap(), Some(b"1".to_vec()));
+
+        // 批量写入同时提交此前的写入
+        db.put(b"d", b"1").unwrap();
+        let mut batch = WriteBatch::new();
+        batch.put(b"e", b"1");
+        db.write_batch(&batch).unwrap();
+        assert_eq!(db.state_version().unwrap(), 2);
+        assert!(db.snapshot_at(Version(2)).unwrap().get(b"d").unwrap().is_some());
+        drop(snapshot);
+        db.close().unwrap();
+
+        // 默认每次写入都是一次提交，commit 不产生新版本
+        let db = Database::new("./test_data/versioned_commit").unwrap();
+        db.put(b"f", b"1").unwrap();
+        assert_eq!(db.commit().unwrap().0, 3);
+    }
+}
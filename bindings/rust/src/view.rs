@@ -0,0 +1,172 @@
+//! 按时间点读取
+//! 根据版本元数据中的提交时间，读取某一时刻（例如“昨天零点”）的状态：
+//! 每个键取提交时间不晚于该时刻的最后一个版本
+//!
+//! 已被保留策略或后台清理删除的版本无法再读到，需要历史状态时请相应地设置 `Retention`。
+
+use std::ops::RangeBounds;
+use std::ptr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::vec;
+
+use crate::reserved::ReservedFilter;
+use crate::{
+    amdb_free_result, amdb_get_at_time, engine_bounds, result_bytes, AmdbResult,
+    Database, Error, KeyValue, Result,
+};
+
+/// 固定时刻的只读视图，见 `Database::view_as_of`
+pub struct HistoricalView<'a> {
+    db: &'a Database,
+    at: f64,
+}
+
+impl Database {
+    /// 读取键在 `timestamp` 时刻的值；该时刻键尚未写入或已删除时返回 `None`
+    pub fn get_as_of(&self, key: &[u8], timestamp: SystemTime) -> Result<Option<Vec<u8>>> {
+        self.get_at(key, unix_seconds(timestamp)?)
+    }
+
+    /// `timestamp` 时刻的只读视图
+    pub fn view_as_of(&self, timestamp: SystemTime) -> Result<HistoricalView<'_>> {
+        Ok(HistoricalView {
+            db: self,
+            at: unix_seconds(timestamp)?,
+        })
+    }
+
+    fn get_at(&self, key: &[u8], at: f64) -> Result<Option<Vec<u8>>> {
+        let mut result = AmdbResult {
+            status: 0,
+            error_msg: ptr::null(),
+            data: ptr::null_mut(),
+            data_len: 0,
+        };
+        let handle = self.live_handle()?;
+        let status = self.retry_status(|| unsafe {
+            amdb_get_at_time(*handle, key.as_ptr(), key.len(), at, &mut result)
+        });
+        if status == -2 {
+            // AMDB_NOT_FOUND
+            return Ok(None);
+        }
+        if status != 0 {
+            return Err(self.engine_error(status));
+        }
+        let data = result_bytes(&result);
+        unsafe { amdb_free_result(&mut result) };
+        self.open_value(data).map(Some)
+    }
+}
+
+impl HistoricalView<'_> {
+    /// 视图对应的时刻
+    pub fn timestamp(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs_f64(self.at)
+    }
+
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.db.get_at(key, self.at)
+    }
+
+    /// 按键升序读出范围内在该时刻存在的键值对；一次读出整个范围
+    pub fn scan(&self, range: impl RangeBounds<Vec<u8>>) -> Result<Vec<KeyValue>> {
+        let Some((start, end)) = engine_bounds(&range) else {
+            return Ok(Vec::new());
+        };
+        let mut entries = self.db.range_query_at(&start, &end, self.at)?;
+        ReservedFilter::from_start(&start).retain(&mut entries);
+        Ok(entries
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.into_boxed_slice(), value?.into_boxed_slice())))
+            .collect())
+    }
+}
+
+impl IntoIterator for &HistoricalView<'_> {
+    type Item = Result<KeyValue>;
+    type IntoIter = HistoricalIter;
+
+    /// 该时刻存在的全部键值对；与 `scan` 相同，一次读出，读取出错时只产生该错误
+    fn into_iter(self) -> HistoricalIter {
+        match self.scan(..) {
+            Ok(entries) => HistoricalIter {
+                entries: entries.into_iter(),
+                error: None,
+            },
+            Err(error) => HistoricalIter {
+                entries: Vec::new().into_iter(),
+                error: Some(error),
+            },
+        }
+    }
+}
+
+/// `&HistoricalView` 的迭代器
+pub struct HistoricalIter {
+    entries: vec::IntoIter<KeyValue>,
+    error: Option<Error>,
+}
+
+impl Iterator for HistoricalIter {
+    type Item = Result<KeyValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.error.take() {
+            Some(error) => Some(Err(error)),
+            None => self.entries.next().map(Ok),
+        }
+    }
+}
+
+fn unix_seconds(timestamp: SystemTime) -> Result<f64> {
+    timestamp
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .map_err(|_| Error::InvalidArgument("timestamp is before the Unix epoch".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_reads_as_of_timestamp() {
+        let _ = std::fs::remove_dir_all("./test_data/as_of");
+        let db = Database::new("./test_data/as_of").unwrap();
+        let before = SystemTime::now();
+        thread::sleep(Duration::from_millis(10));
+        db.put(b"a", b"1").unwrap();
+        db.put(b"b", b"1").unwrap();
+        thread::sleep(Duration::from_millis(10));
+        let midpoint = SystemTime::now();
+        thread::sleep(Duration::from_millis(10));
+        db.put(b"a", b"2").unwrap();
+        db.delete(b"b").unwrap();
+        db.put(b"c", b"2").unwrap();
+
+        assert_eq!(db.get_as_of(b"a", midpoint).unwrap(), Some(b"1".to_vec()));
+        assert_eq!(
+            db.get_as_of(b"a", SystemTime::now()).unwrap(),
+            Some(b"2".to_vec())
+        );
+        assert!(db.get_as_of(b"a", before).unwrap().is_none());
+
+        let view = db.view_as_of(midpoint).unwrap();
+        assert_eq!(view.get(b"b").unwrap(), Some(b"1".to_vec()));
+        assert!(view.get(b"c").unwrap().is_none());
+        let keys: Vec<Box<[u8]>> = view.scan(..).unwrap().into_iter().map(|e| e.0).collect();
+        assert_eq!(keys, vec![b"a"[..].into(), b"b"[..].into()]);
+
+        let now = db.view_as_of(SystemTime::now()).unwrap();
+        assert_eq!(now.scan(..).unwrap().len(), 2);
+        let mut values = Vec::new();
+        for entry in &view {
+            let (_, value) = entry.unwrap();
+            values.push(value);
+        }
+        assert_eq!(values, vec![b"1"[..].into(), b"1"[..].into()]);
+    }
+}
@@ -20,6 +20,7 @@ class DatabaseConfig:
     enable_sharding: bool = True
     shard_count: int = 256
     max_file_size: int = 256 * 1024 * 1024  # 256MB
+    max_open_files: int = 0  # 同时打开的SSTable文件数上限，0为不限制
     
     # LSM树配置
     lsm_memtable_max_size: int = 10 * 1024 * 1024  # 10MB
@@ -103,6 +104,7 @@ class DatabaseConfig:
             db_config.enable_sharding = section.getboolean('enable_sharding', db_config.enable_sharding)
             db_config.shard_count = section.getint('shard_count', db_config.shard_count)
             db_config.max_file_size = section.getint('max_file_size', db_config.max_file_size)
+            db_config.max_open_files = section.getint('max_open_files', db_config.max_open_files)
         
         # LSM树配置
         if config.has_section('lsm'):
@@ -206,6 +208,7 @@ class DatabaseConfig:
         config['database']['enable_sharding'] = str(self.enable_sharding)
         config['database']['shard_count'] = str(self.shard_count)
         config['database']['max_file_size'] = str(self.max_file_size)
+        config['database']['max_open_files'] = str(self.max_open_files)
         
         # LSM树配置
         config.add_section('lsm')
@@ -307,6 +310,8 @@ class DatabaseConfig:
             self.shard_count = int(os.getenv('AMDB_SHARD_COUNT'))
         if os.getenv('AMDB_MAX_FILE_SIZE'):
             self.max_file_size = int(os.getenv('AMDB_MAX_FILE_SIZE'))
+        if os.getenv('AMDB_MAX_OPEN_FILES'):
+            self.max_open_files = int(os.getenv('AMDB_MAX_OPEN_FILES'))
         
         # LSM树配置
         if os.getenv('AMDB_LSM_MEMTABLE_MAX_SIZE'):
@@ -3,11 +3,17 @@ AmDb 主数据库类
 整合所有组件，提供统一的API
 """
 
+import os
 import threading
 import time
 import hashlib
+import dataclasses
 from typing import Optional, Tuple, List, Dict, Any, Callable
 from pathlib import Path
+try:
+    import fcntl
+except ImportError:
+    fcntl = None
 from .storage import StorageEngine
 from .version import VersionManager
 # 完全禁用Cython版本管理器，确保稳定性
@@ -17,6 +23,10 @@ from .transaction import TransactionManager, Transaction
 from .index import IndexManager
 from .audit import AuditLogger
 from .config import DatabaseConfig, load_config, get_config
+from .errors import InvalidOptionError, ReadOnlyError, DatabaseNotFoundError, BusyError
+from .storage.merkle_tree import MerkleTree
+from .metrics import CountHistogram, ProbeCounter
+from . import faults
 
 
 class Database:
@@ -25,12 +35,36 @@ class Database:
     提供完整的数据库功能
     """
     
+    # 运行时打开选项及默认值（见 amdb_init_with_options），每次打开可以不同
+    OPEN_OPTION_DEFAULTS = {
+        'read_only': 'false',
+        'create_if_missing': 'true',
+        'cache_size': '',
+        'node_cache_size': '',
+        'sync': 'normal',
+        'sync_window_us': '',
+        'compression': '',
+        'max_open_files': '',
+        'target_file_size': '',
+        'lock_wait_ms': '',
+        'versioning': 'put',
+    }
+    # normal: 按刷新策略持久化；commit: 每次提交后刷新并fsync；
+    # batched: 提交后至多 sync_window_us 微秒内fsync，窗口内的提交共用一次fsync
+    SYNC_MODES = ('normal', 'commit', 'batched')
+    # put: 每次 put/delete 都是一次提交；commit: put/delete 只改变当前状态，由 commit 产生数据库版本
+    VERSIONING_MODES = ('put', 'commit')
+    DEFAULT_SYNC_WINDOW_US = 2000
+    
     def __init__(self, 
                  data_dir: Optional[str] = None,
                  enable_sharding: Optional[bool] = None,
                  shard_count: Optional[int] = None,
                  max_file_size: Optional[int] = None,
-                 config_path: Optional[str] = None):
+                 config_path: Optional[str] = None,
+                 tree_options: Optional[Dict[str, str]] = None,
+                 options: Optional[Dict[str, str]] = None,
+                 lock_dir: bool = True):
         """
         Args:
             data_dir: 数据目录（如果为None，从配置文件读取）
@@ -38,10 +72,32 @@ class Database:
             shard_count: 分片数量（如果为None，从配置文件读取）
             max_file_size: 单个文件最大大小（如果为None，从配置文件读取）
             config_path: 配置文件路径（如果为None，尝试从默认位置加载）
+            tree_options: Merkle树的创建选项（见 MerkleTree.OPTION_NAMES），仅在新建时生效
+            options: 打开选项（见 OPEN_OPTION_DEFAULTS）与Merkle树创建选项的混合，C接口由此传入
+            lock_dir: 读写打开时是否持有数据目录的锁；命名空间由所在库的锁保护，不单独加锁
         """
+        opened_at = time.monotonic()
+        # 打开过程中发现的问题，见 open_report
+        self._open_warnings: List[str] = []
+        # 读放大：每次 get 查找过的数据结构数、每个证明访问的Merkle节点数，见 read_stats
+        self.read_histograms = {'get': CountHistogram(), 'proof': CountHistogram()}
+        open_options, extra_tree_options = self._split_options(options or {})
+        if extra_tree_options:
+            tree_options = {**(tree_options or {}), **extra_tree_options}
+        self.read_only = open_options['read_only']
+        self.sync_mode = open_options['sync']
+        self.sync_window = open_options['sync_window_us'] / 1e6
+        self.versioning = open_options['versioning']
+        # versioning=commit 时上次提交之后最晚一次 put/delete 的版本时间戳，没有时为None
+        self._uncommitted_at: Optional[float] = None
+        self._sync_timer: Optional[threading.Timer] = None
+        self._sync_timer_lock = threading.Lock()
+        
         # 先确定data_dir，用于查找数据库特定的配置文件
         temp_config = load_config(config_path)
         self.data_dir = data_dir if data_dir is not None else temp_config.data_dir
+        if not Path(self.data_dir).exists() and (self.read_only or not open_options['create_if_missing']):
+            raise DatabaseNotFoundError(f"Data directory not found: {self.data_dir}")
         
         # 尝试加载数据库特定的配置文件（优先级最高）
         db_config_path = Path(self.data_dir) / "database.ini"
@@ -56,19 +112,41 @@ class Database:
             # 使用全局配置
             self.config = temp_config
         
+        # 打开选项覆盖配置文件中的值；配置可能是全局缓存的实例，复制后再修改
+        overrides = {}
+        if open_options['cache_size'] is not None:
+            overrides['cache_size'] = open_options['cache_size']
+        if open_options['compression'] is not None:
+            overrides['compression_enable'] = open_options['compression']
+        if open_options['max_open_files'] is not None:
+            overrides['max_open_files'] = open_options['max_open_files']
+        if open_options['target_file_size'] is not None:
+            # 分片SSTable按该大小分割，未分片的LSM树合并SSTable时不超过该大小
+            overrides['max_file_size'] = open_options['target_file_size']
+        if overrides:
+            self.config = dataclasses.replace(self.config, **overrides)
+        
         # 使用配置值，如果参数提供了值则优先使用参数
         self.data_dir = data_dir if data_dir is not None else self.config.data_dir
         self.enable_sharding = enable_sharding if enable_sharding is not None else self.config.enable_sharding
         shard_count = shard_count if shard_count is not None else self.config.shard_count
         max_file_size = max_file_size if max_file_size is not None else self.config.max_file_size
         
-        self.storage = StorageEngine(
-            self.data_dir, 
-            enable_sharding=self.enable_sharding,
-            shard_count=shard_count,
-            max_file_size=max_file_size,
-            config=self.config
-        )
+        # 读写打开时持有数据目录的独占锁，防止两个实例同时写入同一目录
+        self._lock_file = None
+        if not self.read_only and lock_dir:
+            self._acquire_lock(open_options['lock_wait_ms'] / 1000)
+        
+        # refresh 重新打开存储引擎时沿用
+        self._storage_options = {
+            'enable_sharding': self.enable_sharding,
+            'shard_count': shard_count,
+            'max_file_size': max_file_size,
+            'config': self.config,
+            'tree_options': tree_options,
+        }
+        self._node_cache_size = open_options['node_cache_size']
+        self.storage = self._open_storage()
         # 完全禁用Cython版本管理器，确保稳定性
         # 直接使用纯Python版本管理器，避免任何Cython导入
         self.version_manager = VersionManager(config=self.config)
@@ -83,6 +161,8 @@ class Database:
         self.index_manager.load_from_disk(self.data_dir)
         
         self.lock = threading.RLock()
+        # 已打开的命名空间，见 namespace
+        self._namespaces: Dict[str, 'Database'] = {}
         
         # Flush优化：防抖机制和状态跟踪
         self._flush_lock = threading.RLock()  # flush专用锁，避免与主锁冲突
@@ -92,6 +172,9 @@ class Database:
         self._pending_flush = False  # 是否有待处理的flush请求
         self._flush_thread = None  # 异步flush线程
         
+        # 后台写入（异步WAL等）的第一个异常，见 background_error
+        self._background_error: Optional[str] = None
+        
         # WAL日志（Write-Ahead Log，确保数据不丢失）
         from .storage.wal import WALLogger
         wal_dir = Path(self.data_dir) / "wal"
@@ -114,6 +197,182 @@ class Database:
         
         # 跟踪文件修改时间，用于检测外部更新
         self._last_file_mtime = self._get_version_file_mtime()
+        self._repair_merkle_tree()
+        self._open_report = self._build_open_report(opened_at)
+    
+    def _open_storage(self) -> StorageEngine:
+        storage = StorageEngine(self.data_dir, **self._storage_options)
+        if self._node_cache_size is not None:
+            storage.bplus_tree.cache_size = self._node_cache_size
+        return storage
+    
+    def _repair_merkle_tree(self):
+        """
+        Merkle树文件与最后一次提交不符时（例如刷新到一半时进程退出，两个文件来自不同的提交），
+        由版本记录重建最后一次提交的树；重建结果仍不符时保持原样，由 open_report 报告
+        """
+        commits = self.version_manager.commits
+        tree = self.storage.merkle_tree
+        if not commits or self.versioning != 'put' or tree.get_root_hash() == commits[-1][1]:
+            return
+        at, root_hash = commits[-1]
+        state = {}
+        for key in self.version_manager.versions:
+            visible = self.version_manager.get_at_time(key, at)
+            if visible is not None:
+                state[key] = visible.value
+        root, nodes = tree.build_detached(list(state.items()))
+        if (root.get_hash() if root else tree.empty_hash) != root_hash:
+            return
+        tree.key_value_map = state
+        tree.root = root
+        tree.nodes.update(nodes)
+        self._open_warnings.append(
+            f"Rebuilt the Merkle tree of version {len(commits)} from version history")
+    
+    def _build_open_report(self, opened_at: float) -> Dict[str, Any]:
+        """汇总打开时各部分的加载结果，见 open_report"""
+        warnings = list(self._open_warnings)
+        warnings += getattr(self.storage.merkle_tree, 'load_warnings', [])
+        warnings += self.version_manager.load_warnings
+        wal_records, wal_warnings = self.wal_logger.scan()
+        warnings += wal_warnings
+        commits = self.version_manager.commits
+        if commits and self.versioning == 'put' and self.get_root_hash() != commits[-1][1]:
+            warnings.append(f"Root hash does not match the last commit (version {len(commits)})")
+        return {
+            'wal_records': wal_records,
+            'versions_recovered': len(commits),
+            'key_versions': sum(len(v) for v in self.version_manager.versions.values()),
+            'open_secs': time.monotonic() - opened_at,
+            'warnings': warnings,
+        }
+    
+    def open_report(self) -> Dict[str, Any]:
+        """
+        打开数据库时恢复过程的报告：
+            wal_records: 扫描到的完整WAL记录数。状态以版本记录为准，打开时不重放WAL，只检查其完整性
+            versions_recovered: 从磁盘恢复的数据库版本数
+            key_versions: 恢复的键版本数
+            open_secs: 打开耗时（秒）
+            warnings: 加载时跳过或修复的内容、WAL末尾不完整的记录、根哈希与最后一次提交不符等问题
+        """
+        return dict(self._open_report, warnings=list(self._open_report['warnings']))
+    
+    @classmethod
+    def _split_options(cls, options: Dict[str, str]) -> Tuple[Dict[str, Any], Dict[str, str]]:
+        """把混合的选项分为解析后的打开选项和Merkle树创建选项"""
+        unknown = set(options) - set(cls.OPEN_OPTION_DEFAULTS) - set(MerkleTree.OPTION_NAMES)
+        if unknown:
+            raise InvalidOptionError(f"Unknown options: {sorted(unknown)}")
+        raw = {**cls.OPEN_OPTION_DEFAULTS,
+               **{k: v for k, v in options.items() if k in cls.OPEN_OPTION_DEFAULTS}}
+        tree_options = {k: v for k, v in options.items() if k not in cls.OPEN_OPTION_DEFAULTS}
+        
+        def parse_bool(name):
+            if raw[name] == '':
+                return None
+            if raw[name] not in ('true', 'false'):
+                raise InvalidOptionError(f"{name} must be true or false: {raw[name]!r}")
+            return raw[name] == 'true'
+        
+        def parse_size(name, allow_zero=False):
+            if raw[name] == '':
+                return None
+            if not raw[name].isdigit() or (int(raw[name]) == 0 and not allow_zero):
+                raise InvalidOptionError(f"{name} must be a positive integer: {raw[name]!r}")
+            return int(raw[name])
+        
+        if raw['sync'] not in cls.SYNC_MODES:
+            raise InvalidOptionError(f"Unknown sync mode: {raw['sync']!r}")
+        if raw['versioning'] not in cls.VERSIONING_MODES:
+            raise InvalidOptionError(f"Unknown versioning mode: {raw['versioning']!r}")
+        sync_window_us = parse_size('sync_window_us')
+        if sync_window_us is not None and raw['sync'] != 'batched':
+            raise InvalidOptionError("sync_window_us requires sync=batched")
+        if raw['lock_wait_ms'] != '' and not raw['lock_wait_ms'].isdigit():
+            raise InvalidOptionError(f"lock_wait_ms must be a non-negative integer: {raw['lock_wait_ms']!r}")
+        parsed = {
+            'read_only': parse_bool('read_only'),
+            'create_if_missing': parse_bool('create_if_missing'),
+            'cache_size': parse_size('cache_size'),
+            'node_cache_size': parse_size('node_cache_size'),
+            'sync': raw['sync'],
+            'sync_window_us': sync_window_us or cls.DEFAULT_SYNC_WINDOW_US,
+            'compression': parse_bool('compression'),
+            # 与配置文件一致，0为不限制
+            'max_open_files': parse_size('max_open_files', allow_zero=True),
+            'target_file_size': parse_size('target_file_size'),
+            'lock_wait_ms': int(raw['lock_wait_ms']) if raw['lock_wait_ms'] != '' else 0,
+            'versioning': raw['versioning'],
+        }
+        return parsed, tree_options
+    
+    def _acquire_lock(self, wait: float):
+        """
+        锁定数据目录下的 LOCK 文件，另一个实例持有时最多等待 wait 秒：
+        wait 为0时立即抛出 BusyError，等待超时抛出 TimeoutError。不支持 flock 的平台上不加锁
+        """
+        if fcntl is None:
+            return
+        os.makedirs(self.data_dir, exist_ok=True)
+        lock_file = open(os.path.join(self.data_dir, 'LOCK'), 'a+b')
+        deadline = time.monotonic() + wait
+        while True:
+            try:
+                fcntl.flock(lock_file, fcntl.LOCK_EX | fcntl.LOCK_NB)
+                self._lock_file = lock_file
+                return
+            except BlockingIOError:
+                remaining = deadline - time.monotonic()
+                if remaining <= 0:
+                    lock_file.close()
+                    if wait > 0:
+                        raise TimeoutError(f"Timed out waiting for the lock on {self.data_dir}")
+                    raise BusyError(f"Data directory is locked by another instance: {self.data_dir}")
+                time.sleep(min(0.01, remaining))
+    
+    def release_lock(self):
+        """释放数据目录的锁；C接口关闭句柄时调用，之后不应再写入"""
+        if self._lock_file is not None:
+            self._lock_file.close()
+            self._lock_file = None
+    
+    def _check_writable(self):
+        if self.read_only:
+            raise ReadOnlyError(f"Database opened read-only: {self.data_dir}")
+    
+    def _after_commit(self):
+        """按同步模式持久化刚完成的提交"""
+        faults.check('commit')
+        if self.sync_mode == 'commit':
+            self.sync()
+        elif self.sync_mode == 'batched':
+            self._schedule_sync()
+    
+    def _schedule_sync(self):
+        """窗口内尚未安排fsync时安排一次；之后窗口内的提交由同一次fsync持久化"""
+        with self._sync_timer_lock:
+            if self._sync_timer is not None:
+                return
+            timer = threading.Timer(self.sync_window, self._scheduled_sync)
+            timer.daemon = True
+            self._sync_timer = timer
+            timer.start()
+    
+    def _scheduled_sync(self):
+        with self._sync_timer_lock:
+            self._sync_timer = None
+        try:
+            self.sync()
+        except Exception:
+            pass  # 下一次提交会重新安排
+    
+    def _cancel_scheduled_sync(self):
+        with self._sync_timer_lock:
+            timer, self._sync_timer = self._sync_timer, None
+        if timer is not None:
+            timer.cancel()
     
     def put(self, key: bytes, value: bytes) -> Tuple[bool, bytes]:
         """
@@ -122,12 +381,14 @@ class Database:
         Returns:
             (success, merkle_root_hash)
         """
+        self._check_writable()
         with self.lock:
             # 创建新版本（内存操作，快速）
             version_obj = self.version_manager.create_version(key, value)
             
             # 写入存储引擎（LSM树MemTable，内存操作，快速）
             merkle_root = self.storage.put(key, value, version_obj.version)
+            committed = self._record_write(version_obj.timestamp, merkle_root)
             
             # 更新索引（内存操作，快速）
             self.index_manager.put(
@@ -141,15 +402,16 @@ class Database:
                     def async_wal():
                         try:
                             self.wal_logger.log_put(key, value, version_obj.version)
-                        except Exception:
-                            pass
+                        except Exception as e:
+                            self._record_background_error("WAL写入失败", e)
                     self._wal_thread = threading.Thread(target=async_wal, daemon=True)
                     self._wal_thread.start()
                 else:
                     # 如果线程还在运行，直接写入（WAL内部有锁保护）
                     self.wal_logger.log_put(key, value, version_obj.version)
-            except Exception:
-                pass  # WAL失败不应影响主操作
+            except Exception as e:
+                # WAL失败不应影响主操作，但要记录下来
+                self._record_background_error("WAL写入失败", e)
             
             # 异步记录审计日志（不阻塞主流程）
             if self.audit_logger:
@@ -164,8 +426,165 @@ class Database:
                 except Exception:
                     pass  # 审计日志失败不应影响主操作
             
+            if committed:
+                self._after_commit()
             return (True, merkle_root)
     
+    def _record_write(self, timestamp: float, merkle_root: bytes) -> bool:
+        """记录一次 put/delete：versioning=put 时即为一次提交，否则留给下一次 commit；返回是否提交"""
+        if self.versioning == 'put':
+            self.version_manager.record_commit(timestamp, merkle_root)
+            return True
+        self._uncommitted_at = timestamp
+        return False
+    
+    def commit(self) -> Tuple[int, bytes]:
+        """
+        把上次提交之后的 put/delete 记为一次提交（versioning=commit），没有这样的写入时不产生新版本
+        Returns:
+            (提交后的数据库版本, 根哈希)
+        """
+        self._check_writable()
+        with self.lock:
+            if self._uncommitted_at is None:
+                return (len(self.version_manager.commits), self.get_root_hash())
+            merkle_root = self.get_root_hash()
+            number = self.version_manager.record_commit(self._uncommitted_at, merkle_root)
+            self._uncommitted_at = None
+            self._after_commit()
+            return (number, merkle_root)
+    
+    def commit_batch(self, items: List[Tuple[bytes, bytes]],
+                     stats: Optional[Dict[str, Any]] = None) -> Tuple[bool, bytes]:
+        """
+        在一次提交中写入多个键值对，只计算一次Merkle根哈希，不产生中间根
+        Args:
+            items: [(key, value), ...]，同一个键以最后一项为准；值为None表示删除，空值照常写入
+            stats: 传入时填入本次提交的统计：inserted/updated/deleted（按键计，
+                删除不存在的键不计入）、bytes_written、nodes_touched（新产生的Merkle节点数）、
+                hash_time（计算根哈希）/io_time（写入存储、WAL及同步）的耗时（秒）
+        Returns:
+            (success, merkle_root_hash)
+        """
+        self._check_writable()
+        if not items:
+            if stats is not None:
+                stats.update(self._empty_commit_stats())
+            return (True, self.get_root_hash())
+        items = self._with_tombstones(items)
+        with self.lock:
+            if stats is not None:
+                stats.update(self._count_changes(items))
+            versioned = []
+            for key, value in items:
+                version_obj = self.version_manager.create_version(key, value)
+                versioned.append((key, value, version_obj))
+            merkle_root = self.storage.put_many(
+                [(key, value, v.version) for key, value, v in versioned], stats
+            )
+            # 同时提交此前未提交的 put/delete：它们的版本时间戳都更早
+            self.version_manager.record_commit(
+                max(v.timestamp for _, _, v in versioned), merkle_root
+            )
+            self._uncommitted_at = None
+            started = time.perf_counter()
+            for key, value, version_obj in versioned:
+                self.index_manager.put(
+                    key, value, version_obj.version, version_obj.timestamp
+                )
+                try:
+                    self.wal_logger.log_put(key, value, version_obj.version)
+                except Exception as e:
+                    self._record_background_error("WAL写入失败", e)
+                if self.audit_logger:
+                    try:
+                        self.audit_logger.log_put(key, value)
+                    except Exception:
+                        pass
+            self._after_commit()
+            if stats is not None:
+                stats['io_time'] += time.perf_counter() - started
+            return (True, merkle_root)
+    
+    def commit_batch_versioned(self, items: List[Tuple[bytes, Optional[bytes]]]) -> Tuple[int, bytes]:
+        """
+        同 commit_batch，并在同一把锁内读取提交后的数据库版本，不会混入其他写入产生的版本
+        Returns:
+            (提交后的数据库版本, merkle_root_hash)
+        """
+        with self.lock:
+            _, merkle_root = self.commit_batch(items)
+            return (self.get_state_version(), merkle_root)
+    
+    def batch_root_hash(self, items: List[Tuple[bytes, Optional[bytes]]]) -> bytes:
+        """commit_batch(items) 之后的Merkle根哈希；只计算，不写入任何数据"""
+        with self.lock:
+            if not items:
+                return self.get_root_hash()
+            return self.storage.merkle_tree.root_hash_with(self._with_tombstones(items))
+    
+    def batch_root_hash_at(self, version: int,
+                           items: List[Tuple[bytes, Optional[bytes]]]) -> Optional[bytes]:
+        """
+        在数据库版本 version 的状态之上 commit_batch(items) 之后的Merkle根哈希；只计算，不写入任何数据。
+        版本0是新数据库的空状态；没有该版本或已被清理时返回None
+        """
+        with self.lock:
+            if version == self.get_state_version() and self._uncommitted_at is None:
+                return self.batch_root_hash(items)
+            state: Dict[bytes, bytes] = {}
+            if version > 0:
+                commit = self.version_manager.get_commit(version)
+                if commit is None:
+                    return None
+                for key in self.version_manager.versions:
+                    visible = self.version_manager.get_at_time(key, commit[0])
+                    if visible is not None:
+                        state[key] = visible.value
+            state.update(self._with_tombstones(items))
+            tree = self.storage.merkle_tree
+            root, _ = tree.build_detached(list(state.items()))
+            return root.get_hash() if root else tree.empty_hash
+    
+    @staticmethod
+    def _with_tombstones(items: List[Tuple[bytes, Optional[bytes]]]) -> List[Tuple[bytes, bytes]]:
+        """批量写入项中值为None的删除换成删除标记"""
+        return [(key, b'__DELETED__' if value is None else value) for key, value in items]
+    
+    @staticmethod
+    def _empty_commit_stats() -> Dict[str, Any]:
+        return {'inserted': 0, 'updated': 0, 'deleted': 0, 'bytes_written': 0,
+                'nodes_touched': 0, 'hash_time': 0.0, 'io_time': 0.0}
+    
+    def _is_live(self, key: bytes) -> bool:
+        """键当前是否有值（未删除）"""
+        latest = self.version_manager.get_latest(key)
+        if latest is not None:
+            value = latest.value
+        else:
+            result = self.storage.get(key, use_cache=True)
+            if not result:
+                return False
+            value = result[0]
+        return value != b'__DELETED__'
+    
+    def _count_changes(self, items: List[Tuple[bytes, bytes]]) -> Dict[str, Any]:
+        """按提交前的状态统计一批写入插入、更新和删除的键数"""
+        stats = self._empty_commit_stats()
+        final = dict(items)
+        for key, value in final.items():
+            live = self._is_live(key)
+            if value == b'__DELETED__':
+                if live:
+                    stats['deleted'] += 1
+            elif live:
+                stats['updated'] += 1
+            else:
+                stats['inserted'] += 1
+        stats['bytes_written'] = sum(len(key) + (0 if value == b'__DELETED__' else len(value))
+                                     for key, value in items)
+        return stats
+    
     def delete(self, key: bytes) -> bool:
         """
         删除数据（标记删除）
@@ -177,6 +596,7 @@ class Database:
         Returns:
             是否成功标记删除
         """
+        self._check_writable()
         with self.lock:
             # 使用特殊标记值表示已删除
             # 在版本管理器中创建一个删除标记版本
@@ -184,7 +604,8 @@ class Database:
             version_obj = self.version_manager.create_version(key, deleted_value)
             
             # 写入存储引擎（标记为已删除）
-            self.storage.put(key, deleted_value, version_obj.version)
+            merkle_root = self.storage.put(key, deleted_value, version_obj.version)
+            committed = self._record_write(version_obj.timestamp, merkle_root)
             
             # 更新索引
             self.index_manager.put(
@@ -198,14 +619,14 @@ class Database:
                     def async_wal():
                         try:
                             self.wal_logger.log_put(key, deleted_value, version_obj.version)
-                        except Exception:
-                            pass
+                        except Exception as e:
+                            self._record_background_error("WAL写入失败", e)
                     self._wal_thread = threading.Thread(target=async_wal, daemon=True)
                     self._wal_thread.start()
                 else:
                     self.wal_logger.log_put(key, deleted_value, version_obj.version)
-            except Exception:
-                pass
+            except Exception as e:
+                self._record_background_error("WAL写入失败", e)
             
             # 异步记录审计日志
             if self.audit_logger:
@@ -220,6 +641,8 @@ class Database:
                 except Exception:
                     pass
             
+            if committed:
+                self._after_commit()
             return True
     
     def is_deleted(self, key: bytes) -> bool:
@@ -239,6 +662,14 @@ class Database:
                 return latest.value == b'__DELETED__'
             return False
     
+    def has_history(self, key: bytes) -> bool:
+        """键是否写入过；已删除的键同样返回True"""
+        with self.lock:
+            if self.version_manager.get_latest(key) is not None:
+                return True
+        # 批量写入可能没有创建版本对象，再查存储引擎
+        return bool(self.storage.get(key, use_cache=True))
+    
     def _get_version_file_mtime(self) -> float:
         """获取版本文件的修改时间"""
         try:
@@ -258,6 +689,8 @@ class Database:
             True: 文件已更新并重新加载
             False: 文件未更新
         """
+        if self.read_only:
+            return self.refresh()
         try:
             current_mtime = self._get_version_file_mtime()
             # 如果文件修改时间发生变化，说明有新数据写入或删除
@@ -284,9 +717,17 @@ class Database:
         # 这样即使不重新连接，也能读取到最新数据
         self._check_and_reload_if_updated()
         
+        probes = ProbeCounter()
+        try:
+            return self._get(key, version, probes)
+        finally:
+            self.read_histograms['get'].observe(probes.count)
+    
+    def _get(self, key: bytes, version: Optional[int], probes: ProbeCounter) -> Optional[bytes]:
         # 优化：读取操作减少锁持有时间
         if version is None:
             # 1. 优先从版本管理器获取最新版本（需要锁）
+            probes.add()
             with self.lock:
                 latest = self.version_manager.get_latest(key)
                 if latest:
@@ -298,7 +739,7 @@ class Database:
             # 2. 如果版本管理器没有（可能是批量写入跳过了Version创建），从存储引擎获取
             # 性能优化：批量写入时跳过了Version对象创建，直接从存储引擎读取
             # 同时，为了保持读取性能，我们需要从存储引擎读取并创建Version对象
-            result = self.storage.get(key, use_cache=True)
+            result = self.storage.get(key, use_cache=True, probes=probes)
             if result:
                 value = result[0]
                 # 检查是否已删除
@@ -309,7 +750,7 @@ class Database:
             # 3. 如果存储引擎也没有，尝试直接从LSM树获取（可能数据在MemTable中但未刷新到版本管理器）
             try:
                 if hasattr(self.storage, 'lsm_tree'):
-                    lsm_result = self.storage.lsm_tree.get(key)
+                    lsm_result = self.storage.lsm_tree.get(key, probes)
                     if lsm_result:
                         value = lsm_result[0]
                         if value == b'__DELETED__':
@@ -321,6 +762,7 @@ class Database:
             return None
         else:
             # 读取指定版本（需要锁）
+            probes.add()
             with self.lock:
                 version_obj = self.version_manager.get_version(key, version)
                 if version_obj:
@@ -343,6 +785,20 @@ class Database:
         with self.lock:
             return self.storage.get_with_proof(key)
     
+    def get_with_path_proof(self, key: bytes) -> Tuple[Optional[bytes], int, Optional[bytes], bytes]:
+        """
+        获取最新值、版本号及其路径证明（见 MerkleTree.get_path_proof）
+        Returns:
+            (value, version, proof, root_hash)，键不在Merkle树中时 value 和 proof 为None、version 为0
+        """
+        probes = ProbeCounter()
+        with self.lock:
+            value, proof, root_hash = self.storage.get_with_path_proof(key, probes)
+            if proof is not None:
+                self.read_histograms['proof'].observe(probes.count)
+            latest = self.version_manager.get_latest(key) if proof is not None else None
+            return (value, latest.version if latest else 0, proof, root_hash)
+    
     def verify(self, key: bytes, value: bytes, proof: List[bytes]) -> bool:
         """验证数据完整性"""
         return self.storage.verify(key, value, proof)
@@ -362,11 +818,515 @@ class Database:
                 for v in versions
             ]
     
+    def key_history(self, key: bytes, after_version: int = 0, limit: int = 0,
+                    include_values: bool = True) -> List[Tuple[int, int, bytes, bool, Optional[bytes]]]:
+        """
+        键的版本号大于 after_version 的各个版本，按版本号升序，limit 为0表示不限
+        Returns:
+            [(键的版本号, 数据库版本, 该数据库版本的根哈希, 是否为删除, 值), ...]；
+            删除或 include_values 为False时值为None。已被保留策略删除的版本不在其中
+        """
+        with self.lock:
+            versions = self.version_manager.get_history(key, after_version + 1)
+            if limit > 0:
+                versions = versions[:limit]
+            history = []
+            for v in versions:
+                number = self.version_manager.commit_of(v.timestamp)
+                if number is None:
+                    continue  # 提交记录尚未写入
+                # 不经 get_commit：清理后保留的可见版本可能属于已清理的数据库版本
+                root_hash = self.version_manager.commits[number - 1][1]
+                deleted = v.value == b'__DELETED__'
+                value = v.value if include_values and not deleted else None
+                history.append((v.version, number, root_hash, deleted, value))
+            return history
+    
     def range_query(self, start_key: bytes, end_key: bytes) -> List[Tuple[bytes, bytes]]:
         """范围查询"""
         with self.lock:
             return self.storage.range_query(start_key, end_key)
     
+    def get_state_version(self) -> int:
+        """数据库版本：提交次数，见 VERSIONING_MODES"""
+        return len(self.version_manager.commits)
+    
+    def get_commit(self, version: int) -> Optional[Tuple[float, bytes]]:
+        """
+        数据库版本 version 的 (提交时间, 根哈希)；用 get_at_time(key, 提交时间) 读取该版本的状态
+        """
+        return self.version_manager.get_commit(version)
+    
+    def merkle_at(self, version: int, rebuild: bool = False):
+        """
+        数据库版本 version 的Merkle树 (根哈希, 根节点, 节点表)。节点按哈希存放且不会删除，
+        历史版本的根节点通常仍在当前树的节点表中；不在其中或 rebuild 为True时由该版本的状态重建，
+        并与记录的根哈希比对。没有该版本时返回None，版本0是空树
+        """
+        with self.lock:
+            tree = self.storage.merkle_tree
+            if version == 0:
+                return tree.empty_hash, None, {}
+            commit = self.version_manager.get_commit(version)
+            if commit is None:
+                return None
+            at, root_hash = commit
+            if version == self.get_state_version() and not rebuild:
+                return tree.get_root_hash(), tree.root, tree.nodes
+            if root_hash in tree.nodes and not rebuild:
+                return root_hash, tree.nodes[root_hash], tree.nodes
+            items = []
+            for key in self.version_manager.versions:
+                visible = self.version_manager.get_at_time(key, at)
+                if visible is not None:
+                    items.append((key, visible.value))
+            root, nodes = tree.build_detached(items)
+            rebuilt = root.get_hash() if root else tree.empty_hash
+            if rebuilt != root_hash:
+                raise RuntimeError(f"Merkle tree of version {version} cannot be rebuilt: "
+                                   "versions it depends on have been pruned or purged")
+            return root_hash, root, nodes
+    
+    def merkle_frontier(self, version: int) -> Optional[Tuple[bytes, Optional[Tuple[bytes, bytes, bytes]]]]:
+        """
+        数据库版本 version 的Merkle树右边缘，见 MerkleTree.right_edge
+        Returns:
+            (根哈希, (最大键, 叶子哈希, 路径))，空树时右边缘为None；没有该版本时返回None
+        """
+        with self.lock:
+            merkle = self.merkle_at(version)
+            if merkle is None:
+                return None
+            root_hash, root, nodes = merkle
+            try:
+                return root_hash, self.storage.merkle_tree.right_edge(root, nodes)
+            except (KeyError, StopIteration):
+                # 节点表不完整，重建该版本的树
+                _, root, nodes = self.merkle_at(version, rebuild=True)
+                return root_hash, self.storage.merkle_tree.right_edge(root, nodes)
+    
+    def diff(self, from_version: int,
+             to_version: int) -> Optional[List[Tuple[bytes, Optional[bytes], Optional[bytes]]]]:
+        """
+        两个数据库版本之间值不同的键，及其在 from_version 和 to_version 中的值，按键排序；
+        不存在或已删除的值为None。比较两个版本的Merkle树，不重放提交记录。没有某个版本时返回None
+        """
+        with self.lock:
+            old = self.merkle_at(from_version)
+            new = self.merkle_at(to_version)
+            if old is None or new is None:
+                return None
+            tree = self.storage.merkle_tree
+            try:
+                differences = tree.diff_nodes(old[1], old[2], new[1], new[2])
+            except KeyError:
+                # 节点表不完整（例如加载时跳过了损坏的节点），重建两个版本的树
+                old = self.merkle_at(from_version, rebuild=True)
+                new = self.merkle_at(to_version, rebuild=True)
+                differences = tree.diff_nodes(old[1], old[2], new[1], new[2])
+            changes = []
+            for key, before, after in differences:
+                before = None if before == b'__DELETED__' else before
+                after = None if after == b'__DELETED__' else after
+                if before != after:
+                    changes.append((key, before, after))
+            return changes
+    
+    def changed_keys(self, from_version: int, to_version: int,
+                     prefix: bytes = b'') -> Optional[List[bytes]]:
+        """
+        数据库版本 from_version 之后到 to_version（含）之间写入或删除过的以 prefix 开头的键，按键排序；
+        写回原值的键同样在内，不读取值。版本0是新数据库的空状态；to_version 早于 from_version，
+        或任一版本不存在或已被清理时返回None
+        """
+        with self.lock:
+            vm = self.version_manager
+            if to_version < from_version:
+                return None
+            bounds = []
+            for version in (from_version, to_version):
+                if version == 0:
+                    if vm.pruned_before > 1:
+                        return None
+                    bounds.append(float('-inf'))
+                    continue
+                commit = vm.get_commit(version)
+                if commit is None:
+                    return None
+                bounds.append(commit[0])
+            since, until = bounds
+            keys = [key for key in vm.get_all_keys()
+                    if key.startswith(prefix)
+                    and any(since < v.timestamp <= until for v in vm.get_history(key))]
+            keys.sort()
+            return keys
+    
+    def partition_ranges(self, parts: int, version: int) -> Optional[List[bytes]]:
+        """
+        把数据库版本 version 的键空间分为至多 parts 个条目数大致相等的相邻区间，供并行导出和验证分配工作；
+        返回第2个起各区间的起始键（升序），各区间依次为 [上一个起始键, 下一个起始键)，首尾不设界。
+        按该版本的Merkle树的结构定位分界点，结果只由该版本的状态决定。没有该版本时返回None
+        """
+        if parts < 1:
+            raise InvalidOptionError(f"partition count must be positive: {parts}")
+        with self.lock:
+            merkle = self.merkle_at(version)
+            if merkle is None:
+                return None
+            _, root, nodes = merkle
+            return self.storage.merkle_tree.split_keys(root, nodes, parts)
+    
+    def find_commit(self, root_hash: bytes) -> Optional[int]:
+        """根哈希为 root_hash 的最近一个数据库版本"""
+        return self.version_manager.find_commit(root_hash)
+    
+    def prune_versions_before(self, version: int,
+                              pinned: Tuple[float, ...] = ()) -> Optional[Dict[str, Any]]:
+        """
+        删除早于数据库版本 version 的状态所需的旧版本，保留 version 及之后各版本可读；
+        之后 get_commit 对早于 version 的版本返回None。没有该版本时返回None
+        pinned 中有早于该版本提交时间的时间点时不删除任何版本，返回的 pinned_version 为
+        最早的时间点可见的数据库版本
+        Returns:
+            {'versions_removed', 'keys_pruned', 'bytes_reclaimed', 'pinned', 'pinned_version'}
+        """
+        self._check_writable()
+        with self.lock:
+            commit = self.version_manager.get_commit(version)
+            if commit is None:
+                return None
+            stats = {'versions_removed': 0, 'keys_pruned': 0, 'bytes_reclaimed': 0,
+                     'pinned': False, 'pinned_version': 0}
+            timestamp = commit[0]
+            earliest = min(pinned, default=None)
+            if earliest is not None and earliest < timestamp:
+                stats['pinned'] = True
+                stats['pinned_version'] = sum(
+                    1 for at, _ in self.version_manager.commits if at <= earliest
+                )
+                return stats
+            for key in self.version_manager.get_all_keys():
+                removed, removed_bytes = self.version_manager.prune_before(key, timestamp)
+                if removed:
+                    stats['versions_removed'] += removed
+                    stats['keys_pruned'] += 1
+                    stats['bytes_reclaimed'] += removed_bytes
+            self.version_manager.pruned_before = max(self.version_manager.pruned_before, version)
+            return stats
+    
+    def purge_key_history(self, key: bytes) -> Dict[str, int]:
+        """
+        合规删除：删除键除最新版本外的全部历史版本及其WAL条目，先 delete 再清除即可不留下任何值。
+        已记录的提交根哈希不重算，受影响的数据库版本的根哈希仍承诺被删除的值，
+        这些版本中该键读不到值，也无法再为其生成证明。
+        删除的值在下次持久化时从版本文件释放，SSTable中的旧值在 compact 时释放
+        Returns:
+            {'versions_removed', 'bytes_removed', 'first_version', 'last_version'}
+            first_version..last_version 为状态中含有被删除的值的数据库版本，没有删除时均为0
+        """
+        self._check_writable()
+        with self.lock:
+            stats = {'versions_removed': 0, 'bytes_removed': 0,
+                     'first_version': 0, 'last_version': 0}
+            removed = self.version_manager.purge(key)
+            self.wal_logger.purge_key(key)
+            if not removed:
+                return stats
+            stats['versions_removed'] = len(removed)
+            stats['bytes_removed'] = sum(len(v.value) for v in removed)
+            stats['first_version'] = self.version_manager.commit_of(removed[0].timestamp) or 0
+            latest = self.version_manager.get_latest(key)
+            last = self.version_manager.commit_of(latest.timestamp)
+            stats['last_version'] = last - 1 if last else self.get_state_version()
+            return stats
+    
+    def history_values(self, prefix: bytes) -> List[bytes]:
+        """
+        所有保留的版本（包括各键的最新版本）中以 prefix 开头的不同的值，按字节序；
+        供绑定层回收不再被任何版本引用的外部数据
+        """
+        with self.lock:
+            values = set()
+            for versions in self.version_manager.versions.values():
+                for version in versions:
+                    if version.value.startswith(prefix):
+                        values.add(version.value)
+            return sorted(values)
+    
+    def compact(self) -> Dict[str, Any]:
+        """
+        持久化全部数据后合并LSM树的SSTable（分片LSM树不合并），返回合并前后数据目录的字节数
+        和合并次数；持久化时重写版本文件，此前清理的版本随之从磁盘删除
+        """
+        self._check_writable()
+        self.flush(force_sync=True, debounce=False)
+        bytes_before = self._disk_usage()
+        merges = self.storage.lsm_tree.compact()
+        return {'bytes_before': bytes_before, 'bytes_after': self._disk_usage(),
+                'merges': merges}
+    
+    def stats(self) -> Dict[str, int]:
+        """
+        引擎健康统计：有效键数、数据库版本、Merkle节点数、数据目录字节数、SSTable文件数、
+        B+树节点缓存的命中/未命中次数，以及 compaction_stats 中的刷新与压缩计数。
+        计数类字段为进程内累计值，重新打开后清零
+        """
+        with self.lock:
+            compaction = self.storage.lsm_tree.compaction_stats()
+            bplus_tree = self.storage.bplus_tree
+            return {
+                'key_count': sum(1 for key in self.version_manager.get_all_keys()
+                                 if self._is_live(key)),
+                'state_version': self.get_state_version(),
+                'merkle_nodes': len(self.storage.merkle_tree.nodes),
+                'disk_bytes': self._disk_usage(),
+                'sstable_count': len(compaction['files']),
+                'pending_bytes': compaction['pending_bytes'],
+                'bytes_ingested': compaction['bytes_ingested'],
+                'bytes_flushed': compaction['bytes_flushed'],
+                'bytes_compacted': compaction['bytes_compacted'],
+                'compactions': compaction['compactions'],
+                'cache_hits': bplus_tree.cache_hits,
+                'cache_misses': bplus_tree.cache_misses,
+            }
+    
+    def read_stats(self) -> Dict[str, Dict[str, Any]]:
+        """
+        读放大直方图（见 CountHistogram.snapshot），进程内累计，重新打开后清零：
+            get: 每次 get 依次查找过的数据结构数，包括版本索引，以及未命中时的B+树、MemTable和SSTable
+            proof: 每个路径证明从根到叶子访问的Merkle节点数（含叶子），随键的分布和公共前缀的长度变化
+        """
+        return {name: histogram.snapshot() for name, histogram in self.read_histograms.items()}
+    
+    def _disk_usage(self) -> int:
+        import os
+        total = 0
+        for root, _, files in os.walk(self.data_dir):
+            for name in files:
+                try:
+                    total += os.path.getsize(os.path.join(root, name))
+                except OSError:
+                    pass  # 文件在遍历期间被删除（例如压缩合并）
+        return total
+    
+    NAMESPACE_RECORD_PREFIX = b'\x00ns/'
+    
+    def namespace(self, name: str) -> 'Database':
+        """
+        打开（不存在时创建）名为 name 的命名空间：数据目录 namespaces/<name> 下的独立数据库，
+        有自己的Merkle树和根哈希。命名空间每次提交后把新的根哈希写入本库的
+        NAMESPACE_RECORD_PREFIX + name 键，使本库的根哈希承诺各命名空间的根哈希
+        name 只能包含字母、数字、'_' 和 '-'
+        """
+        import os
+        import re
+        if not re.fullmatch(r'[A-Za-z0-9_-]+', name):
+            raise InvalidOptionError(f"invalid namespace name: {name!r}")
+        with self.lock:
+            child = self._namespaces.get(name)
+            if child is not None:
+                return child
+            options = {'read_only': 'true' if self.read_only else 'false', 'sync': self.sync_mode,
+                       'versioning': self.versioning}
+            if self.sync_mode == 'batched':
+                options['sync_window_us'] = str(int(self.sync_window * 1e6))
+            child = Database(data_dir=os.path.join(self.data_dir, 'namespaces', name),
+                             options=options, lock_dir=False)
+            # 上次提交根哈希后、写入记录前退出时记录落后，打开时补写
+            record = self.NAMESPACE_RECORD_PREFIX + name.encode()
+            root = child.get_root_hash()
+            if not self.read_only and self.get(record) != root:
+                self.put(record, root)
+            child.version_manager.on_commit = (
+                lambda _timestamp, root_hash: self._namespace_committed(record, root_hash)
+            )
+            self._namespaces[name] = child
+            return child
+    
+    def _namespace_committed(self, record: bytes, root_hash: bytes):
+        with self.lock:
+            self.put(record, root_hash)
+    
+    def checkpoint(self, path: str) -> Tuple[int, bytes]:
+        """
+        持锁持久化全部数据并把数据目录复制到 path，得到可直接打开的一致副本；
+        path 须不存在或为空目录
+        Returns:
+            (副本的数据库版本, 根哈希)
+        """
+        import os
+        import shutil
+        if os.path.isdir(path) and not os.listdir(path):
+            os.rmdir(path)
+        elif os.path.exists(path):
+            raise InvalidOptionError(f"checkpoint target is not empty: {path}")
+        with self.lock:
+            self.flush(force_sync=True, debounce=False)
+            shutil.copytree(self.data_dir, path)
+            return self.get_state_version(), self.get_root_hash()
+    
+    # ioctl(FICLONE)：整个文件以写时复制共享数据块（btrfs、XFS等）
+    FICLONE = 0x40049409
+    
+    def fork(self, path: str, version: int) -> Optional[Tuple[int, bytes]]:
+        """
+        在 path 新建以数据库版本 version 的状态为初始状态的独立数据库，path 须不存在或为空目录。
+        version 为当前版本时持锁持久化后克隆整个数据目录（保留历史）：SSTable写入后不再修改，
+        以硬链接共享；其余文件在文件系统支持时以写时复制克隆，否则复制。
+        更早的版本以一次提交写入该版本可见的全部键（包括删除标记），副本的历史从该提交开始，
+        根哈希与该版本相同。没有该版本或已被清理时返回None
+        Returns:
+            (副本的数据库版本, 根哈希)
+        """
+        import os
+        if os.path.isdir(path) and not os.listdir(path):
+            os.rmdir(path)
+        elif os.path.exists(path):
+            raise InvalidOptionError(f"fork target is not empty: {path}")
+        with self.lock:
+            if version == self.get_state_version():
+                self.flush(force_sync=True, debounce=False)
+                self._clone_tree(self.data_dir, path)
+                return version, self.get_root_hash()
+            commit = self.version_manager.get_commit(version)
+            if commit is None:
+                return None
+            at, root_hash = commit
+            items = []
+            for key in self.version_manager.versions:
+                visible = self.version_manager.get_at_time(key, at)
+                if visible is not None:
+                    items.append((key, visible.value))
+            tree_options = dict(self.storage.merkle_tree.options)
+        fork = Database(data_dir=path, tree_options=tree_options)
+        try:
+            _, forked_root = fork.commit_batch(items)
+            fork.flush(force_sync=True, debounce=False)
+        finally:
+            fork.release_lock()
+        if forked_root != root_hash:
+            raise RuntimeError(f"fork of version {version} cannot be rebuilt: "
+                               "versions it depends on have been pruned or purged")
+        return 1, forked_root
+    
+    @classmethod
+    def _clone_tree(cls, src: str, dst: str):
+        """克隆数据目录，不含目录锁"""
+        import os
+        import shutil
+        for root, _, files in os.walk(src):
+            target = os.path.join(dst, os.path.relpath(root, src))
+            os.makedirs(target, exist_ok=True)
+            for name in files:
+                if root == src and name == 'LOCK':
+                    continue
+                source, copy = os.path.join(root, name), os.path.join(target, name)
+                if name.endswith('.sst'):
+                    try:
+                        os.link(source, copy)
+                        continue
+                    except OSError:
+                        pass  # 跨文件系统或不支持硬链接
+                if fcntl is not None:
+                    try:
+                        with open(source, 'rb') as s, open(copy, 'wb') as d:
+                            fcntl.ioctl(d.fileno(), cls.FICLONE, s.fileno())
+                        shutil.copystat(source, copy)
+                        continue
+                    except OSError:
+                        pass
+                shutil.copy2(source, copy)
+    
+    def changes_since(self, version: int,
+                      timestamp: float) -> Optional[Tuple[bytes, List[Tuple[bytes, Optional[bytes]]]]]:
+        """
+        数据库版本 version 之后、timestamp 及之前写入或删除过的键，及其在 timestamp 时的值，按键排序；
+        删除的键值为None。version 大于当前数据库版本时返回None
+        Returns:
+            (数据库版本 version 的根哈希（version 为0时为空）, timestamp 时的数据库版本, [(键, 值), ...])
+        """
+        with self.lock:
+            commits = self.version_manager.commits
+            if version > len(commits):
+                return None
+            since, base_root = commits[version - 1] if version > 0 else (float('-inf'), b'')
+            changes = []
+            for key in self.version_manager.get_all_keys():
+                versions = self.version_manager.get_history(key)
+                if not any(since < v.timestamp <= timestamp for v in versions):
+                    continue
+                value = self.get_at_time(key, timestamp)
+                changes.append((key, None if value == b'__DELETED__' else value))
+            changes.sort()
+            version_at = sum(1 for at, _ in commits if at <= timestamp)
+            return base_root, version_at, changes
+    
+    def commit_changes(self, number: int,
+                       prefix: bytes = b''
+                       ) -> Optional[Tuple[bytes, List[Tuple[bytes, Optional[bytes], Optional[bytes]]]]]:
+        """
+        第 number 次提交写入或删除的以 prefix 开头的键，及其提交前后的值，按键排序；
+        不存在或已删除的值为None。没有该提交或已被清理时返回None
+        Returns:
+            (该提交的根哈希, [(键, 旧值, 新值), ...])
+        """
+        with self.lock:
+            commits = self.version_manager.commits
+            commit = self.version_manager.get_commit(number)
+            if commit is None:
+                return None
+            at, root = commit
+            since = commits[number - 2][0] if number > 1 else float('-inf')
+            changes = []
+            for key in self.version_manager.get_all_keys():
+                if not key.startswith(prefix):
+                    continue
+                old = new = None
+                for v in self.version_manager.get_history(key):
+                    if v.timestamp <= since:
+                        old = v.value
+                    elif v.timestamp <= at:
+                        new = v.value
+                    else:
+                        break
+                if new is None:
+                    continue
+                changes.append((key,
+                                None if old == b'__DELETED__' else old,
+                                None if new == b'__DELETED__' else new))
+            changes.sort()
+            return root, changes
+    
+    def count_prefixes(self, prefix_len: int, start: bytes = b'', end: bytes = b'') -> int:
+        """[start, end) 内现存的键中不同的 prefix_len 字节前缀的个数，end 为空表示无上界；短于 prefix_len 的键不计"""
+        if prefix_len < 0:
+            raise InvalidOptionError("prefix_len must not be negative")
+        with self.lock:
+            return self.storage.merkle_tree.count_prefixes(prefix_len, start, end)
+    
+    def _record_background_error(self, what: str, error: BaseException):
+        """记录后台写入失败；只保留第一个"""
+        if self._background_error is None:
+            self._background_error = f"{what}: {error}"
+    
+    def background_error(self) -> Optional[str]:
+        """
+        后台任务（异步WAL、MemTable刷新、B+树更新）第一次失败的说明，没有失败过时返回None。
+        一旦出现就一直保留到重新打开数据库，数据可能没有完整落盘
+        """
+        if self._background_error is not None:
+            return self._background_error
+        for what, error in (("B+树更新失败", getattr(self.storage, 'background_error', None)),
+                            ("MemTable刷新失败",
+                             getattr(self.storage.lsm_tree, 'background_error', None))):
+            if error is not None:
+                return f"{what}: {error}"
+        return None
+    
+    def get_tree_option(self, name: str) -> Optional[str]:
+        """获取Merkle树创建时记录的选项，未知选项返回None"""
+        return self.storage.merkle_tree.options.get(name)
+    
     def get_root_hash(self) -> bytes:
         """获取Merkle根哈希"""
         return self.storage.get_root_hash()
@@ -444,6 +1404,7 @@ class Database:
         Returns:
             (success, merkle_root_hash)
         """
+        self._check_writable()
         if not items:
             return (True, self.get_root_hash())
         
@@ -639,6 +1600,25 @@ class Database:
         return self.index_manager.query_secondary_index(index_name, index_value)
     
     # 工具方法
+    def sync(self):
+        """刷新全部数据，并把数据目录下的文件fsync到磁盘"""
+        import os
+        if self.read_only:
+            return
+        # 本次fsync覆盖已安排的批量fsync
+        self._cancel_scheduled_sync()
+        self.flush(force_sync=True, debounce=False)
+        for root, _, files in os.walk(self.data_dir):
+            for name in files:
+                try:
+                    fd = os.open(os.path.join(root, name), os.O_RDONLY)
+                except OSError:
+                    continue  # 文件在遍历期间被删除（例如压缩合并）
+                try:
+                    os.fsync(fd)
+                finally:
+                    os.close(fd)
+    
     def flush(self, async_mode: bool = False, force_sync: bool = False, debounce: bool = True):
         """
         强制刷新到磁盘（确保所有数据写入磁盘文件）
@@ -655,6 +1635,9 @@ class Database:
         """
         import time
         
+        if self.read_only:
+            return  # 只读打开时没有需要持久化的数据
+        
         # 防抖机制：如果距离上次flush时间太短，标记为待处理，稍后统一处理
         if debounce and not force_sync:
             current_time = time.time()
@@ -712,17 +1695,24 @@ class Database:
         """
         # 1. WAL刷新（.wal文件）- 关键，必须同步
         try:
+            torn = faults.check('flush.wal')
             self.wal_logger.flush()
+            if torn:
+                faults.tear([self.wal_logger.current_wal_file])
         except Exception as e:
             print(f"⚠️ WAL刷新失败: {e}")
             # WAL刷新失败不应阻止其他操作
         
         # 2. 存储引擎刷新（LSM树刷新到.sst文件）- 关键，必须同步
         try:
+            torn = faults.check('flush.lsm')
             if hasattr(self.storage.lsm_tree, 'flush'):
                 self.storage.lsm_tree.flush()
             else:
                 self.storage.lsm_tree.flush()
+            if torn:
+                tables = list(Path(self.storage.lsm_tree.data_dir).rglob('*.sst'))
+                faults.tear(sorted(tables, key=os.path.getmtime)[-1:])
         except Exception as e:
             print(f"⚠️ LSM树刷新失败: {e}")
             # LSM刷新失败不应阻止其他操作
@@ -739,9 +1729,12 @@ class Database:
             max_wait = 30  # 最多等待30秒
             wait_time = 0
             while wait_time < max_wait:
-                # 检查是否还有未刷新的MemTable
+                # 检查是否还有未刷新的MemTable（分片LSM树按分片保存列表）
                 if hasattr(self.storage.lsm_tree, 'immutable_memtables'):
-                    if len(self.storage.lsm_tree.immutable_memtables) == 0:
+                    pending = self.storage.lsm_tree.immutable_memtables
+                    if isinstance(pending, dict):
+                        pending = [m for tables in pending.values() for m in tables]
+                    if len(pending) == 0:
                         break
                 time.sleep(0.1)
                 wait_time += 0.1
@@ -775,12 +1768,14 @@ class Database:
             
             try:
                 # Merkle树持久化（.mpt文件）
+                faults.check('flush.merkle')
                 self.storage.merkle_tree.save_to_disk()
             except Exception as e:
                 print(f"⚠️ Merkle树持久化失败: {e}")
             
             try:
                 # 版本管理器持久化（.ver文件）
+                faults.check('flush.versions')
                 self.version_manager.save_to_disk(self.data_dir)
             except Exception as e:
                 print(f"⚠️ 版本管理器持久化失败: {e}")
@@ -793,6 +1788,7 @@ class Database:
             
             try:
                 # 保存数据库元数据（.amdb文件）
+                faults.check('flush.metadata')
                 self._save_metadata()
             except Exception as e:
                 print(f"⚠️ 元数据保存失败: {e}")
@@ -811,8 +1807,10 @@ class Database:
         from .storage.file_format import FileMagic
         
         metadata_file = Path(self.data_dir) / "database.amdb"
+        # 先写临时文件再替换，写到一半时退出不会损坏原有的元数据
+        tmp_file = metadata_file.with_name(metadata_file.name + '.tmp')
         try:
-            with open(metadata_file, 'wb') as f:
+            with open(tmp_file, 'wb') as f:
                 # 写入文件魔数
                 f.write(FileMagic.AMDB)  # 4 bytes
                 
@@ -848,13 +1846,14 @@ class Database:
                 current_pos = f.tell()
             
             # 重新打开文件读取数据并计算checksum
-            with open(metadata_file, 'rb') as rf:
+            with open(tmp_file, 'rb') as rf:
                 data = rf.read()
             
             # 追加checksum
-            with open(metadata_file, 'ab') as af:
+            with open(tmp_file, 'ab') as af:
                 checksum = hashlib.sha256(data).digest()
                 af.write(checksum)  # 32 bytes
+            faults.replace(tmp_file, metadata_file)
         except Exception as e:
             import traceback
             print(f"保存数据库元数据失败: {e}")
@@ -889,6 +1888,44 @@ class Database:
         
         return True
     
+    # refresh 读到刷新途中的文件（Merkle树与版本记录来自不同的提交）时的重试次数和间隔
+    REFRESH_ATTEMPTS = 5
+    REFRESH_RETRY_SECS = 0.01
+    
+    def refresh(self) -> bool:
+        """
+        只读打开时，重新加载另一进程中的写入方已持久化的状态，返回是否转到了新的状态。
+        写入方每次刷新时最后写出版本记录，版本记录没有变化时只检查一次修改时间；
+        加载到的Merkle树与最后一次提交不符（写入方正在刷新）时稍后重试，仍不符则保持原来的状态。
+        读写打开时本进程的写入已经可见，不做任何事
+        """
+        if not self.read_only:
+            return False
+        mtime = self._get_version_file_mtime()
+        if mtime == self._last_file_mtime:
+            return False
+        for attempt in range(self.REFRESH_ATTEMPTS):
+            if attempt:
+                time.sleep(self.REFRESH_RETRY_SECS)
+                mtime = self._get_version_file_mtime()
+            storage = self._open_storage()
+            version_manager = VersionManager(config=self.config)
+            version_manager.load_from_disk(self.data_dir)
+            commits = version_manager.commits
+            if (commits and self.versioning == 'put'
+                    and storage.merkle_tree.get_root_hash() != commits[-1][1]):
+                continue
+            index_manager = IndexManager()
+            index_manager.load_from_disk(self.data_dir)
+            with self.lock:
+                self.storage = storage
+                self.version_manager = version_manager
+                self.index_manager = index_manager
+                self._load_metadata()
+                self._last_file_mtime = mtime
+            return True
+        return False
+    
     def reload_if_files_changed(self) -> bool:
         """
         检查数据库文件状态，如果文件被删除或清空，重新加载数据
@@ -901,8 +1938,7 @@ class Database:
             # 文件不存在或已清空，清空内存缓存
             with self.lock:
                 # 清空版本管理器
-                self.version_manager.current_versions.clear()
-                self.version_manager.versions.clear()
+                self.version_manager.clear()
                 # 清空索引管理器
                 self.index_manager.primary_index.clear()
                 self.index_manager.version_index.clear()
@@ -972,6 +2008,7 @@ class Database:
                 # 如果保存失败，不影响数据库初始化
                 import traceback
                 print(f"警告: 创建数据库元数据文件失败: {e}")
+                self._open_warnings.append(f"Failed to create database metadata: {e}")
                 traceback.print_exc()
             return
         
@@ -997,6 +2034,7 @@ class Database:
             import traceback
             print(f"加载数据库元数据失败: {e}")
             traceback.print_exc()
+            self._open_warnings.append(f"Failed to load database metadata: {e}")
             self._created_at = time.time()
             self._description = ''  # 默认无备注
     
@@ -0,0 +1,28 @@
+"""
+引擎异常
+FatalError 表示引擎已处于不可继续使用的状态，绑定层据此停止使用该数据库实例
+"""
+
+
+class FatalError(Exception):
+    """致命错误：数据损坏或后台任务失败，需要重新打开数据库"""
+
+
+class CorruptionError(FatalError, ValueError):
+    """持久化数据格式不正确"""
+
+
+class BusyError(Exception):
+    """暂时性错误：引擎暂时无法处理请求（例如写入积压），稍后重试可能成功"""
+
+
+class InvalidOptionError(ValueError):
+    """打开选项不合法，或与数据目录中记录的创建选项不一致"""
+
+
+class ReadOnlyError(PermissionError):
+    """以只读方式打开的数据库拒绝写入"""
+
+
+class DatabaseNotFoundError(FileNotFoundError):
+    """数据目录不存在，且打开选项不允许新建"""
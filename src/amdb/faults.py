@@ -0,0 +1,118 @@
+"""
+故障注入（仅供测试）
+在提交和刷新路径上设有故障点，注入后按计数触发：I/O错误、撕裂写入（文件只写出一半）、直接结束进程，
+用于验证崩溃后重新打开能恢复到一致的版本。没有注入故障时每个故障点只做一次字典查找。
+"""
+
+import os
+import threading
+from pathlib import Path
+from typing import Dict, List, Optional, Tuple
+
+# 故障点：提交之后、持久化之前，以及刷新的各个步骤之前
+POINTS = (
+    'commit',
+    'flush.wal',
+    'flush.lsm',
+    'flush.merkle',
+    'flush.versions',
+    'flush.metadata',
+)
+
+# io_error: 抛出OSError；torn_write: 该步骤写出的文件只写出一半后结束进程；kill: 直接结束进程
+ACTIONS = ('io_error', 'torn_write', 'kill')
+
+# 注入的故障结束进程时的退出码，便于测试区分注入的结束与其他失败
+KILL_EXIT_CODE = 86
+
+_lock = threading.Lock()
+# 故障点 -> (动作, 剩余的跳过次数)
+_armed: Dict[str, Tuple[str, int]] = {}
+_fired: List[str] = []
+# 已触发撕裂写入、等待该步骤写出文件的故障点
+_tearing: Optional[str] = None
+
+
+class InjectedFault(OSError):
+    """注入的I/O错误"""
+
+
+def inject(point: str, action: str, countdown: int = 0):
+    """在故障点 point 第 countdown+1 次经过时触发 action；同一故障点只保留最后一次注入"""
+    if point not in POINTS:
+        raise ValueError(f"unknown fault point: {point}")
+    if action not in ACTIONS:
+        raise ValueError(f"unknown fault action: {action}")
+    with _lock:
+        _armed[point] = (action, countdown)
+
+
+def clear():
+    """撤销全部尚未触发的故障"""
+    global _tearing
+    with _lock:
+        _armed.clear()
+        _tearing = None
+
+
+def fired() -> List[str]:
+    """已触发的I/O错误故障点，按触发顺序；结束进程的故障无从记录"""
+    with _lock:
+        return list(_fired)
+
+
+def _take(point: str) -> Optional[str]:
+    """经过故障点：到期时取出并返回动作"""
+    if not _armed:
+        return None
+    with _lock:
+        armed = _armed.get(point)
+        if armed is None:
+            return None
+        action, countdown = armed
+        if countdown > 0:
+            _armed[point] = (action, countdown - 1)
+            return None
+        del _armed[point]
+        if action == 'io_error':
+            _fired.append(point)
+        return action
+
+
+def check(point: str) -> bool:
+    """
+    经过故障点。io_error 抛出 InjectedFault，kill 结束进程；torn_write 返回True，
+    该步骤随后经 replace 写出的文件只写出一半，追加写入的文件由调用方写出后调用 tear
+    """
+    global _tearing
+    action = _take(point)
+    if action == 'io_error':
+        raise InjectedFault(f"injected I/O error at {point}")
+    if action == 'kill':
+        os._exit(KILL_EXIT_CODE)
+    if action == 'torn_write':
+        _tearing = point
+        return True
+    return False
+
+
+def replace(tmp_path, path):
+    """
+    用写好的临时文件原子地替换 path，进程在写出途中退出时 path 保持原样。
+    已触发撕裂写入时临时文件截去后一半后结束进程，不替换
+    """
+    if _tearing is not None:
+        tear([tmp_path])
+    os.replace(tmp_path, path)
+
+
+def tear(paths: List[Path]):
+    """把刚写出的文件截去后一半并结束进程，模拟写到一半时断电"""
+    for path in paths:
+        try:
+            size = os.path.getsize(path)
+            with open(path, 'r+b') as f:
+                f.truncate(size // 2)
+        except OSError:
+            pass
+    os._exit(KILL_EXIT_CODE)
@@ -123,6 +123,47 @@ class MetricsCollector:
             self.start_time = time.time()
 
 
+class CountHistogram:
+    """
+    非负整数观测值（如一次读取访问的节点数）的直方图：第i个桶计数等于i的观测值，
+    最后一个桶计数不小于 BUCKETS-1 的观测值。计数为进程内累计值
+    """
+    
+    BUCKETS = 64
+    
+    def __init__(self):
+        self.lock = threading.Lock()
+        self.buckets = [0] * self.BUCKETS
+        self.count = 0
+        self.sum = 0
+        self.max = 0
+    
+    def observe(self, value: int):
+        with self.lock:
+            self.buckets[min(value, self.BUCKETS - 1)] += 1
+            self.count += 1
+            self.sum += value
+            self.max = max(self.max, value)
+    
+    def snapshot(self) -> Dict[str, Any]:
+        """{'count', 'sum', 'max', 'buckets'}"""
+        with self.lock:
+            return {'count': self.count, 'sum': self.sum, 'max': self.max,
+                    'buckets': list(self.buckets)}
+
+
+class ProbeCounter:
+    """一次读取中访问的节点或查找的数据结构的计数，沿读取路径传递"""
+    
+    __slots__ = ('count',)
+    
+    def __init__(self):
+        self.count = 0
+    
+    def add(self, n: int = 1):
+        self.count += n
+
+
 class PerformanceMonitor:
     """性能监控器"""
     
@@ -56,6 +56,9 @@ class BPlusTree:
         self.lock = threading.RLock()
         self.node_cache: OrderedDict[int, BPlusNode] = OrderedDict()
         self.cache_size = 1000
+        # 节点缓存的命中与未命中次数（进程内累计）
+        self.cache_hits = 0
+        self.cache_misses = 0
         self.next_node_id = 1
         self.meta_file = os.path.join(data_dir, "tree.meta")
         
@@ -288,11 +291,13 @@ class BPlusTree:
         # 先查缓存
         if node_id in self.node_cache:
             # 移到末尾（LRU）
+            self.cache_hits += 1
             node = self.node_cache.pop(node_id)
             self.node_cache[node_id] = node
             return node
         
         # 从磁盘加载
+        self.cache_misses += 1
         node_file = os.path.join(self.data_dir, f"node_{node_id}.bpt")
         if not os.path.exists(node_file):
             raise FileNotFoundError(f"Node file not found: {node_file}")
@@ -6,6 +6,7 @@ import struct
 import hashlib
 from typing import List, Tuple, Optional, Dict, Any
 from enum import IntEnum
+from ..errors import CorruptionError
 
 
 class FileMagic:
@@ -47,7 +48,7 @@ class SSTableFormat:
         """读取文件头"""
         magic = f.read(4)
         if magic != FileMagic.SST:
-            raise ValueError("Invalid SSTable file")
+            raise CorruptionError("Invalid SSTable file")
         
         version = struct.unpack('H', f.read(2))[0]
         key_count = struct.unpack('Q', f.read(8))[0]
@@ -201,21 +202,18 @@ class WALFormat:
         if timestamp is None:
             timestamp = time.time()
         
-        f.write(struct.pack('B', entry_type))  # 1 byte
-        f.write(struct.pack('d', timestamp))  # 8 bytes
-        f.write(struct.pack('I', len(key)))  # 4 bytes
-        f.write(key)
+        data = struct.pack('B', entry_type)  # 1 byte
+        data += struct.pack('d', timestamp)  # 8 bytes
+        data += struct.pack('I', len(key))  # 4 bytes
+        data += key
         
         if entry_type == WALFormat.ENTRY_PUT and value is not None:
-            f.write(struct.pack('I', len(value)))  # 4 bytes
-            f.write(value)
+            data += struct.pack('I', len(value))  # 4 bytes
+            data += value
         
-        # 计算checksum
-        f.seek(f.tell() - (1 + 8 + 4 + len(key) + (4 + len(value) if value else 0)))
-        data = f.read()
-        f.seek(0, 2)
-        checksum = hashlib.sha256(data).digest()
-        f.write(checksum)  # 32 bytes
+        # 在内存中计算checksum，追加模式打开的文件不可读
+        f.write(data)
+        f.write(hashlib.sha256(data).digest())  # 32 bytes
     
     @staticmethod
     def read_entry(f) -> Optional[Dict[str, Any]]:
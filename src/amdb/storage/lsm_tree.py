@@ -8,10 +8,13 @@ import struct
 import threading
 import json
 from typing import Optional, Dict, List, Tuple, Iterator
-from collections import OrderedDict
+from collections import OrderedDict, deque
+from contextlib import contextmanager
 import hashlib
 import time
 
+from ..metrics import ProbeCounter
+
 
 class MemTable:
     """
@@ -176,11 +179,36 @@ class MemTable:
             self.size = 0
 
 
+class OpenFileLimit:
+    """
+    限制一个数据库同时打开的SSTable文件数，达到上限时等待其他读写关闭文件，
+    而不是在进程的文件描述符耗尽时失败。limit为0时不限制
+    """
+    
+    def __init__(self, limit: int = 0):
+        self.limit = limit
+        self._slots = threading.BoundedSemaphore(limit) if limit > 0 else None
+    
+    @contextmanager
+    def open(self, path, mode: str):
+        if self._slots is None:
+            with open(path, mode) as f:
+                yield f
+            return
+        with self._slots:
+            with open(path, mode) as f:
+                yield f
+
+
+NO_FILE_LIMIT = OpenFileLimit()
+
+
 class SSTable:
     """有序字符串表 - LSM树的磁盘层"""
     
-    def __init__(self, filepath: str):
+    def __init__(self, filepath: str, files: OpenFileLimit = NO_FILE_LIMIT):
         self.filepath = filepath
+        self.files = files
         self.index: Dict[bytes, int] = {}  # key -> offset
         self._loaded = False
     
@@ -190,7 +218,7 @@ class SSTable:
         
         os.makedirs(os.path.dirname(self.filepath), exist_ok=True)
         
-        with open(self.filepath, 'wb') as f:
+        with self.files.open(self.filepath, 'wb') as f:
             index_data = {}
             data_start = f.tell()
             
@@ -224,7 +252,7 @@ class SSTable:
             file_size = f.tell()
         
         # 重新打开文件读取数据部分用于计算checksum
-        with open(self.filepath, 'r+b') as f:
+        with self.files.open(self.filepath, 'r+b') as f:
             f.seek(0)
             data = f.read(index_offset)
             f.seek(file_size)  # 回到文件末尾
@@ -243,7 +271,7 @@ class SSTable:
         
         from .file_format import SSTableFormat, FileMagic
         
-        with open(self.filepath, 'rb') as f:
+        with self.files.open(self.filepath, 'rb') as f:
             # 检查文件格式
             magic = f.read(4)
             f.seek(0)
@@ -293,7 +321,7 @@ class SSTable:
             self._loaded = True
             return
         
-        with open(self.filepath, 'rb') as f:
+        with self.files.open(self.filepath, 'rb') as f:
             # 检查文件魔数
             magic = f.read(4)
             if len(magic) < 4:
@@ -409,6 +437,54 @@ class SSTable:
         return os.path.exists(self.filepath)
 
 
+class CompactionStats:
+    """
+    刷新与压缩统计（进程内累计，重启后清零）
+    写放大 = (刷新写入字节 + 压缩写入字节) / 写入的键值字节
+    """
+    
+    RECENT_COMPACTIONS = 8
+    
+    def __init__(self):
+        self.lock = threading.Lock()
+        self.bytes_ingested = 0
+        self.bytes_flushed = 0
+        self.bytes_compacted = 0
+        self.compactions = 0
+        self.recent_durations = deque(maxlen=self.RECENT_COMPACTIONS)
+    
+    def record_ingest(self, nbytes: int):
+        with self.lock:
+            self.bytes_ingested += nbytes
+    
+    def record_flush(self, nbytes: int):
+        with self.lock:
+            self.bytes_flushed += nbytes
+    
+    def record_compaction(self, nbytes: int, seconds: float):
+        with self.lock:
+            self.bytes_compacted += nbytes
+            self.compactions += 1
+            self.recent_durations.append(seconds)
+    
+    def snapshot(self, pending_bytes: int, files: List[Tuple[int, str, int]]) -> Dict:
+        """
+        Args:
+            pending_bytes: 尚未刷新到磁盘的MemTable字节数
+            files: [(level, name, size), ...]，0层为刷新产生的文件，1层为压缩合并产生的文件
+        """
+        with self.lock:
+            return {
+                'pending_bytes': pending_bytes,
+                'bytes_ingested': self.bytes_ingested,
+                'bytes_flushed': self.bytes_flushed,
+                'bytes_compacted': self.bytes_compacted,
+                'compactions': self.compactions,
+                'recent_durations': list(self.recent_durations),
+                'files': files,
+            }
+
+
 class LSMTree:
     """
     LSM树实现
@@ -429,6 +505,8 @@ class LSMTree:
             self._enable_skip_list = config.lsm_enable_skip_list
             self._enable_cython = config.lsm_enable_cython
             self.level_size_limit = config.lsm_level_size_limit
+            self.target_file_size = config.max_file_size
+            self.files = OpenFileLimit(config.max_open_files)
             memtable_max_size = self._memtable_max_size
             enable_skip_list = self._enable_skip_list
             enable_cython = self._enable_cython
@@ -437,6 +515,8 @@ class LSMTree:
             enable_skip_list = False
             enable_cython = False
             self.level_size_limit = 10
+            self.target_file_size = 256 * 1024 * 1024
+            self.files = NO_FILE_LIMIT
             self._memtable_max_size = memtable_max_size
             self._enable_skip_list = enable_skip_list
             self._enable_cython = enable_cython
@@ -452,6 +532,9 @@ class LSMTree:
         self.immutable_memtables: List[MemTable] = []
         self.sstables: List[SSTable] = []
         self.lock = threading.RLock()
+        self.stats = CompactionStats()
+        # 后台刷新的第一个异常，见 Database.background_error
+        self.background_error: Optional[BaseException] = None
         
         # 加载已有的SSTable
         self._load_sstables()
@@ -473,6 +556,7 @@ class LSMTree:
     
     def put(self, key: bytes, value: bytes, version: int) -> bool:
         """写入数据（优化：使用预分配MemTable）"""
+        self.stats.record_ingest(len(key) + len(value))
         with self.lock:
             if not self.memtable.put(key, value, version):
                 # MemTable满了，切换到不可变状态
@@ -496,6 +580,7 @@ class LSMTree:
         # 优化：对于大批量数据，一次性处理，减少锁获取次数
         # 优化：使用局部变量减少属性访问
         # 优化：降低大批量阈值，更早启用优化路径（降低到2000以匹配批量大小）
+        self.stats.record_ingest(sum(len(k) + len(v) for k, v, _ in items))
         items_len = len(items)
         if items_len > 2000:
             # 大批量：一次性处理，优化锁策略
@@ -551,8 +636,9 @@ class LSMTree:
                                     self.data_dir,
                                     f"sstable_{int(time.time() * 1000000)}.sst"
                                 )
-                                sstable = SSTable(sstable_path)
+                                sstable = SSTable(sstable_path, self.files)
                                 sstable.write(entries)
+                                self.stats.record_flush(os.path.getsize(sstable_path))
                                 with self.lock:
                                     self.sstables.append(sstable)
                                     if immutable in self.immutable_memtables:
@@ -560,8 +646,9 @@ class LSMTree:
                                         self.immutable_memtables.remove(immutable)
                                     if len(self.sstables) > self.level_size_limit:
                                         self._compact()
-                        except Exception:
-                            pass
+                        except Exception as e:
+                            if self.background_error is None:
+                                self.background_error = e
                     threading.Thread(target=async_flush, daemon=True).start()
                     
                     # 重新尝试（在新MemTable上，不需要锁）
@@ -622,8 +709,9 @@ class LSMTree:
                                         self.data_dir,
                                         f"sstable_{int(time.time() * 1000000)}.sst"
                                     )
-                                    sstable = SSTable(sstable_path)
+                                    sstable = SSTable(sstable_path, self.files)
                                     sstable.write(entries)
+                                    self.stats.record_flush(os.path.getsize(sstable_path))
                                     with self.lock:
                                         self.sstables.append(sstable)
                                         if immutable in self.immutable_memtables:
@@ -631,8 +719,9 @@ class LSMTree:
                                             self.immutable_memtables.remove(immutable)
                                         if len(self.sstables) > self.level_size_limit:
                                             self._compact()
-                            except Exception:
-                                pass
+                            except Exception as e:
+                                if self.background_error is None:
+                                    self.background_error = e
                         threading.Thread(target=async_flush, daemon=True).start()
                         
                         # 重新尝试（在新MemTable上）
@@ -644,10 +733,12 @@ class LSMTree:
         
         return True
     
-    def get(self, key: bytes) -> Optional[Tuple[bytes, int]]:
-        """读取数据（从新到旧查找，优化锁竞争）"""
+    def get(self, key: bytes, probes: Optional[ProbeCounter] = None) -> Optional[Tuple[bytes, int]]:
+        """读取数据（从新到旧查找，优化锁竞争）；probes 计入查找过的MemTable和SSTable数"""
         # 优化：读取操作使用更细粒度的锁
         # 1. 先查MemTable（跳表内部使用读锁，允许多个读并发）
+        if probes is not None:
+            probes.add()
         result = self.memtable.get(key)
         if result:
             return result
@@ -655,6 +746,8 @@ class LSMTree:
         # 2. 查不可变MemTable（需要锁保护）
         with self.lock:
             for imm_memtable in reversed(self.immutable_memtables):
+                if probes is not None:
+                    probes.add()
                 result = imm_memtable.get(key)
                 if result:
                     return result
@@ -662,6 +755,8 @@ class LSMTree:
         # 3. 查SSTable（从新到旧，需要锁保护）
         with self.lock:
             for sstable in reversed(self.sstables):
+                if probes is not None:
+                    probes.add()
                 result = sstable.get(key)
                 if result:
                     return result
@@ -717,8 +812,9 @@ class LSMTree:
                         self.data_dir,
                         f"sstable_{int(time.time() * 1000000)}.sst"
                     )
-                    sstable = SSTable(sstable_path)
+                    sstable = SSTable(sstable_path, self.files)
                     sstable.write(entries)
+                    self.stats.record_flush(os.path.getsize(sstable_path))
                     
                     with self.lock:
                         self.sstables.append(sstable)
@@ -733,6 +829,8 @@ class LSMTree:
                 import traceback
                 print(f"刷新MemTable失败: {e}")
                 traceback.print_exc()
+                if self.background_error is None:
+                    self.background_error = e
         
         if sync:
             # 同步刷新：直接执行，等待完成
@@ -757,13 +855,16 @@ class LSMTree:
         
         if len(sstable_info) < 2:
             return
+        started = time.time()
         
         # 按大小和时间排序（小的、旧的优先）
         sstable_info.sort(key=lambda x: (x[1], x[2]))
         
-        # 合并最旧的两个SSTable
+        # 合并最旧的两个SSTable；合并结果会超过目标文件大小时不再合并
         sstable1, size1, mtime1 = sstable_info[0]
         sstable2, size2, mtime2 = sstable_info[1]
+        if size1 + size2 > self.target_file_size:
+            return
         
         # 读取两个SSTable的所有数据
         merged_entries = []
@@ -772,7 +873,7 @@ class LSMTree:
         # 从sstable1读取
         from .file_format import SSTableFormat
         if os.path.exists(sstable1.filepath):
-            with open(sstable1.filepath, 'rb') as f:
+            with self.files.open(sstable1.filepath, 'rb') as f:
                 # 跳过文件头
                 f.seek(SSTableFormat.HEADER_SIZE)
                 while True:
@@ -786,7 +887,7 @@ class LSMTree:
         
         # 从sstable2读取（覆盖sstable1中的相同key）
         if os.path.exists(sstable2.filepath):
-            with open(sstable2.filepath, 'rb') as f:
+            with self.files.open(sstable2.filepath, 'rb') as f:
                 # 跳过文件头
                 f.seek(SSTableFormat.HEADER_SIZE)
                 while True:
@@ -812,6 +913,7 @@ class LSMTree:
             )
             new_sstable = SSTable(new_sstable_path)
             new_sstable.write(merged_entries)
+            self.stats.record_compaction(os.path.getsize(new_sstable_path), time.time() - started)
             
             # 移除旧文件，添加新文件
             self.sstables.remove(sstable1)
@@ -822,6 +924,35 @@ class LSMTree:
                 os.remove(sstable2.filepath)
             self.sstables.append(new_sstable)
     
+    def compact(self) -> int:
+        """合并SSTable，直到只剩一个或任意两个合并后都会超过目标文件大小，返回合并次数"""
+        merges = 0
+        with self.lock:
+            while len(self.sstables) >= 2:
+                count = len(self.sstables)
+                self._compact()
+                if len(self.sstables) >= count:
+                    break  # 文件已不存在等原因无法继续合并
+                merges += 1
+        return merges
+    
+    def pending_bytes(self) -> int:
+        """尚未刷新到磁盘的MemTable字节数（包括等待刷新的不可变MemTable）"""
+        with self.lock:
+            return self.memtable.size + sum(m.size for m in self.immutable_memtables)
+    
+    def compaction_stats(self) -> Dict:
+        """刷新与压缩统计，见 CompactionStats.snapshot"""
+        with self.lock:
+            pending = self.pending_bytes()
+            files = []
+            for sstable in self.sstables:
+                if os.path.exists(sstable.filepath):
+                    name = os.path.basename(sstable.filepath)
+                    level = 1 if name.startswith("sstable_merged_") else 0
+                    files.append((level, name, os.path.getsize(sstable.filepath)))
+        return self.stats.snapshot(pending, files)
+    
     def _load_sstables(self):
         """加载已有的SSTable"""
         if not os.path.exists(self.data_dir):
@@ -834,7 +965,7 @@ class LSMTree:
         
         for filename in sstable_files:
             filepath = os.path.join(self.data_dir, filename)
-            sstable = SSTable(filepath)
+            sstable = SSTable(filepath, self.files)
             if sstable.exists():
                 self.sstables.append(sstable)
     
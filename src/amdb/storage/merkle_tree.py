@@ -11,6 +11,9 @@ import json
 from typing import Optional, Dict, List, Tuple
 from enum import Enum
 from .file_format import FileMagic
+from ..errors import InvalidOptionError
+from ..metrics import ProbeCounter
+from .. import faults
 
 
 class NodeType(Enum):
@@ -20,33 +23,68 @@ class NodeType(Enum):
     BRANCH = "branch"  # 分支节点
 
 
+class NodeHasher:
+    """
+    节点哈希的域分隔方案，由树的创建选项决定
+    
+    叶子节点: sha256(leaf_prefix + 键 + value)，键按 key_framing 编码：
+        separator  键后接 b':'（默认）
+        none       不编码
+        u32be      4字节大端长度 + 键
+    内部节点: node_prefix 为None时使用默认标签，扩展节点 sha256(b'ext:' + nibble + b':' + child_hash)、
+        分支节点 sha256(b'branch:' + 16个子节点哈希)；否则两者都以 node_prefix 开头，后接各自的内容
+    """
+    
+    KEY_FRAMINGS = ('separator', 'none', 'u32be')
+    
+    def __init__(self, leaf_prefix: bytes = b'leaf:', node_prefix: Optional[bytes] = None,
+                 key_framing: str = 'separator'):
+        self.leaf_prefix = leaf_prefix
+        self.node_prefix = node_prefix
+        self.key_framing = key_framing
+    
+    def leaf(self, key: bytes, value: bytes) -> bytes:
+        if self.key_framing == 'separator':
+            framed = key + b':'
+        elif self.key_framing == 'u32be':
+            framed = struct.pack('>I', len(key)) + key
+        else:
+            framed = key
+        return hashlib.sha256(self.leaf_prefix + framed + value).digest()
+    
+    def extension(self, prefix: bytes, child_hash: bytes) -> bytes:
+        if self.node_prefix is None:
+            return hashlib.sha256(b'ext:' + prefix + b':' + child_hash).digest()
+        return hashlib.sha256(self.node_prefix + prefix + child_hash).digest()
+    
+    def branch(self, children: List[bytes]) -> bytes:
+        tag = b'branch:' if self.node_prefix is None else self.node_prefix
+        return hashlib.sha256(tag + b''.join(children)).digest()
+
+
+DEFAULT_HASHER = NodeHasher()
+
+
 class MerkleNode:
     """Merkle树节点"""
     
-    def __init__(self, node_type: NodeType, data: Dict):
+    def __init__(self, node_type: NodeType, data: Dict, hasher: NodeHasher = DEFAULT_HASHER):
         self.node_type = node_type
         self.data = data
         self.hash: Optional[bytes] = None
-        self._compute_hash()
+        self._compute_hash(hasher)
     
-    def _compute_hash(self):
+    def _compute_hash(self, hasher: NodeHasher):
         """计算节点哈希"""
         if self.node_type == NodeType.LEAF:
             # 叶子节点：hash(key + value)
-            key = self.data.get('key', b'')
-            value = self.data.get('value', b'')
-            content = b'leaf:' + key + b':' + value
+            self.hash = hasher.leaf(self.data.get('key', b''), self.data.get('value', b''))
         elif self.node_type == NodeType.EXTENSION:
             # 扩展节点：hash(prefix + child_hash)
-            prefix = self.data.get('prefix', b'')
-            child_hash = self.data.get('child_hash', b'')
-            content = b'ext:' + prefix + b':' + child_hash
+            self.hash = hasher.extension(self.data.get('prefix', b''), self.data.get('child_hash', b''))
         else:  # BRANCH
             # 分支节点：hash(所有子节点哈希)
-            children = self.data.get('children', [b''] * 16)
-            content = b'branch:' + b''.join(children)
-        
-        self.hash = hashlib.sha256(content).digest()
+            self.hash = hasher.branch(self.data.get('children', [b''] * 16))
     
     def get_hash(self) -> bytes:
         """获取节点哈希"""
@@ -60,22 +98,235 @@ class MerkleTree:
     支持持久化到磁盘（.mpt文件）
     """
     
-    def __init__(self, data_dir: str = "./data/merkle"):
+    # 创建时确定、记录在 tree_options.json 中的选项（值均为字符串）
+    OPTION_NAMES = ('empty_hash', 'leaf_prefix', 'node_prefix', 'key_framing')
+    OPTION_DEFAULTS = {
+        'empty_hash': '',
+        'leaf_prefix': b'leaf:'.hex(),
+        'node_prefix': '',
+        'key_framing': 'separator',
+    }
+    
+    def __init__(self, data_dir: str = "./data/merkle", options: Optional[Dict[str, str]] = None):
+        """
+        Args:
+            data_dir: 数据目录
+            options: 创建选项，见 OPTION_NAMES；仅在新建时生效，重新打开时须与记录一致
+                empty_hash: 空子树（分支节点的空位和空树的根）的占位哈希，十六进制，默认为空字节串
+                leaf_prefix: 叶子节点哈希的前缀，十六进制，默认为 b'leaf:'
+                node_prefix: 内部节点哈希的前缀，十六进制，默认为空，表示使用默认标签
+                key_framing: 叶子节点哈希中键的编码，见 NodeHasher
+        """
         self.data_dir = data_dir
         os.makedirs(data_dir, exist_ok=True)
         self.root: Optional[MerkleNode] = None
         self.nodes: Dict[bytes, MerkleNode] = {}  # hash -> node
         self.key_value_map: Dict[bytes, bytes] = {}  # key -> value
         self.mpt_file = os.path.join(data_dir, "merkle_tree.mpt")
+        self.options_file = os.path.join(data_dir, "tree_options.json")
+        self.options = self._load_options(options or {})
+        self.empty_hash = bytes.fromhex(self.options['empty_hash'])
+        node_prefix = bytes.fromhex(self.options['node_prefix'])
+        self.hasher = NodeHasher(
+            leaf_prefix=bytes.fromhex(self.options['leaf_prefix']),
+            node_prefix=node_prefix or None,
+            key_framing=self.options['key_framing'],
+        )
         
-        # 从磁盘加载
+        # 子树中未删除的叶子数，按节点哈希缓存，见 leaf_count
+        self._leaf_counts: Dict[bytes, int] = {}
+        # 从磁盘加载；跳过或丢弃的内容记在 load_warnings 中
+        self.load_warnings: List[str] = []
         self._load_from_disk()
     
+    def _load_options(self, requested: Dict[str, str]) -> Dict[str, str]:
+        """读取记录的创建选项；新建时校验并记录 requested"""
+        unknown = set(requested) - set(self.OPTION_NAMES)
+        if unknown:
+            raise InvalidOptionError(f"Unknown tree options: {sorted(unknown)}")
+        if os.path.exists(self.options_file):
+            with open(self.options_file, 'r') as f:
+                recorded = json.load(f)
+        elif os.path.exists(self.mpt_file):
+            # 早于选项记录创建的树使用默认选项
+            recorded = {}
+        else:
+            recorded = None
+        
+        defaults = self.OPTION_DEFAULTS
+        if recorded is not None:
+            options = {**defaults, **recorded}
+            for name, value in requested.items():
+                if options[name] != value:
+                    raise InvalidOptionError(
+                        f"Tree option {name}={value!r} differs from the recorded {options[name]!r}"
+                    )
+        else:
+            options = {**defaults, **requested}
+        for name in ('empty_hash', 'leaf_prefix', 'node_prefix'):
+            try:
+                value = bytes.fromhex(options[name])
+            except ValueError:
+                raise InvalidOptionError(f"{name} must be hex: {options[name]!r}")
+            if len(value) > 255:
+                raise InvalidOptionError(f"{name} must be at most 255 bytes")
+        if options['key_framing'] not in NodeHasher.KEY_FRAMINGS:
+            raise InvalidOptionError(f"Unknown key_framing: {options['key_framing']!r}")
+        
+        if not os.path.exists(self.options_file):
+            with open(self.options_file, 'w') as f:
+                json.dump(options, f)
+        return options
+    
     def put(self, key: bytes, value: bytes) -> bytes:
         """插入键值对，返回根哈希"""
         self.key_value_map[key] = value
         self.root = self._build_tree()
-        return self.root.get_hash() if self.root else b''
+        return self.root.get_hash() if self.root else self.empty_hash
+    
+    def put_many(self, items: List[Tuple[bytes, bytes]]) -> bytes:
+        """插入多个键值对，只在最后重建一次树，返回根哈希"""
+        for key, value in items:
+            self.key_value_map[key] = value
+        self.root = self._build_tree()
+        return self.root.get_hash() if self.root else self.empty_hash
+    
+    def root_hash_with(self, items: List[Tuple[bytes, bytes]]) -> bytes:
+        """put_many(items) 之后的根哈希；只计算，不修改树"""
+        merged = dict(self.key_value_map)
+        merged.update(items)
+        if not merged:
+            return self.empty_hash
+        return self._build_mpt_node(list(merged.items()), 0, nodes={}).get_hash()
+    
+    def build_detached(self, items: List[Tuple[bytes, bytes]]
+                       ) -> Tuple[Optional[MerkleNode], Dict[bytes, MerkleNode]]:
+        """由 items 构建一棵独立的树，不修改本树；返回 (根节点, 节点表)，items 为空时根节点为None"""
+        nodes: Dict[bytes, MerkleNode] = {}
+        if not items:
+            return None, nodes
+        return self._build_mpt_node(list(items), 0, nodes), nodes
+    
+    def right_edge(self, root: Optional[MerkleNode], nodes: Dict[bytes, MerkleNode]
+                   ) -> Optional[Tuple[bytes, bytes, bytes]]:
+        """
+        树的右边缘：从根沿最大的非空子节点到最大键所在的叶子
+        Returns:
+            (最大键, 叶子哈希, 路径)，空树返回None。路径编码同 get_path_proof，
+            但分支节点的每个子节点（包括路径上的）都写出哈希
+        """
+        if root is None:
+            return None
+        path = bytearray()
+        node = root
+        while node.node_type != NodeType.LEAF:
+            if node.node_type == NodeType.EXTENSION:
+                path += b'\x01' + node.data['prefix']
+                node = nodes[node.data['child_hash']]
+                continue
+            children = node.data['children']
+            path += b'\x02'
+            for child in children:
+                path += bytes([len(child)]) + child
+            node = nodes[next(h for h in reversed(children) if h != self.empty_hash)]
+        return node.data['key'], node.get_hash(), bytes(path)
+    
+    def leaf_count(self, node: Optional[MerkleNode], nodes: Dict[bytes, MerkleNode]) -> int:
+        """子树中未删除的叶子数；节点按哈希不可变，结果按哈希缓存，之后的版本只需计算变化的路径"""
+        if node is None:
+            return 0
+        node_hash = node.get_hash()
+        count = self._leaf_counts.get(node_hash)
+        if count is not None:
+            return count
+        if node.node_type == NodeType.LEAF:
+            count = 0 if node.data['value'] == b'__DELETED__' else 1
+        elif node.node_type == NodeType.EXTENSION:
+            count = self.leaf_count(nodes[node.data['child_hash']], nodes)
+        else:
+            count = sum(self.leaf_count(nodes[h], nodes)
+                        for h in node.data['children'] if h != self.empty_hash)
+        self._leaf_counts[node_hash] = count
+        return count
+    
+    def split_keys(self, root: Optional[MerkleNode], nodes: Dict[bytes, MerkleNode],
+                   parts: int) -> List[bytes]:
+        """
+        按树的顺序把未删除的叶子分为 parts 份条目数大致相等的区间，返回第2份起各区间的起始键，升序且不重复；
+        叶子少于 parts 时区间也相应减少。每个分界点从根向下按子树的叶子数定位，不遍历叶子
+        """
+        total = self.leaf_count(root, nodes)
+        keys = set()
+        for i in range(1, parts):
+            rank = i * total // parts
+            if rank == 0:
+                continue
+            node = root
+            while node.node_type != NodeType.LEAF:
+                if node.node_type == NodeType.EXTENSION:
+                    node = nodes[node.data['child_hash']]
+                    continue
+                for child_hash in node.data['children']:
+                    if child_hash == self.empty_hash:
+                        continue
+                    child = nodes[child_hash]
+                    count = self.leaf_count(child, nodes)
+                    if rank < count:
+                        node = child
+                        break
+                    rank -= count
+            keys.add(node.data['key'])
+        return sorted(keys)
+    
+    def diff_nodes(self, old_root: Optional[MerkleNode], old_nodes: Dict[bytes, MerkleNode],
+                   new_root: Optional[MerkleNode], new_nodes: Dict[bytes, MerkleNode]
+                   ) -> List[Tuple[bytes, Optional[bytes], Optional[bytes]]]:
+        """
+        比较两棵树，返回值不同的键 [(键, 旧值, 新值), ...]，按键排序，不在树中的值为None。
+        同时自上而下遍历两棵树，哈希相同的子树整棵跳过，开销与变化的键数成正比；
+        节点表中缺少节点时抛出KeyError
+        """
+        def child(node_hash: bytes, nodes: Dict[bytes, MerkleNode]) -> Optional[MerkleNode]:
+            return None if node_hash == self.empty_hash else nodes[node_hash]
+        
+        def leaves(node: Optional[MerkleNode], nodes: Dict[bytes, MerkleNode]):
+            if node is None:
+                return
+            if node.node_type == NodeType.LEAF:
+                yield node.data['key'], node.data['value']
+            elif node.node_type == NodeType.EXTENSION:
+                yield from leaves(child(node.data['child_hash'], nodes), nodes)
+            else:
+                for child_hash in node.data['children']:
+                    yield from leaves(child(child_hash, nodes), nodes)
+        
+        changes = []
+        
+        def walk(old: Optional[MerkleNode], new: Optional[MerkleNode]):
+            if old is None and new is None:
+                return
+            if old is not None and new is not None:
+                if old.get_hash() == new.get_hash():
+                    return
+                if old.node_type == new.node_type == NodeType.BRANCH:
+                    for old_child, new_child in zip(old.data['children'], new.data['children']):
+                        walk(child(old_child, old_nodes), child(new_child, new_nodes))
+                    return
+                if (old.node_type == new.node_type == NodeType.EXTENSION
+                        and old.data['prefix'] == new.data['prefix']):
+                    walk(child(old.data['child_hash'], old_nodes),
+                         child(new.data['child_hash'], new_nodes))
+                    return
+            # 结构不同（或一侧为空）：逐一比较两侧子树中的叶子
+            before = dict(leaves(old, old_nodes))
+            after = dict(leaves(new, new_nodes))
+            for key in before.keys() | after.keys():
+                if before.get(key) != after.get(key):
+                    changes.append((key, before.get(key), after.get(key)))
+        
+        walk(old_root, new_root)
+        changes.sort()
+        return changes
     
     def get(self, key: bytes) -> Optional[bytes]:
         """获取值"""
@@ -85,7 +336,7 @@ class MerkleTree:
         """获取根哈希"""
         if self.root:
             return self.root.get_hash()
-        return b''
+        return self.empty_hash
     
     def verify(self, key: bytes, value: bytes, proof: List[bytes]) -> bool:
         """验证键值对（使用Merkle证明，完整MPT结构）"""
@@ -93,7 +344,7 @@ class MerkleTree:
             return False
         
         # 计算叶子节点哈希
-        leaf_hash = hashlib.sha256(b'leaf:' + key + b':' + value).digest()
+        leaf_hash = self.hasher.leaf(key, value)
         
         # 使用证明路径重建根哈希（按照MPT结构）
         current_hash = leaf_hash
@@ -114,7 +365,7 @@ class MerkleTree:
             proof_hash = proof[proof_index]
             
             # 构建分支节点哈希
-            children = [b''] * 16
+            children = [self.empty_hash] * 16
             children[nibble] = current_hash
             # 其他位置使用证明哈希
             for j, ph in enumerate(proof[proof_index:proof_index+15]):
@@ -122,8 +373,7 @@ class MerkleTree:
                     children[j] = ph
             
             # 计算分支节点哈希
-            branch_content = b'branch:' + b''.join(children)
-            current_hash = hashlib.sha256(branch_content).digest()
+            current_hash = self.hasher.branch(children)
             proof_index += 1
         
         # 如果还有剩余的证明哈希，继续合并
@@ -142,7 +392,7 @@ class MerkleTree:
         if len(self.key_value_map) == 1:
             # 单个键值对，创建叶子节点
             key, value = next(iter(self.key_value_map.items()))
-            node = MerkleNode(NodeType.LEAF, {'key': key, 'value': value})
+            node = MerkleNode(NodeType.LEAF, {'key': key, 'value': value}, self.hasher)
             self.nodes[node.get_hash()] = node
             return node
         
@@ -150,13 +400,16 @@ class MerkleTree:
         root = self._build_mpt_node(list(self.key_value_map.items()), 0)
         return root
     
-    def _build_mpt_node(self, items: List[Tuple[bytes, bytes]], nibble_pos: int) -> MerkleNode:
-        """递归构建MPT节点"""
+    def _build_mpt_node(self, items: List[Tuple[bytes, bytes]], nibble_pos: int,
+                        nodes: Optional[Dict[bytes, MerkleNode]] = None) -> MerkleNode:
+        """递归构建MPT节点，新节点记录到 nodes（默认为树的节点表）"""
+        if nodes is None:
+            nodes = self.nodes
         if len(items) == 1:
             # 单个项，创建叶子节点
             key, value = items[0]
-            node = MerkleNode(NodeType.LEAF, {'key': key, 'value': value})
-            self.nodes[node.get_hash()] = node
+            node = MerkleNode(NodeType.LEAF, {'key': key, 'value': value}, self.hasher)
+            nodes[node.get_hash()] = node
             return node
         
         # 按当前nibble位置分组
@@ -180,23 +433,23 @@ class MerkleTree:
         if len(groups) == 1:
             nibble, group_items = next(iter(groups.items()))
             # 继续构建
-            child = self._build_mpt_node(group_items, nibble_pos + 1)
+            child = self._build_mpt_node(group_items, nibble_pos + 1, nodes)
             prefix = bytes([nibble])
             node = MerkleNode(NodeType.EXTENSION, {
                 'prefix': prefix,
                 'child_hash': child.get_hash()
-            })
-            self.nodes[node.get_hash()] = node
+            }, self.hasher)
+            nodes[node.get_hash()] = node
             return node
         
         # 创建分支节点
-        children = [b''] * 16
+        children = [self.empty_hash] * 16
         for nibble, group_items in groups.items():
-            child = self._build_mpt_node(group_items, nibble_pos + 1)
+            child = self._build_mpt_node(group_items, nibble_pos + 1, nodes)
             children[nibble] = child.get_hash()
         
-        node = MerkleNode(NodeType.BRANCH, {'children': children})
-        self.nodes[node.get_hash()] = node
+        node = MerkleNode(NodeType.BRANCH, {'children': children}, self.hasher)
+        nodes[node.get_hash()] = node
         return node
     
     def get_proof(self, key: bytes) -> List[bytes]:
@@ -243,12 +496,12 @@ class MerkleTree:
                     
                     # 收集兄弟节点哈希作为证明
                     for i, child_hash in enumerate(node.data.get('children', [])):
-                        if i != nibble and child_hash:
+                        if i != nibble and child_hash != self.empty_hash:
                             proof.append(child_hash)
                     
                     # 继续到子节点
                     children = node.data.get('children', [])
-                    if nibble < len(children) and children[nibble]:
+                    if nibble < len(children) and children[nibble] != self.empty_hash:
                         node = self.nodes.get(children[nibble])
                     else:
                         break
@@ -257,6 +510,123 @@ class MerkleTree:
         
         return proof
     
+    def count_prefixes(self, prefix_len: int, start: bytes = b'', end: bytes = b'') -> int:
+        """
+        [start, end) 内未删除的键中不同的 prefix_len 字节前缀的个数（end 为空表示无上界），
+        短于 prefix_len 的键不计。树的第 2*prefix_len 层每棵子树恰好对应一个前缀，遍历到该层即止，
+        只有与区间边界相交的子树才继续展开；子树是否含有这样的键按哈希记忆，哈希相同的子树只计算一次
+        """
+        def live(key: bytes, value: bytes) -> bool:
+            return (len(key) >= prefix_len and value != b'__DELETED__'
+                    and key >= start and (not end or key < end))
+        
+        def child(node_hash: bytes) -> MerkleNode:
+            return self.nodes[node_hash]
+        
+        def children(node: MerkleNode) -> List[MerkleNode]:
+            if node.node_type == NodeType.EXTENSION:
+                return [child(node.data['child_hash'])]
+            if node.node_type == NodeType.BRANCH:
+                return [child(h) for h in node.data['children'] if h != self.empty_hash]
+            return []
+        
+        # 子树哈希 -> 子树中是否有不短于 prefix_len 的未删除的键（不考虑区间）
+        occupied: Dict[bytes, bool] = {}
+        
+        def has_keys(node: MerkleNode) -> bool:
+            node_hash = node.get_hash()
+            if node_hash not in occupied:
+                if node.node_type == NodeType.LEAF:
+                    key, value = node.data['key'], node.data['value']
+                    occupied[node_hash] = (len(key) >= prefix_len
+                                           and value != b'__DELETED__')
+                else:
+                    occupied[node_hash] = any(has_keys(c) for c in children(node))
+            return occupied[node_hash]
+        
+        def has_keys_in_range(node: MerkleNode) -> bool:
+            if node.node_type == NodeType.LEAF:
+                return live(node.data['key'], node.data['value'])
+            return any(has_keys_in_range(c) for c in children(node))
+        
+        def walk(node: MerkleNode, path: List[int]) -> int:
+            if node.node_type == NodeType.LEAF:
+                return 1 if live(node.data['key'], node.data['value']) else 0
+            if len(path) == 2 * prefix_len:
+                prefix = bytes((path[i] << 4) | path[i + 1] for i in range(0, len(path), 2))
+                upper = _prefix_successor(prefix)
+                if (upper is not None and upper <= start) or (end and prefix >= end):
+                    return 0
+                if prefix >= start and (not end or (upper is not None and upper <= end)):
+                    return 1 if has_keys(node) else 0
+                return 1 if has_keys_in_range(node) else 0
+            if node.node_type == NodeType.EXTENSION:
+                return walk(child(node.data['child_hash']), path + [node.data['prefix'][0]])
+            return sum(walk(child(h), path + [nibble])
+                       for nibble, h in enumerate(node.data['children']) if h != self.empty_hash)
+        
+        if self.root is None:
+            return 0
+        try:
+            return walk(self.root, [])
+        except (KeyError, IndexError):
+            # 节点缺失（例如加载时跳过了损坏的节点）时退回到遍历全部键
+            return len({key[:prefix_len] for key, value in self.key_value_map.items()
+                        if live(key, value)})
+    
+    def get_path_proof(self, key: bytes, probes: Optional[ProbeCounter] = None) -> Optional[bytes]:
+        """
+        获取从根到键所在叶子的路径证明，键不在树中时返回None
+        
+        证明依次编码路径上（不含叶子）的每个节点，第i个节点对应键的第i个nibble（键用完后按0处理）：
+            扩展节点: 0x01 + nibble（1字节）
+            分支节点: 0x02 + 16个子节点，每个为 长度（1字节）+ 哈希；路径上的子节点长度为0，由验证方计算
+        验证方从叶子哈希开始按 NodeHasher 的方案自下而上重算，与根哈希比较；
+        空位的占位哈希已写在证明中，验证时无需知道树的创建选项。
+        probes 计入路径上访问的节点数（含叶子）。
+        """
+        if key not in self.key_value_map or self.root is None:
+            return None
+        
+        proof = bytearray()
+        node = self.root
+        nibble_pos = 0
+        while True:
+            if probes is not None:
+                probes.add()
+            if node.node_type == NodeType.LEAF:
+                break
+            byte_pos = nibble_pos // 2
+            if byte_pos < len(key):
+                nibble = (key[byte_pos] >> 4) & 0xF if nibble_pos % 2 == 0 else key[byte_pos] & 0xF
+            else:
+                nibble = 0
+            nibble_pos += 1
+            
+            if node.node_type == NodeType.EXTENSION:
+                prefix = node.data.get('prefix', b'')
+                if prefix != bytes([nibble]):
+                    return None
+                proof += b'\x01' + prefix
+                child_hash = node.data.get('child_hash')
+            else:
+                children = node.data.get('children', [])
+                proof += b'\x02'
+                for i, child in enumerate(children):
+                    if i == nibble:
+                        proof += b'\x00'
+                    else:
+                        proof += bytes([len(child)]) + child
+                child_hash = children[nibble]
+            
+            node = self.nodes.get(child_hash)
+            if node is None:
+                return None
+        
+        if node.data.get('key') != key:
+            return None
+        return bytes(proof)
+    
     def update_root(self, new_root_hash: bytes) -> bool:
         """更新根节点（用于同步）"""
         if new_root_hash in self.nodes:
@@ -265,9 +635,10 @@ class MerkleTree:
         return False
     
     def save_to_disk(self):
-        """保存Merkle树到磁盘（.mpt文件）"""
+        """保存Merkle树到磁盘（.mpt文件）；先写临时文件再替换，写到一半时退出不会损坏原有的文件"""
+        tmp_file = self.mpt_file + '.tmp'
         try:
-            with open(self.mpt_file, 'wb') as f:
+            with open(tmp_file, 'wb') as f:
                 # 写入文件魔数
                 f.write(FileMagic.MPT)  # 4 bytes
                 
@@ -311,13 +682,14 @@ class MerkleTree:
                 current_pos = f.tell()
             
             # 重新打开文件读取数据并计算checksum
-            with open(self.mpt_file, 'rb') as rf:
+            with open(tmp_file, 'rb') as rf:
                 data = rf.read()
             
             # 追加checksum
-            with open(self.mpt_file, 'ab') as af:
+            with open(tmp_file, 'ab') as af:
                 checksum = hashlib.sha256(data).digest()
                 af.write(checksum)  # 32 bytes
+            faults.replace(tmp_file, self.mpt_file)
         except Exception as e:
             import traceback
             print(f"保存Merkle树失败: {e}")
@@ -333,6 +705,8 @@ class MerkleTree:
             file_size = os.path.getsize(self.mpt_file)
             if file_size < 50:  # 至少需要文件头+一些数据
                 print(f"⚠️ 警告: Merkle树文件太小 ({file_size} 字节)，可能已损坏，跳过加载")
+                self.load_warnings.append(
+                    f"Merkle tree file is too small ({file_size} bytes), not loaded")
                 return
             with open(self.mpt_file, 'rb') as f:
                 # 读取文件魔数
@@ -387,6 +761,8 @@ class MerkleTree:
                         print(f"⚠️ 警告: Merkle树节点JSON解析失败 (位置 {f.tell() - node_data_len}, 长度 {node_data_len}): {e}")
                         print(f"   前100个字符: {node_data_json[:100] if len(node_data_json) > 100 else node_data_json}")
                         # 跳过该节点，继续加载其他节点
+                        self.load_warnings.append(
+                            f"Skipped a Merkle node with invalid JSON at offset {f.tell() - node_data_len}")
                         continue
                     
                     # 转换JSON数据：将字符串形式的bytes转换回bytes
@@ -422,7 +798,7 @@ class MerkleTree:
                             node_data[k] = v
                     
                     # 重建节点
-                    node = MerkleNode(node_type, node_data)
+                    node = MerkleNode(node_type, node_data, self.hasher)
                     self.nodes[node_hash] = node
                 
                 # 重建根节点
@@ -431,6 +807,7 @@ class MerkleTree:
         except Exception as e:
             import traceback
             print(f"⚠️ 警告: 加载Merkle树失败: {e}")
+            self.load_warnings.append(f"Failed to load the Merkle tree, starting empty: {e}")
             # 不打印完整traceback，只记录错误，允许继续运行
             # traceback.print_exc()
             # 清空已加载的数据，避免部分加载导致的不一致
@@ -438,3 +815,10 @@ class MerkleTree:
             self.key_value_map.clear()
             self.root = None
 
+
+def _prefix_successor(prefix: bytes) -> Optional[bytes]:
+    """大于所有以 prefix 开头的字节串的最小字节串，不存在（全为0xff或为空）时返回None"""
+    trimmed = prefix.rstrip(b'\xff')
+    if not trimmed:
+        return None
+    return trimmed[:-1] + bytes([trimmed[-1] + 1])
@@ -12,20 +12,25 @@ from typing import Optional, Dict, List, Tuple, Iterator
 from collections import OrderedDict
 from pathlib import Path
 from ..sharding import ShardManager, FileSizeManager
-from .lsm_tree import MemTable, SSTable
+from .lsm_tree import MemTable, SSTable, CompactionStats, OpenFileLimit, NO_FILE_LIMIT
+from ..errors import FatalError
+from ..metrics import ProbeCounter
 
 
 class ShardedSSTable:
     """支持分片的SSTable"""
     
-    def __init__(self, filepath: str, max_file_size: int = 256 * 1024 * 1024):
+    def __init__(self, filepath: str, max_file_size: int = 256 * 1024 * 1024,
+                 files: OpenFileLimit = NO_FILE_LIMIT):
         """
         Args:
             filepath: 文件路径
             max_file_size: 最大文件大小（256MB）
+            files: 同时打开的文件数限制，由所在的LSM树共享
         """
         self.filepath = Path(filepath)
         self.max_file_size = max_file_size
+        self.files = files
         self.index: Dict[bytes, Tuple[int, int]] = {}  # key -> (file_id, offset)
         self.file_sizes: Dict[int, int] = {}  # file_id -> size
         self.current_file_id = 0
@@ -83,7 +88,7 @@ class ShardedSSTable:
             data_parts.append(entry_data)
         
         # 写入文件
-        with open(filepath, 'wb') as f:
+        with self.files.open(filepath, 'wb') as f:
             # 写入文件头
             key_count = len(entries)
             data_offset = SSTableFormat.HEADER_SIZE
@@ -109,7 +114,7 @@ class ShardedSSTable:
             file_size = f.tell()
         
         # 重新打开文件读取数据部分用于计算checksum
-        with open(filepath, 'r+b') as f:
+        with self.files.open(filepath, 'r+b') as f:
             f.seek(0)
             data = f.read(index_offset)
             f.seek(file_size)  # 回到文件末尾
@@ -134,7 +139,7 @@ class ShardedSSTable:
         if not filepath.exists():
             return None
         
-        with open(filepath, 'rb') as f:
+        with self.files.open(filepath, 'rb') as f:
             # 检查文件格式
             magic = f.read(4)
             f.seek(0)
@@ -210,7 +215,7 @@ class ShardedSSTable:
         
         from .file_format import SSTableFormat, FileMagic
         
-        with open(filepath, 'rb') as f:
+        with self.files.open(filepath, 'rb') as f:
             # 检查文件魔数
             magic = f.read(4)
             f.seek(0)
@@ -293,10 +298,12 @@ class ShardedLSMTree:
             self._memtable_max_size = config.lsm_memtable_max_size
             self._enable_skip_list = config.lsm_enable_skip_list
             self._enable_cython = config.lsm_enable_cython
+            self.files = OpenFileLimit(config.max_open_files)
         else:
             self._memtable_max_size = 10 * 1024 * 1024
             self._enable_skip_list = False
             self._enable_cython = False
+            self.files = NO_FILE_LIMIT
         
         # 分片管理器
         from ..sharding import ShardManager
@@ -313,12 +320,22 @@ class ShardedLSMTree:
         
         self.lock = threading.RLock()
         self.max_file_size = max_file_size
+        self.stats = CompactionStats()
+        # 后台刷新的第一个异常；出现后拒绝继续写入，避免数据在内存中无限堆积却无法落盘
+        self.background_error: Optional[BaseException] = None
         
         # 加载已有的SSTable
         self._load_sstables()
     
+    def _check_background_error(self):
+        """后台刷新失败过时抛出 FatalError"""
+        if self.background_error is not None:
+            raise FatalError(f"后台刷新失败: {self.background_error}") from self.background_error
+    
     def put(self, key: bytes, value: bytes, version: int) -> bool:
         """写入数据（自动分片）"""
+        self._check_background_error()
+        self.stats.record_ingest(len(key) + len(value))
         with self.lock:
             shard_id = self.shard_manager.get_shard_id(key)
             
@@ -355,6 +372,8 @@ class ShardedLSMTree:
         Args:
             items: [(key, value, version), ...]
         """
+        self._check_background_error()
+        self.stats.record_ingest(sum(len(k) + len(v) for k, v, _ in items))
         # 按分片分组（不加锁，提高性能）
         # 优化：减少函数调用和字典查找开销
         # 优化：批量计算分片ID，减少函数调用开销
@@ -472,13 +491,15 @@ class ShardedLSMTree:
         
         return True
     
-    def get(self, key: bytes) -> Optional[Tuple[bytes, int]]:
-        """读取数据（自动定位分片）"""
+    def get(self, key: bytes, probes: Optional[ProbeCounter] = None) -> Optional[Tuple[bytes, int]]:
+        """读取数据（自动定位分片）；probes 计入查找过的MemTable和SSTable数"""
         with self.lock:
             shard_id = self.shard_manager.get_shard_id(key)
             
             # 1. 先查MemTable
             if shard_id in self.memtables:
+                if probes is not None:
+                    probes.add()
                 result = self.memtables[shard_id].get(key)
                 if result:
                     return result
@@ -486,6 +507,8 @@ class ShardedLSMTree:
             # 2. 查不可变MemTable
             if shard_id in self.immutable_memtables:
                 for imm_memtable in reversed(self.immutable_memtables[shard_id]):
+                    if probes is not None:
+                        probes.add()
                     result = imm_memtable.get(key)
                     if result:
                         return result
@@ -493,6 +516,8 @@ class ShardedLSMTree:
             # 3. 查SSTable
             if shard_id in self.sstables:
                 for sstable in reversed(self.sstables[shard_id]):
+                    if probes is not None:
+                        probes.add()
                     result = sstable.get(key)
                     if result:
                         return result
@@ -537,8 +562,9 @@ class ShardedLSMTree:
                 # 创建分片SSTable
                 entries = list(immutable.get_all())
                 if entries:
-                    sstable = ShardedSSTable(str(sstable_path), self.max_file_size)
+                    sstable = ShardedSSTable(str(sstable_path), self.max_file_size, self.files)
                     sstable.write(entries)
+                    self.stats.record_flush(sum(sstable.file_sizes.values()))
                     
                     with self.lock:
                         if shard_id not in self.sstables:
@@ -557,6 +583,8 @@ class ShardedLSMTree:
                 import traceback
                 print(f"刷新分片{shard_id}的MemTable失败: {e}")
                 traceback.print_exc()
+                if self.background_error is None:
+                    self.background_error = e
         
         if sync:
             # 同步刷新：直接执行，等待完成
@@ -599,7 +627,7 @@ class ShardedLSMTree:
                     if files:
                         # 使用第一个文件作为基础路径
                         base_path = files[0]
-                        sstable = ShardedSSTable(str(base_path), self.max_file_size)
+                        sstable = ShardedSSTable(str(base_path), self.max_file_size, self.files)
                         sstable._load_index()
                         self.sstables[shard_id].append(sstable)
     
@@ -617,6 +645,29 @@ class ShardedLSMTree:
             # 保存分片统计
             self.shard_manager.save_shard_stats()
     
+    def compact(self) -> int:
+        """分片LSM树不合并SSTable，返回0；接口与 LSMTree.compact 一致"""
+        return 0
+    
+    def pending_bytes(self) -> int:
+        """尚未刷新到磁盘的MemTable字节数（包括等待刷新的不可变MemTable）"""
+        with self.lock:
+            pending = sum(m.size for m in self.memtables.values())
+            pending += sum(m.size for tables in self.immutable_memtables.values() for m in tables)
+            return pending
+    
+    def compaction_stats(self) -> Dict:
+        """刷新与压缩统计（分片LSM树不做压缩，文件都在0层），见 CompactionStats.snapshot"""
+        with self.lock:
+            pending = self.pending_bytes()
+            files = []
+            for shard_id in sorted(self.sstables):
+                for sstable in self.sstables[shard_id]:
+                    for file_id, size in sorted(sstable.file_sizes.items()):
+                        name = f"{shard_id}/{sstable.filepath.stem}_{file_id:06d}.sst"
+                        files.append((0, name, size))
+        return self.stats.snapshot(pending, files)
+    
     def get_shard_info(self) -> Dict[int, Dict]:
         """获取所有分片信息"""
         with self.lock:
@@ -6,12 +6,14 @@
 
 import os
 import threading
-from typing import Optional, Tuple, List
+import time
+from typing import Optional, Tuple, List, Dict, Any
 from .lsm_tree import LSMTree
 from .sharded_lsm_tree import ShardedLSMTree
 from .bplus_tree import BPlusTree
 from .merkle_tree import MerkleTree
 from ..sharding import ShardManager, PartitionManager
+from ..metrics import ProbeCounter
 
 
 class StorageEngine:
@@ -29,7 +31,8 @@ class StorageEngine:
                  enable_sharding: bool = True,
                  shard_count: int = 256,
                  max_file_size: int = 256 * 1024 * 1024,
-                 config = None):  # 256MB
+                 config = None,  # 256MB
+                 tree_options = None):
         """
         Args:
             data_dir: 数据目录
@@ -37,6 +40,7 @@ class StorageEngine:
             shard_count: 分片数量
             max_file_size: 单个文件最大大小
             config: 配置对象（DatabaseConfig）
+            tree_options: Merkle树的创建选项
         """
         self.data_dir = data_dir
         self.enable_sharding = enable_sharding
@@ -56,8 +60,10 @@ class StorageEngine:
             self.lsm_tree = LSMTree(f"{data_dir}/lsm", config=config)
         
         self.bplus_tree = BPlusTree(data_dir=f"{data_dir}/bplus")
-        self.merkle_tree = MerkleTree(data_dir=f"{data_dir}/merkle")
+        self.merkle_tree = MerkleTree(data_dir=f"{data_dir}/merkle", options=tree_options)
         self.lock = threading.RLock()
+        # 异步更新B+树的第一个异常
+        self.background_error: Optional[BaseException] = None
         
         # 分片和分区管理器
         if enable_sharding:
@@ -92,8 +98,10 @@ class StorageEngine:
             def async_update_bplus():
                 try:
                     self.bplus_tree.insert(key, value)
-                except Exception:
-                    pass  # 忽略更新错误，不影响主流程
+                except Exception as e:
+                    # 不影响主流程，记录下来供 Database.background_error 查询
+                    if self.background_error is None:
+                        self.background_error = e
             
             if not self._bplus_synced:
                 # 首次同步时直接更新
@@ -109,27 +117,54 @@ class StorageEngine:
             
             return root_hash
     
-    def get(self, key: bytes, use_cache: bool = True) -> Optional[Tuple[bytes, int]]:
+    def put_many(self, items: List[Tuple[bytes, bytes, int]],
+                 stats: Optional[Dict[str, Any]] = None) -> bytes:
+        """
+        写入多个键值对 [(key, value, version), ...]
+        Merkle树只在全部写入后更新一次，返回该次的根哈希
+        传入 stats 时累加写入LSM/B+树的耗时（io_time）、计算根哈希的耗时（hash_time）
+        和新产生的Merkle节点数（nodes_touched）
+        """
+        with self.lock:
+            started = time.perf_counter()
+            for key, value, version in items:
+                self.lsm_tree.put(key, value, version)
+                self.bplus_tree.insert(key, value)
+            written = time.perf_counter()
+            node_count = len(self.merkle_tree.nodes)
+            root_hash = self.merkle_tree.put_many([(key, value) for key, value, _ in items])
+            if stats is not None:
+                stats['io_time'] = stats.get('io_time', 0.0) + written - started
+                stats['hash_time'] = stats.get('hash_time', 0.0) + time.perf_counter() - written
+                stats['nodes_touched'] = (stats.get('nodes_touched', 0)
+                                          + len(self.merkle_tree.nodes) - node_count)
+            return root_hash
+    
+    def get(self, key: bytes, use_cache: bool = True,
+            probes: Optional[ProbeCounter] = None) -> Optional[Tuple[bytes, int]]:
         """
         读取数据
         Args:
             key: 键
             use_cache: 是否使用B+树缓存
+            probes: 计入查找过的B+树、MemTable和SSTable数
         Returns:
             (value, version) 或 None
         """
         with self.lock:
             # 1. 优先从B+树读取（如果已同步）
             if use_cache and self._bplus_synced:
+                if probes is not None:
+                    probes.add()
                 value = self.bplus_tree.get(key)
                 if value:
                     # 从LSM树获取版本号
-                    result = self.lsm_tree.get(key)
+                    result = self.lsm_tree.get(key, probes)
                     if result:
                         return result
             
             # 2. 从LSM树读取
-            return self.lsm_tree.get(key)
+            return self.lsm_tree.get(key, probes)
     
     def get_with_proof(self, key: bytes) -> Tuple[Optional[bytes], List[bytes], bytes]:
         """
@@ -144,6 +179,18 @@ class StorageEngine:
             root_hash = self.merkle_tree.get_root_hash()
             return (value, proof, root_hash)
     
+    def get_with_path_proof(self, key: bytes, probes: Optional[ProbeCounter] = None
+                            ) -> Tuple[Optional[bytes], Optional[bytes], bytes]:
+        """
+        获取Merkle树中的值及其路径证明（见 MerkleTree.get_path_proof），probes 计入访问的节点数
+        Returns:
+            (value, proof, root_hash)，键不在树中时 value 和 proof 为None
+        """
+        with self.lock:
+            proof = self.merkle_tree.get_path_proof(key, probes)
+            value = self.merkle_tree.get(key) if proof is not None else None
+            return (value, proof, self.merkle_tree.get_root_hash())
+    
     def verify(self, key: bytes, value: bytes, proof: List[bytes]) -> bool:
         """验证数据完整性"""
         return self.merkle_tree.verify(key, value, proof)
@@ -181,7 +228,7 @@ class StorageEngine:
             for sstable in self.lsm_tree.sstables:
                 if hasattr(sstable, 'filepath') and os.path.exists(sstable.filepath):
                     # 读取SSTable中的所有键值对
-                    with open(sstable.filepath, 'rb') as f:
+                    with self.lsm_tree.files.open(sstable.filepath, 'rb') as f:
                         # 跳过文件头
                         f.seek(0)
                         magic = f.read(4)
@@ -8,7 +8,7 @@ import os
 import struct
 import time
 import threading
-from typing import Optional, List, Dict, Any
+from typing import Optional, List, Dict, Any, Tuple
 from pathlib import Path
 from .file_format import WALFormat, FileMagic
 
@@ -96,6 +96,85 @@ class WALLogger:
                 entry_size = f.tell() - entry_start
                 self.current_file_size += entry_size
     
+    def purge_key(self, key: bytes) -> int:
+        """
+        从WAL文件中删除键除最后一条外的全部PUT/DELETE条目，重放结果不变
+        Returns:
+            删除的条目数
+        """
+        with self.lock:
+            files = []
+            for wal_file in sorted(self.data_dir.glob("wal_*.wal")):
+                with open(wal_file, 'rb') as f:
+                    header = f.read(6)
+                    if header[:4] != FileMagic.WAL:
+                        continue
+                    entries = []
+                    while True:
+                        entry = WALFormat.read_entry(f)
+                        if entry is None:
+                            break
+                        entries.append(entry)
+                files.append((wal_file, header, entries))
+            
+            def is_write(entry) -> bool:
+                return (entry['key'] == key and
+                        entry['type'] in (WALFormat.ENTRY_PUT, WALFormat.ENTRY_DELETE))
+            
+            total = sum(1 for _, _, entries in files for entry in entries if is_write(entry))
+            if total < 2:
+                return 0
+            skip = total - 1
+            for wal_file, header, entries in files:
+                kept = []
+                for entry in entries:
+                    if skip and is_write(entry):
+                        skip -= 1
+                        continue
+                    kept.append(entry)
+                if len(kept) == len(entries):
+                    continue
+                # 先写临时文件再改名
+                tmp = wal_file.with_suffix('.tmp')
+                with open(tmp, 'wb') as f:
+                    f.write(header)
+                    for entry in kept:
+                        WALFormat.write_entry(f, entry['type'], entry['key'], entry['value'],
+                                              entry['timestamp'])
+                    f.flush()
+                    os.fsync(f.fileno())
+                os.replace(tmp, wal_file)
+                if wal_file == self.current_wal_file:
+                    self.current_file_size = wal_file.stat().st_size
+            return total - 1
+    
+    def scan(self) -> Tuple[int, List[str]]:
+        """
+        检查全部WAL文件，返回 (完整的记录数, 警告)；文件头无效或末尾有不完整的记录时给出警告
+        """
+        records = 0
+        warnings = []
+        with self.lock:
+            for wal_file in sorted(self.data_dir.glob("wal_*.wal")):
+                try:
+                    with open(wal_file, 'rb') as f:
+                        size = os.fstat(f.fileno()).st_size
+                        if f.read(4) != FileMagic.WAL or len(f.read(2)) != 2:
+                            warnings.append(f"WAL file {wal_file.name} has an invalid header")
+                            continue
+                        while True:
+                            start = f.tell()
+                            entry = WALFormat.read_entry(f)
+                            if entry is None or len(entry['checksum']) != 32:
+                                break
+                            records += 1
+                        if start < size:
+                            warnings.append(f"WAL file {wal_file.name} ends with an incomplete "
+                                            f"record ({size - start} bytes)")
+                except OSError as e:
+                    warnings.append(f"Failed to read WAL file {wal_file.name}: {e}")
+        return records, warnings
+    
     def replay(self, callback: callable):
         """重放WAL日志"""
         wal_files = sorted(self.data_dir.glob("wal_*.wal"))
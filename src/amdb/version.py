@@ -3,13 +3,17 @@
 为每个键维护版本历史链，支持时间点查询
 """
 
+import bisect
+import math
 import time
 import hashlib
-from typing import Optional, List, Tuple, Dict
+from typing import Optional, List, Tuple, Dict, Callable
 from dataclasses import dataclass
 from collections import defaultdict
 import threading
 
+from . import faults
+
 
 @dataclass
 class Version:
@@ -42,8 +46,20 @@ class VersionManager:
     def __init__(self, config=None):
         self.versions: Dict[bytes, List[Version]] = defaultdict(list)
         self.current_versions: Dict[bytes, int] = {}
+        # current_versions 的键按字节序排列，供 range_keys 二分查找；None表示需要重建
+        self._sorted_keys: Optional[List[bytes]] = None
         self.lock = threading.RLock()
+        # 提交记录：第n次提交（从1开始）的 (提交时间, 提交后的根哈希)
+        self.commits: List[Tuple[float, bytes]] = []
+        # 早于该数据库版本的状态已被 prune_before 清理，不能再读取（0表示未清理过）
+        self.pruned_before = 0
+        # 每次提交后以 (提交时间, 根哈希) 调用，见 Database.namespace
+        self.on_commit: Optional[Callable[[float, bytes], None]] = None
+        # 最近分配的版本时间戳，保证之后提交的版本时间戳严格更大
+        self._last_timestamp = 0.0
         self._config = config  # 保存配置引用
+        # load_from_disk 遇到的问题，见 Database.open_report
+        self.load_warnings: List[str] = []
         # 优化：缓存配置值，避免重复访问（性能关键路径）
         if config:
             self._batch_max_size = config.version_batch_max_size
@@ -52,6 +68,14 @@ class VersionManager:
             self._batch_max_size = 1000
             self._skip_prev_hash_threshold = 100
     
+    def _next_timestamp(self) -> float:
+        """单调递增的版本时间戳（调用方持有锁）"""
+        now = time.time()
+        if now <= self._last_timestamp:
+            now = math.nextafter(self._last_timestamp, math.inf)
+        self._last_timestamp = now
+        return now
+    
     def create_version(self, key: bytes, value: bytes) -> Version:
         """创建新版本"""
         with self.lock:
@@ -68,12 +92,14 @@ class VersionManager:
             
             version = Version(
                 version=new_ver,
-                timestamp=time.time(),
+                timestamp=self._next_timestamp(),
                 value=value,
                 prev_hash=prev_hash
             )
             
             self.versions[key].append(version)
+            if current_ver == 0:
+                self._add_sorted_key(key)
             self.current_versions[key] = new_ver
             
             return version
@@ -105,7 +131,7 @@ class VersionManager:
         """内部批量创建版本方法（简化稳定版本）"""
         try:
             with self.lock:
-                current_time = time.time()
+                current_time = self._next_timestamp()
                 versions = []
                 updates_dict = {}
                 
@@ -119,6 +145,8 @@ class VersionManager:
                         # 获取当前版本号
                         current_ver = self.current_versions.get(key, 0)
                         new_ver = current_ver + 1
+                        if current_ver == 0 and key not in updates_dict:
+                            self._add_sorted_key(key)
                         updates_dict[key] = new_ver
                         
                         # 获取前一个版本的哈希（大批量时跳过）
@@ -227,11 +255,126 @@ class VersionManager:
                 if start_version <= v.version <= end_version
             ]
     
+    def prune(self, key: bytes, keep_recent: int, interval: int = 0,
+              pinned: Tuple[float, ...] = ()) -> int:
+        """
+        按保留策略删除旧版本，返回删除的版本数
+        保留最近 keep_recent 个版本（至少保留最新版本）、版本号为 interval 整数倍的版本
+        （interval 为0时不保留），以及 pinned 中各时间点可见的版本
+        """
+        with self.lock:
+            versions = self.versions.get(key)
+            keep_recent = max(keep_recent, 1)
+            if not versions or len(versions) <= keep_recent:
+                return 0
+            
+            keep = set(range(len(versions) - keep_recent, len(versions)))
+            if interval > 0:
+                keep.update(i for i, v in enumerate(versions) if v.version % interval == 0)
+            for timestamp in pinned:
+                visible = None
+                for i, v in enumerate(versions):
+                    if v.timestamp > timestamp:
+                        break
+                    visible = i
+                if visible is not None:
+                    keep.add(visible)
+            
+            if len(keep) == len(versions):
+                return 0
+            self.versions[key] = [v for i, v in enumerate(versions) if i in keep]
+            return len(versions) - len(keep)
+    
+    def prune_before(self, key: bytes, timestamp: float) -> Tuple[int, int]:
+        """
+        删除在 timestamp 时已被覆盖的版本：保留 timestamp 时可见的版本及之后的版本
+        Returns:
+            (删除的版本数, 删除的值字节数)
+        """
+        with self.lock:
+            versions = self.versions.get(key)
+            if not versions:
+                return (0, 0)
+            visible = None
+            for i, v in enumerate(versions):
+                if v.timestamp > timestamp:
+                    break
+                visible = i
+            if not visible:
+                return (0, 0)
+            removed = versions[:visible]
+            self.versions[key] = versions[visible:]
+            return (len(removed), sum(len(v.value) for v in removed))
+    
+    def purge(self, key: bytes) -> List[Version]:
+        """删除键除最新版本外的全部版本，返回删除的版本（按版本号升序）"""
+        with self.lock:
+            versions = self.versions.get(key)
+            if not versions or len(versions) < 2:
+                return []
+            self.versions[key] = versions[-1:]
+            return versions[:-1]
+    
+    def record_commit(self, timestamp: float, root_hash: bytes) -> int:
+        """
+        记录一次提交，返回其序号（数据库版本）
+        timestamp 为该提交中最晚的版本时间戳：不晚于它的版本恰好构成提交后的状态
+        """
+        with self.lock:
+            self.commits.append((timestamp, root_hash))
+            number = len(self.commits)
+        if self.on_commit is not None:
+            self.on_commit(timestamp, root_hash)
+        return number
+    
+    def get_commit(self, number: int) -> Optional[Tuple[float, bytes]]:
+        """第 number 次提交的 (提交时间, 根哈希)，不存在时返回None"""
+        with self.lock:
+            if number < max(self.pruned_before, 1) or number > len(self.commits):
+                return None
+            return self.commits[number - 1]
+    
+    def commit_of(self, timestamp: float) -> Optional[int]:
+        """包含时间戳为 timestamp 的版本的提交序号：提交时间不早于它的第一次提交"""
+        with self.lock:
+            number = bisect.bisect_left(self.commits, (timestamp,)) + 1
+            return number if number <= len(self.commits) else None
+    
+    def find_commit(self, root_hash: bytes) -> Optional[int]:
+        """根哈希为 root_hash 的最近一次提交的序号"""
+        with self.lock:
+            for i in range(len(self.commits) - 1, -1, -1):
+                if self.commits[i][1] == root_hash:
+                    return i + 1
+            return None
+    
     def get_all_keys(self) -> List[bytes]:
         """获取所有键"""
         with self.lock:
             return list(self.current_versions.keys())
     
+    def _add_sorted_key(self, key: bytes):
+        """登记新出现的键（调用方持有锁）"""
+        if self._sorted_keys is not None:
+            bisect.insort(self._sorted_keys, key)
+    
+    def range_keys(self, start: bytes = b'', end: bytes = b'') -> List[bytes]:
+        """[start, end) 内的全部键（含已删除的键），按字节序排列；空边界表示不限制"""
+        with self.lock:
+            if self._sorted_keys is None:
+                self._sorted_keys = sorted(self.current_versions)
+            keys = self._sorted_keys
+            lo = bisect.bisect_left(keys, start) if start else 0
+            hi = bisect.bisect_left(keys, end) if end else len(keys)
+            return keys[lo:hi]
+    
+    def clear(self):
+        """清空全部版本数据"""
+        with self.lock:
+            self.current_versions.clear()
+            self.versions.clear()
+            self._sorted_keys = None
+    
     def get_current_version(self, key: bytes) -> int:
         """获取当前版本号"""
         with self.lock:
@@ -248,10 +391,12 @@ class VersionManager:
         os.makedirs(versions_dir, exist_ok=True)
         
         version_file = versions_dir / "versions.ver"
+        # 各文件先写临时文件再替换，写到一半时退出不会损坏原有的文件
+        tmp_file = versions_dir / "versions.ver.tmp"
         
         try:
             with self.lock:
-                with open(version_file, 'wb') as f:
+                with open(tmp_file, 'wb') as f:
                     # 写入文件魔数
                     f.write(FileMagic.VER)  # 4 bytes
                     
@@ -293,13 +438,23 @@ class VersionManager:
                     current_pos = f.tell()
                 
                 # 重新打开文件读取数据并计算checksum
-                with open(version_file, 'rb') as rf:
+                with open(tmp_file, 'rb') as rf:
                     data = rf.read()
                 
                 # 追加checksum
-                with open(version_file, 'ab') as af:
+                with open(tmp_file, 'ab') as af:
                     checksum = hashlib.sha256(data).digest()
                     af.write(checksum)  # 32 bytes
+                faults.replace(tmp_file, version_file)
+                
+                # 提交记录：每条为 提交时间 (8) + 根哈希长度 (1) + 根哈希
+                commits_tmp = versions_dir / "commits.log.tmp"
+                with open(commits_tmp, 'wb') as cf:
+                    for timestamp, root_hash in self.commits:
+                        cf.write(struct.pack('dB', timestamp, len(root_hash)))
+                        cf.write(root_hash)
+                os.replace(commits_tmp, versions_dir / "commits.log")
+                (versions_dir / "pruned_before").write_text(str(self.pruned_before))
         except Exception as e:
             import traceback
             print(f"保存版本数据失败: {e}")
@@ -341,6 +496,7 @@ class VersionManager:
                         # 当前版本号
                         current_ver = struct.unpack('I', f.read(4))[0]
                         self.current_versions[key] = current_ver
+                        self._sorted_keys = None
                         
                         # 版本历史数量
                         version_count = struct.unpack('I', f.read(4))[0]
@@ -363,9 +519,24 @@ class VersionManager:
                                 prev_hash=prev_hash
                             )
                             version_list.append(version_obj)
+                            self._last_timestamp = max(self._last_timestamp, timestamp)
                         
                         self.versions[key] = version_list
+                
+                commits_file = versions_dir / "commits.log"
+                self.commits = []
+                if commits_file.exists():
+                    data = commits_file.read_bytes()
+                    pos = 0
+                    while pos + 9 <= len(data):
+                        timestamp, hash_len = struct.unpack_from('dB', data, pos)
+                        pos += 9
+                        self.commits.append((timestamp, data[pos:pos + hash_len]))
+                        pos += hash_len
+                pruned_file = versions_dir / "pruned_before"
+                self.pruned_before = int(pruned_file.read_text()) if pruned_file.exists() else 0
         except Exception as e:
             import traceback
             print(f"加载版本数据失败: {e}")
             traceback.print_exc()
+            self.load_warnings.append(f"Failed to load version data: {e}")